@@ -14,10 +14,10 @@ pub fn derive_map_layer(input: TokenStream) -> TokenStream {
         _ => panic!("Only enums can automatically derive MapLayer"),
     };
 
-    assert!(variants.len() <= 32, "Reached the maximum of 32 layers");
+    assert!(variants.len() <= 64, "Reached the maximum of 64 layers");
 
     let to_bits = variants.iter().enumerate().map(|(index, variant)| {
-        let bits: u32 = 1 << index;
+        let bits: u64 = 1 << index;
         assert!(
             variant.fields.is_empty(),
             "Can only derive MapLayer for enums without fields"
@@ -26,8 +26,8 @@ pub fn derive_map_layer(input: TokenStream) -> TokenStream {
         quote! { #enum_ident::#ident => #bits, }
     });
 
-    let all_bits: u32 = if variants.len() == 32 {
-        0xffffffff
+    let all_bits: u64 = if variants.len() == 64 {
+        u64::MAX
     } else {
         (1 << variants.len()) - 1
     };
@@ -35,11 +35,11 @@ pub fn derive_map_layer(input: TokenStream) -> TokenStream {
     let expanded = quote! {
         use bevy_sparse_tilemap::map::MapLayer;
         impl MapLayer for #enum_ident {
-            fn all_bits() -> u32 {
+            fn all_bits() -> u64 {
                 #all_bits
             }
 
-            fn to_bits(&self) -> u32 {
+            fn to_bits(&self) -> u64 {
                 match self {
                     #(#to_bits)*
                 }
@@ -0,0 +1,191 @@
+//! Builds the same maps in bevy_sparse_tilemap and bevy_ecs_tilemap and prints how long construction takes,
+//! roughly how much memory the map data uses, and how fast random tile reads are.
+//!
+//! Run with `cargo run --release --example ecs_tilemap_comparison --features ecs_tilemap_comparison`.
+//!
+//! Both maps are built headless straight into a [`World`] so only the map data is measured, not rendering.
+//! Memory is estimated from the size of the stored data and leaves out allocator and ECS table overhead.
+
+use bevy::ecs::system::{Commands, SystemState};
+use bevy::math::UVec2;
+use bevy::prelude::{Entity, Query, World};
+use bevy_ecs_tilemap::prelude::{
+    TileBundle, TilePos, TileStorage, TileTextureIndex, TilemapId, TilemapSize,
+};
+use bevy_sparse_tilemap::map::chunk::Chunk;
+use bevy_sparse_tilemap::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+use bevy_sparse_tilemap::square::map_data::SquareMapData;
+use bevy_sparse_tilemap::square::{SquareTilemapBuilder, SquareTilemapManager};
+use bevy_sparse_tilemap::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use bst_map_layer_derive::MapLayer;
+use lettuces::cell::Cell;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+
+#[derive(MapLayer, Clone, Copy, Default)]
+pub enum MapLayers {
+    #[default]
+    Main,
+}
+
+/// The map sizes compared
+const MAP_SIZES: [u32; 3] = [256, 1024, 2048];
+/// The amount of random reads timed on every map
+const READS: usize = 1_000_000;
+
+struct Measurement {
+    build: Duration,
+    memory: usize,
+    reads: Duration,
+}
+
+fn main() {
+    println!(
+        "{:<12} {:<20} {:>12} {:>12} {:>14}",
+        "map size", "crate", "build (ms)", "memory (MB)", "reads (M/s)"
+    );
+    for size in MAP_SIZES {
+        let map_size = UVec2::splat(size);
+        let cells = random_cells(map_size);
+        for (name, measurement) in [
+            (
+                "bevy_sparse_tilemap",
+                measure_sparse_tilemap(map_size, &cells),
+            ),
+            ("bevy_ecs_tilemap", measure_ecs_tilemap(map_size, &cells)),
+        ] {
+            println!(
+                "{:<12} {:<20} {:>12.1} {:>12.1} {:>14.1}",
+                format!("{size}x{size}"),
+                name,
+                measurement.build.as_secs_f64() * 1000.0,
+                measurement.memory as f64 / (1024.0 * 1024.0),
+                READS as f64 / measurement.reads.as_secs_f64() / 1_000_000.0,
+            );
+        }
+    }
+}
+
+/// The cells read by both maps, the same for every run
+fn random_cells(map_size: UVec2) -> Vec<UVec2> {
+    let mut rng = StdRng::seed_from_u64(0);
+    (0..READS)
+        .map(|_| UVec2::new(rng.gen_range(0..map_size.x), rng.gen_range(0..map_size.y)))
+        .collect()
+}
+
+/// The texture index stored in the given cell of both maps
+fn texture_index(x: u32, y: u32) -> u32 {
+    (x * 7 + y * 13) % 16
+}
+
+fn measure_sparse_tilemap(map_size: UVec2, cells: &[UVec2]) -> Measurement {
+    let mut world = World::new();
+    let mut system_state: SystemState<(Commands, SquareTilemapManager<u32, MapLayers>)> =
+        SystemState::new(&mut world);
+
+    let start = Instant::now();
+    let tile_data: Vec<Vec<u32>> = (0..map_size.y)
+        .map(|y| (0..map_size.x).map(|x| texture_index(x, y)).collect())
+        .collect();
+    let (mut commands, _) = system_state.get_mut(&mut world);
+    let map_entity = SquareTilemapBuilder::<u32, MapLayers>::new(
+        TilemapLayer::new_dense_from_vecs(tile_data),
+        SquareMapData {
+            max_chunk_size: UVec2::splat(64),
+        },
+        SquareChunkSettings {
+            max_chunk_size: UVec2::splat(64),
+        },
+    )
+    .spawn_tilemap(&mut commands)
+    .expect("map has a main layer");
+    system_state.apply(&mut world);
+    let build = start.elapsed();
+
+    let memory = world
+        .query::<&Chunk<SquareChunkLayer<u32>, u32>>()
+        .iter(&world)
+        .map(|chunk| {
+            size_of::<Chunk<SquareChunkLayer<u32>, u32>>()
+                + chunk
+                    .data
+                    .values()
+                    .map(|layer| layer.heap_size())
+                    .sum::<usize>()
+        })
+        .sum();
+
+    let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+    tilemap_manager.set_tilemap_entity(map_entity);
+    let start = Instant::now();
+    for cell in cells {
+        black_box(tilemap_manager.get_tile_data(Cell::new(cell.x as i32, cell.y as i32)))
+            .expect("cell is on the map");
+    }
+    let reads = start.elapsed();
+
+    Measurement {
+        build,
+        memory,
+        reads,
+    }
+}
+
+fn measure_ecs_tilemap(map_size: UVec2, cells: &[UVec2]) -> Measurement {
+    let mut world = World::new();
+
+    let start = Instant::now();
+    let tilemap_size = TilemapSize {
+        x: map_size.x,
+        y: map_size.y,
+    };
+    let tilemap_entity = world.spawn_empty().id();
+    let mut tile_storage = TileStorage::empty(tilemap_size);
+    for y in 0..map_size.y {
+        for x in 0..map_size.x {
+            let position = TilePos { x, y };
+            let tile_entity = world
+                .spawn(TileBundle {
+                    position,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    texture_index: TileTextureIndex(texture_index(x, y)),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&position, tile_entity);
+        }
+    }
+    world.entity_mut(tilemap_entity).insert(tile_storage);
+    let build = start.elapsed();
+
+    let tile_count = (map_size.x * map_size.y) as usize;
+    let memory = tile_count * (size_of::<TileBundle>() + size_of::<Option<Entity>>());
+
+    let mut system_state: SystemState<(Query<&TileStorage>, Query<&TileTextureIndex>)> =
+        SystemState::new(&mut world);
+    let (tile_storages, texture_indexes) = system_state.get(&world);
+    let tile_storage = tile_storages
+        .get(tilemap_entity)
+        .expect("tilemap has a tile storage");
+    let start = Instant::now();
+    for cell in cells {
+        let tile_entity = tile_storage
+            .get(&TilePos {
+                x: cell.x,
+                y: cell.y,
+            })
+            .expect("cell is on the map");
+        black_box(texture_indexes.get(tile_entity)).expect("tile has a texture index");
+    }
+    let reads = start.elapsed();
+
+    Measurement {
+        build,
+        memory,
+        reads,
+    }
+}
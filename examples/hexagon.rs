@@ -84,6 +84,7 @@ fn spawn_map(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
         HexagonChunkSettings {
             orientation: HEXAGON_ORIENTATION,
             max_chunk_size,
+            ..default()
         },
     );
     tilemap_builder.add_layer(
@@ -5,7 +5,12 @@ use bevy::prelude::*;
 use bevy::window::PresentMode;
 use bevy::DefaultPlugins;
 use bevy_fast_tilemap::{FastTileMapPlugin, Map, MapBundleManaged};
-use bevy_sparse_tilemap::map::chunk::Chunk;
+use bevy_sparse_tilemap::chunk_culling::ChunkCullingPlugin;
+use bevy_sparse_tilemap::chunk_sync::{visible_chunk_rect, ChunkSyncPlugin, ChunkSyncQueue};
+use bevy_sparse_tilemap::map::chunk::{Chunk, ChunkDirtyRect};
+use bevy_sparse_tilemap::map::{
+    LayerRenderHint, LayerRenderHints, Tilemap, TilemapGeometry, TilemapSettings,
+};
 use bevy_sparse_tilemap::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
 use bevy_sparse_tilemap::square::map_data::SquareMapData;
 use bevy_sparse_tilemap::tilemap_builder::tilemap_layer_builder::TilemapLayer;
@@ -29,10 +34,22 @@ fn main() {
             FrameTimeDiagnosticsPlugin::default(),
         ))
         .add_plugins(FastTileMapPlugin::default())
+        .add_plugins(ChunkSyncPlugin::<TileData, SquareChunkLayer<TileData>>::default())
+        // Hides the fast tilemaps of chunks outside of the view
+        .add_plugins(ChunkCullingPlugin::<
+            TileData,
+            SquareChunkLayer<TileData>,
+            SquareMapData,
+        >::default())
         .add_systems(Startup, startup)
         .add_systems(
             Update,
-            (mouse_controls_camera, spawn_or_update_fast_tilemaps),
+            (
+                mouse_controls_camera,
+                toggle_main_layer,
+                spawn_or_update_fast_tilemaps,
+                sync_fast_tilemap_visibility,
+            ),
         )
         .run();
 }
@@ -64,37 +81,97 @@ fn startup(mut commands: Commands) {
     let map_size = UVec2::new(15000, 15000);
     let max_chunk_size = UVec2::new(250, 250);
 
-    let tilemap_builder =
+    let mut tilemap_builder =
         TilemapBuilder::<TileData, MapLayers, SquareChunkLayer<TileData>, SquareMapData>::new(
-            TilemapLayer::new_dense_from_vecs(generate_random_tile_data(map_size.clone())),
+            TilemapLayer::new_generated(map_size, |_| random_tile_data()),
             SquareMapData { max_chunk_size },
             SquareChunkSettings { max_chunk_size },
         );
+    // The render hints live on the map entity and are read when the fast tilemaps are spawned
+    let main_hint = LayerRenderHint::new(1.0).with_material_key("tiles_16.png");
+    let z_offset = main_hint.z_offset;
+    tilemap_builder.set_layer_render_hint(MapLayers::Main, main_hint);
 
-    let Some(tilemap) = tilemap_builder.spawn_tilemap(&mut commands) else {
+    // Every chunk is placed in the world as it is spawned
+    let Some(tilemap) = tilemap_builder
+        .with_chunk_bundle(move |chunk_pos, dimensions| SpatialBundle {
+            transform: Transform::from_xyz(
+                chunk_pos.x() as f32 * dimensions.x as f32 * TILE_SIZE,
+                chunk_pos.y() as f32 * dimensions.y as f32 * TILE_SIZE,
+                z_offset,
+            ),
+            ..default()
+        })
+        .spawn_tilemap(&mut commands)
+    else {
         return;
     };
-    commands.entity(tilemap).insert(SpatialBundle::default());
+    // Upload at most 16 changed chunks every frame, the ones on screen first
+    commands.entity(tilemap).insert((
+        SpatialBundle::default(),
+        TilemapGeometry::new(Vec2::ZERO, Vec2::splat(TILE_SIZE)),
+        TilemapSettings {
+            chunk_sync_budget: Some(16),
+            ..default()
+        },
+    ));
     commands.insert_resource(MapEntity(tilemap));
 }
 
 fn spawn_or_update_fast_tilemaps(
-    chunk_query: Query<
-        (
-            Entity,
-            &Chunk<SquareChunkLayer<TileData>, TileData>,
-            Option<&Children>,
-            Option<&ChunkMapSpawned>,
-        ),
-        Changed<Chunk<SquareChunkLayer<TileData>, TileData>>,
-    >,
+    mut map_query: Query<(
+        &Tilemap,
+        &SquareMapData,
+        &TilemapGeometry,
+        &TilemapSettings,
+        &mut ChunkSyncQueue,
+    )>,
+    mut chunk_query: Query<(
+        &mut Chunk<SquareChunkLayer<TileData>, TileData>,
+        Option<&Children>,
+        Option<&ChunkMapSpawned>,
+    )>,
+    camera_query: Query<(&GlobalTransform, &OrthographicProjection), With<Camera>>,
     fast_tile_map_query: Query<&Handle<Map>, With<FastTileMap>>,
+    render_hints_query: Query<&LayerRenderHints>,
+    map_entity: Option<Res<MapEntity>>,
     asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<Map>>,
     mut commands: Commands,
 ) {
+    let Some(map_entity) = map_entity else {
+        return;
+    };
+    let Ok((tilemap, map_data, geometry, settings, mut sync_queue)) =
+        map_query.get_mut(map_entity.0)
+    else {
+        return;
+    };
+    let Ok((camera_transform, projection)) = camera_query.get_single() else {
+        return;
+    };
+    let view = Rect::from_corners(
+        camera_transform
+            .transform_point(projection.area.min.extend(0.0))
+            .truncate(),
+        camera_transform
+            .transform_point(projection.area.max.extend(0.0))
+            .truncate(),
+    );
+    let (visible_min, visible_max) = visible_chunk_rect(view, geometry, map_data);
+    let hint = render_hints_query
+        .get(map_entity.0)
+        .map(|hints| hints.get(MapLayers::Main))
+        .unwrap_or_default();
     let mut rng = rand::thread_rng();
-    'main_loop: for (entity, chunk, children, map_spawned_option) in chunk_query.iter() {
+    'main_loop: for (chunk_pos, _) in sync_queue.pop_prioritized(visible_min, visible_max, settings)
+    {
+        let Some(entity) = tilemap.get_chunk(chunk_pos) else {
+            continue;
+        };
+        let Ok((mut chunk, children, map_spawned_option)) = chunk_query.get_mut(entity) else {
+            continue;
+        };
         if let Some(_) = map_spawned_option {
             for child in children.unwrap().iter() {
                 if let Ok(map) = fast_tile_map_query.get(*child) {
@@ -109,10 +186,15 @@ fn spawn_or_update_fast_tilemaps(
 
                     let mut m = m.indexer_mut();
 
-                    for y in 0..chunk.get_chunk_dimensions().y {
-                        for x in 0..chunk.get_chunk_dimensions().x {
+                    // Only the cells written since the last upload need to be updated
+                    let dimensions = chunk.get_chunk_dimensions();
+                    let dirty_rect = chunk
+                        .take_dirty_rect()
+                        .unwrap_or(ChunkDirtyRect::full(dimensions));
+                    for y in dirty_rect.min.y()..=dirty_rect.max.y() {
+                        for x in dirty_rect.min.x()..=dirty_rect.max.x() {
                             let i = rng.gen_range(1..12);
-                            m.set(x, y, i);
+                            m.set(x as u32, y as u32, i);
                         }
                     }
                     continue 'main_loop;
@@ -120,6 +202,9 @@ fn spawn_or_update_fast_tilemaps(
             }
         }
 
+        // The whole chunk is uploaded when its map is created
+        chunk.take_dirty_rect();
+
         // Create map with the given dimensions of our chunk
         let map = Map::builder(
             // Map size (tiles)
@@ -128,7 +213,11 @@ fn spawn_or_update_fast_tilemaps(
                 chunk.get_chunk_dimensions().y,
             ),
             // Tile atlas
-            asset_server.load("tiles_16.png"),
+            asset_server.load(
+                hint.material_key
+                    .clone()
+                    .unwrap_or_else(|| String::from("tiles_16.png")),
+            ),
             // Tile size (pixels)
             vec2(TILE_SIZE, TILE_SIZE),
         )
@@ -136,55 +225,63 @@ fn spawn_or_update_fast_tilemaps(
 
         commands
             .entity(entity)
-            .insert((
-                SpatialBundle {
-                    transform: Transform {
-                        translation: Vec3::new(
-                            chunk.chunk_pos.x() as f32
-                                * chunk.get_chunk_dimensions().x as f32
-                                * TILE_SIZE,
-                            chunk.chunk_pos.y() as f32
-                                * chunk.get_chunk_dimensions().y as f32
-                                * TILE_SIZE,
-                            1.0,
-                        ),
-                        ..default()
-                    },
-                    ..default()
-                },
-                ChunkMapSpawned,
-            ))
+            .insert(ChunkMapSpawned)
             .with_children(|parent| {
                 let mut map_bundle = MapBundleManaged::new(map, &mut materials);
                 map_bundle.transform.translation = Vec3::new(
                     chunk.chunk_pos.x() as f32 * chunk.get_chunk_dimensions().x as f32 * TILE_SIZE,
                     chunk.chunk_pos.y() as f32 * chunk.get_chunk_dimensions().y as f32 * TILE_SIZE,
-                    1.0,
+                    hint.z_offset,
                 );
                 parent
                     .spawn(map_bundle)
                     .insert(Transform::from_translation(Vec3::new(1.0, 1.0, 1.0)))
+                    .insert(layer_visibility(&hint))
                     // Have the map manage our mesh so it always has the right size
                     .insert(FastTileMap);
             });
     }
 }
 
-fn generate_random_tile_data(size_to_generate: UVec2) -> Vec<Vec<TileData>> {
-    let mut rng = rand::thread_rng();
-
-    let mut vec: Vec<Vec<TileData>> = vec![];
-    for _ in 0..size_to_generate.y as usize {
-        let mut x_vec: Vec<TileData> = vec![];
-        for _ in 0..size_to_generate.x as usize {
-            let zero = rng.gen_range(1..12);
-            let one = rng.gen_range(1..12);
+/// Press V to show or hide the main layer
+fn toggle_main_layer(
+    keys: Res<ButtonInput<KeyCode>>,
+    map_entity: Option<Res<MapEntity>>,
+    mut render_hints_query: Query<&mut LayerRenderHints>,
+) {
+    let Some(map_entity) = map_entity else {
+        return;
+    };
+    if !keys.just_pressed(KeyCode::KeyV) {
+        return;
+    }
+    if let Ok(mut hints) = render_hints_query.get_mut(map_entity.0) {
+        hints.toggle_visible(MapLayers::Main);
+    }
+}
 
-            x_vec.push(TileData(zero, one));
+fn sync_fast_tilemap_visibility(
+    render_hints_query: Query<&LayerRenderHints, Changed<LayerRenderHints>>,
+    mut fast_tile_map_query: Query<&mut Visibility, With<FastTileMap>>,
+) {
+    for hints in render_hints_query.iter() {
+        let visibility = layer_visibility(&hints.get(MapLayers::Main));
+        for mut fast_tile_map_visibility in fast_tile_map_query.iter_mut() {
+            *fast_tile_map_visibility = visibility;
         }
-        vec.push(x_vec);
     }
-    vec
+}
+
+fn layer_visibility(hint: &LayerRenderHint) -> Visibility {
+    match hint.visible {
+        true => Visibility::Inherited,
+        false => Visibility::Hidden,
+    }
+}
+
+fn random_tile_data() -> TileData {
+    let mut rng = rand::thread_rng();
+    TileData(rng.gen_range(1..12), rng.gen_range(1..12))
 }
 
 /// Use RMB for panning
@@ -0,0 +1,437 @@
+//! Animated tiles such as water, lava, or fire. Requires the `animation` feature.
+//!
+//! Insert a [`TileAnimations`] on a tilemap entity to describe the [`AnimatedTile`]s of each of its layers
+//! along with a function selecting the animation played by some tile data. The [`TileAnimationPlugin`]
+//! advances the animations of every map each frame and only marks the [`TileAnimations`] as changed when a
+//! frame actually changes, so renderers can re-upload animated chunks on
+//! [`Changed<TileAnimations>`](bevy::prelude::Changed).
+//!
+//! Renderers read the frame shown by a tile with [`TileAnimations::frame`],
+//! [`TileAnimations::frame_at_cell`], or [`TilemapManager::animation_frame`]. Every tile with the same
+//! animation shows the same frame. Maps can pause their animations with [`TilemapSubsystems::ANIMATION`].
+//!
+//! ```ignore
+//! app.add_plugins(TileAnimationPlugin::<TileData>::default());
+//! commands.entity(map).insert(TileAnimations::<TileData>::new().with_layer(
+//!     MapLayers::Main,
+//!     vec![AnimatedTile::new(vec![4, 5, 6, 7], 8.0)],
+//!     |tile_data| (tile_data.kind == TileKind::Water).then_some(0),
+//! ));
+//! ```
+
+use crate::map::chunk::{Chunk, ChunkLayer};
+use crate::map::{MapData, MapLayer, TilemapSettings, TilemapSubsystems};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::{Component, DetectChangesMut, Entity, Local, Mut, Query, Res};
+use bevy::time::Time;
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// A looping animation made of frames shown at a fixed rate
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct AnimatedTile {
+    /// The frames of the animation, usually indices into a texture atlas
+    pub frames: Vec<u32>,
+    /// How many frames are shown every second
+    pub fps: f32,
+}
+
+impl AnimatedTile {
+    /// Creates an animation showing the given frames at the given rate
+    pub fn new(frames: Vec<u32>, fps: f32) -> Self {
+        Self { frames, fps }
+    }
+
+    /// Returns the index into [`Self::frames`] shown after the given amount of seconds. Animations without
+    /// frames or with a rate of zero stay on the first frame
+    pub fn frame_index_at(&self, elapsed: f64) -> usize {
+        if self.frames.is_empty() || self.fps <= 0.0 {
+            return 0;
+        }
+        ((elapsed * self.fps as f64) as u64 % self.frames.len() as u64) as usize
+    }
+
+    /// Returns the frame shown after the given amount of seconds, or `None` if the animation has no frames
+    pub fn frame_at(&self, elapsed: f64) -> Option<u32> {
+        self.frames.get(self.frame_index_at(elapsed)).copied()
+    }
+}
+
+/// The animations of a single layer and the function selecting which of them some tile data plays
+#[derive(Clone, Debug)]
+struct LayerAnimations<TileData> {
+    animations: Vec<AnimatedTile>,
+    frame_indices: Vec<usize>,
+    select: fn(&TileData) -> Option<usize>,
+}
+
+/// The [`AnimatedTile`]s of every layer of a map and how long they have been playing. Lives on the map
+/// entity. See the [module docs](self) for details
+#[derive(Component, Clone, Debug)]
+pub struct TileAnimations<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    layers: HashMap<u64, LayerAnimations<TileData>>,
+    elapsed: f64,
+}
+
+impl<TileData> Default for TileAnimations<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            layers: HashMap::default(),
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl<TileData> TileAnimations<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    /// Creates a set without any animated layers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the animations of the given layer and returns self. See [`Self::set_layer`]
+    pub fn with_layer(
+        mut self,
+        map_layer: impl MapLayer,
+        animations: Vec<AnimatedTile>,
+        select: fn(&TileData) -> Option<usize>,
+    ) -> Self {
+        self.set_layer(map_layer, animations, select);
+        self
+    }
+
+    /// Sets the animations of the given layer. `select` returns the index of the animation played by tiles
+    /// with the given tile data, or `None` if they aren't animated
+    pub fn set_layer(
+        &mut self,
+        map_layer: impl MapLayer,
+        animations: Vec<AnimatedTile>,
+        select: fn(&TileData) -> Option<usize>,
+    ) {
+        let frame_indices = animations
+            .iter()
+            .map(|animation| animation.frame_index_at(self.elapsed))
+            .collect();
+        self.layers.insert(
+            map_layer.to_bits(),
+            LayerAnimations {
+                animations,
+                frame_indices,
+                select,
+            },
+        );
+    }
+
+    /// Removes the animations of the given layer
+    pub fn remove_layer(&mut self, map_layer: impl MapLayer) {
+        self.layers.remove(&map_layer.to_bits());
+    }
+
+    /// Returns the animations of the given layer
+    pub fn animations(&self, map_layer: impl MapLayer) -> &[AnimatedTile] {
+        self.layers
+            .get(&map_layer.to_bits())
+            .map(|layer| layer.animations.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns how many seconds the animations have been playing
+    pub fn elapsed(&self) -> f64 {
+        self.elapsed
+    }
+
+    /// Advances every animation by the given amount of seconds. Returns true if any animation changed frame
+    pub fn advance(&mut self, seconds: f64) -> bool {
+        self.elapsed += seconds;
+        let mut changed = false;
+        for layer in self.layers.values_mut() {
+            for (animation, frame_index) in
+                layer.animations.iter().zip(layer.frame_indices.iter_mut())
+            {
+                let new_index = animation.frame_index_at(self.elapsed);
+                changed |= *frame_index != new_index;
+                *frame_index = new_index;
+            }
+        }
+        changed
+    }
+
+    /// Returns the frame currently shown by tiles with the given tile data on the given layer, or `None` if
+    /// they aren't animated
+    pub fn frame(&self, map_layer: impl MapLayer, tile_data: &TileData) -> Option<u32> {
+        self.frame_by_bits(map_layer.to_bits(), tile_data)
+    }
+
+    /// Returns the frame currently shown by the tile at the given [`Cell`] of the chunk on the given layer, or
+    /// `None` if the cell has no tile data or isn't animated
+    pub fn frame_at_cell<MapChunk>(
+        &self,
+        chunk: &Chunk<MapChunk, TileData>,
+        map_layer: impl MapLayer,
+        cell: Cell,
+    ) -> Option<u32>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    {
+        let map_layer = map_layer.to_bits();
+        let tile_data = chunk
+            .data
+            .get(&map_layer)?
+            .get_tile_data(MapChunk::into_chunk_cell(cell, &chunk.chunk_settings))?;
+        self.frame_by_bits(map_layer, tile_data)
+    }
+
+    fn frame_by_bits(&self, map_layer: u64, tile_data: &TileData) -> Option<u32> {
+        let layer = self.layers.get(&map_layer)?;
+        let index = (layer.select)(tile_data)?;
+        let animation = layer.animations.get(index)?;
+        animation.frames.get(layer.frame_indices[index]).copied()
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Returns the frame currently shown by the tile at the given [`Cell`] on the current layer, or `None` if
+    /// it isn't animated. Pass the [`TileAnimations`] of the map this manager is set to
+    pub fn animation_frame(
+        &self,
+        animations: &TileAnimations<TileData>,
+        cell: Cell,
+    ) -> Result<Option<u32>, TilemapManagerError> {
+        let tile_data = self.get_tile_data(cell)?;
+        Ok(animations.frame(self.layer(), &tile_data))
+    }
+}
+
+/// Plugin that advances the [`TileAnimations`] of every map with the given tile data every frame. See the
+/// [module docs](self) for details
+pub struct TileAnimationPlugin<TileData> {
+    ph: PhantomData<TileData>,
+}
+
+impl<TileData> Default for TileAnimationPlugin<TileData> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData> Plugin for TileAnimationPlugin<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, advance_tile_animations::<TileData>);
+    }
+}
+
+/// Advances the [`TileAnimations`] of every map by the time since the last frame. Maps without
+/// [`TilemapSubsystems::ANIMATION`] are paused, and maps with a tick divisor catch up on the time they
+/// skipped the next time they run
+pub fn advance_tile_animations<TileData>(
+    time: Res<Time>,
+    mut maps: Query<(
+        Entity,
+        &mut TileAnimations<TileData>,
+        Option<&TilemapSettings>,
+    )>,
+    mut pending_time: Local<HashMap<Entity, f64>>,
+    mut tick: Local<u64>,
+) where
+    TileData: Send + Sync + 'static,
+{
+    let current_tick = *tick;
+    *tick = tick.wrapping_add(1);
+    pending_time.retain(|map_entity, _| maps.contains(*map_entity));
+
+    for (map_entity, mut animations, map_settings) in maps.iter_mut() {
+        if map_settings
+            .is_some_and(|settings| !settings.enabled.contains(TilemapSubsystems::ANIMATION))
+        {
+            continue;
+        }
+        let pending = pending_time.entry(map_entity).or_insert(0.0);
+        *pending += time.delta_seconds_f64();
+        if !TilemapSettings::should_run_for(
+            map_settings,
+            TilemapSubsystems::ANIMATION,
+            current_tick,
+        ) {
+            continue;
+        }
+        let seconds = std::mem::take(pending);
+        advance_animations(&mut animations, seconds);
+    }
+}
+
+/// Advances the animations without triggering change detection unless a frame changed
+fn advance_animations<TileData>(animations: &mut Mut<TileAnimations<TileData>>, seconds: f64)
+where
+    TileData: Send + Sync + 'static,
+{
+    if animations.bypass_change_detection().advance(seconds) {
+        animations.set_changed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimatedTile, TileAnimationPlugin, TileAnimations};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::map::{TilemapSettings, TilemapSubsystems};
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{DetectChanges, Entity};
+    use bevy::time::Time;
+    use bevy::utils::Duration;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    fn animations() -> TileAnimations<u8> {
+        TileAnimations::new().with_layer(
+            MapLayers::Main,
+            vec![
+                AnimatedTile::new(vec![10, 11, 12], 4.0),
+                AnimatedTile::new(vec![20, 21], 1.0),
+            ],
+            |tile_data| match tile_data {
+                1 => Some(0),
+                2 => Some(1),
+                _ => None,
+            },
+        )
+    }
+
+    #[test]
+    fn test_animated_tile_frames() {
+        let animation = AnimatedTile::new(vec![10, 11, 12], 4.0);
+        assert_eq!(animation.frame_at(0.0), Some(10));
+        assert_eq!(animation.frame_at(0.3), Some(11));
+        assert_eq!(animation.frame_at(0.75), Some(10));
+        assert_eq!(AnimatedTile::new(vec![], 4.0).frame_at(1.0), None);
+        assert_eq!(AnimatedTile::new(vec![3, 4], 0.0).frame_at(10.0), Some(3));
+
+        let mut animations = animations();
+        assert_eq!(animations.frame(MapLayers::Main, &0), None);
+        assert_eq!(animations.frame(MapLayers::Main, &1), Some(10));
+        assert!(animations.advance(0.5));
+        assert_eq!(animations.frame(MapLayers::Main, &1), Some(12));
+        assert_eq!(animations.frame(MapLayers::Main, &2), Some(20));
+        assert!(!animations.advance(0.1));
+    }
+
+    #[test]
+    fn test_animation_plugin() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_plugins(TileAnimationPlugin::<u8>::default());
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let spawn_map = |commands: &mut Commands| -> Entity {
+            let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+                TilemapLayer::new_dense_from_fn(UVec2::new(4, 4), |cell| (cell.x % 3) as u8),
+                SquareMapData {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+                SquareChunkSettings {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+            )
+            .spawn_tilemap(commands)
+            .expect("map has a main layer");
+            commands.entity(map_entity).insert(animations());
+            map_entity
+        };
+        let map_entity = spawn_map(&mut commands);
+        let paused_map = spawn_map(&mut commands);
+        let mut map_settings = TilemapSettings::default();
+        map_settings.set_enabled(TilemapSubsystems::ANIMATION, false);
+        commands.entity(paused_map).insert(map_settings);
+        system_state.apply(&mut app.world);
+
+        let advance = |app: &mut App, millis: u64| {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_millis(millis));
+            app.update();
+        };
+        let last_changed = |app: &App, map_entity: Entity| {
+            app.world
+                .entity(map_entity)
+                .get_ref::<TileAnimations<u8>>()
+                .unwrap()
+                .last_changed()
+        };
+        advance(&mut app, 300);
+        let animations = app.world.get::<TileAnimations<u8>>(map_entity).unwrap();
+        assert_eq!(animations.frame(MapLayers::Main, &1), Some(11));
+        let paused = app.world.get::<TileAnimations<u8>>(paused_map).unwrap();
+        assert_eq!(paused.elapsed(), 0.0);
+
+        // Animations are only marked as changed when a frame changes
+        let changed_at = last_changed(&app, map_entity);
+        advance(&mut app, 100);
+        assert_eq!(last_changed(&app, map_entity), changed_at);
+        advance(&mut app, 100);
+        assert_ne!(last_changed(&app, map_entity), changed_at);
+
+        // Renderers read the frame of a cell from its chunk or through the manager
+        let animations = app
+            .world
+            .get::<TileAnimations<u8>>(map_entity)
+            .unwrap()
+            .clone();
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let chunk = tilemap_manager.get_chunk(ChunkPos::new(0, 0)).unwrap();
+        assert_eq!(
+            animations.frame_at_cell(chunk, MapLayers::Main, Cell::new(1, 1)),
+            Some(12)
+        );
+        assert_eq!(
+            tilemap_manager
+                .animation_frame(&animations, Cell::new(2, 0))
+                .unwrap(),
+            Some(20)
+        );
+        assert_eq!(
+            tilemap_manager
+                .animation_frame(&animations, Cell::new(3, 0))
+                .unwrap(),
+            None
+        );
+    }
+}
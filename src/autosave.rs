@@ -0,0 +1,380 @@
+//! Time-sliced autosaving of chunks. Requires the `autosave` feature.
+//!
+//! The [`AutosavePlugin`] remembers every chunk that changed and once every [`AutosaveSettings::interval`]
+//! serializes them into the [`ChunkStore`] held by the [`AutosaveStore`] resource. Only a few chunks are
+//! serialized each frame so long play sessions get periodic saves without hitching, and chunks whose content
+//! hash didn't change since they were last saved are skipped. Send a [`ForceSave`] event to save every
+//! changed chunk immediately, for example right before the game exits.
+//!
+//! ```ignore
+//! app.insert_resource(AutosaveStore::new(DirectoryChunkStore::new("saves/world")))
+//!     .add_plugins(AutosavePlugin::<TileData, SquareChunkLayer<TileData>>::default());
+//! ```
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
+use bevy::app::{App, Last, Plugin};
+use bevy::prelude::{
+    Changed, Entity, Event, EventReader, EventWriter, Local, Parent, Query, Res, ResMut, Resource,
+};
+use bevy::tasks::{IoTaskPool, TaskPool};
+use bevy::time::Time;
+use bevy::utils::{Duration, HashMap, HashSet, Instant};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Errors returned while autosaving a chunk
+#[derive(thiserror::Error, Debug)]
+pub enum AutosaveError {
+    /// Failed to write the chunk to the store
+    #[error("Failed to write the chunk: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to serialize the chunk
+    #[error("Failed to serialize the chunk: {0}")]
+    Serialize(#[from] ron::Error),
+}
+
+/// Identifies a single saved chunk
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkSaveKey {
+    /// The map the chunk belongs to
+    pub map_entity: Entity,
+    /// The position of the chunk in the map
+    pub chunk_pos: ChunkPos,
+}
+
+/// A destination for autosaved chunks
+pub trait ChunkStore: Send + Sync + 'static {
+    /// Stores the serialized chunk, replacing any previous data saved under the same key.
+    ///
+    /// This is called from the autosave system so slow stores should hand the actual write off to a
+    /// background task like [`DirectoryChunkStore`] does.
+    fn store_chunk(&mut self, key: ChunkSaveKey, data: Vec<u8>) -> Result<(), AutosaveError>;
+}
+
+/// Resource holding the [`ChunkStore`] used by the [`AutosavePlugin`]. Nothing is saved until it is inserted
+#[derive(Resource)]
+pub struct AutosaveStore(pub Box<dyn ChunkStore>);
+
+impl AutosaveStore {
+    /// Creates a new store resource wrapping the given [`ChunkStore`]
+    pub fn new(store: impl ChunkStore) -> Self {
+        Self(Box::new(store))
+    }
+}
+
+/// A [`ChunkStore`] keeping saved chunks in memory. Clones share the same storage so a clone can be kept
+/// around to read the saved chunks
+#[derive(Clone, Default)]
+pub struct MemoryChunkStore {
+    chunks: Arc<Mutex<HashMap<ChunkSaveKey, Vec<u8>>>>,
+}
+
+impl MemoryChunkStore {
+    /// Returns the data saved for the given chunk
+    pub fn get(&self, key: ChunkSaveKey) -> Option<Vec<u8>> {
+        self.chunks
+            .lock()
+            .ok()
+            .and_then(|chunks| chunks.get(&key).cloned())
+    }
+
+    /// Returns the amount of saved chunks
+    pub fn len(&self) -> usize {
+        self.chunks.lock().map(|chunks| chunks.len()).unwrap_or(0)
+    }
+
+    /// Returns true if no chunks have been saved
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ChunkStore for MemoryChunkStore {
+    fn store_chunk(&mut self, key: ChunkSaveKey, data: Vec<u8>) -> Result<(), AutosaveError> {
+        if let Ok(mut chunks) = self.chunks.lock() {
+            chunks.insert(key, data);
+        }
+        Ok(())
+    }
+}
+
+/// A [`ChunkStore`] writing every chunk to its own `.ron` file in a directory. Writes happen on the
+/// [`IoTaskPool`] and failures are logged
+#[derive(Clone, Debug)]
+pub struct DirectoryChunkStore {
+    directory: PathBuf,
+}
+
+impl DirectoryChunkStore {
+    /// Creates a store writing into the given directory. The directory is created on the first write
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the path the given chunk is written to
+    pub fn chunk_path(&self, key: ChunkSaveKey) -> PathBuf {
+        self.directory.join(format!(
+            "map_{}_chunk_{}_{}.ron",
+            key.map_entity.to_bits(),
+            key.chunk_pos.x(),
+            key.chunk_pos.y()
+        ))
+    }
+}
+
+impl ChunkStore for DirectoryChunkStore {
+    fn store_chunk(&mut self, key: ChunkSaveKey, data: Vec<u8>) -> Result<(), AutosaveError> {
+        let directory = self.directory.clone();
+        let path = self.chunk_path(key);
+        IoTaskPool::get_or_init(TaskPool::new)
+            .spawn(async move {
+                let result =
+                    std::fs::create_dir_all(&directory).and_then(|_| std::fs::write(&path, data));
+                if let Err(err) = result {
+                    bevy::log::error!("Failed to autosave chunk to {}: {}", path.display(), err);
+                }
+            })
+            .detach();
+        Ok(())
+    }
+}
+
+/// Settings controlling how often and how much the [`AutosavePlugin`] saves
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct AutosaveSettings {
+    /// Time between two autosaves
+    pub interval: Duration,
+    /// The max amount of chunks serialized in a single frame
+    pub max_chunks_per_frame: usize,
+    /// The max amount of time spent serializing chunks in a single frame. At least one chunk is always
+    /// serialized per frame while an autosave is in progress
+    pub frame_budget: Duration,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            max_chunks_per_frame: 8,
+            frame_budget: Duration::from_millis(2),
+        }
+    }
+}
+
+/// Send to save every changed chunk this frame, ignoring the per frame limits in [`AutosaveSettings`]
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ForceSave;
+
+/// Event sent when a chunk failed to save. The chunk is retried on the next autosave
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct AutosaveFailed {
+    /// The chunk that failed to save
+    pub key: ChunkSaveKey,
+    /// Description of the error
+    pub error: String,
+}
+
+/// Plugin that autosaves every chunk entity with the given `TileData` and `MapChunk`. See the
+/// [module docs](self) for details
+pub struct AutosavePlugin<TileData, MapChunk> {
+    ph: PhantomData<(TileData, MapChunk)>,
+}
+
+impl<TileData, MapChunk> Default for AutosavePlugin<TileData, MapChunk> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk> Plugin for AutosavePlugin<TileData, MapChunk>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Chunk<MapChunk, TileData>: Serialize,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutosaveSettings>()
+            .add_event::<ForceSave>()
+            .add_event::<AutosaveFailed>()
+            .add_systems(Last, autosave_chunks::<TileData, MapChunk>);
+    }
+}
+
+/// Bookkeeping used by [`autosave_chunks`]
+#[derive(Default)]
+pub struct AutosaveState {
+    dirty: HashSet<Entity>,
+    queue: VecDeque<Entity>,
+    saved_hashes: HashMap<Entity, u64>,
+    since_last_save: Duration,
+}
+
+/// Tracks changed chunks and serializes them into the [`AutosaveStore`] a few at a time
+#[allow(clippy::too_many_arguments)]
+pub fn autosave_chunks<TileData, MapChunk>(
+    settings: Res<AutosaveSettings>,
+    time: Res<Time>,
+    store: Option<ResMut<AutosaveStore>>,
+    mut force_save: EventReader<ForceSave>,
+    mut save_failed: EventWriter<AutosaveFailed>,
+    changed_chunks: Query<Entity, Changed<Chunk<MapChunk, TileData>>>,
+    chunks: Query<(&Chunk<MapChunk, TileData>, &Parent)>,
+    mut state: Local<AutosaveState>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Chunk<MapChunk, TileData>: Serialize,
+{
+    let state = &mut *state;
+    state.dirty.extend(changed_chunks.iter());
+    state.since_last_save += time.delta();
+    let forced = force_save.read().count() > 0;
+    let Some(mut store) = store else {
+        return;
+    };
+
+    if forced || (state.queue.is_empty() && state.since_last_save >= settings.interval) {
+        state.since_last_save = Duration::ZERO;
+        state.queue.extend(state.dirty.drain());
+    }
+
+    let started = Instant::now();
+    let mut serialized = 0;
+    while let Some(chunk_entity) = state.queue.pop_front() {
+        if !forced
+            && serialized > 0
+            && (serialized >= settings.max_chunks_per_frame
+                || started.elapsed() >= settings.frame_budget)
+        {
+            state.queue.push_front(chunk_entity);
+            break;
+        }
+        let Ok((chunk, parent)) = chunks.get(chunk_entity) else {
+            state.saved_hashes.remove(&chunk_entity);
+            continue;
+        };
+
+        let mut hasher = DefaultHasher::new();
+        chunk.hash(&mut hasher);
+        let hash = hasher.finish();
+        if state.saved_hashes.get(&chunk_entity) == Some(&hash) {
+            continue;
+        }
+
+        serialized += 1;
+        let key = ChunkSaveKey {
+            map_entity: parent.get(),
+            chunk_pos: chunk.chunk_pos,
+        };
+        let result = ron::to_string(chunk)
+            .map_err(AutosaveError::from)
+            .and_then(|data| store.0.store_chunk(key, data.into_bytes()));
+        match result {
+            Ok(()) => {
+                state.saved_hashes.insert(chunk_entity, hash);
+            }
+            Err(err) => {
+                state.dirty.insert(chunk_entity);
+                save_failed.send(AutosaveFailed {
+                    key,
+                    error: err.to_string(),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AutosavePlugin, AutosaveSettings, AutosaveStore, ChunkSaveKey, ForceSave, MemoryChunkStore,
+    };
+    use crate::map::chunk::{Chunk, ChunkCell, ChunkLayerType, ChunkPos};
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use bevy::app::App;
+    use bevy::ecs::change_detection::DetectChangesMut;
+    use bevy::hierarchy::BuildWorldChildren;
+    use bevy::math::UVec2;
+    use bevy::time::Time;
+    use bevy::utils::Duration;
+
+    type TestChunk = Chunk<SquareChunkLayer<u8>, u8>;
+
+    fn new_chunk(x: i32) -> TestChunk {
+        Chunk::new(
+            ChunkPos::new(x, 0),
+            UVec2::new(2, 2),
+            ChunkLayerType::Dense(vec![vec![1u8; 2]; 2]),
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+    }
+
+    #[test]
+    fn test_autosave() {
+        let store = MemoryChunkStore::default();
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(AutosaveSettings {
+                interval: Duration::from_secs(1000),
+                max_chunks_per_frame: 1,
+                frame_budget: Duration::from_secs(1),
+            })
+            .insert_resource(AutosaveStore::new(store.clone()))
+            .add_plugins(AutosavePlugin::<u8, SquareChunkLayer<u8>>::default());
+
+        let map_entity = app.world.spawn_empty().id();
+        let first = app.world.spawn(new_chunk(0)).set_parent(map_entity).id();
+        app.world.spawn(new_chunk(1)).set_parent(map_entity);
+        let key = ChunkSaveKey {
+            map_entity,
+            chunk_pos: ChunkPos::new(0, 0),
+        };
+
+        // Nothing is saved before the interval passes
+        app.update();
+        assert!(store.is_empty());
+
+        app.world.send_event(ForceSave);
+        app.update();
+        assert_eq!(store.len(), 2);
+        let saved = store.get(key).unwrap();
+
+        // Unchanged content is skipped even though the chunk was marked as changed
+        app.world.get_mut::<TestChunk>(first).unwrap().set_changed();
+        app.world.send_event(ForceSave);
+        app.update();
+        assert_eq!(store.get(key).unwrap(), saved);
+
+        // A regular autosave only serializes one chunk per frame
+        app.world.resource_mut::<AutosaveSettings>().interval = Duration::ZERO;
+        for mut chunk in app.world.query::<&mut TestChunk>().iter_mut(&mut app.world) {
+            chunk.set_tile_data(1, ChunkCell::new(0, 0), 5);
+        }
+        let changed_keys = [0, 1].map(|x| ChunkSaveKey {
+            map_entity,
+            chunk_pos: ChunkPos::new(x, 0),
+        });
+        let before = changed_keys.map(|key| store.get(key));
+        let saved_count = |store: &MemoryChunkStore| {
+            changed_keys
+                .iter()
+                .zip(before.iter())
+                .filter(|(key, before)| store.get(**key) != **before)
+                .count()
+        };
+        app.update();
+        assert_eq!(saved_count(&store), 1);
+        app.update();
+        assert_eq!(saved_count(&store), 2);
+    }
+}
@@ -0,0 +1,123 @@
+//! Command line tooling for `.bstmap` files. Requires the `tool` feature.
+//!
+//! Run `bst-tool help` for a list of commands. Every command exits with a non zero status when it fails or
+//! finds a problem so it can be used to check level content in CI.
+
+use bevy_sparse_tilemap::map_file::{MapFile, MapFileError};
+use std::collections::HashSet;
+use std::process::ExitCode;
+
+const USAGE: &str = "Usage: bst-tool <command> [args]
+
+Commands:
+  inspect <map>                     Print the layers, dimensions, and tile counts of a map
+  validate <map>...                 Check that every map is well formed
+  convert <input> <output>          Convert between RON (.ron) and binary (.bstmap) maps
+  diff <expected> <found>           Print the cells that differ between two maps
+  thumbnail <map> <output.pgm> [layer]
+                                    Render a layer as a greyscale PGM image. Defaults to the first layer";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["inspect", path] => inspect(path),
+        ["validate", paths @ ..] if !paths.is_empty() => validate(paths),
+        ["convert", input, output] => convert(input, output),
+        ["diff", expected, found] => diff(expected, found),
+        ["thumbnail", path, output] => thumbnail(path, output, None),
+        ["thumbnail", path, output, map_layer] => match map_layer.parse() {
+            Ok(map_layer) => thumbnail(path, output, Some(map_layer)),
+            Err(_) => Err(format!("Invalid layer {map_layer}")),
+        },
+        ["help"] | ["--help"] | ["-h"] => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("{message}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn load(path: &str) -> Result<MapFile, String> {
+    MapFile::load(path).map_err(|err: MapFileError| format!("{path}: {err}"))
+}
+
+fn inspect(path: &str) -> Result<(), String> {
+    let map_file = load(path)?;
+    println!(
+        "{path}: version {}, {} layer(s)",
+        map_file.version,
+        map_file.layers.len()
+    );
+    for layer in map_file.layers.iter() {
+        let tiles = layer.snapshot.tiles.iter().flatten();
+        let filled = tiles.clone().flatten().count();
+        let distinct: HashSet<u64> = tiles.flatten().cloned().collect();
+        println!(
+            "  layer {}: {}x{}, {} filled cell(s), {} distinct tile(s)",
+            layer.map_layer,
+            layer.snapshot.dimensions.x,
+            layer.snapshot.dimensions.y,
+            filled,
+            distinct.len()
+        );
+    }
+    Ok(())
+}
+
+fn validate(paths: &[&str]) -> Result<(), String> {
+    let mut failed = 0;
+    for path in paths {
+        let problems = match MapFile::load(path) {
+            Ok(map_file) => map_file.validate(),
+            Err(err) => vec![err.to_string()],
+        };
+        if problems.is_empty() {
+            println!("{path}: ok");
+            continue;
+        }
+        failed += 1;
+        println!("{path}: {} problem(s)", problems.len());
+        for problem in problems {
+            println!("  {problem}");
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        _ => Err(format!("{failed} map(s) failed validation")),
+    }
+}
+
+fn convert(input: &str, output: &str) -> Result<(), String> {
+    load(input)?
+        .save(output)
+        .map_err(|err| format!("{output}: {err}"))
+}
+
+fn diff(expected: &str, found: &str) -> Result<(), String> {
+    match load(expected)?.diff_report(&load(found)?) {
+        Some(report) => Err(report),
+        None => {
+            println!("Maps match");
+            Ok(())
+        }
+    }
+}
+
+fn thumbnail(path: &str, output: &str, map_layer: Option<u64>) -> Result<(), String> {
+    let map_file = load(path)?;
+    let map_layer = map_layer
+        .or_else(|| map_file.layers.first().map(|layer| layer.map_layer))
+        .ok_or_else(|| format!("{path}: the map has no layers"))?;
+    let pgm = map_file
+        .thumbnail_pgm(map_layer)
+        .ok_or_else(|| format!("{path}: layer {map_layer} does not exist"))?;
+    std::fs::write(output, pgm).map_err(|err| format!("{output}: {err}"))
+}
@@ -0,0 +1,287 @@
+//! Orthographic camera controls for viewing maps. Requires the `camera` feature.
+//!
+//! Add the [`TilemapCameraPlugin`] and insert a [`TilemapCamera`] on a camera with an
+//! [`OrthographicProjection`], such as one spawned with a `Camera2dBundle`. Dragging with the
+//! [`pan_buttons`](TilemapCamera::pan_buttons) held pans the camera, the mouse wheel zooms, and the camera
+//! is kept over the map it views. Send a [`FitCameraToMap`] event to zoom out until the whole map is visible.
+//!
+//! Map bounds come from the [`TilemapGeometry`](crate::map::TilemapGeometry) of the map, cameras viewing maps
+//! without one are never clamped or fitted.
+//!
+//! ```ignore
+//! app.add_plugins(TilemapCameraPlugin::<TileData, MapLayers, SquareChunkLayer<TileData>, SquareMapData>::default());
+//! commands.spawn((Camera2dBundle::default(), TilemapCamera::default()));
+//! ```
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapSelection};
+use bevy::app::{App, Plugin, Update};
+use bevy::input::mouse::{MouseButton, MouseMotion, MouseWheel};
+use bevy::input::ButtonInput;
+use bevy::math::{Rect, Vec2};
+use bevy::prelude::{Component, Entity, Event, EventReader, Query, Res, Transform};
+use bevy::render::camera::OrthographicProjection;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Pan and zoom settings of a camera controlled by the [`TilemapCameraPlugin`]
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct TilemapCamera {
+    /// The map the camera views. Uses the [`ActiveTilemap`](crate::tilemap_manager::ActiveTilemap) if `None`
+    pub map: Option<Entity>,
+    /// The mouse buttons that pan the camera while held
+    pub pan_buttons: Vec<MouseButton>,
+    /// How fast the mouse wheel zooms. Every line scrolled multiplies the scale of the projection by
+    /// `2^-zoom_speed`
+    pub zoom_speed: f32,
+    /// The smallest scale of the projection, the furthest the camera can zoom in
+    pub min_scale: f32,
+    /// The largest scale of the projection, the furthest the camera can zoom out
+    pub max_scale: f32,
+    /// Keeps the center of the camera inside of the bounds of the map
+    pub clamp_to_map: bool,
+}
+
+impl Default for TilemapCamera {
+    fn default() -> Self {
+        Self {
+            map: None,
+            pan_buttons: vec![MouseButton::Left, MouseButton::Right],
+            zoom_speed: 0.5,
+            min_scale: 1.0 / 128.0,
+            max_scale: 128.0,
+            clamp_to_map: true,
+        }
+    }
+}
+
+/// Send to center the given camera on its map and zoom it so the whole map is visible
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FitCameraToMap {
+    /// The camera entity
+    pub camera: Entity,
+}
+
+/// Plugin that controls every [`TilemapCamera`] viewing maps with the given types. See the
+/// [module docs](self) for details
+pub struct TilemapCameraPlugin<TileData, MapLayers, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapLayers, MapChunk, Map)>,
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Default
+    for TilemapCameraPlugin<TileData, MapLayers, MapChunk, Map>
+{
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Plugin
+    for TilemapCameraPlugin<TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ButtonInput<MouseButton>>()
+            .add_event::<MouseMotion>()
+            .add_event::<MouseWheel>()
+            .add_event::<FitCameraToMap>()
+            .add_systems(
+                Update,
+                control_tilemap_cameras::<TileData, MapLayers, MapChunk, Map>,
+            );
+    }
+}
+
+/// Returns the scale an orthographic projection needs to show all of `bounds`. `area` is the area the
+/// projection shows at `scale`
+pub fn scale_to_fit(area: Rect, scale: f32, bounds: Rect) -> f32 {
+    let area = area.size();
+    if area.x <= 0.0 || area.y <= 0.0 {
+        return scale;
+    }
+    let ratio = bounds.size() / area;
+    scale * ratio.x.max(ratio.y)
+}
+
+/// Pans, zooms, fits, and clamps every [`TilemapCamera`]
+pub fn control_tilemap_cameras<TileData, MapLayers, MapChunk, Map>(
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut fit_events: EventReader<FitCameraToMap>,
+    mut cameras: Query<(
+        Entity,
+        &TilemapCamera,
+        &mut Transform,
+        &mut OrthographicProjection,
+    )>,
+    mut tilemap_manager: TilemapManager<TileData, MapLayers, MapChunk, Map>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let motion: Vec2 = mouse_motion.read().map(|event| event.delta).sum();
+    let wheel: f32 = mouse_wheel.read().map(|event| event.y).sum();
+    let fit: Vec<Entity> = fit_events.read().map(|event| event.camera).collect();
+
+    for (camera_entity, camera, mut transform, mut projection) in cameras.iter_mut() {
+        tilemap_manager.set_selection(TilemapSelection {
+            map_entity: camera.map,
+            map_layer: MapLayers::default(),
+        });
+        let bounds = tilemap_manager
+            .tilemap_entity()
+            .and_then(|_| tilemap_manager.world_bounds().ok());
+
+        if camera
+            .pan_buttons
+            .iter()
+            .any(|button| mouse_buttons.pressed(*button))
+        {
+            transform.translation.x -= motion.x * projection.scale;
+            transform.translation.y += motion.y * projection.scale;
+        }
+        if wheel != 0.0 {
+            projection.scale = (projection.scale * 2f32.powf(-wheel * camera.zoom_speed))
+                .clamp(camera.min_scale, camera.max_scale);
+        }
+        if let Some(bounds) = bounds {
+            if fit.contains(&camera_entity) {
+                projection.scale = scale_to_fit(projection.area, projection.scale, bounds)
+                    .clamp(camera.min_scale, camera.max_scale);
+                let center = bounds.center();
+                transform.translation.x = center.x;
+                transform.translation.y = center.y;
+            }
+            if camera.clamp_to_map {
+                let position = transform
+                    .translation
+                    .truncate()
+                    .clamp(bounds.min, bounds.max);
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FitCameraToMap, TilemapCamera, TilemapCameraPlugin};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::TilemapGeometry;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::SquareTilemapBuilder;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::input::mouse::{MouseButton, MouseMotion, MouseScrollUnit, MouseWheel};
+    use bevy::input::ButtonInput;
+    use bevy::math::{Rect, UVec2, Vec2, Vec3};
+    use bevy::prelude::{Entity, Transform};
+    use bevy::render::camera::OrthographicProjection;
+    use bst_map_layer_derive::MapLayer;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    fn setup() -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(TilemapCameraPlugin::<
+            u8,
+            MapLayers,
+            SquareChunkLayer<u8>,
+            SquareMapData,
+        >::default());
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut app.world);
+        let mut commands = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 20]; 10]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(5, 5),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(5, 5),
+            },
+        )
+        .with_map_bundle(TilemapGeometry::new(Vec2::ZERO, Vec2::splat(16.0)))
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let camera = commands
+            .spawn((
+                TilemapCamera {
+                    map: Some(map_entity),
+                    ..TilemapCamera::default()
+                },
+                Transform::default(),
+                OrthographicProjection {
+                    area: Rect::new(-50.0, -50.0, 50.0, 50.0),
+                    ..OrthographicProjection::default()
+                },
+            ))
+            .id();
+        system_state.apply(&mut app.world);
+        (app, camera)
+    }
+
+    #[test]
+    fn test_camera_controls() {
+        let (mut app, camera) = setup();
+
+        app.world.send_event(MouseWheel {
+            unit: MouseScrollUnit::Line,
+            x: 0.0,
+            y: 2.0,
+            window: Entity::PLACEHOLDER,
+        });
+        app.world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Left);
+        app.world.send_event(MouseMotion {
+            delta: Vec2::new(-40.0, -20.0),
+        });
+        app.update();
+        let projection = app.world.get::<OrthographicProjection>(camera).unwrap();
+        assert_eq!(projection.scale, 0.5);
+        assert_eq!(
+            app.world.get::<Transform>(camera).unwrap().translation,
+            Vec3::new(40.0, 0.0, 0.0)
+        );
+
+        // The camera is kept over the map
+        app.world.send_event(MouseMotion {
+            delta: Vec2::new(10000.0, 0.0),
+        });
+        app.update();
+        assert_eq!(
+            app.world.get::<Transform>(camera).unwrap().translation.x,
+            0.0
+        );
+
+        // The map is 320 by 160 units so the 100 unit wide area needs to be 3.2 times larger at a scale of 0.5
+        app.world.send_event(FitCameraToMap { camera });
+        app.update();
+        assert_eq!(
+            app.world
+                .get::<OrthographicProjection>(camera)
+                .unwrap()
+                .scale,
+            1.6
+        );
+        assert_eq!(
+            app.world.get::<Transform>(camera).unwrap().translation,
+            Vec3::new(160.0, 80.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Cells scheduled to tick at a future time.
+//!
+//! Crops that grow, fires that burn out, and resources that respawn all need a timer per cell. Instead of
+//! keeping a hashmap of timers keyed by cell, add a [`CellScheduler`] to the tilemap entity and schedule cells
+//! with [`CellScheduler::schedule_cell`]. The [`CellSchedulerPlugin`] sends a [`CellTickDue`] event with the
+//! payload of every scheduled tick once its delay has passed.
+//!
+//! Ticks are stored in a small queue per chunk, so scheduling and cancelling only touch the chunk of the
+//! cell and a map with no pending ticks costs nothing.
+//!
+//! ```ignore
+//! app.add_plugins(CellSchedulerPlugin::<CropStage>::default());
+//! commands.entity(map).insert(CellScheduler::<CropStage>::new(UVec2::splat(64)));
+//! // ... later
+//! scheduler.schedule_cell(cell, Duration::from_secs(30), CropStage::Ripe);
+//! ```
+
+use crate::map::chunk::ChunkPos;
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::math::{IVec2, UVec2};
+use bevy::prelude::{Component, Entity, Event, EventWriter, Query, Res};
+use bevy::time::Time;
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// A single scheduled tick. Ordered by due time and then by the order ticks were scheduled in
+struct ScheduledTick<Payload> {
+    due: Duration,
+    order: u64,
+    cell: Cell,
+    payload: Payload,
+}
+
+impl<Payload> PartialEq for ScheduledTick<Payload> {
+    fn eq(&self, other: &Self) -> bool {
+        (self.due, self.order) == (other.due, other.order)
+    }
+}
+
+impl<Payload> Eq for ScheduledTick<Payload> {}
+
+impl<Payload> PartialOrd for ScheduledTick<Payload> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<Payload> Ord for ScheduledTick<Payload> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.due, self.order).cmp(&(other.due, other.order))
+    }
+}
+
+/// The pending ticks of the cells of a tilemap. Add to the tilemap entity
+#[derive(Component)]
+pub struct CellScheduler<Payload>
+where
+    Payload: Send + Sync + 'static,
+{
+    chunk_size: UVec2,
+    now: Duration,
+    next_order: u64,
+    chunks: HashMap<ChunkPos, BinaryHeap<Reverse<ScheduledTick<Payload>>>>,
+}
+
+impl<Payload> CellScheduler<Payload>
+where
+    Payload: Send + Sync + 'static,
+{
+    /// Creates an empty scheduler that groups ticks into chunks of the given size. Use the max chunk size of
+    /// the map
+    pub fn new(chunk_size: UVec2) -> Self {
+        Self {
+            chunk_size: chunk_size.max(UVec2::ONE),
+            now: Duration::ZERO,
+            next_order: 0,
+            chunks: HashMap::default(),
+        }
+    }
+
+    /// Returns the chunk the ticks of the cell are stored in
+    fn chunk_of(&self, cell: Cell) -> ChunkPos {
+        let chunk = IVec2::new(cell.x, cell.y).div_euclid(self.chunk_size.as_ivec2());
+        ChunkPos::new(chunk.x, chunk.y)
+    }
+
+    /// Schedules the cell to tick with the given payload once the delay has passed. A cell can have any
+    /// amount of pending ticks
+    pub fn schedule_cell(&mut self, cell: Cell, delay: Duration, payload: Payload) {
+        let tick = ScheduledTick {
+            due: self.now + delay,
+            order: self.next_order,
+            cell,
+            payload,
+        };
+        self.next_order += 1;
+        self.chunks
+            .entry(self.chunk_of(cell))
+            .or_default()
+            .push(Reverse(tick));
+    }
+
+    /// Cancels every pending tick of the cell and returns how many were cancelled
+    pub fn cancel_cell(&mut self, cell: Cell) -> usize {
+        let chunk_pos = self.chunk_of(cell);
+        let Some(ticks) = self.chunks.get_mut(&chunk_pos) else {
+            return 0;
+        };
+        let before = ticks.len();
+        ticks.retain(|Reverse(tick)| tick.cell != cell);
+        let cancelled = before - ticks.len();
+        if ticks.is_empty() {
+            self.chunks.remove(&chunk_pos);
+        }
+        cancelled
+    }
+
+    /// Returns the amount of pending ticks of the cell
+    pub fn pending_for_cell(&self, cell: Cell) -> usize {
+        self.chunks
+            .get(&self.chunk_of(cell))
+            .map(|ticks| {
+                ticks
+                    .iter()
+                    .filter(|Reverse(tick)| tick.cell == cell)
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Returns the amount of pending ticks of the whole map
+    pub fn len(&self) -> usize {
+        self.chunks.values().map(|ticks| ticks.len()).sum()
+    }
+
+    /// Returns true if the map has no pending ticks
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Advances the time of the scheduler by `delta` and returns every tick that became due, in the order
+    /// they were due in. Called by the [`CellSchedulerPlugin`], use it directly when not using the plugin
+    pub fn advance(&mut self, delta: Duration) -> Vec<(Cell, Payload)> {
+        self.now += delta;
+        let mut due = vec![];
+        for ticks in self.chunks.values_mut() {
+            while ticks
+                .peek()
+                .is_some_and(|Reverse(tick)| tick.due <= self.now)
+            {
+                if let Some(Reverse(tick)) = ticks.pop() {
+                    due.push(tick);
+                }
+            }
+        }
+        self.chunks.retain(|_, ticks| !ticks.is_empty());
+        due.sort();
+        due.into_iter()
+            .map(|tick| (tick.cell, tick.payload))
+            .collect()
+    }
+}
+
+/// Sent by the [`CellSchedulerPlugin`] when a tick scheduled with [`CellScheduler::schedule_cell`] is due
+#[derive(Event, Clone, Debug, PartialEq)]
+pub struct CellTickDue<Payload> {
+    /// The tilemap entity the cell is in
+    pub map_entity: Entity,
+    /// The cell that ticked
+    pub cell: Cell,
+    /// The payload the tick was scheduled with
+    pub payload: Payload,
+}
+
+/// Plugin that advances every [`CellScheduler`] with the given payload and sends [`CellTickDue`] events.
+/// Requires bevys [`Time`] resource
+pub struct CellSchedulerPlugin<Payload> {
+    ph: PhantomData<Payload>,
+}
+
+impl<Payload> Default for CellSchedulerPlugin<Payload> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<Payload> Plugin for CellSchedulerPlugin<Payload>
+where
+    Payload: Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<CellTickDue<Payload>>()
+            .add_systems(PreUpdate, send_due_cell_ticks::<Payload>);
+    }
+}
+
+/// Advances every [`CellScheduler`] by the frame time and sends a [`CellTickDue`] for every tick that is due
+pub fn send_due_cell_ticks<Payload>(
+    time: Res<Time>,
+    mut schedulers: Query<(Entity, &mut CellScheduler<Payload>)>,
+    mut cell_tick_due: EventWriter<CellTickDue<Payload>>,
+) where
+    Payload: Send + Sync + 'static,
+{
+    for (map_entity, mut scheduler) in schedulers.iter_mut() {
+        cell_tick_due.send_batch(scheduler.advance(time.delta()).into_iter().map(
+            |(cell, payload)| CellTickDue {
+                map_entity,
+                cell,
+                payload,
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CellScheduler, CellSchedulerPlugin, CellTickDue};
+    use bevy::app::App;
+    use bevy::ecs::event::Events;
+    use bevy::math::UVec2;
+    use bevy::time::Time;
+    use lettuces::cell::Cell;
+    use std::time::Duration;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    enum Burn {
+        Spread,
+        BurnOut,
+    }
+
+    #[test]
+    fn test_cell_scheduler() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_plugins(CellSchedulerPlugin::<Burn>::default());
+
+        let mut scheduler = CellScheduler::<Burn>::new(UVec2::splat(4));
+        scheduler.schedule_cell(Cell::new(1, 1), Duration::from_secs(5), Burn::BurnOut);
+        scheduler.schedule_cell(Cell::new(9, -3), Duration::from_secs(2), Burn::Spread);
+        scheduler.schedule_cell(Cell::new(2, 2), Duration::from_secs(2), Burn::Spread);
+        scheduler.schedule_cell(Cell::new(2, 2), Duration::from_secs(3), Burn::BurnOut);
+        assert_eq!(scheduler.pending_for_cell(Cell::new(2, 2)), 2);
+        assert_eq!(scheduler.cancel_cell(Cell::new(1, 1)), 1);
+        assert_eq!(scheduler.len(), 3);
+        let map_entity = app.world.spawn(scheduler).id();
+
+        let drain = |app: &mut App, delta: u64| {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs(delta));
+            app.update();
+            app.world
+                .resource_mut::<Events<CellTickDue<Burn>>>()
+                .drain()
+                .inspect(|event| assert_eq!(event.map_entity, map_entity))
+                .map(|event| (event.cell, event.payload))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(drain(&mut app, 1), vec![]);
+        assert_eq!(
+            drain(&mut app, 1),
+            vec![
+                (Cell::new(9, -3), Burn::Spread),
+                (Cell::new(2, 2), Burn::Spread)
+            ]
+        );
+        assert_eq!(drain(&mut app, 10), vec![(Cell::new(2, 2), Burn::BurnOut)]);
+        assert!(app
+            .world
+            .get::<CellScheduler<Burn>>(map_entity)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,266 @@
+//! Targeted change notifications for specific cells.
+//!
+//! Instead of every system reading every tile change and filtering for the handful of cells it cares about,
+//! observers register interest in cells or rects with [`CellWatchers::watch_cells`] or
+//! [`CellWatchers::watch_rect`]. Register [`CellWatchers::hook`] in the maps
+//! [`TileWriteHooks`](crate::map::TileWriteHooks) and the [`CellWatchersPlugin`] sends a
+//! [`WatchedCellChanged`] event for each observer interested in a written cell, and only to those observers.
+
+use crate::map::{TileWrite, TileWriteHook};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::{IRect, IVec2};
+use bevy::prelude::{Component, Entity, Event, EventWriter, Query};
+use bevy::utils::{HashMap, HashSet};
+use lettuces::cell::Cell;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+struct WatchState<TileData> {
+    cells: HashMap<Cell, HashSet<Entity>>,
+    rects: Vec<(Entity, IRect)>,
+    pending: Vec<(Entity, TileWrite<TileData>)>,
+}
+
+impl<TileData> WatchState<TileData> {
+    fn observers_of(&self, cell: Cell) -> HashSet<Entity> {
+        let mut observers = self.cells.get(&cell).cloned().unwrap_or_default();
+        for (observer, rect) in self.rects.iter() {
+            if rect.contains(IVec2::new(cell.x, cell.y)) {
+                observers.insert(*observer);
+            }
+        }
+        observers
+    }
+}
+
+/// The observers interested in cells of a tilemap. Add to the tilemap entity
+#[derive(Component)]
+pub struct CellWatchers<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    state: Arc<Mutex<WatchState<TileData>>>,
+}
+
+impl<TileData> Default for CellWatchers<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(WatchState {
+                cells: HashMap::default(),
+                rects: vec![],
+                pending: vec![],
+            })),
+        }
+    }
+}
+
+impl<TileData> CellWatchers<TileData>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    /// Creates a new set of watchers with no observers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers interest of `observer` in every given cell
+    pub fn watch_cells(&self, observer: Entity, cells: impl IntoIterator<Item = Cell>) {
+        let mut state = self.state.lock().expect("CellWatchers mutex poisoned");
+        for cell in cells {
+            state.cells.entry(cell).or_default().insert(observer);
+        }
+    }
+
+    /// Registers interest of `observer` in every cell of the given inclusive rect
+    pub fn watch_rect(&self, observer: Entity, rect: IRect) {
+        self.state
+            .lock()
+            .expect("CellWatchers mutex poisoned")
+            .rects
+            .push((observer, rect));
+    }
+
+    /// Removes interest of `observer` in the given cells. Doesn't affect rects it watches
+    pub fn unwatch_cells(&self, observer: Entity, cells: impl IntoIterator<Item = Cell>) {
+        let mut state = self.state.lock().expect("CellWatchers mutex poisoned");
+        for cell in cells {
+            if let Some(observers) = state.cells.get_mut(&cell) {
+                observers.remove(&observer);
+                if observers.is_empty() {
+                    state.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// Removes every cell and rect `observer` watches
+    pub fn unwatch(&self, observer: Entity) {
+        let mut state = self.state.lock().expect("CellWatchers mutex poisoned");
+        state.cells.retain(|_, observers| {
+            observers.remove(&observer);
+            !observers.is_empty()
+        });
+        state
+            .rects
+            .retain(|(rect_observer, _)| *rect_observer != observer);
+    }
+
+    /// Returns every observer interested in the given cell
+    pub fn observers_of(&self, cell: Cell) -> HashSet<Entity> {
+        self.state
+            .lock()
+            .expect("CellWatchers mutex poisoned")
+            .observers_of(cell)
+    }
+
+    /// Returns a [`TileWriteHook`] that queues a change for every observer interested in the written cell.
+    /// Register it in the maps [`TileWriteHooks`](crate::map::TileWriteHooks)
+    pub fn hook(&self) -> impl TileWriteHook<TileData> {
+        let state = self.state.clone();
+        move |write: &TileWrite<TileData>| {
+            let mut state = state.lock().expect("CellWatchers mutex poisoned");
+            let observers = state.observers_of(write.cell);
+            state
+                .pending
+                .extend(observers.into_iter().map(|observer| (observer, *write)));
+        }
+    }
+
+    /// Takes every queued change and the observer it is for
+    pub fn take_changes(&self) -> Vec<(Entity, TileWrite<TileData>)> {
+        std::mem::take(
+            &mut self
+                .state
+                .lock()
+                .expect("CellWatchers mutex poisoned")
+                .pending,
+        )
+    }
+}
+
+/// Event sent by the [`CellWatchersPlugin`] to a single observer when a cell it watches changed
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchedCellChanged<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    /// The observer interested in the cell
+    pub observer: Entity,
+    /// The tilemap entity the cell is in
+    pub map_entity: Entity,
+    /// The write that changed the cell
+    pub write: TileWrite<TileData>,
+}
+
+/// Plugin that sends queued [`CellWatchers`] changes as [`WatchedCellChanged`] events
+pub struct CellWatchersPlugin<TileData> {
+    ph: PhantomData<TileData>,
+}
+
+impl<TileData> Default for CellWatchersPlugin<TileData> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData> Plugin for CellWatchersPlugin<TileData>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<WatchedCellChanged<TileData>>()
+            .add_systems(PostUpdate, send_watched_cell_changes::<TileData>);
+    }
+}
+
+/// Sends a [`WatchedCellChanged`] for every change queued by the [`CellWatchers`] of every map
+pub fn send_watched_cell_changes<TileData>(
+    watchers: Query<(Entity, &CellWatchers<TileData>)>,
+    mut watched_cell_changed: EventWriter<WatchedCellChanged<TileData>>,
+) where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    for (map_entity, cell_watchers) in watchers.iter() {
+        watched_cell_changed.send_batch(cell_watchers.take_changes().into_iter().map(
+            |(observer, write)| WatchedCellChanged {
+                observer,
+                map_entity,
+                write,
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CellWatchers;
+    use crate as bevy_sparse_tilemap;
+    use crate::map::TileWriteHooks;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{IRect, UVec2};
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_cell_watchers_only_notify_interested_observers() {
+        let mut world = World::new();
+        let cell_observer = world.spawn_empty().id();
+        let rect_observer = world.spawn_empty().id();
+        let watchers = CellWatchers::<u8>::new();
+        watchers.watch_cells(cell_observer, [Cell::new(1, 1)]);
+        watchers.watch_rect(rect_observer, IRect::new(0, 0, 1, 1));
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        commands
+            .entity(map_entity)
+            .insert(TileWriteHooks::<u8>::new().with(watchers.hook()));
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(1, Cell::new(1, 1)).unwrap();
+        tilemap_manager.sets_tile_data(2, Cell::new(0, 1)).unwrap();
+        tilemap_manager.sets_tile_data(3, Cell::new(3, 3)).unwrap();
+
+        let mut changes: Vec<_> = watchers
+            .take_changes()
+            .into_iter()
+            .map(|(observer, write)| (observer, write.new))
+            .collect();
+        changes.sort();
+        let mut expected = vec![(cell_observer, 1), (rect_observer, 1), (rect_observer, 2)];
+        expected.sort();
+        assert_eq!(changes, expected);
+
+        watchers.unwatch(rect_observer);
+        assert_eq!(watchers.observers_of(Cell::new(1, 1)).len(), 1);
+        assert!(watchers.observers_of(Cell::new(0, 1)).is_empty());
+    }
+}
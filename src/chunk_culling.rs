@@ -0,0 +1,258 @@
+//! Hiding chunks outside of the view of every camera. Requires the `culling` feature.
+//!
+//! Renderers spawn a mesh or a material per chunk, and without culling every chunk of a huge map is drawn
+//! every frame. The [`ChunkCullingPlugin`] gives chunk entities and their map a [`VisibilityBundle`] and sets
+//! the [`Visibility`] of every chunk to [`Visibility::Hidden`] while its world space bounds are outside of the
+//! [`Frustum`] of every active [`Camera`], and back to [`Visibility::Inherited`] once any camera sees it. Chunk
+//! meshes spawned as children of the chunk entity inherit the visibility.
+//!
+//! The bounds of a chunk come from the [`TilemapGeometry`] of its map and the dimensions of the chunk, chunks
+//! of maps without a geometry are never hidden. Only the x and y extents of the view are tested so the depth
+//! of the chunks doesn't matter.
+//!
+//! ```ignore
+//! app.add_plugins(ChunkCullingPlugin::<TileData, SquareChunkLayer<TileData>, SquareMapData>::default());
+//! ```
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
+use crate::map::{MapData, Tilemap, TilemapGeometry};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::{Affine3A, IVec2, Rect, UVec2};
+use bevy::prelude::{
+    Added, Commands, Entity, IntoSystemConfigs, Query, Res, Resource, With, Without,
+};
+use bevy::render::camera::Camera;
+use bevy::render::primitives::{Aabb, Frustum};
+use bevy::render::view::{Visibility, VisibilityBundle, VisibilitySystems};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Settings used when culling chunks
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct ChunkCullingSettings {
+    /// How far in world units the bounds of every chunk are grown before testing them against the view, so
+    /// chunks are shown slightly before they scroll into view
+    pub margin: f32,
+}
+
+impl Default for ChunkCullingSettings {
+    fn default() -> Self {
+        Self { margin: 0.0 }
+    }
+}
+
+/// Plugin that hides the chunks with the given types outside of the view of every camera. See the
+/// [module docs](self) for details
+pub struct ChunkCullingPlugin<TileData, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapChunk, Map)>,
+}
+
+impl<TileData, MapChunk, Map> Default for ChunkCullingPlugin<TileData, MapChunk, Map> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk, Map> Plugin for ChunkCullingPlugin<TileData, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ChunkCullingSettings>().add_systems(
+            PostUpdate,
+            (
+                insert_chunk_visibility::<TileData, MapChunk, Map>,
+                cull_chunks::<TileData, MapChunk, Map>,
+            )
+                .chain()
+                .after(VisibilitySystems::UpdateOrthographicFrusta)
+                .after(VisibilitySystems::UpdatePerspectiveFrusta)
+                .after(VisibilitySystems::UpdateProjectionFrusta)
+                .before(VisibilitySystems::VisibilityPropagate),
+        );
+    }
+}
+
+/// Returns the world space bounds of the chunk at the given position, or `None` if the map doesn't have
+/// the chunk
+pub fn chunk_world_rect(
+    tilemap: &Tilemap,
+    map: &impl MapData,
+    geometry: &TilemapGeometry,
+    chunk_pos: ChunkPos,
+    chunk_dimensions: UVec2,
+) -> Option<Rect> {
+    tilemap.get_chunk(chunk_pos)?;
+    let first = chunk_pos.as_ivec2() * map.max_chunk_size().as_ivec2();
+    let last = first + chunk_dimensions.as_ivec2().max(IVec2::ONE) - 1;
+    Some(
+        geometry
+            .cell_rect(Cell::new(first.x, first.y))
+            .union(geometry.cell_rect(Cell::new(last.x, last.y))),
+    )
+}
+
+/// Inserts a [`VisibilityBundle`] on new chunks and their maps that don't have a [`Visibility`] yet
+#[allow(clippy::type_complexity)]
+pub fn insert_chunk_visibility<TileData, MapChunk, Map>(
+    mut commands: Commands,
+    new_chunks: Query<Entity, (Added<Chunk<MapChunk, TileData>>, Without<Visibility>)>,
+    maps: Query<Entity, (With<Tilemap>, With<Map>, Without<Visibility>)>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    for entity in new_chunks.iter().chain(maps.iter()) {
+        commands.entity(entity).insert(VisibilityBundle::default());
+    }
+}
+
+/// Hides every chunk outside of the [`Frustum`] of every active [`Camera`] and shows every other chunk
+pub fn cull_chunks<TileData, MapChunk, Map>(
+    settings: Res<ChunkCullingSettings>,
+    cameras: Query<(&Camera, &Frustum)>,
+    maps: Query<(&Tilemap, &Map, &TilemapGeometry)>,
+    mut chunks: Query<(&Chunk<MapChunk, TileData>, &mut Visibility)>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let frusta: Vec<&Frustum> = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(_, frustum)| frustum)
+        .collect();
+
+    for (tilemap, map, geometry) in maps.iter() {
+        let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+        ) {
+            let Some(chunk_entity) = tilemap.get_chunk(chunk_pos) else {
+                continue;
+            };
+            let Ok((chunk, mut visibility)) = chunks.get_mut(chunk_entity) else {
+                continue;
+            };
+            let Some(rect) = chunk_world_rect(
+                tilemap,
+                map,
+                geometry,
+                chunk_pos,
+                chunk.get_chunk_dimensions(),
+            ) else {
+                continue;
+            };
+            let rect = rect.inset(settings.margin);
+            let aabb = Aabb::from_min_max(rect.min.extend(0.0), rect.max.extend(0.0));
+            let visible = frusta
+                .iter()
+                .any(|frustum| frustum.intersects_obb(&aabb, &Affine3A::IDENTITY, false, false));
+            let new_visibility = match visible {
+                true => Visibility::Inherited,
+                false => Visibility::Hidden,
+            };
+            if *visibility != new_visibility {
+                *visibility = new_visibility;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkCullingPlugin, ChunkCullingSettings};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::map::{Tilemap, TilemapGeometry};
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::SquareTilemapBuilder;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{Mat4, UVec2, Vec2};
+    use bevy::prelude::Entity;
+    use bevy::render::camera::Camera;
+    use bevy::render::primitives::Frustum;
+    use bevy::render::view::Visibility;
+    use bst_map_layer_derive::MapLayer;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    fn view(left: f32, right: f32, bottom: f32, top: f32) -> Frustum {
+        Frustum::from_view_projection(&Mat4::orthographic_rh(
+            left, right, bottom, top, -1000.0, 1000.0,
+        ))
+    }
+
+    fn visible_chunks(app: &App, map_entity: Entity) -> Vec<ChunkPos> {
+        let tilemap = app.world.get::<Tilemap>(map_entity).unwrap();
+        let mut visible = vec![];
+        for chunk_pos in ChunkPos::iter_rect(ChunkPos::new(0, 0), ChunkPos::new(3, 3)) {
+            let chunk_entity = tilemap.get_chunk(chunk_pos).unwrap();
+            if app.world.get::<Visibility>(chunk_entity) == Some(&Visibility::Inherited) {
+                visible.push(chunk_pos);
+            }
+        }
+        visible
+    }
+
+    #[test]
+    fn test_chunk_culling() {
+        let mut app = App::new();
+        app.add_plugins(ChunkCullingPlugin::<u8, SquareChunkLayer<u8>, SquareMapData>::default());
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut app.world);
+        let mut commands = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .with_map_bundle(TilemapGeometry::new(Vec2::ZERO, Vec2::splat(16.0)))
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let camera = commands
+            .spawn((Camera::default(), view(8.0, 56.0, 8.0, 56.0)))
+            .id();
+        system_state.apply(&mut app.world);
+
+        // Visibility is inserted in the first update and chunks are culled from the second
+        app.update();
+        app.update();
+        assert!(app.world.get::<Visibility>(map_entity).is_some());
+        let visible = visible_chunks(&app, map_entity);
+        assert_eq!(visible.len(), 4);
+        assert!(visible.contains(&ChunkPos::new(1, 1)));
+        assert!(!visible.contains(&ChunkPos::new(2, 0)));
+
+        app.world
+            .entity_mut(camera)
+            .insert(view(70.0, 86.0, 100.0, 118.0));
+        app.update();
+        assert_eq!(visible_chunks(&app, map_entity), vec![ChunkPos::new(2, 3)]);
+
+        // The margin shows chunks close to the view
+        app.world.resource_mut::<ChunkCullingSettings>().margin = 8.0;
+        app.update();
+        assert_eq!(visible_chunks(&app, map_entity).len(), 4);
+
+        app.world.get_mut::<Camera>(camera).unwrap().is_active = false;
+        app.update();
+        assert!(visible_chunks(&app, map_entity).is_empty());
+    }
+}
@@ -0,0 +1,212 @@
+//! Chunk level-of-detail summaries.
+//!
+//! When a map is viewed from far away renderers often want to draw one quad per chunk instead of every
+//! tile. The [`ChunkLodPlugin`] keeps a [`ChunkLod`] summary on every chunk entity containing the dominant
+//! tile and an average color of a single layer. Summaries are only recomputed for chunks that changed and a
+//! [`ChunkLodChanged`] event is sent whenever a summary changes. Maps can turn summaries off or update them
+//! less often through [`TilemapSettings`].
+
+use crate::map::chunk::{Chunk, ChunkCell, ChunkLayer};
+use crate::map::{MapLayer, TilemapSettings, TilemapSubsystems};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::Vec4;
+use bevy::prelude::{
+    Changed, Commands, Component, Entity, Event, EventWriter, Local, Parent, Query, Res, Resource,
+};
+use bevy::utils::{HashMap, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// A level-of-detail summary of a single layer of a chunk. Inserted onto chunk entities by the
+/// [`ChunkLodPlugin`]
+#[derive(Component, Clone, Debug, PartialEq)]
+pub struct ChunkLod<TileData> {
+    /// The tile that appears most often in the layer. `None` if the layer has no tiles
+    pub dominant_tile: Option<TileData>,
+    /// The average of the colors returned by [`ChunkLodSettings::color`] for every tile in the layer
+    pub average_color: Vec4,
+    /// The amount of tiles in the layer that have data
+    pub tile_count: u32,
+}
+
+impl<TileData> ChunkLod<TileData>
+where
+    TileData: Eq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+{
+    /// Computes the summary for the given layer of the chunk. Returns an empty summary if the layer doesn't
+    /// exist in the chunk.
+    ///
+    /// If several tiles are tied for the most common tile any one of them is returned as the dominant tile.
+    pub fn from_chunk<MapChunk>(
+        chunk: &Chunk<MapChunk, TileData>,
+        map_layer: u64,
+        color: fn(&TileData) -> Vec4,
+    ) -> Self
+    where
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    {
+        let mut counts: HashMap<TileData, u32> = HashMap::default();
+        let mut color_sum = Vec4::ZERO;
+        let mut tile_count = 0u32;
+
+        if let Some(layer) = chunk.data.get(&map_layer) {
+            let dimensions = layer.get_chunk_dimensions();
+            for y in 0..dimensions.y as i32 {
+                for x in 0..dimensions.x as i32 {
+                    if let Some(tile_data) = layer.get_tile_data(ChunkCell::new(x, y)) {
+                        *counts.entry(*tile_data).or_insert(0) += 1;
+                        color_sum += color(tile_data);
+                        tile_count += 1;
+                    }
+                }
+            }
+        }
+
+        Self {
+            dominant_tile: counts
+                .into_iter()
+                .max_by_key(|(_, count)| *count)
+                .map(|(tile_data, _)| tile_data),
+            average_color: if tile_count == 0 {
+                Vec4::ZERO
+            } else {
+                color_sum / tile_count as f32
+            },
+            tile_count,
+        }
+    }
+}
+
+/// Settings used by the [`ChunkLodPlugin`] to compute [`ChunkLod`]s
+#[derive(Resource)]
+pub struct ChunkLodSettings<TileData> {
+    /// The layer that summaries are computed for
+    pub map_layer: u64,
+    /// Function returning the color of a tile. Colors are averaged to get [`ChunkLod::average_color`]
+    pub color: fn(&TileData) -> Vec4,
+}
+
+/// Event sent whenever the [`ChunkLod`] of a chunk changes
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkLodChanged {
+    /// The entity of the chunk whose summary changed
+    pub chunk_entity: Entity,
+}
+
+/// Plugin that maintains a [`ChunkLod`] on every chunk entity with the given `TileData` and `MapChunk`
+pub struct ChunkLodPlugin<TileData, MapChunk> {
+    map_layer: u64,
+    color: fn(&TileData) -> Vec4,
+    ph: PhantomData<MapChunk>,
+}
+
+impl<TileData, MapChunk> ChunkLodPlugin<TileData, MapChunk> {
+    /// Creates a new plugin that summarizes the given [`MapLayer`] using the given color function
+    pub fn new(map_layer: impl MapLayer, color: fn(&TileData) -> Vec4) -> Self {
+        Self {
+            map_layer: map_layer.to_bits(),
+            color,
+            ph: PhantomData,
+        }
+    }
+}
+
+impl<TileData, MapChunk> Plugin for ChunkLodPlugin<TileData, MapChunk>
+where
+    TileData: Eq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ChunkLodSettings::<TileData> {
+            map_layer: self.map_layer,
+            color: self.color,
+        })
+        .add_event::<ChunkLodChanged>()
+        .add_systems(PostUpdate, update_chunk_lods::<TileData, MapChunk>);
+    }
+}
+
+/// Recomputes the [`ChunkLod`] of every chunk that changed since the last run.
+///
+/// Chunks of maps whose [`TilemapSettings`] skip [`TilemapSubsystems::CHUNK_LOD`] this tick are remembered and
+/// recomputed the next time their map runs.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn update_chunk_lods<TileData, MapChunk>(
+    mut commands: Commands,
+    settings: Res<ChunkLodSettings<TileData>>,
+    changed_chunks: Query<Entity, Changed<Chunk<MapChunk, TileData>>>,
+    chunks: Query<(
+        &Chunk<MapChunk, TileData>,
+        Option<&ChunkLod<TileData>>,
+        Option<&Parent>,
+    )>,
+    map_settings: Query<&TilemapSettings>,
+    mut pending: Local<HashSet<Entity>>,
+    mut tick: Local<u64>,
+    mut lod_changed: EventWriter<ChunkLodChanged>,
+) where
+    TileData: Eq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    pending.extend(changed_chunks.iter());
+    let current_tick = *tick;
+    *tick = tick.wrapping_add(1);
+
+    pending.retain(|chunk_entity| {
+        let Ok((chunk, old_lod, parent)) = chunks.get(*chunk_entity) else {
+            return false;
+        };
+        let map_settings = parent.and_then(|parent| map_settings.get(parent.get()).ok());
+        if !TilemapSettings::should_run_for(
+            map_settings,
+            TilemapSubsystems::CHUNK_LOD,
+            current_tick,
+        ) {
+            return true;
+        }
+        let lod = ChunkLod::from_chunk(chunk, settings.map_layer, settings.color);
+        if old_lod != Some(&lod) {
+            commands.entity(*chunk_entity).insert(lod);
+            lod_changed.send(ChunkLodChanged {
+                chunk_entity: *chunk_entity,
+            });
+        }
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkLod;
+    use crate::map::chunk::{Chunk, ChunkCell, ChunkLayerType, ChunkPos};
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use bevy::math::{UVec2, Vec4};
+    use bevy::utils::HashMap;
+
+    #[test]
+    fn test_chunk_lod_summary() {
+        let vecs = vec![vec![1u8, 1, 2], vec![1, 3, 1]];
+        let mut chunk: Chunk<SquareChunkLayer<u8>, u8> = Chunk::new(
+            ChunkPos::new(0, 0),
+            UVec2::new(3, 2),
+            ChunkLayerType::Dense(vecs),
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(3, 2),
+            },
+        );
+        let lod = ChunkLod::from_chunk(&chunk, 1, |tile| Vec4::splat(*tile as f32));
+        assert_eq!(lod.dominant_tile, Some(1));
+        assert_eq!(lod.tile_count, 6);
+        assert_eq!(lod.average_color, Vec4::splat(1.5));
+
+        chunk.add_layer(2, ChunkLayerType::Sparse(HashMap::new()));
+        let lod = ChunkLod::from_chunk(&chunk, 2, |tile| Vec4::splat(*tile as f32));
+        assert_eq!(lod.dominant_tile, None);
+        assert_eq!(lod.tile_count, 0);
+
+        chunk.set_tile_data(2, ChunkCell::new(1, 1), 7);
+        let lod = ChunkLod::from_chunk(&chunk, 2, |tile| Vec4::splat(*tile as f32));
+        assert_eq!(lod.dominant_tile, Some(7));
+        assert_eq!(lod.average_color, Vec4::splat(7.0));
+    }
+}
@@ -0,0 +1,285 @@
+//! Prioritized uploading of changed chunks to renderers.
+//!
+//! Rendering integrations such as `bevy_fast_tilemap` copy the tile data of every changed chunk to the GPU.
+//! When a large part of the map changes at once, for example when the map is revealed or terraformed,
+//! uploading every chunk in the same frame stalls the app. The [`ChunkSyncPlugin`] records changed chunks in
+//! a [`ChunkSyncQueue`] on their map instead. Renderers pop at most
+//! [`TilemapSettings::chunk_sync_budget`] chunks every frame, chunks on screen first, then chunks near the
+//! screen, then the rest.
+//!
+//! ```ignore
+//! fn upload_chunks(mut maps: Query<(&Tilemap, &mut ChunkSyncQueue, Option<&TilemapSettings>)>) {
+//!     for (tilemap, mut queue, settings) in maps.iter_mut() {
+//!         let settings = settings.cloned().unwrap_or_default();
+//!         for (chunk_pos, _) in queue.pop_prioritized(visible_min, visible_max, &settings) {
+//!             // Upload the chunk at tilemap.get_chunk(chunk_pos)
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
+use crate::map::{MapData, TilemapGeometry, TilemapSettings};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::Rect;
+use bevy::prelude::{Changed, Commands, Component, Entity, Parent, Query};
+use bevy::utils::{HashMap, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// How urgently a changed chunk should be uploaded. Lower priorities are uploaded first
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChunkSyncPriority {
+    /// The chunk is visible
+    OnScreen,
+    /// The chunk is within [`TilemapSettings::chunk_sync_margin`] chunks of the screen
+    NearScreen,
+    /// The chunk is further away from the screen
+    OffScreen,
+}
+
+impl ChunkSyncPriority {
+    /// Returns the priority of the chunk given the inclusive rectangle of visible chunks
+    pub fn of(
+        chunk_pos: ChunkPos,
+        visible_min: ChunkPos,
+        visible_max: ChunkPos,
+        margin: u32,
+    ) -> Self {
+        let distance =
+            |value: i32, min: i32, max: i32| (min - value).max(value - max).max(0) as u32;
+        let distance = distance(chunk_pos.x(), visible_min.x(), visible_max.x()).max(distance(
+            chunk_pos.y(),
+            visible_min.y(),
+            visible_max.y(),
+        ));
+        match distance {
+            0 => ChunkSyncPriority::OnScreen,
+            distance if distance <= margin => ChunkSyncPriority::NearScreen,
+            _ => ChunkSyncPriority::OffScreen,
+        }
+    }
+}
+
+/// The chunks of a map that changed since they were last uploaded by a renderer. Inserted on the map entity by
+/// the [`ChunkSyncPlugin`]
+#[derive(Component, Clone, Debug, Default)]
+pub struct ChunkSyncQueue {
+    dirty: HashSet<ChunkPos>,
+}
+
+impl ChunkSyncQueue {
+    /// Marks the chunk at the given position as changed
+    pub fn mark_dirty(&mut self, chunk_pos: ChunkPos) {
+        self.dirty.insert(chunk_pos);
+    }
+
+    /// Returns true if the chunk at the given position is waiting to be uploaded
+    pub fn is_dirty(&self, chunk_pos: ChunkPos) -> bool {
+        self.dirty.contains(&chunk_pos)
+    }
+
+    /// Returns the amount of chunks waiting to be uploaded
+    pub fn len(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Returns true if no chunks are waiting to be uploaded
+    pub fn is_empty(&self) -> bool {
+        self.dirty.is_empty()
+    }
+
+    /// Removes and returns the chunks to upload this frame together with their priority, at most
+    /// [`TilemapSettings::chunk_sync_budget`] of them.
+    ///
+    /// Chunks are ordered by their [`ChunkSyncPriority`] given the inclusive rectangle of visible chunks, and
+    /// chunks of the same priority by their distance to the center of the screen. Chunks over the budget stay
+    /// in the queue for later frames.
+    pub fn pop_prioritized(
+        &mut self,
+        visible_min: ChunkPos,
+        visible_max: ChunkPos,
+        settings: &TilemapSettings,
+    ) -> Vec<(ChunkPos, ChunkSyncPriority)> {
+        let center = (visible_min.as_ivec2() + visible_max.as_ivec2()) / 2;
+        let mut chunks: Vec<(ChunkPos, ChunkSyncPriority)> = self
+            .dirty
+            .iter()
+            .map(|chunk_pos| {
+                (
+                    *chunk_pos,
+                    ChunkSyncPriority::of(
+                        *chunk_pos,
+                        visible_min,
+                        visible_max,
+                        settings.chunk_sync_margin,
+                    ),
+                )
+            })
+            .collect();
+        chunks.sort_by_key(|(chunk_pos, priority)| {
+            let offset = chunk_pos.as_ivec2() - center;
+            (
+                *priority,
+                offset.x.abs() + offset.y.abs(),
+                chunk_pos.y(),
+                chunk_pos.x(),
+            )
+        });
+        if let Some(budget) = settings.chunk_sync_budget {
+            chunks.truncate(budget);
+        }
+        for (chunk_pos, _) in chunks.iter() {
+            self.dirty.remove(chunk_pos);
+        }
+        chunks
+    }
+}
+
+/// Returns the inclusive rectangle of chunks overlapping the given world space view, such as the area shown
+/// by a camera
+pub fn visible_chunk_rect(
+    view: Rect,
+    geometry: &TilemapGeometry,
+    map: &impl MapData,
+) -> (ChunkPos, ChunkPos) {
+    (
+        map.into_chunk_pos(geometry.world_to_cell(view.min)),
+        map.into_chunk_pos(geometry.world_to_cell(view.max)),
+    )
+}
+
+/// Plugin that records changed chunks with the given types in the [`ChunkSyncQueue`] of their map
+pub struct ChunkSyncPlugin<TileData, MapChunk> {
+    ph: PhantomData<(TileData, MapChunk)>,
+}
+
+impl<TileData, MapChunk> Default for ChunkSyncPlugin<TileData, MapChunk> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk> Plugin for ChunkSyncPlugin<TileData, MapChunk>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, queue_changed_chunks::<TileData, MapChunk>);
+    }
+}
+
+/// Marks every chunk that changed since the last run as dirty in the [`ChunkSyncQueue`] of its map, inserting
+/// the queue if the map doesn't have one yet
+#[allow(clippy::type_complexity)]
+pub fn queue_changed_chunks<TileData, MapChunk>(
+    mut commands: Commands,
+    changed_chunks: Query<
+        (&Chunk<MapChunk, TileData>, &Parent),
+        Changed<Chunk<MapChunk, TileData>>,
+    >,
+    mut queues: Query<&mut ChunkSyncQueue>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    let mut new_queues: HashMap<Entity, ChunkSyncQueue> = HashMap::default();
+    for (chunk, parent) in changed_chunks.iter() {
+        match queues.get_mut(parent.get()) {
+            Ok(mut queue) => queue.mark_dirty(chunk.chunk_pos),
+            Err(_) => new_queues
+                .entry(parent.get())
+                .or_default()
+                .mark_dirty(chunk.chunk_pos),
+        }
+    }
+    for (map_entity, queue) in new_queues {
+        if let Some(mut map_commands) = commands.get_entity(map_entity) {
+            map_commands.insert(queue);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkSyncPlugin, ChunkSyncPriority, ChunkSyncQueue};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::map::TilemapSettings;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::SquareTilemapBuilder;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bst_map_layer_derive::MapLayer;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_chunk_sync_priority() {
+        let (min, max) = (ChunkPos::new(2, 2), ChunkPos::new(3, 3));
+        assert_eq!(
+            ChunkSyncPriority::of(ChunkPos::new(3, 2), min, max, 1),
+            ChunkSyncPriority::OnScreen
+        );
+        assert_eq!(
+            ChunkSyncPriority::of(ChunkPos::new(1, 4), min, max, 1),
+            ChunkSyncPriority::NearScreen
+        );
+        assert_eq!(
+            ChunkSyncPriority::of(ChunkPos::new(5, 3), min, max, 1),
+            ChunkSyncPriority::OffScreen
+        );
+    }
+
+    #[test]
+    fn test_chunk_sync_queue() {
+        let mut app = App::new();
+        app.add_plugins(ChunkSyncPlugin::<u8, SquareChunkLayer<u8>>::default());
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut app.world);
+        let mut commands = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut app.world);
+
+        // Every freshly spawned chunk needs uploading
+        app.update();
+        let mut queue = app
+            .world
+            .get_mut::<ChunkSyncQueue>(map_entity)
+            .expect("the plugin inserts a queue");
+        assert_eq!(queue.len(), 16);
+
+        let settings = TilemapSettings {
+            chunk_sync_budget: Some(6),
+            ..TilemapSettings::default()
+        };
+        let first = queue.pop_prioritized(ChunkPos::new(0, 0), ChunkPos::new(1, 1), &settings);
+        assert_eq!(first.len(), 6);
+        assert!(first[..4]
+            .iter()
+            .all(|(_, priority)| *priority == ChunkSyncPriority::OnScreen));
+        assert!(first[4..]
+            .iter()
+            .all(|(_, priority)| *priority == ChunkSyncPriority::NearScreen));
+        assert!(!queue.is_dirty(ChunkPos::new(1, 1)));
+        assert!(queue.is_dirty(ChunkPos::new(3, 3)));
+        assert_eq!(queue.len(), 10);
+    }
+}
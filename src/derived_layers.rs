@@ -0,0 +1,340 @@
+//! Layers computed from other layers.
+//!
+//! A [`DerivedLayers`] holds rules that derive the tile data of one layer from another, such as a navigation cost
+//! layer derived from a terrain layer or an autotile index layer derived from a wall layer. Register
+//! [`DerivedLayers::hook`] in the maps [`TileWriteHooks`](crate::map::TileWriteHooks) and every write to a
+//! source layer marks the cell dirty. The [`DerivedLayersPlugin`] recomputes dirty cells once per frame, grouped
+//! by chunk, and writes the result into the derived layer through the
+//! [`TilemapManager`](crate::tilemap_manager::TilemapManager).
+//!
+//! Derived layers can be sources of other rules. Chains are resolved in the same frame, while cycles are
+//! recomputed at most once per rule each frame and continue in the next one. Cells that already existed when
+//! the rule was added are not recomputed until they are written or marked with [`DerivedLayers::mark_dirty`].
+
+use crate::map::chunk::{ChunkLayer, ChunkPos};
+use crate::map::{MapData, MapLayer, TileWrite, TileWriteHook};
+use crate::tilemap_manager::TilemapManager;
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::prelude::{Component, Entity, Query};
+use bevy::utils::{HashMap, HashSet};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+struct DerivedRule<TileData, MapLayers> {
+    source: MapLayers,
+    target: MapLayers,
+    derive: Box<dyn Fn(TileData) -> TileData + Send + Sync>,
+}
+
+struct DerivedState<TileData, MapLayers> {
+    rules: Vec<DerivedRule<TileData, MapLayers>>,
+    dirty: HashSet<(usize, Cell)>,
+}
+
+impl<TileData, MapLayers: MapLayer> DerivedState<TileData, MapLayers> {
+    fn mark_dirty(&mut self, map_layer: u64, cell: Cell) {
+        for (index, rule) in self.rules.iter().enumerate() {
+            if rule.source.to_bits() == map_layer {
+                self.dirty.insert((index, cell));
+            }
+        }
+    }
+}
+
+/// The derived layer rules of a tilemap. Add to the tilemap entity
+#[derive(Component)]
+pub struct DerivedLayers<TileData, MapLayers>
+where
+    TileData: Send + Sync + 'static,
+    MapLayers: Send + Sync + 'static,
+{
+    state: Arc<Mutex<DerivedState<TileData, MapLayers>>>,
+}
+
+impl<TileData, MapLayers> Default for DerivedLayers<TileData, MapLayers>
+where
+    TileData: Send + Sync + 'static,
+    MapLayers: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(DerivedState {
+                rules: vec![],
+                dirty: HashSet::default(),
+            })),
+        }
+    }
+}
+
+impl<TileData, MapLayers> DerivedLayers<TileData, MapLayers>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+{
+    /// Creates a new set of derived layers without any rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule that sets the tile data of `target` to `derive` of the tile data in the same cell of
+    /// `source` whenever it changes
+    pub fn derive(
+        &self,
+        source: MapLayers,
+        target: MapLayers,
+        derive: impl Fn(TileData) -> TileData + Send + Sync + 'static,
+    ) {
+        self.state
+            .lock()
+            .expect("DerivedLayers mutex poisoned")
+            .rules
+            .push(DerivedRule {
+                source,
+                target,
+                derive: Box::new(derive),
+            });
+    }
+
+    /// Adds a rule and returns self. See [`Self::derive`]
+    pub fn with(
+        self,
+        source: MapLayers,
+        target: MapLayers,
+        derive: impl Fn(TileData) -> TileData + Send + Sync + 'static,
+    ) -> Self {
+        self.derive(source, target, derive);
+        self
+    }
+
+    /// Marks the given cells of `map_layer` dirty so every layer derived from it is recomputed there. Use it to
+    /// compute derived layers of a freshly built map
+    pub fn mark_dirty(&self, map_layer: MapLayers, cells: impl IntoIterator<Item = Cell>) {
+        let mut state = self.state.lock().expect("DerivedLayers mutex poisoned");
+        for cell in cells {
+            state.mark_dirty(map_layer.to_bits(), cell);
+        }
+    }
+
+    /// Returns the amount of dirty cells waiting to be recomputed, counted once per rule
+    pub fn dirty_count(&self) -> usize {
+        self.state
+            .lock()
+            .expect("DerivedLayers mutex poisoned")
+            .dirty
+            .len()
+    }
+
+    /// Returns a [`TileWriteHook`] that marks written cells of source layers dirty. Register it in the maps
+    /// [`TileWriteHooks`](crate::map::TileWriteHooks)
+    pub fn hook(&self) -> impl TileWriteHook<TileData> {
+        let state = self.state.clone();
+        move |write: &TileWrite<TileData>| {
+            state
+                .lock()
+                .expect("DerivedLayers mutex poisoned")
+                .mark_dirty(write.map_layer, write.cell);
+        }
+    }
+
+    /// Takes every dirty cell and returns the cells of each rule grouped by the [`ChunkPos`] they are in
+    fn take_dirty(&self, map: &impl MapData) -> HashMap<ChunkPos, Vec<(usize, Cell)>> {
+        let mut chunks: HashMap<ChunkPos, Vec<(usize, Cell)>> = HashMap::default();
+        let dirty = std::mem::take(
+            &mut self
+                .state
+                .lock()
+                .expect("DerivedLayers mutex poisoned")
+                .dirty,
+        );
+        for (rule, cell) in dirty {
+            chunks
+                .entry(map.into_chunk_pos(cell))
+                .or_default()
+                .push((rule, cell));
+        }
+        chunks
+    }
+
+    /// Returns the target layer of the rule and the derived tile data
+    fn apply_rule(&self, rule: usize, source_data: TileData) -> (MapLayers, TileData) {
+        let state = self.state.lock().expect("DerivedLayers mutex poisoned");
+        let rule = &state.rules[rule];
+        (rule.target, (rule.derive)(source_data))
+    }
+
+    fn rule_source(&self, rule: usize) -> MapLayers {
+        self.state
+            .lock()
+            .expect("DerivedLayers mutex poisoned")
+            .rules[rule]
+            .source
+    }
+
+    fn rule_count(&self) -> usize {
+        self.state
+            .lock()
+            .expect("DerivedLayers mutex poisoned")
+            .rules
+            .len()
+    }
+}
+
+/// Plugin that recomputes the dirty cells of every [`DerivedLayers`] in [`PostUpdate`]
+pub struct DerivedLayersPlugin<TileData, MapLayers, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapLayers, MapChunk, Map)>,
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Default
+    for DerivedLayersPlugin<TileData, MapLayers, MapChunk, Map>
+{
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Plugin
+    for DerivedLayersPlugin<TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            recompute_derived_layers::<TileData, MapLayers, MapChunk, Map>,
+        );
+    }
+}
+
+/// Recomputes the dirty cells of every [`DerivedLayers`] one chunk at a time
+pub fn recompute_derived_layers<TileData, MapLayers, MapChunk, Map>(
+    derived_layers: Query<(Entity, &DerivedLayers<TileData, MapLayers>, &Map)>,
+    mut tilemap_manager: TilemapManager<TileData, MapLayers, MapChunk, Map>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let selection = tilemap_manager.selection();
+    for (map_entity, derived, map) in derived_layers.iter() {
+        tilemap_manager.set_tilemap_entity(map_entity);
+        // Every pass resolves one more link of a chain of rules. Cycles stay dirty for the next frame
+        for _ in 0..derived.rule_count() {
+            let chunks = derived.take_dirty(map);
+            if chunks.is_empty() {
+                break;
+            }
+            for (rule, cell) in chunks.into_values().flatten() {
+                tilemap_manager.set_layer(derived.rule_source(rule));
+                let Ok(source_data) = tilemap_manager.get_tile_data(cell) else {
+                    continue;
+                };
+                let (target, tile_data) = derived.apply_rule(rule, source_data);
+                tilemap_manager.set_layer(target);
+                let _ = tilemap_manager.sets_tile_data(tile_data, cell);
+            }
+        }
+    }
+    tilemap_manager.set_selection(selection);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DerivedLayers, DerivedLayersPlugin};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::TileWriteHooks;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Terrain,
+        Cost,
+        DoubleCost,
+    }
+
+    #[test]
+    fn test_derived_layers() {
+        let mut app = App::new();
+        app.add_plugins(DerivedLayersPlugin::<
+            u8,
+            MapLayers,
+            SquareChunkLayer<u8>,
+            SquareMapData,
+        >::default());
+        // Registered out of order so the chain needs two passes
+        let derived = DerivedLayers::<u8, MapLayers>::new()
+            .with(MapLayers::Cost, MapLayers::DoubleCost, |cost| cost * 2)
+            .with(MapLayers::Terrain, MapLayers::Cost, |terrain| terrain + 1);
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let mut builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            MapLayers::Cost,
+        );
+        builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            MapLayers::DoubleCost,
+        );
+        let map_entity = builder
+            .with_map_bundle(TileWriteHooks::<u8>::new().with(derived.hook()))
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut app.world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(3, Cell::new(0, 0)).unwrap();
+        tilemap_manager.sets_tile_data(5, Cell::new(3, 3)).unwrap();
+        derived.mark_dirty(MapLayers::Terrain, [Cell::new(2, 0)]);
+        assert_eq!(derived.dirty_count(), 3);
+        app.world.entity_mut(map_entity).insert(derived);
+
+        app.update();
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        for (cell, cost) in [
+            (Cell::new(0, 0), 4),
+            (Cell::new(3, 3), 6),
+            (Cell::new(2, 0), 1),
+            (Cell::new(1, 1), 0),
+        ] {
+            tilemap_manager.set_layer(MapLayers::Cost);
+            assert_eq!(tilemap_manager.get_tile_data(cell).unwrap(), cost);
+            tilemap_manager.set_layer(MapLayers::DoubleCost);
+            assert_eq!(tilemap_manager.get_tile_data(cell).unwrap(), cost * 2);
+        }
+        assert_eq!(
+            app.world
+                .get::<DerivedLayers<u8, MapLayers>>(map_entity)
+                .unwrap()
+                .dirty_count(),
+            0
+        );
+    }
+}
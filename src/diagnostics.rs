@@ -0,0 +1,267 @@
+//! Per frame statistics about tilemap activity for profiling overlays.
+//!
+//! The [`TilemapDiagnosticsPlugin`] registers a set of [`Diagnostic`]s with bevys diagnostics so perf HUDs
+//! and the `LogDiagnosticsPlugin` can chart tilemap activity next to frame times, and sends a
+//! [`TilemapMetrics`] event with the raw numbers at the end of every frame.
+//!
+//! Tile writes are counted by registering [`TilemapMetricsCounters::hook`] in the
+//! [`TileWriteHooks`](crate::map::TileWriteHooks) of every map that should be measured. Events and batches
+//! are recorded by whoever sends or applies them through [`TilemapMetricsCounters::record_events`] and
+//! [`TilemapMetricsCounters::record_batch`].
+
+use crate::map::chunk::{Chunk, ChunkLayer};
+use crate::map::{TileWrite, TileWriteHook};
+use bevy::app::{App, Last, Plugin};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::{Changed, Event, EventWriter, Query, Res, Resource};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The tilemap activity of a single frame
+#[derive(Event, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TilemapMetrics {
+    /// Tiles written through hooked [`TilemapManager`](crate::tilemap_manager::TilemapManager)s
+    pub tiles_written: u64,
+    /// Chunks whose data changed
+    pub chunks_dirtied: u64,
+    /// Events recorded with [`TilemapMetricsCounters::record_events`]
+    pub events_emitted: u64,
+    /// Batches recorded with [`TilemapMetricsCounters::record_batch`]
+    pub batches: u64,
+    /// The total size of every recorded batch
+    pub batched_tiles: u64,
+    /// The largest recorded batch
+    pub largest_batch: u64,
+}
+
+impl TilemapMetrics {
+    /// Returns the average size of the recorded batches. 0 if there were none
+    pub fn average_batch_size(&self) -> f64 {
+        if self.batches == 0 {
+            return 0.0;
+        }
+        self.batched_tiles as f64 / self.batches as f64
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    tiles_written: AtomicU64,
+    events_emitted: AtomicU64,
+    batches: AtomicU64,
+    batched_tiles: AtomicU64,
+    largest_batch: AtomicU64,
+}
+
+/// The counters of the current frame. Cheap to clone, every clone shares the same counters
+#[derive(Resource, Clone, Default)]
+pub struct TilemapMetricsCounters {
+    counters: Arc<Counters>,
+}
+
+impl TilemapMetricsCounters {
+    /// Returns a [`TileWriteHook`] that counts every write to the map it is registered on
+    pub fn hook<TileData>(&self) -> impl TileWriteHook<TileData> {
+        let counters = self.counters.clone();
+        move |_: &TileWrite<TileData>| {
+            counters.tiles_written.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records that `count` tiles were written without going through a hooked manager
+    pub fn record_tiles_written(&self, count: u64) {
+        self.counters
+            .tiles_written
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records that `count` tilemap events were sent
+    pub fn record_events(&self, count: u64) {
+        self.counters
+            .events_emitted
+            .fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records that a batch of `size` tiles was applied
+    pub fn record_batch(&self, size: u64) {
+        self.counters.batches.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .batched_tiles
+            .fetch_add(size, Ordering::Relaxed);
+        self.counters
+            .largest_batch
+            .fetch_max(size, Ordering::Relaxed);
+    }
+
+    /// Returns the counters recorded since the last call and resets them. `chunks_dirtied` is always 0 as it
+    /// is counted by [`measure_tilemap_metrics`]
+    pub fn take(&self) -> TilemapMetrics {
+        TilemapMetrics {
+            tiles_written: self.counters.tiles_written.swap(0, Ordering::Relaxed),
+            chunks_dirtied: 0,
+            events_emitted: self.counters.events_emitted.swap(0, Ordering::Relaxed),
+            batches: self.counters.batches.swap(0, Ordering::Relaxed),
+            batched_tiles: self.counters.batched_tiles.swap(0, Ordering::Relaxed),
+            largest_batch: self.counters.largest_batch.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// Plugin that measures tilemap activity every frame. See the [module docs](self) for more details
+pub struct TilemapDiagnosticsPlugin<TileData, MapChunk> {
+    ph: PhantomData<(TileData, MapChunk)>,
+}
+
+impl<TileData, MapChunk> Default for TilemapDiagnosticsPlugin<TileData, MapChunk> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk> TilemapDiagnosticsPlugin<TileData, MapChunk> {
+    /// Tiles written during the frame
+    pub const TILES_WRITTEN: DiagnosticPath =
+        DiagnosticPath::const_new("bevy_sparse_tilemap/tiles_written");
+    /// Chunks whose data changed during the frame
+    pub const CHUNKS_DIRTIED: DiagnosticPath =
+        DiagnosticPath::const_new("bevy_sparse_tilemap/chunks_dirtied");
+    /// Tilemap events recorded during the frame
+    pub const EVENTS_EMITTED: DiagnosticPath =
+        DiagnosticPath::const_new("bevy_sparse_tilemap/events_emitted");
+    /// Average size of the batches recorded during the frame
+    pub const AVERAGE_BATCH_SIZE: DiagnosticPath =
+        DiagnosticPath::const_new("bevy_sparse_tilemap/average_batch_size");
+}
+
+impl<TileData, MapChunk> Plugin for TilemapDiagnosticsPlugin<TileData, MapChunk>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapMetricsCounters>()
+            .add_event::<TilemapMetrics>()
+            .register_diagnostic(Diagnostic::new(Self::TILES_WRITTEN))
+            .register_diagnostic(Diagnostic::new(Self::CHUNKS_DIRTIED))
+            .register_diagnostic(Diagnostic::new(Self::EVENTS_EMITTED))
+            .register_diagnostic(Diagnostic::new(Self::AVERAGE_BATCH_SIZE))
+            .add_systems(Last, measure_tilemap_metrics::<TileData, MapChunk>);
+    }
+}
+
+/// Collects the counters of the frame, records them as diagnostics and sends them as a [`TilemapMetrics`]
+pub fn measure_tilemap_metrics<TileData, MapChunk>(
+    counters: Res<TilemapMetricsCounters>,
+    dirtied_chunks: Query<(), Changed<Chunk<MapChunk, TileData>>>,
+    mut diagnostics: Diagnostics,
+    mut tilemap_metrics: EventWriter<TilemapMetrics>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    let mut metrics = counters.take();
+    metrics.chunks_dirtied = dirtied_chunks.iter().count() as u64;
+
+    diagnostics.add_measurement(
+        &TilemapDiagnosticsPlugin::<TileData, MapChunk>::TILES_WRITTEN,
+        || metrics.tiles_written as f64,
+    );
+    diagnostics.add_measurement(
+        &TilemapDiagnosticsPlugin::<TileData, MapChunk>::CHUNKS_DIRTIED,
+        || metrics.chunks_dirtied as f64,
+    );
+    diagnostics.add_measurement(
+        &TilemapDiagnosticsPlugin::<TileData, MapChunk>::EVENTS_EMITTED,
+        || metrics.events_emitted as f64,
+    );
+    diagnostics.add_measurement(
+        &TilemapDiagnosticsPlugin::<TileData, MapChunk>::AVERAGE_BATCH_SIZE,
+        || metrics.average_batch_size(),
+    );
+    tilemap_metrics.send(metrics);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TilemapDiagnosticsPlugin, TilemapMetrics, TilemapMetricsCounters};
+    use crate::map::TileWriteHooks;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::diagnostic::DiagnosticsStore;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    use crate as bevy_sparse_tilemap;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_tilemap_metrics() {
+        type Plugin = TilemapDiagnosticsPlugin<u8, SquareChunkLayer<u8>>;
+        let mut app = App::new();
+        app.add_plugins(Plugin::default());
+        let counters = app.world.resource::<TilemapMetricsCounters>().clone();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        commands
+            .entity(map_entity)
+            .insert(TileWriteHooks::<u8>::new().with(counters.hook()));
+        system_state.apply(&mut app.world);
+        app.update();
+        app.world.resource_mut::<Events<TilemapMetrics>>().clear();
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(1, Cell::new(0, 0)).unwrap();
+        tilemap_manager.sets_tile_data(1, Cell::new(1, 1)).unwrap();
+        tilemap_manager.sets_tile_data(1, Cell::new(3, 3)).unwrap();
+        counters.record_events(2);
+        counters.record_batch(4);
+        counters.record_batch(8);
+        app.update();
+
+        let metrics = *app
+            .world
+            .resource::<Events<TilemapMetrics>>()
+            .iter_current_update_events()
+            .last()
+            .unwrap();
+        assert_eq!(metrics.tiles_written, 3);
+        assert_eq!(metrics.chunks_dirtied, 2);
+        assert_eq!(metrics.events_emitted, 2);
+        assert_eq!(metrics.largest_batch, 8);
+        assert_eq!(metrics.average_batch_size(), 6.0);
+        assert_eq!(
+            app.world
+                .resource::<DiagnosticsStore>()
+                .get(&Plugin::TILES_WRITTEN)
+                .and_then(|diagnostic| diagnostic.value()),
+            Some(3.0)
+        );
+    }
+}
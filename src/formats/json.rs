@@ -0,0 +1,253 @@
+//! A small JSON reader for the project files written by map editors. Numbers are read as `f64`.
+
+/// A JSON value
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    /// Returns the value of the given key of an object. `None` if the key is missing or the value isn't an
+    /// object
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(entries) => entries
+                .iter()
+                .find(|(entry, _)| entry == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            JsonValue::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a JSON document. Returns a description of the problem and the byte offset it was found at if the
+/// document isn't valid JSON
+pub(crate) fn parse_json(text: &str) -> Result<JsonValue, (String, usize)> {
+    let mut reader = JsonReader { text, position: 0 };
+    let value = reader.value()?;
+    reader.skip_whitespace();
+    if reader.position < text.len() {
+        return Err(reader.error("content after the value"));
+    }
+    Ok(value)
+}
+
+struct JsonReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> JsonReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, message: &str) -> (String, usize) {
+        (message.to_string(), self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), (String, usize)> {
+        self.skip_whitespace();
+        if !self.rest().starts_with(token) {
+            return Err(self.error(&format!("expected {token:?}")));
+        }
+        self.position += token.len();
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<JsonValue, (String, usize)> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        match rest.chars().next() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(JsonValue::String(self.string()?)),
+            Some('-' | '0'..='9') => self.number(),
+            _ => {
+                for (token, value) in [
+                    ("null", JsonValue::Null),
+                    ("true", JsonValue::Bool(true)),
+                    ("false", JsonValue::Bool(false)),
+                ] {
+                    if rest.starts_with(token) {
+                        self.position += token.len();
+                        return Ok(value);
+                    }
+                }
+                Err(self.error("expected a value"))
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, (String, usize)> {
+        self.expect("{")?;
+        let mut entries = vec![];
+        self.skip_whitespace();
+        if self.rest().starts_with('}') {
+            self.position += 1;
+            return Ok(JsonValue::Object(entries));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(":")?;
+            entries.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.rest().chars().next() {
+                Some(',') => self.position += 1,
+                Some('}') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Object(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, (String, usize)> {
+        self.expect("[")?;
+        let mut values = vec![];
+        self.skip_whitespace();
+        if self.rest().starts_with(']') {
+            self.position += 1;
+            return Ok(JsonValue::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.rest().chars().next() {
+                Some(',') => self.position += 1,
+                Some(']') => {
+                    self.position += 1;
+                    return Ok(JsonValue::Array(values));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<JsonValue, (String, usize)> {
+        let rest = self.rest();
+        let length = rest
+            .find(|character: char| !matches!(character, '-' | '+' | '.' | 'e' | 'E' | '0'..='9'))
+            .unwrap_or(rest.len());
+        let number = rest[..length]
+            .parse()
+            .map_err(|_| self.error("invalid number"))?;
+        self.position += length;
+        Ok(JsonValue::Number(number))
+    }
+
+    fn string(&mut self) -> Result<String, (String, usize)> {
+        self.expect("\"")?;
+        let mut string = String::new();
+        let mut characters = self.rest().char_indices();
+        while let Some((index, character)) = characters.next() {
+            match character {
+                '"' => {
+                    self.position += index + 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    let escaped = match characters.next().map(|(_, escaped)| escaped) {
+                        Some('"') => '"',
+                        Some('\\') => '\\',
+                        Some('/') => '/',
+                        Some('b') => '\u{8}',
+                        Some('f') => '\u{c}',
+                        Some('n') => '\n',
+                        Some('r') => '\r',
+                        Some('t') => '\t',
+                        Some('u') => {
+                            let code_point = match code_unit(&mut characters) {
+                                // A surrogate pair written as two escapes
+                                Some(high @ 0xD800..=0xDBFF) => {
+                                    let _ = (characters.next(), characters.next());
+                                    code_unit(&mut characters).map(|low| {
+                                        0x10000 + ((high - 0xD800) << 10) + low.wrapping_sub(0xDC00)
+                                    })
+                                }
+                                code_point => code_point,
+                            };
+                            code_point
+                                .and_then(char::from_u32)
+                                .unwrap_or(char::REPLACEMENT_CHARACTER)
+                        }
+                        _ => return Err(self.error("invalid escape in a string")),
+                    };
+                    string.push(escaped);
+                }
+                character => string.push(character),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+}
+
+/// Reads the four hex digits of a `\u` escape
+fn code_unit(characters: &mut std::str::CharIndices) -> Option<u32> {
+    let hex: String = characters.take(4).map(|(_, character)| character).collect();
+    u32::from_str_radix(&hex, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_json, JsonValue};
+
+    #[test]
+    fn test_parse_json() {
+        let value = parse_json(
+            r#"{
+                "name": "Level_0\n\u00e9\ud83d\ude00",
+                "size": [16, -2.5e1],
+                "external": false,
+                "path": null,
+                "empty": {}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(value.get("name").unwrap().as_str(), Some("Level_0\né😀"));
+        let size = value.get("size").unwrap().as_array().unwrap();
+        assert_eq!(size[0].as_f64(), Some(16.0));
+        assert_eq!(size[1].as_f64(), Some(-25.0));
+        assert_eq!(value.get("external"), Some(&JsonValue::Bool(false)));
+        assert_eq!(value.get("path"), Some(&JsonValue::Null));
+        assert_eq!(value.get("empty"), Some(&JsonValue::Object(vec![])));
+        assert_eq!(value.get("missing"), None);
+
+        assert!(parse_json(r#"{"a": [1, 2}"#).is_err());
+        assert!(parse_json(r#"{"a": 1} 2"#).is_err());
+        assert!(parse_json(r#""unterminated"#).is_err());
+    }
+}
@@ -0,0 +1,668 @@
+//! Importing projects made in the [LDtk](https://ldtk.io) editor. Requires the `ldtk` feature.
+//!
+//! [`LdtkProject::from_json`] reads a `.ldtk` project and [`LdtkProject::resolve_levels`] reads the `.ldtkl`
+//! files of a project saved with separate level files. Every [`LdtkLevel`] becomes its own tilemap through
+//! [`LdtkLevel::tilemap_builder`] or [`LdtkProject::spawn_levels`]: IntGrid layers become dense
+//! [`TilemapLayer`]s holding the tile data of their values and entity layers become sparse [`TilemapLayer`]s
+//! with a tile entity carrying an [`LdtkEntityMarker`] for every entity. Layers are matched to `MapLayers`
+//! by their identifier in LDtk, tile and auto layers without IntGrid values are skipped.
+//!
+//! LDtk puts row 0 at the top of a level while this crate puts it at the bottom, so rows are flipped when
+//! importing.
+//!
+//! ```ignore
+//! let mut project = LdtkProject::from_json(&std::fs::read_to_string("assets/world.ldtk")?)?;
+//! project.resolve_levels(|path| std::fs::read_to_string(format!("assets/{path}")).ok())?;
+//! let levels = project.spawn_levels::<TileData, MapLayers, SquareChunkLayer<TileData>, _>(
+//!     &mut commands,
+//!     SquareMapData { max_chunk_size: UVec2::splat(64) },
+//!     SquareChunkSettings { max_chunk_size: UVec2::splat(64) },
+//!     |identifier| match identifier {
+//!         "Collisions" => Some(MapLayers::Main),
+//!         "Entities" => Some(MapLayers::Entities),
+//!         _ => None,
+//!     },
+//!     |value| TileData::from_int_grid(value),
+//!     |entity| (entity.identifier == "Chest").then(|| TileData::chest()),
+//! );
+//! ```
+
+use crate::formats::json::{parse_json, JsonValue};
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_builder::TilemapBuilder;
+use bevy::math::UVec2;
+use bevy::prelude::{Commands, Component, Entity};
+use bevy::utils::hashbrown::HashMap;
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// Errors returned when importing an LDtk project
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum LdtkError {
+    /// The file isn't valid JSON
+    #[error("Invalid JSON at byte {position}: {message}")]
+    Json {
+        /// A description of the problem
+        message: String,
+        /// The byte offset of the problem
+        position: usize,
+    },
+
+    /// A field is missing or has the wrong type
+    #[error("{object} has a missing or invalid {field:?} field")]
+    InvalidField {
+        /// The object holding the field
+        object: String,
+        /// The name of the field
+        field: &'static str,
+    },
+
+    /// The IntGrid values of a layer don't match its size
+    #[error("Layer {layer:?} doesn't have one IntGrid value for each of its cells")]
+    InvalidLayerData {
+        /// The identifier of the layer
+        layer: String,
+    },
+
+    /// The layers of a level are stored in a separate file that hasn't been read
+    #[error("The layers of level {0:?} are in a separate file, use LdtkProject::resolve_levels")]
+    UnresolvedLevel(String),
+
+    /// The contents of a separate level file couldn't be read
+    #[error("The level file {0:?} couldn't be read")]
+    MissingLevel(String),
+}
+
+/// Marks the tile entity spawned for an LDtk entity
+#[derive(Component, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LdtkEntityMarker {
+    /// The identifier of the entity definition, such as `Player`
+    pub identifier: String,
+    /// The unique id of the entity instance
+    pub iid: String,
+}
+
+/// An entity placed in an entity layer of an [`LdtkLevel`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LdtkEntity {
+    /// The identifier of the entity definition, such as `Player`
+    pub identifier: String,
+    /// The unique id of the entity instance
+    pub iid: String,
+    /// The cell of the entity with row 0 at the bottom of the level
+    pub cell: Cell,
+    /// The tags of the entity definition
+    pub tags: Vec<String>,
+}
+
+/// The contents of a layer of an [`LdtkLevel`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LdtkLayerData {
+    /// The values of an IntGrid layer with row 0 at the bottom of the level. 0 is an empty cell
+    IntGrid(Vec<Vec<i32>>),
+    /// The entities of an entity layer
+    Entities(Vec<LdtkEntity>),
+}
+
+/// A layer of an [`LdtkLevel`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LdtkLayer {
+    /// The identifier of the layer
+    pub identifier: String,
+    /// The size of the layer in cells
+    pub size: UVec2,
+    /// The size of a cell in pixels
+    pub grid_size: u32,
+    /// The contents of the layer
+    pub data: LdtkLayerData,
+}
+
+/// A level of an [`LdtkProject`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LdtkLevel {
+    /// The identifier of the level, such as `Level_0`
+    pub identifier: String,
+    /// The unique id of the level
+    pub iid: String,
+    /// The path of the `.ldtkl` file holding the layers of the level, relative to the project. `None` if the
+    /// layers are stored in the project
+    pub external_path: Option<String>,
+    /// The IntGrid and entity layers of the level from the top to the bottom. Empty until an external level is
+    /// resolved
+    pub layers: Vec<LdtkLayer>,
+}
+
+impl LdtkLevel {
+    /// Reads a level from the contents of its `.ldtkl` file
+    pub fn from_json(ldtkl: &str) -> Result<Self, LdtkError> {
+        Self::from_value(&parse_document(ldtkl)?)
+    }
+
+    fn from_value(value: &JsonValue) -> Result<Self, LdtkError> {
+        let identifier = string_field(value, "Level", "identifier")?;
+        let object = format!("Level {identifier:?}");
+        let mut layers = vec![];
+        for layer in value
+            .get("layerInstances")
+            .and_then(JsonValue::as_array)
+            .unwrap_or_default()
+        {
+            if let Some(layer) = LdtkLayer::from_value(layer)? {
+                layers.push(layer);
+            }
+        }
+        Ok(Self {
+            iid: string_field(value, &object, "iid")?,
+            external_path: value
+                .get("externalRelPath")
+                .and_then(JsonValue::as_str)
+                .map(str::to_string),
+            layers,
+            identifier,
+        })
+    }
+
+    /// Returns the size of the level in cells, the size of its largest layer
+    pub fn size(&self) -> UVec2 {
+        self.layers
+            .iter()
+            .fold(UVec2::ZERO, |size, layer| size.max(layer.size))
+    }
+
+    /// Returns the layer with the given identifier
+    pub fn layer(&self, identifier: &str) -> Option<&LdtkLayer> {
+        self.layers
+            .iter()
+            .find(|layer| layer.identifier == identifier)
+    }
+
+    /// Creates a [`TilemapBuilder`] out of the level.
+    ///
+    /// `map_layer` picks the `MapLayers` variant each layer is imported as from its identifier, layers it
+    /// returns `None` for are skipped. If several layers are imported as the same variant the topmost one is
+    /// used. The layer imported as the default variant is the main layer, if there is none the main layer is a
+    /// dense layer of default tile data.
+    ///
+    /// IntGrid values are turned into tile data with `int_grid_data`, empty cells hold the default tile data.
+    /// Entity layers hold the tile data `entity_data` returns for each entity and get a tile entity with an
+    /// [`LdtkEntityMarker`] spawned for every entity. Only the last entity in a cell is imported. Layers
+    /// smaller than the level are padded with empty cells.
+    pub fn tilemap_builder<TileData, MapLayers, MapChunk, MapType>(
+        &self,
+        commands: &mut Commands,
+        map_type: MapType,
+        chunk_settings: MapChunk::ChunkSettings,
+        map_layer: impl Fn(&str) -> Option<MapLayers>,
+        int_grid_data: impl Fn(i32) -> TileData,
+        entity_data: impl Fn(&LdtkEntity) -> Option<TileData>,
+    ) -> Result<TilemapBuilder<TileData, MapLayers, MapChunk, MapType>, LdtkError>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        MapType: MapData + Default + Send + Sync + 'static,
+    {
+        if self.external_path.is_some() && self.layers.is_empty() {
+            return Err(LdtkError::UnresolvedLevel(self.identifier.clone()));
+        }
+        let size = self.size();
+        let mut layers: Vec<(MapLayers, TilemapLayer<TileData>)> = vec![];
+        for layer in self.layers.iter() {
+            let Some(target) = map_layer(&layer.identifier) else {
+                continue;
+            };
+            if layers
+                .iter()
+                .any(|(map_layer, _)| map_layer.to_bits() == target.to_bits())
+            {
+                continue;
+            }
+            let layer_data = match &layer.data {
+                LdtkLayerData::IntGrid(rows) => TilemapLayer::new_dense_from_vecs(
+                    (0..size.y as usize)
+                        .map(|y| {
+                            (0..size.x as usize)
+                                .map(|x| match rows.get(y).and_then(|row| row.get(x)) {
+                                    Some(value) if *value != 0 => int_grid_data(*value),
+                                    _ => TileData::default(),
+                                })
+                                .collect()
+                        })
+                        .collect(),
+                ),
+                LdtkLayerData::Entities(entities) => {
+                    let entities: HashMap<Cell, &LdtkEntity> = entities
+                        .iter()
+                        .filter(|entity| {
+                            entity.cell.x < size.x as i32 && entity.cell.y < size.y as i32
+                        })
+                        .map(|entity| (entity.cell, entity))
+                        .collect();
+                    let tiles = entities
+                        .iter()
+                        .filter_map(|(cell, entity)| Some((*cell, entity_data(entity)?)))
+                        .collect();
+                    let mut layer_data = TilemapLayer::new_sparse_from_hashmap(
+                        size.x as usize,
+                        size.y as usize,
+                        tiles,
+                    );
+                    for (cell, entity) in entities {
+                        layer_data.spawn_entity_at_tile_pos(
+                            cell,
+                            LdtkEntityMarker {
+                                identifier: entity.identifier.clone(),
+                                iid: entity.iid.clone(),
+                            },
+                            commands,
+                        );
+                    }
+                    layer_data
+                }
+            };
+            layers.push((target, layer_data));
+        }
+
+        let main_bits = MapLayers::default().to_bits();
+        let main_layer = match layers
+            .iter()
+            .position(|(map_layer, _)| map_layer.to_bits() == main_bits)
+        {
+            Some(index) => layers.remove(index).1,
+            None => TilemapLayer::new_dense_default(size.x as usize, size.y as usize),
+        };
+        let mut tilemap_builder = TilemapBuilder::new(main_layer, map_type, chunk_settings);
+        for (map_layer, layer_data) in layers {
+            tilemap_builder.add_layer(layer_data, map_layer);
+        }
+        Ok(tilemap_builder)
+    }
+}
+
+impl LdtkLayer {
+    /// Reads an IntGrid or entity layer, returns `None` for other layers
+    fn from_value(value: &JsonValue) -> Result<Option<Self>, LdtkError> {
+        let identifier = string_field(value, "Layer", "__identifier")?;
+        let object = format!("Layer {identifier:?}");
+        let size = UVec2::new(
+            u32_field(value, &object, "__cWid")?,
+            u32_field(value, &object, "__cHei")?,
+        );
+        let grid_size = u32_field(value, &object, "__gridSize")?;
+        let int_grid = value
+            .get("intGridCsv")
+            .and_then(JsonValue::as_array)
+            .filter(|values| !values.is_empty());
+
+        let data = match (string_field(value, &object, "__type")?.as_str(), int_grid) {
+            ("Entities", _) => {
+                let mut entities = vec![];
+                for entity in value
+                    .get("entityInstances")
+                    .and_then(JsonValue::as_array)
+                    .unwrap_or_default()
+                {
+                    entities.push(LdtkEntity::from_value(entity, size)?);
+                }
+                LdtkLayerData::Entities(entities)
+            }
+            ("IntGrid", Some(values)) => {
+                let values: Vec<i32> = values
+                    .iter()
+                    .map(|value| value.as_f64().map(|value| value as i32))
+                    .collect::<Option<_>>()
+                    .ok_or_else(|| LdtkError::InvalidLayerData {
+                        layer: identifier.clone(),
+                    })?;
+                if size.x == 0 || values.len() != (size.x * size.y) as usize {
+                    return Err(LdtkError::InvalidLayerData { layer: identifier });
+                }
+                LdtkLayerData::IntGrid(
+                    values
+                        .chunks(size.x as usize)
+                        .rev()
+                        .map(<[i32]>::to_vec)
+                        .collect(),
+                )
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(Self {
+            identifier,
+            size,
+            grid_size,
+            data,
+        }))
+    }
+}
+
+impl LdtkEntity {
+    fn from_value(value: &JsonValue, layer_size: UVec2) -> Result<Self, LdtkError> {
+        let identifier = string_field(value, "Entity", "__identifier")?;
+        let object = format!("Entity {identifier:?}");
+        let invalid_grid = || LdtkError::InvalidField {
+            object: object.clone(),
+            field: "__grid",
+        };
+        let grid = value
+            .get("__grid")
+            .and_then(JsonValue::as_array)
+            .ok_or_else(invalid_grid)?;
+        let [x, row] = [grid.first(), grid.get(1)]
+            .map(|coordinate| coordinate.and_then(JsonValue::as_f64).map(|c| c as i32));
+        let (Some(x), Some(row)) = (x, row) else {
+            return Err(invalid_grid());
+        };
+        Ok(Self {
+            iid: string_field(value, &object, "iid")?,
+            cell: Cell::new(x, layer_size.y as i32 - 1 - row),
+            tags: value
+                .get("__tags")
+                .and_then(JsonValue::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|tag| tag.as_str().map(str::to_string))
+                .collect(),
+            identifier,
+        })
+    }
+}
+
+/// A project read from an LDtk `.ldtk` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LdtkProject {
+    /// The levels of the project
+    pub levels: Vec<LdtkLevel>,
+}
+
+impl LdtkProject {
+    /// Reads a project from the contents of its `.ldtk` file. Levels saved in separate files are only
+    /// referenced, use [`Self::resolve_levels`] to read them
+    pub fn from_json(ldtk: &str) -> Result<Self, LdtkError> {
+        let root = parse_document(ldtk)?;
+        let mut levels = vec![];
+        for level in
+            root.get("levels")
+                .and_then(JsonValue::as_array)
+                .ok_or(LdtkError::InvalidField {
+                    object: "Project".to_string(),
+                    field: "levels",
+                })?
+        {
+            levels.push(LdtkLevel::from_value(level)?);
+        }
+        Ok(Self { levels })
+    }
+
+    /// Reads every level saved in a separate file. `read` returns the contents of the `.ldtkl` file at the
+    /// given path
+    pub fn resolve_levels(
+        &mut self,
+        mut read: impl FnMut(&str) -> Option<String>,
+    ) -> Result<(), LdtkError> {
+        for level in self.levels.iter_mut() {
+            let Some(path) = level.external_path.clone() else {
+                continue;
+            };
+            let ldtkl = read(&path).ok_or_else(|| LdtkError::MissingLevel(path.clone()))?;
+            level.layers = LdtkLevel::from_json(&ldtkl)?.layers;
+        }
+        Ok(())
+    }
+
+    /// Returns the level with the given identifier
+    pub fn level(&self, identifier: &str) -> Option<&LdtkLevel> {
+        self.levels
+            .iter()
+            .find(|level| level.identifier == identifier)
+    }
+
+    /// Spawns one tilemap for every level and returns the identifier and tilemap entity of each. See
+    /// [`LdtkLevel::tilemap_builder`] for how levels are imported
+    pub fn spawn_levels<TileData, MapLayers, MapChunk, MapType>(
+        &self,
+        commands: &mut Commands,
+        map_type: MapType,
+        chunk_settings: MapChunk::ChunkSettings,
+        map_layer: impl Fn(&str) -> Option<MapLayers>,
+        int_grid_data: impl Fn(i32) -> TileData,
+        entity_data: impl Fn(&LdtkEntity) -> Option<TileData>,
+    ) -> Result<Vec<(String, Entity)>, LdtkError>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        MapType: MapData + Clone + Default + Send + Sync + 'static,
+    {
+        let mut tilemaps = vec![];
+        for level in self.levels.iter() {
+            let tilemap_builder = level.tilemap_builder::<TileData, MapLayers, MapChunk, MapType>(
+                commands,
+                map_type.clone(),
+                chunk_settings,
+                &map_layer,
+                &int_grid_data,
+                &entity_data,
+            )?;
+            if let Some(map_entity) = tilemap_builder.spawn_tilemap(commands) {
+                tilemaps.push((level.identifier.clone(), map_entity));
+            }
+        }
+        Ok(tilemaps)
+    }
+}
+
+fn parse_document(text: &str) -> Result<JsonValue, LdtkError> {
+    parse_json(text).map_err(|(message, position)| LdtkError::Json { message, position })
+}
+
+fn string_field(value: &JsonValue, object: &str, field: &'static str) -> Result<String, LdtkError> {
+    value
+        .get(field)
+        .and_then(JsonValue::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| LdtkError::InvalidField {
+            object: object.to_string(),
+            field,
+        })
+}
+
+fn u32_field(value: &JsonValue, object: &str, field: &'static str) -> Result<u32, LdtkError> {
+    value
+        .get(field)
+        .and_then(JsonValue::as_f64)
+        .filter(|number| *number >= 0.0)
+        .map(|number| number as u32)
+        .ok_or_else(|| LdtkError::InvalidField {
+            object: object.to_string(),
+            field,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LdtkEntityMarker, LdtkError, LdtkLayerData, LdtkLevel, LdtkProject};
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::SquareTilemapManager;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Collisions,
+        Entities,
+    }
+
+    const LDTK: &str = r#"{
+        "jsonVersion": "1.5.3",
+        "externalLevels": false,
+        "levels": [
+            {
+                "identifier": "Level_0",
+                "iid": "a1",
+                "externalRelPath": null,
+                "layerInstances": [
+                    {
+                        "__identifier": "Entities",
+                        "__type": "Entities",
+                        "__cWid": 3,
+                        "__cHei": 2,
+                        "__gridSize": 16,
+                        "intGridCsv": [],
+                        "entityInstances": [
+                            { "__identifier": "Player", "iid": "e1", "__grid": [0, 0], "__tags": ["actor"], "px": [0, 0] },
+                            { "__identifier": "Chest", "iid": "e2", "__grid": [2, 1], "__tags": [], "px": [32, 16] }
+                        ]
+                    },
+                    {
+                        "__identifier": "Collisions",
+                        "__type": "IntGrid",
+                        "__cWid": 3,
+                        "__cHei": 2,
+                        "__gridSize": 16,
+                        "intGridCsv": [1, 0, 2, 0, 0, 1],
+                        "entityInstances": []
+                    },
+                    {
+                        "__identifier": "Background",
+                        "__type": "Tiles",
+                        "__cWid": 3,
+                        "__cHei": 2,
+                        "__gridSize": 16,
+                        "intGridCsv": [],
+                        "entityInstances": []
+                    }
+                ]
+            },
+            {
+                "identifier": "Level_1",
+                "iid": "b2",
+                "externalRelPath": "world/Level_1.ldtkl",
+                "layerInstances": null
+            }
+        ]
+    }"#;
+
+    const LDTKL: &str = r#"{
+        "identifier": "Level_1",
+        "iid": "b2",
+        "layerInstances": [
+            {
+                "__identifier": "Collisions",
+                "__type": "IntGrid",
+                "__cWid": 2,
+                "__cHei": 2,
+                "__gridSize": 16,
+                "intGridCsv": [3, 3, 3, 3],
+                "entityInstances": []
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_read_ldtk() {
+        let mut project = LdtkProject::from_json(LDTK).unwrap();
+        let level = project.level("Level_0").unwrap();
+        assert_eq!(level.size(), UVec2::new(3, 2));
+        // Tile layers are skipped
+        assert_eq!(level.layers.len(), 2);
+        let LdtkLayerData::IntGrid(rows) = &level.layer("Collisions").unwrap().data else {
+            panic!("Collisions is an IntGrid layer");
+        };
+        // The last row in the file is row 0
+        assert_eq!(rows, &vec![vec![0, 0, 1], vec![1, 0, 2]]);
+        let LdtkLayerData::Entities(entities) = &level.layer("Entities").unwrap().data else {
+            panic!("Entities is an entity layer");
+        };
+        assert_eq!(entities[0].cell, Cell::new(0, 1));
+        assert_eq!(entities[0].tags, vec!["actor".to_string()]);
+        assert_eq!(entities[1].cell, Cell::new(2, 0));
+
+        assert!(project.level("Level_1").unwrap().layers.is_empty());
+        assert_eq!(
+            project.resolve_levels(|_| None),
+            Err(LdtkError::MissingLevel("world/Level_1.ldtkl".to_string()))
+        );
+        project
+            .resolve_levels(|path| (path == "world/Level_1.ldtkl").then(|| LDTKL.to_string()))
+            .unwrap();
+        assert_eq!(project.level("Level_1").unwrap().size(), UVec2::new(2, 2));
+        assert_eq!(
+            LdtkLevel::from_json(&LDTKL.replace("[3, 3, 3, 3]", "[3, 3, 3]")),
+            Err(LdtkError::InvalidLayerData {
+                layer: "Collisions".to_string()
+            })
+        );
+        assert!(matches!(
+            LdtkProject::from_json("{\"levels\": [}"),
+            Err(LdtkError::Json { .. })
+        ));
+    }
+
+    #[test]
+    fn test_spawn_ldtk_levels() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let mut project = LdtkProject::from_json(LDTK).unwrap();
+        let map_layer = |identifier: &str| match identifier {
+            "Collisions" => Some(MapLayers::Collisions),
+            "Entities" => Some(MapLayers::Entities),
+            _ => None,
+        };
+        let spawn = |project: &LdtkProject, commands: &mut Commands| {
+            project.spawn_levels::<u8, MapLayers, SquareChunkLayer<u8>, _>(
+                commands,
+                SquareMapData {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+                SquareChunkSettings {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+                map_layer,
+                |value| value as u8,
+                |entity| (entity.identifier == "Chest").then_some(9),
+            )
+        };
+        assert_eq!(
+            spawn(&project, &mut commands),
+            Err(LdtkError::UnresolvedLevel("Level_1".to_string()))
+        );
+        project.resolve_levels(|_| Some(LDTKL.to_string())).unwrap();
+        let levels = spawn(&project, &mut commands).unwrap();
+        system_state.apply(&mut world);
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[1].0, "Level_1");
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(levels[0].1);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(3, 2));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 1)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 1)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 0);
+        tilemap_manager.set_layer(MapLayers::Entities);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 0)).unwrap(), 9);
+        assert!(tilemap_manager.get_tile_data(Cell::new(0, 1)).is_err());
+        let player = tilemap_manager.get_tile_entity(Cell::new(0, 1)).unwrap();
+        tilemap_manager.set_tilemap_entity(levels[1].1);
+        tilemap_manager.set_layer(MapLayers::Collisions);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 3);
+
+        assert_eq!(
+            world.get::<LdtkEntityMarker>(player),
+            Some(&LdtkEntityMarker {
+                identifier: "Player".to_string(),
+                iid: "e1".to_string()
+            })
+        );
+    }
+}
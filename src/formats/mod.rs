@@ -0,0 +1,11 @@
+//! Importers that turn maps made in other editors into [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder)s,
+//! so levels can be authored in an editor and loaded into chunked storage.
+
+#[cfg(feature = "ldtk")]
+mod json;
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
+#[cfg(feature = "tiled")]
+pub mod tiled;
+#[cfg(feature = "tiled")]
+mod xml;
@@ -0,0 +1,704 @@
+//! Importing maps made in the [Tiled](https://www.mapeditor.org) editor. Requires the `tiled` feature.
+//!
+//! [`TiledMap::from_tmx`] reads a `.tmx` map and [`TiledMap::resolve_tilesets`] reads the external `.tsx`
+//! tilesets it references. [`TiledMap::tilemap_builder`] then turns the map into a [`TilemapBuilder`]: every
+//! tile layer becomes a dense [`TilemapLayer`] holding the tile data of its tiles and every object layer a
+//! sparse [`TilemapLayer`] holding the tile data of its objects. Layers are matched to `MapLayers` by their
+//! name in Tiled.
+//!
+//! Only finite orthogonal maps are supported. Tile layers can use the CSV, XML, or uncompressed base64
+//! encodings. Tiled puts row 0 at the top of the map while this crate puts it at the bottom, so rows are
+//! flipped when importing.
+//!
+//! ```ignore
+//! let mut tiled_map = TiledMap::from_tmx(&std::fs::read_to_string("assets/level.tmx")?)?;
+//! tiled_map.resolve_tilesets(|source| std::fs::read_to_string(format!("assets/{source}")).ok())?;
+//! let map_entity = tiled_map
+//!     .tilemap_builder::<TileData, MapLayers, SquareChunkLayer<TileData>, _>(
+//!         SquareMapData { max_chunk_size: UVec2::splat(64) },
+//!         SquareChunkSettings { max_chunk_size: UVec2::splat(64) },
+//!         |name| match name {
+//!             "Ground" => Some(MapLayers::Main),
+//!             "Spawns" => Some(MapLayers::Spawns),
+//!             _ => None,
+//!         },
+//!         |tile| TileData::new(tile.local_id),
+//!         |object| (object.object_type == "spawn").then(|| TileData::spawn()),
+//!     )
+//!     .spawn_tilemap(&mut commands);
+//! ```
+
+use crate::formats::xml::{parse_xml, XmlElement};
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_builder::TilemapBuilder;
+use base64::Engine;
+use bevy::math::{UVec2, Vec2};
+use bevy::utils::hashbrown::HashMap;
+use lettuces::cell::Cell;
+use std::hash::Hash;
+use std::str::FromStr;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const ROTATED_HEXAGONAL_120: u32 = 0x1000_0000;
+const GID_FLAGS: u32 =
+    FLIPPED_HORIZONTALLY | FLIPPED_VERTICALLY | FLIPPED_DIAGONALLY | ROTATED_HEXAGONAL_120;
+
+/// Errors returned when importing a Tiled map
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TiledError {
+    /// The file isn't well formed XML
+    #[error("Invalid XML at byte {position}: {message}")]
+    Xml {
+        /// A description of the problem
+        message: String,
+        /// The byte offset of the problem
+        position: usize,
+    },
+
+    /// The root element isn't the expected one
+    #[error("Expected a <{0}> element")]
+    UnexpectedRoot(&'static str),
+
+    /// An element is missing a required attribute
+    #[error("<{element}> is missing the {attribute} attribute")]
+    MissingAttribute {
+        /// The name of the element
+        element: String,
+        /// The name of the attribute
+        attribute: &'static str,
+    },
+
+    /// An attribute couldn't be parsed
+    #[error("<{element}> has an invalid {attribute} attribute: {value:?}")]
+    InvalidAttribute {
+        /// The name of the element
+        element: String,
+        /// The name of the attribute
+        attribute: &'static str,
+        /// The value of the attribute
+        value: String,
+    },
+
+    /// The map is an infinite map
+    #[error("Infinite maps are not supported")]
+    InfiniteMap,
+
+    /// A tile layer uses an encoding or compression that isn't supported
+    #[error("Layer {layer:?} uses the unsupported encoding {encoding:?}")]
+    UnsupportedEncoding {
+        /// The name of the layer
+        layer: String,
+        /// The encoding and compression of the layer
+        encoding: String,
+    },
+
+    /// The tiles of a tile layer don't match its size
+    #[error("Layer {layer:?} doesn't have one valid tile for each of its cells")]
+    InvalidLayerData {
+        /// The name of the layer
+        layer: String,
+    },
+
+    /// The contents of an external tileset couldn't be read
+    #[error("The tileset {0:?} couldn't be read")]
+    MissingTileset(String),
+}
+
+/// A tileset used by a [`TiledMap`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TiledTileset {
+    /// The gid of the first tile of the tileset
+    pub first_gid: u32,
+    /// The path of the `.tsx` file of an external tileset, relative to the map
+    pub source: Option<String>,
+    /// The name of the tileset. Empty for external tilesets until they are resolved
+    pub name: String,
+    /// The amount of tiles in the tileset. 0 for external tilesets until they are resolved
+    pub tile_count: u32,
+}
+
+impl TiledTileset {
+    /// Reads the name and tile count of an external tileset from the contents of its `.tsx` file
+    pub fn from_tsx(first_gid: u32, source: &str, tsx: &str) -> Result<Self, TiledError> {
+        let root = parse_document(tsx)?;
+        if root.name != "tileset" {
+            return Err(TiledError::UnexpectedRoot("tileset"));
+        }
+        let mut tileset = Self::from_element(&root, first_gid)?;
+        tileset.source = Some(source.to_string());
+        Ok(tileset)
+    }
+
+    fn from_element(element: &XmlElement, first_gid: u32) -> Result<Self, TiledError> {
+        Ok(Self {
+            first_gid,
+            source: element.attribute("source").map(str::to_string),
+            name: element.attribute("name").unwrap_or_default().to_string(),
+            tile_count: optional_attribute(element, "tilecount")?.unwrap_or(0),
+        })
+    }
+}
+
+/// A tile placed in a Tiled layer or a tile object
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TiledTile {
+    /// The global id of the tile without the flip flags
+    pub gid: u32,
+    /// The index of the tileset of the tile in [`TiledMap::tilesets`], if one contains it
+    pub tileset: Option<usize>,
+    /// The id of the tile inside of its tileset
+    pub local_id: u32,
+    /// If the tile is flipped horizontally
+    pub flip_x: bool,
+    /// If the tile is flipped vertically
+    pub flip_y: bool,
+    /// If the tile is flipped diagonally
+    pub flip_diagonal: bool,
+}
+
+/// A tile layer of a [`TiledMap`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TiledTileLayer {
+    /// The name of the layer
+    pub name: String,
+    /// The gids of the tiles of the layer including the flip flags, with row 0 at the bottom of the map. 0 is
+    /// an empty cell
+    pub gids: Vec<Vec<u32>>,
+}
+
+/// An object in an object layer of a [`TiledMap`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledObject {
+    /// The id of the object
+    pub id: u32,
+    /// The name of the object
+    pub name: String,
+    /// The class of the object. Called type before Tiled 1.9
+    pub object_type: String,
+    /// The tile of a tile object
+    pub tile: Option<TiledTile>,
+    /// The position of the object in pixels from the top left of the map
+    pub position: Vec2,
+}
+
+/// An object layer of a [`TiledMap`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledObjectLayer {
+    /// The name of the layer
+    pub name: String,
+    /// The objects of the layer
+    pub objects: Vec<TiledObject>,
+}
+
+/// A layer of a [`TiledMap`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum TiledLayer {
+    /// A layer of tiles
+    Tiles(TiledTileLayer),
+    /// A layer of objects
+    Objects(TiledObjectLayer),
+}
+
+impl TiledLayer {
+    /// Returns the name of the layer
+    pub fn name(&self) -> &str {
+        match self {
+            TiledLayer::Tiles(layer) => &layer.name,
+            TiledLayer::Objects(layer) => &layer.name,
+        }
+    }
+}
+
+/// A map read from a Tiled `.tmx` file
+#[derive(Clone, Debug, PartialEq)]
+pub struct TiledMap {
+    /// The size of the map in tiles
+    pub size: UVec2,
+    /// The size of a tile in pixels
+    pub tile_size: UVec2,
+    /// The tilesets of the map ordered by their first gid
+    pub tilesets: Vec<TiledTileset>,
+    /// The tile and object layers of the map from the bottom to the top. Layers inside of groups are flattened
+    pub layers: Vec<TiledLayer>,
+}
+
+impl TiledMap {
+    /// Reads a map from the contents of a `.tmx` file. External tilesets are only referenced, use
+    /// [`Self::resolve_tilesets`] to read them
+    pub fn from_tmx(tmx: &str) -> Result<Self, TiledError> {
+        let root = parse_document(tmx)?;
+        if root.name != "map" {
+            return Err(TiledError::UnexpectedRoot("map"));
+        }
+        if root.attribute("infinite") == Some("1") {
+            return Err(TiledError::InfiniteMap);
+        }
+        let size = UVec2::new(
+            required_attribute(&root, "width")?,
+            required_attribute(&root, "height")?,
+        );
+        let tile_size = UVec2::new(
+            required_attribute(&root, "tilewidth")?,
+            required_attribute(&root, "tileheight")?,
+        );
+
+        let mut tilesets = vec![];
+        for element in root.children.iter().filter(|child| child.name == "tileset") {
+            let first_gid = required_attribute(element, "firstgid")?;
+            tilesets.push(TiledTileset::from_element(element, first_gid)?);
+        }
+        tilesets.sort_by_key(|tileset| tileset.first_gid);
+
+        let mut map = Self {
+            size,
+            tile_size,
+            tilesets,
+            layers: vec![],
+        };
+        map.read_layers(&root)?;
+        Ok(map)
+    }
+
+    /// Reads every external tileset of the map. `read` returns the contents of the `.tsx` file at the given
+    /// source path
+    pub fn resolve_tilesets(
+        &mut self,
+        mut read: impl FnMut(&str) -> Option<String>,
+    ) -> Result<(), TiledError> {
+        for tileset in self.tilesets.iter_mut() {
+            let Some(source) = tileset.source.clone() else {
+                continue;
+            };
+            let tsx = read(&source).ok_or_else(|| TiledError::MissingTileset(source.clone()))?;
+            *tileset = TiledTileset::from_tsx(tileset.first_gid, &source, &tsx)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the tile with the given gid or `None` for the empty gid 0. The gid may include flip flags
+    pub fn tile(&self, gid: u32) -> Option<TiledTile> {
+        let id = gid & !GID_FLAGS;
+        if id == 0 {
+            return None;
+        }
+        let tileset = self
+            .tilesets
+            .iter()
+            .rposition(|tileset| tileset.first_gid <= id);
+        Some(TiledTile {
+            gid: id,
+            tileset,
+            local_id: tileset.map_or(id, |index| id - self.tilesets[index].first_gid),
+            flip_x: gid & FLIPPED_HORIZONTALLY != 0,
+            flip_y: gid & FLIPPED_VERTICALLY != 0,
+            flip_diagonal: gid & FLIPPED_DIAGONALLY != 0,
+        })
+    }
+
+    /// Returns the layer with the given name
+    pub fn layer(&self, name: &str) -> Option<&TiledLayer> {
+        self.layers.iter().find(|layer| layer.name() == name)
+    }
+
+    /// Returns the cell an object is placed in or `None` if it is outside of the map. Tile objects are
+    /// anchored at their bottom left corner and other objects at their top left corner
+    pub fn object_cell(&self, object: &TiledObject) -> Option<Cell> {
+        let tile_size = self.tile_size.as_vec2();
+        let mut position = object.position / tile_size;
+        if object.tile.is_some() {
+            position.y -= 1.0;
+        }
+        let (x, row) = (position.x.floor() as i32, position.y.floor() as i32);
+        let cell = Cell::new(x, self.size.y as i32 - 1 - row);
+        (x >= 0 && row >= 0 && x < self.size.x as i32 && row < self.size.y as i32).then_some(cell)
+    }
+
+    /// Creates a dense [`TilemapLayer`] out of a tile layer. Empty cells hold the default tile data
+    pub fn tile_layer<T>(
+        &self,
+        layer: &TiledTileLayer,
+        tile_data: impl Fn(TiledTile) -> T,
+    ) -> TilemapLayer<T>
+    where
+        T: Clone + Copy + Sized + Default + Send + Sync,
+    {
+        TilemapLayer::new_dense_from_vecs(
+            layer
+                .gids
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .map(|gid| self.tile(*gid).map(&tile_data).unwrap_or_default())
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// Creates a sparse [`TilemapLayer`] out of an object layer holding the tile data of every object inside
+    /// of the map that `tile_data` returns some for. Later objects replace earlier ones in the same cell
+    pub fn object_layer<T>(
+        &self,
+        layer: &TiledObjectLayer,
+        tile_data: impl Fn(&TiledObject) -> Option<T>,
+    ) -> TilemapLayer<T>
+    where
+        T: Clone + Copy + Sized + Default + Send + Sync,
+    {
+        let tiles: HashMap<Cell, T> = layer
+            .objects
+            .iter()
+            .filter_map(|object| Some((self.object_cell(object)?, tile_data(object)?)))
+            .collect();
+        TilemapLayer::new_sparse_from_hashmap(self.size.x as usize, self.size.y as usize, tiles)
+    }
+
+    /// Creates a [`TilemapBuilder`] out of the map.
+    ///
+    /// `map_layer` picks the `MapLayers` variant each Tiled layer is imported as from its name, layers it
+    /// returns `None` for are skipped. If several layers are imported as the same variant the topmost one is
+    /// used. The layer imported as the default variant is the main layer, if there is none the main layer is a
+    /// dense layer of default tile data.
+    pub fn tilemap_builder<TileData, MapLayers, MapChunk, MapType>(
+        &self,
+        map_type: MapType,
+        chunk_settings: MapChunk::ChunkSettings,
+        map_layer: impl Fn(&str) -> Option<MapLayers>,
+        tile_data: impl Fn(TiledTile) -> TileData,
+        object_data: impl Fn(&TiledObject) -> Option<TileData>,
+    ) -> TilemapBuilder<TileData, MapLayers, MapChunk, MapType>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        MapType: MapData + Default + Send + Sync + 'static,
+    {
+        let mut layers = vec![];
+        for layer in self.layers.iter() {
+            let Some(target) = map_layer(layer.name()) else {
+                continue;
+            };
+            let layer_data = match layer {
+                TiledLayer::Tiles(layer) => self.tile_layer(layer, &tile_data),
+                TiledLayer::Objects(layer) => self.object_layer(layer, &object_data),
+            };
+            layers
+                .retain(|(map_layer, _): &(MapLayers, _)| map_layer.to_bits() != target.to_bits());
+            layers.push((target, layer_data));
+        }
+
+        let main_bits = MapLayers::default().to_bits();
+        let main_layer = match layers
+            .iter()
+            .position(|(map_layer, _)| map_layer.to_bits() == main_bits)
+        {
+            Some(index) => layers.remove(index).1,
+            None => TilemapLayer::new_dense_default(self.size.x as usize, self.size.y as usize),
+        };
+        let mut tilemap_builder = TilemapBuilder::new(main_layer, map_type, chunk_settings);
+        for (map_layer, layer_data) in layers {
+            tilemap_builder.add_layer(layer_data, map_layer);
+        }
+        tilemap_builder
+    }
+
+    fn read_layers(&mut self, parent: &XmlElement) -> Result<(), TiledError> {
+        for element in parent.children.iter() {
+            match element.name.as_str() {
+                "layer" => {
+                    let layer = self.read_tile_layer(element)?;
+                    self.layers.push(TiledLayer::Tiles(layer));
+                }
+                "objectgroup" => {
+                    let layer = self.read_object_layer(element)?;
+                    self.layers.push(TiledLayer::Objects(layer));
+                }
+                "group" => self.read_layers(element)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn read_tile_layer(&self, element: &XmlElement) -> Result<TiledTileLayer, TiledError> {
+        let name = element.attribute("name").unwrap_or_default().to_string();
+        let invalid_data = || TiledError::InvalidLayerData {
+            layer: name.clone(),
+        };
+        let data = element.child("data").ok_or_else(invalid_data)?;
+        if data.child("chunk").is_some() {
+            return Err(TiledError::InfiniteMap);
+        }
+
+        let gids: Vec<u32> = match (data.attribute("encoding"), data.attribute("compression")) {
+            (None, None) => data
+                .children
+                .iter()
+                .filter(|child| child.name == "tile")
+                .map(|tile| optional_attribute(tile, "gid").map(Option::unwrap_or_default))
+                .collect::<Result<_, _>>()?,
+            (Some("csv"), None) => data
+                .text
+                .split(',')
+                .map(|gid| gid.trim().parse().map_err(|_| invalid_data()))
+                .collect::<Result<_, _>>()?,
+            (Some("base64"), None) => {
+                let text: String = data.text.split_whitespace().collect();
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(text)
+                    .map_err(|_| invalid_data())?;
+                if bytes.len() % 4 != 0 {
+                    return Err(invalid_data());
+                }
+                bytes
+                    .chunks_exact(4)
+                    .map(|gid| u32::from_le_bytes([gid[0], gid[1], gid[2], gid[3]]))
+                    .collect()
+            }
+            (encoding, compression) => {
+                return Err(TiledError::UnsupportedEncoding {
+                    layer: name,
+                    encoding: [encoding, compression].into_iter().flatten().collect(),
+                })
+            }
+        };
+
+        let width = self.size.x as usize;
+        if width == 0 || gids.len() != width * self.size.y as usize {
+            return Err(invalid_data());
+        }
+        let gids = gids.chunks(width).rev().map(<[u32]>::to_vec).collect();
+        Ok(TiledTileLayer { name, gids })
+    }
+
+    fn read_object_layer(&self, element: &XmlElement) -> Result<TiledObjectLayer, TiledError> {
+        let mut objects = vec![];
+        for object in element
+            .children
+            .iter()
+            .filter(|child| child.name == "object")
+        {
+            objects.push(TiledObject {
+                id: optional_attribute(object, "id")?.unwrap_or(0),
+                name: object.attribute("name").unwrap_or_default().to_string(),
+                object_type: object
+                    .attribute("class")
+                    .or(object.attribute("type"))
+                    .unwrap_or_default()
+                    .to_string(),
+                tile: optional_attribute(object, "gid")?.and_then(|gid| self.tile(gid)),
+                position: Vec2::new(
+                    optional_attribute(object, "x")?.unwrap_or(0.0),
+                    optional_attribute(object, "y")?.unwrap_or(0.0),
+                ),
+            });
+        }
+        Ok(TiledObjectLayer {
+            name: element.attribute("name").unwrap_or_default().to_string(),
+            objects,
+        })
+    }
+}
+
+fn parse_document(text: &str) -> Result<XmlElement, TiledError> {
+    parse_xml(text).map_err(|(message, position)| TiledError::Xml { message, position })
+}
+
+fn optional_attribute<T: FromStr>(
+    element: &XmlElement,
+    attribute: &'static str,
+) -> Result<Option<T>, TiledError> {
+    element
+        .attribute(attribute)
+        .map(|value| {
+            value.parse().map_err(|_| TiledError::InvalidAttribute {
+                element: element.name.clone(),
+                attribute,
+                value: value.to_string(),
+            })
+        })
+        .transpose()
+}
+
+fn required_attribute<T: FromStr>(
+    element: &XmlElement,
+    attribute: &'static str,
+) -> Result<T, TiledError> {
+    optional_attribute(element, attribute)?.ok_or_else(|| TiledError::MissingAttribute {
+        element: element.name.clone(),
+        attribute,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TiledError, TiledLayer, TiledMap};
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::SquareTilemapManager;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Ground,
+        Decoration,
+        Spawns,
+    }
+
+    const TMX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="3" height="2" tilewidth="16" tileheight="16" infinite="0">
+ <tileset firstgid="1" name="terrain" tilewidth="16" tileheight="16" tilecount="4" columns="2"/>
+ <tileset firstgid="5" source="props.tsx"/>
+ <layer id="1" name="Ground" width="3" height="2">
+  <data encoding="csv">
+1,2,3,
+4,1,2
+</data>
+ </layer>
+ <group name="Details">
+  <layer id="2" name="Decoration" width="3" height="2">
+   <data encoding="base64">
+   AAAAAAUAAAAAAACABgAAAAAAAAAAAAAA
+   </data>
+  </layer>
+ </group>
+ <objectgroup id="3" name="Spawns">
+  <object id="1" name="player" type="spawn" x="40" y="8"/>
+  <object id="2" class="chest" gid="6" x="0" y="32" width="16" height="16"/>
+  <object id="3" type="spawn" x="100" y="8"/>
+ </objectgroup>
+</map>"#;
+
+    const TSX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="props" tilewidth="16" tileheight="16" tilecount="8" columns="4">
+ <image source="props.png" width="64" height="32"/>
+</tileset>"#;
+
+    #[test]
+    fn test_read_tmx() {
+        let mut tiled_map = TiledMap::from_tmx(TMX).unwrap();
+        assert_eq!(tiled_map.size, UVec2::new(3, 2));
+        assert_eq!(tiled_map.layers.len(), 3);
+        assert_eq!(tiled_map.tilesets[1].name, "");
+        tiled_map
+            .resolve_tilesets(|source| (source == "props.tsx").then(|| TSX.to_string()))
+            .unwrap();
+        assert_eq!(tiled_map.tilesets[1].name, "props");
+        assert_eq!(tiled_map.tilesets[1].tile_count, 8);
+
+        let Some(TiledLayer::Tiles(ground)) = tiled_map.layer("Ground") else {
+            panic!("Ground is a tile layer");
+        };
+        // The last row in the file is row 0
+        assert_eq!(ground.gids, vec![vec![4, 1, 2], vec![1, 2, 3]]);
+        let Some(TiledLayer::Tiles(decoration)) = tiled_map.layer("Decoration") else {
+            panic!("Decoration is a tile layer");
+        };
+        assert_eq!(decoration.gids[1], vec![0, 5, 0x8000_0000]);
+        assert_eq!(decoration.gids[0], vec![6, 0, 0]);
+
+        let flipped = tiled_map.tile(0x8000_0000 | 6).unwrap();
+        assert_eq!(flipped.tileset, Some(1));
+        assert_eq!(flipped.local_id, 1);
+        assert!(flipped.flip_x);
+        assert_eq!(tiled_map.tile(0x8000_0000), None);
+
+        let Some(TiledLayer::Objects(spawns)) = tiled_map.layer("Spawns") else {
+            panic!("Spawns is an object layer");
+        };
+        assert_eq!(
+            tiled_map.object_cell(&spawns.objects[0]),
+            Some(Cell::new(2, 1))
+        );
+        assert_eq!(spawns.objects[1].object_type, "chest");
+        assert_eq!(
+            tiled_map.object_cell(&spawns.objects[1]),
+            Some(Cell::new(0, 0))
+        );
+        assert_eq!(tiled_map.object_cell(&spawns.objects[2]), None);
+    }
+
+    #[test]
+    fn test_read_tmx_errors() {
+        assert_eq!(
+            TiledMap::from_tmx(&TMX.replace(r#"infinite="0""#, r#"infinite="1""#)),
+            Err(TiledError::InfiniteMap)
+        );
+        assert!(matches!(
+            TiledMap::from_tmx(&TMX.replace(
+                r#"encoding="base64""#,
+                r#"encoding="base64" compression="zlib""#
+            )),
+            Err(TiledError::UnsupportedEncoding { .. })
+        ));
+        assert!(matches!(
+            TiledMap::from_tmx(&TMX.replace("4,1,2", "4,1")),
+            Err(TiledError::InvalidLayerData { .. })
+        ));
+        assert!(matches!(
+            TiledMap::from_tmx(&TMX.replace(
+                r#"width="3" height="2" tilewidth"#,
+                r#"height="2" tilewidth"#
+            )),
+            Err(TiledError::MissingAttribute {
+                attribute: "width",
+                ..
+            })
+        ));
+        assert_eq!(
+            TiledMap::from_tmx(TMX).unwrap().resolve_tilesets(|_| None),
+            Err(TiledError::MissingTileset("props.tsx".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_tiled_tilemap_builder() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u32, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let tiled_map = TiledMap::from_tmx(TMX).unwrap();
+        let map_entity = tiled_map
+            .tilemap_builder::<_, _, SquareChunkLayer<u32>, _>(
+                SquareMapData {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+                SquareChunkSettings {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+                |name| match name {
+                    "Ground" => Some(MapLayers::Ground),
+                    "Decoration" => Some(MapLayers::Decoration),
+                    "Spawns" => Some(MapLayers::Spawns),
+                    _ => None,
+                },
+                |tile| tile.gid,
+                |object| (object.object_type == "spawn").then_some(object.id),
+            )
+            .spawn_tilemap(&mut commands)
+            .unwrap();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(3, 2));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 1)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 0)).unwrap(), 2);
+        tilemap_manager.set_layer(MapLayers::Decoration);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 6);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 1)).unwrap(), 0);
+        tilemap_manager.set_layer(MapLayers::Spawns);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 1)).unwrap(), 1);
+        assert!(tilemap_manager.get_tile_data(Cell::new(0, 0)).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! A small XML reader covering the subset of XML written by map editors: elements, attributes, text,
+//! comments, and the five predefined entities. DTDs, CDATA sections, and namespaces are not supported.
+
+/// An XML element and everything inside of it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct XmlElement {
+    pub name: String,
+    pub attributes: Vec<(String, String)>,
+    pub children: Vec<XmlElement>,
+    pub text: String,
+}
+
+impl XmlElement {
+    /// Returns the value of the attribute with the given name
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the first child element with the given name
+    pub fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.name == name)
+    }
+}
+
+/// Parses the root element of the document. Returns a description of the problem and the byte offset it was
+/// found at if the document isn't well formed
+pub(crate) fn parse_xml(text: &str) -> Result<XmlElement, (String, usize)> {
+    let mut reader = XmlReader { text, position: 0 };
+    reader.skip_prolog()?;
+    let root = reader.element()?;
+    reader.skip_misc()?;
+    if reader.position < text.len() {
+        return Err(reader.error("content after the root element"));
+    }
+    Ok(root)
+}
+
+struct XmlReader<'a> {
+    text: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn error(&self, message: &str) -> (String, usize) {
+        (message.to_string(), self.position)
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<(), (String, usize)> {
+        match self.rest().find(end) {
+            Some(index) => {
+                self.position += index + end.len();
+                Ok(())
+            }
+            None => Err(self.error(&format!("missing {end:?}"))),
+        }
+    }
+
+    /// Skips whitespace, comments, and processing instructions
+    fn skip_misc(&mut self) -> Result<(), (String, usize)> {
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if self.rest().starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_prolog(&mut self) -> Result<(), (String, usize)> {
+        self.skip_misc()?;
+        if self.rest().starts_with("<!DOCTYPE") {
+            self.skip_past(">")?;
+            self.skip_misc()?;
+        }
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<String, (String, usize)> {
+        let rest = self.rest();
+        let length = rest
+            .find(|character: char| {
+                character.is_whitespace() || matches!(character, '=' | '>' | '/')
+            })
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.error("expected a name"));
+        }
+        self.position += length;
+        Ok(rest[..length].to_string())
+    }
+
+    fn element(&mut self) -> Result<XmlElement, (String, usize)> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected an element"));
+        }
+        self.position += 1;
+        let mut element = XmlElement {
+            name: self.name()?,
+            ..Default::default()
+        };
+
+        loop {
+            self.skip_whitespace();
+            if self.rest().starts_with("/>") {
+                self.position += 2;
+                return Ok(element);
+            }
+            if self.rest().starts_with('>') {
+                self.position += 1;
+                break;
+            }
+            let name = self.name()?;
+            self.skip_whitespace();
+            if !self.rest().starts_with('=') {
+                return Err(self.error("expected '=' after an attribute name"));
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let Some(quote) = self
+                .rest()
+                .chars()
+                .next()
+                .filter(|c| matches!(c, '"' | '\''))
+            else {
+                return Err(self.error("expected a quoted attribute value"));
+            };
+            self.position += 1;
+            let Some(length) = self.rest().find(quote) else {
+                return Err(self.error("unterminated attribute value"));
+            };
+            let value = unescape(&self.rest()[..length]);
+            self.position += length + 1;
+            element.attributes.push((name, value));
+        }
+
+        loop {
+            let rest = self.rest();
+            let text_length = rest.find('<').unwrap_or(rest.len());
+            element.text.push_str(&unescape(&rest[..text_length]));
+            self.position += text_length;
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(self.error(&format!("unclosed element <{}>", element.name)));
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if let Some(closing) = rest.strip_prefix("</") {
+                if !closing.starts_with(element.name.as_str()) {
+                    return Err(self.error(&format!("expected </{}>", element.name)));
+                }
+                self.position += 2 + element.name.len();
+                self.skip_whitespace();
+                if !self.rest().starts_with('>') {
+                    return Err(self.error(&format!("expected </{}>", element.name)));
+                }
+                self.position += 1;
+                return Ok(element);
+            } else {
+                element.children.push(self.element()?);
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_xml;
+
+    #[test]
+    fn test_parse_xml() {
+        let root = parse_xml(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- A comment -->
+            <map width="2" name='a &amp; b'>
+                <layer id="1"/>
+                <data>1,2</data>
+            </map>"#,
+        )
+        .unwrap();
+        assert_eq!(root.name, "map");
+        assert_eq!(root.attribute("width"), Some("2"));
+        assert_eq!(root.attribute("name"), Some("a & b"));
+        assert_eq!(root.children.len(), 2);
+        assert_eq!(root.child("layer").unwrap().attribute("id"), Some("1"));
+        assert_eq!(root.child("data").unwrap().text, "1,2");
+
+        assert!(parse_xml("<map><layer></map>").is_err());
+        assert!(parse_xml("<map/><map/>").is_err());
+    }
+}
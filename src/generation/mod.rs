@@ -0,0 +1,98 @@
+//! Procedural generation helpers that work directly on tilemap data.
+//!
+//! Generators produce the [`Cell`]s that they affect. Those cells can then be written into a
+//! [`TilemapLayer`] before the map is spawned using [`write_cells_to_layer`] or into a live map through a
+//! [`TilemapManager`] using [`write_cells_to_map`].
+
+mod random_walk;
+#[cfg(feature = "wfc")]
+pub mod wfc;
+
+pub use random_walk::{dig_corridor, DrunkardsWalk, RandomWalk};
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// A small deterministic random number generator (SplitMix64) used by the generators.
+///
+/// The same seed always produces the same sequence on every platform and crate version so generated
+/// maps can be recreated from their seed.
+#[derive(Clone, Debug)]
+pub struct SeededRng {
+    state: u64,
+}
+
+impl SeededRng {
+    /// Creates a new generator from the given seed
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Returns the next random `u64`
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a random number in `0..upper`. Returns 0 if `upper` is 0
+    pub fn range(&mut self, upper: u32) -> u32 {
+        if upper == 0 {
+            return 0;
+        }
+        (self.next_u64() % upper as u64) as u32
+    }
+
+    /// Returns a random float in `0.0..1.0`
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Returns true with the given probability
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+/// Writes the given tile data into every given [`Cell`] of the [`TilemapLayer`]. Cells outside the layer
+/// are ignored
+pub fn write_cells_to_layer<TileData>(
+    layer: &mut TilemapLayer<TileData>,
+    cells: impl IntoIterator<Item = Cell>,
+    tile_data: TileData,
+) where
+    TileData: Clone + Copy + Sized + Default + Send + Sync,
+{
+    for cell in cells {
+        layer.set_tile_data(cell, tile_data);
+    }
+}
+
+/// Writes the given tile data into every given [`Cell`] of the layer the [`TilemapManager`] is set to.
+///
+/// Cells outside of the map are skipped. Returns the first other error encountered.
+pub fn write_cells_to_map<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    cells: impl IntoIterator<Item = Cell>,
+    tile_data: TileData,
+) -> Result<(), TilemapManagerError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    for cell in cells {
+        match tilemap_manager.sets_tile_data(tile_data, cell) {
+            Ok(()) | Err(TilemapManagerError::InvalidChunkPos) => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
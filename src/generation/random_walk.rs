@@ -0,0 +1,207 @@
+use crate::generation::SeededRng;
+use bevy::math::{IRect, IVec2};
+use bevy::utils::HashSet;
+use lettuces::cell::Cell;
+
+const ORTHOGONAL_STEPS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+const ALL_STEPS: [IVec2; 8] = [
+    IVec2::X,
+    IVec2::NEG_X,
+    IVec2::Y,
+    IVec2::NEG_Y,
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// Takes one random step from the given cell, staying inside the inclusive bounds. Returns the original
+/// cell if the chosen step would leave the bounds.
+fn step(cell: Cell, bounds: IRect, diagonal: bool, rng: &mut SeededRng) -> Cell {
+    let steps: &[IVec2] = if diagonal {
+        &ALL_STEPS
+    } else {
+        &ORTHOGONAL_STEPS
+    };
+    let next = IVec2::new(cell.x, cell.y) + steps[rng.range(steps.len() as u32) as usize];
+    if bounds.contains(next) {
+        Cell::new(next.x, next.y)
+    } else {
+        cell
+    }
+}
+
+/// A simple random walk that wanders for a fixed amount of steps inside of the given bounds.
+#[derive(Clone, Copy, Debug)]
+pub struct RandomWalk {
+    /// The cell the walk starts at. Clamped into the bounds
+    pub start: Cell,
+    /// The amount of steps the walk takes
+    pub steps: u32,
+    /// The inclusive bounds that the walk is constrained to
+    pub bounds: IRect,
+    /// If the walk may step diagonally
+    pub diagonal: bool,
+}
+
+impl RandomWalk {
+    /// Runs the walk and returns every cell visited in order, including the start cell
+    pub fn generate(&self, rng: &mut SeededRng) -> Vec<Cell> {
+        let mut current = clamp_cell(self.start, self.bounds);
+        let mut path = Vec::with_capacity(self.steps as usize + 1);
+        path.push(current);
+        for _ in 0..self.steps {
+            current = step(current, self.bounds, self.diagonal, rng);
+            path.push(current);
+        }
+        path
+    }
+}
+
+/// A drunkard's walk used to carve out caves. The walker wanders randomly until the requested fraction of the
+/// bounds has been carved or it runs out of steps.
+#[derive(Clone, Copy, Debug)]
+pub struct DrunkardsWalk {
+    /// The cell the walk starts at. Clamped into the bounds
+    pub start: Cell,
+    /// The inclusive bounds that the walk is constrained to
+    pub bounds: IRect,
+    /// The fraction of the cells inside the bounds that should be carved. Between 0.0 and 1.0
+    pub fill_fraction: f32,
+    /// The maximum amount of steps taken before the walk gives up
+    pub max_steps: u32,
+    /// If the walk may step diagonally
+    pub diagonal: bool,
+}
+
+impl DrunkardsWalk {
+    /// Runs the walk and returns the unique cells carved in the order they were first visited
+    pub fn generate(&self, rng: &mut SeededRng) -> Vec<Cell> {
+        let size = self.bounds.size() + IVec2::ONE;
+        let area = (size.x.max(0) as u64 * size.y.max(0) as u64) as f32;
+        let target = ((area * self.fill_fraction.clamp(0.0, 1.0)).ceil() as usize).max(1);
+
+        let mut current = clamp_cell(self.start, self.bounds);
+        let mut visited = HashSet::new();
+        let mut carved = vec![current];
+        visited.insert(current);
+
+        for _ in 0..self.max_steps {
+            if carved.len() >= target {
+                break;
+            }
+            current = step(current, self.bounds, self.diagonal, rng);
+            if visited.insert(current) {
+                carved.push(current);
+            }
+        }
+        carved
+    }
+}
+
+/// Digs a corridor from `from` to `to` and returns the cells in order.
+///
+/// Each step moves towards the target, except that with a probability of `wiggle` a random orthogonal step
+/// is taken instead which makes the corridor meander. The corridor always stays inside the inclusive bounds
+/// and always reaches the target as long as both points are inside the bounds.
+pub fn dig_corridor(
+    from: Cell,
+    to: Cell,
+    bounds: IRect,
+    wiggle: f32,
+    rng: &mut SeededRng,
+) -> Vec<Cell> {
+    let target = clamp_cell(to, bounds);
+    let mut current = clamp_cell(from, bounds);
+    let mut path = vec![current];
+
+    while current != target {
+        current = if rng.chance(wiggle) {
+            step(current, bounds, false, rng)
+        } else {
+            let delta = IVec2::new(target.x - current.x, target.y - current.y);
+            // Move along the axis with the larger distance, breaking ties randomly
+            let move_x = match delta.x.abs().cmp(&delta.y.abs()) {
+                std::cmp::Ordering::Greater => true,
+                std::cmp::Ordering::Less => false,
+                std::cmp::Ordering::Equal => rng.chance(0.5),
+            };
+            if move_x {
+                Cell::new(current.x + delta.x.signum(), current.y)
+            } else {
+                Cell::new(current.x, current.y + delta.y.signum())
+            }
+        };
+        if path.last() != Some(&current) {
+            path.push(current);
+        }
+    }
+    path
+}
+
+fn clamp_cell(cell: Cell, bounds: IRect) -> Cell {
+    Cell::new(
+        cell.x.clamp(bounds.min.x, bounds.max.x),
+        cell.y.clamp(bounds.min.y, bounds.max.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dig_corridor, DrunkardsWalk, RandomWalk};
+    use crate::generation::{write_cells_to_layer, SeededRng};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::math::IRect;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_random_walk_is_seeded_and_bounded() {
+        let bounds = IRect::new(2, 2, 6, 6);
+        let walk = RandomWalk {
+            start: Cell::new(4, 4),
+            steps: 200,
+            bounds,
+            diagonal: true,
+        };
+        let first = walk.generate(&mut SeededRng::new(7));
+        let second = walk.generate(&mut SeededRng::new(7));
+        assert_eq!(first, second);
+        assert_eq!(first.len(), 201);
+        assert!(first
+            .iter()
+            .all(|cell| bounds.contains(bevy::math::IVec2::new(cell.x, cell.y))));
+    }
+
+    #[test]
+    fn test_drunkards_walk_fills_fraction() {
+        let walk = DrunkardsWalk {
+            start: Cell::new(0, 0),
+            bounds: IRect::new(0, 0, 9, 9),
+            fill_fraction: 0.4,
+            max_steps: 100_000,
+            diagonal: false,
+        };
+        let carved = walk.generate(&mut SeededRng::new(42));
+        assert_eq!(carved.len(), 40);
+
+        let mut layer = TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 10]; 10]);
+        write_cells_to_layer(&mut layer, carved.iter().copied(), 0);
+        for cell in carved {
+            assert_eq!(layer.get_tile_data(cell), Some(0));
+        }
+    }
+
+    #[test]
+    fn test_corridor_reaches_target() {
+        let corridor = dig_corridor(
+            Cell::new(0, 0),
+            Cell::new(8, 5),
+            IRect::new(0, 0, 9, 9),
+            0.3,
+            &mut SeededRng::new(3),
+        );
+        assert_eq!(corridor.first(), Some(&Cell::new(0, 0)));
+        assert_eq!(corridor.last(), Some(&Cell::new(8, 5)));
+    }
+}
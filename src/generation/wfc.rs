@@ -0,0 +1,470 @@
+//! Wave function collapse over a region of a square map. Requires the `wfc` feature.
+//!
+//! [`AdjacencyRules`] describe which tiles may be placed next to each other. They can be declared explicitly
+//! with [`AdjacencyRules::allow`] or derived from an example patch with [`AdjacencyRules::from_example`].
+//! [`solve_region`] then fills an inclusive region while respecting any tiles that already exist around it,
+//! which allows large maps to be generated incrementally one chunk sized region at a time.
+
+use crate::generation::SeededRng;
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::math::{IRect, IVec2};
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// One of the four orthogonal directions that adjacency rules are declared in
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum WfcDirection {
+    /// Positive y
+    Up,
+    /// Negative y
+    Down,
+    /// Negative x
+    Left,
+    /// Positive x
+    Right,
+}
+
+impl WfcDirection {
+    /// All four directions
+    pub const ALL: [WfcDirection; 4] = [
+        WfcDirection::Up,
+        WfcDirection::Down,
+        WfcDirection::Left,
+        WfcDirection::Right,
+    ];
+
+    /// Returns the offset of a cell in this direction
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            WfcDirection::Up => IVec2::Y,
+            WfcDirection::Down => IVec2::NEG_Y,
+            WfcDirection::Left => IVec2::NEG_X,
+            WfcDirection::Right => IVec2::X,
+        }
+    }
+
+    /// Returns the opposite direction
+    pub fn opposite(&self) -> WfcDirection {
+        match self {
+            WfcDirection::Up => WfcDirection::Down,
+            WfcDirection::Down => WfcDirection::Up,
+            WfcDirection::Left => WfcDirection::Right,
+            WfcDirection::Right => WfcDirection::Left,
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            WfcDirection::Up => 0,
+            WfcDirection::Down => 1,
+            WfcDirection::Left => 2,
+            WfcDirection::Right => 3,
+        }
+    }
+}
+
+/// Errors returned while solving wave function collapse
+#[derive(thiserror::Error, Debug)]
+pub enum WfcError {
+    /// Every attempt ended with a cell that had no valid tiles left
+    #[error("Wave function collapse reached a contradiction in every one of {attempts} attempts")]
+    Contradiction {
+        /// The amount of attempts made
+        attempts: u32,
+    },
+
+    /// Reading or writing the map failed
+    #[error(transparent)]
+    Manager(#[from] TilemapManagerError),
+}
+
+/// The tiles that can be placed by wave function collapse and which of them are allowed next to each other
+#[derive(Clone, Debug)]
+pub struct AdjacencyRules<TileData> {
+    tiles: Vec<TileData>,
+    weights: Vec<f32>,
+    lookup: HashMap<TileData, usize>,
+    /// `allowed[tile][direction][neighbour]`
+    allowed: Vec<[Vec<bool>; 4]>,
+}
+
+impl<TileData> Default for AdjacencyRules<TileData> {
+    fn default() -> Self {
+        Self {
+            tiles: vec![],
+            weights: vec![],
+            lookup: HashMap::default(),
+            allowed: vec![],
+        }
+    }
+}
+
+impl<TileData> AdjacencyRules<TileData>
+where
+    TileData: Eq + Hash + Clone + Copy,
+{
+    /// Creates a new empty set of rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Derives rules from an example patch. Every pair of orthogonally adjacent tiles in the example is
+    /// allowed and tiles are weighted by how often they appear.
+    pub fn from_example(example: &TilemapLayer<TileData>) -> Self
+    where
+        TileData: Default + Send + Sync,
+    {
+        let mut rules = Self::new();
+        let dimensions = example.dimensions();
+        for y in 0..dimensions.y as i32 {
+            for x in 0..dimensions.x as i32 {
+                let Some(tile_data) = example.get_tile_data(Cell::new(x, y)) else {
+                    continue;
+                };
+                let index = rules.add_tile(tile_data, 0.0);
+                rules.weights[index] += 1.0;
+                for direction in WfcDirection::ALL {
+                    let offset = direction.offset();
+                    if let Some(neighbour) =
+                        example.get_tile_data(Cell::new(x + offset.x, y + offset.y))
+                    {
+                        rules.allow(tile_data, neighbour, direction);
+                    }
+                }
+            }
+        }
+        rules
+    }
+
+    /// Adds a tile with the given weight and returns its index. If the tile already exists its weight is
+    /// replaced unless the new weight is 0.0
+    pub fn add_tile(&mut self, tile_data: TileData, weight: f32) -> usize {
+        if let Some(index) = self.lookup.get(&tile_data) {
+            if weight != 0.0 {
+                self.weights[*index] = weight;
+            }
+            return *index;
+        }
+        let index = self.tiles.len();
+        self.tiles.push(tile_data);
+        self.weights.push(weight);
+        self.lookup.insert(tile_data, index);
+        for allowed in self.allowed.iter_mut() {
+            for direction in allowed.iter_mut() {
+                direction.push(false);
+            }
+        }
+        self.allowed
+            .push(std::array::from_fn(|_| vec![false; self.tiles.len()]));
+        index
+    }
+
+    /// Allows `neighbour` to be placed in `direction` of `tile_data`. The opposite rule is added as well.
+    /// Tiles that weren't added yet are added with a weight of 1.0
+    pub fn allow(&mut self, tile_data: TileData, neighbour: TileData, direction: WfcDirection) {
+        let tile_index = self.index_or_insert(tile_data);
+        let neighbour_index = self.index_or_insert(neighbour);
+        self.allowed[tile_index][direction.index()][neighbour_index] = true;
+        self.allowed[neighbour_index][direction.opposite().index()][tile_index] = true;
+    }
+
+    /// Returns true if `neighbour` may be placed in `direction` of `tile_data`
+    pub fn is_allowed(
+        &self,
+        tile_data: TileData,
+        neighbour: TileData,
+        direction: WfcDirection,
+    ) -> bool {
+        match (self.lookup.get(&tile_data), self.lookup.get(&neighbour)) {
+            (Some(tile), Some(neighbour)) => self.allowed[*tile][direction.index()][*neighbour],
+            _ => false,
+        }
+    }
+
+    /// Returns the tiles known to these rules
+    pub fn tiles(&self) -> &[TileData] {
+        &self.tiles
+    }
+
+    fn index_or_insert(&mut self, tile_data: TileData) -> usize {
+        match self.lookup.get(&tile_data) {
+            Some(index) => *index,
+            None => self.add_tile(tile_data, 1.0),
+        }
+    }
+}
+
+/// Solves the given inclusive region using the rules.
+///
+/// `existing` is called for the cells directly surrounding the region and any tile it returns constrains the
+/// neighbouring cells inside the region. Tiles unknown to the rules are ignored. Each attempt that ends in a
+/// contradiction is retried with the same generator up to `max_attempts` times.
+pub fn solve_region<TileData>(
+    rules: &AdjacencyRules<TileData>,
+    region: IRect,
+    existing: impl Fn(Cell) -> Option<TileData>,
+    max_attempts: u32,
+    rng: &mut SeededRng,
+) -> Result<HashMap<Cell, TileData>, WfcError>
+where
+    TileData: Eq + Hash + Clone + Copy,
+{
+    let size = (region.size() + IVec2::ONE).max(IVec2::ZERO);
+    let cell_count = (size.x * size.y) as usize;
+    let tile_count = rules.tiles.len();
+
+    // Possibilities for every cell once the surrounding tiles have been applied
+    let mut initial = vec![vec![true; tile_count]; cell_count];
+    for (index, possible) in initial.iter_mut().enumerate() {
+        let cell = index_to_cell(index, region, size);
+        for direction in WfcDirection::ALL {
+            let offset = direction.offset();
+            let neighbour = IVec2::new(cell.x, cell.y) + offset;
+            if region.contains(neighbour) {
+                continue;
+            }
+            let Some(neighbour_index) = existing(Cell::new(neighbour.x, neighbour.y))
+                .and_then(|tile_data| rules.lookup.get(&tile_data))
+            else {
+                continue;
+            };
+            for (tile, possible) in possible.iter_mut().enumerate() {
+                *possible &= rules.allowed[tile][direction.index()][*neighbour_index];
+            }
+        }
+    }
+
+    for _ in 0..max_attempts.max(1) {
+        let mut wave = initial.clone();
+        if let Some(result) = run_attempt(rules, &mut wave, size, rng) {
+            return Ok(result
+                .into_iter()
+                .enumerate()
+                .map(|(index, tile)| (index_to_cell(index, region, size), rules.tiles[tile]))
+                .collect());
+        }
+    }
+
+    Err(WfcError::Contradiction {
+        attempts: max_attempts.max(1),
+    })
+}
+
+/// Solves the region and writes the result into the [`TilemapLayer`]. Tiles already in the layer around the
+/// region constrain the solve
+pub fn solve_region_in_layer<TileData>(
+    layer: &mut TilemapLayer<TileData>,
+    rules: &AdjacencyRules<TileData>,
+    region: IRect,
+    max_attempts: u32,
+    rng: &mut SeededRng,
+) -> Result<(), WfcError>
+where
+    TileData: Eq + Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    let result = solve_region(
+        rules,
+        region,
+        |cell| layer.get_tile_data(cell),
+        max_attempts,
+        rng,
+    )?;
+    for (cell, tile_data) in result {
+        layer.set_tile_data(cell, tile_data);
+    }
+    Ok(())
+}
+
+/// Solves the region and writes the result into the layer the [`TilemapManager`] is set to. Tiles already
+/// on the map around the region constrain the solve, so calling this once per chunk sized region lets a
+/// large map be generated incrementally
+pub fn solve_region_in_map<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    rules: &AdjacencyRules<TileData>,
+    region: IRect,
+    max_attempts: u32,
+    rng: &mut SeededRng,
+) -> Result<(), WfcError>
+where
+    TileData: Eq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let dimensions = tilemap_manager.dimensions()?.as_ivec2();
+    let result = solve_region(
+        rules,
+        region,
+        |cell| {
+            if cell.x < 0 || cell.y < 0 || cell.x >= dimensions.x || cell.y >= dimensions.y {
+                return None;
+            }
+            tilemap_manager.get_tile_data(cell).ok()
+        },
+        max_attempts,
+        rng,
+    )?;
+    for (cell, tile_data) in result {
+        tilemap_manager.sets_tile_data(tile_data, cell)?;
+    }
+    Ok(())
+}
+
+fn index_to_cell(index: usize, region: IRect, size: IVec2) -> Cell {
+    Cell::new(
+        region.min.x + index as i32 % size.x,
+        region.min.y + index as i32 / size.x,
+    )
+}
+
+/// Runs a single attempt. Returns the chosen tile index of every cell or `None` on a contradiction
+fn run_attempt<TileData>(
+    rules: &AdjacencyRules<TileData>,
+    wave: &mut [Vec<bool>],
+    size: IVec2,
+    rng: &mut SeededRng,
+) -> Option<Vec<usize>> {
+    let mut stack: Vec<usize> = (0..wave.len()).collect();
+    if !propagate(rules, wave, size, &mut stack) {
+        return None;
+    }
+
+    loop {
+        // Find the undecided cell with the fewest possibilities
+        let mut lowest: Option<(usize, usize)> = None;
+        for (index, possible) in wave.iter().enumerate() {
+            let count = possible.iter().filter(|possible| **possible).count();
+            if count > 1 && !matches!(lowest, Some((_, lowest)) if lowest <= count) {
+                lowest = Some((index, count));
+            }
+        }
+        let Some((index, _)) = lowest else {
+            break;
+        };
+
+        let total: f32 = wave[index]
+            .iter()
+            .enumerate()
+            .filter(|(_, possible)| **possible)
+            .map(|(tile, _)| rules.weights[tile].max(f32::EPSILON))
+            .sum();
+        let mut pick = rng.next_f32() * total;
+        let mut chosen = None;
+        for (tile, possible) in wave[index].iter().enumerate() {
+            if !possible {
+                continue;
+            }
+            chosen = Some(tile);
+            pick -= rules.weights[tile].max(f32::EPSILON);
+            if pick <= 0.0 {
+                break;
+            }
+        }
+        let chosen = chosen?;
+        for (tile, possible) in wave[index].iter_mut().enumerate() {
+            *possible = tile == chosen;
+        }
+
+        stack.push(index);
+        if !propagate(rules, wave, size, &mut stack) {
+            return None;
+        }
+    }
+
+    wave.iter()
+        .map(|possible| possible.iter().position(|possible| *possible))
+        .collect()
+}
+
+/// Removes possibilities that are no longer supported by any neighbour. Returns false on a contradiction
+fn propagate<TileData>(
+    rules: &AdjacencyRules<TileData>,
+    wave: &mut [Vec<bool>],
+    size: IVec2,
+    stack: &mut Vec<usize>,
+) -> bool {
+    while let Some(index) = stack.pop() {
+        if !wave[index].iter().any(|possible| *possible) {
+            return false;
+        }
+        let position = IVec2::new(index as i32 % size.x, index as i32 / size.x);
+        for direction in WfcDirection::ALL {
+            let neighbour = position + direction.offset();
+            if neighbour.x < 0 || neighbour.y < 0 || neighbour.x >= size.x || neighbour.y >= size.y
+            {
+                continue;
+            }
+            let neighbour_index = (neighbour.y * size.x + neighbour.x) as usize;
+            let mut changed = false;
+            for neighbour_tile in 0..wave[neighbour_index].len() {
+                if !wave[neighbour_index][neighbour_tile] {
+                    continue;
+                }
+                let supported = wave[index].iter().enumerate().any(|(tile, possible)| {
+                    *possible && rules.allowed[tile][direction.index()][neighbour_tile]
+                });
+                if !supported {
+                    wave[neighbour_index][neighbour_tile] = false;
+                    changed = true;
+                }
+            }
+            if changed {
+                stack.push(neighbour_index);
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solve_region, solve_region_in_layer, AdjacencyRules, WfcDirection};
+    use crate::generation::SeededRng;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::math::IRect;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_solve_respects_rules() {
+        // A checkerboard is the only valid layout
+        let mut rules = AdjacencyRules::new();
+        for direction in WfcDirection::ALL {
+            rules.allow(0u8, 1u8, direction);
+        }
+        let region = IRect::new(0, 0, 5, 5);
+        let result = solve_region(&rules, region, |_| None, 1, &mut SeededRng::new(1))
+            .expect("checkerboard is solvable");
+        assert_eq!(result.len(), 36);
+        let corner = result[&Cell::new(0, 0)];
+        for (cell, tile) in result.iter() {
+            let expected = if (cell.x + cell.y) % 2 == 0 {
+                corner
+            } else {
+                1 - corner
+            };
+            assert_eq!(*tile, expected);
+        }
+    }
+
+    #[test]
+    fn test_incremental_solve_matches_existing_tiles() {
+        let example = TilemapLayer::new_dense_from_vecs(vec![vec![0u8, 1], vec![1, 0]]);
+        let rules = AdjacencyRules::from_example(&example);
+        assert!(rules.is_allowed(0, 1, WfcDirection::Right));
+        assert!(!rules.is_allowed(0, 0, WfcDirection::Right));
+
+        let mut layer = TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 8]; 4]);
+        for y in (0..4).step_by(2) {
+            layer.set_tile_data(Cell::new(3, y), 1);
+        }
+        let mut rng = SeededRng::new(9);
+        solve_region_in_layer(&mut layer, &rules, IRect::new(4, 0, 7, 3), 4, &mut rng)
+            .expect("region is solvable");
+        assert_eq!(layer.get_tile_data(Cell::new(4, 0)), Some(0));
+        assert_eq!(layer.get_tile_data(Cell::new(5, 0)), Some(1));
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::math::Vec2;
+use bevy::prelude::Component;
+use lettuces::cell::Cell;
+use lettuces::HexOrientation;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::hex::hex_round;
+
+/// The distance between the centers of two rows of pointy hexes, or two columns of flat hexes, that are one
+/// unit across their flat sides
+pub(crate) const HEX_SPACING: f32 = 0.866_025_4;
+
+/// The world space layout of a hex tilemap, the hex counterpart of
+/// [`TilemapGeometry`](crate::map::TilemapGeometry).
+///
+/// Cells are in the axial coordinates used to access hex maps. Hexes are `hex_size` across their flat sides,
+/// so neighbouring pointy hexes of the same row are `hex_size` apart.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct HexGeometry {
+    /// The world position of the center of cell (0, 0)
+    pub origin: Vec2,
+    /// The world distance across the flat sides of a single hex
+    pub hex_size: f32,
+    /// The orientation of the hexes
+    pub orientation: HexOrientation,
+}
+
+impl Default for HexGeometry {
+    fn default() -> Self {
+        Self {
+            origin: Vec2::ZERO,
+            hex_size: 1.0,
+            orientation: HexOrientation::Pointy,
+        }
+    }
+}
+
+impl HexGeometry {
+    /// Creates a new HexGeometry with the given origin, hex size, and orientation
+    pub fn new(origin: Vec2, hex_size: f32, orientation: HexOrientation) -> Self {
+        Self {
+            origin,
+            hex_size,
+            orientation,
+        }
+    }
+
+    /// Returns the [`Cell`] containing the given world position
+    pub fn world_to_cell(&self, world_pos: Vec2) -> Cell {
+        let cell = self.world_to_cell_f32(world_pos);
+        hex_round(cell.x, cell.y)
+    }
+
+    /// Returns the given world position in fractional axial coordinates, where the center of [`Cell`] (q, r)
+    /// is at (q, r). Keeps the position inside of the cell so entities can be placed and moved between tiles
+    /// without losing precision
+    pub fn world_to_cell_f32(&self, world_pos: Vec2) -> Vec2 {
+        let pos = (world_pos - self.origin) / self.hex_size;
+        match self.orientation {
+            HexOrientation::Pointy => {
+                let r = pos.y / HEX_SPACING;
+                Vec2::new(pos.x - r / 2.0, r)
+            }
+            HexOrientation::Flat => {
+                let q = pos.x / HEX_SPACING;
+                Vec2::new(q, pos.y - q / 2.0)
+            }
+        }
+    }
+
+    /// Returns the world position of the given position in fractional axial coordinates. The inverse of
+    /// [`Self::world_to_cell_f32`]
+    pub fn cell_f32_to_world(&self, cell_pos: Vec2) -> Vec2 {
+        let pos = match self.orientation {
+            HexOrientation::Pointy => {
+                Vec2::new(cell_pos.x + cell_pos.y / 2.0, cell_pos.y * HEX_SPACING)
+            }
+            HexOrientation::Flat => {
+                Vec2::new(cell_pos.x * HEX_SPACING, cell_pos.y + cell_pos.x / 2.0)
+            }
+        };
+        self.origin + pos * self.hex_size
+    }
+
+    /// Returns the world position of the center of the given [`Cell`]
+    pub fn cell_center(&self, cell: Cell) -> Vec2 {
+        self.cell_f32_to_world(Vec2::new(cell.x as f32, cell.y as f32))
+    }
+
+    /// Returns the world position of the given corner of the [`Cell`]. Corners are numbered counter clockwise
+    /// starting at the first corner at or above the +x axis, the upper right corner of pointy hexes and the
+    /// right corner of flat hexes, and wrap around, so corner 6 is corner 0 again
+    pub fn cell_corner(&self, cell: Cell, corner: usize) -> Vec2 {
+        let start = match self.orientation {
+            HexOrientation::Pointy => 30.0_f32,
+            HexOrientation::Flat => 0.0,
+        };
+        let angle = (start + 60.0 * (corner % 6) as f32).to_radians();
+        // The distance from the center to a corner is 2 / sqrt(3) times the distance to a flat side
+        let radius = self.hex_size / (2.0 * HEX_SPACING);
+        self.cell_center(cell) + Vec2::new(angle.cos(), angle.sin()) * radius
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HexGeometry;
+    use crate::hex::hex_neighbors;
+    use bevy::math::Vec2;
+    use lettuces::cell::Cell;
+    use lettuces::HexOrientation;
+
+    #[test]
+    fn test_hex_geometry_conversions() {
+        for orientation in [HexOrientation::Pointy, HexOrientation::Flat] {
+            let geometry = HexGeometry::new(Vec2::new(4.0, -2.0), 16.0, orientation);
+            let cell = Cell::new(2, -3);
+            let center = geometry.cell_center(cell);
+            assert_eq!(geometry.world_to_cell(center), cell, "{orientation:?}");
+
+            // Neighbours are one hex across the flat sides apart
+            for neighbor in hex_neighbors(cell) {
+                let distance = center.distance(geometry.cell_center(neighbor));
+                assert!((distance - 16.0).abs() < 1e-3, "{orientation:?}");
+            }
+
+            let cell_pos = Vec2::new(2.25, -2.5);
+            let world_pos = geometry.cell_f32_to_world(cell_pos);
+            assert!(geometry
+                .world_to_cell_f32(world_pos)
+                .abs_diff_eq(cell_pos, 1e-5));
+        }
+    }
+
+    #[test]
+    fn test_hex_geometry_corners() {
+        let pointy = HexGeometry::new(Vec2::ZERO, 2.0, HexOrientation::Pointy);
+        // The top corner of a pointy hex is a corner radius above its center
+        assert!(pointy
+            .cell_corner(Cell::new(0, 0), 1)
+            .abs_diff_eq(Vec2::new(0.0, 2.0 / 3.0_f32.sqrt()), 1e-5));
+        assert_eq!(
+            pointy.cell_corner(Cell::new(1, 0), 8),
+            pointy.cell_corner(Cell::new(1, 0), 2)
+        );
+
+        let flat = HexGeometry::new(Vec2::ZERO, 2.0, HexOrientation::Flat);
+        assert!(flat
+            .cell_corner(Cell::new(0, 0), 0)
+            .abs_diff_eq(Vec2::new(2.0 / 3.0_f32.sqrt(), 0.0), 1e-5));
+
+        // Neighbouring hexes share the corners of the edge between them
+        for geometry in [pointy, flat] {
+            let shared = (0..6)
+                .filter(|a| {
+                    (0..6).any(|b| {
+                        geometry
+                            .cell_corner(Cell::new(0, 0), *a)
+                            .abs_diff_eq(geometry.cell_corner(Cell::new(1, 0), b), 1e-4)
+                    })
+                })
+                .count();
+            assert_eq!(shared, 2);
+        }
+    }
+}
@@ -0,0 +1,485 @@
+use bevy::{
+    math::{IVec2, UVec2},
+    prelude::Component,
+    utils::hashbrown::HashMap,
+};
+use lettuces::{cell::Cell, HexOrientation};
+
+#[cfg(feature = "reflect")]
+use bevy::prelude::Reflect;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::hex::{
+    hex_line, hex_neighbors, hex_offset_from_orientation, hex_range, hex_ring, hex_supercover_line,
+};
+use crate::map::{
+    chunk::{Chunk, ChunkLayer, ChunkLayerType, ChunkPos},
+    Adjacency, MapData, MapLayer,
+};
+use crate::tilemap_builder::tilemap_layer_builder::DenseTileSource;
+
+/// The shape of the chunks that a hexagonal map is split into
+#[derive(Clone, Copy, Hash, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Hash))]
+pub enum HexChunkShape {
+    /// Chunks are rectangles of offset coordinates. Used by [`HexMapData`](crate::hex::map_data::HexMapData)
+    #[default]
+    Rectangle,
+    /// Chunks are hexagons containing every cell within `radius` of the chunks center. Used by
+    /// [`HexagonalChunksMapData`]
+    Hexagon {
+        /// The radius of each chunk. A radius of 0 makes every cell its own chunk
+        radius: u32,
+    },
+}
+
+/// Returns the position of the hexagon shaped chunk of the given radius that contains the given axial
+/// [`Cell`].
+///
+/// Chunk positions are axial coordinates on the lattice of chunk centers, see [`hexagon_chunk_center`].
+pub fn hexagon_chunk_of(cell: Cell, radius: u32) -> IVec2 {
+    let [x, y, z] = [cell.x, cell.y, -cell.x - cell.y];
+    let area = (3 * radius * (radius + 1) + 1) as i32;
+    let shift = (3 * radius + 2) as i32;
+    let [a, b, c] = [
+        (y + shift * x).div_euclid(area),
+        (z + shift * y).div_euclid(area),
+        (x + shift * z).div_euclid(area),
+    ];
+    IVec2::new((1 + a - b).div_euclid(3), (1 + b - c).div_euclid(3))
+}
+
+/// Returns the axial [`Cell`] at the center of the hexagon shaped chunk at the given chunk lattice position
+pub fn hexagon_chunk_center(chunk: IVec2, radius: u32) -> Cell {
+    let radius = radius as i32;
+    Cell::new(
+        chunk.x * (2 * radius + 1) + chunk.y * radius,
+        -chunk.x * radius + chunk.y * (radius + 1),
+    )
+}
+
+/// [`MapData`] implementation for a hexagonal map split into hexagon shaped chunks.
+///
+/// Rectangular chunks create jagged seams on hexagonal maps. This map type instead groups cells into
+/// hexagons of every cell within `chunk_radius` of a chunk center which keeps chunk based logic local.
+/// Must be used with [`HexagonChunkSettings`](crate::hex::map_chunk_layer::HexagonChunkSettings) set to
+/// [`HexChunkShape::Hexagon`] with the same radius.
+///
+/// Like every hex map, dense data given to the builder is in offset coordinates while cells used to access
+/// the map are axial.
+#[derive(Clone, Default, Hash, Component)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Hash))]
+pub struct HexagonalChunksMapData {
+    /// The radius of each chunk
+    pub chunk_radius: u32,
+    /// The hex orientation of the map
+    pub orientation: HexOrientation,
+    /// The size of the map in offset coordinates
+    pub map_size: UVec2,
+    /// The chunk lattice position of [`ChunkPos`] (0, 0)
+    chunk_origin: IVec2,
+    /// The amount of chunks on each axis of the chunk lattice
+    chunk_counts: UVec2,
+}
+
+impl HexagonalChunksMapData {
+    /// Creates a new [`HexagonalChunksMapData`] for a map of the given size
+    pub fn new(map_size: UVec2, chunk_radius: u32, orientation: HexOrientation) -> Self {
+        let offset_mode = hex_offset_from_orientation(orientation);
+        let mut min = IVec2::MAX;
+        let mut max = IVec2::MIN;
+        // The outermost chunks always contain cells within one chunk width of the map edge
+        let band = (2 * chunk_radius + 2) as i32;
+        let size = map_size.as_ivec2();
+        for y in 0..size.y {
+            let in_band_row = y < band || y >= size.y - band;
+            let columns = (0..size.x)
+                .filter(|x| in_band_row || *x < band || *x >= size.x - band)
+                .collect::<Vec<i32>>();
+            for x in columns {
+                let chunk = hexagon_chunk_of(
+                    Cell::from_offset_coordinates([x, y], offset_mode),
+                    chunk_radius,
+                );
+                min = min.min(chunk);
+                max = max.max(chunk);
+            }
+        }
+        if map_size.x == 0 || map_size.y == 0 {
+            min = IVec2::ZERO;
+            max = IVec2::NEG_ONE;
+        }
+        Self {
+            chunk_radius,
+            orientation,
+            map_size,
+            chunk_origin: min,
+            chunk_counts: (max - min + IVec2::ONE).as_uvec2(),
+        }
+    }
+
+    /// Returns the amount of chunks on each axis of the chunk lattice
+    pub fn chunk_counts(&self) -> UVec2 {
+        self.chunk_counts
+    }
+
+    /// Returns the axial [`Cell`] at the center of the chunk at the given [`ChunkPos`]
+    pub fn chunk_center(&self, chunk_pos: ChunkPos) -> Cell {
+        hexagon_chunk_center(
+            IVec2::new(chunk_pos.x(), chunk_pos.y()) + self.chunk_origin,
+            self.chunk_radius,
+        )
+    }
+
+    /// Returns the chunk data for the given chunk or `None` if none of the chunks cells are in the map
+    fn chunk_data<TileData, Source>(
+        &self,
+        data: &Source,
+        chunk_pos: ChunkPos,
+    ) -> Option<Vec<Vec<TileData>>>
+    where
+        TileData: Clone + Copy + Sized + Default + Send + Sync + 'static,
+        Source: DenseTileSource<TileData> + ?Sized,
+    {
+        let dimensions = data.dimensions().as_ivec2();
+        let offset_mode = hex_offset_from_orientation(self.orientation);
+        let radius = self.chunk_radius as i32;
+        let center = self.chunk_center(chunk_pos);
+        let mut any_in_map = false;
+        let mut vec: Vec<Vec<TileData>> = Vec::with_capacity((2 * radius + 1) as usize);
+        for dy in -radius..=radius {
+            let mut row_vec: Vec<TileData> = Vec::with_capacity((2 * radius + 1) as usize);
+            for dx in -radius..=radius {
+                if dx.abs().max(dy.abs()).max((dx + dy).abs()) > radius {
+                    row_vec.push(TileData::default());
+                    continue;
+                }
+                let [x, y] =
+                    Cell::new(center.x + dx, center.y + dy).to_offset_coordinates(offset_mode);
+                if x >= 0 && y >= 0 && x < dimensions.x && y < dimensions.y {
+                    any_in_map = true;
+                    row_vec.push(data.tile_data(x as usize, y as usize));
+                } else {
+                    row_vec.push(TileData::default());
+                }
+            }
+            vec.push(row_vec);
+        }
+        any_in_map.then_some(vec)
+    }
+}
+
+impl MapData for HexagonalChunksMapData {
+    fn into_chunk_pos(&self, cell: Cell) -> ChunkPos {
+        let chunk = hexagon_chunk_of(cell, self.chunk_radius) - self.chunk_origin;
+        ChunkPos::new(chunk.x, chunk.y)
+    }
+
+    fn max_chunk_size(&self) -> UVec2 {
+        UVec2::splat(2 * self.chunk_radius + 1)
+    }
+
+    fn map_size(&self) -> Option<UVec2> {
+        Some(self.map_size)
+    }
+
+    fn neighbors(&self, cell: Cell, _adjacency: Adjacency) -> Vec<Cell> {
+        hex_neighbors(cell)
+    }
+
+    fn line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        hex_line(a, b)
+    }
+
+    fn sight_line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        hex_supercover_line(a, b)
+    }
+
+    fn cells_in_radius(&self, center: Cell, radius: u32) -> Vec<Cell> {
+        hex_range(center, radius)
+    }
+
+    fn cells_in_ring(&self, center: Cell, radius: u32) -> Vec<Cell> {
+        hex_ring(center, radius)
+    }
+
+    fn break_data_vecs_down_into_chunk_data<TileData, Source>(
+        &self,
+        data: &Source,
+        chunk_pos: ChunkPos,
+        max_chunk_size: UVec2,
+    ) -> Vec<Vec<TileData>>
+    where
+        TileData: Clone + Copy + Sized + Default + Send + Sync + 'static,
+        Source: DenseTileSource<TileData> + ?Sized,
+    {
+        self.chunk_data(data, chunk_pos).unwrap_or_else(|| {
+            vec![vec![TileData::default(); max_chunk_size.x as usize]; max_chunk_size.y as usize]
+        })
+    }
+
+    fn break_data_vecs_into_chunks<TileData, MapChunk, Source>(
+        &self,
+        data: &Source,
+        max_chunk_size: UVec2,
+        chunk_settings: MapChunk::ChunkSettings,
+    ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
+    where
+        TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Source: DenseTileSource<TileData> + ?Sized,
+    {
+        let mut chunks: Vec<Vec<Chunk<MapChunk, TileData>>> = vec![];
+        for y in 0..self.chunk_counts.y as i32 {
+            let mut chunks_rows: Vec<Chunk<MapChunk, TileData>> = vec![];
+            for x in 0..self.chunk_counts.x as i32 {
+                let chunk_pos = ChunkPos::new(x, y);
+                // Chunks outside of the map only exist to fill out the chunk grid so keep them sparse
+                let layer_type = match self.chunk_data(data, chunk_pos) {
+                    Some(vec) => ChunkLayerType::Dense(vec),
+                    None => ChunkLayerType::Sparse(HashMap::new()),
+                };
+                chunks_rows.push(Chunk::new(
+                    chunk_pos,
+                    max_chunk_size,
+                    layer_type,
+                    chunk_settings,
+                ));
+            }
+            chunks.push(chunks_rows);
+        }
+        chunks
+    }
+
+    fn break_hashmap_into_chunks<TileData, MapChunk>(
+        &self,
+        map_layer: impl MapLayer,
+        data: &HashMap<Cell, TileData>,
+        _map_size: UVec2,
+        max_chunk_size: UVec2,
+        chunk_settings: MapChunk::ChunkSettings,
+    ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
+    where
+        TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    {
+        let mut chunks: Vec<Vec<Chunk<MapChunk, TileData>>> = vec![];
+        for y in 0..self.chunk_counts.y as i32 {
+            let mut chunks_rows: Vec<Chunk<MapChunk, TileData>> = vec![];
+            for x in 0..self.chunk_counts.x as i32 {
+                chunks_rows.push(Chunk::new(
+                    ChunkPos::new(x, y),
+                    max_chunk_size,
+                    ChunkLayerType::Sparse(HashMap::new()),
+                    chunk_settings,
+                ));
+            }
+            chunks.push(chunks_rows);
+        }
+
+        for (cell, tile_data) in data.iter() {
+            let chunk_pos = self.into_chunk_pos(*cell);
+            let chunk = &mut chunks[chunk_pos.y() as usize][chunk_pos.x() as usize];
+            chunk.set_tile_data(
+                map_layer.to_bits(),
+                MapChunk::into_chunk_cell(*cell, &chunk.chunk_settings),
+                *tile_data,
+            );
+        }
+
+        chunks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hexagon_chunk_center, hexagon_chunk_of, HexChunkShape, HexagonalChunksMapData};
+    use crate as bevy_sparse_tilemap;
+    use crate::hex::hex_offset_from_orientation;
+    use crate::hex::map_chunk_layer::{HexChunkLayer, HexagonChunkSettings};
+    use crate::map::chunk::ChunkLayer;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_builder::TilemapBuilder;
+    use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{IVec2, UVec2};
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+    use lettuces::HexOrientation;
+    use proptest::prelude::*;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_hexagon_chunk_membership() {
+        for radius in 0..4u32 {
+            for q in -20..20 {
+                for r in -20..20 {
+                    let center =
+                        hexagon_chunk_center(hexagon_chunk_of(Cell::new(q, r), radius), radius);
+                    let (dq, dr) = (q - center.x, r - center.y);
+                    assert!(dq.abs().max(dr.abs()).max((dq + dr).abs()) <= radius as i32);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_hexagonal_chunks_map_access() {
+        let map_size = UVec2::new(13, 9);
+        let radius = 2;
+        let orientation = HexOrientation::Pointy;
+        let vecs: Vec<Vec<(i32, i32)>> = (0..map_size.y as i32)
+            .map(|y| (0..map_size.x as i32).map(|x| (x, y)).collect())
+            .collect();
+
+        let mut world = World::new();
+        let mut system_state: SystemState<(
+            Commands,
+            TilemapManager<
+                (i32, i32),
+                MapLayers,
+                HexChunkLayer<(i32, i32)>,
+                HexagonalChunksMapData,
+            >,
+        )> = SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let tilemap_builder = TilemapBuilder::<
+            (i32, i32),
+            MapLayers,
+            HexChunkLayer<(i32, i32)>,
+            HexagonalChunksMapData,
+        >::new(
+            TilemapLayer::new_dense_from_vecs(vecs),
+            HexagonalChunksMapData::new(map_size, radius, orientation),
+            HexagonChunkSettings {
+                orientation,
+                max_chunk_size: UVec2::splat(2 * radius + 1),
+                chunk_shape: HexChunkShape::Hexagon { radius },
+            },
+        );
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            panic!("Failed to spawn tilemap")
+        };
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), map_size);
+        for y in 0..map_size.y as i32 {
+            for x in 0..map_size.x as i32 {
+                let cell =
+                    Cell::from_offset_coordinates([x, y], hex_offset_from_orientation(orientation));
+                assert_eq!(tilemap_manager.get_tile_data(cell).unwrap(), (x, y));
+            }
+        }
+        tilemap_manager
+            .sets_tile_data((100, 100), Cell::new(-2, 5))
+            .unwrap();
+        assert_eq!(
+            tilemap_manager.get_tile_data(Cell::new(-2, 5)).unwrap(),
+            (100, 100)
+        );
+    }
+
+    /// A map size, a chunk radius, an orientation, and the offset coordinates of a cell that may lie outside
+    /// of the map
+    fn map_and_cell() -> impl Strategy<Value = (UVec2, u32, HexOrientation, IVec2)> {
+        (1..24u32, 1..24u32, 0..4u32, any::<bool>()).prop_flat_map(|(w, h, radius, pointy)| {
+            (
+                Just(UVec2::new(w, h)),
+                Just(radius),
+                Just(match pointy {
+                    true => HexOrientation::Pointy,
+                    false => HexOrientation::Flat,
+                }),
+                (-5..w as i32 + 5, -5..h as i32 + 5).prop_map(|(x, y)| IVec2::new(x, y)),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_hexagon_chunk_math_round_trips(q in -200..200i32, r in -200..200i32, radius in 0..6u32) {
+            let cell = Cell::new(q, r);
+            let settings = HexagonChunkSettings {
+                orientation: HexOrientation::Pointy,
+                max_chunk_size: UVec2::splat(2 * radius + 1),
+                chunk_shape: HexChunkShape::Hexagon { radius },
+            };
+            let center = hexagon_chunk_center(hexagon_chunk_of(cell, radius), radius);
+            let chunk_cell = HexChunkLayer::<u8>::into_chunk_cell(cell, &settings);
+
+            prop_assert!(chunk_cell.within(UVec2::splat(2 * radius + 1)));
+            prop_assert_eq!(
+                chunk_cell.as_ivec2() + IVec2::new(center.x, center.y) - IVec2::splat(radius as i32),
+                IVec2::new(cell.x, cell.y)
+            );
+        }
+
+        #[test]
+        fn test_manager_access_never_panics((map_size, radius, orientation, offset) in map_and_cell(), tile_data: u8) {
+            let mut world = World::new();
+            let mut system_state: SystemState<(
+                Commands,
+                TilemapManager<u8, MapLayers, HexChunkLayer<u8>, HexagonalChunksMapData>,
+            )> = SystemState::new(&mut world);
+            let (mut commands, _) = system_state.get_mut(&mut world);
+            let rows = (0..map_size.y)
+                .map(|y| (0..map_size.x).map(|x| (x + y * map_size.x) as u8).collect())
+                .collect();
+            let map_entity = TilemapBuilder::<u8, MapLayers, HexChunkLayer<u8>, HexagonalChunksMapData>::new(
+                TilemapLayer::new_dense_from_vecs(rows),
+                HexagonalChunksMapData::new(map_size, radius, orientation),
+                HexagonChunkSettings {
+                    orientation,
+                    max_chunk_size: UVec2::splat(2 * radius + 1),
+                    chunk_shape: HexChunkShape::Hexagon { radius },
+                },
+            )
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+            system_state.apply(&mut world);
+            let entity = world.spawn_empty().id();
+
+            let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+            tilemap_manager.set_tilemap_entity(map_entity);
+            let offset_mode = hex_offset_from_orientation(orientation);
+            let cell = Cell::from_offset_coordinates([offset.x, offset.y], offset_mode);
+            let origin = Cell::from_offset_coordinates([0, 0], offset_mode);
+
+            // Chunks along the map edge also hold cells outside of the map, so only cells inside of the map
+            // have known data. Every access must agree on which cells exist
+            let data = tilemap_manager.get_tile_data(cell);
+            if offset.cmpge(IVec2::ZERO).all() && offset.cmplt(map_size.as_ivec2()).all() {
+                prop_assert_eq!(
+                    data.as_ref().ok(),
+                    Some(&((offset.x as u32 + offset.y as u32 * map_size.x) as u8))
+                );
+            }
+            let exists = matches!(data, Ok(_) | Err(TilemapManagerError::TileDataDoesNotExist));
+            prop_assert_eq!(tilemap_manager.sets_tile_data(tile_data, cell).is_ok(), exists);
+
+            let spawned = tilemap_manager.get_or_spawn_tile_entity(cell).ok();
+            prop_assert_eq!(spawned.is_some(), exists);
+            prop_assert_eq!(tilemap_manager.get_tile_entity(cell).ok(), spawned);
+            prop_assert_eq!(tilemap_manager.set_tile_entity(cell, entity).is_ok(), exists);
+            prop_assert_eq!(
+                tilemap_manager.move_tile_entity(cell, origin).ok(),
+                (exists && cell != origin).then_some(entity)
+            );
+            prop_assert_eq!(tilemap_manager.despawn_tile_entity(cell).is_ok(), exists);
+        }
+    }
+}
@@ -1,12 +1,17 @@
-use crate::map::chunk::{ChunkCell, ChunkLayer, ChunkLayerType};
+use crate::hex::hexagonal_chunks::{hexagon_chunk_center, hexagon_chunk_of, HexChunkShape};
+use crate::map::chunk::{
+    sparse_map_heap_size, ChunkCell, ChunkLayer, ChunkLayerType, CompactionReport, LayerStorage,
+    SerializationStats, SparseMap, TileEntities, TileEntityStorage,
+};
 use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::math::UVec2;
 use bevy::prelude::{Component, Entity};
-use bevy::utils::HashMap;
 use lettuces::cell::Cell;
+use lettuces::storage::grid::Grid;
 use lettuces::storage::hex::HexRectangleStorage;
 use lettuces::HexOrientation;
 use std::hash::{Hash, Hasher};
+use std::mem::size_of;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
@@ -26,6 +31,8 @@ pub struct HexagonChunkSettings {
     pub orientation: HexOrientation,
     /// The maximum size that a chunk can be
     pub max_chunk_size: UVec2,
+    /// The shape of the chunks in the map. Must match the [`MapData`](crate::map::MapData) used for the map
+    pub chunk_shape: HexChunkShape,
 }
 
 impl Default for HexagonChunkSettings {
@@ -33,12 +40,13 @@ impl Default for HexagonChunkSettings {
         Self {
             max_chunk_size: UVec2 { x: 10, y: 10 },
             orientation: HexOrientation::default(),
+            chunk_shape: HexChunkShape::default(),
         }
     }
 }
 
 /// A struct that holds the chunk map data for the given layer
-#[derive(Component, Default)]
+#[derive(Clone, Component, Default)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Hash, Component, MapEntities))]
@@ -46,8 +54,19 @@ pub struct HexChunkLayer<T>
 where
     T: Hash + Clone + Copy + Sized + Default + Send + Sync,
 {
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(
+            serialize = "T: Serialize + PartialEq",
+            deserialize = "T: Deserialize<'de>"
+        ))
+    )]
     layer_type_data: HexChunkLayerData<T>,
-    tile_entities: HashMap<u64, Entity>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "TileEntities::skip_serializing")
+    )]
+    tile_entities: TileEntities,
 }
 
 impl<T> MapEntities for HexChunkLayer<T>
@@ -55,9 +74,7 @@ where
     T: Hash + Clone + Copy + Sized + Default + Send + Sync,
 {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
-        for tile_entity in self.tile_entities.iter_mut() {
-            *tile_entity.1 = entity_mapper.map_entity(*tile_entity.1);
-        }
+        self.tile_entities.map_entities(entity_mapper);
     }
 }
 
@@ -66,9 +83,7 @@ where
     T: Hash + Clone + Copy + Sized + Default + Send + Sync,
 {
     fn hash<H: Hasher>(&self, h: &mut H) {
-        let mut pairs: Vec<_> = self.tile_entities.iter().collect();
-        pairs.sort_by_key(|i| i.0);
-        Hash::hash(&pairs, h);
+        Hash::hash(&self.tile_entities, h);
         Hash::hash(&self.layer_type_data, h);
     }
 }
@@ -79,6 +94,13 @@ where
     type ChunkSettings = HexagonChunkSettings;
 
     fn into_chunk_cell(cell: Cell, chunk_settings: &Self::ChunkSettings) -> ChunkCell {
+        if let HexChunkShape::Hexagon { radius } = chunk_settings.chunk_shape {
+            let center = hexagon_chunk_center(hexagon_chunk_of(cell, radius), radius);
+            return ChunkCell::new(
+                cell.x - center.x + radius as i32,
+                cell.y - center.y + radius as i32,
+            );
+        }
         let chunk_pos_x = cell.x / chunk_settings.max_chunk_size.x as i32;
         let chunk_pos_y = cell.y / chunk_settings.max_chunk_size.y as i32;
         ChunkCell::new(
@@ -93,6 +115,14 @@ where
         settings: &Self::ChunkSettings,
     ) -> Self {
         match layer_type {
+            ChunkLayerType::Dense(dense_data)
+                if matches!(settings.chunk_shape, HexChunkShape::Hexagon { .. }) =>
+            {
+                Self {
+                    layer_type_data: HexChunkLayerData::new_hexagon_from_vecs(&dense_data),
+                    tile_entities: Default::default(),
+                }
+            }
             ChunkLayerType::Dense(dense_data) => Self {
                 layer_type_data: HexChunkLayerData::new_dense_from_vecs(
                     &dense_data,
@@ -133,13 +163,129 @@ where
     }
 
     fn get_tile_entity(&self, chunk_tile_pos: ChunkCell) -> Option<Entity> {
-        let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
-        self.tile_entities.get(&number).cloned()
+        self.tile_entities.get(chunk_tile_pos)
     }
 
     fn set_tile_entity(&mut self, chunk_tile_pos: ChunkCell, entity: Entity) {
-        let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
-        self.tile_entities.insert(number, entity);
+        self.tile_entities.insert(chunk_tile_pos, entity);
+    }
+
+    fn remove_tile_entity(&mut self, chunk_tile_pos: ChunkCell) -> Option<Entity> {
+        self.tile_entities.remove(chunk_tile_pos)
+    }
+
+    fn remove_tile_data(&mut self, chunk_tile_pos: ChunkCell) -> Option<TileData> {
+        self.layer_type_data.remove_tile_data(chunk_tile_pos)
+    }
+
+    fn iter_tile_entities(&self) -> Box<dyn Iterator<Item = (ChunkCell, Entity)> + '_> {
+        self.tile_entities.iter()
+    }
+
+    fn set_tile_entity_storage(&mut self, storage: TileEntityStorage) {
+        let dimensions = self.get_chunk_dimensions();
+        self.tile_entities.convert(storage, dimensions);
+    }
+
+    fn is_sparse(&self) -> bool {
+        matches!(self.layer_type_data, HexChunkLayerData::Sparse(..))
+    }
+
+    /// Sparse hex layers don't know the orientation or shape of their chunk, so only dense layers can be
+    /// converted
+    fn set_layer_storage(
+        &mut self,
+        storage: LayerStorage,
+        is_empty: &dyn Fn(&TileData) -> bool,
+    ) -> bool {
+        if storage != LayerStorage::Sparse || self.is_sparse() {
+            return false;
+        }
+        let dimensions = self.get_chunk_dimensions();
+        let filled = ChunkCell::iter_chunk(dimensions)
+            .filter_map(|chunk_cell| {
+                let tile_data = self.layer_type_data.get_tile_data(chunk_cell)?;
+                (!is_empty(tile_data)).then_some(((chunk_cell.x(), chunk_cell.y()), *tile_data))
+            })
+            .collect();
+        self.layer_type_data = HexChunkLayerData::Sparse(filled, dimensions);
+        true
+    }
+
+    fn compact(
+        &mut self,
+        is_empty: &dyn Fn(&TileData) -> bool,
+        max_dense_fill: f32,
+    ) -> CompactionReport {
+        let before = self.heap_size();
+        let dimensions = self.get_chunk_dimensions();
+        let mut report = CompactionReport::default();
+        if let HexChunkLayerData::Sparse(layer_data, ..) = &mut self.layer_type_data {
+            let len = layer_data.len();
+            layer_data.retain(|_, tile_data| !is_empty(tile_data));
+            report.dropped_entries = len - layer_data.len();
+            layer_data.shrink_to_fit();
+        } else {
+            let cell_count = (dimensions.x * dimensions.y) as usize;
+            let filled: SparseMap<(i32, i32), TileData> = ChunkCell::iter_chunk(dimensions)
+                .filter_map(|chunk_cell| {
+                    let tile_data = self.layer_type_data.get_tile_data(chunk_cell)?;
+                    (!is_empty(tile_data)).then_some(((chunk_cell.x(), chunk_cell.y()), *tile_data))
+                })
+                .collect();
+            if cell_count > 0 && filled.len() as f32 <= max_dense_fill * cell_count as f32 {
+                self.layer_type_data = HexChunkLayerData::Sparse(filled, dimensions);
+                report.converted_layers = 1;
+            }
+        }
+        self.tile_entities.shrink_to_fit();
+        let after = self.heap_size();
+        report.reclaimed_bytes = before.saturating_sub(after);
+        report.heap_bytes = after;
+        report
+    }
+
+    fn serialization_stats(&self, is_default: &dyn Fn(&TileData) -> bool) -> SerializationStats {
+        let mut stats = SerializationStats {
+            skipped_entity_maps: self.tile_entities.skip_serializing() as usize,
+            ..SerializationStats::default()
+        };
+        match &self.layer_type_data {
+            HexChunkLayerData::Sparse(layer_data, ..) => {
+                stats.stored_tiles = layer_data.len();
+            }
+            HexChunkLayerData::Dense(storage) => {
+                stats.stored_tiles = storage.grid.size().0 * storage.grid.size().1;
+            }
+            HexChunkLayerData::Hexagon(grid) => {
+                let (rows, columns) = grid.size();
+                let defaults = (0..rows)
+                    .flat_map(|y| (0..columns).map(move |x| (x, y)))
+                    .filter_map(|(x, y)| grid.get(y, x))
+                    .filter(|tile_data| is_default(tile_data))
+                    .count();
+                stats.skipped_default_tiles = defaults;
+                stats.stored_tiles = rows * columns - defaults;
+            }
+        }
+        stats
+    }
+}
+
+impl<T> HexChunkLayer<T>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    /// Returns the approximate amount of heap memory used by the layer in bytes, including unused capacity
+    pub fn heap_size(&self) -> usize {
+        let data = match &self.layer_type_data {
+            HexChunkLayerData::Sparse(layer_data, ..) => sparse_map_heap_size(layer_data),
+            HexChunkLayerData::Dense(storage) => {
+                storage.grid.size().0 * storage.grid.size().1 * size_of::<T>()
+            }
+            HexChunkLayerData::Hexagon(grid) => grid.size().0 * grid.size().1 * size_of::<T>(),
+        };
+        data + self.tile_entities.heap_size()
     }
 }
 
@@ -156,9 +302,27 @@ where
     ///
     /// 0. A hashmap of TilePos -> TileData
     /// 1. A UVec2 representing the actual size of the chunk
-    Sparse(HashMap<(i32, i32), T>, UVec2),
+    Sparse(SparseMap<(i32, i32), T>, UVec2),
     /// A layer where ***EVERY***  position on the chunk must have data
     Dense(HexRectangleStorage<T>),
+    /// A layer where ***EVERY*** position on a [`HexChunkShape::Hexagon`] chunk must have data
+    ///
+    /// Stores the chunk as a square grid of axial offsets from the chunk's center, shifted by the chunk radius.
+    /// Tiles holding the default `T`, including the corners of the grid outside of the hexagon, are left out
+    /// when serializing
+    Hexagon(
+        #[cfg_attr(
+            feature = "serde",
+            serde(
+                with = "crate::map::chunk::default_runs",
+                bound(
+                    serialize = "T: Serialize + PartialEq",
+                    deserialize = "T: Deserialize<'de>"
+                )
+            )
+        )]
+        Grid<T>,
+    ),
 }
 
 impl<T> Hash for HexChunkLayerData<T>
@@ -176,6 +340,9 @@ where
             HexChunkLayerData::Dense(grid) => {
                 Hash::hash(grid, h);
             }
+            HexChunkLayerData::Hexagon(grid) => {
+                Hash::hash(grid, h);
+            }
         }
     }
 }
@@ -251,6 +418,36 @@ where
 
         Self::Dense(grid)
     }
+
+    /// Creates a new [`HexChunkLayerData::Hexagon`] from the given vectors of vectors of T
+    pub fn new_hexagon_from_vecs(tile_data: &[Vec<T>]) -> Self {
+        let mut given_tile_count = 0u64;
+
+        for tile_data in tile_data.iter() {
+            given_tile_count += tile_data.len() as u64;
+        }
+
+        assert_eq!(
+            (tile_data[0].len() * tile_data.len()) as u64,
+            given_tile_count
+        );
+
+        let mut grid: Grid<T> = Grid::init(tile_data.len(), tile_data[0].len(), T::default());
+        let mut current_x = 0usize;
+        let mut current_y = 0usize;
+        let row_length = tile_data[0].len();
+        grid.fill_with(|| {
+            let tile = tile_data[current_y][current_x];
+            current_x += 1;
+            if current_x == row_length {
+                current_x = 0;
+                current_y += 1;
+            }
+            tile
+        });
+
+        Self::Hexagon(grid)
+    }
 }
 
 impl<T> HexChunkLayerData<T>
@@ -264,6 +461,9 @@ where
             HexChunkLayerData::Dense(grid) => {
                 UVec2::new(grid.dimensions().y.into(), grid.dimensions().x.into())
             }
+            HexChunkLayerData::Hexagon(grid) => {
+                UVec2::new(grid.size().1 as u32, grid.size().0 as u32)
+            }
         }
     }
 
@@ -280,9 +480,26 @@ where
                     *tile = tile_data
                 };
             }
+            HexChunkLayerData::Hexagon(layer_data) => {
+                if let Some(tile) =
+                    layer_data.get_mut(chunk_tile_pos.y() as usize, chunk_tile_pos.x() as usize)
+                {
+                    *tile = tile_data
+                };
+            }
         };
     }
 
+    /// Removes the tile data at the given [`ChunkCell`] and returns it. Only sparse layers can remove tile data
+    pub fn remove_tile_data(&mut self, chunk_tile_pos: ChunkCell) -> Option<T> {
+        match self {
+            HexChunkLayerData::Sparse(layer_data, ..) => {
+                layer_data.remove(&(chunk_tile_pos.x(), chunk_tile_pos.y()))
+            }
+            HexChunkLayerData::Dense(..) | HexChunkLayerData::Hexagon(..) => None,
+        }
+    }
+
     /// Gets mutable access to the tile data at the given [`ChunkCell`]. Can fail if the given cell is not a valid position in the chunk
     pub fn get_tile_data_mut(&mut self, chunk_tile_pos: ChunkCell) -> Option<&mut T> {
         return match self {
@@ -292,6 +509,9 @@ where
             HexChunkLayerData::Dense(layer_data) => {
                 layer_data.get_mut(Cell::new(chunk_tile_pos.x(), chunk_tile_pos.y()))
             }
+            HexChunkLayerData::Hexagon(layer_data) => {
+                layer_data.get_mut(chunk_tile_pos.y() as usize, chunk_tile_pos.x() as usize)
+            }
         };
     }
 
@@ -304,6 +524,9 @@ where
             HexChunkLayerData::Dense(layer_data) => {
                 layer_data.get(Cell::new(chunk_tile_pos.x(), chunk_tile_pos.y()))
             }
+            HexChunkLayerData::Hexagon(layer_data) => {
+                layer_data.get(chunk_tile_pos.y() as usize, chunk_tile_pos.x() as usize)
+            }
         };
     }
 }
@@ -11,13 +11,15 @@ use bevy::prelude::{Reflect, ReflectComponent};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+use crate::hex::{hex_line, hex_neighbors, hex_range, hex_ring, hex_supercover_line};
 use crate::map::{
     chunk::{Chunk, ChunkLayerType, ChunkPos},
-    MapData, MapLayer,
+    Adjacency, MapData, MapLayer,
 };
+use crate::tilemap_builder::tilemap_layer_builder::DenseTileSource;
 
 /// [`MapData`] implementation for a hexagonal map. Uses essentially the same logic as for a square map. Prior to map construction the map is in offset coordinates
-#[derive(Default, Hash, Component)]
+#[derive(Clone, Default, Hash, Component)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Hash))]
@@ -38,47 +40,78 @@ impl MapData for HexMapData {
         self.max_chunk_size
     }
 
-    fn break_data_vecs_down_into_chunk_data<TileData>(
+    fn neighbors(
         &self,
-        data: &Vec<Vec<TileData>>,
+        cell: lettuces::cell::Cell,
+        _adjacency: Adjacency,
+    ) -> Vec<lettuces::cell::Cell> {
+        hex_neighbors(cell)
+    }
+
+    fn line(&self, a: lettuces::cell::Cell, b: lettuces::cell::Cell) -> Vec<lettuces::cell::Cell> {
+        hex_line(a, b)
+    }
+
+    fn sight_line(
+        &self,
+        a: lettuces::cell::Cell,
+        b: lettuces::cell::Cell,
+    ) -> Vec<lettuces::cell::Cell> {
+        hex_supercover_line(a, b)
+    }
+
+    fn cells_in_radius(
+        &self,
+        center: lettuces::cell::Cell,
+        radius: u32,
+    ) -> Vec<lettuces::cell::Cell> {
+        hex_range(center, radius)
+    }
+
+    fn cells_in_ring(
+        &self,
+        center: lettuces::cell::Cell,
+        radius: u32,
+    ) -> Vec<lettuces::cell::Cell> {
+        hex_ring(center, radius)
+    }
+
+    fn break_data_vecs_down_into_chunk_data<TileData, Source>(
+        &self,
+        data: &Source,
         chunk_pos: ChunkPos,
         max_chunk_size: UVec2,
     ) -> Vec<Vec<TileData>>
     where
         TileData: Clone + Copy + Sized + Default + Send + Sync + 'static,
+        Source: DenseTileSource<TileData> + ?Sized,
     {
+        let dimensions = data.dimensions();
         let amount_of_x_tiles_done = (chunk_pos.x() * max_chunk_size.x as i32) as usize;
         let amount_of_y_tiles_done = (chunk_pos.y() * max_chunk_size.y as i32) as usize;
-        let mut vec: Vec<Vec<TileData>> = vec![];
-        for y in amount_of_y_tiles_done..data.len() {
-            if y >= (amount_of_y_tiles_done + max_chunk_size.y as usize) {
-                continue;
-            }
-            let mut row_vec: Vec<TileData> = vec![];
-            for x in amount_of_x_tiles_done..data[0].len() {
-                if x >= (amount_of_x_tiles_done + max_chunk_size.x as usize) {
-                    continue;
-                }
-                row_vec.push(data[y][x]);
-            }
-            vec.push(row_vec);
-        }
-        vec
+        let x_tiles = amount_of_x_tiles_done
+            ..(amount_of_x_tiles_done + max_chunk_size.x as usize).min(dimensions.x as usize);
+        let y_tiles = amount_of_y_tiles_done
+            ..(amount_of_y_tiles_done + max_chunk_size.y as usize).min(dimensions.y as usize);
+        y_tiles
+            .map(|y| x_tiles.clone().map(|x| data.tile_data(x, y)).collect())
+            .collect()
     }
 
-    fn break_data_vecs_into_chunks<TileData, MapChunk>(
+    fn break_data_vecs_into_chunks<TileData, MapChunk, Source>(
         &self,
-        data: &Vec<Vec<TileData>>,
+        data: &Source,
         max_chunk_size: UVec2,
         chunk_settings: MapChunk::ChunkSettings,
     ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
     where
         TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
         MapChunk: crate::map::chunk::ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Source: DenseTileSource<TileData> + ?Sized,
     {
         let mut chunks: Vec<Vec<Chunk<MapChunk, TileData>>> = vec![];
-        let map_x = data[0].len() as f32;
-        let map_y = data.len() as f32;
+        let map_x = data.dimensions().x as f32;
+        let map_y = data.dimensions().y as f32;
 
         let chunks_on_x = (map_x / max_chunk_size.x as f32).ceil() as i32;
         let chunks_on_y = (map_y / max_chunk_size.y as f32).ceil() as i32;
@@ -87,7 +120,7 @@ impl MapData for HexMapData {
             let mut chunks_rows: Vec<Chunk<MapChunk, TileData>> = vec![];
             for x in 0..chunks_on_x {
                 let vec = self.break_data_vecs_down_into_chunk_data(
-                    &data,
+                    data,
                     ChunkPos::new(x, y),
                     max_chunk_size,
                 );
@@ -166,3 +199,35 @@ impl MapData for HexMapData {
         chunks
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::HexMapData;
+    use crate::hex::map_chunk_layer::{HexChunkLayer, HexagonChunkSettings};
+    use crate::map::chunk::ChunkLayer;
+    use crate::map::MapData;
+    use bevy::math::{IVec2, UVec2};
+    use lettuces::cell::Cell;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_chunk_math_round_trips(x in 0..500i32, y in 0..500i32, cw in 1..12u32, ch in 1..12u32) {
+            let chunk_size = UVec2::new(cw, ch);
+            let map_data = HexMapData { max_chunk_size: chunk_size };
+            let chunk_settings = HexagonChunkSettings {
+                max_chunk_size: chunk_size,
+                ..Default::default()
+            };
+            let cell = Cell::new(x, y);
+            let chunk_pos = map_data.into_chunk_pos(cell);
+            let chunk_cell = HexChunkLayer::<u8>::into_chunk_cell(cell, &chunk_settings);
+
+            prop_assert!(chunk_cell.within(chunk_size));
+            prop_assert_eq!(
+                chunk_pos.as_ivec2() * chunk_size.as_ivec2() + chunk_cell.as_ivec2(),
+                IVec2::new(x, y)
+            );
+        }
+    }
+}
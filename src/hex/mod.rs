@@ -1,9 +1,15 @@
-use lettuces::{HexOrientation, OffsetHexMode, Quat};
+use bevy::math::IVec2;
+use hexagonal_chunks::HexagonalChunksMapData;
+use lettuces::{cell::Cell, HexOrientation, OffsetHexMode, Quat};
 use map_chunk_layer::HexChunkLayer;
 use map_data::HexMapData;
 
 use crate::{map::chunk::Chunk, tilemap_builder::TilemapBuilder, tilemap_manager::TilemapManager};
 
+/// The world space layout of hexagonal maps
+pub mod geometry;
+/// Implements [`MapData`](crate::map::MapData) for a hexagonal map split into hexagon shaped chunks
+pub mod hexagonal_chunks;
 /// Implements [`ChunkLayer`](crate::map::chunk::ChunkLayer) for a hexagonal map
 pub mod map_chunk_layer;
 /// Implements [`MapData`](crate::map::MapData) for a hexagonal map
@@ -13,6 +19,10 @@ pub mod map_data;
 pub type HexTilemapManager<'w, 's, TileData, MapLayers> =
     TilemapManager<'w, 's, TileData, MapLayers, HexChunkLayer<TileData>, HexMapData>;
 
+/// Type alias for [`TilemapManager`] for hexagon maps split into hexagon shaped chunks.
+pub type HexagonalChunksTilemapManager<'w, 's, TileData, MapLayers> =
+    TilemapManager<'w, 's, TileData, MapLayers, HexChunkLayer<TileData>, HexagonalChunksMapData>;
+
 /// Type alias for [`Chunk`] using the built in [`HexChunkLayer`]
 pub type HexChunk<TileData> = Chunk<HexChunkLayer<TileData>, TileData>;
 
@@ -20,6 +30,10 @@ pub type HexChunk<TileData> = Chunk<HexChunkLayer<TileData>, TileData>;
 pub type HexTilemapBuilder<TileData, MapLayers> =
     TilemapBuilder<TileData, MapLayers, HexChunkLayer<TileData>, HexMapData>;
 
+/// Type alias for [`TilemapBuilder`] for hexagon maps split into hexagon shaped chunks
+pub type HexagonalChunksTilemapBuilder<TileData, MapLayers> =
+    TilemapBuilder<TileData, MapLayers, HexChunkLayer<TileData>, HexagonalChunksMapData>;
+
 /// Converts a [`HexOrientation`] into a [`OffsetHexMode`]. This sets it to Odd Rows and Odd Columns respectively which are the only two that this crate supports
 pub fn hex_offset_from_orientation(orientation: HexOrientation) -> OffsetHexMode {
     match orientation {
@@ -28,6 +42,129 @@ pub fn hex_offset_from_orientation(orientation: HexOrientation) -> OffsetHexMode
     }
 }
 
+/// The offsets of the six neighbours of a cell in axial coordinates
+pub const HEX_NEIGHBORS: [IVec2; 6] = [
+    IVec2::new(1, 0),
+    IVec2::new(1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, 1),
+    IVec2::new(0, 1),
+];
+
+/// Returns the six neighbours of the given cell in axial coordinates
+pub fn hex_neighbors(cell: Cell) -> Vec<Cell> {
+    HEX_NEIGHBORS
+        .iter()
+        .map(|offset| Cell::new(cell.x + offset.x, cell.y + offset.y))
+        .collect()
+}
+
+/// Returns the amount of steps between two cells in axial coordinates
+pub fn hex_distance(a: Cell, b: Cell) -> u32 {
+    let (dq, dr) = (a.x - b.x, a.y - b.y);
+    (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2
+}
+
+/// Returns the cells of a line from a to b in axial coordinates including both ends. Consecutive cells are
+/// neighbours
+pub fn hex_line(a: Cell, b: Cell) -> Vec<Cell> {
+    let distance = hex_distance(a, b);
+    if distance == 0 {
+        return vec![a];
+    }
+    (0..=distance)
+        .map(|step| {
+            let t = step as f32 / distance as f32;
+            // Nudged off of the edges between cells so ties are always broken the same way
+            hex_round(
+                a.x as f32 + (b.x - a.x) as f32 * t + 1e-6,
+                a.y as f32 + (b.y - a.y) as f32 * t + 1e-6,
+            )
+        })
+        .collect()
+}
+
+/// Returns every cell touched by the line from a to b in axial coordinates including both ends. Where the line
+/// runs exactly along the edge between two cells both of them are included, so nothing can be seen through the
+/// gap between two walls. Axial lines are the same for pointy and flat hexes, so this works for either
+/// orientation
+pub fn hex_supercover_line(a: Cell, b: Cell) -> Vec<Cell> {
+    let distance = hex_distance(a, b);
+    let mut cells = vec![a];
+    for step in 1..=distance {
+        let t = step as f32 / distance as f32;
+        let (x, y) = (
+            a.x as f32 + (b.x - a.x) as f32 * t,
+            a.y as f32 + (b.y - a.y) as f32 * t,
+        );
+        // Nudged off of the edge to either side, both cells are kept if the line runs along it
+        for nudge in [-1e-4, 1e-4] {
+            let cell = hex_round(x + nudge, y + nudge * 2.0);
+            if !cells.contains(&cell) {
+                cells.push(cell);
+            }
+        }
+    }
+    cells
+}
+
+/// Returns the cells at most radius steps away from the center in axial coordinates
+pub fn hex_range(center: Cell, radius: u32) -> Vec<Cell> {
+    let radius = radius as i32;
+    let mut cells = vec![];
+    for q in -radius..=radius {
+        for r in (-radius).max(-q - radius)..=radius.min(-q + radius) {
+            cells.push(Cell::new(center.x + q, center.y + r));
+        }
+    }
+    cells
+}
+
+/// Returns the cells exactly radius steps away from the center in axial coordinates, walking around the ring
+/// so consecutive cells are neighbours
+pub fn hex_ring(center: Cell, radius: u32) -> Vec<Cell> {
+    if radius == 0 {
+        return vec![center];
+    }
+    let radius = radius as i32;
+    let start = HEX_NEIGHBORS[4] * radius;
+    let mut cell = Cell::new(center.x + start.x, center.y + start.y);
+    let mut cells = Vec::with_capacity(6 * radius as usize);
+    for direction in HEX_NEIGHBORS.iter() {
+        for _ in 0..radius {
+            cells.push(cell);
+            cell = Cell::new(cell.x + direction.x, cell.y + direction.y);
+        }
+    }
+    cells
+}
+
+/// Returns the cells at most radius steps away from the center in axial coordinates ordered by their
+/// distance, starting with the center and followed by every ring from the inside out
+pub fn hex_spiral(center: Cell, radius: u32) -> Vec<Cell> {
+    (0..=radius)
+        .flat_map(|ring_radius| hex_ring(center, ring_radius))
+        .collect()
+}
+
+/// Rounds fractional axial coordinates to the cell containing them
+fn hex_round(q: f32, r: f32) -> Cell {
+    let s = -q - r;
+    let (mut rounded_q, mut rounded_r, rounded_s) = (q.round(), r.round(), s.round());
+    let (diff_q, diff_r, diff_s) = (
+        (rounded_q - q).abs(),
+        (rounded_r - r).abs(),
+        (rounded_s - s).abs(),
+    );
+    if diff_q > diff_r && diff_q > diff_s {
+        rounded_q = -rounded_r - rounded_s;
+    } else if diff_r > diff_s {
+        rounded_r = -rounded_q - rounded_s;
+    }
+    Cell::new(rounded_q as i32, rounded_r as i32)
+}
+
 /// Returns the correct hexagon rotation for the given orientation
 pub fn hex_rotation(orientation: HexOrientation) -> Quat {
     Quat::from_rotation_z(match orientation {
@@ -35,3 +172,68 @@ pub fn hex_rotation(orientation: HexOrientation) -> Quat {
         HexOrientation::Flat => 0.52359878,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        hex_distance, hex_line, hex_neighbors, hex_range, hex_ring, hex_spiral, hex_supercover_line,
+    };
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_hex_line_and_range() {
+        let (a, b) = (Cell::new(-2, 1), Cell::new(3, -3));
+        assert_eq!(hex_distance(a, b), 5);
+        let line = hex_line(a, b);
+        assert_eq!(line.len(), 6);
+        assert_eq!(line.first(), Some(&a));
+        assert_eq!(line.last(), Some(&b));
+        for pair in line.windows(2) {
+            assert!(hex_neighbors(pair[0]).contains(&pair[1]));
+        }
+
+        let range = hex_range(Cell::new(4, 4), 2);
+        assert_eq!(range.len(), 19);
+        assert!(range
+            .iter()
+            .all(|cell| hex_distance(*cell, Cell::new(4, 4)) <= 2));
+        assert_eq!(hex_range(Cell::new(4, 4), 0), vec![Cell::new(4, 4)]);
+    }
+
+    #[test]
+    fn test_hex_supercover_line() {
+        // Lines running along an edge include the cells on both sides
+        assert_eq!(
+            hex_supercover_line(Cell::new(0, 0), Cell::new(1, 1)),
+            vec![
+                Cell::new(0, 0),
+                Cell::new(1, 0),
+                Cell::new(0, 1),
+                Cell::new(1, 1)
+            ]
+        );
+        let (a, b) = (Cell::new(-2, 1), Cell::new(3, -3));
+        assert_eq!(hex_supercover_line(a, b), hex_line(a, b));
+        assert_eq!(hex_supercover_line(a, a), vec![a]);
+    }
+
+    #[test]
+    fn test_hex_ring_and_spiral() {
+        let center = Cell::new(-1, 2);
+        let ring = hex_ring(center, 3);
+        assert_eq!(ring.len(), 18);
+        assert!(ring.iter().all(|cell| hex_distance(*cell, center) == 3));
+        for index in 0..ring.len() {
+            let next = ring[(index + 1) % ring.len()];
+            assert!(hex_neighbors(ring[index]).contains(&next));
+        }
+        assert_eq!(hex_ring(center, 0), vec![center]);
+
+        let spiral = hex_spiral(center, 2);
+        assert_eq!(spiral.len(), hex_range(center, 2).len());
+        assert_eq!(spiral[0], center);
+        assert!(spiral
+            .windows(2)
+            .all(|pair| hex_distance(pair[0], center) <= hex_distance(pair[1], center)));
+    }
+}
@@ -0,0 +1,373 @@
+//! Undo and redo of tile edits. Requires the `history` feature.
+//!
+//! Insert a [`TileHistory`] on a tilemap entity and every write of tile data made through the
+//! [`TilemapManager`](crate::tilemap_manager::TilemapManager) is recorded as an entry that
+//! [`TilemapManager::undo`](crate::tilemap_manager::TilemapManager::undo) reverts and
+//! [`TilemapManager::redo`](crate::tilemap_manager::TilemapManager::redo) applies again. Each call to the
+//! manager is its own entry, so a batch write, a shape fill, or a committed transaction is undone as a whole.
+//! Editors that want to undo several calls at once, like every cell painted during a single brush stroke,
+//! group them with [`TilemapManager::begin_history_group`](crate::tilemap_manager::TilemapManager::begin_history_group)
+//! and [`TilemapManager::end_history_group`](crate::tilemap_manager::TilemapManager::end_history_group).
+//!
+//! Undoing a write to a cell that had no tile data removes the tile data of the cell again. Only tile data is
+//! tracked, changes to the shape of the map such as resizing it or adding layers are not recorded.
+//!
+//! ```ignore
+//! commands.entity(map).insert(TileHistory::<TileData>::new(100));
+//! // ... later
+//! tilemap_manager.sets_tile_data(tile_data, cell)?;
+//! tilemap_manager.undo()?;
+//! ```
+
+use crate::map::TileWrite;
+use bevy::prelude::Component;
+use std::collections::VecDeque;
+
+/// The writes made by a single undoable edit, in the order they were made
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry<TileData> {
+    /// The recorded writes
+    pub writes: Vec<TileWrite<TileData>>,
+}
+
+/// The undo and redo stacks of a tilemap. See the [module docs](self) for details
+#[derive(Component, Clone, Debug)]
+pub struct TileHistory<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    undo: VecDeque<HistoryEntry<TileData>>,
+    redo: Vec<HistoryEntry<TileData>>,
+    group: Option<HistoryEntry<TileData>>,
+    group_depth: usize,
+    max_entries: usize,
+}
+
+impl<TileData> Default for TileHistory<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl<TileData> TileHistory<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    /// Creates an empty history that keeps at most the given amount of entries to undo. The oldest entries
+    /// are dropped first
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            group: None,
+            group_depth: 0,
+            max_entries,
+        }
+    }
+
+    /// Returns the maximum amount of entries kept to undo
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Returns the amount of entries that can be undone
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Returns the amount of entries that can be redone
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// Returns true if there is an entry to undo, including an open group with writes
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+            || self
+                .group
+                .as_ref()
+                .is_some_and(|group| !group.writes.is_empty())
+    }
+
+    /// Returns true if there is an entry to redo
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Returns true while writes are being grouped into a single entry
+    pub fn is_grouping(&self) -> bool {
+        self.group_depth > 0
+    }
+
+    /// Drops every entry and closes any open group
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group = None;
+        self.group_depth = 0;
+    }
+
+    /// Starts grouping writes into a single entry. Groups can be nested, the entry is only closed once every
+    /// group has been ended
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+        self.group
+            .get_or_insert_with(|| HistoryEntry { writes: vec![] });
+    }
+
+    /// Ends the innermost group. Closing the outermost group pushes every write made since it began as one
+    /// entry. Does nothing if no group is open
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+        self.group_depth -= 1;
+        if self.group_depth == 0 {
+            self.close_group();
+        }
+    }
+
+    fn close_group(&mut self) {
+        self.group_depth = 0;
+        if let Some(group) = self.group.take() {
+            self.push_undo(group);
+        }
+    }
+
+    /// Pushes an entry that can be undone without clearing the redo stack, dropping the oldest entry if the
+    /// limit is reached
+    pub(crate) fn push_undo(&mut self, entry: HistoryEntry<TileData>) {
+        if entry.writes.is_empty() || self.max_entries == 0 {
+            return;
+        }
+        if self.undo.len() == self.max_entries {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(entry);
+    }
+
+    /// Records the writes of a single edit, adding them to the open group if there is one. Clears the redo
+    /// stack
+    pub(crate) fn record(&mut self, writes: impl IntoIterator<Item = TileWrite<TileData>>) {
+        let writes: Vec<TileWrite<TileData>> = writes.into_iter().collect();
+        if writes.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        match self.group.as_mut() {
+            Some(group) => group.writes.extend(writes),
+            None => self.push_undo(HistoryEntry { writes }),
+        }
+    }
+
+    /// Removes the entry to undo next, closing any open group first
+    pub(crate) fn pop_undo(&mut self) -> Option<HistoryEntry<TileData>> {
+        self.close_group();
+        self.undo.pop_back()
+    }
+
+    /// Pushes an entry that was undone so it can be redone
+    pub(crate) fn push_redo(&mut self, entry: HistoryEntry<TileData>) {
+        self.redo.push(entry);
+    }
+
+    /// Removes the entry to redo next, closing any open group first
+    pub(crate) fn pop_redo(&mut self) -> Option<HistoryEntry<TileData>> {
+        self.close_group();
+        self.redo.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TileHistory;
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::{ChunkLayer, ChunkPos};
+    use crate::map::{TileOverrides, TileWrite};
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    fn write(x: i32, old: u8, new: u8) -> TileWrite<u8> {
+        TileWrite {
+            cell: Cell::new(x, 0),
+            map_layer: 1,
+            old: Some(old),
+            new,
+        }
+    }
+
+    #[test]
+    fn test_history_groups_and_limits() {
+        let mut history = TileHistory::<u8>::new(2);
+        history.record([write(0, 0, 1)]);
+        history.begin_group();
+        history.record([write(1, 0, 1)]);
+        history.begin_group();
+        history.record([write(2, 0, 1)]);
+        history.end_group();
+        assert!(history.is_grouping());
+        assert_eq!(history.undo_len(), 1);
+        history.end_group();
+        assert!(!history.is_grouping());
+        assert_eq!(history.undo_len(), 2);
+
+        // The oldest entry is dropped once the limit is reached
+        history.record([write(3, 0, 1)]);
+        assert_eq!(history.undo_len(), 2);
+        let entry = history.pop_undo().unwrap();
+        assert_eq!(entry.writes, vec![write(3, 0, 1)]);
+        history.push_redo(entry);
+        let entry = history.pop_undo().unwrap();
+        assert_eq!(entry.writes, vec![write(1, 0, 1), write(2, 0, 1)]);
+        assert!(!history.can_undo());
+        assert!(history.can_redo());
+
+        // New writes clear the redo stack
+        history.record([write(4, 0, 1)]);
+        assert!(!history.can_redo());
+
+        // Empty groups aren't recorded
+        history.begin_group();
+        history.end_group();
+        assert_eq!(history.undo_len(), 1);
+    }
+
+    #[test]
+    fn test_manager_undo_redo() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert!(matches!(
+            tilemap_manager.undo(),
+            Err(TilemapManagerError::HistoryDoesNotExist)
+        ));
+        world
+            .entity_mut(map_entity)
+            .insert(TileHistory::<u8>::new(10));
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.sets_tile_data(1, Cell::new(0, 0)).unwrap();
+        tilemap_manager.sets_tile_data(2, Cell::new(0, 0)).unwrap();
+        tilemap_manager
+            .fill_rect(Cell::new(1, 1), Cell::new(2, 2), 3)
+            .unwrap();
+        tilemap_manager.begin_history_group().unwrap();
+        tilemap_manager.sets_tile_data(4, Cell::new(3, 3)).unwrap();
+        tilemap_manager
+            .modify_tile_data(Cell::new(3, 0), |tile_data| *tile_data = 5)
+            .unwrap();
+        tilemap_manager.end_history_group().unwrap();
+        assert_eq!(tilemap_manager.history().unwrap().undo_len(), 4);
+
+        // The group is undone as a whole
+        assert!(tilemap_manager.undo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 0)).unwrap(), 0);
+
+        // The whole rectangle is one entry
+        assert!(tilemap_manager.undo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 2)).unwrap(), 0);
+        assert!(tilemap_manager.undo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 1);
+
+        assert!(tilemap_manager.redo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 2);
+        assert!(tilemap_manager.redo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 2)).unwrap(), 3);
+        assert_eq!(tilemap_manager.history().unwrap().redo_len(), 1);
+
+        // A new write drops what is left to redo
+        tilemap_manager.sets_tile_data(6, Cell::new(2, 0)).unwrap();
+        assert!(!tilemap_manager.redo().unwrap());
+        assert!(tilemap_manager.undo().unwrap());
+        assert!(tilemap_manager.undo().unwrap());
+        assert!(tilemap_manager.undo().unwrap());
+        assert!(tilemap_manager.undo().unwrap());
+        assert!(!tilemap_manager.undo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 0)).unwrap(), 0);
+    }
+    #[test]
+    fn test_manager_undo_removes_written_empty_cells() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_sparse_empty(4, 4),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        commands
+            .entity(map_entity)
+            .insert((TileHistory::<u8>::new(10), TileOverrides::<u8>::default()));
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(3, Cell::new(1, 1)).unwrap();
+        tilemap_manager.sets_tile_data(4, Cell::new(1, 1)).unwrap();
+        let version = tilemap_manager.map_version().unwrap();
+
+        assert!(tilemap_manager.undo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 3);
+        // The cell had no tile data before the first write
+        assert!(tilemap_manager.undo().unwrap());
+        assert!(matches!(
+            tilemap_manager.get_tile_data(Cell::new(1, 1)),
+            Err(TilemapManagerError::TileDataDoesNotExist)
+        ));
+        assert!(tilemap_manager.map_version().unwrap() > version);
+        assert!(tilemap_manager.export_overrides().unwrap().is_empty());
+        let chunk = tilemap_manager.get_chunk(ChunkPos::new(0, 0)).unwrap();
+        assert_eq!(
+            chunk.data[&MapLayers::Main.to_bits()]
+                .iter_tile_data()
+                .count(),
+            0
+        );
+
+        assert!(tilemap_manager.redo().unwrap());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 3);
+        assert_eq!(tilemap_manager.export_overrides().unwrap().len(), 1);
+    }
+}
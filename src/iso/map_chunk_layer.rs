@@ -0,0 +1,215 @@
+use crate::iso::map_data::IsoLayout;
+use crate::map::chunk::{
+    ChunkCell, ChunkLayer, ChunkLayerType, ChunkStoragePool, ChunkTemplates, CompactionReport,
+    LayerStorage, SerializationStats, TileEntityStorage,
+};
+use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkLayerData, SquareChunkSettings};
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::math::UVec2;
+use bevy::prelude::{Component, Entity};
+use std::hash::Hash;
+
+#[cfg(feature = "reflect")]
+use bevy::ecs::reflect::ReflectMapEntities;
+#[cfg(feature = "reflect")]
+use bevy::prelude::{Reflect, ReflectComponent};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Settings needed for an isometric chunk.
+#[derive(Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Hash))]
+pub struct IsoChunkSettings {
+    /// The maximum size that a chunk in the map can be
+    pub max_chunk_size: UVec2,
+    /// How the cells of the map are laid out on screen
+    pub layout: IsoLayout,
+}
+
+impl Default for IsoChunkSettings {
+    fn default() -> Self {
+        Self {
+            max_chunk_size: UVec2 { x: 10, y: 10 },
+            layout: IsoLayout::default(),
+        }
+    }
+}
+
+impl IsoChunkSettings {
+    /// Returns the settings of the square layer the isometric layer is stored in
+    fn square_settings(&self) -> SquareChunkSettings {
+        SquareChunkSettings {
+            max_chunk_size: self.max_chunk_size,
+        }
+    }
+}
+
+/// A struct that holds the chunk map data for the given layer.
+///
+/// Both isometric layouts index cells on a rectangular grid, only the projection onto the screen differs, so
+/// the tile data is stored the same way as a [`SquareChunkLayer`].
+#[derive(Clone, Component, Default, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        transparent,
+        bound(
+            serialize = "T: Serialize + PartialEq",
+            deserialize = "T: Deserialize<'de>"
+        )
+    )
+)]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Hash, MapEntities, Component))]
+pub struct IsoChunkLayer<T>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    square_layer: SquareChunkLayer<T>,
+}
+
+impl<T> MapEntities for IsoChunkLayer<T>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.square_layer.map_entities(entity_mapper);
+    }
+}
+
+impl<T> ChunkLayer<T> for IsoChunkLayer<T>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    type ChunkSettings = IsoChunkSettings;
+
+    fn into_chunk_cell(
+        cell: lettuces::cell::Cell,
+        chunk_settings: &Self::ChunkSettings,
+    ) -> ChunkCell {
+        SquareChunkLayer::<T>::into_chunk_cell(cell, &chunk_settings.square_settings())
+    }
+
+    fn new(
+        layer_type: ChunkLayerType<T>,
+        chunk_dimensions: UVec2,
+        map_settings: &Self::ChunkSettings,
+    ) -> Self {
+        Self {
+            square_layer: SquareChunkLayer::new(
+                layer_type,
+                chunk_dimensions,
+                &map_settings.square_settings(),
+            ),
+        }
+    }
+
+    fn new_pooled(
+        layer_type: ChunkLayerType<T>,
+        chunk_dimensions: UVec2,
+        map_settings: &Self::ChunkSettings,
+        pool: &ChunkStoragePool<T>,
+    ) -> Self
+    where
+        T: 'static,
+    {
+        Self {
+            square_layer: SquareChunkLayer::new_pooled(
+                layer_type,
+                chunk_dimensions,
+                &map_settings.square_settings(),
+                pool,
+            ),
+        }
+    }
+
+    fn get_chunk_dimensions(&self) -> UVec2 {
+        self.square_layer.get_chunk_dimensions()
+    }
+
+    fn get_tile_data_mut(&mut self, chunk_tile_pos: ChunkCell) -> Option<&mut T> {
+        self.square_layer.get_tile_data_mut(chunk_tile_pos)
+    }
+
+    fn get_tile_data(&self, chunk_tile_pos: ChunkCell) -> Option<&T> {
+        self.square_layer.get_tile_data(chunk_tile_pos)
+    }
+
+    fn set_tile_data(&mut self, chunk_tile_pos: ChunkCell, tile_data: T) {
+        self.square_layer.set_tile_data(chunk_tile_pos, tile_data);
+    }
+
+    fn get_tile_entity(&self, chunk_tile_pos: ChunkCell) -> Option<Entity> {
+        self.square_layer.get_tile_entity(chunk_tile_pos)
+    }
+
+    fn set_tile_entity(&mut self, chunk_tile_pos: ChunkCell, entity: Entity) {
+        self.square_layer.set_tile_entity(chunk_tile_pos, entity);
+    }
+
+    fn remove_tile_entity(&mut self, chunk_tile_pos: ChunkCell) -> Option<Entity> {
+        self.square_layer.remove_tile_entity(chunk_tile_pos)
+    }
+
+    fn remove_tile_data(&mut self, chunk_tile_pos: ChunkCell) -> Option<T> {
+        self.square_layer.remove_tile_data(chunk_tile_pos)
+    }
+
+    fn iter_tile_data(&self) -> Box<dyn Iterator<Item = (ChunkCell, &T)> + '_> {
+        self.square_layer.iter_tile_data()
+    }
+
+    fn iter_tile_entities(&self) -> Box<dyn Iterator<Item = (ChunkCell, Entity)> + '_> {
+        self.square_layer.iter_tile_entities()
+    }
+
+    fn set_tile_entity_storage(&mut self, storage: TileEntityStorage) {
+        self.square_layer.set_tile_entity_storage(storage);
+    }
+
+    fn is_sparse(&self) -> bool {
+        self.square_layer.is_sparse()
+    }
+
+    fn set_layer_storage(&mut self, storage: LayerStorage, is_empty: &dyn Fn(&T) -> bool) -> bool {
+        self.square_layer.set_layer_storage(storage, is_empty)
+    }
+
+    fn compact(&mut self, is_empty: &dyn Fn(&T) -> bool, max_dense_fill: f32) -> CompactionReport {
+        self.square_layer.compact(is_empty, max_dense_fill)
+    }
+
+    fn share_templates(&mut self, templates: &mut ChunkTemplates<T>) {
+        self.square_layer.share_templates(templates);
+    }
+
+    fn release_storage(&mut self, pool: &ChunkStoragePool<T>)
+    where
+        T: 'static,
+    {
+        self.square_layer.release_storage(pool);
+    }
+
+    fn serialization_stats(&self, is_default: &dyn Fn(&T) -> bool) -> SerializationStats {
+        self.square_layer.serialization_stats(is_default)
+    }
+}
+
+impl<T> IsoChunkLayer<T>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    /// Returns the approximate amount of heap memory used by the layer in bytes, including unused capacity
+    pub fn heap_size(&self) -> usize {
+        self.square_layer.heap_size()
+    }
+
+    /// Returns the data of the layer
+    pub fn layer_data(&self) -> &SquareChunkLayerData<T> {
+        self.square_layer.layer_data()
+    }
+}
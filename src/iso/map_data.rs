@@ -0,0 +1,513 @@
+use bevy::{
+    math::{UVec2, Vec2},
+    prelude::{Component, Entity},
+    utils::hashbrown::HashMap,
+};
+use lettuces::cell::Cell;
+
+#[cfg(feature = "reflect")]
+use bevy::prelude::Reflect;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::map::{
+    chunk::{Chunk, ChunkLayer, ChunkPos},
+    square_disk, square_line, square_supercover_line, Adjacency, MapData, MapLayer,
+};
+use crate::square::map_data::SquareMapData;
+use crate::tilemap_builder::tilemap_layer_builder::DenseTileSource;
+
+/// Converts a cell of a staggered map into the cell at the same place on screen of a diamond map
+fn staggered_to_diamond(cell: Cell) -> Cell {
+    let shifted_x = cell.x * 2 + cell.y.rem_euclid(2);
+    Cell::new((shifted_x + cell.y) / 2, (cell.y - shifted_x) / 2)
+}
+
+/// Converts a cell of a diamond map into the cell at the same place on screen of a staggered map
+fn diamond_to_staggered(cell: Cell) -> Cell {
+    let y = cell.x + cell.y;
+    Cell::new((cell.x - cell.y - y.rem_euclid(2)) / 2, y)
+}
+
+/// How the cells of an isometric map are laid out on screen.
+///
+/// Both layouts use diamond shaped tiles that are `tile_size.x` wide and `tile_size.y` high.
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum IsoLayout {
+    /// The map is one large diamond. Cell (0, 0) is the bottom corner, x grows up and to the right and y
+    /// grows up and to the left
+    #[default]
+    Diamond,
+    /// The map is a rectangle made of rows of tiles. Every row is half a tile above the previous one and odd
+    /// rows are shifted right by half a tile so they fill the gaps between the tiles of even rows
+    Staggered,
+}
+
+impl IsoLayout {
+    /// Returns the position of the center of the given cell relative to the center of cell (0, 0)
+    pub fn cell_to_world(&self, cell: Cell, tile_size: Vec2) -> Vec2 {
+        let half_tile = tile_size / 2.0;
+        match self {
+            IsoLayout::Diamond => Vec2::new(
+                (cell.x - cell.y) as f32 * half_tile.x,
+                (cell.x + cell.y) as f32 * half_tile.y,
+            ),
+            IsoLayout::Staggered => Vec2::new(
+                (cell.x as f32 + cell.y.rem_euclid(2) as f32 * 0.5) * tile_size.x,
+                cell.y as f32 * half_tile.y,
+            ),
+        }
+    }
+
+    /// Returns the neighbours of the cell as they appear on screen.
+    ///
+    /// Diamond maps are square grids turned on their side. On staggered maps the tiles sharing an edge with a
+    /// cell are in the rows above and below it and the tiles only sharing a corner are the ones next to it and
+    /// two rows above and below it.
+    pub fn neighbors(&self, cell: Cell, adjacency: Adjacency) -> Vec<Cell> {
+        match self {
+            IsoLayout::Diamond => adjacency.square_neighbors(cell),
+            IsoLayout::Staggered => {
+                // Odd rows are shifted right, so the tiles below and above an even row start one column left
+                let left = cell.x - 1 + cell.y.rem_euclid(2);
+                let mut neighbors = vec![
+                    Cell::new(left, cell.y + 1),
+                    Cell::new(left + 1, cell.y + 1),
+                    Cell::new(left, cell.y - 1),
+                    Cell::new(left + 1, cell.y - 1),
+                ];
+                if adjacency == Adjacency::EdgesAndCorners {
+                    neighbors.extend([
+                        Cell::new(cell.x + 1, cell.y),
+                        Cell::new(cell.x - 1, cell.y),
+                        Cell::new(cell.x, cell.y + 2),
+                        Cell::new(cell.x, cell.y - 2),
+                    ]);
+                }
+                neighbors
+            }
+        }
+    }
+
+    /// Returns the cells of a line from a to b as it appears on screen, including both ends
+    pub fn line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        match self {
+            IsoLayout::Diamond => square_line(a, b),
+            IsoLayout::Staggered => square_line(staggered_to_diamond(a), staggered_to_diamond(b))
+                .into_iter()
+                .map(diamond_to_staggered)
+                .collect(),
+        }
+    }
+
+    /// Returns every cell a line of sight from a to b passes through as it appears on screen, including both
+    /// ends. See [`square_supercover_line`]
+    pub fn sight_line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        match self {
+            IsoLayout::Diamond => square_supercover_line(a, b),
+            IsoLayout::Staggered => {
+                square_supercover_line(staggered_to_diamond(a), staggered_to_diamond(b))
+                    .into_iter()
+                    .map(diamond_to_staggered)
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the cells within the given radius of the center as they appear on screen. See [`square_disk`]
+    pub fn cells_in_radius(&self, center: Cell, radius: u32) -> Vec<Cell> {
+        match self {
+            IsoLayout::Diamond => square_disk(center, radius),
+            IsoLayout::Staggered => square_disk(staggered_to_diamond(center), radius)
+                .into_iter()
+                .map(diamond_to_staggered)
+                .collect(),
+        }
+    }
+
+    /// Returns the cell whose tile contains the given position, relative to the center of cell (0, 0)
+    pub fn world_to_cell(&self, position: Vec2, tile_size: Vec2) -> Cell {
+        let half_tile = tile_size / 2.0;
+        match self {
+            IsoLayout::Diamond => {
+                let right = position.x / half_tile.x;
+                let up = position.y / half_tile.y;
+                Cell::new(
+                    ((up + right) / 2.0).round() as i32,
+                    ((up - right) / 2.0).round() as i32,
+                )
+            }
+            IsoLayout::Staggered => {
+                // The tile containing the position is the one whose center is closest when measured in tile
+                // sized steps, which is either in the nearest row or one of its neighbours
+                let row = (position.y / half_tile.y).round() as i32;
+                (row - 1..=row + 1)
+                    .map(|y| {
+                        let shift = y.rem_euclid(2) as f32 * 0.5;
+                        Cell::new((position.x / tile_size.x - shift).round() as i32, y)
+                    })
+                    .min_by(|a, b| {
+                        let distance = |cell: &Cell| {
+                            let offset = (position - self.cell_to_world(*cell, tile_size)).abs();
+                            offset.x / half_tile.x + offset.y / half_tile.y
+                        };
+                        distance(a).total_cmp(&distance(b))
+                    })
+                    .expect("the range of rows is never empty")
+            }
+        }
+    }
+}
+
+/// An implementation of [`MapData`] for an isometric map.
+///
+/// Cells are chunked the same way as a [`SquareMapData`] map, the layout only affects how cells map to
+/// positions on screen.
+#[derive(Clone, Default, Hash, Component)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+#[cfg_attr(feature = "reflect", reflect(Hash))]
+pub struct IsoMapData {
+    /// The maximum size that a chunk can be in the map
+    pub max_chunk_size: UVec2,
+    /// How the cells of the map are laid out on screen
+    pub layout: IsoLayout,
+}
+
+impl IsoMapData {
+    /// Returns the square map data the chunking is delegated to
+    fn square_map_data(&self) -> SquareMapData {
+        SquareMapData {
+            max_chunk_size: self.max_chunk_size,
+        }
+    }
+
+    /// Returns the position of the center of the given cell relative to the center of cell (0, 0). See
+    /// [`IsoLayout::cell_to_world`]
+    pub fn cell_to_world(&self, cell: Cell, tile_size: Vec2) -> Vec2 {
+        self.layout.cell_to_world(cell, tile_size)
+    }
+
+    /// Returns the cell whose tile contains the given position. See [`IsoLayout::world_to_cell`]
+    pub fn world_to_cell(&self, position: Vec2, tile_size: Vec2) -> Cell {
+        self.layout.world_to_cell(position, tile_size)
+    }
+}
+
+impl MapData for IsoMapData {
+    fn into_chunk_pos(&self, cell: Cell) -> ChunkPos {
+        self.square_map_data().into_chunk_pos(cell)
+    }
+
+    fn max_chunk_size(&self) -> UVec2 {
+        self.max_chunk_size
+    }
+
+    fn neighbors(&self, cell: Cell, adjacency: Adjacency) -> Vec<Cell> {
+        self.layout.neighbors(cell, adjacency)
+    }
+
+    fn line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        self.layout.line(a, b)
+    }
+
+    fn sight_line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        self.layout.sight_line(a, b)
+    }
+
+    fn cells_in_radius(&self, center: Cell, radius: u32) -> Vec<Cell> {
+        self.layout.cells_in_radius(center, radius)
+    }
+
+    fn break_data_vecs_down_into_chunk_data<TileData, Source>(
+        &self,
+        data: &Source,
+        chunk_pos: ChunkPos,
+        max_chunk_size: UVec2,
+    ) -> Vec<Vec<TileData>>
+    where
+        TileData: Clone + Copy + Sized + Default + Send + Sync + 'static,
+        Source: DenseTileSource<TileData> + ?Sized,
+    {
+        self.square_map_data()
+            .break_data_vecs_down_into_chunk_data(data, chunk_pos, max_chunk_size)
+    }
+
+    fn break_data_vecs_into_chunks<TileData, MapChunk, Source>(
+        &self,
+        data: &Source,
+        max_chunk_size: UVec2,
+        chunk_settings: MapChunk::ChunkSettings,
+    ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
+    where
+        TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Source: DenseTileSource<TileData> + ?Sized,
+    {
+        self.square_map_data()
+            .break_data_vecs_into_chunks(data, max_chunk_size, chunk_settings)
+    }
+
+    fn break_hashmap_into_chunks<TileData, MapChunk>(
+        &self,
+        map_layer: impl MapLayer,
+        data: &HashMap<Cell, TileData>,
+        map_size: UVec2,
+        max_chunk_size: UVec2,
+        chunk_settings: MapChunk::ChunkSettings,
+    ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
+    where
+        TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    {
+        self.square_map_data().break_hashmap_into_chunks(
+            map_layer,
+            data,
+            map_size,
+            max_chunk_size,
+            chunk_settings,
+        )
+    }
+
+    fn add_entities_to_layer<TileData, MapChunk>(
+        &self,
+        map_layer: u64,
+        chunks: &mut Vec<Vec<Chunk<MapChunk, TileData>>>,
+        entities: &HashMap<Cell, Entity>,
+    ) where
+        TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    {
+        self.square_map_data()
+            .add_entities_to_layer(map_layer, chunks, entities);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diamond_to_staggered, staggered_to_diamond, IsoLayout, IsoMapData};
+    use crate as bevy_sparse_tilemap;
+    use crate::iso::map_chunk_layer::IsoChunkSettings;
+    use crate::iso::{IsoTilemapBuilder, IsoTilemapManager};
+    use crate::map::Adjacency;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{IVec2, UVec2, Vec2};
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+    use proptest::prelude::*;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_iso_layout_projection() {
+        let tile_size = Vec2::new(64.0, 32.0);
+
+        let diamond = IsoLayout::Diamond;
+        assert_eq!(
+            diamond.cell_to_world(Cell::new(1, 0), tile_size),
+            Vec2::new(32.0, 16.0)
+        );
+        assert_eq!(
+            diamond.cell_to_world(Cell::new(0, 1), tile_size),
+            Vec2::new(-32.0, 16.0)
+        );
+        assert_eq!(
+            diamond.cell_to_world(Cell::new(2, 2), tile_size),
+            Vec2::new(0.0, 64.0)
+        );
+
+        let staggered = IsoLayout::Staggered;
+        assert_eq!(
+            staggered.cell_to_world(Cell::new(1, 0), tile_size),
+            Vec2::new(64.0, 0.0)
+        );
+        assert_eq!(
+            staggered.cell_to_world(Cell::new(0, 1), tile_size),
+            Vec2::new(32.0, 16.0)
+        );
+        assert_eq!(
+            staggered.cell_to_world(Cell::new(0, 2), tile_size),
+            Vec2::new(0.0, 32.0)
+        );
+
+        for layout in [diamond, staggered] {
+            for x in -4..4 {
+                for y in -4..4 {
+                    let cell = Cell::new(x, y);
+                    let center = layout.cell_to_world(cell, tile_size);
+                    assert_eq!(layout.world_to_cell(center, tile_size), cell);
+                    // Points inside the diamond of the tile belong to it
+                    for offset in [
+                        Vec2::new(30.0, 0.0),
+                        Vec2::new(-30.0, 0.0),
+                        Vec2::new(0.0, 14.0),
+                        Vec2::new(0.0, -14.0),
+                        Vec2::new(15.0, 7.0),
+                    ] {
+                        assert_eq!(layout.world_to_cell(center + offset, tile_size), cell);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_iso_layout_shapes() {
+        for x in -4..4 {
+            for y in -4..4 {
+                let cell = Cell::new(x, y);
+                assert_eq!(diamond_to_staggered(staggered_to_diamond(cell)), cell);
+            }
+        }
+
+        let staggered = IsoLayout::Staggered;
+        let line = staggered.line(Cell::new(0, 0), Cell::new(3, 5));
+        assert_eq!(line.first(), Some(&Cell::new(0, 0)));
+        assert_eq!(line.last(), Some(&Cell::new(3, 5)));
+        for pair in line.windows(2) {
+            assert!(staggered
+                .neighbors(pair[0], Adjacency::EdgesAndCorners)
+                .contains(&pair[1]));
+        }
+        let sight_line = staggered.sight_line(Cell::new(0, 0), Cell::new(3, 5));
+        assert_eq!(sight_line.last(), Some(&Cell::new(3, 5)));
+        assert!(line.iter().all(|cell| sight_line.contains(cell)));
+        for pair in sight_line.windows(2) {
+            assert!(staggered
+                .neighbors(pair[0], Adjacency::EdgesAndCorners)
+                .contains(&pair[1]));
+        }
+
+        let center = Cell::new(2, 3);
+        let mut disk = staggered.cells_in_radius(center, 1);
+        let mut expected = staggered.neighbors(center, Adjacency::EdgesAndCorners);
+        expected.push(center);
+        disk.sort_by_key(|cell| (cell.x, cell.y));
+        expected.sort_by_key(|cell| (cell.x, cell.y));
+        assert_eq!(disk, expected);
+    }
+
+    #[test]
+    fn test_iso_neighbors() {
+        let tile_size = Vec2::new(64.0, 32.0);
+        for layout in [IsoLayout::Diamond, IsoLayout::Staggered] {
+            for cell in [Cell::new(2, 2), Cell::new(2, 3), Cell::new(-1, -1)] {
+                let center = layout.cell_to_world(cell, tile_size);
+                let offsets: Vec<Vec2> = layout
+                    .neighbors(cell, Adjacency::EdgesAndCorners)
+                    .into_iter()
+                    .map(|neighbor| (layout.cell_to_world(neighbor, tile_size) - center).abs())
+                    .collect();
+                assert_eq!(offsets.len(), 8);
+                // Tiles sharing an edge are half a tile away diagonally, tiles sharing a corner a full tile away
+                // horizontally or vertically
+                assert!(offsets[..4].iter().all(|offset| *offset == tile_size / 2.0));
+                assert!(offsets[4..]
+                    .iter()
+                    .all(|offset| *offset == Vec2::new(tile_size.x, 0.0)
+                        || *offset == Vec2::new(0.0, tile_size.y)));
+                assert_eq!(layout.neighbors(cell, Adjacency::Edges).len(), 4);
+            }
+        }
+    }
+
+    #[test]
+    fn test_iso_tilemap() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, IsoTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = IsoTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 5]; 5]),
+            IsoMapData {
+                max_chunk_size: UVec2::new(2, 2),
+                layout: IsoLayout::Staggered,
+            },
+            IsoChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+                layout: IsoLayout::Staggered,
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 4)).unwrap(), 1);
+        tilemap_manager
+            .sets_tile_data(7, Cell::new(3, 2))
+            .expect("cell is in the map");
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 2)).unwrap(), 7);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 3)).unwrap(), 1);
+        assert!(tilemap_manager.get_tile_data(Cell::new(5, 0)).is_err());
+    }
+
+    /// A map size, a chunk size, a layout, and a cell that may lie outside of the map
+    fn map_and_cell() -> impl Strategy<Value = (UVec2, UVec2, IsoLayout, IVec2)> {
+        (1..40u32, 1..40u32, 1..12u32, 1..12u32, any::<bool>()).prop_flat_map(
+            |(w, h, cw, ch, diamond)| {
+                (
+                    Just(UVec2::new(w, h)),
+                    Just(UVec2::new(cw, ch)),
+                    Just(match diamond {
+                        true => IsoLayout::Diamond,
+                        false => IsoLayout::Staggered,
+                    }),
+                    (-5..w as i32 + 5, -5..h as i32 + 5).prop_map(|(x, y)| IVec2::new(x, y)),
+                )
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_manager_access_never_panics((map_size, chunk_size, layout, cell) in map_and_cell(), tile_data: u8) {
+            let mut world = World::new();
+            let mut system_state: SystemState<(Commands, IsoTilemapManager<u8, MapLayers>)> =
+                SystemState::new(&mut world);
+            let (mut commands, _) = system_state.get_mut(&mut world);
+            let rows = (0..map_size.y)
+                .map(|y| (0..map_size.x).map(|x| (x + y * map_size.x) as u8).collect())
+                .collect();
+            let map_entity = IsoTilemapBuilder::<u8, MapLayers>::new(
+                TilemapLayer::new_dense_from_vecs(rows),
+                IsoMapData { max_chunk_size: chunk_size, layout },
+                IsoChunkSettings { max_chunk_size: chunk_size, layout },
+            )
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+            system_state.apply(&mut world);
+            let entity = world.spawn_empty().id();
+
+            let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+            tilemap_manager.set_tilemap_entity(map_entity);
+
+            let in_bounds = cell.cmpge(IVec2::ZERO).all() && cell.cmplt(map_size.as_ivec2()).all();
+            let cell = Cell::new(cell.x, cell.y);
+            let expected = in_bounds.then(|| (cell.x as u32 + cell.y as u32 * map_size.x) as u8);
+            prop_assert_eq!(tilemap_manager.get_tile_data(cell).ok(), expected);
+            prop_assert_eq!(tilemap_manager.sets_tile_data(tile_data, cell).is_ok(), in_bounds);
+
+            let spawned = tilemap_manager.get_or_spawn_tile_entity(cell).ok();
+            prop_assert_eq!(spawned.is_some(), in_bounds);
+            prop_assert_eq!(tilemap_manager.get_tile_entity(cell).ok(), spawned);
+            prop_assert_eq!(tilemap_manager.set_tile_entity(cell, entity).is_ok(), in_bounds);
+            let origin = Cell::new(0, 0);
+            prop_assert_eq!(
+                tilemap_manager.move_tile_entity(cell, origin).ok(),
+                (in_bounds && cell != origin).then_some(entity)
+            );
+            prop_assert_eq!(tilemap_manager.despawn_tile_entity(cell).is_ok(), in_bounds);
+        }
+    }
+}
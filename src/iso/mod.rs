@@ -0,0 +1,20 @@
+use map_chunk_layer::IsoChunkLayer;
+use map_data::IsoMapData;
+
+use crate::{map::chunk::Chunk, tilemap_builder::TilemapBuilder, tilemap_manager::TilemapManager};
+
+/// Implements [`ChunkLayer`](crate::map::chunk::ChunkLayer) for an isometric map type
+pub mod map_chunk_layer;
+/// Implements [`MapData`](crate::map::MapData) for an isometric map type
+pub mod map_data;
+
+/// Type alias for [`TilemapManager`] for the built in isometric map types.
+pub type IsoTilemapManager<'w, 's, TileData, MapLayers> =
+    TilemapManager<'w, 's, TileData, MapLayers, IsoChunkLayer<TileData>, IsoMapData>;
+
+/// Type alias for [`Chunk`] using the built in [`IsoChunkLayer`]
+pub type IsoChunk<TileData> = Chunk<IsoChunkLayer<TileData>, TileData>;
+
+/// Type alias for [`TilemapBuilder`] for the built in isometric map types
+pub type IsoTilemapBuilder<TileData, MapLayers> =
+    TilemapBuilder<TileData, MapLayers, IsoChunkLayer<TileData>, IsoMapData>;
@@ -1,3 +1,4 @@
+#![forbid(unsafe_code)]
 #![deny(
     missing_docs,
     trivial_casts,
@@ -98,7 +99,7 @@
 //! # pub struct MapEntity(Entity);
 //!
 //!  fn access(mut tilemap_manager: TilemapManager<TileData, MapLayers, SquareChunkLayer<TileData>, SquareMapData>, mut commands: Commands, map_entity: Res<MapEntity>) {
-//!     // We have to set the TilemapManager to the desired tilemap
+//!     // We have to set the TilemapManager to the desired tilemap unless an ActiveTilemap resource is inserted
 //!     tilemap_manager.set_tilemap_entity(map_entity.0);
 //!     // And set the manager to whatever layer we want to affect. Defaults to the default layer of the enum
 //!     tilemap_manager.set_layer(MapLayers::Main);
@@ -110,18 +111,100 @@
 //!
 //! ```
 //!
+//! ## Prelude and Stability
+//!
+//! The [`prelude`](crate::prelude) exports the builder, the manager, map layers, and cell types. Those are
+//! covered by semver and renamed items keep a deprecated alias for at least one minor release. Experimental
+//! subsystems are only compiled with the `unstable` feature and may change in any release.
+//!
 
+/// Animated tiles such as water and lava. Requires the `animation` feature. See [`TileAnimations`](crate::animation::TileAnimations) for more details
+#[cfg(feature = "animation")]
+pub mod animation;
+/// Time-sliced autosaving of changed chunks. Requires the `autosave` feature
+#[cfg(feature = "autosave")]
+pub mod autosave;
+/// Orthographic camera controls for viewing maps. Requires the `camera` feature. See [`TilemapCameraPlugin`](crate::camera::TilemapCameraPlugin) for more details
+#[cfg(feature = "camera")]
+pub mod camera;
+/// Cells scheduled to tick at a future time, such as growing crops. Requires the `unstable` feature. See [`CellScheduler`](crate::cell_scheduler::CellScheduler) for more details
+#[cfg(feature = "unstable")]
+pub mod cell_scheduler;
+/// Targeted change notifications for observers watching specific cells. Requires the `unstable` feature. See [`CellWatchers`](crate::cell_watchers::CellWatchers) for more details
+#[cfg(feature = "unstable")]
+pub mod cell_watchers;
+/// Hiding chunks outside of the view of every camera. Requires the `culling` feature. See [`ChunkCullingPlugin`](crate::chunk_culling::ChunkCullingPlugin) for more details
+#[cfg(feature = "culling")]
+pub mod chunk_culling;
+/// Level-of-detail summaries of chunks for rendering maps from far away. See [`ChunkLodPlugin`](crate::chunk_lod::ChunkLodPlugin) for more details
+pub mod chunk_lod;
+/// Prioritized uploading of changed chunks to renderers. See [`ChunkSyncQueue`](crate::chunk_sync::ChunkSyncQueue) for more details
+pub mod chunk_sync;
+/// Layers computed from other layers whenever they change. Requires the `unstable` feature. See [`DerivedLayers`](crate::derived_layers::DerivedLayers) for more details
+#[cfg(feature = "unstable")]
+pub mod derived_layers;
+/// Per frame statistics about tilemap activity integrated with bevys diagnostics. Requires the `unstable` feature. See [`TilemapDiagnosticsPlugin`](crate::diagnostics::TilemapDiagnosticsPlugin) for more details
+#[cfg(feature = "unstable")]
+pub mod diagnostics;
+/// Importers for maps made in other editors. Requires the `tiled` or `ldtk` feature.
+#[cfg(any(feature = "tiled", feature = "ldtk"))]
+pub mod formats;
+/// Seeded procedural generators such as random walks that write into tilemap layers or live maps
+pub mod generation;
+/// Undo and redo of tile edits made through the manager. Requires the `history` feature. See [`TileHistory`](crate::history::TileHistory) for more details
+#[cfg(feature = "history")]
+pub mod history;
 /// Implements a hexagonal map type. See the [Hexagon Example](https://github.com/NoahShomette/bevy_sparse_tilemap/blob/main/examples/hexagon.rs) for an overview of how to use it
 #[cfg(feature = "hex")]
 pub mod hex;
+/// Implements an isometric map type with diamond and staggered layouts. See [`IsoLayout`](crate::iso::map_data::IsoLayout) for more details
+#[cfg(feature = "iso")]
+pub mod iso;
 pub mod map;
+/// The `.bstmap` file format used by the `bst-tool` binary. Requires the `tool` feature
+#[cfg(feature = "tool")]
+pub mod map_file;
+/// Objects such as buildings covering several cells of a map. See [`TileObjects`](crate::objects::TileObjects) for more details
+pub mod objects;
+/// A*, Dijkstra, and hierarchical pathfinding over map layers. Requires the `pathfinding` feature. See [`find_path`](crate::pathfinding::find_path) for more details
+#[cfg(feature = "pathfinding")]
+pub mod pathfinding;
+/// Saving whole maps to files and loading them back. Requires the `persistence` feature. See [`save_tilemap`](crate::persistence::save_tilemap) for more details
+#[cfg(feature = "persistence")]
+pub mod persistence;
+/// The core types needed to build and access tilemaps, covered by semver. See the module docs for the stability policy
+pub mod prelude;
+/// Capture and replay of tile edit streams for reproducing bugs. Requires the `recording` feature
+#[cfg(feature = "recording")]
+pub mod recording;
+/// Registration of every reflected map type. Requires the `reflect` feature. See [`register_square_map_types`](crate::registration::register_square_map_types) for more details
+#[cfg(feature = "reflect")]
+pub mod registration;
+/// Restricting expensive simulation to the chunks near players and AI. See [`SimulationGatingPlugin`](crate::simulation::SimulationGatingPlugin) for more details
+pub mod simulation;
 /// Implements a square map type. See the [Square Example](https://github.com/NoahShomette/bevy_sparse_tilemap/blob/main/examples/square.rs) for an overview of how to use it
 #[cfg(feature = "square")]
 pub mod square;
+/// Despawning far away chunks and respawning them around loaders. See [`ChunkStreamingPlugin`](crate::streaming::ChunkStreamingPlugin) for more details
+pub mod streaming;
+/// Snapshot helpers used to regression test maps against checked in files. Requires the `testing` feature
+#[cfg(feature = "testing")]
+pub mod testing;
+/// Saving and restoring the components of tile entities when chunks are despawned. Requires the `tile_archetypes` feature
+#[cfg(feature = "tile_archetypes")]
+pub mod tile_archetypes;
+/// Map wide and regional color tints for day-night cycles and weather. Requires the `unstable` feature. See [`GlobalTint`](crate::tint::GlobalTint) for more details
+#[cfg(feature = "unstable")]
+pub mod tint;
 /// A helper used to construct new tilemaps. See [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder) for more details
 pub mod tilemap_builder;
 /// A system param used to interact with tilemaps. See [`TilemapManager`](crate::tilemap_manager::TilemapManager) for more details
 pub mod tilemap_manager;
+/// Unit occupancy and movement ranges for turn based games. Requires the `pathfinding` feature. See [`movement_range`](crate::turn_based::movement_range) for more details
+#[cfg(feature = "pathfinding")]
+pub mod turn_based;
+/// Declarative invariants over map layers checked on demand or after writes. See [`MapValidator`](crate::validation::MapValidator) for more details
+pub mod validation;
 
 pub use bst_map_layer_derive::MapLayer;
 /// Re-export [Lettuces](https://crates.io/crates/lettuces)
@@ -0,0 +1,42 @@
+use bevy::math::IVec2;
+use lettuces::cell::Cell;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The offsets of the neighbours sharing an edge with a cell of a square grid
+pub const SQUARE_EDGE_NEIGHBORS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// The offsets of the neighbours only sharing a corner with a cell of a square grid
+pub const SQUARE_CORNER_NEIGHBORS: [IVec2; 4] = [
+    IVec2::new(1, 1),
+    IVec2::new(-1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, -1),
+];
+
+/// Which cells count as the neighbours of a cell. See [`MapData::neighbors`](super::MapData::neighbors)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Adjacency {
+    /// Only cells sharing an edge with the cell. 4 on square maps and 6 on hexagonal maps
+    #[default]
+    Edges,
+    /// Cells sharing an edge or a corner with the cell. 8 on square maps. Hexagons only touch at their edges
+    /// so hexagonal maps return the same 6 cells as [`Adjacency::Edges`]
+    EdgesAndCorners,
+}
+
+impl Adjacency {
+    /// Returns the neighbours of the cell on a square grid
+    pub fn square_neighbors(&self, cell: Cell) -> Vec<Cell> {
+        let corners = match self {
+            Adjacency::Edges => &[][..],
+            Adjacency::EdgesAndCorners => &SQUARE_CORNER_NEIGHBORS[..],
+        };
+        SQUARE_EDGE_NEIGHBORS
+            .iter()
+            .chain(corners)
+            .map(|offset| Cell::new(cell.x + offset.x, cell.y + offset.y))
+            .collect()
+    }
+}
@@ -0,0 +1,246 @@
+use bevy::math::{IRect, IVec2, UVec2};
+use lettuces::cell::Cell;
+
+/// The amount of bits in a word of a [`CellMask`]
+const WORD_BITS: u32 = u64::BITS;
+
+/// A dense boolean mask over the cells of a map, such as explored, walkable, or occupied cells.
+///
+/// The mask is split into chunks and every row of a chunk is packed into 64 bit words, so set operations
+/// between masks run over whole words and counting uses hardware popcounts. Build masks from map layers with
+/// [`TilemapManager::layer_mask`](crate::tilemap_manager::TilemapManager::layer_mask) and combine them to
+/// answer queries like "explored AND walkable AND NOT occupied" over large areas:
+///
+/// ```ignore
+/// let targets = explored.intersect(&walkable).difference(&occupied);
+/// let visible_targets = targets.count_ones_in_rect(camera_rect);
+/// ```
+///
+/// Set operations require both masks to have the same dimensions and chunk size.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CellMask {
+    dimensions: UVec2,
+    chunk_size: UVec2,
+    chunk_counts: UVec2,
+    words_per_row: u32,
+    chunks: Vec<Vec<u64>>,
+}
+
+impl CellMask {
+    /// Creates a mask over a map with the given dimensions with no cells set. Use the max chunk size of the
+    /// map for the chunk size
+    pub fn new(dimensions: UVec2, chunk_size: UVec2) -> Self {
+        let chunk_size = chunk_size.max(UVec2::ONE);
+        let chunk_counts = (dimensions + chunk_size - UVec2::ONE) / chunk_size;
+        let words_per_row = chunk_size.x.div_ceil(WORD_BITS);
+        Self {
+            dimensions,
+            chunk_size,
+            chunk_counts,
+            words_per_row,
+            chunks: vec![
+                vec![0; (words_per_row * chunk_size.y) as usize];
+                (chunk_counts.x * chunk_counts.y) as usize
+            ],
+        }
+    }
+
+    /// Returns the dimensions of the map the mask covers
+    pub fn dimensions(&self) -> UVec2 {
+        self.dimensions
+    }
+
+    /// Returns the chunk index, word index, and bit of the cell if it is inside of the mask
+    fn locate(&self, cell: Cell) -> Option<(usize, usize, u64)> {
+        if cell.x < 0
+            || cell.y < 0
+            || cell.x as u32 >= self.dimensions.x
+            || cell.y as u32 >= self.dimensions.y
+        {
+            return None;
+        }
+        let cell = UVec2::new(cell.x as u32, cell.y as u32);
+        let chunk = cell / self.chunk_size;
+        let local = cell % self.chunk_size;
+        Some((
+            (chunk.y * self.chunk_counts.x + chunk.x) as usize,
+            (local.y * self.words_per_row + local.x / WORD_BITS) as usize,
+            1 << (local.x % WORD_BITS),
+        ))
+    }
+
+    /// Returns true if the cell is set. Cells outside of the mask are never set
+    pub fn get(&self, cell: Cell) -> bool {
+        self.locate(cell)
+            .is_some_and(|(chunk, word, bit)| self.chunks[chunk][word] & bit != 0)
+    }
+
+    /// Sets or clears the cell. Returns false if the cell is outside of the mask
+    pub fn set(&mut self, cell: Cell, value: bool) -> bool {
+        let Some((chunk, word, bit)) = self.locate(cell) else {
+            return false;
+        };
+        match value {
+            true => self.chunks[chunk][word] |= bit,
+            false => self.chunks[chunk][word] &= !bit,
+        }
+        true
+    }
+
+    /// Combines every word of both masks into a new mask with the given operation
+    fn combine(&self, other: &CellMask, op: impl Fn(u64, u64) -> u64) -> CellMask {
+        assert_eq!(
+            (self.dimensions, self.chunk_size),
+            (other.dimensions, other.chunk_size),
+            "Masks must have the same dimensions and chunk size"
+        );
+        let mut result = self.clone();
+        for (chunk, other_chunk) in result.chunks.iter_mut().zip(other.chunks.iter()) {
+            for (word, other_word) in chunk.iter_mut().zip(other_chunk.iter()) {
+                *word = op(*word, *other_word);
+            }
+        }
+        result
+    }
+
+    /// Returns a mask of the cells set in either mask
+    pub fn union(&self, other: &CellMask) -> CellMask {
+        self.combine(other, |a, b| a | b)
+    }
+
+    /// Returns a mask of the cells set in both masks
+    pub fn intersect(&self, other: &CellMask) -> CellMask {
+        self.combine(other, |a, b| a & b)
+    }
+
+    /// Returns a mask of the cells set in this mask but not in the other
+    pub fn difference(&self, other: &CellMask) -> CellMask {
+        self.combine(other, |a, b| a & !b)
+    }
+
+    /// Returns the amount of set cells
+    pub fn count_ones(&self) -> u32 {
+        self.chunks
+            .iter()
+            .flatten()
+            .map(|word| word.count_ones())
+            .sum()
+    }
+
+    /// Returns the amount of set cells in the rect, both corners included. Parts of the rect outside of the
+    /// mask are ignored
+    pub fn count_ones_in_rect(&self, rect: IRect) -> u32 {
+        let min = rect.min.max(IVec2::ZERO);
+        let max = rect.max.min(self.dimensions.as_ivec2() - IVec2::ONE);
+        if min.cmpgt(max).any() {
+            return 0;
+        }
+        let (min, max) = (min.as_uvec2(), max.as_uvec2());
+        let mut count = 0;
+        for chunk_y in min.y / self.chunk_size.y..=max.y / self.chunk_size.y {
+            for chunk_x in min.x / self.chunk_size.x..=max.x / self.chunk_size.x {
+                let origin = UVec2::new(chunk_x, chunk_y) * self.chunk_size;
+                let local_min = min.max(origin) - origin;
+                let local_max = max.min(origin + self.chunk_size - UVec2::ONE) - origin;
+                let chunk = &self.chunks[(chunk_y * self.chunk_counts.x + chunk_x) as usize];
+                for y in local_min.y..=local_max.y {
+                    let row =
+                        &chunk[(y * self.words_per_row) as usize..][..self.words_per_row as usize];
+                    count += count_row_range(row, local_min.x, local_max.x);
+                }
+            }
+        }
+        count
+    }
+
+    /// Iterates over every set cell, chunk by chunk
+    pub fn iter_ones(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.chunks
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, chunk)| {
+                let index = index as u32;
+                let origin = UVec2::new(index % self.chunk_counts.x, index / self.chunk_counts.x)
+                    * self.chunk_size;
+                chunk
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, word)| **word != 0)
+                    .flat_map(move |(word_index, word)| {
+                        let word_index = word_index as u32;
+                        let y = word_index / self.words_per_row;
+                        let x = (word_index % self.words_per_row) * WORD_BITS;
+                        (0..WORD_BITS)
+                            .filter(move |bit| word & (1 << bit) != 0)
+                            .map(move |bit| {
+                                Cell::new((origin.x + x + bit) as i32, (origin.y + y) as i32)
+                            })
+                    })
+            })
+    }
+}
+
+/// Counts the set bits between the `min` and `max` bit of a row of words, both included
+fn count_row_range(row: &[u64], min: u32, max: u32) -> u32 {
+    let mut count = 0;
+    for word_index in min / WORD_BITS..=max / WORD_BITS {
+        let first = word_index * WORD_BITS;
+        let low = min.max(first) - first;
+        let high = max.min(first + WORD_BITS - 1) - first;
+        // Bits low..=high set
+        let mask = (u64::MAX >> (WORD_BITS - 1 - high)) & (u64::MAX << low);
+        count += (row[word_index as usize] & mask).count_ones();
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CellMask;
+    use bevy::math::{IRect, UVec2};
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_mask_operations() {
+        let dimensions = UVec2::new(150, 90);
+        let chunk_size = UVec2::new(70, 40);
+        let mut explored = CellMask::new(dimensions, chunk_size);
+        let mut walkable = CellMask::new(dimensions, chunk_size);
+        let mut occupied = CellMask::new(dimensions, chunk_size);
+        for y in 0..90 {
+            for x in 0..150 {
+                let cell = Cell::new(x, y);
+                explored.set(cell, x < 100);
+                walkable.set(cell, (x + y) % 2 == 0);
+                occupied.set(cell, x % 10 == 0);
+            }
+        }
+        assert!(!explored.set(Cell::new(150, 0), true));
+        assert!(!explored.get(Cell::new(-1, 0)));
+
+        let targets = explored.intersect(&walkable).difference(&occupied);
+        let expected = |rect: IRect| {
+            let mut count = 0;
+            for y in rect.min.y.max(0)..=rect.max.y.min(89) {
+                for x in rect.min.x.max(0)..=rect.max.x.min(149) {
+                    if x < 100 && (x + y) % 2 == 0 && x % 10 != 0 {
+                        count += 1;
+                    }
+                }
+            }
+            count
+        };
+        assert_eq!(targets.count_ones(), expected(IRect::new(0, 0, 149, 89)));
+        for rect in [
+            IRect::new(0, 0, 0, 0),
+            IRect::new(63, 5, 65, 50),
+            IRect::new(60, 30, 140, 45),
+            IRect::new(-20, -20, 400, 400),
+        ] {
+            assert_eq!(targets.count_ones_in_rect(rect), expected(rect));
+        }
+        assert_eq!(targets.iter_ones().count() as u32, targets.count_ones());
+        assert!(targets.iter_ones().all(|cell| targets.get(cell)));
+        assert_eq!(explored.union(&occupied).count_ones(), 100 * 90 + 5 * 90);
+    }
+}
@@ -1,8 +1,12 @@
-use bevy::{math::IVec2, prelude::Component};
+use bevy::{
+    math::{IVec2, UVec2},
+    prelude::Component,
+};
 use lettuces::cell::Cell;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
 
 #[cfg(feature = "reflect")]
 use bevy::ecs::reflect::ReflectMapEntities;
@@ -31,6 +35,25 @@ impl ChunkCell {
     pub fn y(&self) -> i32 {
         self.0.y
     }
+
+    /// Returns Self as an [`IVec2`]
+    pub fn as_ivec2(&self) -> IVec2 {
+        IVec2::new(self.0.x, self.0.y)
+    }
+
+    /// Returns true if Self is inside a chunk with the given dimensions
+    pub fn within(&self, dimensions: UVec2) -> bool {
+        self.0.x >= 0
+            && self.0.y >= 0
+            && (self.0.x as u32) < dimensions.x
+            && (self.0.y as u32) < dimensions.y
+    }
+
+    /// Iterates over every [`ChunkCell`] in a chunk with the given dimensions, row by row starting at (0, 0)
+    pub fn iter_chunk(dimensions: UVec2) -> impl Iterator<Item = ChunkCell> {
+        (0..dimensions.y as i32)
+            .flat_map(move |y| (0..dimensions.x as i32).map(move |x| ChunkCell::new(x, y)))
+    }
 }
 
 impl From<IVec2> for ChunkCell {
@@ -55,3 +78,39 @@ impl Display for ChunkCell {
         f.write_str(&*format!("x:{}, y:{}", self.0.x, self.0.y))
     }
 }
+
+impl Add<IVec2> for ChunkCell {
+    type Output = ChunkCell;
+
+    fn add(self, rhs: IVec2) -> Self::Output {
+        ChunkCell::new(self.0.x + rhs.x, self.0.y + rhs.y)
+    }
+}
+
+impl AddAssign<IVec2> for ChunkCell {
+    fn add_assign(&mut self, rhs: IVec2) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<IVec2> for ChunkCell {
+    type Output = ChunkCell;
+
+    fn sub(self, rhs: IVec2) -> Self::Output {
+        ChunkCell::new(self.0.x - rhs.x, self.0.y - rhs.y)
+    }
+}
+
+impl SubAssign<IVec2> for ChunkCell {
+    fn sub_assign(&mut self, rhs: IVec2) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub<ChunkCell> for ChunkCell {
+    type Output = IVec2;
+
+    fn sub(self, rhs: ChunkCell) -> Self::Output {
+        self.as_ivec2() - rhs.as_ivec2()
+    }
+}
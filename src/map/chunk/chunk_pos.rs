@@ -1,8 +1,12 @@
-use bevy::{math::IVec2, prelude::Component};
+use bevy::{
+    math::{IVec2, UVec2},
+    prelude::Component,
+};
 use lettuces::cell::Cell;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 use std::fmt::{Display, Formatter};
+use std::ops::{Add, AddAssign, Sub, SubAssign};
 
 #[cfg(feature = "reflect")]
 use bevy::ecs::reflect::ReflectMapEntities;
@@ -32,6 +36,25 @@ impl ChunkPos {
     pub fn y(&self) -> i32 {
         self.0.y
     }
+
+    /// Returns Self as an [`IVec2`]
+    pub fn as_ivec2(&self) -> IVec2 {
+        IVec2::new(self.0.x, self.0.y)
+    }
+
+    /// Returns true if Self is inside a map that is the given amount of chunks wide and tall
+    pub fn within(&self, chunk_counts: UVec2) -> bool {
+        self.0.x >= 0
+            && self.0.y >= 0
+            && (self.0.x as u32) < chunk_counts.x
+            && (self.0.y as u32) < chunk_counts.y
+    }
+
+    /// Iterates over every [`ChunkPos`] in the inclusive rectangle between min and max, row by row starting
+    /// at min
+    pub fn iter_rect(min: ChunkPos, max: ChunkPos) -> impl Iterator<Item = ChunkPos> {
+        (min.y()..=max.y()).flat_map(move |y| (min.x()..=max.x()).map(move |x| ChunkPos::new(x, y)))
+    }
 }
 
 impl From<IVec2> for ChunkPos {
@@ -56,3 +79,72 @@ impl Display for ChunkPos {
         f.write_str(&*format!("x:{}, y:{}", self.0.x, self.0.y))
     }
 }
+
+impl Add<IVec2> for ChunkPos {
+    type Output = ChunkPos;
+
+    fn add(self, rhs: IVec2) -> Self::Output {
+        ChunkPos::new(self.0.x + rhs.x, self.0.y + rhs.y)
+    }
+}
+
+impl AddAssign<IVec2> for ChunkPos {
+    fn add_assign(&mut self, rhs: IVec2) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub<IVec2> for ChunkPos {
+    type Output = ChunkPos;
+
+    fn sub(self, rhs: IVec2) -> Self::Output {
+        ChunkPos::new(self.0.x - rhs.x, self.0.y - rhs.y)
+    }
+}
+
+impl SubAssign<IVec2> for ChunkPos {
+    fn sub_assign(&mut self, rhs: IVec2) {
+        *self = *self - rhs;
+    }
+}
+
+impl Sub<ChunkPos> for ChunkPos {
+    type Output = IVec2;
+
+    fn sub(self, rhs: ChunkPos) -> Self::Output {
+        self.as_ivec2() - rhs.as_ivec2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkPos;
+    use crate::map::chunk::ChunkCell;
+    use bevy::math::{IVec2, UVec2};
+
+    #[test]
+    fn test_chunk_space_helpers() {
+        let mut chunk_pos = ChunkPos::new(1, 1) + IVec2::new(2, -1);
+        assert_eq!(chunk_pos, ChunkPos::new(3, 0));
+        chunk_pos -= IVec2::ONE;
+        assert_eq!(chunk_pos - ChunkPos::new(0, 0), IVec2::new(2, -1));
+        assert!(!chunk_pos.within(UVec2::new(4, 4)));
+
+        let rect: Vec<ChunkPos> =
+            ChunkPos::iter_rect(ChunkPos::new(1, 1), ChunkPos::new(2, 2)).collect();
+        assert_eq!(
+            rect,
+            vec![
+                ChunkPos::new(1, 1),
+                ChunkPos::new(2, 1),
+                ChunkPos::new(1, 2),
+                ChunkPos::new(2, 2)
+            ]
+        );
+
+        let dimensions = UVec2::new(3, 2);
+        assert_eq!(ChunkCell::iter_chunk(dimensions).count(), 6);
+        assert!(ChunkCell::iter_chunk(dimensions).all(|cell| cell.within(dimensions)));
+        assert!(!(ChunkCell::new(2, 1) + IVec2::X).within(dimensions));
+    }
+}
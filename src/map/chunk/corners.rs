@@ -0,0 +1,146 @@
+use bevy::math::{IVec2, UVec2};
+use bevy::prelude::Component;
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The corner of a cell on a square grid, used by dual grid layers.
+///
+/// Corner (x, y) is the bottom left corner of [`Cell`] (x, y). A map that is w x h cells has
+/// (w + 1) x (h + 1) corners and every corner is shared by up to four cells.
+#[derive(Default, Eq, Hash, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CornerId {
+    /// The x position of the corner
+    pub x: i32,
+    /// The y position of the corner
+    pub y: i32,
+}
+
+impl CornerId {
+    /// Offsets from a corner to each of the cells sharing it, in the same order as
+    /// [`CornerId::adjacent_cells`]
+    pub const ADJACENT_CELL_OFFSETS: [IVec2; 4] = [
+        IVec2::new(0, 0),
+        IVec2::new(-1, 0),
+        IVec2::new(0, -1),
+        IVec2::new(-1, -1),
+    ];
+
+    /// Constructs a new CornerId from the given x and y
+    pub fn new(x: i32, y: i32) -> CornerId {
+        Self { x, y }
+    }
+
+    /// Returns the four corners of the given [`Cell`] in the order bottom left, bottom right, top left, top
+    /// right
+    pub fn of_cell(cell: Cell) -> [CornerId; 4] {
+        [
+            CornerId::new(cell.x, cell.y),
+            CornerId::new(cell.x + 1, cell.y),
+            CornerId::new(cell.x, cell.y + 1),
+            CornerId::new(cell.x + 1, cell.y + 1),
+        ]
+    }
+
+    /// Returns the four [`Cell`]s sharing this corner. The first cell is the one whose bottom left corner
+    /// this is
+    pub fn adjacent_cells(&self) -> [Cell; 4] {
+        Self::ADJACENT_CELL_OFFSETS.map(|offset| Cell::new(self.x + offset.x, self.y + offset.y))
+    }
+
+    /// Returns Self as an [`IVec2`]
+    pub fn as_ivec2(&self) -> IVec2 {
+        IVec2::new(self.x, self.y)
+    }
+}
+
+/// The corner data of one layer of a chunk
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct CornerGrid<T> {
+    dimensions: UVec2,
+    data: Vec<T>,
+}
+
+impl<T> CornerGrid<T> {
+    fn index(&self, corner: UVec2) -> Option<usize> {
+        if corner.x >= self.dimensions.x || corner.y >= self.dimensions.y {
+            return None;
+        }
+        Some((corner.y * self.dimensions.x + corner.x) as usize)
+    }
+}
+
+/// Corner layers of a chunk. Lives on the chunk entity next to the [`Chunk`](crate::map::chunk::Chunk).
+///
+/// Each layer stores (w + 1) x (h + 1) corners for a chunk that is w x h cells, so corners on the edge of a
+/// chunk are stored in every chunk touching them. Use
+/// [`TilemapManager::set_corner_data`](crate::tilemap_manager::TilemapManager::set_corner_data) to write
+/// corners, which keeps the copies in every chunk in sync.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChunkCorners<T>
+where
+    T: Clone + Copy + Send + Sync + 'static,
+{
+    layers: HashMap<u64, CornerGrid<T>>,
+}
+
+impl<T> Default for ChunkCorners<T>
+where
+    T: Clone + Copy + Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            layers: HashMap::default(),
+        }
+    }
+}
+
+impl<T> ChunkCorners<T>
+where
+    T: Clone + Copy + Send + Sync + 'static,
+{
+    /// Adds a corner layer for a chunk with the given dimensions in cells, filling every corner with
+    /// `tile_data`. Replaces the layer if it already exists
+    pub fn add_layer(&mut self, map_layer: u64, chunk_dimensions: UVec2, tile_data: T) {
+        let dimensions = chunk_dimensions + UVec2::ONE;
+        self.layers.insert(
+            map_layer,
+            CornerGrid {
+                dimensions,
+                data: vec![tile_data; (dimensions.x * dimensions.y) as usize],
+            },
+        );
+    }
+
+    /// Returns true if the given layer exists
+    pub fn has_layer(&self, map_layer: u64) -> bool {
+        self.layers.contains_key(&map_layer)
+    }
+
+    /// Returns the dimensions of the given layer in corners
+    pub fn dimensions(&self, map_layer: u64) -> Option<UVec2> {
+        self.layers.get(&map_layer).map(|grid| grid.dimensions)
+    }
+
+    /// Gets the data of the given chunk local corner
+    pub fn get(&self, map_layer: u64, corner: UVec2) -> Option<T> {
+        let grid = self.layers.get(&map_layer)?;
+        grid.index(corner).map(|index| grid.data[index])
+    }
+
+    /// Sets the data of the given chunk local corner. Returns false if the layer or corner does not exist
+    pub fn set(&mut self, map_layer: u64, corner: UVec2, tile_data: T) -> bool {
+        let Some(grid) = self.layers.get_mut(&map_layer) else {
+            return false;
+        };
+        let Some(index) = grid.index(corner) else {
+            return false;
+        };
+        grid.data[index] = tile_data;
+        true
+    }
+}
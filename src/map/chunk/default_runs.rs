@@ -0,0 +1,144 @@
+//! Serialization of dense grids that leaves out tiles holding the default `TileData`.
+//!
+//! Grids are written row by row as runs: the amount of default tiles skipped followed by the tiles stored
+//! after them. Trailing default tiles are not written at all. Grids saved as plain [`Grid`]s before this
+//! format existed still deserialize.
+
+use lettuces::storage::grid::Grid;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// A grid stored as runs of skipped default tiles and stored tiles
+#[derive(Serialize, Deserialize)]
+pub(crate) struct DefaultRuns<T> {
+    rows: usize,
+    columns: usize,
+    runs: Vec<(usize, Vec<T>)>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DenseGrid<T> {
+    Runs(DefaultRuns<T>),
+    Grid(Grid<T>),
+}
+
+impl<T> DefaultRuns<T>
+where
+    T: Clone + Copy + Default + PartialEq,
+{
+    /// Splits the grid into runs
+    pub(crate) fn from_grid(grid: &Grid<T>) -> Self {
+        let (rows, columns) = grid.size();
+        let mut runs = vec![];
+        let mut skipped = 0;
+        let mut tiles = vec![];
+        for tile_data in (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .filter_map(|(x, y)| grid.get(y, x))
+        {
+            if *tile_data != T::default() {
+                tiles.push(*tile_data);
+                continue;
+            }
+            if !tiles.is_empty() {
+                runs.push((skipped, std::mem::take(&mut tiles)));
+                skipped = 0;
+            }
+            skipped += 1;
+        }
+        if !tiles.is_empty() {
+            runs.push((skipped, tiles));
+        }
+        Self {
+            rows,
+            columns,
+            runs,
+        }
+    }
+}
+
+impl<T> DefaultRuns<T>
+where
+    T: Clone + Default,
+{
+    /// Rebuilds the grid the runs were made from. Returns `None` if the runs hold more tiles than the grid
+    pub(crate) fn into_grid(self) -> Option<Grid<T>> {
+        let mut grid = Grid::new(self.rows, self.columns);
+        let mut index = 0;
+        for (skipped, tiles) in self.runs {
+            index += skipped;
+            for tile_data in tiles {
+                if self.columns == 0 {
+                    return None;
+                }
+                *grid.get_mut(index / self.columns, index % self.columns)? = tile_data;
+                index += 1;
+            }
+        }
+        Some(grid)
+    }
+}
+
+/// Serializes the grid as [`DefaultRuns`]
+pub(crate) fn serialize<T, S>(grid: &Grid<T>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Clone + Copy + Default + PartialEq + Serialize,
+    S: Serializer,
+{
+    DefaultRuns::from_grid(grid).serialize(serializer)
+}
+
+/// Deserializes a grid saved as [`DefaultRuns`] or as a plain [`Grid`]
+pub(crate) fn deserialize<'de, T, D>(deserializer: D) -> Result<Grid<T>, D::Error>
+where
+    T: Clone + Default + Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    match DenseGrid::deserialize(deserializer)? {
+        DenseGrid::Runs(runs) => runs
+            .into_grid()
+            .ok_or_else(|| D::Error::custom("tile runs extend past the end of the grid")),
+        DenseGrid::Grid(grid) => Ok(grid),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DefaultRuns;
+    use lettuces::storage::grid::Grid;
+
+    fn cells(grid: &Grid<u8>) -> Vec<u8> {
+        let (rows, columns) = grid.size();
+        (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| (x, y)))
+            .filter_map(|(x, y)| grid.get(y, x).copied())
+            .collect()
+    }
+
+    #[test]
+    fn test_default_runs() {
+        let mut grid = Grid::<u8>::new(3, 4);
+        *grid.get_mut(0, 1).unwrap() = 4;
+        *grid.get_mut(0, 2).unwrap() = 5;
+        *grid.get_mut(2, 0).unwrap() = 6;
+
+        let runs = DefaultRuns::from_grid(&grid);
+        assert_eq!(runs.runs, vec![(1, vec![4, 5]), (5, vec![6])]);
+        assert_eq!(cells(&runs.into_grid().unwrap()), cells(&grid));
+
+        // Grids written before runs existed still load
+        let serialized = ron::to_string(&grid).unwrap();
+        let deserialized: Grid<u8> =
+            super::deserialize(&mut ron::Deserializer::from_str(&serialized).unwrap()).unwrap();
+        assert_eq!(deserialized.size(), (3, 4));
+        assert_eq!(cells(&deserialized), cells(&grid));
+
+        let overflowing = DefaultRuns {
+            rows: 1,
+            columns: 1,
+            runs: vec![(1, vec![1u8])],
+        };
+        assert!(overflowing.into_grid().is_none());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::map::chunk::ChunkCell;
+use bevy::math::{IVec2, UVec2};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The smallest rectangle of [`ChunkCell`]s containing every cell of a [`Chunk`](crate::map::chunk::Chunk)
+/// written since the rectangle was last taken. Both corners are inclusive.
+///
+/// Renderers mirroring chunks into GPU textures take it with
+/// [`Chunk::take_dirty_rect`](crate::map::chunk::Chunk::take_dirty_rect) and only upload the changed
+/// sub-region instead of the whole chunk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChunkDirtyRect {
+    /// The corner of the rectangle with the lowest coordinates
+    pub min: ChunkCell,
+    /// The corner of the rectangle with the highest coordinates
+    pub max: ChunkCell,
+}
+
+impl ChunkDirtyRect {
+    /// Creates a rectangle containing only the given [`ChunkCell`]
+    pub fn new(chunk_cell: ChunkCell) -> Self {
+        Self {
+            min: chunk_cell,
+            max: chunk_cell,
+        }
+    }
+
+    /// Creates a rectangle covering every cell of a chunk with the given dimensions
+    pub fn full(dimensions: UVec2) -> Self {
+        let max = dimensions.as_ivec2().max(IVec2::ONE) - IVec2::ONE;
+        Self {
+            min: ChunkCell::new(0, 0),
+            max: ChunkCell::new(max.x, max.y),
+        }
+    }
+
+    /// Grows the rectangle to contain the given [`ChunkCell`]
+    pub fn include(&mut self, chunk_cell: ChunkCell) {
+        let min = self.min.as_ivec2().min(chunk_cell.as_ivec2());
+        let max = self.max.as_ivec2().max(chunk_cell.as_ivec2());
+        self.min = ChunkCell::new(min.x, min.y);
+        self.max = ChunkCell::new(max.x, max.y);
+    }
+
+    /// Returns the smallest rectangle containing both rectangles
+    pub fn union(mut self, other: ChunkDirtyRect) -> Self {
+        self.include(other.min);
+        self.include(other.max);
+        self
+    }
+
+    /// Returns true if the rectangle contains the given [`ChunkCell`]
+    pub fn contains(&self, chunk_cell: ChunkCell) -> bool {
+        let cell = chunk_cell.as_ivec2();
+        cell.cmpge(self.min.as_ivec2()).all() && cell.cmple(self.max.as_ivec2()).all()
+    }
+
+    /// Returns the amount of cells the rectangle spans on each axis
+    pub fn size(&self) -> UVec2 {
+        (self.max.as_ivec2() - self.min.as_ivec2() + IVec2::ONE).as_uvec2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkDirtyRect;
+    use crate::map::chunk::ChunkCell;
+    use bevy::math::UVec2;
+
+    #[test]
+    fn test_dirty_rect() {
+        let mut rect = ChunkDirtyRect::new(ChunkCell::new(3, 1));
+        assert_eq!(rect.size(), UVec2::new(1, 1));
+        rect.include(ChunkCell::new(1, 4));
+        assert_eq!(rect.min, ChunkCell::new(1, 1));
+        assert_eq!(rect.max, ChunkCell::new(3, 4));
+        assert_eq!(rect.size(), UVec2::new(3, 4));
+        assert!(rect.contains(ChunkCell::new(2, 2)));
+        assert!(!rect.contains(ChunkCell::new(0, 2)));
+
+        let full = ChunkDirtyRect::full(UVec2::new(8, 6));
+        assert_eq!(full.max, ChunkCell::new(7, 5));
+        assert_eq!(rect.union(full), full);
+    }
+}
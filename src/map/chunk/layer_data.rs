@@ -3,7 +3,10 @@ use std::hash::Hash;
 use bevy::{ecs::entity::MapEntities, math::UVec2, prelude::Entity, utils::HashMap};
 use lettuces::cell::Cell;
 
-use super::ChunkCell;
+use super::{
+    ChunkCell, ChunkStoragePool, ChunkTemplates, CompactionReport, LayerStorage,
+    SerializationStats, TileEntityStorage,
+};
 
 /// The data for a specific chunk. Contains only the data for that chunk
 pub enum ChunkLayerType<T> {
@@ -31,6 +34,21 @@ pub trait ChunkLayer<TileData>: Hash + MapEntities {
         map_settings: &Self::ChunkSettings,
     ) -> Self;
 
+    /// Creates a new chunk like [`Self::new`], taking its storage from the given [`ChunkStoragePool`]. Layers
+    /// that don't use pooled storage can ignore this
+    fn new_pooled(
+        layer_type: ChunkLayerType<TileData>,
+        chunk_dimensions: UVec2,
+        map_settings: &Self::ChunkSettings,
+        _pool: &ChunkStoragePool<TileData>,
+    ) -> Self
+    where
+        Self: Sized,
+        TileData: Send + Sync + 'static,
+    {
+        Self::new(layer_type, chunk_dimensions, map_settings)
+    }
+
     /// Returns the dimensions of this specific chunk
     fn get_chunk_dimensions(&self) -> UVec2;
 
@@ -48,4 +66,88 @@ pub trait ChunkLayer<TileData>: Hash + MapEntities {
 
     /// Sets the [`Entity`] at the given [`ChunkCell`]
     fn set_tile_entity(&mut self, chunk_cell: ChunkCell, entity: Entity);
+
+    /// Removes the [`Entity`] at the given [`ChunkCell`] and returns it
+    fn remove_tile_entity(&mut self, chunk_cell: ChunkCell) -> Option<Entity>;
+
+    /// Iterates over every [`ChunkCell`] that has `TileData` along with its data. The default visits every
+    /// cell inside of [`Self::get_chunk_dimensions`], layers with sparse storage should override it
+    fn iter_tile_data(&self) -> Box<dyn Iterator<Item = (ChunkCell, &TileData)> + '_> {
+        Box::new(
+            ChunkCell::iter_chunk(self.get_chunk_dimensions()).filter_map(|chunk_cell| {
+                self.get_tile_data(chunk_cell)
+                    .map(|tile_data| (chunk_cell, tile_data))
+            }),
+        )
+    }
+
+    /// Iterates over every [`ChunkCell`] that has a tile [`Entity`]. The default visits every cell inside of
+    /// [`Self::get_chunk_dimensions`]
+    fn iter_tile_entities(&self) -> Box<dyn Iterator<Item = (ChunkCell, Entity)> + '_> {
+        Box::new(
+            ChunkCell::iter_chunk(self.get_chunk_dimensions()).filter_map(|chunk_cell| {
+                self.get_tile_entity(chunk_cell)
+                    .map(|entity| (chunk_cell, entity))
+            }),
+        )
+    }
+
+    /// Changes how the tile entities of this layer are stored, keeping every existing tile entity. Layers
+    /// that only support one kind of storage can ignore this
+    fn set_tile_entity_storage(&mut self, _storage: TileEntityStorage) {}
+
+    /// Returns true if the layer only stores the cells that were written to it. Layers that only support one
+    /// kind of storage can ignore this
+    fn is_sparse(&self) -> bool {
+        false
+    }
+
+    /// Removes the `TileData` at the given [`ChunkCell`] and returns it. Only layers that store cells without
+    /// data can remove tile data, the default does nothing and returns `None`
+    fn remove_tile_data(&mut self, _chunk_cell: ChunkCell) -> Option<TileData> {
+        None
+    }
+
+    /// Changes how the tile data of this layer is stored and returns true if it changed. Tile entities are
+    /// kept.
+    ///
+    /// Converting to [`LayerStorage::Sparse`] leaves out cells whose tile data `is_empty`, and converting to
+    /// [`LayerStorage::Dense`] fills cells without tile data with the default `TileData`. Layers that only
+    /// support one kind of storage can ignore this
+    fn set_layer_storage(
+        &mut self,
+        _storage: LayerStorage,
+        _is_empty: &dyn Fn(&TileData) -> bool,
+    ) -> bool {
+        false
+    }
+
+    /// Releases memory held by the layer. Sparse entries whose tile data `is_empty` are removed, dense layers
+    /// where at most `max_dense_fill` of the cells are not empty are converted to sparse layers, and unused
+    /// capacity is released. Layers that can't be compacted can ignore this
+    fn compact(
+        &mut self,
+        _is_empty: &dyn Fn(&TileData) -> bool,
+        _max_dense_fill: f32,
+    ) -> CompactionReport {
+        CompactionReport::default()
+    }
+
+    /// Replaces the dense tile data of the layer with the template of the same contents, creating the
+    /// template if there is none yet. Layers that can't share their data can ignore this
+    fn share_templates(&mut self, _templates: &mut ChunkTemplates<TileData>) {}
+
+    /// Moves the storage of the layer into the given [`ChunkStoragePool`] for later layers to reuse, leaving
+    /// the layer empty. Layers that don't use pooled storage can ignore this
+    fn release_storage(&mut self, _pool: &ChunkStoragePool<TileData>)
+    where
+        TileData: Send + Sync + 'static,
+    {
+    }
+
+    /// Returns what serializing the layer leaves out. `is_default` must match tile data equal to the default
+    /// `TileData`. Layers that serialize everything can ignore this
+    fn serialization_stats(&self, _is_default: &dyn Fn(&TileData) -> bool) -> SerializationStats {
+        SerializationStats::default()
+    }
 }
@@ -2,15 +2,36 @@
 
 mod chunk_cell;
 mod chunk_pos;
+mod corners;
+mod dirty_rect;
+#[cfg(feature = "serde")]
+pub(crate) mod default_runs;
 mod layer_data;
+mod sparse_map;
+mod storage;
+mod storage_pool;
+mod templates;
 
 pub use crate::map::chunk::chunk_cell::ChunkCell;
 pub use crate::map::chunk::chunk_pos::ChunkPos;
+pub use crate::map::chunk::corners::{ChunkCorners, CornerId};
+pub use crate::map::chunk::dirty_rect::ChunkDirtyRect;
 use crate::map::MapLayer;
 use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::prelude::{Component, Entity, UVec2};
 use bevy::utils::hashbrown::HashMap;
 pub use layer_data::{ChunkLayer, ChunkLayerType};
+#[cfg(feature = "fxhash")]
+pub use sparse_map::SparseKeyState;
+pub use sparse_map::SparseMap;
+#[cfg(any(feature = "hex", feature = "square"))]
+pub(crate) use sparse_map::sparse_map_heap_size;
+pub use storage::{
+    ChunkStorageOverride, CompactionReport, LayerStorage, SerializationStats, TileEntities,
+    TileEntityStorage,
+};
+pub use storage_pool::ChunkStoragePool;
+pub use templates::{ChunkTemplate, ChunkTemplates};
 use lettuces::cell::Cell;
 use lettuces::storage::grid::Grid;
 use std::hash::{Hash, Hasher};
@@ -107,6 +128,17 @@ impl Chunks {
             .cloned()
     }
 
+    /// Points the given [`ChunkPos`] at a new chunk entity. Does nothing if the position is outside of the
+    /// map
+    pub fn set_chunk(&mut self, chunk_pos: ChunkPos, chunk_entity: Entity) {
+        if let Some(entity) = self
+            .chunk_entities
+            .get_mut(chunk_pos.y() as usize, chunk_pos.x() as usize)
+        {
+            *entity = chunk_entity;
+        }
+    }
+
     /// Returns the x and y count of chunks
     pub fn chunk_counts(&self) -> UVec2 {
         UVec2::new(
@@ -131,11 +163,18 @@ where
     /// The position of the Chunk in the map
     pub chunk_pos: ChunkPos,
     /// Chunk tile data mapped to layers
-    pub data: HashMap<u32, MapChunk>,
+    pub data: HashMap<u64, MapChunk>,
     /// Settings related to the chunk
     pub chunk_settings: MapChunk::ChunkSettings,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "reflect", reflect(ignore))]
+    dirty_rect: Option<ChunkDirtyRect>,
     #[cfg_attr(feature = "reflect", reflect(ignore))]
     ph: PhantomData<TileData>,
+    /// The data returned for cells of a layer that have no data, mapped to layers
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "reflect", reflect(ignore))]
+    layer_defaults: HashMap<u64, TileData>,
 }
 
 impl<MapChunk, TileData> MapEntities for Chunk<MapChunk, TileData>
@@ -160,6 +199,28 @@ where
         pairs.sort_by_key(|i| i.0);
         Hash::hash(&pairs, h);
         Hash::hash(&self.chunk_pos, h);
+        if !self.layer_defaults.is_empty() {
+            let mut defaults: Vec<_> = self.layer_defaults.iter().collect();
+            defaults.sort_by_key(|i| i.0);
+            Hash::hash(&defaults, h);
+        }
+    }
+}
+
+impl<MapChunk, TileData> Clone for Chunk<MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            chunk_pos: self.chunk_pos,
+            data: self.data.clone(),
+            chunk_settings: self.chunk_settings,
+            dirty_rect: self.dirty_rect,
+            ph: PhantomData,
+            layer_defaults: self.layer_defaults.clone(),
+        }
     }
 }
 
@@ -173,7 +234,9 @@ where
             chunk_pos: Default::default(),
             data: HashMap::default(),
             chunk_settings: MapChunk::ChunkSettings::default(),
+            dirty_rect: None,
             ph: Default::default(),
+            layer_defaults: HashMap::default(),
         }
     }
 }
@@ -191,12 +254,14 @@ where
         chunk_settings: MapChunk::ChunkSettings,
     ) -> Chunk<MapChunk, TileData> {
         let mut hashmap = HashMap::new();
-        hashmap.insert(1u32, MapChunk::new(tile_data, chunk_size, &chunk_settings));
+        hashmap.insert(1u64, MapChunk::new(tile_data, chunk_size, &chunk_settings));
         Self {
             chunk_pos,
             data: hashmap,
             chunk_settings,
+            dirty_rect: None,
             ph: Default::default(),
+            layer_defaults: HashMap::default(),
         }
     }
 
@@ -204,12 +269,44 @@ where
     ///
     /// # Note
     /// - Overwrites the layer if it already exists
-    pub fn add_layer(&mut self, map_layer: u32, tile_data: ChunkLayerType<TileData>) {
+    pub fn add_layer(&mut self, map_layer: u64, tile_data: ChunkLayerType<TileData>) {
         self.data.insert(
             map_layer,
             MapChunk::new(tile_data, self.get_chunk_dimensions(), &self.chunk_settings),
         );
     }
+
+    /// Adds a new layer to the chunk like [`Self::add_layer`], taking its storage from the given
+    /// [`ChunkStoragePool`]
+    pub fn add_layer_pooled(
+        &mut self,
+        map_layer: u64,
+        tile_data: ChunkLayerType<TileData>,
+        pool: &ChunkStoragePool<TileData>,
+    ) where
+        TileData: 'static,
+    {
+        self.data.insert(
+            map_layer,
+            MapChunk::new_pooled(
+                tile_data,
+                self.get_chunk_dimensions(),
+                &self.chunk_settings,
+                pool,
+            ),
+        );
+    }
+
+    /// Moves the storage of every layer into the given [`ChunkStoragePool`], leaving the layers empty. Used
+    /// before despawning a chunk so the next map can reuse its memory
+    pub fn release_storage(&mut self, pool: &ChunkStoragePool<TileData>)
+    where
+        TileData: 'static,
+    {
+        for layer in self.data.values_mut() {
+            layer.release_storage(pool);
+        }
+    }
 }
 
 impl<MapChunk, TileData> Chunk<MapChunk, TileData>
@@ -222,7 +319,7 @@ where
     /// # Panics
     /// - If the [`MapLayer`] does not exist in the chunk
     pub fn get_chunk_dimensions(&self) -> UVec2 {
-        if let Some(tiles) = self.data.get(&1u32) {
+        if let Some(tiles) = self.data.get(&1u64) {
             return tiles.get_chunk_dimensions();
         } else {
             panic!("MapLayer does not exist in chunk")
@@ -234,7 +331,7 @@ where
     /// # Panics
     /// - If the [`ChunkCell`] does not exist in the [`Chunk`]
     /// - If the [`MapLayer`] does not exist in the chunk
-    pub fn set_tile_data_from_cell(&mut self, map_layer: u32, cell: Cell, tile_data: TileData) {
+    pub fn set_tile_data_from_cell(&mut self, map_layer: u64, cell: Cell, tile_data: TileData) {
         self.set_tile_data(
             map_layer,
             MapChunk::into_chunk_cell(cell, &self.chunk_settings),
@@ -247,14 +344,66 @@ where
     /// # Panics
     /// - If the [`ChunkCell`] does not exist in the [`Chunk`]
     /// - If the [`MapLayer`] does not exist in the chunk
-    pub fn set_tile_data(&mut self, map_layer: u32, chunk_cell: ChunkCell, tile_data: TileData) {
+    pub fn set_tile_data(&mut self, map_layer: u64, chunk_cell: ChunkCell, tile_data: TileData) {
         if let Some(tiles) = self.data.get_mut(&map_layer) {
             tiles.set_tile_data(chunk_cell, tile_data);
+            self.mark_dirty(chunk_cell);
         } else {
             panic!("MapLayer does not exist in chunk")
         }
     }
 
+    /// Removes the tile data at the given [`ChunkCell`] and returns it. Returns `None` if the layer doesn't
+    /// exist, the cell has no data, or the layer can't remove tile data. See [`ChunkLayer::remove_tile_data`]
+    pub fn remove_tile_data(&mut self, map_layer: u64, chunk_cell: ChunkCell) -> Option<TileData> {
+        let tile_data = self
+            .data
+            .get_mut(&map_layer)
+            .and_then(|layer| layer.remove_tile_data(chunk_cell))?;
+        self.mark_dirty(chunk_cell);
+        Some(tile_data)
+    }
+
+    /// Sets the data returned by [`TilemapManager`](crate::tilemap_manager::TilemapManager) for cells of the
+    /// given layer that have no data, or clears it if `None`. Lets sparse layers act as overrides on top of
+    /// another layer without every read having to handle missing data
+    pub fn set_layer_default(&mut self, map_layer: u64, tile_data: Option<TileData>) {
+        match tile_data {
+            Some(tile_data) => self.layer_defaults.insert(map_layer, tile_data),
+            None => self.layer_defaults.remove(&map_layer),
+        };
+    }
+
+    /// Returns the default data of the given layer if it has one. See [`Self::set_layer_default`]
+    pub fn layer_default(&self, map_layer: u64) -> Option<TileData> {
+        self.layer_defaults.get(&map_layer).copied()
+    }
+
+    /// Grows the [`ChunkDirtyRect`] of the chunk to contain the given [`ChunkCell`]. Writes made through the
+    /// chunk mark their cells automatically, call this after writing to a layer in [`Self::data`] directly
+    pub fn mark_dirty(&mut self, chunk_cell: ChunkCell) {
+        match self.dirty_rect.as_mut() {
+            Some(dirty_rect) => dirty_rect.include(chunk_cell),
+            None => self.dirty_rect = Some(ChunkDirtyRect::new(chunk_cell)),
+        }
+    }
+
+    /// Marks every cell of the chunk as dirty
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty_rect = Some(ChunkDirtyRect::full(self.get_chunk_dimensions()));
+    }
+
+    /// Returns the [`ChunkDirtyRect`] of every cell written since it was last taken, or `None` if no cell
+    /// was written
+    pub fn dirty_rect(&self) -> Option<ChunkDirtyRect> {
+        self.dirty_rect
+    }
+
+    /// Returns the [`ChunkDirtyRect`] of every cell written since it was last taken and clears it
+    pub fn take_dirty_rect(&mut self) -> Option<ChunkDirtyRect> {
+        self.dirty_rect.take()
+    }
+
     /// Returns a clone of the TileData at the given world [`Cell`] if it exists in this chunk
     ///
     /// # Panics
@@ -310,8 +459,37 @@ where
             .get_tile_entity(chunk_cell)
     }
 
+    /// Iterates over the bits of every [`MapLayer`] in the chunk
+    pub fn layers(&self) -> impl Iterator<Item = u64> + '_ {
+        self.data.keys().copied()
+    }
+
+    /// Iterates over every [`ChunkCell`] of the given layer that has `TileData` along with its data. Yields
+    /// nothing if the [`MapLayer`] does not exist in the chunk
+    pub fn iter_layer(
+        &self,
+        map_layer: impl MapLayer,
+    ) -> impl Iterator<Item = (ChunkCell, &TileData)> + '_ {
+        self.data
+            .get(&map_layer.to_bits())
+            .into_iter()
+            .flat_map(|layer| layer.iter_tile_data())
+    }
+
+    /// Iterates over every [`ChunkCell`] of the given layer that has a tile [`Entity`]. Yields nothing if the
+    /// [`MapLayer`] does not exist in the chunk
+    pub fn iter_entities(
+        &self,
+        map_layer: impl MapLayer,
+    ) -> impl Iterator<Item = (ChunkCell, Entity)> + '_ {
+        self.data
+            .get(&map_layer.to_bits())
+            .into_iter()
+            .flat_map(|layer| layer.iter_tile_entities())
+    }
+
     /// Sets the [`Entity`] for the given [`Cell`] to the given Entity.
-    pub fn set_tile_entity_from_cell(&mut self, map_layer: u32, cell: Cell, entity: Entity) {
+    pub fn set_tile_entity_from_cell(&mut self, map_layer: u64, cell: Cell, entity: Entity) {
         self.set_tile_entity(
             map_layer,
             MapChunk::into_chunk_cell(cell, &self.chunk_settings),
@@ -320,12 +498,44 @@ where
     }
 
     /// Sets the [`Entity`] for the given [`ChunkCell`] to the given Entity.
-    pub fn set_tile_entity(&mut self, map_layer: u32, chunk_cell: ChunkCell, entity: Entity) {
+    pub fn set_tile_entity(&mut self, map_layer: u64, chunk_cell: ChunkCell, entity: Entity) {
         self.data
             .get_mut(&map_layer)
             .expect("MapLayer does not exist in chunk")
             .set_tile_entity(chunk_cell, entity);
     }
+
+    /// Removes the [`Entity`] for the given [`ChunkCell`] and returns it. Returns `None` if the layer doesn't
+    /// exist or the cell has no entity
+    pub fn remove_tile_entity(&mut self, map_layer: u64, chunk_cell: ChunkCell) -> Option<Entity> {
+        self.data
+            .get_mut(&map_layer)
+            .and_then(|layer| layer.remove_tile_entity(chunk_cell))
+    }
+
+    /// Changes how the tile data of the given layer is stored and returns true if it changed. Sparse layers
+    /// leave out cells holding the default `TileData`. See [`ChunkLayer::set_layer_storage`]
+    pub fn convert_layer_storage(&mut self, map_layer: u64, storage: LayerStorage) -> bool
+    where
+        TileData: PartialEq,
+    {
+        self.data.get_mut(&map_layer).is_some_and(|layer| {
+            layer.set_layer_storage(storage, &|tile_data| *tile_data == TileData::default())
+        })
+    }
+
+    /// Returns what serializing the chunk leaves out: empty tile entity storage and dense tiles holding the
+    /// default `TileData`
+    pub fn serialization_stats(&self) -> SerializationStats
+    where
+        TileData: PartialEq,
+    {
+        let mut stats = SerializationStats::default();
+        for layer in self.data.values() {
+            stats += layer.serialization_stats(&|tile_data| *tile_data == TileData::default());
+        }
+        stats
+    }
 }
 
 #[cfg(test)]
@@ -334,10 +544,13 @@ mod tests {
     use crate::{self as bevy_sparse_tilemap};
     use crate::{
         map::chunk::chunk_cell::ChunkCell, map::chunk::chunk_pos::ChunkPos, map::chunk::Chunk,
+        map::chunk::LayerStorage,
     };
     use bevy::math::UVec2;
+    use bevy::prelude::Entity;
     use bevy::utils::hashbrown::HashMap;
     use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
 
     #[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash)]
     struct TileData(u8);
@@ -513,6 +726,103 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_iter_layers() {
+        let mut chunk: Chunk<SquareChunkLayer<u8>, u8> = Chunk::new(
+            ChunkPos::new(0, 0),
+            UVec2 { x: 3, y: 2 },
+            crate::map::chunk::ChunkLayerType::Dense(vec![vec![1, 2, 3], vec![4, 5, 6]]),
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 3, y: 2 },
+            },
+        );
+        let mut hashmap: HashMap<ChunkCell, u8> = HashMap::new();
+        hashmap.insert(ChunkCell::new(2, 1), 9);
+        chunk.add_layer(
+            MapLayers::Secondary.to_bits(),
+            crate::map::chunk::ChunkLayerType::Sparse(hashmap),
+        );
+        chunk.set_tile_entity(
+            MapLayers::Secondary.to_bits(),
+            ChunkCell::new(1, 0),
+            Entity::from_raw(7),
+        );
+
+        let mut layers: Vec<u64> = chunk.layers().collect();
+        layers.sort();
+        assert_eq!(
+            layers,
+            vec![MapLayers::Main.to_bits(), MapLayers::Secondary.to_bits()]
+        );
+
+        let main: Vec<(ChunkCell, u8)> = chunk
+            .iter_layer(MapLayers::Main)
+            .map(|(chunk_cell, tile_data)| (chunk_cell, *tile_data))
+            .collect();
+        assert_eq!(main.len(), 6);
+        assert!(main.contains(&(ChunkCell::new(2, 1), 6)));
+        assert!(main.contains(&(ChunkCell::new(0, 0), 1)));
+
+        let secondary: Vec<(ChunkCell, u8)> = chunk
+            .iter_layer(MapLayers::Secondary)
+            .map(|(chunk_cell, tile_data)| (chunk_cell, *tile_data))
+            .collect();
+        assert_eq!(secondary, vec![(ChunkCell::new(2, 1), 9)]);
+        assert_eq!(
+            chunk.iter_entities(MapLayers::Secondary).collect::<Vec<_>>(),
+            vec![(ChunkCell::new(1, 0), Entity::from_raw(7))]
+        );
+        assert_eq!(chunk.iter_entities(MapLayers::Main).count(), 0);
+    }
+
+    #[test]
+    fn test_convert_layer_storage() {
+        let mut chunk: Chunk<SquareChunkLayer<u8>, u8> = Chunk::new(
+            ChunkPos::new(0, 0),
+            UVec2 { x: 3, y: 2 },
+            crate::map::chunk::ChunkLayerType::Dense(vec![vec![0, 2, 0], vec![0, 0, 5]]),
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 3, y: 2 },
+            },
+        );
+        chunk.set_tile_entity(
+            MapLayers::Main.to_bits(),
+            ChunkCell::new(0, 1),
+            Entity::from_raw(3),
+        );
+
+        assert!(chunk.convert_layer_storage(MapLayers::Main.to_bits(), LayerStorage::Sparse));
+        assert!(!chunk.convert_layer_storage(MapLayers::Main.to_bits(), LayerStorage::Sparse));
+        assert_eq!(chunk.iter_layer(MapLayers::Main).count(), 2);
+        assert_eq!(
+            chunk.get_tile_data(MapLayers::Main, ChunkCell::new(1, 0)),
+            Some(2)
+        );
+        assert_eq!(
+            chunk.get_tile_data(MapLayers::Main, ChunkCell::new(0, 0)),
+            None
+        );
+
+        assert!(chunk.convert_layer_storage(MapLayers::Main.to_bits(), LayerStorage::Dense));
+        assert_eq!(chunk.iter_layer(MapLayers::Main).count(), 6);
+        assert_eq!(
+            chunk.get_tile_data(MapLayers::Main, ChunkCell::new(0, 0)),
+            Some(0)
+        );
+        assert_eq!(
+            chunk.get_tile_data(MapLayers::Main, ChunkCell::new(2, 1)),
+            Some(5)
+        );
+        assert_eq!(
+            chunk.get_tile_entity(MapLayers::Main, ChunkCell::new(0, 1)),
+            Some(Entity::from_raw(3))
+        );
+        assert!(!chunk.convert_layer_storage(
+            MapLayers::Secondary.to_bits(),
+            LayerStorage::Dense
+        ));
+    }
+
     #[test]
     fn test_adding_dense_layer() {
         let mut chunk: Chunk<SquareChunkLayer<(i32, i32)>, (i32, i32)> = Chunk::new(
@@ -541,25 +851,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dirty_rect_tracking() {
+        let mut chunk: Chunk<SquareChunkLayer<u8>, u8> = Chunk::new(
+            ChunkPos::new(0, 0),
+            UVec2 { x: 4, y: 4 },
+            crate::map::chunk::ChunkLayerType::Dense(vec![vec![0; 4]; 4]),
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 4, y: 4 },
+            },
+        );
+        assert_eq!(chunk.dirty_rect(), None);
+
+        chunk.set_tile_data(1, ChunkCell::new(2, 1), 1);
+        chunk.set_tile_data_from_cell(1, Cell::new(1, 3), 1);
+        chunk.mark_dirty(ChunkCell::new(3, 2));
+        let dirty_rect = chunk.take_dirty_rect().unwrap();
+        assert_eq!(dirty_rect.min, ChunkCell::new(1, 1));
+        assert_eq!(dirty_rect.max, ChunkCell::new(3, 3));
+        assert_eq!(chunk.take_dirty_rect(), None);
+
+        chunk.mark_all_dirty();
+        assert_eq!(chunk.dirty_rect().unwrap().size(), UVec2::new(4, 4));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialization_skips_defaults() {
+        use crate::map::chunk::SerializationStats;
+        use bevy::prelude::Entity;
+        use std::hash::{Hash, Hasher};
+
+        fn hash(chunk: &Chunk<SquareChunkLayer<u8>, u8>) -> u64 {
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            chunk.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut vecs = vec![vec![0u8; 4]; 4];
+        vecs[1][2] = 3;
+        let mut chunk: Chunk<SquareChunkLayer<u8>, u8> = Chunk::new(
+            ChunkPos::new(0, 0),
+            UVec2::new(4, 4),
+            crate::map::chunk::ChunkLayerType::Dense(vecs),
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        );
+        chunk.add_layer(
+            MapLayers::Secondary.to_bits(),
+            crate::map::chunk::ChunkLayerType::Sparse(HashMap::new()),
+        );
+        assert_eq!(
+            chunk.serialization_stats(),
+            SerializationStats {
+                skipped_entity_maps: 2,
+                skipped_default_tiles: 15,
+                stored_tiles: 1,
+            }
+        );
+
+        let serialized = ron::to_string(&chunk).unwrap();
+        assert!(!serialized.contains("tile_entities"));
+        let deserialized: Chunk<SquareChunkLayer<u8>, u8> = ron::from_str(&serialized).unwrap();
+        assert_eq!(hash(&deserialized), hash(&chunk));
+        assert_eq!(
+            deserialized.get_tile_data(MapLayers::Main, ChunkCell::new(2, 1)),
+            Some(3)
+        );
+        assert_eq!(
+            deserialized.get_tile_data(MapLayers::Main, ChunkCell::new(3, 3)),
+            Some(0)
+        );
+
+        // Layers with tile entities keep them
+        chunk.set_tile_entity(
+            MapLayers::Main.to_bits(),
+            ChunkCell::new(0, 0),
+            Entity::from_raw(5),
+        );
+        assert_eq!(chunk.serialization_stats().skipped_entity_maps, 1);
+        let deserialized: Chunk<SquareChunkLayer<u8>, u8> =
+            ron::from_str(&ron::to_string(&chunk).unwrap()).unwrap();
+        assert_eq!(
+            deserialized.get_tile_entity(MapLayers::Main, ChunkCell::new(0, 0)),
+            Some(Entity::from_raw(5))
+        );
+    }
+
     #[cfg(feature = "reflect")]
     mod reflect_test {
-        use crate::square::map_chunk_layer::{
-            SquareChunkLayer, SquareChunkLayerData, SquareChunkSettings,
-        };
-        use crate::square::map_data::SquareMapDataConversionSettings;
-        use crate::{self as bevy_sparse_tilemap};
-        use crate::{
-            map::chunk::chunk_cell::ChunkCell, map::chunk::chunk_pos::ChunkPos, map::chunk::Chunk,
-        };
+        use crate::registration::register_square_map_types;
+        use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+        use crate::{map::chunk::chunk_pos::ChunkPos, map::chunk::Chunk};
+        use bevy::app::App;
+        use bevy::ecs::reflect::AppTypeRegistry;
         use bevy::math::UVec2;
-        use bevy::prelude::{Entity, FromReflect, Reflect};
+        use bevy::prelude::{FromReflect, Reflect};
         use bevy::reflect::erased_serde::__private::serde::de::DeserializeSeed;
         use bevy::reflect::serde::{ReflectSerializer, UntypedReflectDeserializer};
-        use bevy::reflect::TypeRegistry;
         use bevy::utils::hashbrown::HashMap;
-        use bst_map_layer_derive::MapLayer;
-        use lettuces::cell::Cell;
-        use lettuces::storage::grid::Grid;
 
         #[test]
         fn test_hashing_chunk() {
@@ -571,22 +962,9 @@ mod tests {
                     max_chunk_size: UVec2 { x: 2, y: 2 },
                 },
             );
-            let mut registry = TypeRegistry::default();
-            registry.register::<Chunk<SquareChunkLayer<(u32, u32)>, (u32, u32)>>();
-            registry.register::<ChunkPos>();
-            registry.register::<Cell>();
-            registry.register::<HashMap<u32, SquareChunkLayer<(u32, u32)>>>();
-            registry.register::<HashMap<u64, Entity>>();
-            registry.register::<HashMap<u64, (u32, u32)>>();
-            registry.register::<SquareChunkLayer<(u32, u32)>>();
-            registry.register::<SquareChunkLayerConversionSettings>();
-            registry.register::<SquareMapDataConversionSettings>();
-            registry.register::<SquareChunkLayerData<(u32, u32)>>();
-            registry.register::<Grid<(u32, u32)>>();
-            registry.register::<Vec<(u32, u32)>>();
-            registry.register::<(u32, u32)>();
-            registry.register::<Entity>();
-            registry.register::<UVec2>();
+            let mut app = App::new();
+            register_square_map_types::<(u32, u32)>(&mut app);
+            let registry = app.world.resource::<AppTypeRegistry>().read();
 
             // Serialize
             let reflect_serializer = ReflectSerializer::new(&chunk, &registry);
@@ -607,5 +985,85 @@ mod tests {
 
             assert_eq!(converted_value.chunk_pos, ChunkPos::new(0, 0));
         }
+
+        #[cfg(feature = "hex")]
+        #[test]
+        fn test_hashing_hex_chunk() {
+            use super::MapLayers;
+            use crate::hex::hexagonal_chunks::HexChunkShape;
+            use crate::hex::map_chunk_layer::{HexChunkLayer, HexagonChunkSettings};
+            use crate::map::chunk::{ChunkCell, ChunkLayerType};
+            use crate::registration::register_hex_map_types;
+            use lettuces::HexOrientation;
+
+            let mut app = App::new();
+            register_hex_map_types::<(u32, u32)>(&mut app);
+            let registry = app.world.resource::<AppTypeRegistry>().read();
+
+            let rectangle = HexagonChunkSettings {
+                orientation: HexOrientation::Flat,
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+                chunk_shape: HexChunkShape::Rectangle,
+            };
+            let hexagon = HexagonChunkSettings {
+                orientation: HexOrientation::Pointy,
+                max_chunk_size: UVec2 { x: 3, y: 3 },
+                chunk_shape: HexChunkShape::Hexagon { radius: 1 },
+            };
+            let mut sparse = HashMap::new();
+            sparse.insert(ChunkCell::new(1, 0), (9, 10));
+
+            // One chunk for each of the hex layer storages
+            for (settings, tile_data, cell, expected) in [
+                (
+                    rectangle,
+                    ChunkLayerType::Dense(vec![vec![(1, 2), (3, 4)], vec![(5, 6), (7, 8)]]),
+                    ChunkCell::new(0, 0),
+                    (1, 2),
+                ),
+                (
+                    hexagon,
+                    ChunkLayerType::Dense(vec![vec![(1, 2); 3], vec![(3, 4); 3], vec![(5, 6); 3]]),
+                    ChunkCell::new(1, 1),
+                    (3, 4),
+                ),
+                (
+                    rectangle,
+                    ChunkLayerType::Sparse(sparse),
+                    ChunkCell::new(1, 0),
+                    (9, 10),
+                ),
+            ] {
+                let chunk: Chunk<HexChunkLayer<(u32, u32)>, (u32, u32)> = Chunk::new(
+                    ChunkPos::new(1, 0),
+                    settings.max_chunk_size,
+                    tile_data,
+                    settings,
+                );
+
+                // Serialize
+                let reflect_serializer = ReflectSerializer::new(&chunk, &registry);
+                let serialized_value: String = ron::to_string(&reflect_serializer).unwrap();
+
+                // Deserialize
+                let reflect_deserializer = UntypedReflectDeserializer::new(&registry);
+                let deserialized_value: Box<dyn Reflect> = reflect_deserializer
+                    .deserialize(&mut ron::Deserializer::from_str(&serialized_value).unwrap())
+                    .unwrap();
+
+                // Convert
+                let converted_value =
+                    <Chunk<HexChunkLayer<(u32, u32)>, (u32, u32)> as FromReflect>::from_reflect(
+                        &*deserialized_value,
+                    )
+                    .unwrap();
+
+                assert_eq!(converted_value.chunk_pos, ChunkPos::new(1, 0));
+                assert_eq!(
+                    converted_value.get_tile_data(MapLayers::Main, cell),
+                    Some(expected)
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,42 @@
+//! The map type used to store sparse chunk layer data and tile entities.
+//!
+//! Keys are small integers derived from [`ChunkCell`](crate::map::chunk::ChunkCell)s so they don't need a
+//! DoS resistant hasher. By default [`SparseMap`] is bevys [`HashMap`](bevy::utils::HashMap). With the
+//! `fxhash` feature it uses [`FxHasher`](rustc_hash::FxHasher) instead, which is cheaper for integer keys
+//! in hot loops.
+
+#[cfg(feature = "fxhash")]
+use std::hash::BuildHasher;
+use std::mem::size_of;
+
+#[cfg(all(feature = "fxhash", feature = "reflect"))]
+use bevy::reflect::TypePath;
+
+/// The map used for sparse layer data and tile entities inside of chunk layers
+#[cfg(not(feature = "fxhash"))]
+pub type SparseMap<K, V> = bevy::utils::HashMap<K, V>;
+
+/// The map used for sparse layer data and tile entities inside of chunk layers
+#[cfg(feature = "fxhash")]
+pub type SparseMap<K, V> = bevy::utils::hashbrown::HashMap<K, V, SparseKeyState>;
+
+/// A [`BuildHasher`] that creates [`FxHasher`](rustc_hash::FxHasher)s for [`SparseMap`]s
+#[cfg(feature = "fxhash")]
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "reflect", derive(TypePath))]
+pub struct SparseKeyState;
+
+#[cfg(feature = "fxhash")]
+impl BuildHasher for SparseKeyState {
+    type Hasher = rustc_hash::FxHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        rustc_hash::FxHasher::default()
+    }
+}
+
+/// Returns the approximate amount of heap memory used by the map in bytes, including unused capacity
+pub(crate) fn sparse_map_heap_size<K, V>(map: &SparseMap<K, V>) -> usize {
+    // Every bucket holds a key value pair and a control byte
+    map.capacity() * (size_of::<(K, V)>() + 1)
+}
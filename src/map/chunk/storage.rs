@@ -0,0 +1,340 @@
+//! Storage choices that can be overridden per chunk.
+//!
+//! Every chunk of a map normally stores its layers the way the [`TilemapLayer`] they were built from was
+//! stored, and keeps its tile entities in a [`SparseMap`]. Maps mixing dense regions with vast empty ones can
+//! register a [`ChunkStorageOverride`] for a region of chunks on the
+//! [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder) to pick a better fit for each region.
+//!
+//! [`TilemapLayer`]: crate::tilemap_builder::tilemap_layer_builder::TilemapLayer
+
+use crate::map::chunk::sparse_map::sparse_map_heap_size;
+use crate::map::chunk::{ChunkCell, SparseMap};
+use bevy::ecs::entity::EntityMapper;
+use bevy::math::UVec2;
+use bevy::prelude::Entity;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+
+#[cfg(feature = "reflect")]
+use bevy::prelude::Reflect;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How the tile data of every layer in a chunk is stored
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum LayerStorage {
+    /// Keep the storage of the [`TilemapLayer`](crate::tilemap_builder::tilemap_layer_builder::TilemapLayer)
+    /// the layer was built from
+    #[default]
+    Unchanged,
+    /// Store every cell of the chunk. Missing cells are filled with the default `TileData`
+    Dense,
+    /// Only store cells that don't hold the default `TileData`
+    Sparse,
+}
+
+/// How the tile entities of a chunk layer are stored
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TileEntityStorage {
+    /// Store tile entities in a [`SparseMap`]. Best when few cells have tile entities
+    #[default]
+    Sparse,
+    /// Store a slot for every cell of the chunk. Best when most cells have tile entities
+    Dense,
+}
+
+/// Storage settings that replace the defaults for a region of chunks
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ChunkStorageOverride {
+    /// How the tile data of every layer is stored
+    pub layer_storage: LayerStorage,
+    /// How the tile entities of every layer are stored
+    pub tile_entity_storage: TileEntityStorage,
+}
+
+/// The result of compacting chunk layers with [`ChunkLayer::compact`](crate::map::chunk::ChunkLayer::compact)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct CompactionReport {
+    /// The approximate amount of heap memory released in bytes
+    pub reclaimed_bytes: usize,
+    /// The approximate amount of heap memory still used after compacting in bytes
+    pub heap_bytes: usize,
+    /// The amount of sparse entries holding empty tile data that were removed
+    pub dropped_entries: usize,
+    /// The amount of dense layers that were converted to sparse layers
+    pub converted_layers: usize,
+    /// The amount of sparse layers that were converted to dense layers
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub densified_layers: usize,
+}
+
+impl std::ops::AddAssign for CompactionReport {
+    fn add_assign(&mut self, rhs: Self) {
+        self.reclaimed_bytes += rhs.reclaimed_bytes;
+        self.heap_bytes += rhs.heap_bytes;
+        self.dropped_entries += rhs.dropped_entries;
+        self.converted_layers += rhs.converted_layers;
+        self.densified_layers += rhs.densified_layers;
+    }
+}
+
+/// What was left out when serializing chunk layers. See
+/// [`ChunkLayer::serialization_stats`](crate::map::chunk::ChunkLayer::serialization_stats)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SerializationStats {
+    /// The amount of layers whose tile entity storage was left out because it was empty
+    pub skipped_entity_maps: usize,
+    /// The amount of dense tiles holding the default tile data that were left out
+    pub skipped_default_tiles: usize,
+    /// The amount of tiles that were written
+    pub stored_tiles: usize,
+}
+
+impl std::ops::AddAssign for SerializationStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.skipped_entity_maps += rhs.skipped_entity_maps;
+        self.skipped_default_tiles += rhs.skipped_default_tiles;
+        self.stored_tiles += rhs.stored_tiles;
+    }
+}
+
+/// The tile entities of a chunk layer, stored as chosen by a [`TileEntityStorage`]
+///
+/// Serialized untagged so sparse storage keeps the format of the plain maps used before.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub enum TileEntities {
+    /// Tile entities keyed by their packed [`ChunkCell`]
+    Sparse(SparseMap<u64, Entity>),
+    /// A slot for every cell of the chunk, row by row
+    Dense {
+        /// The dimensions of the chunk
+        dimensions: UVec2,
+        /// The tile entity of every cell
+        entities: Vec<Option<Entity>>,
+    },
+}
+
+impl Default for TileEntities {
+    fn default() -> Self {
+        Self::Sparse(SparseMap::default())
+    }
+}
+
+impl Hash for TileEntities {
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        let mut pairs: Vec<_> = self.iter().collect();
+        pairs.sort_by_key(|(chunk_cell, _)| (chunk_cell.x(), chunk_cell.y()));
+        Hash::hash(&pairs, h);
+    }
+}
+
+impl TileEntities {
+    /// Creates an empty storage of the given kind for a chunk with the given dimensions
+    pub fn new(storage: TileEntityStorage, dimensions: UVec2) -> Self {
+        match storage {
+            TileEntityStorage::Sparse => Self::Sparse(SparseMap::default()),
+            TileEntityStorage::Dense => Self::Dense {
+                dimensions,
+                entities: vec![None; (dimensions.x * dimensions.y) as usize],
+            },
+        }
+    }
+
+    /// Returns the kind of storage used
+    pub fn storage(&self) -> TileEntityStorage {
+        match self {
+            TileEntities::Sparse(_) => TileEntityStorage::Sparse,
+            TileEntities::Dense { .. } => TileEntityStorage::Dense,
+        }
+    }
+
+    /// Moves every tile entity into a new storage of the given kind
+    pub fn convert(&mut self, storage: TileEntityStorage, dimensions: UVec2) {
+        if self.storage() == storage {
+            return;
+        }
+        let mut converted = Self::new(storage, dimensions);
+        for (chunk_cell, entity) in self.iter() {
+            converted.insert(chunk_cell, entity);
+        }
+        *self = converted;
+    }
+
+    /// Gets the tile entity at the given [`ChunkCell`]
+    pub fn get(&self, chunk_cell: ChunkCell) -> Option<Entity> {
+        match self {
+            TileEntities::Sparse(map) => map.get(&pack(chunk_cell)).cloned(),
+            TileEntities::Dense {
+                dimensions,
+                entities,
+            } => dense_index(*dimensions, chunk_cell).and_then(|index| entities[index]),
+        }
+    }
+
+    /// Sets the tile entity at the given [`ChunkCell`]. Cells outside of a dense storage are ignored
+    pub fn insert(&mut self, chunk_cell: ChunkCell, entity: Entity) {
+        match self {
+            TileEntities::Sparse(map) => {
+                map.insert(pack(chunk_cell), entity);
+            }
+            TileEntities::Dense {
+                dimensions,
+                entities,
+            } => {
+                if let Some(index) = dense_index(*dimensions, chunk_cell) {
+                    entities[index] = Some(entity);
+                }
+            }
+        }
+    }
+
+    /// Removes the tile entity at the given [`ChunkCell`] and returns it
+    pub fn remove(&mut self, chunk_cell: ChunkCell) -> Option<Entity> {
+        match self {
+            TileEntities::Sparse(map) => map.remove(&pack(chunk_cell)),
+            TileEntities::Dense {
+                dimensions,
+                entities,
+            } => dense_index(*dimensions, chunk_cell).and_then(|index| entities[index].take()),
+        }
+    }
+
+    /// Iterates over every [`ChunkCell`] that has a tile entity
+    pub fn iter(&self) -> Box<dyn Iterator<Item = (ChunkCell, Entity)> + '_> {
+        match self {
+            TileEntities::Sparse(map) => Box::new(
+                map.iter()
+                    .map(|(number, entity)| (unpack(*number), *entity)),
+            ),
+            TileEntities::Dense {
+                dimensions,
+                entities,
+            } => Box::new(entities.iter().enumerate().filter_map(|(index, entity)| {
+                let index = index as u32;
+                entity.map(|entity| {
+                    (
+                        ChunkCell::new(
+                            (index % dimensions.x) as i32,
+                            (index / dimensions.x) as i32,
+                        ),
+                        entity,
+                    )
+                })
+            })),
+        }
+    }
+
+    /// Returns true if no cell has a tile entity
+    pub fn is_empty(&self) -> bool {
+        match self {
+            TileEntities::Sparse(map) => map.is_empty(),
+            TileEntities::Dense { entities, .. } => entities.iter().all(Option::is_none),
+        }
+    }
+
+    /// Returns true if the storage holds nothing worth serializing. Only empty sparse storage is skipped
+    /// since it is what missing storage deserializes to, empty dense storage keeps its slots
+    #[cfg(any(feature = "hex", feature = "square"))]
+    pub(crate) fn skip_serializing(&self) -> bool {
+        matches!(self, TileEntities::Sparse(map) if map.is_empty())
+    }
+
+    /// Returns the approximate amount of heap memory used in bytes, including unused capacity
+    pub fn heap_size(&self) -> usize {
+        match self {
+            TileEntities::Sparse(map) => sparse_map_heap_size(map),
+            TileEntities::Dense { entities, .. } => {
+                entities.capacity() * size_of::<Option<Entity>>()
+            }
+        }
+    }
+
+    /// Releases unused capacity
+    pub fn shrink_to_fit(&mut self) {
+        match self {
+            TileEntities::Sparse(map) => map.shrink_to_fit(),
+            TileEntities::Dense { entities, .. } => entities.shrink_to_fit(),
+        }
+    }
+
+    /// Maps every tile entity with the given [`EntityMapper`]
+    pub fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        match self {
+            TileEntities::Sparse(map) => {
+                for entity in map.values_mut() {
+                    *entity = entity_mapper.map_entity(*entity);
+                }
+            }
+            TileEntities::Dense { entities, .. } => {
+                for entity in entities.iter_mut().flatten() {
+                    *entity = entity_mapper.map_entity(*entity);
+                }
+            }
+        }
+    }
+}
+
+fn pack(chunk_cell: ChunkCell) -> u64 {
+    ((chunk_cell.x() as u64) << 32) | chunk_cell.y() as u32 as u64
+}
+
+fn unpack(number: u64) -> ChunkCell {
+    ChunkCell::new((number >> 32) as u32 as i32, number as u32 as i32)
+}
+
+fn dense_index(dimensions: UVec2, chunk_cell: ChunkCell) -> Option<usize> {
+    if chunk_cell.x() < 0
+        || chunk_cell.y() < 0
+        || chunk_cell.x() as u32 >= dimensions.x
+        || chunk_cell.y() as u32 >= dimensions.y
+    {
+        return None;
+    }
+    Some((chunk_cell.y() as u32 * dimensions.x + chunk_cell.x() as u32) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TileEntities, TileEntityStorage};
+    use crate::map::chunk::ChunkCell;
+    use bevy::math::UVec2;
+    use bevy::prelude::Entity;
+
+    #[test]
+    fn test_tile_entities_convert() {
+        let mut tile_entities = TileEntities::default();
+        tile_entities.insert(ChunkCell::new(1, 2), Entity::from_raw(7));
+        tile_entities.insert(ChunkCell::new(3, 0), Entity::from_raw(9));
+
+        tile_entities.convert(TileEntityStorage::Dense, UVec2::new(4, 4));
+        assert_eq!(tile_entities.storage(), TileEntityStorage::Dense);
+        assert_eq!(
+            tile_entities.get(ChunkCell::new(1, 2)),
+            Some(Entity::from_raw(7))
+        );
+        assert_eq!(
+            tile_entities.get(ChunkCell::new(3, 0)),
+            Some(Entity::from_raw(9))
+        );
+        assert_eq!(tile_entities.get(ChunkCell::new(0, 0)), None);
+
+        tile_entities.convert(TileEntityStorage::Sparse, UVec2::new(4, 4));
+        let mut entities: Vec<_> = tile_entities.iter().collect();
+        entities.sort_by_key(|(chunk_cell, _)| chunk_cell.x());
+        assert_eq!(
+            entities,
+            vec![
+                (ChunkCell::new(1, 2), Entity::from_raw(7)),
+                (ChunkCell::new(3, 0), Entity::from_raw(9))
+            ]
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Reusing the memory of chunk layers between maps.
+//!
+//! Games that load and unload whole maps, such as levels on consoles or embedded targets, allocate and free
+//! the same amount of chunk storage over and over, fragmenting memory. A [`ChunkStoragePool`] keeps the
+//! buffers of released chunk layers around and hands them to new chunk layers of the same size instead.
+//!
+//! Release the storage of a map into the pool with
+//! [`TilemapManager::despawn_tilemap_pooled`](crate::tilemap_manager::TilemapManager::despawn_tilemap_pooled)
+//! and build the next map from the pool with
+//! [`TilemapBuilder::with_storage_pool`](crate::tilemap_builder::TilemapBuilder::with_storage_pool).
+//! [`ChunkStoragePool::clear`] frees every pooled buffer at once.
+
+use bevy::prelude::Resource;
+use std::mem::size_of;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+/// The buffers held by a [`ChunkStoragePool`]
+struct PooledBuffers<T> {
+    buffers: Vec<Vec<T>>,
+    max_buffers: usize,
+    reused: u64,
+    allocated: u64,
+}
+
+/// A pool of tile data buffers shared by chunk layers. Cloning the pool returns another handle to the same
+/// buffers, so it can be kept as a resource and handed to builders at the same time.
+///
+/// Only layers that store their tile data in one contiguous buffer, such as dense square layers, use the
+/// pool. Other layers allocate as usual.
+#[derive(Resource)]
+pub struct ChunkStoragePool<T>
+where
+    T: Send + Sync + 'static,
+{
+    buffers: Arc<Mutex<PooledBuffers<T>>>,
+}
+
+impl<T> Clone for ChunkStoragePool<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            buffers: Arc::clone(&self.buffers),
+        }
+    }
+}
+
+impl<T> Default for ChunkStoragePool<T>
+where
+    T: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self::new(4096)
+    }
+}
+
+impl<T> ChunkStoragePool<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Creates an empty pool that keeps at most `max_buffers` released buffers. Buffers released into a full
+    /// pool are freed
+    pub fn new(max_buffers: usize) -> Self {
+        Self {
+            buffers: Arc::new(Mutex::new(PooledBuffers {
+                buffers: vec![],
+                max_buffers,
+                reused: 0,
+                allocated: 0,
+            })),
+        }
+    }
+
+    /// Locks the buffers. A panic while holding the lock can't leave the buffers in an invalid state, so
+    /// poisoning is ignored
+    fn lock(&self) -> MutexGuard<'_, PooledBuffers<T>> {
+        self.buffers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns an empty buffer with room for at least `capacity` tiles, reusing a pooled buffer if one is
+    /// large enough
+    pub fn take(&self, capacity: usize) -> Vec<T> {
+        let mut pooled = self.lock();
+        match pooled
+            .buffers
+            .iter()
+            .position(|buffer| buffer.capacity() >= capacity)
+        {
+            Some(index) => {
+                pooled.reused += 1;
+                pooled.buffers.swap_remove(index)
+            }
+            None => {
+                pooled.allocated += 1;
+                Vec::with_capacity(capacity)
+            }
+        }
+    }
+
+    /// Clears the buffer and keeps it for later layers, or frees it if the pool is full
+    pub fn release(&self, mut buffer: Vec<T>) {
+        let mut pooled = self.lock();
+        if buffer.capacity() == 0 || pooled.buffers.len() >= pooled.max_buffers {
+            return;
+        }
+        buffer.clear();
+        pooled.buffers.push(buffer);
+    }
+
+    /// Returns the amount of buffers held by the pool
+    pub fn len(&self) -> usize {
+        self.lock().buffers.len()
+    }
+
+    /// Returns true if the pool holds no buffers
+    pub fn is_empty(&self) -> bool {
+        self.lock().buffers.is_empty()
+    }
+
+    /// Returns the amount of bytes held by the buffers of the pool
+    pub fn heap_size(&self) -> usize {
+        self.lock()
+            .buffers
+            .iter()
+            .map(|buffer| buffer.capacity() * size_of::<T>())
+            .sum()
+    }
+
+    /// Returns how many buffers were handed out from the pool and how many had to be allocated
+    pub fn reused_and_allocated(&self) -> (u64, u64) {
+        let pooled = self.lock();
+        (pooled.reused, pooled.allocated)
+    }
+
+    /// Frees every buffer held by the pool
+    pub fn clear(&self) {
+        self.lock().buffers = vec![];
+    }
+}
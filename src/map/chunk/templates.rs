@@ -0,0 +1,140 @@
+//! Dense chunk data shared between chunks with identical contents.
+//!
+//! Large maps often repeat the same chunk many times, such as open ocean or standard forest blocks. A
+//! [`ChunkTemplate`] holds the dense tile data of such a chunk once, and every chunk layer with the same
+//! contents refers to it instead of storing its own copy. Templates are immutable, the first write to a chunk
+//! layer using a template copies the data into the layer, leaving every other chunk untouched.
+//!
+//! [`TilemapBuilder::with_chunk_templates`](crate::tilemap_builder::TilemapBuilder::with_chunk_templates)
+//! detects identical dense chunk layers by their content hash while building the map.
+
+use bevy::utils::HashMap;
+use lettuces::storage::grid::Grid;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+use std::sync::Arc;
+
+/// Immutable dense tile data shared by every chunk layer with identical contents
+pub struct ChunkTemplate<T> {
+    grid: Arc<Grid<T>>,
+}
+
+impl<T> Clone for ChunkTemplate<T> {
+    fn clone(&self) -> Self {
+        Self {
+            grid: Arc::clone(&self.grid),
+        }
+    }
+}
+
+impl<T> Default for ChunkTemplate<T>
+where
+    T: Clone + Default,
+{
+    fn default() -> Self {
+        Self::new(Grid::new(0, 0))
+    }
+}
+
+impl<T> Hash for ChunkTemplate<T>
+where
+    T: Hash,
+{
+    fn hash<H: Hasher>(&self, h: &mut H) {
+        Hash::hash(self.grid.as_ref(), h);
+    }
+}
+
+impl<T> ChunkTemplate<T> {
+    /// Creates a new template that isn't shared yet
+    pub fn new(grid: Grid<T>) -> Self {
+        Self {
+            grid: Arc::new(grid),
+        }
+    }
+
+    /// Returns the tile data of the template
+    pub fn grid(&self) -> &Grid<T> {
+        &self.grid
+    }
+
+    /// Returns the amount of chunk layers using the template
+    pub fn shared_count(&self) -> usize {
+        Arc::strong_count(&self.grid)
+    }
+
+    /// Returns the approximate heap memory of the template in bytes, divided between every chunk layer using
+    /// it
+    pub fn shared_heap_size(&self) -> usize {
+        let (rows, columns) = self.grid.size();
+        rows * columns * size_of::<T>() / self.shared_count()
+    }
+
+    /// Returns the tile data, copying it if other chunk layers still use the template
+    pub fn into_grid(self) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Arc::try_unwrap(self.grid).unwrap_or_else(|grid| grid.as_ref().clone())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T> serde::Serialize for ChunkTemplate<T>
+where
+    T: Clone + Copy + Default + PartialEq + serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        crate::map::chunk::default_runs::serialize(self.grid(), serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for ChunkTemplate<T>
+where
+    T: Clone + Default + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::map::chunk::default_runs::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// The templates found while building a map, keyed by the content hash of their tile data
+pub struct ChunkTemplates<T> {
+    templates: HashMap<u64, ChunkTemplate<T>>,
+}
+
+impl<T> Default for ChunkTemplates<T> {
+    fn default() -> Self {
+        Self {
+            templates: HashMap::default(),
+        }
+    }
+}
+
+impl<T> ChunkTemplates<T>
+where
+    T: Hash,
+{
+    /// Returns the template with the same contents as the grid, creating it from the grid if there is none
+    pub fn share(&mut self, grid: Grid<T>) -> ChunkTemplate<T> {
+        let mut hasher = DefaultHasher::new();
+        grid.size().hash(&mut hasher);
+        grid.hash(&mut hasher);
+        self.templates
+            .entry(hasher.finish())
+            .or_insert_with(|| ChunkTemplate::new(grid))
+            .clone()
+    }
+
+    /// Returns the amount of distinct templates
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    /// Returns true if there are no templates
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+}
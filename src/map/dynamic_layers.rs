@@ -0,0 +1,135 @@
+//! Layers registered at runtime by name instead of as a variant of the [`MapLayer`](crate::map::MapLayer)
+//! enum.
+//!
+//! Modding-friendly games often need data layers that mods add without recompiling the layer enum, which is
+//! also capped at 64 variants. Every [`Tilemap`](crate::map::Tilemap) has a [`DynamicLayerRegistry`] that
+//! interns layer names into [`DynamicLayerId`]s. The id of a layer is stable for the lifetime of the map and
+//! is saved along with it.
+//!
+//! Layers are stored in the chunks like any other layer, keyed by [`DynamicLayerId::to_bits`]. The bits of
+//! dynamic layers are never a power of two so they can't collide with the bits of a
+//! [`MapLayer`](crate::map::MapLayer) variant. Register layers at build time with
+//! [`TilemapBuilder::add_dynamic_layer`](crate::tilemap_builder::TilemapBuilder::add_dynamic_layer) or at
+//! runtime through [`Tilemap::dynamic_layers_mut`](crate::map::Tilemap::dynamic_layers_mut), then fill them
+//! with [`TilemapManager::add_dynamic_layer`](crate::tilemap_manager::TilemapManager::add_dynamic_layer).
+
+#[cfg(feature = "reflect")]
+use bevy::prelude::Reflect;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The interned id of a layer registered in a [`DynamicLayerRegistry`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct DynamicLayerId(u32);
+
+impl DynamicLayerId {
+    /// Returns the order the layer was registered in, starting at 0
+    pub fn index(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns the key of the layer in the data of each [`Chunk`](crate::map::chunk::Chunk). Always odd and at
+    /// least 3, so never the bits of a [`MapLayer`](crate::map::MapLayer) variant
+    pub fn to_bits(&self) -> u64 {
+        u64::from(self.0) * 2 + 3
+    }
+
+    /// Returns the id whose layer is stored under the given bits, if they are the bits of a dynamic layer
+    pub fn from_bits(map_layer: u64) -> Option<Self> {
+        if map_layer < 3 || map_layer & 1 == 0 {
+            return None;
+        }
+        u32::try_from((map_layer - 3) / 2).ok().map(Self)
+    }
+}
+
+/// The names of the dynamic layers of a [`Tilemap`](crate::map::Tilemap). See the [module docs](self) for
+/// details
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect))]
+pub struct DynamicLayerRegistry {
+    names: Vec<String>,
+}
+
+impl DynamicLayerRegistry {
+    /// Creates a registry without any layers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a layer with the given name and returns its id. Registering a name twice returns the id it
+    /// was first registered with
+    pub fn register(&mut self, name: &str) -> DynamicLayerId {
+        if let Some(id) = self.get(name) {
+            return id;
+        }
+        assert!(
+            self.names.len() < u32::MAX as usize,
+            "Reached the maximum of dynamic layers"
+        );
+        self.names.push(name.to_string());
+        DynamicLayerId(self.names.len() as u32 - 1)
+    }
+
+    /// Returns the id of the layer with the given name
+    pub fn get(&self, name: &str) -> Option<DynamicLayerId> {
+        self.names
+            .iter()
+            .position(|registered| registered == name)
+            .map(|index| DynamicLayerId(index as u32))
+    }
+
+    /// Returns the name of the layer with the given id
+    pub fn name(&self, id: DynamicLayerId) -> Option<&str> {
+        self.names.get(id.0 as usize).map(String::as_str)
+    }
+
+    /// Returns true if the id belongs to a layer of this registry
+    pub fn contains(&self, id: DynamicLayerId) -> bool {
+        (id.0 as usize) < self.names.len()
+    }
+
+    /// Returns an iterator over the id and name of every layer in the order they were registered
+    pub fn iter(&self) -> impl Iterator<Item = (DynamicLayerId, &str)> + '_ {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (DynamicLayerId(index as u32), name.as_str()))
+    }
+
+    /// Returns the amount of registered layers
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if no layer is registered
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DynamicLayerId, DynamicLayerRegistry};
+
+    #[test]
+    fn test_dynamic_layer_registry() {
+        let mut registry = DynamicLayerRegistry::new();
+        let heat = registry.register("mod_a:heat");
+        let mana = registry.register("mod_b:mana");
+        assert_eq!(registry.register("mod_a:heat"), heat);
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.get("mod_b:mana"), Some(mana));
+        assert_eq!(registry.name(heat), Some("mod_a:heat"));
+        assert_eq!(registry.get("mod_c:water"), None);
+
+        for id in [heat, mana] {
+            assert!(!id.to_bits().is_power_of_two());
+            assert_eq!(DynamicLayerId::from_bits(id.to_bits()), Some(id));
+        }
+        assert_eq!(DynamicLayerId::from_bits(1 << 4), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! Tile data for layers whose cells reference entities managed elsewhere.
+//!
+//! [`Entity`] doesn't have a meaningful [`Default`], so storing it directly as tile data forces the use of
+//! [`Entity::PLACEHOLDER`] for empty cells. Use [`EntityLayer`] as the `TileData` of a map instead. The
+//! [`EntityLayerPlugin`] clears cells whose entity was despawned, and [`map_entity_layer_entities`] remaps
+//! the entities in a chunk when it is loaded into a different world.
+
+use crate::map::chunk::{Chunk, ChunkCell, ChunkLayer};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::ecs::entity::{Entities, EntityMapper, MapEntities};
+use bevy::prelude::{DetectChangesMut, Entity, Query};
+use std::marker::PhantomData;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Tile data referencing an optional [`Entity`]. Defaults to an empty cell
+#[derive(Clone, Copy, Default, Hash, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntityLayer(pub Option<Entity>);
+
+impl EntityLayer {
+    /// An empty cell
+    pub const EMPTY: EntityLayer = EntityLayer(None);
+
+    /// Creates tile data referencing the given entity
+    pub fn new(entity: Entity) -> Self {
+        Self(Some(entity))
+    }
+
+    /// Returns the referenced entity if there is one
+    pub fn entity(&self) -> Option<Entity> {
+        self.0
+    }
+
+    /// Returns true if the cell doesn't reference an entity
+    pub fn is_empty(&self) -> bool {
+        self.0.is_none()
+    }
+}
+
+impl From<Entity> for EntityLayer {
+    fn from(entity: Entity) -> Self {
+        Self::new(entity)
+    }
+}
+
+impl MapEntities for EntityLayer {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        if let Some(entity) = self.0.as_mut() {
+            *entity = entity_mapper.map_entity(*entity);
+        }
+    }
+}
+
+/// Maps the entities referenced by every [`EntityLayer`] cell in the given chunk.
+///
+/// [`Chunk`]s only map their tile entities through [`MapEntities`], so call this as well when moving a chunk
+/// of [`EntityLayer`]s into a different world.
+pub fn map_entity_layer_entities<MapChunk, M: EntityMapper>(
+    chunk: &mut Chunk<MapChunk, EntityLayer>,
+    entity_mapper: &mut M,
+) where
+    MapChunk: ChunkLayer<EntityLayer> + Send + Sync + 'static + Default,
+{
+    for layer in chunk.data.values_mut() {
+        for chunk_cell in ChunkCell::iter_chunk(layer.get_chunk_dimensions()) {
+            if let Some(tile_data) = layer.get_tile_data_mut(chunk_cell) {
+                tile_data.map_entities(entity_mapper);
+            }
+        }
+    }
+}
+
+/// Plugin that clears [`EntityLayer`] cells whose entity was despawned
+pub struct EntityLayerPlugin<MapChunk> {
+    ph: PhantomData<MapChunk>,
+}
+
+impl<MapChunk> Default for EntityLayerPlugin<MapChunk> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<MapChunk> Plugin for EntityLayerPlugin<MapChunk>
+where
+    MapChunk: ChunkLayer<EntityLayer> + Send + Sync + 'static + Default,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, clear_despawned_entity_layer_cells::<MapChunk>);
+    }
+}
+
+/// Clears every [`EntityLayer`] cell whose entity no longer exists.
+///
+/// Checks every cell of every chunk, so on very large maps consider running it less often than every frame.
+/// Only chunks that had cells cleared are marked as changed.
+pub fn clear_despawned_entity_layer_cells<MapChunk>(
+    mut chunks: Query<&mut Chunk<MapChunk, EntityLayer>>,
+    entities: &Entities,
+) where
+    MapChunk: ChunkLayer<EntityLayer> + Send + Sync + 'static + Default,
+{
+    for mut chunk in chunks.iter_mut() {
+        let mut cleared = false;
+        for layer in chunk.bypass_change_detection().data.values_mut() {
+            for chunk_cell in ChunkCell::iter_chunk(layer.get_chunk_dimensions()) {
+                let Some(tile_data) = layer.get_tile_data_mut(chunk_cell) else {
+                    continue;
+                };
+                if let Some(entity) = tile_data.0 {
+                    if !entities.contains(entity) {
+                        *tile_data = EntityLayer::EMPTY;
+                        cleared = true;
+                    }
+                }
+            }
+        }
+        if cleared {
+            chunk.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clear_despawned_entity_layer_cells, EntityLayer};
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::schedule::Schedule;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_despawned_entities_are_cleared() {
+        let mut world = World::new();
+        let kept = world.spawn_empty().id();
+        let despawned = world.spawn_empty().id();
+
+        let mut system_state: SystemState<(
+            Commands,
+            SquareTilemapManager<EntityLayer, MapLayers>,
+        )> = SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<EntityLayer, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![EntityLayer::EMPTY; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager
+            .sets_tile_data(kept.into(), Cell::new(0, 0))
+            .unwrap();
+        tilemap_manager
+            .sets_tile_data(despawned.into(), Cell::new(3, 2))
+            .unwrap();
+        world.despawn(despawned);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(clear_despawned_entity_layer_cells::<SquareChunkLayer<EntityLayer>>);
+        schedule.run(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(
+            tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(),
+            EntityLayer::new(kept)
+        );
+        assert!(tilemap_manager
+            .get_tile_data(Cell::new(3, 2))
+            .unwrap()
+            .is_empty());
+    }
+}
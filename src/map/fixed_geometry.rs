@@ -0,0 +1,171 @@
+use bevy::math::{I64Vec2, Vec2};
+use bevy::prelude::Component;
+use lettuces::cell::Cell;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The amount of millipixels in one world unit
+pub const MILLIPIXELS_PER_UNIT: i64 = 1000;
+
+/// Converts a world position into millipixels, rounding to the closest millipixel. Only use it on input
+/// that is the same on every machine, such as positions from a level file
+pub fn to_millipixels(world_pos: Vec2) -> I64Vec2 {
+    (world_pos * MILLIPIXELS_PER_UNIT as f32)
+        .round()
+        .as_i64vec2()
+}
+
+/// Converts a position in millipixels into a world position, for rendering
+pub fn from_millipixels(fixed_pos: I64Vec2) -> Vec2 {
+    fixed_pos.as_vec2() / MILLIPIXELS_PER_UNIT as f32
+}
+
+/// An axis aligned rect in millipixels. `min` and `max` are both part of the rect
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FixedRect {
+    /// The bottom left corner
+    pub min: I64Vec2,
+    /// The top right corner
+    pub max: I64Vec2,
+}
+
+impl FixedRect {
+    /// Creates a rect out of any two opposite corners
+    pub fn from_corners(a: I64Vec2, b: I64Vec2) -> Self {
+        Self {
+            min: a.min(b),
+            max: a.max(b),
+        }
+    }
+
+    /// Returns true if the rect has no area
+    pub fn is_empty(&self) -> bool {
+        self.max.cmple(self.min).any()
+    }
+
+    /// Returns the smallest rect containing both rects
+    pub fn union(&self, other: FixedRect) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Returns true if the two rects share some area. Rects that only touch don't overlap
+    pub fn overlaps(&self, other: FixedRect) -> bool {
+        self.min.max(other.min).cmplt(self.max.min(other.max)).all()
+    }
+
+    /// Returns true if the rect shares some area with the circle
+    pub fn overlaps_circle(&self, center: I64Vec2, radius: i64) -> bool {
+        let offset = center.clamp(self.min, self.max) - center;
+        let distance_squared =
+            offset.x as i128 * offset.x as i128 + offset.y as i128 * offset.y as i128;
+        distance_squared < radius as i128 * radius as i128
+    }
+}
+
+/// The world space layout of a square tilemap in fixed point millipixels, for deterministic lockstep games
+/// where floating point results may differ between machines. Requires the `fixed_point` feature.
+///
+/// Every function only uses integer math so it returns bit identical results everywhere. It mirrors
+/// [`TilemapGeometry`](crate::map::TilemapGeometry) and is used by the fixed point world space queries of the
+/// [`TilemapManager`](crate::tilemap_manager::TilemapManager) such as
+/// [`cells_in_fixed_aabb`](crate::tilemap_manager::TilemapManager::cells_in_fixed_aabb).
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FixedTilemapGeometry {
+    /// The position of the bottom left corner of cell (0, 0) in millipixels
+    pub origin: I64Vec2,
+    /// The size of a single cell in millipixels. Must be positive
+    pub cell_size: I64Vec2,
+}
+
+impl Default for FixedTilemapGeometry {
+    fn default() -> Self {
+        Self {
+            origin: I64Vec2::ZERO,
+            cell_size: I64Vec2::splat(MILLIPIXELS_PER_UNIT),
+        }
+    }
+}
+
+impl FixedTilemapGeometry {
+    /// Creates a new FixedTilemapGeometry with the given origin and cell size in millipixels
+    pub fn new(origin: I64Vec2, cell_size: I64Vec2) -> Self {
+        Self { origin, cell_size }
+    }
+
+    /// Returns the [`Cell`] containing the given position
+    pub fn world_to_cell(&self, fixed_pos: I64Vec2) -> Cell {
+        self.world_to_cell_offset(fixed_pos).0
+    }
+
+    /// Returns the [`Cell`] containing the given position and the offset of the position from the bottom left
+    /// corner of the cell in millipixels, so entities can be placed inside of cells without losing precision
+    pub fn world_to_cell_offset(&self, fixed_pos: I64Vec2) -> (Cell, I64Vec2) {
+        let local = fixed_pos - self.origin;
+        let cell = local.div_euclid(self.cell_size);
+        (
+            Cell::new(cell.x as i32, cell.y as i32),
+            local.rem_euclid(self.cell_size),
+        )
+    }
+
+    /// Returns the position of the given corner of the [`Cell`]. Corners are numbered counter clockwise
+    /// starting at the bottom left corner and wrap around, so corner 4 is corner 0 again
+    pub fn cell_corner(&self, cell: Cell, corner: usize) -> I64Vec2 {
+        let offset = match corner % 4 {
+            0 => I64Vec2::new(0, 0),
+            1 => I64Vec2::new(1, 0),
+            2 => I64Vec2::new(1, 1),
+            _ => I64Vec2::new(0, 1),
+        };
+        self.origin + (I64Vec2::new(cell.x as i64, cell.y as i64) + offset) * self.cell_size
+    }
+
+    /// Returns the position of the center of the given [`Cell`]. Odd cell sizes round the center down
+    pub fn cell_center(&self, cell: Cell) -> I64Vec2 {
+        self.cell_corner(cell, 0) + self.cell_size.div_euclid(I64Vec2::splat(2))
+    }
+
+    /// Returns the rect covered by the given [`Cell`]
+    pub fn cell_rect(&self, cell: Cell) -> FixedRect {
+        FixedRect::from_corners(self.cell_corner(cell, 0), self.cell_corner(cell, 2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_millipixels, FixedRect, FixedTilemapGeometry};
+    use bevy::math::{I64Vec2, Vec2};
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_fixed_geometry() {
+        let geometry = FixedTilemapGeometry::new(I64Vec2::splat(-8000), I64Vec2::splat(16000));
+        assert_eq!(
+            to_millipixels(Vec2::new(-9.0, 40.001)),
+            I64Vec2::new(-9000, 40001)
+        );
+        assert_eq!(
+            geometry.world_to_cell_offset(I64Vec2::new(-9000, 40001)),
+            (Cell::new(-1, 3), I64Vec2::new(15000, 1))
+        );
+        assert_eq!(
+            geometry.cell_center(Cell::new(1, 0)),
+            I64Vec2::new(16000, 0)
+        );
+        assert_eq!(
+            geometry.cell_rect(Cell::new(0, 0)),
+            FixedRect::from_corners(I64Vec2::splat(8000), I64Vec2::splat(-8000))
+        );
+
+        let rect = geometry.cell_rect(Cell::new(0, 0));
+        assert!(rect.overlaps_circle(I64Vec2::new(-8500, 0), 501));
+        assert!(!rect.overlaps_circle(I64Vec2::new(-8500, 0), 500));
+        assert!(!rect.overlaps(geometry.cell_rect(Cell::new(1, 0))));
+        assert!(rect.overlaps(FixedRect::from_corners(I64Vec2::ZERO, I64Vec2::splat(1))));
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::math::{Rect, Vec2};
+use bevy::prelude::Component;
+use lettuces::cell::Cell;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The world space layout of a square tilemap. Insert it on the map entity to use the world space queries of
+/// the [`TilemapManager`](crate::tilemap_manager::TilemapManager).
+///
+/// [`Cell`] (x, y) covers the rectangle from `origin + (x, y) * cell_size` to
+/// `origin + (x + 1, y + 1) * cell_size`.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TilemapGeometry {
+    /// The world position of the bottom left corner of cell (0, 0)
+    pub origin: Vec2,
+    /// The world size of a single cell
+    pub cell_size: Vec2,
+}
+
+impl Default for TilemapGeometry {
+    fn default() -> Self {
+        Self {
+            origin: Vec2::ZERO,
+            cell_size: Vec2::ONE,
+        }
+    }
+}
+
+impl TilemapGeometry {
+    /// Creates a new TilemapGeometry with the given origin and cell size
+    pub fn new(origin: Vec2, cell_size: Vec2) -> Self {
+        Self { origin, cell_size }
+    }
+
+    /// Returns the [`Cell`] containing the given world position
+    pub fn world_to_cell(&self, world_pos: Vec2) -> Cell {
+        let cell = self.world_to_cell_f32(world_pos).floor();
+        Cell::new(cell.x as i32, cell.y as i32)
+    }
+
+    /// Returns the given world position in fractional cell space, where [`Cell`] (x, y) covers `x..x + 1` and
+    /// `y..y + 1`. Keeps the position inside of the cell so entities can be placed and moved between tiles
+    /// without losing precision
+    pub fn world_to_cell_f32(&self, world_pos: Vec2) -> Vec2 {
+        (world_pos - self.origin) / self.cell_size
+    }
+
+    /// Returns the world position of the given position in fractional cell space. The inverse of
+    /// [`Self::world_to_cell_f32`]
+    pub fn cell_f32_to_world(&self, cell_pos: Vec2) -> Vec2 {
+        self.origin + cell_pos * self.cell_size
+    }
+
+    /// Returns the world position of the center of the given [`Cell`]
+    pub fn cell_center(&self, cell: Cell) -> Vec2 {
+        self.cell_rect(cell).center()
+    }
+
+    /// Returns the world position of the given corner of the [`Cell`]. Corners are numbered counter clockwise
+    /// starting at the bottom left corner and wrap around, so corner 4 is corner 0 again
+    pub fn cell_corner(&self, cell: Cell, corner: usize) -> Vec2 {
+        let offset = match corner % 4 {
+            0 => Vec2::new(0.0, 0.0),
+            1 => Vec2::new(1.0, 0.0),
+            2 => Vec2::new(1.0, 1.0),
+            _ => Vec2::new(0.0, 1.0),
+        };
+        self.cell_f32_to_world(Vec2::new(cell.x as f32, cell.y as f32) + offset)
+    }
+
+    /// Returns the world space rectangle covered by the given [`Cell`]
+    pub fn cell_rect(&self, cell: Cell) -> Rect {
+        Rect::from_corners(self.cell_corner(cell, 0), self.cell_corner(cell, 2))
+    }
+}
+
+/// Returns true if the two rects share some area. Rects that only touch don't overlap
+pub(crate) fn rect_overlaps_rect(a: Rect, b: Rect) -> bool {
+    !a.intersect(b).is_empty()
+}
+
+/// Returns true if the rect shares some area with the circle
+pub(crate) fn rect_overlaps_circle(rect: Rect, center: Vec2, radius: f32) -> bool {
+    let closest = center.clamp(rect.min, rect.max);
+    closest.distance_squared(center) < radius * radius
+}
+
+/// Returns true if the rect shares some area with the polygon. Polygons with less than three points cover
+/// nothing
+pub(crate) fn rect_overlaps_polygon(rect: Rect, polygon: &[Vec2]) -> bool {
+    if polygon.len() < 3 {
+        return false;
+    }
+    // Clip the polygon to the rect one edge at a time. Clipping against a convex region keeps the area of
+    // concave polygons correct even though the result may contain degenerate edges
+    let mut clipped = polygon.to_vec();
+    for (axis, bound, keep_below) in [
+        (0, rect.min.x, false),
+        (0, rect.max.x, true),
+        (1, rect.min.y, false),
+        (1, rect.max.y, true),
+    ] {
+        let inside = |point: Vec2| match keep_below {
+            true => point[axis] <= bound,
+            false => point[axis] >= bound,
+        };
+        let input = std::mem::take(&mut clipped);
+        for (a, b) in polygon_edges(&input) {
+            if inside(a) {
+                clipped.push(a);
+            }
+            if inside(a) != inside(b) {
+                let t = (bound - a[axis]) / (b[axis] - a[axis]);
+                clipped.push(a.lerp(b, t));
+            }
+        }
+        if clipped.len() < 3 {
+            return false;
+        }
+    }
+    polygon_area(&clipped) > f32::EPSILON * rect.size().x * rect.size().y
+}
+
+/// Returns the bounding rect of the polygon
+pub(crate) fn polygon_bounds(polygon: &[Vec2]) -> Rect {
+    let mut bounds = Rect {
+        min: Vec2::INFINITY,
+        max: Vec2::NEG_INFINITY,
+    };
+    for point in polygon {
+        bounds.min = bounds.min.min(*point);
+        bounds.max = bounds.max.max(*point);
+    }
+    bounds
+}
+
+fn polygon_edges(polygon: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| (*a, *b))
+}
+
+/// The unsigned area of the polygon
+fn polygon_area(polygon: &[Vec2]) -> f32 {
+    (polygon_edges(polygon)
+        .map(|(a, b)| a.perp_dot(b))
+        .sum::<f32>()
+        / 2.0)
+        .abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rect_overlaps_circle, rect_overlaps_polygon, TilemapGeometry};
+    use bevy::math::Vec2;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_geometry_conversions() {
+        let geometry = TilemapGeometry::new(Vec2::new(-8.0, -8.0), Vec2::splat(16.0));
+        assert_eq!(geometry.world_to_cell(Vec2::ZERO), Cell::new(0, 0));
+        assert_eq!(
+            geometry.world_to_cell(Vec2::new(-9.0, 40.0)),
+            Cell::new(-1, 3)
+        );
+        assert_eq!(geometry.cell_center(Cell::new(1, 0)), Vec2::new(16.0, 0.0));
+    }
+
+    #[test]
+    fn test_geometry_cell_space() {
+        let geometry = TilemapGeometry::new(Vec2::new(-8.0, -8.0), Vec2::splat(16.0));
+        let cell_pos = geometry.world_to_cell_f32(Vec2::new(4.0, -4.0));
+        assert_eq!(cell_pos, Vec2::new(0.75, 0.25));
+        assert_eq!(geometry.cell_f32_to_world(cell_pos), Vec2::new(4.0, -4.0));
+        assert_eq!(
+            geometry.cell_f32_to_world(
+                geometry
+                    .world_to_cell_f32(Vec2::ZERO)
+                    .lerp(Vec2::new(1.5, 0.5), 0.5)
+            ),
+            Vec2::new(8.0, 0.0)
+        );
+        assert_eq!(
+            geometry.cell_corner(Cell::new(1, 0), 2),
+            Vec2::new(24.0, 8.0)
+        );
+        assert_eq!(
+            geometry.cell_corner(Cell::new(1, 0), 7),
+            geometry.cell_corner(Cell::new(1, 0), 3)
+        );
+    }
+
+    #[test]
+    fn test_shape_overlaps() {
+        let geometry = TilemapGeometry::default();
+        let rect = geometry.cell_rect(Cell::new(2, 2));
+        assert!(rect_overlaps_circle(rect, Vec2::new(1.5, 2.5), 0.6));
+        assert!(!rect_overlaps_circle(rect, Vec2::new(1.5, 2.5), 0.5));
+
+        // A thin triangle crossing the cell without any of its vertices inside the cell or the other way around
+        let triangle = [
+            Vec2::new(0.0, 2.1),
+            Vec2::new(5.0, 2.1),
+            Vec2::new(5.0, 2.2),
+        ];
+        assert!(rect_overlaps_polygon(rect, &triangle));
+        assert!(!rect_overlaps_polygon(
+            geometry.cell_rect(Cell::new(2, 3)),
+            &triangle
+        ));
+
+        // Covers the half of the cell above its diagonal
+        let half = [
+            Vec2::new(2.0, 3.0),
+            Vec2::new(3.0, 2.0),
+            Vec2::new(3.0, 3.0),
+        ];
+        assert!(rect_overlaps_polygon(rect, &half));
+        assert!(!rect_overlaps_polygon(
+            geometry.cell_rect(Cell::new(1, 1)),
+            &half
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+use crate::map::MapLayer;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The version of [`MapLayer`] used before layers were widened to `u64` bits, kept for manual implementations
+/// that can't be migrated at once.
+///
+/// Wrap such a layer type in [`Layer32`] wherever a [`MapLayer`] is expected. Each variant keeps the same bits,
+/// so maps and saves made with the old layer type work with the wrapped one.
+pub trait MapLayer32: Default {
+    /// Converts the layer to a bitmask.
+    fn to_bits(&self) -> u32;
+    /// Creates a layer bitmask with all bits set to 1.
+    fn all_bits() -> u32;
+}
+
+/// Adapts a [`MapLayer32`] layer type into a [`MapLayer`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Layer32<L>(pub L);
+
+impl<L: MapLayer32> MapLayer for Layer32<L> {
+    fn to_bits(&self) -> u64 {
+        u64::from(MapLayer32::to_bits(&self.0))
+    }
+
+    fn all_bits() -> u64 {
+        u64::from(L::all_bits())
+    }
+}
+
+impl<L> From<L> for Layer32<L> {
+    fn from(map_layer: L) -> Self {
+        Self(map_layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layer32, MapLayer32};
+    use crate::map::MapLayer;
+
+    #[derive(Default, Clone, Copy)]
+    enum OldLayers {
+        #[default]
+        Terrain,
+        Units,
+    }
+
+    impl MapLayer32 for OldLayers {
+        fn to_bits(&self) -> u32 {
+            match self {
+                OldLayers::Terrain => 1,
+                OldLayers::Units => 2,
+            }
+        }
+
+        fn all_bits() -> u32 {
+            3
+        }
+    }
+
+    #[test]
+    fn test_layer_32() {
+        assert_eq!(Layer32(OldLayers::Units).to_bits(), 2);
+        assert_eq!(Layer32::<OldLayers>::default().to_bits(), 1);
+        assert_eq!(Layer32::<OldLayers>::all_bits(), 3);
+    }
+}
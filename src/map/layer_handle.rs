@@ -0,0 +1,140 @@
+use crate::map::MapLayer;
+use bevy::prelude::{Component, Entity};
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// A marker type identifying one kind of map, such as the overworld or a dungeon, and the [`MapLayer`] enum
+/// its layers use.
+///
+/// Spawn maps with
+/// [`TilemapBuilder::spawn_tilemap_with_marker`](crate::tilemap_builder::TilemapBuilder::spawn_tilemap_with_marker)
+/// to get a [`TilemapHandle`] and select layers through [`LayerHandle`]s instead of bare layer enums. Handles
+/// remember the map they belong to and can only be passed to managers using the markers layer enum, so a
+/// layer of one map can't be used with another by accident.
+///
+/// ```ignore
+/// #[derive(Component, Default)]
+/// struct Overworld;
+///
+/// impl MapMarker for Overworld {
+///     type Layers = OverworldLayers;
+/// }
+/// ```
+pub trait MapMarker: Component {
+    /// The [`MapLayer`] enum used by maps with this marker
+    type Layers: MapLayer + Clone + Copy + Send + Sync + 'static;
+}
+
+/// A map spawned with a [`MapMarker`]. Hands out [`LayerHandle`]s for the layers of the map
+pub struct TilemapHandle<Marker> {
+    map_entity: Entity,
+    ph: PhantomData<fn() -> Marker>,
+}
+
+impl<Marker: MapMarker> TilemapHandle<Marker> {
+    /// Creates a handle of the given tilemap entity
+    pub(crate) fn new(map_entity: Entity) -> Self {
+        Self {
+            map_entity,
+            ph: PhantomData,
+        }
+    }
+
+    /// Returns the tilemap entity
+    pub fn map_entity(&self) -> Entity {
+        self.map_entity
+    }
+
+    /// Returns a handle of the given layer of this map
+    pub fn layer(&self, map_layer: Marker::Layers) -> LayerHandle<Marker> {
+        LayerHandle {
+            map_entity: self.map_entity,
+            map_layer,
+        }
+    }
+}
+
+/// A layer of a specific map spawned with a [`MapMarker`]. Pass it to
+/// [`TilemapManager::set_layer_handle`](crate::tilemap_manager::TilemapManager::set_layer_handle) to select
+/// both the map and the layer
+pub struct LayerHandle<Marker: MapMarker> {
+    map_entity: Entity,
+    map_layer: Marker::Layers,
+}
+
+impl<Marker: MapMarker> LayerHandle<Marker> {
+    /// Returns the tilemap entity the layer belongs to
+    pub fn map_entity(&self) -> Entity {
+        self.map_entity
+    }
+
+    /// Returns the layer
+    pub fn map_layer(&self) -> Marker::Layers {
+        self.map_layer
+    }
+}
+
+// Manual impls so markers don't need to implement these traits themselves
+
+impl<Marker> Clone for TilemapHandle<Marker> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Marker> Copy for TilemapHandle<Marker> {}
+
+impl<Marker> PartialEq for TilemapHandle<Marker> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map_entity == other.map_entity
+    }
+}
+
+impl<Marker> Eq for TilemapHandle<Marker> {}
+
+impl<Marker> Hash for TilemapHandle<Marker> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map_entity.hash(state);
+    }
+}
+
+impl<Marker> Debug for TilemapHandle<Marker> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TilemapHandle")
+            .field("map_entity", &self.map_entity)
+            .finish()
+    }
+}
+
+impl<Marker: MapMarker> Clone for LayerHandle<Marker> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Marker: MapMarker> Copy for LayerHandle<Marker> {}
+
+impl<Marker: MapMarker> PartialEq for LayerHandle<Marker> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map_entity == other.map_entity && self.map_layer.to_bits() == other.map_layer.to_bits()
+    }
+}
+
+impl<Marker: MapMarker> Eq for LayerHandle<Marker> {}
+
+impl<Marker: MapMarker> Hash for LayerHandle<Marker> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map_entity.hash(state);
+        self.map_layer.to_bits().hash(state);
+    }
+}
+
+impl<Marker: MapMarker> Debug for LayerHandle<Marker> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LayerHandle")
+            .field("map_entity", &self.map_entity)
+            .field("map_layer", &self.map_layer.to_bits())
+            .finish()
+    }
+}
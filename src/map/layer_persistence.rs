@@ -0,0 +1,46 @@
+use crate::map::MapLayer;
+use bevy::prelude::Component;
+use bevy::utils::HashSet;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Which layers of a map are written to saves. Lives on the map entity.
+///
+/// Layers are persistent unless marked transient, such as debug overlays or fog of war that is recomputed at
+/// runtime. The main layer of a map is always saved.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayerPersistence {
+    transient: HashSet<u64>,
+}
+
+impl LayerPersistence {
+    /// Creates a registry where every layer is persistent
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Marks the given layer as transient and returns self
+    pub fn with_transient(mut self, map_layer: impl MapLayer) -> Self {
+        self.set_persistent(map_layer, false);
+        self
+    }
+
+    /// Sets whether the given layer is saved
+    pub fn set_persistent(&mut self, map_layer: impl MapLayer, persistent: bool) {
+        match persistent {
+            true => self.transient.remove(&map_layer.to_bits()),
+            false => self.transient.insert(map_layer.to_bits()),
+        };
+    }
+
+    /// Returns true if the given layer is saved
+    pub fn is_persistent(&self, map_layer: impl MapLayer) -> bool {
+        self.is_persistent_by_bits(map_layer.to_bits())
+    }
+
+    /// Returns true if the layer with the given bits is saved
+    pub fn is_persistent_by_bits(&self, map_layer: u64) -> bool {
+        !self.transient.contains(&map_layer)
+    }
+}
@@ -0,0 +1,119 @@
+use crate::generation::SeededRng;
+use crate::map::CellMask;
+use bevy::math::UVec2;
+use bevy::prelude::Component;
+use lettuces::cell::Cell;
+
+/// The playable area of a map that isn't a full rectangle, such as an island or a circular arena.
+///
+/// Insert it on the tilemap entity, usually through [`TilemapBuilder::with_map_mask`](crate::tilemap_builder::TilemapBuilder::with_map_mask).
+/// The [`TilemapManager`](crate::tilemap_manager::TilemapManager) treats cells outside of the mask like cells
+/// outside of the map, so reads, writes, neighbour queries, pathfinding, cell iterators, and random sampling
+/// all skip them.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+pub struct MapMask {
+    mask: CellMask,
+}
+
+impl MapMask {
+    /// Creates a mask from the given [`CellMask`]. Set cells are playable
+    pub fn new(mask: CellMask) -> Self {
+        Self { mask }
+    }
+
+    /// Creates a mask over a map with the given dimensions where every cell the predicate returns true for is
+    /// playable. Use the max chunk size of the map for the chunk size
+    pub fn from_predicate(
+        dimensions: UVec2,
+        chunk_size: UVec2,
+        predicate: impl Fn(Cell) -> bool,
+    ) -> Self {
+        let mut mask = CellMask::new(dimensions, chunk_size);
+        for y in 0..dimensions.y as i32 {
+            for x in 0..dimensions.x as i32 {
+                let cell = Cell::new(x, y);
+                mask.set(cell, predicate(cell));
+            }
+        }
+        Self { mask }
+    }
+
+    /// Creates a mask from the alpha channel of an RGBA8 image with one pixel per cell, such as the data of a
+    /// bevy `Image` in the `Rgba8UnormSrgb` format. Cells with an alpha above the threshold are playable. The
+    /// first row of pixels is row 0 of the map
+    pub fn from_rgba8_alpha(
+        dimensions: UVec2,
+        chunk_size: UVec2,
+        rgba: &[u8],
+        threshold: u8,
+    ) -> Self {
+        Self::from_predicate(dimensions, chunk_size, |cell| {
+            let index = (cell.y as usize * dimensions.x as usize + cell.x as usize) * 4 + 3;
+            rgba.get(index).is_some_and(|alpha| *alpha > threshold)
+        })
+    }
+
+    /// Returns true if the cell is playable
+    pub fn contains(&self, cell: Cell) -> bool {
+        self.mask.get(cell)
+    }
+
+    /// Returns the [`CellMask`] of playable cells
+    pub fn cell_mask(&self) -> &CellMask {
+        &self.mask
+    }
+
+    /// Returns the amount of playable cells
+    pub fn count(&self) -> u32 {
+        self.mask.count_ones()
+    }
+
+    /// Returns a uniformly random playable cell or `None` if no cell is playable
+    pub fn random_cell(&self, rng: &mut SeededRng) -> Option<Cell> {
+        let count = self.mask.count_ones();
+        if count == 0 {
+            return None;
+        }
+        self.mask.iter_ones().nth(rng.range(count) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MapMask;
+    use crate::generation::SeededRng;
+    use bevy::math::UVec2;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_map_mask_from_predicate() {
+        // A diamond shaped island
+        let mask = MapMask::from_predicate(UVec2::new(9, 9), UVec2::new(4, 4), |cell| {
+            (cell.x - 4).abs() + (cell.y - 4).abs() <= 4
+        });
+        assert_eq!(mask.count(), 41);
+        assert!(mask.contains(Cell::new(4, 0)));
+        assert!(!mask.contains(Cell::new(0, 0)));
+        assert!(!mask.contains(Cell::new(-1, 4)));
+
+        let mut rng = SeededRng::new(7);
+        for _ in 0..32 {
+            let cell = mask.random_cell(&mut rng).unwrap();
+            assert!(mask.contains(cell));
+        }
+    }
+
+    #[test]
+    fn test_map_mask_from_rgba8_alpha() {
+        let mut rgba = vec![255u8; 3 * 2 * 4];
+        // Pixel (1, 0) is transparent
+        rgba[4 + 3] = 0;
+        let mask = MapMask::from_rgba8_alpha(UVec2::new(3, 2), UVec2::new(2, 2), &rgba, 127);
+        assert_eq!(mask.count(), 5);
+        assert!(!mask.contains(Cell::new(1, 0)));
+        assert!(mask.contains(Cell::new(1, 1)));
+
+        let empty = MapMask::from_predicate(UVec2::new(3, 2), UVec2::new(2, 2), |_| false);
+        assert_eq!(empty.random_cell(&mut SeededRng::new(1)), None);
+    }
+}
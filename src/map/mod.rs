@@ -11,38 +11,89 @@
 //! 
 //! ChunkLayer is the meat and potatoes of BST and controls all of the access of the map.
 
+mod adjacency;
+mod cell_mask;
 pub mod chunk;
+mod dynamic_layers;
+mod entity_layer;
+#[cfg(feature = "fixed_point")]
+mod fixed_geometry;
+pub(crate) mod geometry;
+mod layer_compat;
+mod layer_handle;
+mod layer_persistence;
+mod map_mask;
+mod overrides;
+mod palette;
+mod points_of_interest;
+mod render_hints;
+mod settings;
+mod shapes;
+mod stats;
+mod tile_cell;
 mod tilemap;
+mod version;
+mod write_hooks;
 
 use bevy::{
     math::UVec2,
     prelude::{Component, Entity},
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
+use crate::tilemap_builder::tilemap_layer_builder::DenseTileSource;
 use chunk::{Chunk, ChunkLayer, ChunkPos};
 use lettuces::cell::Cell;
 use std::hash::Hash;
+pub use adjacency::{Adjacency, SQUARE_CORNER_NEIGHBORS, SQUARE_EDGE_NEIGHBORS};
+pub use cell_mask::CellMask;
+pub use dynamic_layers::{DynamicLayerId, DynamicLayerRegistry};
+pub use entity_layer::{
+    clear_despawned_entity_layer_cells, map_entity_layer_entities, EntityLayer, EntityLayerPlugin,
+};
+#[cfg(feature = "fixed_point")]
+pub use fixed_geometry::{
+    from_millipixels, to_millipixels, FixedRect, FixedTilemapGeometry, MILLIPIXELS_PER_UNIT,
+};
+pub use geometry::TilemapGeometry;
+pub use layer_compat::{Layer32, MapLayer32};
+pub use layer_handle::{LayerHandle, MapMarker, TilemapHandle};
+pub use layer_persistence::LayerPersistence;
+pub use map_mask::MapMask;
+pub use overrides::{SparseOverrideSet, TileOverride, TileOverrides};
+pub use palette::{PaletteIndex, TilePalette};
+pub use points_of_interest::{PointOfInterest, PointsOfInterest};
+pub use render_hints::{LayerRenderHint, LayerRenderHints};
+pub use settings::{TileEntityParenting, TilemapSettings, TilemapSubsystems};
+pub use shapes::{square_disk, square_line, square_supercover_line};
+pub use stats::TilemapStats;
+pub use tile_cell::{TileCell, TilemapRef};
 pub use tilemap::Tilemap;
+pub use version::MapVersion;
+pub use write_hooks::{TileWrite, TileWriteHook, TileWriteHooks};
 
 /// A layer used for identifying and accessing multiple layers of a [`Tilemap`]
 ///
-/// This trait can be derived for enums with `#[derive(MapLayer)]`.
+/// This trait can be derived for enums with up to 64 variants with `#[derive(MapLayer)]`.
+///
+/// Layers used to be limited to 32 variants with `u32` bits. The bits of each variant didn't change when they
+/// were widened to `u64`, so manual implementations only need to widen their return types and data saved
+/// with the old bits loads as is. See [`MapLayer32`] for layer types that can't be changed yet.
 pub trait MapLayer: Default {
     /// Converts the layer to a bitmask.
-    fn to_bits(&self) -> u32;
+    fn to_bits(&self) -> u64;
     /// Creates a layer bitmask with all bits set to 1.
-    fn all_bits() -> u32;
+    fn all_bits() -> u64;
 }
 
 impl<L: MapLayer> MapLayer for &L
 where
     for<'a> &'a L: Default,
 {
-    fn to_bits(&self) -> u32 {
+    fn to_bits(&self) -> u64 {
         L::to_bits(self)
     }
 
-    fn all_bits() -> u32 {
+    fn all_bits() -> u64 {
         L::all_bits()
     }
 }
@@ -55,32 +106,97 @@ pub trait MapData: Hash + Component {
     /// The maximum size that a chunk can be
     fn max_chunk_size(&self) -> UVec2;
 
-    /// Function that breaks a [`Vec<Vec<TileData>>`] down into a [`Vec<Vec<TileData>>`] of the given [`ChunkPos`] chunks data
-    fn break_data_vecs_down_into_chunk_data<TileData>(
+    /// The size of the map if the map type tracks it.
+    ///
+    /// Map types whose chunks don't line up into a rectangle must return their size here as it can't be
+    /// calculated from the chunks.
+    fn map_size(&self) -> Option<UVec2> {
+        None
+    }
+
+    /// Returns the neighbours of the cell with the given [`Adjacency`], including neighbours outside of the map.
+    ///
+    /// Defaults to the neighbours on a square grid. Map types with a different layout, such as hexagonal maps,
+    /// must override this.
+    fn neighbors(&self, cell: Cell, adjacency: Adjacency) -> Vec<Cell> {
+        adjacency.square_neighbors(cell)
+    }
+
+    /// Returns the cells of a line from a to b including both ends, including cells outside of the map.
+    ///
+    /// Defaults to [`square_line`]. Map types with a different layout must override this.
+    fn line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        square_line(a, b)
+    }
+
+    /// Returns every cell a line of sight from a to b passes through including both ends, including cells
+    /// outside of the map. Unlike [`line`](MapData::line) this doesn't skip cells the line only clips, so
+    /// sight can't slip between two diagonal walls.
+    ///
+    /// Defaults to [`square_supercover_line`]. Map types with a different layout must override this.
+    fn sight_line(&self, a: Cell, b: Cell) -> Vec<Cell> {
+        square_supercover_line(a, b)
+    }
+
+    /// Returns the cells within the given radius of the center, including cells outside of the map.
+    ///
+    /// Defaults to [`square_disk`]. Map types with a different layout must override this.
+    fn cells_in_radius(&self, center: Cell, radius: u32) -> Vec<Cell> {
+        square_disk(center, radius)
+    }
+
+    /// Returns the cells at exactly the given radius from the center, the cells of
+    /// [`cells_in_radius`](MapData::cells_in_radius) that aren't within any smaller radius, including cells
+    /// outside of the map.
+    ///
+    /// Defaults to the difference of the disks of both radii. Map types that can walk their rings directly
+    /// should override this.
+    fn cells_in_ring(&self, center: Cell, radius: u32) -> Vec<Cell> {
+        if radius == 0 {
+            return self.cells_in_radius(center, 0);
+        }
+        let inner: HashSet<Cell> = self
+            .cells_in_radius(center, radius - 1)
+            .into_iter()
+            .collect();
+        self.cells_in_radius(center, radius)
+            .into_iter()
+            .filter(|cell| !inner.contains(cell))
+            .collect()
+    }
+
+    /// Function that breaks the data of a dense layer down into a [`Vec<Vec<TileData>>`] of the given [`ChunkPos`] chunks data
+    ///
+    /// The data is read from a [`DenseTileSource`], either a [`Vec<Vec<TileData>>`] or a
+    /// [`TileGenerator`](crate::tilemap_builder::tilemap_layer_builder::TileGenerator) that generates only the
+    /// cells of this chunk.
+    fn break_data_vecs_down_into_chunk_data<TileData, Source>(
         &self,
-        data: &Vec<Vec<TileData>>,
+        data: &Source,
         chunk_pos: ChunkPos,
         max_chunk_size: UVec2,
     ) -> Vec<Vec<TileData>>
     where
-        TileData: Clone + Copy + Sized + Default + Send + Sync + 'static;
+        TileData: Clone + Copy + Sized + Default + Send + Sync + 'static,
+        Source: DenseTileSource<TileData> + ?Sized;
 
-    /// Function that breaks a [`Vec<Vec<TileData>>`] into [`Vec<Vec<Chunk<TileData>>>`]
+    /// Function that breaks the data of a dense layer into [`Vec<Vec<Chunk<TileData>>>`]
     ///
     /// This function should:
     /// - Create new chunks
-    /// - Insert the correct data for each chunk
+    /// - Insert the correct data for each chunk, reading only the cells of that chunk from the [`DenseTileSource`]
     /// - Return a [`Vec<Vec<Chunk<TileData>>>`] where each chunk is correctly positioned.
     ///     - Correctly positioned meaning chunk 0:0 contains the tiles for cell positions 0:0 -> 0:max chunk size and max chunk size:0 and so forth for each chunk in order
-    fn break_data_vecs_into_chunks<TileData, MapChunk>(
+    fn break_data_vecs_into_chunks<TileData, MapChunk, Source>(
         &self,
-        data: &Vec<Vec<TileData>>,
+        data: &Source,
         max_chunk_size: UVec2,
         chunk_settings: MapChunk::ChunkSettings,
     ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
     where
         TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
-        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default;
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Source: DenseTileSource<TileData> + ?Sized;
 
     /// Function that breaks a [`HashMap<TilePos, TileData>`] into [`Vec<Vec<Chunk<TileData>>>`]
     fn break_hashmap_into_chunks<TileData, MapChunk>(
@@ -98,7 +214,7 @@ pub trait MapData: Hash + Component {
     /// Adds the given hashmap of entities to the map
     fn add_entities_to_layer<TileData, MapChunk>(
         &self,
-        map_layer: u32,
+        map_layer: u64,
         chunks: &mut Vec<Vec<Chunk<MapChunk, TileData>>>,
         entities: &HashMap<Cell, Entity>,
     ) where
@@ -0,0 +1,165 @@
+//! Tracking of the tiles changed since a map was loaded.
+//!
+//! A server that loads a static base map only needs to persist what players changed. Insert a
+//! [`TileOverrides`] on the tilemap entity after loading the base map and every write made through the
+//! [`TilemapManager`](crate::tilemap_manager::TilemapManager) is tracked against the tile data the cell had
+//! when it was first written. [`TilemapManager::export_overrides`](crate::tilemap_manager::TilemapManager::export_overrides)
+//! returns the cells that still differ from that baseline as a [`SparseOverrideSet`], which is usually tiny
+//! compared to a full snapshot of the map. Load the base map again and call
+//! [`TilemapManager::apply_overrides`](crate::tilemap_manager::TilemapManager::apply_overrides) to restore it.
+
+use bevy::prelude::Component;
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A single cell that differs from the base map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TileOverride<TileData> {
+    /// The bits of the [`MapLayer`](crate::map::MapLayer) of the cell
+    pub map_layer: u64,
+    /// The cell
+    pub cell: Cell,
+    /// The tile data of the cell
+    pub tile_data: TileData,
+}
+
+/// Every cell of a map that differs from the base map, sorted by layer and then by row
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SparseOverrideSet<TileData> {
+    /// The overridden cells
+    pub overrides: Vec<TileOverride<TileData>>,
+}
+
+impl<TileData> Default for SparseOverrideSet<TileData> {
+    fn default() -> Self {
+        Self { overrides: vec![] }
+    }
+}
+
+impl<TileData> SparseOverrideSet<TileData> {
+    /// Returns the amount of overridden cells
+    pub fn len(&self) -> usize {
+        self.overrides.len()
+    }
+
+    /// Returns true if no cells are overridden
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    /// Returns the overrides that are missing from or have different tile data in the previous set. Applying
+    /// the result on top of `previous` gives the same tile data as applying self
+    pub fn changes_since(&self, previous: &SparseOverrideSet<TileData>) -> Self
+    where
+        TileData: Clone + Copy + PartialEq,
+    {
+        let previous: HashMap<(u64, Cell), TileData> = previous
+            .overrides
+            .iter()
+            .map(|tile_override| {
+                (
+                    (tile_override.map_layer, tile_override.cell),
+                    tile_override.tile_data,
+                )
+            })
+            .collect();
+        Self {
+            overrides: self
+                .overrides
+                .iter()
+                .filter(|tile_override| {
+                    previous.get(&(tile_override.map_layer, tile_override.cell))
+                        != Some(&tile_override.tile_data)
+                })
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+/// The baseline and current tile data of every cell written since the component was inserted or last
+/// [cleared](TileOverrides::clear). Updated by the [`TilemapManager`](crate::tilemap_manager::TilemapManager)
+/// on every write
+#[derive(Component, Clone, Debug)]
+pub struct TileOverrides<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    baseline: HashMap<(u64, Cell), Option<TileData>>,
+    current: HashMap<(u64, Cell), Option<TileData>>,
+}
+
+impl<TileData> Default for TileOverrides<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            baseline: HashMap::default(),
+            current: HashMap::default(),
+        }
+    }
+}
+
+impl<TileData> TileOverrides<TileData>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    /// Records a write. The first write to a cell stores its old tile data as the baseline of the cell
+    pub fn record(&mut self, map_layer: u64, cell: Cell, old: Option<TileData>, new: TileData) {
+        self.baseline.entry((map_layer, cell)).or_insert(old);
+        self.current.insert((map_layer, cell), Some(new));
+    }
+
+    /// Records that the tile data of a cell was removed, like [`record`](Self::record) does for writes
+    pub fn record_removal(&mut self, map_layer: u64, cell: Cell, old: Option<TileData>) {
+        self.baseline.entry((map_layer, cell)).or_insert(old);
+        self.current.insert((map_layer, cell), None);
+    }
+
+    /// Returns the amount of cells written since the baseline, including cells that were written back to
+    /// their baseline tile data
+    pub fn written_len(&self) -> usize {
+        self.current.len()
+    }
+
+    /// Returns every cell that differs from the baseline. Cells whose tile data was removed can't be
+    /// expressed as an override and are left out
+    pub fn export(&self) -> SparseOverrideSet<TileData>
+    where
+        TileData: PartialEq,
+    {
+        let mut overrides: Vec<TileOverride<TileData>> = self
+            .current
+            .iter()
+            .filter(|(key, tile_data)| self.baseline.get(*key) != Some(*tile_data))
+            .filter_map(|((map_layer, cell), tile_data)| {
+                tile_data.map(|tile_data| TileOverride {
+                    map_layer: *map_layer,
+                    cell: *cell,
+                    tile_data,
+                })
+            })
+            .collect();
+        overrides.sort_by_key(|tile_override| {
+            (
+                tile_override.map_layer,
+                tile_override.cell.y,
+                tile_override.cell.x,
+            )
+        });
+        SparseOverrideSet { overrides }
+    }
+
+    /// Makes the current tile data of every cell the new baseline, for example after the overrides were
+    /// persisted into a new base map
+    pub fn clear(&mut self) {
+        self.baseline.clear();
+        self.current.clear();
+    }
+}
@@ -0,0 +1,228 @@
+//! Tile palettes used to shrink the memory footprint of large `TileData` types.
+//!
+//! Instead of storing a full `TileData` for every cell, chunk layers store a small [`PaletteIndex`] (`u8`
+//! or `u16`) into a [`TilePalette`] that lives on the tilemap entity. Use a
+//! [`PaletteTilemapManager`](crate::tilemap_manager::PaletteTilemapManager) to read and write `TileData`
+//! transparently.
+
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use bevy::prelude::Component;
+use bevy::utils::HashMap;
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// An index into a [`TilePalette`]. Used as the `TileData` stored in chunks for paletted maps.
+pub trait PaletteIndex: Hash + Eq + Clone + Copy + Sized + Default + Send + Sync + 'static {
+    /// Converts the given palette position into an index. Returns `None` if the position doesn't fit.
+    fn from_index(index: usize) -> Option<Self>;
+
+    /// Returns the palette position this index points to
+    fn index(&self) -> usize;
+}
+
+impl PaletteIndex for u8 {
+    fn from_index(index: usize) -> Option<Self> {
+        u8::try_from(index).ok()
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl PaletteIndex for u16 {
+    fn from_index(index: usize) -> Option<Self> {
+        u16::try_from(index).ok()
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// A per map list of every unique `TileData` used in the map. Should be inserted onto the tilemap entity.
+///
+/// The default `TileData` is always stored at index 0 so that default chunk data resolves to the
+/// default `TileData`.
+#[derive(Component, Clone, Debug)]
+pub struct TilePalette<TileData, Index = u16>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + 'static,
+    Index: PaletteIndex,
+{
+    tiles: Vec<TileData>,
+    lookup: HashMap<TileData, Index>,
+}
+
+impl<TileData, Index> Default for TilePalette<TileData, Index>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + 'static,
+    Index: PaletteIndex,
+{
+    fn default() -> Self {
+        let mut palette = Self {
+            tiles: vec![],
+            lookup: HashMap::default(),
+        };
+        palette.get_or_insert(TileData::default());
+        palette
+    }
+}
+
+impl<TileData, Index> TilePalette<TileData, Index>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + 'static,
+    Index: PaletteIndex,
+{
+    /// Creates a new palette containing only the default `TileData`
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new palette from the given tiles. Duplicate tiles are only inserted once.
+    ///
+    /// Returns `None` if there are more unique tiles than `Index` can address.
+    pub fn from_tiles(tiles: impl IntoIterator<Item = TileData>) -> Option<Self> {
+        let mut palette = Self::new();
+        for tile in tiles {
+            palette.get_or_insert(tile)?;
+        }
+        Some(palette)
+    }
+
+    /// Returns the amount of unique tiles in the palette
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Returns true if the palette contains no tiles
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// Returns the `TileData` for the given index if it exists
+    pub fn get(&self, index: Index) -> Option<TileData> {
+        self.tiles.get(index.index()).cloned()
+    }
+
+    /// Returns the index of the given `TileData` if it is in the palette
+    pub fn index_of(&self, tile_data: &TileData) -> Option<Index> {
+        self.lookup.get(tile_data).cloned()
+    }
+
+    /// Returns the index of the given `TileData`, inserting it into the palette if it is new.
+    ///
+    /// Returns `None` if the palette is full.
+    pub fn get_or_insert(&mut self, tile_data: TileData) -> Option<Index> {
+        if let Some(index) = self.lookup.get(&tile_data) {
+            return Some(*index);
+        }
+        let index = Index::from_index(self.tiles.len())?;
+        self.tiles.push(tile_data);
+        self.lookup.insert(tile_data, index);
+        Some(index)
+    }
+
+    /// Returns an iterator over every tile in the palette in index order
+    pub fn iter(&self) -> impl Iterator<Item = &TileData> {
+        self.tiles.iter()
+    }
+
+    /// Converts the given [`TilemapLayer`] into a layer of palette indexes, inserting any new tiles into the
+    /// palette. Used to build paletted maps with the [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder).
+    ///
+    /// Returns `None` if the palette runs out of indexes. [`TilemapLayer::Generated`] layers are generated and
+    /// palettized as [`TilemapLayer::Dense`] layers.
+    pub fn palettize_layer(
+        &mut self,
+        layer: TilemapLayer<TileData>,
+    ) -> Option<TilemapLayer<Index>> {
+        Some(match layer {
+            TilemapLayer::Sparse(data, dimensions, entities) => {
+                let mut indexed = HashMap::with_capacity(data.len());
+                for (cell, tile_data) in data {
+                    indexed.insert(cell, self.get_or_insert(tile_data)?);
+                }
+                TilemapLayer::Sparse(indexed, dimensions, entities)
+            }
+            TilemapLayer::Dense(data, entities) => {
+                let mut indexed = Vec::with_capacity(data.len());
+                for row in data {
+                    let mut indexed_row = Vec::with_capacity(row.len());
+                    for tile_data in row {
+                        indexed_row.push(self.get_or_insert(tile_data)?);
+                    }
+                    indexed.push(indexed_row);
+                }
+                TilemapLayer::Dense(indexed, entities)
+            }
+            layer @ TilemapLayer::Generated(..) => self.palettize_layer(layer.into_dense())?,
+        })
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<TileData, Index> Serialize for TilePalette<TileData, Index>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + Serialize + 'static,
+    Index: PaletteIndex,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.tiles.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, TileData, Index> Deserialize<'de> for TilePalette<TileData, Index>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + Deserialize<'de> + 'static,
+    Index: PaletteIndex,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let tiles = Vec::<TileData>::deserialize(deserializer)?;
+        let mut palette = Self {
+            tiles: Vec::with_capacity(tiles.len()),
+            lookup: HashMap::default(),
+        };
+        for tile_data in tiles {
+            let index = Index::from_index(palette.tiles.len()).ok_or_else(|| {
+                serde::de::Error::custom("Palette has too many tiles for its index")
+            })?;
+            palette.tiles.push(tile_data);
+            palette.lookup.insert(tile_data, index);
+        }
+        Ok(palette)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TilePalette;
+
+    #[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Hash)]
+    struct TileData(u32, u32);
+
+    #[test]
+    fn test_palette_insert() {
+        let mut palette = TilePalette::<TileData, u8>::new();
+        assert_eq!(palette.get(0), Some(TileData::default()));
+        assert_eq!(palette.get_or_insert(TileData(1, 2)), Some(1));
+        assert_eq!(palette.get_or_insert(TileData(1, 2)), Some(1));
+        assert_eq!(palette.get_or_insert(TileData(3, 4)), Some(2));
+        assert_eq!(palette.get(2), Some(TileData(3, 4)));
+        assert_eq!(palette.index_of(&TileData(5, 6)), None);
+        assert_eq!(palette.len(), 3);
+    }
+
+    #[test]
+    fn test_palette_full() {
+        let mut palette = TilePalette::<TileData, u8>::new();
+        for i in 1..256 {
+            assert!(palette.get_or_insert(TileData(i, 0)).is_some());
+        }
+        assert_eq!(palette.get_or_insert(TileData(256, 0)), None);
+        assert_eq!(palette.get_or_insert(TileData(255, 0)), Some(255));
+    }
+}
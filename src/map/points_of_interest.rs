@@ -0,0 +1,170 @@
+//! Named points of interest stored alongside a map.
+//!
+//! Spawn points, quest markers, level exits, and similar locations can be stored in a [`PointsOfInterest`]
+//! component on the tilemap entity instead of in separate resources, so they are saved and loaded together
+//! with the rest of the map.
+
+use bevy::prelude::Component;
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A single named location on the map with an optional payload
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PointOfInterest<Payload = ()> {
+    /// The cell the point is located at
+    pub cell: Cell,
+    /// Extra user data for the point
+    pub payload: Payload,
+}
+
+/// A registry of named [`PointOfInterest`]s. Insert it on the tilemap entity to keep it with the map.
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PointsOfInterest<Payload = ()>
+where
+    Payload: Send + Sync + 'static,
+{
+    points: HashMap<String, PointOfInterest<Payload>>,
+}
+
+impl<Payload> Default for PointsOfInterest<Payload>
+where
+    Payload: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            points: HashMap::default(),
+        }
+    }
+}
+
+impl<Payload> PointsOfInterest<Payload>
+where
+    Payload: Send + Sync + 'static,
+{
+    /// Creates a new empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a point with the given name, returning the point it replaced if there was one
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        cell: Cell,
+        payload: Payload,
+    ) -> Option<PointOfInterest<Payload>> {
+        self.points
+            .insert(name.into(), PointOfInterest { cell, payload })
+    }
+
+    /// Removes the point with the given name and returns it
+    pub fn remove(&mut self, name: &str) -> Option<PointOfInterest<Payload>> {
+        self.points.remove(name)
+    }
+
+    /// Returns the point with the given name
+    pub fn get(&self, name: &str) -> Option<&PointOfInterest<Payload>> {
+        self.points.get(name)
+    }
+
+    /// Returns the point with the given name mutably
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PointOfInterest<Payload>> {
+        self.points.get_mut(name)
+    }
+
+    /// Returns the cell of the point with the given name
+    pub fn cell(&self, name: &str) -> Option<Cell> {
+        self.points.get(name).map(|point| point.cell)
+    }
+
+    /// Returns the amount of points in the registry
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    /// Returns true if the registry has no points
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Iterates over every point and its name in an arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PointOfInterest<Payload>)> {
+        self.points
+            .iter()
+            .map(|(name, point)| (name.as_str(), point))
+    }
+
+    /// Iterates over every point located at the given cell
+    pub fn at_cell(&self, cell: Cell) -> impl Iterator<Item = (&str, &PointOfInterest<Payload>)> {
+        self.iter().filter(move |(_, point)| point.cell == cell)
+    }
+
+    /// Returns every point within the given radius of the center, measured as the straight line distance
+    /// between cell coordinates. Points are sorted from nearest to furthest
+    pub fn within_radius(
+        &self,
+        center: Cell,
+        radius: f32,
+    ) -> Vec<(&str, &PointOfInterest<Payload>)> {
+        let radius_squared = radius * radius;
+        let mut points: Vec<(f32, (&str, &PointOfInterest<Payload>))> = self
+            .iter()
+            .filter_map(|entry| {
+                let x = (entry.1.cell.x - center.x) as f32;
+                let y = (entry.1.cell.y - center.y) as f32;
+                let distance_squared = x * x + y * y;
+                (distance_squared <= radius_squared).then_some((distance_squared, entry))
+            })
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1 .0.cmp(b.1 .0)));
+        points.into_iter().map(|(_, entry)| entry).collect()
+    }
+
+    /// Returns the nearest point to the given cell
+    pub fn nearest(&self, cell: Cell) -> Option<(&str, &PointOfInterest<Payload>)> {
+        self.iter().min_by(|a, b| {
+            let distance = |point: &PointOfInterest<Payload>| {
+                let x = (point.cell.x - cell.x) as i64;
+                let y = (point.cell.y - cell.y) as i64;
+                x * x + y * y
+            };
+            distance(a.1).cmp(&distance(b.1)).then_with(|| a.0.cmp(b.0))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PointsOfInterest;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_points_of_interest_queries() {
+        let mut points: PointsOfInterest<u32> = PointsOfInterest::new();
+        points.insert("spawn", Cell::new(0, 0), 0);
+        points.insert("exit", Cell::new(10, 10), 1);
+        points.insert("chest", Cell::new(2, 1), 2);
+
+        assert_eq!(points.cell("exit"), Some(Cell::new(10, 10)));
+        let nearby: Vec<&str> = points
+            .within_radius(Cell::new(1, 1), 3.0)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(nearby, vec!["chest", "spawn"]);
+        assert_eq!(
+            points.nearest(Cell::new(9, 8)).map(|(name, _)| name),
+            Some("exit")
+        );
+
+        assert!(points.insert("spawn", Cell::new(1, 1), 3).is_some());
+        assert_eq!(points.at_cell(Cell::new(1, 1)).count(), 1);
+        assert!(points.remove("spawn").is_some());
+        assert_eq!(points.len(), 2);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::map::MapLayer;
+use bevy::prelude::Component;
+use bevy::utils::HashMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Rendering metadata for a single map layer. `bevy_sparse_tilemap` doesn't render anything itself, these
+/// hints are read by rendering integrations so layers are configured in one place.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayerRenderHint {
+    /// Offset added to the z position of the layer. Layers with a higher offset are drawn on top
+    pub z_offset: f32,
+    /// Key used by the integration to pick the material or texture atlas of the layer, such as an asset path
+    pub material_key: Option<String>,
+    /// Whether the layer should be drawn
+    pub visible: bool,
+}
+
+impl Default for LayerRenderHint {
+    fn default() -> Self {
+        Self {
+            z_offset: 0.0,
+            material_key: None,
+            visible: true,
+        }
+    }
+}
+
+impl LayerRenderHint {
+    /// Creates a visible hint with the given z offset and no material key
+    pub fn new(z_offset: f32) -> Self {
+        Self {
+            z_offset,
+            ..Self::default()
+        }
+    }
+
+    /// Sets the material key and returns self
+    pub fn with_material_key(mut self, material_key: impl Into<String>) -> Self {
+        self.material_key = Some(material_key.into());
+        self
+    }
+
+    /// Sets the visibility and returns self
+    pub fn with_visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+}
+
+/// The [`LayerRenderHint`]s of every layer of a map. Lives on the map entity.
+///
+/// Layers without a hint use [`LayerRenderHint::default`].
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayerRenderHints {
+    hints: HashMap<u64, LayerRenderHint>,
+}
+
+impl LayerRenderHints {
+    /// Creates an empty set of hints
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the hint of the given layer and returns self
+    pub fn with(mut self, map_layer: impl MapLayer, hint: LayerRenderHint) -> Self {
+        self.set(map_layer, hint);
+        self
+    }
+
+    /// Sets the hint of the given layer
+    pub fn set(&mut self, map_layer: impl MapLayer, hint: LayerRenderHint) {
+        self.hints.insert(map_layer.to_bits(), hint);
+    }
+
+    /// Returns the hint of the given layer
+    pub fn get(&self, map_layer: impl MapLayer) -> LayerRenderHint {
+        self.get_by_bits(map_layer.to_bits())
+    }
+
+    /// Returns the hint of the layer with the given bits
+    pub fn get_by_bits(&self, map_layer: u64) -> LayerRenderHint {
+        self.hints.get(&map_layer).cloned().unwrap_or_default()
+    }
+
+    /// Shows or hides the given layer
+    pub fn set_visible(&mut self, map_layer: impl MapLayer, visible: bool) {
+        self.hints.entry(map_layer.to_bits()).or_default().visible = visible;
+    }
+
+    /// Flips the visibility of the given layer and returns the new visibility
+    pub fn toggle_visible(&mut self, map_layer: impl MapLayer) -> bool {
+        let hint = self.hints.entry(map_layer.to_bits()).or_default();
+        hint.visible = !hint.visible;
+        hint.visible
+    }
+
+    /// Returns the bits of the given layers that should be drawn, ordered from the bottom to the top
+    pub fn draw_order(&self, map_layers: impl IntoIterator<Item = u64>) -> Vec<u64> {
+        let mut layers: Vec<(u64, LayerRenderHint)> = map_layers
+            .into_iter()
+            .map(|map_layer| (map_layer, self.get_by_bits(map_layer)))
+            .filter(|(_, hint)| hint.visible)
+            .collect();
+        layers.sort_by(|(a_layer, a), (b_layer, b)| {
+            a.z_offset.total_cmp(&b.z_offset).then(a_layer.cmp(b_layer))
+        });
+        layers.into_iter().map(|(map_layer, _)| map_layer).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LayerRenderHint, LayerRenderHints};
+    use crate as bevy_sparse_tilemap;
+    use bst_map_layer_derive::MapLayer;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Ground,
+        Decoration,
+        Overlay,
+    }
+
+    #[test]
+    fn test_layer_render_hints() {
+        let mut hints = LayerRenderHints::new()
+            .with(
+                MapLayers::Ground,
+                LayerRenderHint::new(0.0).with_material_key("ground.png"),
+            )
+            .with(MapLayers::Decoration, LayerRenderHint::new(2.0))
+            .with(MapLayers::Overlay, LayerRenderHint::new(1.0));
+        assert_eq!(
+            hints.get(MapLayers::Ground).material_key.as_deref(),
+            Some("ground.png")
+        );
+
+        let all_layers = [
+            MapLayers::Ground.to_bits(),
+            MapLayers::Decoration.to_bits(),
+            MapLayers::Overlay.to_bits(),
+        ];
+        assert_eq!(
+            hints.draw_order(all_layers),
+            vec![
+                MapLayers::Ground.to_bits(),
+                MapLayers::Overlay.to_bits(),
+                MapLayers::Decoration.to_bits()
+            ]
+        );
+
+        assert!(!hints.toggle_visible(MapLayers::Overlay));
+        assert_eq!(
+            hints.draw_order(all_layers),
+            vec![MapLayers::Ground.to_bits(), MapLayers::Decoration.to_bits()]
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Per map scheduling controls for the systems added by this crate.
+//!
+//! Insert a [`TilemapSettings`] on a tilemap entity to turn subsystems off for that map or to run them less
+//! often. Maps without settings run every subsystem on every update. Only the subsystems listed in
+//! [`TilemapSubsystems`] check the settings, every other system of the crate always runs.
+
+use bevy::prelude::{Component, Entity};
+use bevy::utils::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A set of crate subsystems stored as bitflags
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TilemapSubsystems(u32);
+
+impl TilemapSubsystems {
+    /// No subsystems
+    pub const NONE: TilemapSubsystems = TilemapSubsystems(0);
+    /// Chunk level-of-detail summaries. See [`ChunkLodPlugin`](crate::chunk_lod::ChunkLodPlugin)
+    pub const CHUNK_LOD: TilemapSubsystems = TilemapSubsystems(1);
+    /// Tile animation. See `TileAnimationPlugin` with the `animation` feature
+    pub const ANIMATION: TilemapSubsystems = TilemapSubsystems(1 << 1);
+    // Bits 2 and 3 are unused so the bits of saved settings keep their meaning
+    /// Chunk streaming. See [`ChunkStreamingPlugin`](crate::streaming::ChunkStreamingPlugin)
+    pub const STREAMING: TilemapSubsystems = TilemapSubsystems(1 << 4);
+    /// Validation of written cells. See [`ValidationPlugin`](crate::validation::ValidationPlugin)
+    pub const VALIDATION: TilemapSubsystems = TilemapSubsystems(1 << 5);
+    /// Idle time compaction. See [`TilemapCompactionPlugin`](crate::tilemap_manager::TilemapCompactionPlugin)
+    pub const COMPACTION: TilemapSubsystems = TilemapSubsystems(1 << 6);
+    /// Every subsystem
+    pub const ALL: TilemapSubsystems = TilemapSubsystems(u32::MAX);
+
+    /// Creates a set from raw bits
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bits of the set
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    /// Returns true if every subsystem in `other` is in this set
+    pub const fn contains(&self, other: TilemapSubsystems) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Adds the subsystems in `other` to this set
+    pub fn insert(&mut self, other: TilemapSubsystems) {
+        self.0 |= other.0;
+    }
+
+    /// Removes the subsystems in `other` from this set
+    pub fn remove(&mut self, other: TilemapSubsystems) {
+        self.0 &= !other.0;
+    }
+}
+
+impl Default for TilemapSubsystems {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl std::ops::BitOr for TilemapSubsystems {
+    type Output = TilemapSubsystems;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        TilemapSubsystems(self.0 | rhs.0)
+    }
+}
+
+/// Which entity the tile entities of a map are parented to when they are spawned or set
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum TileEntityParenting {
+    /// Tile entities have no parent and outlive the map unless despawned manually
+    #[default]
+    Unparented,
+    /// Tile entities are children of the chunk they are in and despawn with it. Moving a tile entity into
+    /// another chunk re-parents it, and streamed out chunks keep their tile entities alive
+    Chunk,
+    /// Tile entities are children of the map and despawn with it
+    Map,
+}
+
+impl TileEntityParenting {
+    /// Returns the parent of a tile entity in the given chunk of the given map
+    pub fn parent(&self, map_entity: Entity, chunk_entity: Entity) -> Option<Entity> {
+        match self {
+            TileEntityParenting::Unparented => None,
+            TileEntityParenting::Chunk => Some(chunk_entity),
+            TileEntityParenting::Map => Some(map_entity),
+        }
+    }
+}
+
+/// Scheduling settings for a single map. The systems of the subsystems in [`TilemapSubsystems`] check the
+/// settings of the map they are working on before doing any work. Other systems ignore
+/// [`enabled`](Self::enabled) and [`tick_divisors`](Self::tick_divisors).
+#[derive(Component, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TilemapSettings {
+    /// The subsystems that run for this map
+    pub enabled: TilemapSubsystems,
+    /// Subsystems with a divisor only run on every n-th tick. Subsystems without one run every tick
+    pub tick_divisors: HashMap<u32, u32>,
+    /// The most chunks renderers upload every frame. Renderers upload every changed chunk at once if `None`.
+    /// See [`ChunkSyncQueue`](crate::chunk_sync::ChunkSyncQueue)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub chunk_sync_budget: Option<usize>,
+    /// How many chunks around the screen count as near the screen when prioritizing chunk uploads
+    #[cfg_attr(feature = "serde", serde(default = "default_chunk_sync_margin"))]
+    pub chunk_sync_margin: u32,
+    /// The parent of the tile entities of this map
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tile_entity_parenting: TileEntityParenting,
+}
+
+/// The default [`TilemapSettings::chunk_sync_margin`]
+fn default_chunk_sync_margin() -> u32 {
+    1
+}
+
+impl Default for TilemapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: TilemapSubsystems::default(),
+            tick_divisors: HashMap::default(),
+            chunk_sync_budget: None,
+            chunk_sync_margin: default_chunk_sync_margin(),
+            tile_entity_parenting: TileEntityParenting::default(),
+        }
+    }
+}
+
+impl TilemapSettings {
+    /// Creates settings with every subsystem disabled. Useful for static decorative maps
+    pub fn disabled() -> Self {
+        Self {
+            enabled: TilemapSubsystems::NONE,
+            ..Self::default()
+        }
+    }
+
+    /// Enables or disables the given subsystems
+    pub fn set_enabled(&mut self, subsystems: TilemapSubsystems, enabled: bool) {
+        if enabled {
+            self.enabled.insert(subsystems);
+        } else {
+            self.enabled.remove(subsystems);
+        }
+    }
+
+    /// Makes the given subsystem only run on every `divisor` ticks. A divisor of 0 or 1 runs it every tick
+    pub fn set_tick_divisor(&mut self, subsystem: TilemapSubsystems, divisor: u32) {
+        if divisor <= 1 {
+            self.tick_divisors.remove(&subsystem.bits());
+        } else {
+            self.tick_divisors.insert(subsystem.bits(), divisor);
+        }
+    }
+
+    /// Returns the tick divisor of the given subsystem
+    pub fn tick_divisor(&self, subsystem: TilemapSubsystems) -> u32 {
+        self.tick_divisors
+            .get(&subsystem.bits())
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Returns true if the subsystem should run on the given tick for this map
+    pub fn should_run(&self, subsystem: TilemapSubsystems, tick: u64) -> bool {
+        self.enabled.contains(subsystem)
+            && tick.checked_rem(self.tick_divisor(subsystem) as u64) == Some(0)
+    }
+
+    /// Returns true if the subsystem should run on the given tick for a map with the given optional settings.
+    /// Maps without settings always run
+    pub fn should_run_for(
+        settings: Option<&TilemapSettings>,
+        subsystem: TilemapSubsystems,
+        tick: u64,
+    ) -> bool {
+        match settings {
+            Some(settings) => settings.should_run(subsystem, tick),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TilemapSettings, TilemapSubsystems};
+
+    #[test]
+    fn test_settings_scheduling() {
+        let mut settings = TilemapSettings::default();
+        assert!(settings.should_run(TilemapSubsystems::STREAMING, 3));
+
+        settings.set_tick_divisor(TilemapSubsystems::STREAMING, 4);
+        assert!(settings.should_run(TilemapSubsystems::STREAMING, 8));
+        assert!(!settings.should_run(TilemapSubsystems::STREAMING, 9));
+        assert!(settings.should_run(TilemapSubsystems::CHUNK_LOD, 9));
+
+        settings.set_enabled(
+            TilemapSubsystems::STREAMING | TilemapSubsystems::CHUNK_LOD,
+            false,
+        );
+        assert!(!settings.should_run(TilemapSubsystems::STREAMING, 8));
+        assert!(!settings.should_run(TilemapSubsystems::CHUNK_LOD, 8));
+        assert!(settings.should_run(TilemapSubsystems::ANIMATION, 8));
+        assert!(TilemapSettings::should_run_for(
+            None,
+            TilemapSubsystems::STREAMING,
+            1
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use lettuces::cell::Cell;
+
+/// Returns the cells of a line from a to b on a square grid, including both ends, using Bresenham's
+/// algorithm. Consecutive cells share an edge or a corner
+pub fn square_line(a: Cell, b: Cell) -> Vec<Cell> {
+    let (dx, dy) = ((b.x - a.x).abs(), -(b.y - a.y).abs());
+    let (step_x, step_y) = ((b.x - a.x).signum(), (b.y - a.y).signum());
+    let mut error = dx + dy;
+    let mut cell = a;
+    let mut cells = vec![a];
+    while cell != b {
+        let doubled = error * 2;
+        if doubled >= dy {
+            error += dy;
+            cell.x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            cell.y += step_y;
+        }
+        cells.push(cell);
+    }
+    cells
+}
+
+/// Returns every cell of a square grid touched by the line from the center of a to the center of b, including
+/// both ends. Consecutive cells share an edge, except where the line passes exactly through a corner. Both
+/// cells beside the corner are then included before the cell diagonal to it, so nothing can be seen through
+/// the gap between two diagonal walls
+pub fn square_supercover_line(a: Cell, b: Cell) -> Vec<Cell> {
+    let (nx, ny) = ((b.x - a.x).abs(), (b.y - a.y).abs());
+    let (step_x, step_y) = ((b.x - a.x).signum(), (b.y - a.y).signum());
+    let mut cell = a;
+    let mut cells = vec![a];
+    let (mut ix, mut iy) = (0, 0);
+    while ix < nx || iy < ny {
+        // Compares the distance to the next vertical and horizontal edges along the line
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+        if decision == 0 {
+            cells.push(Cell::new(cell.x + step_x, cell.y));
+            cells.push(Cell::new(cell.x, cell.y + step_y));
+            cell.x += step_x;
+            cell.y += step_y;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            cell.x += step_x;
+            ix += 1;
+        } else {
+            cell.y += step_y;
+            iy += 1;
+        }
+        cells.push(cell);
+    }
+    cells
+}
+
+/// Returns the cells of a square grid whose centers are within radius and a half cells of the center of the
+/// given cell. A radius of 0 is just the cell itself
+pub fn square_disk(center: Cell, radius: u32) -> Vec<Cell> {
+    let radius = radius as i32;
+    let mut cells = vec![];
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            if x * x + y * y <= radius * radius + radius {
+                cells.push(Cell::new(center.x + x, center.y + y));
+            }
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{square_disk, square_line, square_supercover_line};
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_square_line() {
+        assert_eq!(
+            square_line(Cell::new(0, 0), Cell::new(4, 2)),
+            vec![
+                Cell::new(0, 0),
+                Cell::new(1, 1),
+                Cell::new(2, 1),
+                Cell::new(3, 2),
+                Cell::new(4, 2)
+            ]
+        );
+        let reversed = square_line(Cell::new(3, -1), Cell::new(-2, 5));
+        assert_eq!(reversed.len(), 7);
+        assert_eq!(reversed.first(), Some(&Cell::new(3, -1)));
+        assert_eq!(reversed.last(), Some(&Cell::new(-2, 5)));
+        assert_eq!(
+            square_line(Cell::new(2, 2), Cell::new(2, 2)),
+            vec![Cell::new(2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_square_supercover_line() {
+        assert_eq!(
+            square_supercover_line(Cell::new(0, 0), Cell::new(2, 1)),
+            vec![
+                Cell::new(0, 0),
+                Cell::new(1, 0),
+                Cell::new(1, 1),
+                Cell::new(2, 1)
+            ]
+        );
+        // Corners crossed exactly include the cells on both sides
+        assert_eq!(
+            square_supercover_line(Cell::new(0, 0), Cell::new(-2, 2)),
+            vec![
+                Cell::new(0, 0),
+                Cell::new(-1, 0),
+                Cell::new(0, 1),
+                Cell::new(-1, 1),
+                Cell::new(-2, 1),
+                Cell::new(-1, 2),
+                Cell::new(-2, 2)
+            ]
+        );
+        let line = square_supercover_line(Cell::new(3, -1), Cell::new(-2, 5));
+        assert_eq!(line.len(), 12);
+        assert_eq!(line.last(), Some(&Cell::new(-2, 5)));
+        assert_eq!(
+            square_supercover_line(Cell::new(2, 2), Cell::new(2, 2)),
+            vec![Cell::new(2, 2)]
+        );
+    }
+
+    #[test]
+    fn test_square_disk() {
+        assert_eq!(square_disk(Cell::new(5, 5), 0), vec![Cell::new(5, 5)]);
+        assert_eq!(square_disk(Cell::new(0, 0), 1).len(), 9);
+        // The corners of the bounding square are cut off at larger radii
+        let disk = square_disk(Cell::new(0, 0), 3);
+        assert_eq!(disk.len(), 37);
+        assert!(!disk.contains(&Cell::new(3, 3)));
+        assert!(disk.contains(&Cell::new(3, 1)));
+    }
+}
@@ -0,0 +1,33 @@
+//! Running statistics about the memory of a map.
+
+use crate::map::chunk::CompactionReport;
+use bevy::prelude::Component;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Statistics about the compactions of a map. Inserted on the tilemap entity and updated by
+/// [`TilemapManager::compact`](crate::tilemap_manager::TilemapManager::compact)
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TilemapStats {
+    /// The amount of times the map was compacted
+    pub compactions: u64,
+    /// The approximate amount of heap memory released by every compaction in bytes
+    pub reclaimed_bytes: u64,
+    /// The approximate amount of heap memory released by the last compaction in bytes
+    pub last_reclaimed_bytes: u64,
+    /// The approximate amount of heap memory used by the chunk layers of the map after the last compaction in
+    /// bytes
+    pub heap_bytes: u64,
+}
+
+impl TilemapStats {
+    /// Adds the result of a compaction to the statistics
+    pub fn record_compaction(&mut self, report: &CompactionReport) {
+        self.compactions += 1;
+        self.reclaimed_bytes += report.reclaimed_bytes as u64;
+        self.last_reclaimed_bytes = report.reclaimed_bytes as u64;
+        self.heap_bytes = report.heap_bytes as u64;
+    }
+}
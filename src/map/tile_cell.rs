@@ -0,0 +1,32 @@
+//! Components describing where a tile entity is on its map.
+//!
+//! Tile entities spawned or set through the [`TilemapManager`](crate::tilemap_manager::TilemapManager) get a
+//! [`TileCell`] and a [`TilemapRef`], which are kept up to date when the entity is moved with
+//! [`TilemapManager::move_tile_entity`](crate::tilemap_manager::TilemapManager::move_tile_entity). Systems
+//! working on tile entities can use them to find the tile data of the entity without searching every chunk.
+
+use crate::map::chunk::ChunkPos;
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::prelude::{Component, Entity};
+use lettuces::cell::Cell;
+
+/// The [`Cell`] of a tile entity along with its [`ChunkPos`] and the bits of the layer it is on
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileCell {
+    /// The cell of the tile entity in the map
+    pub cell: Cell,
+    /// The position of the chunk the cell is in
+    pub chunk_pos: ChunkPos,
+    /// The bits of the [`MapLayer`](crate::map::MapLayer) the tile entity is on
+    pub map_layer: u64,
+}
+
+/// Points from a tile entity back to the tilemap entity it belongs to
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TilemapRef(pub Entity);
+
+impl MapEntities for TilemapRef {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.0 = entity_mapper.map_entity(self.0);
+    }
+}
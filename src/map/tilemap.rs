@@ -7,6 +7,7 @@
 
 use crate::map::chunk::ChunkPos;
 use crate::map::chunk::Chunks;
+use crate::map::DynamicLayerRegistry;
 use bevy::ecs::entity::{EntityMapper, MapEntities};
 
 #[cfg(feature = "reflect")]
@@ -35,6 +36,9 @@ use super::MapData;
 pub struct Tilemap {
     /// Struct containing [`Entity`] mappings to the [`Chunk`](super::chunk::Chunk)s that hold tile data
     chunks: Chunks,
+    /// The names of the layers registered at runtime
+    #[cfg_attr(feature = "serde", serde(default))]
+    dynamic_layers: DynamicLayerRegistry,
 }
 
 impl MapEntities for Tilemap {
@@ -46,7 +50,16 @@ impl MapEntities for Tilemap {
 impl Tilemap {
     /// Creates a new [`Tilemap`] out of the given chunks struct
     pub fn new(chunks: Chunks) -> Tilemap {
-        Self { chunks }
+        Self {
+            chunks,
+            dynamic_layers: DynamicLayerRegistry::default(),
+        }
+    }
+
+    /// Replaces the [`DynamicLayerRegistry`] of the map and returns self
+    pub fn with_dynamic_layers(mut self, dynamic_layers: DynamicLayerRegistry) -> Tilemap {
+        self.dynamic_layers = dynamic_layers;
+        self
     }
 
     /// Gets the chunk entity that contains this cell
@@ -59,6 +72,24 @@ impl Tilemap {
         self.chunks.get_chunk(chunk_pos)
     }
 
+    /// Iterates over the positions of the chunks of the map at most `radius` chunks away from `center` in every
+    /// direction
+    pub fn chunks_in_radius(
+        &self,
+        center: ChunkPos,
+        radius: u32,
+    ) -> impl Iterator<Item = ChunkPos> {
+        let chunk_counts = self.chunks.chunk_counts().as_ivec2();
+        let radius = radius as i32;
+        let min = ChunkPos::new((center.x() - radius).max(0), (center.y() - radius).max(0));
+        let max = ChunkPos::new(
+            (center.x() + radius).min(chunk_counts.x - 1),
+            (center.y() + radius).min(chunk_counts.y - 1),
+        );
+        // The rect is empty if it lies outside of the map
+        ChunkPos::iter_rect(min, max)
+    }
+
     /// Returns the max size that a chunk can be
     pub fn get_chunks_max_size(&self) -> UVec2 {
         self.chunks.max_chunk_size()
@@ -73,4 +104,16 @@ impl Tilemap {
     pub fn chunks_mut(&mut self) -> &mut Chunks {
         &mut self.chunks
     }
+
+    /// Returns the [`DynamicLayerRegistry`] holding the layers of the map registered by name
+    pub fn dynamic_layers(&self) -> &DynamicLayerRegistry {
+        &self.dynamic_layers
+    }
+
+    /// Returns a mutable reference to the [`DynamicLayerRegistry`]. Registering a layer only reserves its id,
+    /// add its data to the chunks with
+    /// [`TilemapManager::add_dynamic_layer`](crate::tilemap_manager::TilemapManager::add_dynamic_layer)
+    pub fn dynamic_layers_mut(&mut self) -> &mut DynamicLayerRegistry {
+        &mut self.dynamic_layers
+    }
 }
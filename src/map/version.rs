@@ -0,0 +1,39 @@
+//! Generational versions of a map used for cache invalidation.
+//!
+//! Caches built from map data, such as navmeshes, baked lighting, or GPU mirrors, can store the version they
+//! were built from and cheaply check if they need to be rebuilt instead of listening to every change.
+
+use bevy::prelude::Component;
+use bevy::utils::HashMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Monotonically increasing versions of a map and each of its layers. Spawned on every tilemap entity by
+/// the [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder) and bumped by the
+/// [`TilemapManager`](crate::tilemap_manager::TilemapManager) on every tile or tile entity change.
+#[derive(Component, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MapVersion {
+    version: u64,
+    layer_versions: HashMap<u64, u64>,
+}
+
+impl MapVersion {
+    /// Returns the version of the whole map. Changes whenever any layer changes
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Returns the version of the layer with the given bits. Layers that never changed are at version 0
+    pub fn layer_version(&self, map_layer: u64) -> u64 {
+        self.layer_versions.get(&map_layer).copied().unwrap_or(0)
+    }
+
+    /// Bumps the version of the map and the given layer
+    pub fn bump(&mut self, map_layer: u64) {
+        self.version = self.version.wrapping_add(1);
+        let layer_version = self.layer_versions.entry(map_layer).or_insert(0);
+        *layer_version = layer_version.wrapping_add(1);
+    }
+}
@@ -0,0 +1,100 @@
+//! Synchronous hooks that run after tile data is written.
+//!
+//! Add a [`TileWriteHooks`] component to a tilemap entity and register [`TileWriteHook`]s on it. Every
+//! successful write made through the [`TilemapManager`](crate::tilemap_manager::TilemapManager) calls each
+//! hook immediately, which lets derived structures such as navmeshes or GPU mirrors stay in sync without
+//! waiting a frame for events.
+//!
+//! # Reentrancy
+//!
+//! Hooks run while the manager holds mutable access to the map. They only receive the [`TileWrite`] and
+//! their own state, so they can't read or write the map themselves. A hook that needs to react by editing
+//! the map should record what it needs and apply it later from a system. Hooks run in registration order and
+//! a panicking hook panics the system that made the write.
+
+use bevy::prelude::Component;
+use lettuces::cell::Cell;
+
+/// Information about a single successful write passed to every [`TileWriteHook`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TileWrite<TileData> {
+    /// The cell that was written
+    pub cell: Cell,
+    /// The bits of the [`MapLayer`](crate::map::MapLayer) that was written
+    pub map_layer: u64,
+    /// The tile data in the cell before the write. `None` if the cell had no data
+    pub old: Option<TileData>,
+    /// The tile data that was written
+    pub new: TileData,
+}
+
+/// A hook called after every successful write to a map. Implemented for any matching `FnMut` closure
+pub trait TileWriteHook<TileData>: Send + Sync + 'static {
+    /// Called after the write has been applied
+    fn on_write(&mut self, write: &TileWrite<TileData>);
+}
+
+impl<TileData, F> TileWriteHook<TileData> for F
+where
+    F: FnMut(&TileWrite<TileData>) + Send + Sync + 'static,
+{
+    fn on_write(&mut self, write: &TileWrite<TileData>) {
+        self(write)
+    }
+}
+
+/// The [`TileWriteHook`]s registered on a tilemap entity
+#[derive(Component)]
+pub struct TileWriteHooks<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    hooks: Vec<Box<dyn TileWriteHook<TileData>>>,
+}
+
+impl<TileData> Default for TileWriteHooks<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self { hooks: vec![] }
+    }
+}
+
+impl<TileData> TileWriteHooks<TileData>
+where
+    TileData: Send + Sync + 'static,
+{
+    /// Creates an empty set of hooks
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a hook. Hooks are called in the order they are registered
+    pub fn register(&mut self, hook: impl TileWriteHook<TileData>) {
+        self.hooks.push(Box::new(hook));
+    }
+
+    /// Registers a hook and returns self
+    pub fn with(mut self, hook: impl TileWriteHook<TileData>) -> Self {
+        self.register(hook);
+        self
+    }
+
+    /// Returns the amount of registered hooks
+    pub fn len(&self) -> usize {
+        self.hooks.len()
+    }
+
+    /// Returns true if no hooks are registered
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Calls every registered hook with the given write
+    pub fn call(&mut self, write: &TileWrite<TileData>) {
+        for hook in self.hooks.iter_mut() {
+            hook.on_write(write);
+        }
+    }
+}
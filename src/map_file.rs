@@ -0,0 +1,410 @@
+//! The `.bstmap` file format read and written by the `bst-tool` binary. Requires the `tool` feature.
+//!
+//! A [`MapFile`] stores one [`MapSnapshot`] per map layer. Tile data is stored as `u64` ids, such as
+//! [`PaletteIndex`](crate::map::PaletteIndex)es or enum discriminants, so files can be processed without
+//! knowing the `TileData` type of the game. Files ending in `.ron` are stored as RON, every other file uses
+//! a compact binary encoding.
+
+use crate::testing::MapSnapshot;
+use bevy::math::UVec2;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::path::Path;
+
+/// The magic bytes at the start of every binary `.bstmap` file
+pub const MAP_FILE_MAGIC: [u8; 4] = *b"BSTM";
+
+/// The current version of the map file format. Version 1 files stored layer bits as `u32` and can still be
+/// loaded
+pub const MAP_FILE_VERSION: u32 = 2;
+
+/// Errors returned when reading or writing map files
+#[derive(thiserror::Error, Debug)]
+pub enum MapFileError {
+    /// Failed to read or write the file
+    #[error("Failed to access the map file: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to serialize the map file to RON
+    #[error("Failed to serialize the map file: {0}")]
+    Serialize(#[from] ron::Error),
+
+    /// Failed to deserialize a RON map file
+    #[error("Failed to deserialize the map file: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+
+    /// The binary data is not a valid map file
+    #[error("Invalid binary map file: {0}")]
+    InvalidBinary(&'static str),
+
+    /// The map file was written by a newer version of the format
+    #[error("Unsupported map file version {0}")]
+    UnsupportedVersion(u32),
+}
+
+/// A single layer of a [`MapFile`]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MapFileLayer {
+    /// The bits of the [`MapLayer`](crate::map::MapLayer) this layer was saved from
+    pub map_layer: u64,
+    /// The tile data of the layer
+    pub snapshot: MapSnapshot<u64>,
+}
+
+/// A map saved to disk as a set of layer snapshots
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MapFile {
+    /// The version of the format the file was written with
+    pub version: u32,
+    /// Every layer of the map
+    pub layers: Vec<MapFileLayer>,
+}
+
+impl Default for MapFile {
+    fn default() -> Self {
+        Self {
+            version: MAP_FILE_VERSION,
+            layers: vec![],
+        }
+    }
+}
+
+impl MapFile {
+    /// Creates an empty map file
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a layer to the file, replacing any existing layer with the same bits
+    pub fn add_layer(&mut self, map_layer: u64, snapshot: MapSnapshot<u64>) {
+        self.layers.retain(|layer| layer.map_layer != map_layer);
+        self.layers.push(MapFileLayer {
+            map_layer,
+            snapshot,
+        });
+    }
+
+    /// Returns the layer with the given bits
+    pub fn layer(&self, map_layer: u64) -> Option<&MapFileLayer> {
+        self.layers
+            .iter()
+            .find(|layer| layer.map_layer == map_layer)
+    }
+
+    /// Loads a map file, picking the encoding from the extension of the path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, MapFileError> {
+        let path = path.as_ref();
+        match is_ron(path) {
+            true => Self::from_ron(&std::fs::read_to_string(path)?),
+            false => Self::from_binary(&std::fs::read(path)?),
+        }
+    }
+
+    /// Saves the map file, picking the encoding from the extension of the path
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MapFileError> {
+        let path = path.as_ref();
+        match is_ron(path) {
+            true => std::fs::write(path, self.to_ron()?)?,
+            false => std::fs::write(path, self.to_binary())?,
+        }
+        Ok(())
+    }
+
+    /// Serializes the map file to RON
+    pub fn to_ron(&self) -> Result<String, MapFileError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    /// Deserializes a map file from RON
+    pub fn from_ron(ron: &str) -> Result<Self, MapFileError> {
+        let map_file: MapFile = ron::from_str(ron)?;
+        map_file.check_version()?;
+        Ok(map_file)
+    }
+
+    /// Serializes the map file to the binary encoding
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut bytes = MAP_FILE_MAGIC.to_vec();
+        bytes.extend(MAP_FILE_VERSION.to_le_bytes());
+        bytes.extend((self.layers.len() as u32).to_le_bytes());
+        for layer in self.layers.iter() {
+            bytes.extend(layer.map_layer.to_le_bytes());
+            bytes.extend(layer.snapshot.dimensions.x.to_le_bytes());
+            bytes.extend(layer.snapshot.dimensions.y.to_le_bytes());
+            bytes.extend((layer.snapshot.tiles.len() as u32).to_le_bytes());
+            for row in layer.snapshot.tiles.iter() {
+                bytes.extend((row.len() as u32).to_le_bytes());
+                for tile in row.iter() {
+                    match tile {
+                        Some(tile_data) => {
+                            bytes.push(1);
+                            bytes.extend(tile_data.to_le_bytes());
+                        }
+                        None => bytes.push(0),
+                    }
+                }
+            }
+        }
+        bytes
+    }
+
+    /// Deserializes a map file from the binary encoding
+    pub fn from_binary(bytes: &[u8]) -> Result<Self, MapFileError> {
+        let mut reader = BinaryReader { bytes };
+        if reader.take(4)? != MAP_FILE_MAGIC {
+            return Err(MapFileError::InvalidBinary("missing magic bytes"));
+        }
+        let version = reader.u32()?;
+        let mut map_file = MapFile {
+            version,
+            layers: vec![],
+        };
+        map_file.check_version()?;
+        for _ in 0..reader.u32()? {
+            let map_layer = match version {
+                1 => u64::from(reader.u32()?),
+                _ => reader.u64()?,
+            };
+            let dimensions = UVec2::new(reader.u32()?, reader.u32()?);
+            let row_count = reader.u32()?;
+            let mut tiles = vec![];
+            for _ in 0..row_count {
+                let row_length = reader.u32()?;
+                let mut row = vec![];
+                for _ in 0..row_length {
+                    match reader.take(1)?[0] {
+                        0 => row.push(None),
+                        1 => row.push(Some(reader.u64()?)),
+                        _ => return Err(MapFileError::InvalidBinary("invalid tile tag")),
+                    }
+                }
+                tiles.push(row);
+            }
+            map_file.layers.push(MapFileLayer {
+                map_layer,
+                snapshot: MapSnapshot { dimensions, tiles },
+            });
+        }
+        if !reader.bytes.is_empty() {
+            return Err(MapFileError::InvalidBinary("trailing bytes"));
+        }
+        Ok(map_file)
+    }
+
+    /// Checks that every layer is well formed and that all layers share the same dimensions. Returns a
+    /// description of every problem found
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let dimensions = self.layers.first().map(|layer| layer.snapshot.dimensions);
+        for layer in self.layers.iter() {
+            let snapshot = &layer.snapshot;
+            if Some(snapshot.dimensions) != dimensions {
+                problems.push(format!(
+                    "layer {}: dimensions {} differ from the first layer",
+                    layer.map_layer, snapshot.dimensions
+                ));
+            }
+            if snapshot.tiles.len() != snapshot.dimensions.y as usize {
+                problems.push(format!(
+                    "layer {}: has {} rows but is {} tall",
+                    layer.map_layer,
+                    snapshot.tiles.len(),
+                    snapshot.dimensions.y
+                ));
+            }
+            for (y, row) in snapshot.tiles.iter().enumerate() {
+                if row.len() != snapshot.dimensions.x as usize {
+                    problems.push(format!(
+                        "layer {}: row {} has {} tiles but the layer is {} wide",
+                        layer.map_layer,
+                        y,
+                        row.len(),
+                        snapshot.dimensions.x
+                    ));
+                }
+            }
+        }
+        let mut layer_bits: Vec<u64> = self.layers.iter().map(|layer| layer.map_layer).collect();
+        layer_bits.sort();
+        layer_bits.dedup();
+        if layer_bits.len() != self.layers.len() {
+            problems.push("the same layer is stored more than once".to_string());
+        }
+        problems
+    }
+
+    /// Returns a readable report of the differences between self (the expected map) and the given map or
+    /// `None` if they match
+    pub fn diff_report(&self, other: &MapFile) -> Option<String> {
+        let mut report = String::new();
+        for layer in self.layers.iter() {
+            match other.layer(layer.map_layer) {
+                Some(other_layer) => {
+                    if let Some(layer_report) =
+                        layer.snapshot.mismatch_report(&other_layer.snapshot)
+                    {
+                        let _ = write!(report, "layer {}: {}", layer.map_layer, layer_report);
+                    }
+                }
+                None => {
+                    let _ = writeln!(report, "layer {}: missing", layer.map_layer);
+                }
+            }
+        }
+        for layer in other.layers.iter() {
+            if self.layer(layer.map_layer).is_none() {
+                let _ = writeln!(report, "layer {}: unexpected", layer.map_layer);
+            }
+        }
+        match report.is_empty() {
+            true => None,
+            false => Some(report),
+        }
+    }
+
+    /// Renders the given layer as a binary PGM image with one pixel per cell and the top row of the map at
+    /// the top of the image. Cells without data are black, every tile id gets its own shade of grey
+    pub fn thumbnail_pgm(&self, map_layer: u64) -> Option<Vec<u8>> {
+        let snapshot = &self.layer(map_layer)?.snapshot;
+        let dimensions = snapshot.dimensions;
+        let mut pgm = format!("P5\n{} {}\n255\n", dimensions.x, dimensions.y).into_bytes();
+        for y in (0..dimensions.y as i32).rev() {
+            for x in 0..dimensions.x as i32 {
+                pgm.push(match snapshot.get(lettuces::cell::Cell::new(x, y)) {
+                    Some(tile_data) => tile_shade(tile_data),
+                    None => 0,
+                });
+            }
+        }
+        Some(pgm)
+    }
+
+    fn check_version(&self) -> Result<(), MapFileError> {
+        match self.version > MAP_FILE_VERSION {
+            true => Err(MapFileError::UnsupportedVersion(self.version)),
+            false => Ok(()),
+        }
+    }
+}
+
+fn is_ron(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension == "ron")
+        .unwrap_or(false)
+}
+
+/// Spreads tile ids over the shades 32..=255 so neighbouring ids are easy to tell apart
+fn tile_shade(tile_data: u64) -> u8 {
+    let mixed = tile_data.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 56;
+    32 + (mixed % 224) as u8
+}
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> BinaryReader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], MapFileError> {
+        if self.bytes.len() < count {
+            return Err(MapFileError::InvalidBinary("unexpected end of file"));
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, MapFileError> {
+        let mut bytes = [0; 4];
+        bytes.copy_from_slice(self.take(4)?);
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn u64(&mut self) -> Result<u64, MapFileError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MapFile, MapFileError, MAP_FILE_MAGIC};
+    use crate::testing::MapSnapshot;
+    use bevy::math::UVec2;
+
+    fn map_file() -> MapFile {
+        let mut map_file = MapFile::new();
+        map_file.add_layer(
+            1,
+            MapSnapshot {
+                dimensions: UVec2::new(3, 2),
+                tiles: vec![
+                    vec![Some(0), Some(1), None],
+                    vec![Some(7), None, Some(u64::MAX)],
+                ],
+            },
+        );
+        map_file.add_layer(
+            2,
+            MapSnapshot {
+                dimensions: UVec2::new(3, 2),
+                tiles: vec![vec![None; 3]; 2],
+            },
+        );
+        map_file
+    }
+
+    #[test]
+    fn test_map_file_encodings_round_trip() {
+        let map_file = map_file();
+        assert_eq!(
+            MapFile::from_binary(&map_file.to_binary()).unwrap(),
+            map_file
+        );
+        assert_eq!(
+            MapFile::from_ron(&map_file.to_ron().unwrap()).unwrap(),
+            map_file
+        );
+
+        let mut truncated = map_file.to_binary();
+        truncated.pop();
+        assert!(matches!(
+            MapFile::from_binary(&truncated),
+            Err(MapFileError::InvalidBinary(_))
+        ));
+
+        // Version 1 files stored the layer bits as u32
+        let mut version_1 = MAP_FILE_MAGIC.to_vec();
+        for value in [1u32, 1, 1 << 31, 1, 1, 1, 1] {
+            version_1.extend(value.to_le_bytes());
+        }
+        version_1.push(1);
+        version_1.extend(5u64.to_le_bytes());
+        let map_file = MapFile::from_binary(&version_1).unwrap();
+        assert_eq!(map_file.layers[0].map_layer, 1 << 31);
+        assert_eq!(map_file.layers[0].snapshot.tiles, vec![vec![Some(5)]]);
+    }
+
+    #[test]
+    fn test_map_file_validate_and_diff() {
+        let expected = map_file();
+        assert!(expected.validate().is_empty());
+        assert!(expected.diff_report(&expected).is_none());
+
+        let mut found = expected.clone();
+        found.layers[0].snapshot.tiles[1].pop();
+        found.layers[1].snapshot.dimensions = UVec2::new(2, 2);
+        assert_eq!(found.validate().len(), 4);
+        let report = expected.diff_report(&found).unwrap();
+        assert!(report.contains("layer 1"));
+        assert!(report.contains("layer 2"));
+
+        let pgm = expected.thumbnail_pgm(1).unwrap();
+        assert!(pgm.starts_with(b"P5\n3 2\n255\n"));
+        // The top row of the image is the last row of the map
+        assert_eq!(pgm[pgm.len() - 6..pgm.len() - 3][1], 0);
+    }
+}
@@ -0,0 +1,383 @@
+//! Objects occupying several cells of a map, such as buildings in strategy games.
+//!
+//! A [`TileObjects`] on a tilemap entity maps every object entity to its footprint, the set of cells it covers
+//! on a layer, and keeps a reverse lookup from each covered cell to its object. Footprints can't overlap, so
+//! placing an object doubles as collision detection. Tile entities only ever belong to a single cell, use them
+//! for per cell state and [`TileObjects`] for anything larger.
+//!
+//! Place objects with [`TilemapManager::place_object`] to also check the footprint against the bounds and
+//! [`MapMask`](crate::map::MapMask) of the map. The [`TileObjectsPlugin`] removes objects whose entity was
+//! despawned.
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::ecs::entity::Entities;
+use bevy::prelude::{Component, DetectChangesMut, Entity, Query};
+use bevy::utils::{HashMap, HashSet};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// Errors returned when placing an object
+#[derive(thiserror::Error, Debug)]
+pub enum ObjectError {
+    /// A cell of the footprint is already covered by another object
+    #[error("Cell {cell:?} is already occupied by {occupant:?}")]
+    CellOccupied {
+        /// The cell that was requested
+        cell: Cell,
+        /// The object covering the cell
+        occupant: Entity,
+    },
+
+    /// A cell of the footprint is outside of the map or its [`MapMask`](crate::map::MapMask)
+    #[error("Cell {0:?} is outside of the map")]
+    CellOutOfBounds(Cell),
+
+    /// Objects must cover at least one cell
+    #[error("The footprint of an object can't be empty")]
+    EmptyFootprint,
+
+    /// The [`TilemapManager`] failed to access the map
+    #[error(transparent)]
+    Tilemap(#[from] TilemapManagerError),
+}
+
+/// The objects of a single layer
+#[derive(Clone, Debug, Default)]
+struct LayerObjects {
+    occupants: HashMap<Cell, Entity>,
+    footprints: HashMap<Entity, Vec<Cell>>,
+}
+
+impl LayerObjects {
+    fn remove(&mut self, object: Entity) -> Option<Vec<Cell>> {
+        let footprint = self.footprints.remove(&object)?;
+        for cell in footprint.iter() {
+            self.occupants.remove(cell);
+        }
+        Some(footprint)
+    }
+}
+
+/// The footprints of every object on each layer of a map and which object covers each cell. Insert it on the
+/// tilemap entity. See the [module docs](self) for details
+#[derive(Component, Clone, Debug, Default)]
+pub struct TileObjects {
+    layers: HashMap<u64, LayerObjects>,
+}
+
+impl TileObjects {
+    /// Creates a set without any objects
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places the object on the given cells of the layer. An object that was already placed on the layer is
+    /// moved to its new footprint, which may overlap its old one.
+    ///
+    /// Fails without changing anything if the footprint is empty or another object covers any of its cells
+    pub fn place_object(
+        &mut self,
+        map_layer: impl MapLayer,
+        object: Entity,
+        cells: impl IntoIterator<Item = Cell>,
+    ) -> Result<(), ObjectError> {
+        let mut seen = HashSet::new();
+        let footprint: Vec<Cell> = cells
+            .into_iter()
+            .filter(|cell| seen.insert(*cell))
+            .collect();
+        if footprint.is_empty() {
+            return Err(ObjectError::EmptyFootprint);
+        }
+        let layer = self.layers.entry(map_layer.to_bits()).or_default();
+        if let Some((cell, occupant)) = footprint.iter().find_map(|cell| {
+            layer
+                .occupants
+                .get(cell)
+                .filter(|occupant| **occupant != object)
+                .map(|occupant| (*cell, *occupant))
+        }) {
+            return Err(ObjectError::CellOccupied { cell, occupant });
+        }
+        layer.remove(object);
+        for cell in footprint.iter() {
+            layer.occupants.insert(*cell, object);
+        }
+        layer.footprints.insert(object, footprint);
+        Ok(())
+    }
+
+    /// Removes the object from the layer and returns its footprint
+    pub fn remove_object(&mut self, map_layer: impl MapLayer, object: Entity) -> Option<Vec<Cell>> {
+        self.layers.get_mut(&map_layer.to_bits())?.remove(object)
+    }
+
+    /// Returns the object covering the given cell of the layer
+    pub fn object_at(&self, map_layer: impl MapLayer, cell: Cell) -> Option<Entity> {
+        self.layers
+            .get(&map_layer.to_bits())?
+            .occupants
+            .get(&cell)
+            .copied()
+    }
+
+    /// Returns the cells covered by the object on the layer
+    pub fn footprint(&self, map_layer: impl MapLayer, object: Entity) -> Option<&[Cell]> {
+        self.layers
+            .get(&map_layer.to_bits())?
+            .footprints
+            .get(&object)
+            .map(|footprint| footprint.as_slice())
+    }
+
+    /// Returns every given cell of the layer that is covered by an object along with that object
+    pub fn collisions(
+        &self,
+        map_layer: impl MapLayer,
+        cells: impl IntoIterator<Item = Cell>,
+    ) -> Vec<(Cell, Entity)> {
+        let Some(layer) = self.layers.get(&map_layer.to_bits()) else {
+            return vec![];
+        };
+        cells
+            .into_iter()
+            .filter_map(|cell| layer.occupants.get(&cell).map(|object| (cell, *object)))
+            .collect()
+    }
+
+    /// Returns true if the object could be placed on the given cells of the layer without overlapping any
+    /// other object
+    pub fn can_place(
+        &self,
+        map_layer: impl MapLayer,
+        object: Entity,
+        cells: impl IntoIterator<Item = Cell>,
+    ) -> bool {
+        self.collisions(map_layer, cells)
+            .iter()
+            .all(|(_, occupant)| *occupant == object)
+    }
+
+    /// Returns an iterator over every object on the layer and its footprint
+    pub fn iter(&self, map_layer: impl MapLayer) -> impl Iterator<Item = (Entity, &[Cell])> + '_ {
+        self.layers
+            .get(&map_layer.to_bits())
+            .into_iter()
+            .flat_map(|layer| layer.footprints.iter())
+            .map(|(object, footprint)| (*object, footprint.as_slice()))
+    }
+
+    /// Removes every object whose entity no longer exists and returns how many were removed
+    pub fn remove_despawned(&mut self, entities: &Entities) -> usize {
+        let mut removed = 0;
+        for layer in self.layers.values_mut() {
+            let despawned: Vec<Entity> = layer
+                .footprints
+                .keys()
+                .filter(|object| !entities.contains(**object))
+                .copied()
+                .collect();
+            for object in despawned {
+                layer.remove(object);
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Places the object on the given cells of the current layer like [`TileObjects::place_object`]. Pass the
+    /// [`TileObjects`] of the map this manager is set to.
+    ///
+    /// Also fails with [`ObjectError::CellOutOfBounds`] if a cell is outside of the map or its
+    /// [`MapMask`](crate::map::MapMask)
+    pub fn place_object(
+        &self,
+        objects: &mut TileObjects,
+        object: Entity,
+        cells: impl IntoIterator<Item = Cell>,
+    ) -> Result<(), ObjectError> {
+        let cells: Vec<Cell> = cells.into_iter().collect();
+        let in_map: HashSet<Cell> = self
+            .cells_in_map(cells.iter().copied())?
+            .into_iter()
+            .collect();
+        if let Some(cell) = cells.iter().find(|cell| !in_map.contains(*cell)) {
+            return Err(ObjectError::CellOutOfBounds(*cell));
+        }
+        objects.place_object(self.layer(), object, cells)
+    }
+}
+
+/// Plugin that removes objects whose entity was despawned from every [`TileObjects`]
+#[derive(Default)]
+pub struct TileObjectsPlugin;
+
+impl Plugin for TileObjectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, remove_despawned_objects);
+    }
+}
+
+/// Removes every object whose entity no longer exists. Only [`TileObjects`] that had objects removed are
+/// marked as changed
+pub fn remove_despawned_objects(mut maps: Query<&mut TileObjects>, entities: &Entities) {
+    for mut objects in maps.iter_mut() {
+        if objects.bypass_change_detection().remove_despawned(entities) > 0 {
+            objects.set_changed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ObjectError, TileObjects, TileObjectsPlugin};
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Overlay,
+    }
+
+    fn square(x: i32, y: i32) -> Vec<Cell> {
+        vec![
+            Cell::new(x, y),
+            Cell::new(x + 1, y),
+            Cell::new(x, y + 1),
+            Cell::new(x + 1, y + 1),
+        ]
+    }
+
+    #[test]
+    fn test_tile_objects() {
+        let mut world = World::new();
+        let house = world.spawn_empty().id();
+        let tower = world.spawn_empty().id();
+        let mut objects = TileObjects::new();
+
+        objects
+            .place_object(MapLayers::Main, house, square(0, 0))
+            .unwrap();
+        assert_eq!(
+            objects.object_at(MapLayers::Main, Cell::new(1, 1)),
+            Some(house)
+        );
+        assert_eq!(objects.object_at(MapLayers::Overlay, Cell::new(1, 1)), None);
+        assert!(matches!(
+            objects.place_object(MapLayers::Main, tower, square(1, 1)),
+            Err(ObjectError::CellOccupied { cell, occupant }) if cell == Cell::new(1, 1) && occupant == house
+        ));
+        assert!(!objects.can_place(MapLayers::Main, tower, square(1, 1)));
+        assert_eq!(
+            objects
+                .collisions(MapLayers::Main, square(1, 1))
+                .into_iter()
+                .map(|(_, object)| object)
+                .collect::<Vec<_>>(),
+            vec![house]
+        );
+        objects
+            .place_object(MapLayers::Overlay, tower, square(1, 1))
+            .unwrap();
+
+        // Moving an object may overlap its own footprint
+        assert!(objects.can_place(MapLayers::Main, house, square(1, 0)));
+        objects
+            .place_object(MapLayers::Main, house, square(1, 0))
+            .unwrap();
+        assert_eq!(objects.object_at(MapLayers::Main, Cell::new(0, 0)), None);
+        assert_eq!(
+            objects.object_at(MapLayers::Main, Cell::new(2, 1)),
+            Some(house)
+        );
+        assert_eq!(objects.footprint(MapLayers::Main, house).unwrap().len(), 4);
+        assert!(matches!(
+            objects.place_object(MapLayers::Main, tower, []),
+            Err(ObjectError::EmptyFootprint)
+        ));
+
+        assert_eq!(
+            objects.remove_object(MapLayers::Main, house),
+            Some(square(1, 0))
+        );
+        assert_eq!(objects.iter(MapLayers::Main).count(), 0);
+        assert_eq!(objects.iter(MapLayers::Overlay).count(), 1);
+    }
+
+    #[test]
+    fn test_place_object_on_map() {
+        let mut app = App::new();
+        app.add_plugins(TileObjectsPlugin);
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let house = commands.spawn_empty().id();
+        system_state.apply(&mut app.world);
+
+        let mut objects = TileObjects::new();
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert!(matches!(
+            tilemap_manager.place_object(&mut objects, house, square(3, 3)),
+            Err(ObjectError::CellOutOfBounds(cell)) if cell == Cell::new(4, 3)
+        ));
+        assert_eq!(objects.iter(MapLayers::Main).count(), 0);
+        tilemap_manager
+            .place_object(&mut objects, house, square(1, 1))
+            .unwrap();
+        app.world.entity_mut(map_entity).insert(objects);
+
+        // Despawned objects are removed
+        app.update();
+        assert_eq!(
+            app.world
+                .get::<TileObjects>(map_entity)
+                .unwrap()
+                .object_at(MapLayers::Main, Cell::new(2, 2)),
+            Some(house)
+        );
+        app.world.despawn(house);
+        app.update();
+        assert_eq!(
+            app.world
+                .get::<TileObjects>(map_entity)
+                .unwrap()
+                .object_at(MapLayers::Main, Cell::new(2, 2)),
+            None
+        );
+    }
+}
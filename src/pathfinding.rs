@@ -0,0 +1,855 @@
+//! Grid pathfinding over a single map layer.
+//!
+//! [`find_path`] runs a plain A* search over the orthogonal neighbours of each cell. The cost of a path is the
+//! sum of the costs of every cell it enters, as returned by a cost function. Cells the cost function returns
+//! `None` for, and cells without tile data, can't be entered. [`dijkstra_map`] instead finds the cheapest path
+//! from one cell to every cell it can reach, for AI that weighs many destinations at once. Both read tiles
+//! through the [`TilemapManager`] so paths cross chunk borders like any other cell.
+//!
+//! Long paths on large maps are answered by a [`HierarchicalPathfinder`] (HPA*) instead. It splits the map
+//! into clusters, usually the size of a chunk, and caches a graph of the entrances between neighbouring
+//! clusters together with the cost of crossing each cluster. Queries only search that small graph and
+//! [`HierarchicalPathfinder::refine_path`] turns the result into a cell by cell path when needed. Register
+//! [`HierarchicalPathfinder::hook`] in the maps [`TileWriteHooks`](crate::map::TileWriteHooks) so clusters
+//! are rebuilt when the tiles inside them change.
+//!
+//! Hierarchical paths are close to, but not always exactly, the shortest path.
+
+use crate::map::chunk::{ChunkLayer, ChunkPos};
+use crate::map::{MapData, MapLayer, TileWrite, TileWriteHook};
+use crate::tilemap_manager::TilemapManager;
+use crate::validation::ORTHOGONAL_NEIGHBOURS;
+use bevy::math::{IVec2, UVec2};
+use bevy::prelude::Component;
+use bevy::utils::{HashMap, HashSet};
+use lettuces::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+
+/// Entrances longer than this get a transition at both ends instead of one in the middle
+const LONG_ENTRANCE: i32 = 6;
+
+/// A path between two cells
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Path {
+    /// Every cell of the path including the start and the goal
+    pub cells: Vec<Cell>,
+    /// The summed cost of every cell entered along the path
+    pub cost: u32,
+}
+
+/// A path found by a [`HierarchicalPathfinder`]. Consecutive waypoints are either neighbours or in the same
+/// cluster. Use [`HierarchicalPathfinder::refine_path`] to get every cell of the path
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HierarchicalPath {
+    /// The start, the cluster entrances the path passes through, and the goal
+    pub waypoints: Vec<Cell>,
+    /// The summed cost of every cell entered along the refined path
+    pub cost: u32,
+}
+
+/// Finds the cheapest path between start and goal on the layer the [`TilemapManager`] is set to.
+///
+/// `cost` returns the cost of entering a cell or `None` if it can't be entered. Costs below one are treated
+/// as one. Returns `None` if no path exists or either end can't be entered.
+pub fn find_path<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    start: Cell,
+    goal: Cell,
+    cost: impl Fn(Cell, &TileData) -> Option<u32>,
+) -> Option<Path>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let tile_cost = |cell: Cell| tile_cost(tilemap_manager, cell, &cost);
+    tile_cost(start)?;
+    tile_cost(goal)?;
+    astar(start, goal, &tile_cost, |_| true)
+}
+
+/// The cheapest paths from a single source cell to every cell reachable from it, as found by [`dijkstra_map`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DijkstraMap {
+    source: Cell,
+    costs: HashMap<Cell, u32>,
+    came_from: HashMap<Cell, Cell>,
+}
+
+impl DijkstraMap {
+    /// Returns the cell the paths start at
+    pub fn source(&self) -> Cell {
+        self.source
+    }
+
+    /// Returns the cost of the cheapest path from the source to the cell or `None` if it can't be reached
+    pub fn cost(&self, cell: Cell) -> Option<u32> {
+        self.costs.get(&cell).copied()
+    }
+
+    /// Returns true if the cell can be reached from the source
+    pub fn contains(&self, cell: Cell) -> bool {
+        self.costs.contains_key(&cell)
+    }
+
+    /// Returns every reachable cell together with the cost of reaching it
+    pub fn iter(&self) -> impl Iterator<Item = (Cell, u32)> + '_ {
+        self.costs.iter().map(|(cell, cost)| (*cell, *cost))
+    }
+
+    /// Returns the cheapest path from the source to the cell or `None` if it can't be reached
+    pub fn path_to(&self, cell: Cell) -> Option<Path> {
+        let cost = self.cost(cell)?;
+        let mut cells = vec![cell];
+        let mut current = cell;
+        while let Some(previous) = self.came_from.get(&current) {
+            cells.push(*previous);
+            current = *previous;
+        }
+        cells.reverse();
+        Some(Path { cells, cost })
+    }
+}
+
+/// Finds the cheapest path from source to every cell it can reach on the layer the [`TilemapManager`] is set
+/// to, stopping at the edges of the map.
+///
+/// `cost` works like it does for [`find_path`]. The source itself only has to have tile data, its cost is
+/// never paid. Returns an empty map if the source has no tile data or the map has no known dimensions.
+pub fn dijkstra_map<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    source: Cell,
+    cost: impl Fn(Cell, &TileData) -> Option<u32>,
+) -> DijkstraMap
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let mut dijkstra_map = DijkstraMap {
+        source,
+        costs: HashMap::new(),
+        came_from: HashMap::new(),
+    };
+    let (Ok(dimensions), Ok(_)) = (
+        tilemap_manager.dimensions(),
+        tilemap_manager.get_tile_data(source),
+    ) else {
+        return dijkstra_map;
+    };
+    let max = dimensions.as_ivec2() - IVec2::ONE;
+
+    let mut open = BinaryHeap::new();
+    dijkstra_map.costs.insert(source, 0);
+    open.push(Reverse((0u32, (source.x, source.y))));
+
+    while let Some(Reverse((distance, (x, y)))) = open.pop() {
+        let cell = Cell::new(x, y);
+        if dijkstra_map
+            .costs
+            .get(&cell)
+            .is_some_and(|best| *best < distance)
+        {
+            continue;
+        }
+        for next in neighbours(cell) {
+            if next.x < 0 || next.y < 0 || next.x > max.x || next.y > max.y {
+                continue;
+            }
+            let Some(step_cost) = tile_cost(tilemap_manager, next, &cost) else {
+                continue;
+            };
+            let next_distance = distance + step_cost;
+            if dijkstra_map
+                .costs
+                .get(&next)
+                .is_some_and(|best| *best <= next_distance)
+            {
+                continue;
+            }
+            dijkstra_map.costs.insert(next, next_distance);
+            dijkstra_map.came_from.insert(next, cell);
+            open.push(Reverse((next_distance, (next.x, next.y))));
+        }
+    }
+    dijkstra_map
+}
+
+/// A cached hierarchical pathfinding graph for a single layer of a map. Insert it on the tilemap entity.
+///
+/// Clusters are built the first time a query needs them and rebuilt after tiles inside them or on their
+/// border are written. The cached graph is built with the cost function of the query that built it, so
+/// every query should pass the same cost function or call [`Self::invalidate_all`] when it changes.
+#[derive(Component)]
+pub struct HierarchicalPathfinder<MapLayers>
+where
+    MapLayers: Send + Sync + 'static,
+{
+    map_layer: MapLayers,
+    cluster_size: IVec2,
+    clusters: HashMap<ChunkPos, ClusterGraph>,
+    dirty: Arc<Mutex<HashSet<ChunkPos>>>,
+}
+
+/// The entrances of a cluster and the edges leaving them
+#[derive(Default)]
+struct ClusterGraph {
+    edges: HashMap<Cell, Vec<(Cell, u32)>>,
+}
+
+impl<MapLayers> HierarchicalPathfinder<MapLayers>
+where
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+{
+    /// Creates a pathfinder for the given layer splitting the map into clusters of the given size. Using the
+    /// maps max chunk size works well for most maps.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either axis of `cluster_size` is zero
+    pub fn new(map_layer: MapLayers, cluster_size: UVec2) -> Self {
+        assert!(
+            cluster_size.x > 0 && cluster_size.y > 0,
+            "Clusters must contain at least one cell"
+        );
+        Self {
+            map_layer,
+            cluster_size: cluster_size.as_ivec2(),
+            clusters: HashMap::new(),
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Returns the layer paths are found on
+    pub fn map_layer(&self) -> MapLayers {
+        self.map_layer
+    }
+
+    /// Returns the amount of clusters that currently have a cached graph
+    pub fn cached_cluster_count(&self) -> usize {
+        self.clusters.len()
+    }
+
+    /// Returns a [`TileWriteHook`] that marks the clusters around written cells as needing a rebuild
+    pub fn hook<TileData>(&self) -> impl TileWriteHook<TileData>
+    where
+        TileData: Send + Sync + 'static,
+    {
+        let dirty = self.dirty.clone();
+        let map_layer = self.map_layer.to_bits();
+        let cluster_size = self.cluster_size;
+        move |write: &TileWrite<TileData>| {
+            if write.map_layer != map_layer {
+                return;
+            }
+            dirty
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(cluster_of(write.cell, cluster_size));
+        }
+    }
+
+    /// Drops every cached cluster
+    pub fn invalidate_all(&mut self) {
+        self.clusters.clear();
+        self.take_dirty_clusters();
+    }
+
+    /// Finds a path between start and goal through the cached cluster graph, building any clusters the search
+    /// reaches that aren't cached yet.
+    ///
+    /// `cost` returns the cost of entering a cell or `None` if it can't be entered. Costs below one are
+    /// treated as one. Returns `None` if no path exists or either end can't be entered.
+    pub fn find_path_hierarchical<TileData, MapChunk, Map>(
+        &mut self,
+        tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+        start: Cell,
+        goal: Cell,
+        cost: impl Fn(Cell, &TileData) -> Option<u32>,
+    ) -> Option<HierarchicalPath>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        for cluster in self.take_dirty_clusters() {
+            self.clusters.remove(&cluster);
+            for offset in ORTHOGONAL_NEIGHBOURS {
+                self.clusters
+                    .remove(&ChunkPos::from(cluster.as_ivec2() + offset));
+            }
+        }
+
+        let previous_layer = tilemap_manager.layer();
+        tilemap_manager.set_layer(self.map_layer);
+        let path = self.search(tilemap_manager, start, goal, &cost);
+        tilemap_manager.set_layer(previous_layer);
+        path
+    }
+
+    /// Expands a [`HierarchicalPath`] into every cell along it. `cost` must be the cost function the path was
+    /// found with. Returns `None` if tiles along the path changed so it can no longer be followed
+    pub fn refine_path<TileData, MapChunk, Map>(
+        &self,
+        tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+        path: &HierarchicalPath,
+        cost: impl Fn(Cell, &TileData) -> Option<u32>,
+    ) -> Option<Path>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let previous_layer = tilemap_manager.layer();
+        tilemap_manager.set_layer(self.map_layer);
+        let manager: &TilemapManager<TileData, MapLayers, MapChunk, Map> = tilemap_manager;
+        let tile_cost = |cell: Cell| tile_cost(manager, cell, &cost);
+
+        let mut refined = Path {
+            cells: path.waypoints.first().cloned().into_iter().collect(),
+            cost: 0,
+        };
+        for pair in path.waypoints.windows(2) {
+            let cluster = cluster_of(pair[0], self.cluster_size);
+            let Some(segment) = astar(pair[0], pair[1], &tile_cost, |cell| {
+                cluster_of(cell, self.cluster_size) == cluster || cell == pair[1]
+            }) else {
+                tilemap_manager.set_layer(previous_layer);
+                return None;
+            };
+            refined.cells.extend(segment.cells.into_iter().skip(1));
+            refined.cost += segment.cost;
+        }
+        tilemap_manager.set_layer(previous_layer);
+        Some(refined)
+    }
+
+    fn take_dirty_clusters(&self) -> HashSet<ChunkPos> {
+        std::mem::take(
+            &mut *self
+                .dirty
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    fn search<TileData, MapChunk, Map>(
+        &mut self,
+        tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+        start: Cell,
+        goal: Cell,
+        cost: &impl Fn(Cell, &TileData) -> Option<u32>,
+    ) -> Option<HierarchicalPath>
+    where
+        TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let tile_cost = |cell: Cell| tile_cost(tilemap_manager, cell, cost);
+        tile_cost(start)?;
+        tile_cost(goal)?;
+        if start == goal {
+            return Some(HierarchicalPath {
+                waypoints: vec![start],
+                cost: 0,
+            });
+        }
+
+        let start_cluster = cluster_of(start, self.cluster_size);
+        let goal_cluster = cluster_of(goal, self.cluster_size);
+        let start_bounds = cluster_bounds(start_cluster, self.cluster_size);
+        let goal_bounds = cluster_bounds(goal_cluster, self.cluster_size);
+        self.cluster(start_cluster, &tile_cost);
+        self.cluster(goal_cluster, &tile_cost);
+
+        // Connect the start and goal to the entrances of their clusters
+        let from_start = dijkstra(start, start_bounds, &tile_cost, false);
+        let to_goal = dijkstra(goal, goal_bounds, &tile_cost, true);
+        let mut start_edges: Vec<(Cell, u32)> = self.clusters[&start_cluster]
+            .edges
+            .keys()
+            .filter_map(|node| from_start.get(node).map(|distance| (*node, *distance)))
+            .collect();
+        if let Some(distance) = from_start
+            .get(&goal)
+            .filter(|_| start_cluster == goal_cluster)
+        {
+            start_edges.push((goal, *distance));
+        }
+        let goal_edges: HashMap<Cell, u32> = self.clusters[&goal_cluster]
+            .edges
+            .keys()
+            .filter_map(|node| to_goal.get(node).map(|distance| (*node, *distance)))
+            .collect();
+
+        let mut open = BinaryHeap::new();
+        let mut best: HashMap<Cell, u32> = HashMap::new();
+        let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+        best.insert(start, 0);
+        open.push(Reverse((manhattan(start, goal), 0u32, (start.x, start.y))));
+
+        while let Some(Reverse((_, distance, (x, y)))) = open.pop() {
+            let node = Cell::new(x, y);
+            if node == goal {
+                let mut waypoints = vec![goal];
+                let mut current = goal;
+                while let Some(previous) = came_from.get(&current) {
+                    waypoints.push(*previous);
+                    current = *previous;
+                }
+                waypoints.reverse();
+                return Some(HierarchicalPath {
+                    waypoints,
+                    cost: distance,
+                });
+            }
+            if best.get(&node).is_some_and(|best| *best < distance) {
+                continue;
+            }
+
+            let mut edges: Vec<(Cell, u32)> = vec![];
+            if node == start {
+                edges.extend(start_edges.iter().cloned());
+            }
+            let cluster = cluster_of(node, self.cluster_size);
+            if let Some(node_edges) = self.cluster(cluster, &tile_cost).edges.get(&node) {
+                edges.extend(node_edges.iter().cloned());
+            }
+            if let Some(distance) = goal_edges.get(&node) {
+                edges.push((goal, *distance));
+            }
+
+            for (next, edge_cost) in edges {
+                let next_distance = distance + edge_cost;
+                if best.get(&next).is_some_and(|best| *best <= next_distance) {
+                    continue;
+                }
+                best.insert(next, next_distance);
+                came_from.insert(next, node);
+                open.push(Reverse((
+                    next_distance + manhattan(next, goal),
+                    next_distance,
+                    (next.x, next.y),
+                )));
+            }
+        }
+        None
+    }
+
+    /// Returns the graph of the given cluster, building it if it isn't cached
+    fn cluster(
+        &mut self,
+        cluster: ChunkPos,
+        tile_cost: &impl Fn(Cell) -> Option<u32>,
+    ) -> &ClusterGraph {
+        let cluster_size = self.cluster_size;
+        self.clusters
+            .entry(cluster)
+            .or_insert_with(|| build_cluster(cluster, cluster_size, tile_cost))
+    }
+}
+
+/// Builds the entrances of a cluster, the edges crossing into neighbouring clusters, and the cost of moving
+/// between every pair of entrances inside the cluster
+fn build_cluster(
+    cluster: ChunkPos,
+    cluster_size: IVec2,
+    tile_cost: &impl Fn(Cell) -> Option<u32>,
+) -> ClusterGraph {
+    let (min, max) = cluster_bounds(cluster, cluster_size);
+    let mut graph = ClusterGraph::default();
+
+    // Each side is walked along its length, pairing border cells inside the cluster with the cells across
+    let sides = [
+        (IVec2::new(max.x, min.y), IVec2::Y, IVec2::X, cluster_size.y),
+        (
+            IVec2::new(min.x, min.y),
+            IVec2::Y,
+            IVec2::NEG_X,
+            cluster_size.y,
+        ),
+        (IVec2::new(min.x, max.y), IVec2::X, IVec2::Y, cluster_size.x),
+        (
+            IVec2::new(min.x, min.y),
+            IVec2::X,
+            IVec2::NEG_Y,
+            cluster_size.x,
+        ),
+    ];
+    for (first, along, across, length) in sides {
+        let open = |step: i32| {
+            let inside = first + along * step;
+            let outside = inside + across;
+            let inside = Cell::new(inside.x, inside.y);
+            let outside = Cell::new(outside.x, outside.y);
+            (tile_cost(inside).is_some() && tile_cost(outside).is_some())
+                .then_some((inside, outside))
+        };
+        let mut step = 0;
+        while step < length {
+            if open(step).is_none() {
+                step += 1;
+                continue;
+            }
+            let run_start = step;
+            while step < length && open(step).is_some() {
+                step += 1;
+            }
+            let run_end = step - 1;
+            let transitions = if run_end - run_start + 1 >= LONG_ENTRANCE {
+                vec![run_start, run_end]
+            } else {
+                vec![run_start + (run_end - run_start) / 2]
+            };
+            for transition in transitions {
+                let Some((inside, outside)) = open(transition) else {
+                    continue;
+                };
+                let Some(outside_cost) = tile_cost(outside) else {
+                    continue;
+                };
+                graph
+                    .edges
+                    .entry(inside)
+                    .or_default()
+                    .push((outside, outside_cost));
+            }
+        }
+    }
+
+    let nodes: Vec<Cell> = graph.edges.keys().cloned().collect();
+    for node in nodes.iter() {
+        let distances = dijkstra(*node, (min, max), tile_cost, false);
+        let intra_edges: Vec<(Cell, u32)> = nodes
+            .iter()
+            .filter(|other| *other != node)
+            .filter_map(|other| distances.get(other).map(|distance| (*other, *distance)))
+            .collect();
+        if let Some(edges) = graph.edges.get_mut(node) {
+            edges.extend(intra_edges);
+        }
+    }
+    graph
+}
+
+/// A* search from start to goal only entering cells accepted by `within`
+fn astar(
+    start: Cell,
+    goal: Cell,
+    tile_cost: &impl Fn(Cell) -> Option<u32>,
+    within: impl Fn(Cell) -> bool,
+) -> Option<Path> {
+    let mut open = BinaryHeap::new();
+    let mut best: HashMap<Cell, u32> = HashMap::new();
+    let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+    best.insert(start, 0);
+    open.push(Reverse((manhattan(start, goal), 0u32, (start.x, start.y))));
+
+    while let Some(Reverse((_, distance, (x, y)))) = open.pop() {
+        let cell = Cell::new(x, y);
+        if cell == goal {
+            let mut cells = vec![goal];
+            let mut current = goal;
+            while let Some(previous) = came_from.get(&current) {
+                cells.push(*previous);
+                current = *previous;
+            }
+            cells.reverse();
+            return Some(Path {
+                cells,
+                cost: distance,
+            });
+        }
+        if best.get(&cell).is_some_and(|best| *best < distance) {
+            continue;
+        }
+        for next in neighbours(cell) {
+            if !within(next) {
+                continue;
+            }
+            let Some(step_cost) = tile_cost(next) else {
+                continue;
+            };
+            let next_distance = distance + step_cost;
+            if best.get(&next).is_some_and(|best| *best <= next_distance) {
+                continue;
+            }
+            best.insert(next, next_distance);
+            came_from.insert(next, cell);
+            open.push(Reverse((
+                next_distance + manhattan(next, goal),
+                next_distance,
+                (next.x, next.y),
+            )));
+        }
+    }
+    None
+}
+
+/// Returns the cost of the cheapest path from source to every reachable cell inside the bounds. If `reverse`
+/// is set the costs are of the paths from every cell to source instead
+fn dijkstra(
+    source: Cell,
+    (min, max): (IVec2, IVec2),
+    tile_cost: &impl Fn(Cell) -> Option<u32>,
+    reverse: bool,
+) -> HashMap<Cell, u32> {
+    let mut open = BinaryHeap::new();
+    let mut best: HashMap<Cell, u32> = HashMap::new();
+    best.insert(source, 0);
+    open.push(Reverse((0u32, (source.x, source.y))));
+
+    while let Some(Reverse((distance, (x, y)))) = open.pop() {
+        let cell = Cell::new(x, y);
+        if best.get(&cell).is_some_and(|best| *best < distance) {
+            continue;
+        }
+        let cell_cost = match reverse {
+            true => tile_cost(cell),
+            false => Some(0),
+        };
+        let Some(cell_cost) = cell_cost else {
+            continue;
+        };
+        for next in neighbours(cell) {
+            if next.x < min.x || next.y < min.y || next.x > max.x || next.y > max.y {
+                continue;
+            }
+            let Some(next_cost) = tile_cost(next) else {
+                continue;
+            };
+            let next_distance = distance
+                + match reverse {
+                    true => cell_cost,
+                    false => next_cost,
+                };
+            if best.get(&next).is_some_and(|best| *best <= next_distance) {
+                continue;
+            }
+            best.insert(next, next_distance);
+            open.push(Reverse((next_distance, (next.x, next.y))));
+        }
+    }
+    best
+}
+
+/// Returns the cost of entering the cell, never below one, or `None` if it can't be entered
+pub(crate) fn tile_cost<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    cell: Cell,
+    cost: &impl Fn(Cell, &TileData) -> Option<u32>,
+) -> Option<u32>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let tile_data = tilemap_manager.get_tile_data(cell).ok()?;
+    cost(cell, &tile_data).map(|cost| cost.max(1))
+}
+
+/// Returns the orthogonal neighbours of the cell
+pub(crate) fn neighbours(cell: Cell) -> impl Iterator<Item = Cell> {
+    ORTHOGONAL_NEIGHBOURS
+        .into_iter()
+        .map(move |offset| Cell::new(cell.x + offset.x, cell.y + offset.y))
+}
+
+fn manhattan(a: Cell, b: Cell) -> u32 {
+    a.x.abs_diff(b.x) + a.y.abs_diff(b.y)
+}
+
+fn cluster_of(cell: Cell, cluster_size: IVec2) -> ChunkPos {
+    ChunkPos::new(
+        cell.x.div_euclid(cluster_size.x),
+        cell.y.div_euclid(cluster_size.y),
+    )
+}
+
+fn cluster_bounds(cluster: ChunkPos, cluster_size: IVec2) -> (IVec2, IVec2) {
+    let min = cluster.as_ivec2() * cluster_size;
+    (min, min + cluster_size - IVec2::ONE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dijkstra_map, find_path, HierarchicalPathfinder};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::TileWriteHooks;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bevy::utils::HashMap;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    const FLOOR: u8 = 0;
+    const WALL: u8 = 1;
+
+    fn cost(_: Cell, tile: &u8) -> Option<u32> {
+        (*tile == FLOOR).then_some(1)
+    }
+
+    #[test]
+    fn test_pathfinding() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        // A wall down the middle of the map with a single gap at (5, 10)
+        let mut tiles = vec![vec![FLOOR; 12]; 12];
+        for (y, row) in tiles.iter_mut().enumerate() {
+            if y != 10 {
+                row[5] = WALL;
+            }
+        }
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tiles),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let mut pathfinder = HierarchicalPathfinder::new(MapLayers::Main, UVec2::new(4, 4));
+        commands
+            .entity(map_entity)
+            .insert(TileWriteHooks::<u8>::new().with(pathfinder.hook()));
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let (start, goal) = (Cell::new(0, 0), Cell::new(11, 0));
+
+        let path = find_path(&tilemap_manager, start, goal, cost).unwrap();
+        assert_eq!(path.cost, 31);
+        assert_eq!(path.cells.len(), 32);
+        assert!(path.cells.contains(&Cell::new(5, 10)));
+
+        let hierarchical = pathfinder
+            .find_path_hierarchical(&mut tilemap_manager, start, goal, cost)
+            .unwrap();
+        assert!(hierarchical.cost >= path.cost);
+        let refined = pathfinder
+            .refine_path(&mut tilemap_manager, &hierarchical, cost)
+            .unwrap();
+        assert_eq!(refined.cost, hierarchical.cost);
+        assert_eq!(refined.cells.first(), Some(&start));
+        assert_eq!(refined.cells.last(), Some(&goal));
+        assert_eq!(refined.cells.len() as u32, refined.cost + 1);
+        for pair in refined.cells.windows(2) {
+            assert_eq!(
+                pair[0].x.abs_diff(pair[1].x) + pair[0].y.abs_diff(pair[1].y),
+                1
+            );
+            assert_eq!(tilemap_manager.get_tile_data(pair[1]).unwrap(), FLOOR);
+        }
+
+        // Closing the gap invalidates the cached clusters around it
+        tilemap_manager
+            .sets_tile_data(WALL, Cell::new(5, 10))
+            .unwrap();
+        assert!(find_path(&tilemap_manager, start, goal, cost).is_none());
+        assert!(pathfinder
+            .find_path_hierarchical(&mut tilemap_manager, start, goal, cost)
+            .is_none());
+
+        tilemap_manager
+            .sets_tile_data(FLOOR, Cell::new(5, 3))
+            .unwrap();
+        let hierarchical = pathfinder
+            .find_path_hierarchical(&mut tilemap_manager, start, goal, cost)
+            .unwrap();
+        assert_eq!(hierarchical.cost, 17);
+    }
+
+    #[test]
+    fn test_dijkstra_map() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        // Two routes from (0, 0) to (4, 0): straight through the mud or around the wall. The column of walls
+        // at x = 5 cuts off x = 6, and (6, 2) has no tile data
+        const MUD: u8 = 2;
+        let rows = [
+            [FLOOR, FLOOR, FLOOR, FLOOR, FLOOR, WALL, FLOOR],
+            [FLOOR, WALL, WALL, WALL, FLOOR, WALL, FLOOR],
+            [FLOOR, MUD, MUD, MUD, FLOOR, WALL, FLOOR],
+        ];
+        let mut tiles = HashMap::new();
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                tiles.insert(Cell::new(x as i32, y as i32), *tile);
+            }
+        }
+        tiles.remove(&Cell::new(6, 2));
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_sparse_from_hashmap(7, 3, tiles),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let cost = |_: Cell, tile: &u8| match *tile {
+            FLOOR => Some(1),
+            MUD => Some(5),
+            _ => None,
+        };
+        let start = Cell::new(0, 0);
+        let costs = dijkstra_map(&tilemap_manager, start, cost);
+        assert_eq!(costs.cost(start), Some(0));
+
+        // Walls and the cells only reachable through them are never reached
+        assert_eq!(costs.cost(Cell::new(2, 1)), None);
+        assert_eq!(costs.cost(Cell::new(6, 0)), None);
+        assert!(costs.path_to(Cell::new(6, 0)).is_none());
+        assert!(costs.path_to(Cell::new(7, 0)).is_none());
+        assert_eq!(costs.iter().count(), 12);
+
+        // Going around the wall costs 8 while the mud costs 16
+        let path = costs.path_to(Cell::new(4, 0)).unwrap();
+        assert_eq!(path.cost, 8);
+        assert_eq!(path.cells.len(), 9);
+        assert_eq!(path.cells.first(), Some(&start));
+        assert_eq!(path.cells.last(), Some(&Cell::new(4, 0)));
+        assert!(path.cells.contains(&Cell::new(2, 2)));
+        assert!(!path.cells.contains(&Cell::new(2, 0)));
+        assert_eq!(costs.cost(Cell::new(2, 0)), Some(10));
+
+        // A source without tile data reaches nothing, not even itself
+        let costs = dijkstra_map(&tilemap_manager, Cell::new(6, 2), cost);
+        assert_eq!(costs.cost(Cell::new(6, 2)), None);
+        assert_eq!(costs.iter().count(), 0);
+        assert!(costs.path_to(Cell::new(6, 1)).is_none());
+    }
+}
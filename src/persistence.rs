@@ -0,0 +1,862 @@
+//! Saving whole maps to files and loading them back. Requires the `persistence` feature.
+//!
+//! [`save_tilemap`] writes the [`Tilemap`], its [`MapData`] and every chunk with all of their layers and tile
+//! entity markers to a writer. [`load_tilemap`] reads them back and spawns the map into any world, even a
+//! fresh one, remapping the saved chunk and tile entities through [`MapEntities`]. Tile entities are spawned
+//! empty, components on them are not saved.
+//!
+//! Layers marked transient in the maps [`LayerPersistence`], such as debug overlays or fog of war, are left
+//! out of saves. Use [`save_tilemap_filtered`] to pick the saved layers for a single save instead.
+//!
+//! Saves are either RON or a compact binary encoding, see [`SaveFormat`]. Both start with the
+//! [`PERSISTENCE_VERSION`] they were written with and loading a save from a newer version fails with
+//! [`PersistenceError::UnsupportedVersion`].
+//!
+//! Every chunk is written as a separate blob together with a checksum, so a truncated save or a corrupt chunk
+//! only loses the affected chunks. Lost chunks are filled with the default `TileData` in every layer they had
+//! and listed in the [`LoadReport`] returned by [`load_tilemap`]:
+//!
+//! ```ignore
+//! save_tilemap::<TileData, SquareChunkLayer<TileData>, SquareMapData>(
+//!     world,
+//!     map_entity,
+//!     File::create("world.bstsave")?,
+//!     SaveFormat::Binary,
+//! )?;
+//!
+//! let report = load_tilemap::<TileData, SquareChunkLayer<TileData>, SquareMapData>(
+//!     &mut commands,
+//!     File::open("world.bstsave")?,
+//!     SaveFormat::Binary,
+//! )?;
+//! if !report.is_complete() {
+//!     warn!("Lost {} chunks of the world", report.lost.len());
+//! }
+//! ```
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkLayerType, ChunkPos};
+use crate::map::{LayerPersistence, MapData, MapVersion, Tilemap};
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::math::UVec2;
+use bevy::prelude::{BuildChildren, Commands, Entity, World};
+use bevy::utils::HashMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::io::{Read, Write};
+use std::marker::PhantomData;
+
+/// The magic bytes at the start of every binary save
+pub const PERSISTENCE_MAGIC: [u8; 4] = *b"BSTS";
+
+/// The current version of the save format. Version 1 saves stored the whole map as a single document without
+/// checksums and can still be loaded
+pub const PERSISTENCE_VERSION: u32 = 2;
+
+/// The bits of the main layer of every chunk, which is always saved
+const MAIN_LAYER: u64 = 1;
+
+/// Errors returned when saving or loading a map
+#[derive(thiserror::Error, Debug)]
+pub enum PersistenceError {
+    /// Failed to read or write the save
+    #[error("Failed to access the save: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to serialize the save to RON
+    #[error("Failed to serialize the save: {0}")]
+    RonSerialize(#[from] ron::Error),
+
+    /// Failed to deserialize a RON save
+    #[error("Failed to deserialize the save: {0}")]
+    RonDeserialize(#[from] ron::error::SpannedError),
+
+    /// Failed to serialize a binary save
+    #[error("Failed to serialize the binary save: {0}")]
+    BinarySerialize(#[from] rmp_serde::encode::Error),
+
+    /// Failed to deserialize a binary save
+    #[error("Failed to deserialize the binary save: {0}")]
+    BinaryDeserialize(#[from] rmp_serde::decode::Error),
+
+    /// The data doesn't start with [`PERSISTENCE_MAGIC`] or the header of the save is incomplete
+    #[error("The data is not a map save")]
+    InvalidHeader,
+
+    /// The save was written by a newer version of the format
+    #[error("Unsupported save version {0}")]
+    UnsupportedVersion(u32),
+
+    /// The entity is not a map with the given map and chunk types
+    #[error("Entity {0:?} is not a map with the given types")]
+    MapDoesNotExist(Entity),
+
+    /// A chunk of the map doesn't exist, for example because it is streamed out
+    #[error("The chunk at {0:?} does not exist")]
+    ChunkDoesNotExist(ChunkPos),
+}
+
+/// The encoding used for a save
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SaveFormat {
+    /// A compact MessagePack encoding following [`PERSISTENCE_MAGIC`] and the format version. The header and
+    /// every chunk are prefixed with their length
+    #[default]
+    Binary,
+    /// Human readable RON, useful for debugging and diffing saves. The header and every chunk are written on
+    /// their own line
+    Ron,
+}
+
+/// What [`load_tilemap`] managed to recover from a save
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadReport {
+    /// The entity of the loaded map
+    pub map_entity: Entity,
+    /// The chunks that were loaded intact
+    pub recovered: Vec<ChunkPos>,
+    /// The chunks that were corrupt or missing from the save. They are filled with the default `TileData` in
+    /// every layer they had and lost their tile entities
+    pub lost: Vec<ChunkPos>,
+}
+
+impl LoadReport {
+    /// Returns true if every chunk was loaded intact
+    pub fn is_complete(&self) -> bool {
+        self.lost.is_empty()
+    }
+}
+
+/// The contents of a version 1 save, which stored every chunk inline
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "TilemapSave")]
+struct LegacyTilemapSave<T, M, C> {
+    version: u32,
+    tilemap: T,
+    map: M,
+    chunks: Vec<C>,
+}
+
+/// The header of a save, followed by one blob per chunk in the same order as `chunks`. Generic over the field
+/// types so saving can borrow from the world
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "TilemapSave")]
+struct TilemapSaveHeader<T, M, S> {
+    version: u32,
+    tilemap: T,
+    map: M,
+    chunks: Vec<SavedChunkEntry<S>>,
+}
+
+/// Describes the blob of a saved chunk. Used to validate the blob and to rebuild the chunk if it is lost
+#[derive(Serialize, Deserialize)]
+struct SavedChunkEntry<S> {
+    chunk_pos: ChunkPos,
+    dimensions: UVec2,
+    chunk_settings: S,
+    layers: Vec<u64>,
+    checksum: u64,
+}
+
+/// The entries of a loaded save paired with their chunk, or `None` if the blob of the chunk was lost
+type LoadedChunks<MapChunk, TileData> = Vec<(
+    SavedChunkEntry<<MapChunk as ChunkLayer<TileData>>::ChunkSettings>,
+    Option<Chunk<MapChunk, TileData>>,
+)>;
+
+/// A chunk borrowed from the world with only the saved layers. Serializes exactly like a [`Chunk`]
+#[derive(Serialize)]
+#[serde(rename = "Chunk")]
+struct SavedChunk<'a, MapChunk, ChunkSettings, TileData> {
+    chunk_pos: ChunkPos,
+    data: HashMap<u64, &'a MapChunk>,
+    chunk_settings: &'a ChunkSettings,
+    ph: PhantomData<()>,
+    layer_defaults: HashMap<u64, TileData>,
+}
+
+/// Only the version of a RON save, read before the rest so old saves fail with a clear error
+#[derive(Deserialize)]
+#[serde(rename = "TilemapSave")]
+struct TilemapSaveVersion {
+    version: u32,
+}
+
+/// Saves the map with the given types to the writer, leaving out the layers marked transient in the maps
+/// [`LayerPersistence`]
+pub fn save_tilemap<TileData, MapChunk, Map>(
+    world: &World,
+    map_entity: Entity,
+    writer: impl Write,
+    format: SaveFormat,
+) -> Result<(), PersistenceError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static + Serialize,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default + Serialize,
+    MapChunk::ChunkSettings: Serialize,
+    Map: MapData + Serialize,
+{
+    let layer_persistence = world
+        .get::<LayerPersistence>(map_entity)
+        .cloned()
+        .unwrap_or_default();
+    save_tilemap_filtered::<TileData, MapChunk, Map>(
+        world,
+        map_entity,
+        writer,
+        format,
+        |map_layer| layer_persistence.is_persistent_by_bits(map_layer),
+    )
+}
+
+/// Saves the map with the given types to the writer like [`save_tilemap`], only saving the layers the filter
+/// returns true for instead of the persistent ones. The filter is passed the bits of each layer. The main layer
+/// is always saved
+pub fn save_tilemap_filtered<TileData, MapChunk, Map>(
+    world: &World,
+    map_entity: Entity,
+    mut writer: impl Write,
+    format: SaveFormat,
+    filter: impl Fn(u64) -> bool,
+) -> Result<(), PersistenceError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static + Serialize,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default + Serialize,
+    MapChunk::ChunkSettings: Serialize,
+    Map: MapData + Serialize,
+{
+    let map_entity_ref = world
+        .get_entity(map_entity)
+        .ok_or(PersistenceError::MapDoesNotExist(map_entity))?;
+    let (Some(tilemap), Some(map)) = (map_entity_ref.get::<Tilemap>(), map_entity_ref.get::<Map>())
+    else {
+        return Err(PersistenceError::MapDoesNotExist(map_entity));
+    };
+
+    let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+    let mut entries = vec![];
+    let mut blobs = vec![];
+    for chunk_pos in ChunkPos::iter_rect(
+        ChunkPos::new(0, 0),
+        ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+    ) {
+        let chunk = tilemap
+            .get_chunk(chunk_pos)
+            .and_then(|chunk_entity| world.get::<Chunk<MapChunk, TileData>>(chunk_entity))
+            .ok_or(PersistenceError::ChunkDoesNotExist(chunk_pos))?;
+        let data: HashMap<u64, &MapChunk> = chunk
+            .data
+            .iter()
+            .filter(|(map_layer, _)| **map_layer == MAIN_LAYER || filter(**map_layer))
+            .map(|(map_layer, layer)| (*map_layer, layer))
+            .collect();
+        let saved_chunk = SavedChunk {
+            chunk_pos: chunk.chunk_pos,
+            layer_defaults: data
+                .keys()
+                .filter_map(|map_layer| Some((*map_layer, chunk.layer_default(*map_layer)?)))
+                .collect(),
+            data,
+            chunk_settings: &chunk.chunk_settings,
+            ph: PhantomData,
+        };
+        let blob = match format {
+            SaveFormat::Binary => rmp_serde::to_vec(&saved_chunk)?,
+            SaveFormat::Ron => ron::to_string(&saved_chunk)?.into_bytes(),
+        };
+        let mut layers: Vec<u64> = saved_chunk.data.keys().copied().collect();
+        layers.sort_unstable();
+        entries.push(SavedChunkEntry {
+            chunk_pos,
+            dimensions: chunk.get_chunk_dimensions(),
+            chunk_settings: &chunk.chunk_settings,
+            layers,
+            checksum: checksum(&blob),
+        });
+        blobs.push(blob);
+    }
+
+    let header = TilemapSaveHeader {
+        version: PERSISTENCE_VERSION,
+        tilemap,
+        map,
+        chunks: entries,
+    };
+    match format {
+        SaveFormat::Binary => {
+            writer.write_all(&PERSISTENCE_MAGIC)?;
+            writer.write_all(&PERSISTENCE_VERSION.to_le_bytes())?;
+            write_frame(&mut writer, &rmp_serde::to_vec(&header)?)?;
+            for blob in blobs {
+                write_frame(&mut writer, &blob)?;
+            }
+        }
+        SaveFormat::Ron => {
+            writeln!(writer, "{}", ron::to_string(&header)?)?;
+            for blob in blobs {
+                writer.write_all(&blob)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Loads a map saved with [`save_tilemap`] from the reader and spawns it, returning a [`LoadReport`] with the
+/// new map entity.
+///
+/// The chunks are spawned as children of the map and every saved tile entity is replaced with a newly spawned
+/// empty entity. Chunks that are corrupt or missing because the save is truncated are rebuilt with default
+/// data, only a save without a readable header fails to load.
+pub fn load_tilemap<TileData, MapChunk, Map>(
+    commands: &mut Commands,
+    mut reader: impl Read,
+    format: SaveFormat,
+) -> Result<LoadReport, PersistenceError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapChunk::ChunkSettings: DeserializeOwned,
+    Map: MapData + DeserializeOwned,
+    Chunk<MapChunk, TileData>: DeserializeOwned,
+{
+    let mut contents = vec![];
+    reader.read_to_end(&mut contents)?;
+
+    let (tilemap, map, chunks) = match format {
+        SaveFormat::Binary => {
+            if contents.len() < 8 || contents[..4] != PERSISTENCE_MAGIC {
+                return Err(PersistenceError::InvalidHeader);
+            }
+            let version = u32::from_le_bytes([contents[4], contents[5], contents[6], contents[7]]);
+            check_version(version)?;
+            let mut body = &contents[8..];
+            if version == 1 {
+                let save: LegacyTilemapSave<Tilemap, Map, Chunk<MapChunk, TileData>> =
+                    rmp_serde::from_slice(body)?;
+                (save.tilemap, save.map, legacy_chunks(save.chunks))
+            } else {
+                let header: TilemapSaveHeader<Tilemap, Map, MapChunk::ChunkSettings> =
+                    rmp_serde::from_slice(
+                        next_frame(&mut body).ok_or(PersistenceError::InvalidHeader)?,
+                    )?;
+                let chunks = header
+                    .chunks
+                    .into_iter()
+                    .map(|entry| {
+                        let chunk = next_frame(&mut body)
+                            .filter(|blob| checksum(blob) == entry.checksum)
+                            .and_then(|blob| rmp_serde::from_slice(blob).ok());
+                        (entry, chunk)
+                    })
+                    .collect();
+                (header.tilemap, header.map, chunks)
+            }
+        }
+        SaveFormat::Ron => {
+            let mut lines = contents.split(|byte| *byte == b'\n');
+            let header_line = lines
+                .next()
+                .and_then(|line| std::str::from_utf8(line).ok())
+                .unwrap_or_default();
+            match ron::from_str::<TilemapSaveVersion>(header_line) {
+                Ok(header) if header.version > 1 => {
+                    check_version(header.version)?;
+                    let header: TilemapSaveHeader<Tilemap, Map, MapChunk::ChunkSettings> =
+                        ron::from_str(header_line)?;
+                    let chunks = header
+                        .chunks
+                        .into_iter()
+                        .map(|entry| {
+                            let chunk = lines
+                                .next()
+                                .filter(|blob| checksum(blob) == entry.checksum)
+                                .and_then(|blob| std::str::from_utf8(blob).ok())
+                                .and_then(|blob| ron::from_str(blob).ok());
+                            (entry, chunk)
+                        })
+                        .collect();
+                    (header.tilemap, header.map, chunks)
+                }
+                // Version 1 saves are a single pretty printed document
+                _ => {
+                    let ron = String::from_utf8_lossy(&contents);
+                    check_version(ron::from_str::<TilemapSaveVersion>(&ron)?.version)?;
+                    let save: LegacyTilemapSave<Tilemap, Map, Chunk<MapChunk, TileData>> =
+                        ron::from_str(&ron)?;
+                    (save.tilemap, save.map, legacy_chunks(save.chunks))
+                }
+            }
+        }
+    };
+
+    spawn_loaded_tilemap(commands, tilemap, map, chunks)
+}
+
+/// Spawns the loaded map, rebuilding every lost chunk from its entry
+fn spawn_loaded_tilemap<TileData, MapChunk, Map>(
+    commands: &mut Commands,
+    mut tilemap: Tilemap,
+    map: Map,
+    chunks: LoadedChunks<MapChunk, TileData>,
+) -> Result<LoadReport, PersistenceError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let mut entity_mapper = SpawningEntityMapper {
+        commands,
+        entities: HashMap::default(),
+    };
+    tilemap.map_entities(&mut entity_mapper);
+
+    let mut recovered = vec![];
+    let mut lost = vec![];
+    let mut loaded_chunks = vec![];
+    for (entry, chunk) in chunks {
+        let chunk = match chunk {
+            Some(mut chunk) => {
+                chunk.map_entities(&mut entity_mapper);
+                recovered.push(entry.chunk_pos);
+                chunk
+            }
+            None => {
+                lost.push(entry.chunk_pos);
+                default_chunk(entry)
+            }
+        };
+        loaded_chunks.push(chunk);
+    }
+
+    let mut chunk_entities = vec![];
+    for chunk in loaded_chunks {
+        let chunk_entity = tilemap
+            .get_chunk(chunk.chunk_pos)
+            .ok_or(PersistenceError::ChunkDoesNotExist(chunk.chunk_pos))?;
+        commands.entity(chunk_entity).insert(chunk);
+        chunk_entities.push(chunk_entity);
+    }
+
+    let mut map_commands = commands.spawn((tilemap, map, MapVersion::default()));
+    map_commands.push_children(&chunk_entities);
+    Ok(LoadReport {
+        map_entity: map_commands.id(),
+        recovered,
+        lost,
+    })
+}
+
+/// Pairs the inline chunks of a version 1 save with entries. Version 1 saves have no checksums, so every
+/// chunk counts as intact
+fn legacy_chunks<TileData, MapChunk>(
+    chunks: Vec<Chunk<MapChunk, TileData>>,
+) -> LoadedChunks<MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let entry = SavedChunkEntry {
+                chunk_pos: chunk.chunk_pos,
+                dimensions: chunk.get_chunk_dimensions(),
+                chunk_settings: chunk.chunk_settings,
+                layers: chunk.layers().collect(),
+                checksum: 0,
+            };
+            (entry, Some(chunk))
+        })
+        .collect()
+}
+
+/// Rebuilds a lost chunk with the default `TileData` in every layer it had
+fn default_chunk<TileData, MapChunk>(
+    entry: SavedChunkEntry<MapChunk::ChunkSettings>,
+) -> Chunk<MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    let default_layer = || {
+        ChunkLayerType::Dense(vec![
+            vec![TileData::default(); entry.dimensions.x as usize];
+            entry.dimensions.y as usize
+        ])
+    };
+    let mut chunk = Chunk::new(
+        entry.chunk_pos,
+        entry.dimensions,
+        default_layer(),
+        entry.chunk_settings,
+    );
+    for map_layer in entry.layers {
+        if map_layer != MAIN_LAYER {
+            chunk.add_layer(map_layer, default_layer());
+        }
+    }
+    chunk
+}
+
+/// Writes the data prefixed with its length
+fn write_frame(writer: &mut impl Write, data: &[u8]) -> Result<(), PersistenceError> {
+    writer.write_all(&(data.len() as u32).to_le_bytes())?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// Reads the next length prefixed frame. Returns `None` once the data ends or the frame is truncated
+fn next_frame<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let length = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let end = length.checked_add(4)?;
+    let frame = data.get(4..end)?;
+    *data = &data[end..];
+    Some(frame)
+}
+
+/// FNV-1a hash used to detect corrupt chunk blobs
+fn checksum(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns an error if the save was written by a newer version of the format
+fn check_version(version: u32) -> Result<(), PersistenceError> {
+    match version > PERSISTENCE_VERSION {
+        true => Err(PersistenceError::UnsupportedVersion(version)),
+        false => Ok(()),
+    }
+}
+
+/// Maps every saved entity to a newly spawned entity, spawning each one the first time it is seen
+struct SpawningEntityMapper<'a, 'w, 's> {
+    commands: &'a mut Commands<'w, 's>,
+    entities: HashMap<Entity, Entity>,
+}
+
+impl EntityMapper for SpawningEntityMapper<'_, '_, '_> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        *self
+            .entities
+            .entry(entity)
+            .or_insert_with(|| self.commands.spawn_empty().id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_tilemap, next_frame, save_tilemap, save_tilemap_filtered, LegacyTilemapSave,
+        LoadReport, PersistenceError, SaveFormat, PERSISTENCE_MAGIC,
+    };
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::{Chunk, ChunkPos};
+    use crate::map::Tilemap;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Entity, World};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Fog,
+    }
+
+    fn spawn_map(world: &mut World) -> (Entity, Entity) {
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(world);
+        let (mut commands, _) = system_state.get_mut(world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 5]; 5]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager
+            .sets_tile_data(7, Cell::new(3, 4))
+            .expect("cell is in the map");
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(2, 1))
+            .expect("cell is in the map");
+        system_state.apply(world);
+        (map_entity, tile_entity)
+    }
+
+    #[test]
+    fn test_save_and_load_tilemap() {
+        let mut world = World::new();
+        let (map_entity, tile_entity) = spawn_map(&mut world);
+
+        for format in [SaveFormat::Binary, SaveFormat::Ron] {
+            let mut save = vec![];
+            save_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &world, map_entity, &mut save, format,
+            )
+            .expect("map is saved");
+
+            let mut loaded_world = World::new();
+            // Occupy the saved entity ids so the loaded entities have to be remapped
+            for _ in 0..40 {
+                loaded_world.spawn_empty();
+            }
+            let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+                SystemState::new(&mut loaded_world);
+            let (mut commands, _) = system_state.get_mut(&mut loaded_world);
+            let loaded_map = load_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &mut commands,
+                save.as_slice(),
+                format,
+            )
+            .expect("map is loaded");
+            system_state.apply(&mut loaded_world);
+            assert!(loaded_map.is_complete());
+            assert_eq!(loaded_map.recovered.len(), 9);
+
+            let (_, mut tilemap_manager) = system_state.get_mut(&mut loaded_world);
+            tilemap_manager.set_tilemap_entity(loaded_map.map_entity);
+            assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(5, 5));
+            assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 4)).unwrap(), 7);
+            assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 0)).unwrap(), 1);
+            let loaded_tile_entity = tilemap_manager
+                .get_tile_entity(Cell::new(2, 1))
+                .expect("the tile entity marker is loaded");
+            assert_ne!(loaded_tile_entity, tile_entity);
+            assert!(loaded_world.get_entity(loaded_tile_entity).is_some());
+        }
+    }
+
+    #[test]
+    fn test_load_newer_version() {
+        let mut world = World::new();
+        let (map_entity, _) = spawn_map(&mut world);
+        let mut save = vec![];
+        save_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+            &world,
+            map_entity,
+            &mut save,
+            SaveFormat::Binary,
+        )
+        .expect("map is saved");
+        save[4..8].copy_from_slice(&3u32.to_le_bytes());
+
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut world);
+        let mut commands = system_state.get_mut(&mut world);
+        assert!(matches!(
+            load_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &mut commands,
+                save.as_slice(),
+                SaveFormat::Binary,
+            ),
+            Err(PersistenceError::UnsupportedVersion(3))
+        ));
+        assert!(matches!(
+            load_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &mut commands,
+                &b"BSTM\x01\x00\x00\x00"[..],
+                SaveFormat::Binary,
+            ),
+            Err(PersistenceError::InvalidHeader)
+        ));
+        assert_eq!(&save[..4], &PERSISTENCE_MAGIC);
+    }
+
+    #[test]
+    fn test_save_without_transient_layers() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        tilemap_builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![9u8; 4]; 4]),
+            MapLayers::Fog,
+        );
+        tilemap_builder.set_layer_persistent(MapLayers::Fog, false);
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let load = |save: &[u8], format: SaveFormat| {
+            let mut loaded_world = World::new();
+            let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+                SystemState::new(&mut loaded_world);
+            let (mut commands, _) = system_state.get_mut(&mut loaded_world);
+            let loaded_map = load_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &mut commands,
+                save,
+                format,
+            )
+            .expect("map is loaded");
+            system_state.apply(&mut loaded_world);
+
+            let (_, mut tilemap_manager) = system_state.get_mut(&mut loaded_world);
+            tilemap_manager.set_tilemap_entity(loaded_map.map_entity);
+            assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 1);
+            tilemap_manager.set_layer(MapLayers::Fog);
+            tilemap_manager.get_tile_data(Cell::new(3, 3))
+        };
+
+        for format in [SaveFormat::Binary, SaveFormat::Ron] {
+            let mut save = vec![];
+            save_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &world, map_entity, &mut save, format,
+            )
+            .expect("map is saved");
+            assert!(matches!(
+                load(&save, format),
+                Err(TilemapManagerError::LayerDoesNotExist)
+            ));
+
+            // Overriding the filter saves the transient layer as well
+            let mut save = vec![];
+            save_tilemap_filtered::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &world,
+                map_entity,
+                &mut save,
+                format,
+                |_| true,
+            )
+            .expect("map is saved");
+            assert_eq!(load(&save, format).unwrap(), 9);
+        }
+    }
+
+    /// Loads the save into a new world and returns the report and the tile data of the given cells
+    fn load_cells(save: &[u8], format: SaveFormat, cells: &[Cell]) -> (LoadReport, Vec<u8>) {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let report =
+            load_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(&mut commands, save, format)
+                .expect("the header is intact");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(report.map_entity);
+        let tile_data = cells
+            .iter()
+            .map(|cell| tilemap_manager.get_tile_data(*cell).unwrap())
+            .collect();
+        (report, tile_data)
+    }
+
+    #[test]
+    fn test_load_corrupt_and_truncated_saves() {
+        let mut world = World::new();
+        let (map_entity, _) = spawn_map(&mut world);
+        let cells = [Cell::new(0, 0), Cell::new(3, 4), Cell::new(4, 4)];
+
+        let mut save = vec![];
+        save_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+            &world,
+            map_entity,
+            &mut save,
+            SaveFormat::Binary,
+        )
+        .expect("map is saved");
+        // Flip a byte in the blob of the first chunk
+        let mut body = &save[8..];
+        next_frame(&mut body).expect("the header is intact");
+        let first_chunk_start = save.len() - body.len();
+        let first_chunk_end = first_chunk_start + 4 + next_frame(&mut body).unwrap().len();
+        let mut corrupt = save.clone();
+        corrupt[first_chunk_end - 1] ^= 0xff;
+        let (report, tile_data) = load_cells(&corrupt, SaveFormat::Binary, &cells);
+        assert_eq!(report.lost, vec![ChunkPos::new(0, 0)]);
+        assert_eq!(report.recovered.len(), 8);
+        assert_eq!(tile_data, vec![0, 7, 1]);
+
+        // Cutting off the end of the save loses the last chunk
+        let (report, tile_data) = load_cells(&save[..save.len() - 3], SaveFormat::Binary, &cells);
+        assert_eq!(report.lost, vec![ChunkPos::new(2, 2)]);
+        assert_eq!(tile_data, vec![1, 7, 0]);
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut world);
+        let mut commands = system_state.get_mut(&mut world);
+        assert!(matches!(
+            load_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+                &mut commands,
+                &save[..12],
+                SaveFormat::Binary,
+            ),
+            Err(PersistenceError::InvalidHeader)
+        ));
+
+        let mut save = vec![];
+        save_tilemap::<u8, SquareChunkLayer<u8>, SquareMapData>(
+            &world,
+            map_entity,
+            &mut save,
+            SaveFormat::Ron,
+        )
+        .expect("map is saved");
+        let mut lines: Vec<String> = String::from_utf8(save)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect();
+        // The chunk at (1, 2) holds the cell (3, 4)
+        lines[8] = lines[8].replace('7', "8");
+        let (report, tile_data) = load_cells(lines.join("\n").as_bytes(), SaveFormat::Ron, &cells);
+        assert_eq!(report.lost, vec![ChunkPos::new(1, 2)]);
+        assert_eq!(tile_data, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn test_load_version_1_save() {
+        let mut world = World::new();
+        let (map_entity, _) = spawn_map(&mut world);
+        let tilemap = world.get::<Tilemap>(map_entity).unwrap();
+        let chunks: Vec<&Chunk<SquareChunkLayer<u8>, u8>> = world
+            .iter_entities()
+            .filter_map(|entity| entity.get::<Chunk<SquareChunkLayer<u8>, u8>>())
+            .collect();
+        let legacy = LegacyTilemapSave {
+            version: 1,
+            tilemap,
+            map: world.get::<SquareMapData>(map_entity).unwrap(),
+            chunks,
+        };
+
+        let mut binary = PERSISTENCE_MAGIC.to_vec();
+        binary.extend(1u32.to_le_bytes());
+        rmp_serde::encode::write(&mut binary, &legacy).unwrap();
+        let ron = ron::ser::to_string_pretty(&legacy, ron::ser::PrettyConfig::default()).unwrap();
+        for (save, format) in [
+            (binary, SaveFormat::Binary),
+            (ron.into_bytes(), SaveFormat::Ron),
+        ] {
+            let (report, tile_data) = load_cells(&save, format, &[Cell::new(3, 4)]);
+            assert!(report.is_complete());
+            assert_eq!(tile_data, vec![7]);
+        }
+    }
+}
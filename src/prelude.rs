@@ -0,0 +1,50 @@
+//! The core types needed to build and access tilemaps.
+//!
+//! ```
+//! use bevy_sparse_tilemap::prelude::*;
+//! ```
+//!
+//! # Stability
+//!
+//! Everything exported here is covered by semver. Items are never renamed or removed in a single release.
+//! A renamed item is kept under its old name as a deprecated alias for at least one minor release before it
+//! is removed, so code written against the prelude keeps compiling across an upgrade and the deprecation
+//! warnings point at the new names.
+//!
+//! Subsystems that are still changing between releases live behind the `unstable` feature and are not
+//! exported here.
+
+pub use crate::map::chunk::{Chunk, ChunkCell, ChunkLayer, ChunkPos};
+pub use crate::map::{Adjacency, MapData, MapLayer, Tilemap, TilemapSettings};
+pub use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+pub use crate::tilemap_builder::TilemapBuilder;
+pub use crate::tilemap_manager::{ActiveTilemap, TilemapManager, TilemapManagerError};
+pub use bst_map_layer_derive::MapLayer;
+pub use lettuces::cell::Cell;
+
+#[cfg(feature = "hex")]
+pub use crate::hex::{
+    map_chunk_layer::{HexChunkLayer, HexagonChunkSettings},
+    map_data::HexMapData,
+    HexTilemapBuilder, HexTilemapManager,
+};
+#[cfg(feature = "iso")]
+pub use crate::iso::{
+    map_chunk_layer::{IsoChunkLayer, IsoChunkSettings},
+    map_data::{IsoLayout, IsoMapData},
+    IsoTilemapBuilder, IsoTilemapManager,
+};
+#[cfg(feature = "square")]
+pub use crate::square::{
+    map_chunk_layer::{SquareChunkLayer, SquareChunkSettings},
+    map_data::SquareMapData,
+    SquareTilemapBuilder, SquareTilemapManager,
+};
+
+/// The position of a tile on the map
+#[deprecated(since = "0.2.5", note = "renamed to `Cell`")]
+pub type TilePos = Cell;
+
+/// The settings a [`ChunkLayer`] uses to convert between map and chunk cells
+#[deprecated(since = "0.2.5", note = "renamed to `ChunkLayer::ChunkSettings`")]
+pub type ConversionSettings<MapChunk, TileData> = <MapChunk as ChunkLayer<TileData>>::ChunkSettings;
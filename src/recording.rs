@@ -0,0 +1,361 @@
+//! Capture and replay of tile edit streams. Requires the `recording` feature.
+//!
+//! An [`EditRecorder`] is registered as a [`TileWriteHook`] on a map and logs every write made through the
+//! [`TilemapManager`] together with the tick it happened on. The resulting [`EditRecording`] can be saved to
+//! a compact `.ron` file and later replayed onto a fresh copy of the map, which makes desyncs and bugs in
+//! map mutating systems reproducible.
+//!
+//! ```ignore
+//! let recorder = EditRecorder::<TileData>::default();
+//! commands.entity(map).insert(TileWriteHooks::new().with(recorder.hook()));
+//! // ... later
+//! recorder.recording().save("desync.ron")?;
+//! ```
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer, TileWrite, TileWriteHook};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::app::{App, First, Plugin};
+use bevy::prelude::{Res, Resource};
+use lettuces::cell::Cell;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Errors returned when saving or loading an [`EditRecording`]
+#[derive(thiserror::Error, Debug)]
+pub enum EditRecordingError {
+    /// Failed to read or write the recording file
+    #[error("Failed to access the recording file: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to serialize the recording
+    #[error("Failed to serialize the recording: {0}")]
+    Serialize(#[from] ron::Error),
+
+    /// Failed to deserialize the recording file
+    #[error("Failed to deserialize the recording file: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+}
+
+/// A single recorded tile write
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordedEdit<TileData> {
+    /// The tick the write happened on
+    pub tick: u64,
+    /// The cell that was written
+    pub cell: Cell,
+    /// The bits of the [`MapLayer`] that was written
+    pub map_layer: u64,
+    /// The tile data that was written
+    pub value: TileData,
+}
+
+/// An ordered list of recorded tile writes
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EditRecording<TileData> {
+    /// The recorded writes in the order they happened
+    pub edits: Vec<RecordedEdit<TileData>>,
+}
+
+impl<TileData> Default for EditRecording<TileData> {
+    fn default() -> Self {
+        Self { edits: vec![] }
+    }
+}
+
+impl<TileData> EditRecording<TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+{
+    /// Returns the recorded writes that happened on the given tick
+    pub fn edits_for_tick(&self, tick: u64) -> impl Iterator<Item = &RecordedEdit<TileData>> {
+        self.edits.iter().filter(move |edit| edit.tick == tick)
+    }
+
+    /// Applies every recorded write with a tick less than or equal to `until_tick` to the map the given
+    /// [`TilemapManager`] is set to. Pass `u64::MAX` to replay everything.
+    ///
+    /// `layer_from_bits` converts the recorded layer bits back into a `MapLayers`. Writes to layers it returns
+    /// `None` for are skipped. The managers selected layer is restored afterwards.
+    pub fn replay<MapLayers, MapChunk, Map>(
+        &self,
+        tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+        until_tick: u64,
+        layer_from_bits: impl Fn(u64) -> Option<MapLayers>,
+    ) -> Result<(), TilemapManagerError>
+    where
+        MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let original_layer = tilemap_manager.layer();
+        let mut result = Ok(());
+        for edit in self.edits.iter().filter(|edit| edit.tick <= until_tick) {
+            let Some(map_layer) = layer_from_bits(edit.map_layer) else {
+                continue;
+            };
+            tilemap_manager.set_layer(map_layer);
+            result = tilemap_manager.sets_tile_data(edit.value, edit.cell);
+            if result.is_err() {
+                break;
+            }
+        }
+        tilemap_manager.set_layer(original_layer);
+        result
+    }
+}
+
+impl<TileData> EditRecording<TileData>
+where
+    TileData: Serialize + DeserializeOwned,
+{
+    /// Serializes the recording into a compact ron string
+    pub fn to_ron_string(&self) -> Result<String, EditRecordingError> {
+        Ok(ron::to_string(self)?)
+    }
+
+    /// Deserializes a recording from a ron string
+    pub fn from_ron_str(recording: &str) -> Result<Self, EditRecordingError> {
+        Ok(ron::from_str(recording)?)
+    }
+
+    /// Saves the recording to the given path
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EditRecordingError> {
+        std::fs::write(path, self.to_ron_string()?)?;
+        Ok(())
+    }
+
+    /// Loads a recording from the given path
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, EditRecordingError> {
+        Self::from_ron_str(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Records every tile write made to the maps it is registered on.
+///
+/// The recorder is a cheap handle and clones share the same recording. Register [`EditRecorder::hook`] in
+/// a maps [`TileWriteHooks`](crate::map::TileWriteHooks) and advance the tick with
+/// [`EditRecorder::advance_tick`] or by adding the [`EditRecorderPlugin`].
+#[derive(Resource)]
+pub struct EditRecorder<TileData> {
+    recording: Arc<Mutex<EditRecording<TileData>>>,
+    tick: Arc<AtomicU64>,
+}
+
+impl<TileData> Clone for EditRecorder<TileData> {
+    fn clone(&self) -> Self {
+        Self {
+            recording: self.recording.clone(),
+            tick: self.tick.clone(),
+        }
+    }
+}
+
+impl<TileData> Default for EditRecorder<TileData> {
+    fn default() -> Self {
+        Self {
+            recording: Arc::new(Mutex::new(EditRecording::default())),
+            tick: Arc::new(AtomicU64::new(0)),
+        }
+    }
+}
+
+impl<TileData> EditRecorder<TileData>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    /// Returns a [`TileWriteHook`] that records into this recorder
+    pub fn hook(&self) -> impl TileWriteHook<TileData> {
+        let recorder = self.clone();
+        move |write: &TileWrite<TileData>| {
+            let tick = recorder.tick();
+            recorder.lock().edits.push(RecordedEdit {
+                tick,
+                cell: write.cell,
+                map_layer: write.map_layer,
+                value: write.new,
+            });
+        }
+    }
+
+    /// Returns the current tick
+    pub fn tick(&self) -> u64 {
+        self.tick.load(Ordering::Relaxed)
+    }
+
+    /// Advances the current tick by one
+    pub fn advance_tick(&self) {
+        self.tick.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns a copy of everything recorded so far
+    pub fn recording(&self) -> EditRecording<TileData> {
+        self.lock().clone()
+    }
+
+    /// Returns everything recorded so far and clears the recorder
+    pub fn take_recording(&self) -> EditRecording<TileData> {
+        std::mem::take(&mut *self.lock())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, EditRecording<TileData>> {
+        // A panic while recording leaves the recording intact so keep using it
+        self.recording
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Inserts an [`EditRecorder`] resource and advances its tick at the start of every update
+pub struct EditRecorderPlugin<TileData> {
+    ph: PhantomData<TileData>,
+}
+
+impl<TileData> Default for EditRecorderPlugin<TileData> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData> Plugin for EditRecorderPlugin<TileData>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EditRecorder<TileData>>()
+            .add_systems(First, advance_edit_recorder_tick::<TileData>);
+    }
+}
+
+/// Advances the tick of the [`EditRecorder`] resource
+pub fn advance_edit_recorder_tick<TileData>(recorder: Res<EditRecorder<TileData>>)
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+{
+    recorder.advance_tick();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditRecorder, EditRecording};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkLayer;
+    use crate::map::{MapData, TileWriteHooks};
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManager;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Entity, World};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Secondary,
+    }
+
+    fn spawn_map(commands: &mut Commands) -> Entity {
+        let mut builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            MapLayers::Secondary,
+        );
+        builder
+            .spawn_tilemap(commands)
+            .expect("map has a main layer")
+    }
+
+    fn layer_from_bits(bits: u64) -> Option<MapLayers> {
+        [MapLayers::Main, MapLayers::Secondary]
+            .into_iter()
+            .find(|layer| layer.to_bits() == bits)
+    }
+
+    fn snapshot<MapChunk, Map>(
+        tilemap_manager: &mut TilemapManager<u8, MapLayers, MapChunk, Map>,
+    ) -> Vec<Option<u8>>
+    where
+        MapChunk: ChunkLayer<u8> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let mut tiles = vec![];
+        for layer in [MapLayers::Main, MapLayers::Secondary] {
+            tilemap_manager.set_layer(layer);
+            for y in 0..4 {
+                for x in 0..4 {
+                    tiles.push(tilemap_manager.get_tile_data(Cell::new(x, y)).ok());
+                }
+            }
+        }
+        tiles
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let recorded_map = spawn_map(&mut commands);
+        let fresh_map = spawn_map(&mut commands);
+        let recorder = EditRecorder::<u8>::default();
+        commands
+            .entity(recorded_map)
+            .insert(TileWriteHooks::new().with(recorder.hook()));
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(recorded_map);
+        tilemap_manager
+            .sets_tile_data(1, Cell::new(0, 0))
+            .expect("cell is on the map");
+        recorder.advance_tick();
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        tilemap_manager
+            .sets_tile_data(2, Cell::new(3, 1))
+            .expect("cell is on the map");
+        tilemap_manager.set_layer(MapLayers::Main);
+        tilemap_manager
+            .sets_tile_data(3, Cell::new(0, 0))
+            .expect("cell is on the map");
+        let expected = snapshot(&mut tilemap_manager);
+
+        let recording = recorder.recording();
+        assert_eq!(recording.edits.len(), 3);
+        assert_eq!(recording.edits_for_tick(1).count(), 2);
+        let recording = EditRecording::<u8>::from_ron_str(
+            &recording.to_ron_string().expect("recording serializes"),
+        )
+        .expect("recording deserializes");
+
+        tilemap_manager.set_tilemap_entity(fresh_map);
+        recording
+            .replay(&mut tilemap_manager, 0, layer_from_bits)
+            .expect("replay succeeds");
+        tilemap_manager.set_layer(MapLayers::Main);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).ok(), Some(1));
+
+        recording
+            .replay(&mut tilemap_manager, u64::MAX, layer_from_bits)
+            .expect("replay succeeds");
+        assert_eq!(snapshot(&mut tilemap_manager), expected);
+    }
+}
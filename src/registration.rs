@@ -0,0 +1,147 @@
+//! Helpers that register every type used by a map in the [`AppTypeRegistry`](bevy::ecs::reflect::AppTypeRegistry).
+//!
+//! Chunks and chunk layers are generic over the tile data of the map so their reflected types can't be
+//! registered ahead of time. Call [`register_square_map_types`], [`register_hex_map_types`] or
+//! [`register_iso_map_types`] once for every `TileData` type used by a map to make its components serializable
+//! through reflection, for example in scenes.
+
+use crate::map::chunk::{Chunk, ChunkCell, ChunkPos, Chunks, SparseMap, TileEntities};
+use crate::map::Tilemap;
+use crate::tilemap_manager::ActiveTilemap;
+use bevy::app::App;
+use bevy::math::UVec2;
+use bevy::prelude::Entity;
+use bevy::reflect::{FromReflect, GetTypeRegistration, TypePath};
+use bevy::utils::hashbrown::HashMap;
+use lettuces::cell::Cell;
+use lettuces::storage::grid::Grid;
+use std::hash::Hash;
+
+/// Registers the types shared by every map type
+fn register_common_types<TileData>(app: &mut App)
+where
+    TileData: Hash
+        + Clone
+        + Copy
+        + Sized
+        + Default
+        + Send
+        + Sync
+        + FromReflect
+        + TypePath
+        + GetTypeRegistration,
+{
+    app.register_type::<Tilemap>()
+        .register_type::<Chunks>()
+        .register_type::<ChunkPos>()
+        .register_type::<ChunkCell>()
+        .register_type::<TileEntities>()
+        .register_type::<ActiveTilemap>()
+        .register_type::<Cell>()
+        .register_type::<Entity>()
+        .register_type::<Option<Entity>>()
+        .register_type::<UVec2>()
+        .register_type::<Grid<Entity>>()
+        .register_type::<Grid<TileData>>()
+        .register_type::<Vec<Entity>>()
+        .register_type::<Vec<Option<Entity>>>()
+        .register_type::<Vec<TileData>>()
+        .register_type::<SparseMap<u64, Entity>>()
+        .register_type::<TileData>();
+}
+
+/// Registers every type used by square maps holding `TileData`
+#[cfg(feature = "square")]
+pub fn register_square_map_types<TileData>(app: &mut App)
+where
+    TileData: Hash
+        + Clone
+        + Copy
+        + Sized
+        + Default
+        + Send
+        + Sync
+        + FromReflect
+        + TypePath
+        + GetTypeRegistration,
+{
+    use crate::square::map_chunk_layer::{
+        SquareChunkLayer, SquareChunkLayerData, SquareChunkSettings,
+    };
+    use crate::square::map_data::SquareMapData;
+
+    register_common_types::<TileData>(app);
+    app.register_type::<Chunk<SquareChunkLayer<TileData>, TileData>>()
+        .register_type::<HashMap<u64, SquareChunkLayer<TileData>>>()
+        .register_type::<SquareChunkLayer<TileData>>()
+        .register_type::<SquareChunkLayerData<TileData>>()
+        .register_type::<SquareChunkSettings>()
+        .register_type::<SquareMapData>()
+        .register_type::<SparseMap<u64, TileData>>();
+}
+
+/// Registers every type used by hexagonal maps holding `TileData`, including maps using
+/// [`HexagonalChunksMapData`](crate::hex::hexagonal_chunks::HexagonalChunksMapData)
+#[cfg(feature = "hex")]
+pub fn register_hex_map_types<TileData>(app: &mut App)
+where
+    TileData: Hash
+        + Clone
+        + Copy
+        + Sized
+        + Default
+        + Send
+        + Sync
+        + FromReflect
+        + TypePath
+        + GetTypeRegistration,
+{
+    use crate::hex::hexagonal_chunks::{HexChunkShape, HexagonalChunksMapData};
+    use crate::hex::map_chunk_layer::{HexChunkLayer, HexChunkLayerData, HexagonChunkSettings};
+    use crate::hex::map_data::HexMapData;
+    use bevy::math::IVec2;
+    use lettuces::storage::hex::HexRectangleStorage;
+    use lettuces::HexOrientation;
+
+    register_common_types::<TileData>(app);
+    app.register_type::<Chunk<HexChunkLayer<TileData>, TileData>>()
+        .register_type::<HashMap<u64, HexChunkLayer<TileData>>>()
+        .register_type::<HexChunkLayer<TileData>>()
+        .register_type::<HexChunkLayerData<TileData>>()
+        .register_type::<HexagonChunkSettings>()
+        .register_type::<HexChunkShape>()
+        .register_type::<HexOrientation>()
+        .register_type::<HexMapData>()
+        .register_type::<HexagonalChunksMapData>()
+        .register_type::<HexRectangleStorage<TileData>>()
+        .register_type::<SparseMap<(i32, i32), TileData>>()
+        .register_type::<(i32, i32)>()
+        .register_type::<IVec2>();
+}
+
+/// Registers every type used by isometric maps holding `TileData`
+#[cfg(feature = "iso")]
+pub fn register_iso_map_types<TileData>(app: &mut App)
+where
+    TileData: Hash
+        + Clone
+        + Copy
+        + Sized
+        + Default
+        + Send
+        + Sync
+        + FromReflect
+        + TypePath
+        + GetTypeRegistration,
+{
+    use crate::iso::map_chunk_layer::{IsoChunkLayer, IsoChunkSettings};
+    use crate::iso::map_data::{IsoLayout, IsoMapData};
+
+    register_square_map_types::<TileData>(app);
+    app.register_type::<Chunk<IsoChunkLayer<TileData>, TileData>>()
+        .register_type::<HashMap<u64, IsoChunkLayer<TileData>>>()
+        .register_type::<IsoChunkLayer<TileData>>()
+        .register_type::<IsoChunkSettings>()
+        .register_type::<IsoLayout>()
+        .register_type::<IsoMapData>();
+}
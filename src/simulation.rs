@@ -0,0 +1,212 @@
+//! Restricting expensive simulation to the chunks near players and AI.
+//!
+//! Simulating every tile of a huge map every frame is rarely needed, only the area around the players and
+//! active AI matters. Insert a [`SimAnchor`] on those entities and add the [`SimulationGatingPlugin`]. Chunks
+//! within [`SimAnchor::radius`] chunks of an anchor get a [`SimActive`] marker, which per-tile simulation
+//! systems can filter their chunk queries with:
+//!
+//! ```ignore
+//! fn grow_crops(mut chunks: Query<&mut Chunk<SquareChunkLayer<TileData>, TileData>, With<SimActive>>) {
+//!     // Only chunks near an anchor are visited
+//! }
+//! ```
+//!
+//! Anchors are positioned through their [`GlobalTransform`] and the [`TilemapGeometry`] of the map, chunks of
+//! maps without a geometry never become active. Systems working through a
+//! [`TilemapManager`](crate::tilemap_manager::TilemapManager) can use
+//! [`TilemapManager::chunks_near`](crate::tilemap_manager::TilemapManager::chunks_near) instead.
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
+use crate::map::{MapData, Tilemap, TilemapGeometry};
+use bevy::app::{App, Plugin, PreUpdate};
+use bevy::prelude::{Commands, Component, Entity, GlobalTransform, Has, Query, With};
+use bevy::utils::HashSet;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Marks an entity, such as a player or an AI, whose surroundings are simulated
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimAnchor {
+    /// The map the anchor activates chunks in. Anchors without a map activate chunks in every map
+    pub map: Option<Entity>,
+    /// How many chunks around the chunk the anchor is in are active in every direction
+    pub radius: u32,
+}
+
+impl SimAnchor {
+    /// Creates an anchor activating `radius` chunks around it in every map
+    pub fn new(radius: u32) -> Self {
+        Self { map: None, radius }
+    }
+
+    /// Creates an anchor activating `radius` chunks around it in the given map
+    pub fn for_map(map: Entity, radius: u32) -> Self {
+        Self {
+            map: Some(map),
+            radius,
+        }
+    }
+}
+
+/// Marker kept on every chunk entity near a [`SimAnchor`] by the [`SimulationGatingPlugin`]
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimActive;
+
+/// Plugin that keeps the [`SimActive`] marker on the chunks near [`SimAnchor`]s up to date. Runs in
+/// [`PreUpdate`] so simulation systems in `Update` see the current set of active chunks
+pub struct SimulationGatingPlugin<TileData, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapChunk, Map)>,
+}
+
+impl<TileData, MapChunk, Map> Default for SimulationGatingPlugin<TileData, MapChunk, Map> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk, Map> Plugin for SimulationGatingPlugin<TileData, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PreUpdate,
+            update_sim_active_chunks::<TileData, MapChunk, Map>,
+        );
+    }
+}
+
+/// Inserts [`SimActive`] on the chunks near a [`SimAnchor`] and removes it from every other chunk
+pub fn update_sim_active_chunks<TileData, MapChunk, Map>(
+    mut commands: Commands,
+    anchors: Query<(&SimAnchor, &GlobalTransform)>,
+    maps: Query<(Entity, &Tilemap, &Map, &TilemapGeometry)>,
+    chunks: Query<Has<SimActive>, With<Chunk<MapChunk, TileData>>>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    for (map_entity, tilemap, map, geometry) in maps.iter() {
+        let mut active: HashSet<ChunkPos> = HashSet::default();
+        for (anchor, transform) in anchors.iter() {
+            if anchor.map.is_some_and(|map| map != map_entity) {
+                continue;
+            }
+            let center =
+                map.into_chunk_pos(geometry.world_to_cell(transform.translation().truncate()));
+            active.extend(tilemap.chunks_in_radius(center, anchor.radius));
+        }
+
+        let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+        ) {
+            let Some(chunk_entity) = tilemap.get_chunk(chunk_pos) else {
+                continue;
+            };
+            let Ok(is_active) = chunks.get(chunk_entity) else {
+                continue;
+            };
+            match (active.contains(&chunk_pos), is_active) {
+                (true, false) => {
+                    commands.entity(chunk_entity).insert(SimActive);
+                }
+                (false, true) => {
+                    commands.entity(chunk_entity).remove::<SimActive>();
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SimActive, SimAnchor, SimulationGatingPlugin};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::map::TilemapGeometry;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{UVec2, Vec2, Vec3};
+    use bevy::prelude::{GlobalTransform, With};
+    use bst_map_layer_derive::MapLayer;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_sim_active_chunks() {
+        let mut app = App::new();
+        app.add_plugins(SimulationGatingPlugin::<
+            u8,
+            SquareChunkLayer<u8>,
+            SquareMapData,
+        >::default());
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        commands
+            .entity(map_entity)
+            .insert(TilemapGeometry::new(Vec2::ZERO, Vec2::ONE));
+        let anchor = commands
+            .spawn((
+                SimAnchor::new(1),
+                GlobalTransform::from_translation(Vec3::new(0.5, 0.5, 0.0)),
+            ))
+            .id();
+        system_state.apply(&mut app.world);
+
+        // The anchor sits in chunk (0, 0) of the 4 x 4 chunks
+        app.update();
+        let active_chunks = |app: &mut App| {
+            app.world
+                .query_filtered::<(), With<SimActive>>()
+                .iter(&app.world)
+                .count()
+        };
+        assert_eq!(active_chunks(&mut app), 4);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let near = tilemap_manager
+            .chunks_near([Vec2::new(7.5, 7.5), Vec2::new(7.5, 0.5)], 0)
+            .unwrap();
+        assert_eq!(near.len(), 2);
+        assert!(near.contains(&ChunkPos::new(3, 3)));
+        assert!(near.contains(&ChunkPos::new(3, 0)));
+
+        // Moving the anchor to the middle of the map activates the chunks around it instead
+        *app.world.get_mut::<GlobalTransform>(anchor).unwrap() =
+            GlobalTransform::from_translation(Vec3::new(4.5, 4.5, 0.0));
+        app.update();
+        assert_eq!(active_chunks(&mut app), 9);
+
+        app.world.despawn(anchor);
+        app.update();
+        assert_eq!(active_chunks(&mut app), 0);
+    }
+}
@@ -0,0 +1,377 @@
+use crate::map::chunk::{
+    ChunkCell, ChunkLayer, ChunkLayerType, SerializationStats, TileEntities, TileEntityStorage,
+};
+use crate::map::MapLayer;
+use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+use crate::square::map_data::SquareMapData;
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_builder::TilemapBuilder;
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::math::UVec2;
+use bevy::prelude::{Component, Entity};
+use std::hash::{Hash, Hasher};
+use std::mem::size_of;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A square chunk layer storing the tile data of every cell in a single boxed `[[T; W]; H]` array.
+///
+/// An alternative to [`SquareChunkLayer`] for maps where every chunk has the same size. Every tile lives in
+/// one flat allocation whose size is known at compile time, which removes the indirection of the heap grid
+/// and keeps whole chunk scans through [`Self::tiles`] cache friendly. Select it by using it as the `MapChunk`
+/// of a [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder), see
+/// [`FixedSquareTilemapBuilder`](crate::square::FixedSquareTilemapBuilder).
+///
+/// The layer is always dense, cells missing from sparse layers given to [`ChunkLayer::new`] hold the default
+/// `T`. Chunks at the edge of the map that are smaller than `W` x `H` leave the rest of the array unused.
+///
+/// # Panics
+/// - If a chunk is created with dimensions larger than `W` x `H`
+#[derive(Clone, Component)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct FixedSquareChunkLayer<T, const W: usize, const H: usize>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "fixed_tiles",
+            bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>")
+        )
+    )]
+    tiles: Box<[[T; W]; H]>,
+    dimensions: UVec2,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "TileEntities::skip_serializing")
+    )]
+    tile_entities: TileEntities,
+}
+
+impl<T, const W: usize, const H: usize> Default for FixedSquareChunkLayer<T, W, H>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    fn default() -> Self {
+        Self {
+            tiles: new_tiles(T::default()),
+            dimensions: UVec2::ZERO,
+            tile_entities: Default::default(),
+        }
+    }
+}
+
+impl<T, const W: usize, const H: usize> MapEntities for FixedSquareChunkLayer<T, W, H>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        self.tile_entities.map_entities(entity_mapper);
+    }
+}
+
+impl<T, const W: usize, const H: usize> Hash for FixedSquareChunkLayer<T, W, H>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    fn hash<H2: Hasher>(&self, h: &mut H2) {
+        Hash::hash(&self.tile_entities, h);
+        Hash::hash(&self.tiles, h);
+        Hash::hash(&self.dimensions, h);
+    }
+}
+
+impl<T, const W: usize, const H: usize> ChunkLayer<T> for FixedSquareChunkLayer<T, W, H>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    type ChunkSettings = SquareChunkSettings;
+
+    fn into_chunk_cell(
+        cell: lettuces::cell::Cell,
+        chunk_settings: &Self::ChunkSettings,
+    ) -> ChunkCell {
+        SquareChunkLayer::<T>::into_chunk_cell(cell, chunk_settings)
+    }
+
+    fn new(
+        layer_type: ChunkLayerType<T>,
+        chunk_dimensions: UVec2,
+        _: &Self::ChunkSettings,
+    ) -> Self {
+        // Like the grid of a `SquareChunkLayer`, dense layers take their dimensions from the data
+        let dimensions = match &layer_type {
+            ChunkLayerType::Dense(dense_data) => UVec2::new(
+                dense_data.first().map_or(0, |row| row.len()) as u32,
+                dense_data.len() as u32,
+            ),
+            ChunkLayerType::Sparse(..) => chunk_dimensions,
+        };
+        assert!(
+            dimensions.x as usize <= W && dimensions.y as usize <= H,
+            "chunk of {dimensions} doesn't fit into a FixedSquareChunkLayer of {W}x{H}"
+        );
+        let mut layer = Self {
+            tiles: new_tiles(T::default()),
+            dimensions,
+            tile_entities: Default::default(),
+        };
+        match layer_type {
+            ChunkLayerType::Dense(dense_data) => {
+                for (y, row) in dense_data.iter().enumerate() {
+                    layer.tiles[y][..row.len()].copy_from_slice(row);
+                }
+            }
+            ChunkLayerType::Sparse(hashmap) => {
+                for (chunk_cell, tile_data) in hashmap {
+                    layer.set_tile_data(chunk_cell, tile_data);
+                }
+            }
+        }
+        layer
+    }
+
+    fn get_chunk_dimensions(&self) -> UVec2 {
+        self.dimensions
+    }
+
+    fn get_tile_data_mut(&mut self, chunk_cell: ChunkCell) -> Option<&mut T> {
+        if !chunk_cell.within(self.dimensions) {
+            return None;
+        }
+        Some(&mut self.tiles[chunk_cell.y() as usize][chunk_cell.x() as usize])
+    }
+
+    fn get_tile_data(&self, chunk_cell: ChunkCell) -> Option<&T> {
+        if !chunk_cell.within(self.dimensions) {
+            return None;
+        }
+        Some(&self.tiles[chunk_cell.y() as usize][chunk_cell.x() as usize])
+    }
+
+    fn set_tile_data(&mut self, chunk_cell: ChunkCell, tile_data: T) {
+        if let Some(tile) = self.get_tile_data_mut(chunk_cell) {
+            *tile = tile_data;
+        }
+    }
+
+    fn get_tile_entity(&self, chunk_cell: ChunkCell) -> Option<Entity> {
+        self.tile_entities.get(chunk_cell)
+    }
+
+    fn set_tile_entity(&mut self, chunk_cell: ChunkCell, entity: Entity) {
+        self.tile_entities.insert(chunk_cell, entity);
+    }
+
+    fn remove_tile_entity(&mut self, chunk_cell: ChunkCell) -> Option<Entity> {
+        self.tile_entities.remove(chunk_cell)
+    }
+
+    fn iter_tile_data(&self) -> Box<dyn Iterator<Item = (ChunkCell, &T)> + '_> {
+        let (columns, rows) = (self.dimensions.x as usize, self.dimensions.y as usize);
+        Box::new(
+            self.tiles[..rows]
+                .iter()
+                .enumerate()
+                .flat_map(move |(y, row)| {
+                    row[..columns]
+                        .iter()
+                        .enumerate()
+                        .map(move |(x, tile_data)| (ChunkCell::new(x as i32, y as i32), tile_data))
+                }),
+        )
+    }
+
+    fn iter_tile_entities(&self) -> Box<dyn Iterator<Item = (ChunkCell, Entity)> + '_> {
+        self.tile_entities.iter()
+    }
+
+    fn set_tile_entity_storage(&mut self, storage: TileEntityStorage) {
+        self.tile_entities.convert(storage, self.dimensions);
+    }
+
+    fn serialization_stats(&self, _is_default: &dyn Fn(&T) -> bool) -> SerializationStats {
+        SerializationStats {
+            skipped_entity_maps: self.tile_entities.skip_serializing() as usize,
+            stored_tiles: W * H,
+            ..SerializationStats::default()
+        }
+    }
+}
+
+impl<T, const W: usize, const H: usize> FixedSquareChunkLayer<T, W, H>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    /// Returns every tile of the layer, row by row. Rows and columns outside of the chunk dimensions hold the
+    /// default `T`
+    pub fn tiles(&self) -> &[[T; W]; H] {
+        &self.tiles
+    }
+
+    /// Returns mutable access to every tile of the layer, row by row. Writes to rows and columns outside of
+    /// the chunk dimensions are ignored by every other method
+    pub fn tiles_mut(&mut self) -> &mut [[T; W]; H] {
+        &mut self.tiles
+    }
+
+    /// Returns the approximate amount of heap memory used by the layer in bytes
+    pub fn heap_size(&self) -> usize {
+        W * H * size_of::<T>() + self.tile_entities.heap_size()
+    }
+}
+
+impl<TileData, MapLayers, const W: usize, const H: usize>
+    TilemapBuilder<TileData, MapLayers, FixedSquareChunkLayer<TileData, W, H>, SquareMapData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+{
+    /// Creates a new builder for a square map whose chunks are `W` x `H` cells and stored in a
+    /// [`FixedSquareChunkLayer`]
+    pub fn new_fixed(layer_data: TilemapLayer<TileData>) -> Self {
+        let max_chunk_size = UVec2::new(W as u32, H as u32);
+        Self::new(
+            layer_data,
+            SquareMapData { max_chunk_size },
+            SquareChunkSettings { max_chunk_size },
+        )
+    }
+}
+
+/// Allocates the tiles of a layer directly on the heap, so large arrays never live on the stack
+fn new_tiles<T: Copy, const W: usize, const H: usize>(tile_data: T) -> Box<[[T; W]; H]> {
+    vec![[tile_data; W]; H]
+        .into_boxed_slice()
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("the vec has H rows"))
+}
+
+/// Serializes the tiles as a flat sequence of `W * H` tiles
+#[cfg(feature = "serde")]
+mod fixed_tiles {
+    use super::new_tiles;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[allow(clippy::borrowed_box)]
+    pub fn serialize<S, T, const W: usize, const H: usize>(
+        tiles: &Box<[[T; W]; H]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        serializer.collect_seq(tiles.iter().flatten())
+    }
+
+    pub fn deserialize<'de, D, T, const W: usize, const H: usize>(
+        deserializer: D,
+    ) -> Result<Box<[[T; W]; H]>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de> + Copy + Default,
+    {
+        let flat = Vec::<T>::deserialize(deserializer)?;
+        if flat.len() != W * H {
+            return Err(D::Error::invalid_length(
+                flat.len(),
+                &format!("{} tiles", W * H).as_str(),
+            ));
+        }
+        let mut tiles = new_tiles(T::default());
+        for (index, tile_data) in flat.into_iter().enumerate() {
+            tiles[index / W][index % W] = tile_data;
+        }
+        Ok(tiles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedSquareChunkLayer;
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::{ChunkCell, ChunkLayer, ChunkLayerType};
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::{FixedSquareTilemapBuilder, FixedSquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bevy::utils::HashMap;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_fixed_chunk_layer() {
+        let mut layer = FixedSquareChunkLayer::<u8, 4, 4>::new(
+            ChunkLayerType::Dense(vec![vec![1, 2, 3], vec![4, 5, 6]]),
+            UVec2::new(3, 2),
+            &SquareChunkSettings::default(),
+        );
+        assert_eq!(layer.get_chunk_dimensions(), UVec2::new(3, 2));
+        assert_eq!(layer.get_tile_data(ChunkCell::new(2, 1)), Some(&6));
+        // Cells outside of the chunk dimensions but inside of the array don't exist
+        assert_eq!(layer.get_tile_data(ChunkCell::new(3, 0)), None);
+        assert_eq!(layer.get_tile_data(ChunkCell::new(0, -1)), None);
+        layer.set_tile_data(ChunkCell::new(0, 0), 9);
+        *layer.get_tile_data_mut(ChunkCell::new(1, 0)).unwrap() += 1;
+        assert_eq!(layer.tiles()[0], [9, 3, 3, 0]);
+        assert_eq!(layer.iter_tile_data().count(), 6);
+
+        let mut hashmap = HashMap::new();
+        hashmap.insert(ChunkCell::new(1, 1), 7u8);
+        let sparse = FixedSquareChunkLayer::<u8, 2, 2>::new(
+            ChunkLayerType::Sparse(hashmap),
+            UVec2::new(2, 2),
+            &SquareChunkSettings::default(),
+        );
+        assert_eq!(sparse.tiles(), &[[0, 0], [0, 7]]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_fixed_chunk_layer_serde() {
+        let layer = FixedSquareChunkLayer::<u8, 2, 2>::new(
+            ChunkLayerType::Dense(vec![vec![1, 2], vec![3, 4]]),
+            UVec2::new(2, 2),
+            &SquareChunkSettings::default(),
+        );
+        let serialized = ron::to_string(&layer).unwrap();
+        let deserialized: FixedSquareChunkLayer<u8, 2, 2> = ron::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.tiles(), layer.tiles());
+        assert!(ron::from_str::<FixedSquareChunkLayer<u8, 3, 3>>(&serialized).is_err());
+    }
+
+    #[test]
+    fn test_fixed_tilemap() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(
+            Commands,
+            FixedSquareTilemapManager<u8, MapLayers, 4, 4>,
+        )> = SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = FixedSquareTilemapBuilder::<u8, MapLayers, 4, 4>::new_fixed(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 10]; 6]),
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(10, 6));
+        tilemap_manager.sets_tile_data(5, Cell::new(9, 5)).unwrap();
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(9, 5)).unwrap(), 5);
+        assert!(tilemap_manager.get_tile_data(Cell::new(10, 5)).is_err());
+    }
+}
@@ -1,10 +1,14 @@
-use crate::map::chunk::{ChunkCell, ChunkLayer, ChunkLayerType};
+use crate::map::chunk::{
+    sparse_map_heap_size, ChunkCell, ChunkLayer, ChunkLayerType, ChunkStoragePool, ChunkTemplate,
+    ChunkTemplates, CompactionReport, LayerStorage, SerializationStats, SparseMap, TileEntities,
+    TileEntityStorage,
+};
 use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::math::UVec2;
 use bevy::prelude::{Component, Entity};
-use bevy::utils::HashMap;
 use lettuces::storage::grid::Grid;
 use std::hash::{Hash, Hasher};
+use std::mem::size_of;
 
 #[cfg(feature = "reflect")]
 use bevy::ecs::reflect::ReflectMapEntities;
@@ -41,8 +45,19 @@ pub struct SquareChunkLayer<T>
 where
     T: Hash + Clone + Copy + Sized + Default + Send + Sync,
 {
+    #[cfg_attr(
+        feature = "serde",
+        serde(bound(
+            serialize = "T: Serialize + PartialEq",
+            deserialize = "T: Deserialize<'de>"
+        ))
+    )]
     layer_type_data: SquareChunkLayerData<T>,
-    tile_entities: HashMap<u64, Entity>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "TileEntities::skip_serializing")
+    )]
+    tile_entities: TileEntities,
 }
 
 impl<T> MapEntities for SquareChunkLayer<T>
@@ -50,9 +65,7 @@ where
     T: Hash + Clone + Copy + Sized + Default + Send + Sync,
 {
     fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
-        for tile_entity in self.tile_entities.iter_mut() {
-            *tile_entity.1 = entity_mapper.map_entity(*tile_entity.1);
-        }
+        self.tile_entities.map_entities(entity_mapper);
     }
 }
 
@@ -61,9 +74,7 @@ where
     T: Hash + Clone + Copy + Sized + Default + Send + Sync,
 {
     fn hash<H: Hasher>(&self, h: &mut H) {
-        let mut pairs: Vec<_> = self.tile_entities.iter().collect();
-        pairs.sort_by_key(|i| i.0);
-        Hash::hash(&pairs, h);
+        Hash::hash(&self.tile_entities, h);
         Hash::hash(&self.layer_type_data, h);
     }
 }
@@ -112,6 +123,27 @@ where
         }
     }
 
+    fn new_pooled(
+        layer_type: ChunkLayerType<T>,
+        chunk_dimensions: UVec2,
+        map_settings: &Self::ChunkSettings,
+        pool: &ChunkStoragePool<T>,
+    ) -> Self
+    where
+        T: 'static,
+    {
+        match layer_type {
+            ChunkLayerType::Dense(dense_data) => Self {
+                layer_type_data: SquareChunkLayerData::new_dense_from_vecs_pooled(
+                    &dense_data,
+                    pool,
+                ),
+                tile_entities: Default::default(),
+            },
+            layer_type => Self::new(layer_type, chunk_dimensions, map_settings),
+        }
+    }
+
     fn get_chunk_dimensions(&self) -> UVec2 {
         self.layer_type_data.get_dimensions()
     }
@@ -130,13 +162,196 @@ where
     }
 
     fn get_tile_entity(&self, chunk_tile_pos: ChunkCell) -> Option<Entity> {
-        let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
-        self.tile_entities.get(&number).cloned()
+        self.tile_entities.get(chunk_tile_pos)
     }
 
     fn set_tile_entity(&mut self, chunk_tile_pos: ChunkCell, entity: Entity) {
-        let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
-        self.tile_entities.insert(number, entity);
+        self.tile_entities.insert(chunk_tile_pos, entity);
+    }
+
+    fn remove_tile_entity(&mut self, chunk_tile_pos: ChunkCell) -> Option<Entity> {
+        self.tile_entities.remove(chunk_tile_pos)
+    }
+
+    fn remove_tile_data(&mut self, chunk_tile_pos: ChunkCell) -> Option<T> {
+        self.layer_type_data.remove_tile_data(chunk_tile_pos)
+    }
+
+    fn iter_tile_data(&self) -> Box<dyn Iterator<Item = (ChunkCell, &T)> + '_> {
+        match &self.layer_type_data {
+            SquareChunkLayerData::Sparse(layer_data, ..) => {
+                Box::new(layer_data.iter().map(|(number, tile_data)| {
+                    (
+                        ChunkCell::new((number >> 32) as u32 as i32, *number as u32 as i32),
+                        tile_data,
+                    )
+                }))
+            }
+            SquareChunkLayerData::Dense(..) | SquareChunkLayerData::Template(..) => {
+                let layer_data = self.layer_type_data.grid().expect("layer is dense");
+                let (rows, columns) = layer_data.size();
+                Box::new(
+                    (0..rows)
+                        .flat_map(move |y| (0..columns).map(move |x| (x, y)))
+                        .filter_map(|(x, y)| {
+                            layer_data
+                                .get(y, x)
+                                .map(|tile_data| (ChunkCell::new(x as i32, y as i32), tile_data))
+                        }),
+                )
+            }
+        }
+    }
+
+    fn iter_tile_entities(&self) -> Box<dyn Iterator<Item = (ChunkCell, Entity)> + '_> {
+        self.tile_entities.iter()
+    }
+
+    fn share_templates(&mut self, templates: &mut ChunkTemplates<T>) {
+        if let SquareChunkLayerData::Dense(grid) = &mut self.layer_type_data {
+            let grid = std::mem::replace(grid, Grid::new(0, 0));
+            self.layer_type_data = SquareChunkLayerData::Template(templates.share(grid));
+        }
+    }
+
+    fn release_storage(&mut self, pool: &ChunkStoragePool<T>)
+    where
+        T: 'static,
+    {
+        if let SquareChunkLayerData::Dense(grid) = &mut self.layer_type_data {
+            let grid = std::mem::replace(grid, Grid::new(0, 0));
+            let dimensions = UVec2::new(grid.size().1 as u32, grid.size().0 as u32);
+            pool.release(grid.into_vec());
+            self.layer_type_data = SquareChunkLayerData::Sparse(SparseMap::default(), dimensions);
+        }
+    }
+
+    fn set_tile_entity_storage(&mut self, storage: TileEntityStorage) {
+        let dimensions = self.get_chunk_dimensions();
+        self.tile_entities.convert(storage, dimensions);
+    }
+
+    fn is_sparse(&self) -> bool {
+        matches!(self.layer_type_data, SquareChunkLayerData::Sparse(..))
+    }
+
+    fn set_layer_storage(&mut self, storage: LayerStorage, is_empty: &dyn Fn(&T) -> bool) -> bool {
+        let dimensions = self.get_chunk_dimensions();
+        let layer_type_data = match (storage, &self.layer_type_data) {
+            (LayerStorage::Sparse, SquareChunkLayerData::Dense(..))
+            | (LayerStorage::Sparse, SquareChunkLayerData::Template(..)) => {
+                let filled = ChunkCell::iter_chunk(dimensions)
+                    .filter_map(|chunk_cell| {
+                        let tile_data = self.layer_type_data.get_tile_data(chunk_cell)?;
+                        (!is_empty(tile_data)).then_some((
+                            ((chunk_cell.x() as u64) << 32) | chunk_cell.y() as u64,
+                            *tile_data,
+                        ))
+                    })
+                    .collect();
+                SquareChunkLayerData::Sparse(filled, dimensions)
+            }
+            (LayerStorage::Dense, SquareChunkLayerData::Sparse(..)) => {
+                let dense: Vec<Vec<T>> = (0..dimensions.y as i32)
+                    .map(|y| {
+                        (0..dimensions.x as i32)
+                            .map(|x| {
+                                self.layer_type_data
+                                    .get_tile_data(ChunkCell::new(x, y))
+                                    .copied()
+                                    .unwrap_or_default()
+                            })
+                            .collect()
+                    })
+                    .collect();
+                SquareChunkLayerData::new_dense_from_vecs(&dense)
+            }
+            _ => return false,
+        };
+        self.layer_type_data = layer_type_data;
+        true
+    }
+
+    fn compact(&mut self, is_empty: &dyn Fn(&T) -> bool, max_dense_fill: f32) -> CompactionReport {
+        let before = self.heap_size();
+        let dimensions = self.get_chunk_dimensions();
+        let mut report = CompactionReport::default();
+        match &mut self.layer_type_data {
+            SquareChunkLayerData::Sparse(layer_data, ..) => {
+                let len = layer_data.len();
+                layer_data.retain(|_, tile_data| !is_empty(tile_data));
+                report.dropped_entries = len - layer_data.len();
+                layer_data.shrink_to_fit();
+            }
+            SquareChunkLayerData::Dense(grid) => {
+                let (rows, columns) = grid.size();
+                let filled: SparseMap<u64, T> = (0..rows)
+                    .flat_map(|y| (0..columns).map(move |x| (x, y)))
+                    .filter_map(|(x, y)| {
+                        let tile_data = grid.get(y, x)?;
+                        (!is_empty(tile_data))
+                            .then_some((((x as u64) << 32) | y as u64, *tile_data))
+                    })
+                    .collect();
+                if rows * columns > 0
+                    && filled.len() as f32 <= max_dense_fill * (rows * columns) as f32
+                {
+                    self.layer_type_data = SquareChunkLayerData::Sparse(filled, dimensions);
+                    report.converted_layers = 1;
+                }
+            }
+            // Templates are already shared between chunks
+            SquareChunkLayerData::Template(..) => {}
+        }
+        self.tile_entities.shrink_to_fit();
+        let after = self.heap_size();
+        report.reclaimed_bytes = before.saturating_sub(after);
+        report.heap_bytes = after;
+        report
+    }
+
+    fn serialization_stats(&self, is_default: &dyn Fn(&T) -> bool) -> SerializationStats {
+        let mut stats = SerializationStats {
+            skipped_entity_maps: self.tile_entities.skip_serializing() as usize,
+            ..SerializationStats::default()
+        };
+        match &self.layer_type_data {
+            SquareChunkLayerData::Sparse(layer_data, ..) => {
+                stats.stored_tiles = layer_data.len();
+            }
+            SquareChunkLayerData::Dense(..) | SquareChunkLayerData::Template(..) => {
+                let grid = self.layer_type_data.grid().expect("layer is dense");
+                let (rows, columns) = grid.size();
+                let defaults = (0..rows)
+                    .flat_map(|y| (0..columns).map(move |x| (x, y)))
+                    .filter_map(|(x, y)| grid.get(y, x))
+                    .filter(|tile_data| is_default(tile_data))
+                    .count();
+                stats.skipped_default_tiles = defaults;
+                stats.stored_tiles = rows * columns - defaults;
+            }
+        }
+        stats
+    }
+}
+
+impl<T> SquareChunkLayer<T>
+where
+    T: Hash + Clone + Copy + Sized + Default + Send + Sync,
+{
+    /// Returns the approximate amount of heap memory used by the layer in bytes, including unused capacity
+    pub fn heap_size(&self) -> usize {
+        let data = match &self.layer_type_data {
+            SquareChunkLayerData::Sparse(layer_data, ..) => sparse_map_heap_size(layer_data),
+            SquareChunkLayerData::Dense(grid) => grid.size().0 * grid.size().1 * size_of::<T>(),
+            SquareChunkLayerData::Template(template) => template.shared_heap_size(),
+        };
+        data + self.tile_entities.heap_size()
+    }
+
+    /// Returns the data of the layer
+    pub fn layer_data(&self) -> &SquareChunkLayerData<T> {
+        &self.layer_type_data
     }
 }
 
@@ -153,9 +368,38 @@ where
     ///
     /// 0. A hashmap of TilePos -> TileData
     /// 1. A UVec2 representing the actual size of the chunk
-    Sparse(HashMap<u64, T>, UVec2),
+    Sparse(SparseMap<u64, T>, UVec2),
     /// A layer where ***EVERY***  position on the chunk must have data
-    Dense(Grid<T>),
+    ///
+    /// Tiles holding the default `T` are left out when serializing
+    Dense(
+        #[cfg_attr(
+            feature = "serde",
+            serde(
+                with = "crate::map::chunk::default_runs",
+                bound(
+                    serialize = "T: Serialize + PartialEq",
+                    deserialize = "T: Deserialize<'de>"
+                )
+            )
+        )]
+        Grid<T>,
+    ),
+    /// A dense layer sharing its tile data with every other layer with the same contents. Copied into a
+    /// [`SquareChunkLayerData::Dense`] layer on the first write
+    ///
+    /// Serialized like a dense layer, so loaded layers no longer share their data
+    Template(
+        #[cfg_attr(feature = "reflect", reflect(ignore))]
+        #[cfg_attr(
+            feature = "serde",
+            serde(bound(
+                serialize = "T: Serialize + PartialEq",
+                deserialize = "T: Deserialize<'de>"
+            ))
+        )]
+        ChunkTemplate<T>,
+    ),
 }
 
 impl<T> Hash for SquareChunkLayerData<T>
@@ -173,6 +417,9 @@ where
             SquareChunkLayerData::Dense(grid) => {
                 Hash::hash(grid, h);
             }
+            SquareChunkLayerData::Template(template) => {
+                Hash::hash(template, h);
+            }
         }
     }
 }
@@ -233,6 +480,22 @@ where
 
         Self::Dense(grid)
     }
+
+    /// Creates a new [`SquareChunkLayerData::Dense`] from the given vectors of vectors of T like
+    /// [`Self::new_dense_from_vecs`], storing the tiles in a buffer taken from the given [`ChunkStoragePool`]
+    pub fn new_dense_from_vecs_pooled(tile_data: &[Vec<T>], pool: &ChunkStoragePool<T>) -> Self
+    where
+        T: 'static,
+    {
+        let row_length = tile_data[0].len();
+        assert!(tile_data.iter().all(|row| row.len() == row_length));
+
+        let mut buffer = pool.take(row_length * tile_data.len());
+        for row in tile_data.iter() {
+            buffer.extend_from_slice(row);
+        }
+        Self::Dense(Grid::from_vec(buffer, row_length))
+    }
 }
 
 impl<T> SquareChunkLayerData<T>
@@ -246,11 +509,33 @@ where
             SquareChunkLayerData::Dense(grid) => {
                 UVec2::new(grid.size().1 as u32, grid.size().0 as u32)
             }
+            SquareChunkLayerData::Template(template) => UVec2::new(
+                template.grid().size().1 as u32,
+                template.grid().size().0 as u32,
+            ),
+        }
+    }
+
+    /// Returns the tile data of dense and template layers
+    pub fn grid(&self) -> Option<&Grid<T>> {
+        match self {
+            SquareChunkLayerData::Sparse(..) => None,
+            SquareChunkLayerData::Dense(grid) => Some(grid),
+            SquareChunkLayerData::Template(template) => Some(template.grid()),
+        }
+    }
+
+    /// Copies the data of a template layer into a dense layer so it can be written
+    fn make_unique(&mut self) {
+        if let SquareChunkLayerData::Template(template) = self {
+            let grid = std::mem::take(template).into_grid();
+            *self = SquareChunkLayerData::Dense(grid);
         }
     }
 
     /// Sets the tile data at the given [`ChunkCell`]. Can fail if the given cell is not a valid position in the chunk
     pub fn set_tile_data(&mut self, chunk_tile_pos: ChunkCell, tile_data: T) {
+        self.make_unique();
         match self {
             SquareChunkLayerData::Sparse(layer_data, ..) => {
                 let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
@@ -263,11 +548,24 @@ where
                     *tile = tile_data
                 };
             }
+            SquareChunkLayerData::Template(..) => unreachable!("templates were made unique"),
         };
     }
 
+    /// Removes the tile data at the given [`ChunkCell`] and returns it. Only sparse layers can remove tile data
+    pub fn remove_tile_data(&mut self, chunk_tile_pos: ChunkCell) -> Option<T> {
+        match self {
+            SquareChunkLayerData::Sparse(layer_data, ..) => {
+                let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
+                layer_data.remove(&number)
+            }
+            SquareChunkLayerData::Dense(..) | SquareChunkLayerData::Template(..) => None,
+        }
+    }
+
     /// Gets mutable access to the tile data at the given [`ChunkCell`]. Can fail if the given cell is not a valid position in the chunk
     pub fn get_tile_data_mut(&mut self, chunk_tile_pos: ChunkCell) -> Option<&mut T> {
+        self.make_unique();
         return match self {
             SquareChunkLayerData::Sparse(layer_data, ..) => {
                 let number = ((chunk_tile_pos.x() as u64) << 32) | chunk_tile_pos.y() as u64;
@@ -276,6 +574,7 @@ where
             SquareChunkLayerData::Dense(layer_data) => {
                 layer_data.get_mut(chunk_tile_pos.y() as usize, chunk_tile_pos.x() as usize)
             }
+            SquareChunkLayerData::Template(..) => unreachable!("templates were made unique"),
         };
     }
 
@@ -289,6 +588,9 @@ where
             SquareChunkLayerData::Dense(layer_data) => {
                 layer_data.get(chunk_tile_pos.y() as usize, chunk_tile_pos.x() as usize)
             }
+            SquareChunkLayerData::Template(template) => template
+                .grid()
+                .get(chunk_tile_pos.y() as usize, chunk_tile_pos.x() as usize),
         };
     }
 }
@@ -15,9 +15,10 @@ use crate::map::{
     chunk::{Chunk, ChunkLayerType, ChunkPos},
     MapData, MapLayer,
 };
+use crate::tilemap_builder::tilemap_layer_builder::DenseTileSource;
 
 /// An implementation of [`MapData`] for a standard square map.
-#[derive(Default, Hash, Component)]
+#[derive(Clone, Default, Hash, Component)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "reflect", derive(Reflect))]
 #[cfg_attr(feature = "reflect", reflect(Hash))]
@@ -38,47 +39,42 @@ impl MapData for SquareMapData {
         self.max_chunk_size
     }
 
-    fn break_data_vecs_down_into_chunk_data<TileData>(
+    fn break_data_vecs_down_into_chunk_data<TileData, Source>(
         &self,
-        data: &Vec<Vec<TileData>>,
+        data: &Source,
         chunk_pos: ChunkPos,
         max_chunk_size: UVec2,
     ) -> Vec<Vec<TileData>>
     where
         TileData: Clone + Copy + Sized + Default + Send + Sync + 'static,
+        Source: DenseTileSource<TileData> + ?Sized,
     {
+        let dimensions = data.dimensions();
         let amount_of_x_tiles_done = (chunk_pos.x() * max_chunk_size.x as i32) as usize;
         let amount_of_y_tiles_done = (chunk_pos.y() * max_chunk_size.y as i32) as usize;
-        let mut vec: Vec<Vec<TileData>> = vec![];
-        for y in amount_of_y_tiles_done..data.len() {
-            if y >= (amount_of_y_tiles_done + max_chunk_size.y as usize) {
-                continue;
-            }
-            let mut row_vec: Vec<TileData> = vec![];
-            for x in amount_of_x_tiles_done..data[0].len() {
-                if x >= (amount_of_x_tiles_done + max_chunk_size.x as usize) {
-                    continue;
-                }
-                row_vec.push(data[y][x]);
-            }
-            vec.push(row_vec);
-        }
-        vec
+        let x_tiles = amount_of_x_tiles_done
+            ..(amount_of_x_tiles_done + max_chunk_size.x as usize).min(dimensions.x as usize);
+        let y_tiles = amount_of_y_tiles_done
+            ..(amount_of_y_tiles_done + max_chunk_size.y as usize).min(dimensions.y as usize);
+        y_tiles
+            .map(|y| x_tiles.clone().map(|x| data.tile_data(x, y)).collect())
+            .collect()
     }
 
-    fn break_data_vecs_into_chunks<TileData, MapChunk>(
+    fn break_data_vecs_into_chunks<TileData, MapChunk, Source>(
         &self,
-        data: &Vec<Vec<TileData>>,
+        data: &Source,
         max_chunk_size: UVec2,
         chunk_settings: MapChunk::ChunkSettings,
     ) -> Vec<Vec<Chunk<MapChunk, TileData>>>
     where
         TileData: std::hash::Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
         MapChunk: crate::map::chunk::ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Source: DenseTileSource<TileData> + ?Sized,
     {
         let mut chunks: Vec<Vec<Chunk<MapChunk, TileData>>> = vec![];
-        let map_x = data[0].len() as f32;
-        let map_y = data.len() as f32;
+        let map_x = data.dimensions().x as f32;
+        let map_y = data.dimensions().y as f32;
 
         let chunks_on_x = (map_x / max_chunk_size.x as f32).ceil() as i32;
         let chunks_on_y = (map_y / max_chunk_size.y as f32).ceil() as i32;
@@ -87,7 +83,7 @@ impl MapData for SquareMapData {
             let mut chunks_rows: Vec<Chunk<MapChunk, TileData>> = vec![];
             for x in 0..chunks_on_x {
                 let vec = self.break_data_vecs_down_into_chunk_data(
-                    &data,
+                    data,
                     ChunkPos::new(x, y),
                     max_chunk_size,
                 );
@@ -173,12 +169,17 @@ mod tests {
     use crate::map::MapData;
     use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
     use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
 
+    use crate::map::chunk::ChunkLayer;
     use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
-    use bevy::math::UVec2;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{IVec2, UVec2};
+    use bevy::prelude::World;
     use bevy::utils::HashMap;
     use bst_map_layer_derive::MapLayer;
     use lettuces::cell::Cell;
+    use proptest::prelude::*;
 
     #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
     struct TileData(u8);
@@ -307,7 +308,7 @@ mod tests {
         assert_eq!(one_one[3][2], (7, 8));
     }
 
-    #[derive(MapLayer, Default)]
+    #[derive(MapLayer, Default, Clone, Copy)]
     enum MapLayers {
         #[default]
         Main,
@@ -375,4 +376,80 @@ mod tests {
             (0, 0)
         );
     }
+
+    /// A map size, a chunk size, and a cell that may lie outside of the map
+    fn map_and_cell() -> impl Strategy<Value = (UVec2, UVec2, IVec2)> {
+        (1..40u32, 1..40u32, 1..12u32, 1..12u32).prop_flat_map(|(w, h, cw, ch)| {
+            (
+                Just(UVec2::new(w, h)),
+                Just(UVec2::new(cw, ch)),
+                (-5..w as i32 + 5, -5..h as i32 + 5).prop_map(|(x, y)| IVec2::new(x, y)),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_chunk_math_round_trips((map_size, chunk_size, cell) in map_and_cell()) {
+            prop_assume!(cell.x >= 0 && cell.y >= 0);
+            let map_data = SquareMapData { max_chunk_size: chunk_size };
+            let chunk_settings = SquareChunkSettings { max_chunk_size: chunk_size };
+            let cell = Cell::new(cell.x, cell.y);
+            let chunk_pos = map_data.into_chunk_pos(cell);
+            let chunk_cell = SquareChunkLayer::<u8>::into_chunk_cell(cell, &chunk_settings);
+
+            prop_assert!(chunk_cell.within(chunk_size));
+            prop_assert_eq!(
+                chunk_pos.as_ivec2() * chunk_size.as_ivec2() + chunk_cell.as_ivec2(),
+                IVec2::new(cell.x, cell.y)
+            );
+            if cell.x < map_size.x as i32 && cell.y < map_size.y as i32 {
+                prop_assert!(chunk_pos.within((map_size + chunk_size - UVec2::ONE) / chunk_size));
+            }
+        }
+
+        #[test]
+        fn test_manager_access_never_panics((map_size, chunk_size, cell) in map_and_cell(), tile_data: u8) {
+            let mut world = World::new();
+            let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+                SystemState::new(&mut world);
+            let (mut commands, _) = system_state.get_mut(&mut world);
+            let rows = (0..map_size.y)
+                .map(|y| (0..map_size.x).map(|x| (x + y * map_size.x) as u8).collect())
+                .collect();
+            let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+                TilemapLayer::new_dense_from_vecs(rows),
+                SquareMapData { max_chunk_size: chunk_size },
+                SquareChunkSettings { max_chunk_size: chunk_size },
+            )
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+            system_state.apply(&mut world);
+            let entity = world.spawn_empty().id();
+
+            let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+            tilemap_manager.set_tilemap_entity(map_entity);
+            prop_assert_eq!(tilemap_manager.dimensions().ok(), Some(map_size));
+
+            let in_bounds = cell.cmpge(IVec2::ZERO).all() && cell.cmplt(map_size.as_ivec2()).all();
+            let cell = Cell::new(cell.x, cell.y);
+            let expected = in_bounds.then(|| (cell.x as u32 + cell.y as u32 * map_size.x) as u8);
+            prop_assert_eq!(tilemap_manager.get_tile_data(cell).ok(), expected);
+            prop_assert_eq!(tilemap_manager.sets_tile_data(tile_data, cell).is_ok(), in_bounds);
+            if in_bounds {
+                prop_assert_eq!(tilemap_manager.get_tile_data(cell).ok(), Some(tile_data));
+            }
+
+            let spawned = tilemap_manager.get_or_spawn_tile_entity(cell).ok();
+            prop_assert_eq!(spawned.is_some(), in_bounds);
+            prop_assert_eq!(tilemap_manager.get_tile_entity(cell).ok(), spawned);
+            prop_assert_eq!(tilemap_manager.set_tile_entity(cell, entity).is_ok(), in_bounds);
+            let origin = Cell::new(0, 0);
+            prop_assert_eq!(
+                tilemap_manager.move_tile_entity(cell, origin).ok(),
+                (in_bounds && cell != origin).then_some(entity)
+            );
+            prop_assert_eq!(tilemap_manager.despawn_tile_entity(cell).is_ok(), in_bounds);
+        }
+    }
 }
@@ -1,8 +1,11 @@
+use fixed_chunk_layer::FixedSquareChunkLayer;
 use map_chunk_layer::SquareChunkLayer;
 use map_data::SquareMapData;
 
 use crate::{map::chunk::Chunk, tilemap_builder::TilemapBuilder, tilemap_manager::TilemapManager};
 
+/// Implements a [`ChunkLayer`](crate::map::chunk::ChunkLayer) with a fixed chunk size for a square map type
+pub mod fixed_chunk_layer;
 /// Implements [`ChunkLayer`](crate::map::chunk::ChunkLayer) for a square map type
 pub mod map_chunk_layer;
 /// Implements [`MapData`](crate::map::MapData) for a square map type
@@ -18,3 +21,19 @@ pub type SquareChunk<TileData> = Chunk<SquareChunkLayer<TileData>, TileData>;
 /// Type alias for [`TilemapBuilder`] for the built in square map types
 pub type SquareTilemapBuilder<TileData, MapLayers> =
     TilemapBuilder<TileData, MapLayers, SquareChunkLayer<TileData>, SquareMapData>;
+
+/// Type alias for [`TilemapManager`] for square maps stored in a [`FixedSquareChunkLayer`]
+pub type FixedSquareTilemapManager<'w, 's, TileData, MapLayers, const W: usize, const H: usize> =
+    TilemapManager<
+        'w,
+        's,
+        TileData,
+        MapLayers,
+        FixedSquareChunkLayer<TileData, W, H>,
+        SquareMapData,
+    >;
+
+/// Type alias for [`TilemapBuilder`] for square maps stored in a [`FixedSquareChunkLayer`]. Create it with
+/// [`TilemapBuilder::new_fixed`] to use chunks of `W` x `H` cells
+pub type FixedSquareTilemapBuilder<TileData, MapLayers, const W: usize, const H: usize> =
+    TilemapBuilder<TileData, MapLayers, FixedSquareChunkLayer<TileData, W, H>, SquareMapData>;
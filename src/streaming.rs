@@ -0,0 +1,382 @@
+//! Despawning chunk entities far away from the player and respawning them when they come back in range.
+//!
+//! Keeping every chunk entity of a huge map alive wastes memory and scheduler time on chunks nobody looks
+//! at. Add the [`ChunkStreamingPlugin`] and insert a [`ChunkLoader`] on the camera or the player. Chunks
+//! further than [`ChunkLoader::radius`] chunks away from every loader are despawned and their [`Chunk`] is
+//! retained in a [`RetainedChunks`] on the map entity. They are spawned again from the retained data once a
+//! loader comes back in range.
+//!
+//! Loaders are positioned through their [`GlobalTransform`] and the [`TilemapGeometry`] of the map, maps
+//! without a geometry are never streamed. Maps can opt out with [`TilemapSubsystems::STREAMING`].
+//!
+//! While a chunk is streamed out the [`TilemapManager`](crate::tilemap_manager::TilemapManager) returns
+//! errors for its cells. Tile entities stay alive while their chunk is streamed out, tile entities parented
+//! to their chunk through [`TileEntityParenting::Chunk`] are detached from it and parented to the respawned
+//! chunk once it streams back in. Only the [`Chunk`] component is retained, other components inserted on the
+//! chunk entity are lost.
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
+use crate::map::{
+    MapData, TileEntityParenting, Tilemap, TilemapGeometry, TilemapSettings, TilemapSubsystems,
+};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::prelude::{
+    BuildChildren, Commands, Component, DespawnRecursiveExt, Entity, Event, EventWriter,
+    GlobalTransform, Local, Query,
+};
+use bevy::utils::{HashMap, HashSet};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Keeps the chunks around the entity it is inserted on spawned. The position of the loader is read from its
+/// [`GlobalTransform`]
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkLoader {
+    /// The map the loader keeps chunks spawned in. Loaders without a map load chunks in every map
+    pub map: Option<Entity>,
+    /// How many chunks around the chunk the loader is in stay spawned in every direction
+    pub radius: u32,
+}
+
+impl ChunkLoader {
+    /// Creates a loader keeping `radius` chunks spawned around it in every map
+    pub fn new(radius: u32) -> Self {
+        Self { map: None, radius }
+    }
+
+    /// Creates a loader keeping `radius` chunks spawned around it in the given map
+    pub fn for_map(map: Entity, radius: u32) -> Self {
+        Self {
+            map: Some(map),
+            radius,
+        }
+    }
+}
+
+/// The chunks of a map that are currently streamed out, keyed by their [`ChunkPos`]. Inserted on the map
+/// entity by the [`ChunkStreamingPlugin`]
+#[derive(Component)]
+pub struct RetainedChunks<MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + Default,
+{
+    chunks: HashMap<ChunkPos, Chunk<MapChunk, TileData>>,
+}
+
+impl<MapChunk, TileData> Default for RetainedChunks<MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + Default,
+{
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::default(),
+        }
+    }
+}
+
+impl<MapChunk, TileData> RetainedChunks<MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    /// Returns the retained chunk at the given position if it is streamed out
+    pub fn get(&self, chunk_pos: ChunkPos) -> Option<&Chunk<MapChunk, TileData>> {
+        self.chunks.get(&chunk_pos)
+    }
+
+    /// Returns true if the chunk at the given position is streamed out
+    pub fn contains(&self, chunk_pos: ChunkPos) -> bool {
+        self.chunks.contains_key(&chunk_pos)
+    }
+
+    /// Returns the amount of chunks that are streamed out
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if no chunks are streamed out
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Iterates over the positions of every chunk that is streamed out
+    pub fn chunk_positions(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+        self.chunks.keys().copied()
+    }
+}
+
+/// Sent whenever a chunk is spawned again because a [`ChunkLoader`] came in range
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkStreamedIn {
+    /// The map the chunk belongs to
+    pub map_entity: Entity,
+    /// The position of the chunk
+    pub chunk_pos: ChunkPos,
+    /// The new entity of the chunk
+    pub chunk_entity: Entity,
+}
+
+/// Sent whenever a chunk is despawned because no [`ChunkLoader`] is in range
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChunkStreamedOut {
+    /// The map the chunk belongs to
+    pub map_entity: Entity,
+    /// The position of the chunk
+    pub chunk_pos: ChunkPos,
+}
+
+/// Plugin that streams chunks of maps with the given types in and out around [`ChunkLoader`]s
+pub struct ChunkStreamingPlugin<TileData, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapChunk, Map)>,
+}
+
+impl<TileData, MapChunk, Map> Default for ChunkStreamingPlugin<TileData, MapChunk, Map> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk, Map> Plugin for ChunkStreamingPlugin<TileData, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChunkStreamedIn>()
+            .add_event::<ChunkStreamedOut>()
+            .add_systems(PostUpdate, stream_chunks::<TileData, MapChunk, Map>);
+    }
+}
+
+/// Despawns the chunks that left the range of every [`ChunkLoader`] and spawns the retained chunks that came
+/// back in range.
+///
+/// Maps are only updated when the set of chunks in range changed since the last run. Maps that no loader
+/// targets are left alone.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub fn stream_chunks<TileData, MapChunk, Map>(
+    mut commands: Commands,
+    loaders: Query<(&ChunkLoader, &GlobalTransform)>,
+    mut maps: Query<(
+        Entity,
+        &mut Tilemap,
+        &Map,
+        &TilemapGeometry,
+        Option<&TilemapSettings>,
+        Option<&mut RetainedChunks<MapChunk, TileData>>,
+    )>,
+    mut chunks: Query<&mut Chunk<MapChunk, TileData>>,
+    mut loaded_chunks: Local<HashMap<Entity, HashSet<ChunkPos>>>,
+    mut tick: Local<u64>,
+    mut streamed_in: EventWriter<ChunkStreamedIn>,
+    mut streamed_out: EventWriter<ChunkStreamedOut>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let current_tick = *tick;
+    *tick = tick.wrapping_add(1);
+    loaded_chunks.retain(|map_entity, _| maps.contains(*map_entity));
+
+    for (map_entity, mut tilemap, map, geometry, map_settings, retained) in maps.iter_mut() {
+        if !TilemapSettings::should_run_for(
+            map_settings,
+            TilemapSubsystems::STREAMING,
+            current_tick,
+        ) {
+            continue;
+        }
+
+        let parent_to_chunk = map_settings
+            .is_some_and(|settings| settings.tile_entity_parenting == TileEntityParenting::Chunk);
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        let mut in_range: HashSet<ChunkPos> = HashSet::default();
+        let mut targeted = false;
+        for (loader, transform) in loaders.iter() {
+            if loader.map.is_some_and(|map| map != map_entity) {
+                continue;
+            }
+            targeted = true;
+            let center =
+                map.into_chunk_pos(geometry.world_to_cell(transform.translation().truncate()));
+            in_range.extend(tilemap.chunks_in_radius(center, loader.radius));
+        }
+        if !targeted || loaded_chunks.get(&map_entity) == Some(&in_range) {
+            continue;
+        }
+
+        let mut new_retained = None;
+        let retained = match retained {
+            Some(retained) => retained.into_inner(),
+            None => new_retained.insert(RetainedChunks::<MapChunk, TileData>::default()),
+        };
+
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x as i32 - 1, chunk_counts.y as i32 - 1),
+        ) {
+            if in_range.contains(&chunk_pos) {
+                let Some(chunk) = retained.chunks.remove(&chunk_pos) else {
+                    continue;
+                };
+                let tile_entities = match parent_to_chunk {
+                    true => chunk_tile_entities(&chunk),
+                    false => vec![],
+                };
+                let chunk_entity = commands.spawn(chunk).id();
+                commands.entity(map_entity).add_child(chunk_entity);
+                commands.entity(chunk_entity).push_children(&tile_entities);
+                tilemap.chunks_mut().set_chunk(chunk_pos, chunk_entity);
+                streamed_in.send(ChunkStreamedIn {
+                    map_entity,
+                    chunk_pos,
+                    chunk_entity,
+                });
+            } else if !retained.chunks.contains_key(&chunk_pos) {
+                let Some(chunk_entity) = tilemap.get_chunk(chunk_pos) else {
+                    continue;
+                };
+                let Ok(mut chunk) = chunks.get_mut(chunk_entity) else {
+                    continue;
+                };
+                retained
+                    .chunks
+                    .insert(chunk_pos, std::mem::take(&mut *chunk));
+                if parent_to_chunk {
+                    let tile_entities = chunk_tile_entities(&retained.chunks[&chunk_pos]);
+                    commands
+                        .entity(chunk_entity)
+                        .remove_children(&tile_entities);
+                }
+                commands.entity(chunk_entity).despawn_recursive();
+                streamed_out.send(ChunkStreamedOut {
+                    map_entity,
+                    chunk_pos,
+                });
+            }
+        }
+
+        if let Some(retained) = new_retained {
+            commands.entity(map_entity).insert(retained);
+        }
+        loaded_chunks.insert(map_entity, in_range);
+    }
+}
+
+/// Returns the tile entities of every layer of the chunk
+fn chunk_tile_entities<TileData, MapChunk>(chunk: &Chunk<MapChunk, TileData>) -> Vec<Entity>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    chunk
+        .data
+        .values()
+        .flat_map(|layer| layer.iter_tile_entities().map(|(_, entity)| entity))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkLoader, ChunkStreamedOut, ChunkStreamingPlugin, RetainedChunks};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::map::TilemapGeometry;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{UVec2, Vec2, Vec3};
+    use bevy::prelude::{GlobalTransform, Transform};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    type Retained = RetainedChunks<SquareChunkLayer<u8>, u8>;
+
+    #[test]
+    fn test_chunk_streaming() {
+        let mut app = App::new();
+        app.add_plugins(ChunkStreamingPlugin::<
+            u8,
+            SquareChunkLayer<u8>,
+            SquareMapData,
+        >::default());
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        commands
+            .entity(map_entity)
+            .insert(TilemapGeometry::new(Vec2::ZERO, Vec2::splat(16.0)));
+        let loader = commands
+            .spawn((
+                ChunkLoader::for_map(map_entity, 1),
+                GlobalTransform::from_translation(Vec3::new(8.0, 8.0, 0.0)),
+            ))
+            .id();
+        system_state.apply(&mut app.world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager
+            .sets_tile_data(5, Cell::new(7, 7))
+            .expect("chunk is spawned");
+
+        // The loader sits in chunk (0, 0) and keeps chunks (0, 0) to (1, 1) spawned out of the 4 x 4 chunks
+        app.update();
+        assert_eq!(app.world.get::<Retained>(map_entity).unwrap().len(), 12);
+        assert!(app
+            .world
+            .get::<Retained>(map_entity)
+            .unwrap()
+            .contains(ChunkPos::new(3, 3)));
+        assert_eq!(
+            app.world
+                .resource::<Events<ChunkStreamedOut>>()
+                .get_reader()
+                .len(app.world.resource::<Events<ChunkStreamedOut>>()),
+            12
+        );
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 1);
+        assert!(tilemap_manager.get_tile_data(Cell::new(7, 7)).is_err());
+
+        // Moving the loader to the other corner spawns those chunks again with their data
+        *app.world.get_mut::<GlobalTransform>(loader).unwrap() =
+            GlobalTransform::from(Transform::from_xyz(120.0, 120.0, 0.0));
+        app.update();
+        let retained = app.world.get::<Retained>(map_entity).unwrap();
+        assert_eq!(retained.len(), 12);
+        assert!(retained.contains(ChunkPos::new(0, 0)));
+        assert!(!retained.contains(ChunkPos::new(3, 3)));
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(7, 7)).unwrap(), 5);
+        assert!(tilemap_manager.get_tile_data(Cell::new(0, 0)).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Helpers for regression testing maps against checked in snapshot files.
+//!
+//! A [`MapSnapshot`] is a normalized copy of a single layer of a tilemap. Snapshots can be compared
+//! against each other with [`MapSnapshot::diff`] or against a `.ron` file on disk with
+//! [`assert_map_matches_ron!`](crate::assert_map_matches_ron).
+//!
+//! If the snapshot file does not exist, or the `BST_UPDATE_SNAPSHOTS` environment variable is set, the
+//! file is (re)written from the current map instead of being compared.
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::math::UVec2;
+use lettuces::cell::Cell;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Debug, Write};
+use std::hash::Hash;
+use std::path::Path;
+
+/// Environment variable that forces snapshot files to be rewritten instead of compared
+pub const UPDATE_SNAPSHOTS_ENV: &str = "BST_UPDATE_SNAPSHOTS";
+
+/// The maximum amount of differing cells that are listed in a mismatch report
+const MAX_REPORTED_DIFFS: usize = 20;
+
+/// Errors returned when checking a map against a snapshot file
+#[derive(thiserror::Error, Debug)]
+pub enum MapSnapshotError {
+    /// Failed to access the map through the [`TilemapManager`]
+    #[error("Failed to read the map: {0}")]
+    Manager(#[from] TilemapManagerError),
+
+    /// Failed to read or write the snapshot file
+    #[error("Failed to access the snapshot file: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to serialize the snapshot
+    #[error("Failed to serialize the snapshot: {0}")]
+    Serialize(#[from] ron::Error),
+
+    /// Failed to deserialize the snapshot file
+    #[error("Failed to deserialize the snapshot file: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+
+    /// The map did not match the snapshot. Contains a readable report of the differences
+    #[error("{0}")]
+    Mismatch(String),
+}
+
+/// A single cell that differs between two [`MapSnapshot`]s
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellDiff<TileData> {
+    /// The cell that differs
+    pub cell: Cell,
+    /// The data in the expected snapshot
+    pub expected: Option<TileData>,
+    /// The data in the actual snapshot
+    pub found: Option<TileData>,
+}
+
+/// A normalized snapshot of a single layer of a tilemap.
+///
+/// Tiles are stored row by row starting at cell (0, 0). Cells without data, such as empty cells in a
+/// sparse layer, are stored as `None`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MapSnapshot<TileData> {
+    /// The dimensions of the map
+    pub dimensions: UVec2,
+    /// The tile data of the map stored as rows of tiles
+    pub tiles: Vec<Vec<Option<TileData>>>,
+}
+
+impl<TileData> MapSnapshot<TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + PartialEq + 'static,
+{
+    /// Creates a snapshot of the layer that the given [`TilemapManager`] is currently set to
+    pub fn from_manager<MapLayers, MapChunk, Map>(
+        tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    ) -> Result<Self, TilemapManagerError>
+    where
+        MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let dimensions = tilemap_manager.dimensions()?;
+        let mut tiles = Vec::with_capacity(dimensions.y as usize);
+        for y in 0..dimensions.y as i32 {
+            let mut row = Vec::with_capacity(dimensions.x as usize);
+            for x in 0..dimensions.x as i32 {
+                match tilemap_manager.get_tile_data(Cell::new(x, y)) {
+                    Ok(tile_data) => row.push(Some(tile_data)),
+                    Err(
+                        TilemapManagerError::TileDataDoesNotExist
+                        | TilemapManagerError::CellOutOfBounds,
+                    ) => row.push(None),
+                    Err(err) => return Err(err),
+                }
+            }
+            tiles.push(row);
+        }
+        Ok(Self { dimensions, tiles })
+    }
+
+    /// Returns the data stored for the given [`Cell`]. Returns `None` if the cell is outside the snapshot or has no data
+    pub fn get(&self, cell: Cell) -> Option<TileData> {
+        if cell.x < 0 || cell.y < 0 {
+            return None;
+        }
+        self.tiles
+            .get(cell.y as usize)
+            .and_then(|row| row.get(cell.x as usize))
+            .cloned()
+            .flatten()
+    }
+
+    /// Returns every cell that differs between self (the expected snapshot) and the given snapshot.
+    ///
+    /// Cells that only exist in one of the snapshots are reported with `None` for the other.
+    pub fn diff(&self, other: &MapSnapshot<TileData>) -> Vec<CellDiff<TileData>> {
+        let max_x = self.dimensions.x.max(other.dimensions.x) as i32;
+        let max_y = self.dimensions.y.max(other.dimensions.y) as i32;
+        let mut diffs = vec![];
+        for y in 0..max_y {
+            for x in 0..max_x {
+                let cell = Cell::new(x, y);
+                let expected = self.get(cell);
+                let found = other.get(cell);
+                if expected != found {
+                    diffs.push(CellDiff {
+                        cell,
+                        expected,
+                        found,
+                    });
+                }
+            }
+        }
+        diffs
+    }
+}
+
+impl<TileData> MapSnapshot<TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + PartialEq + Debug + 'static,
+{
+    /// Returns a readable report of the differences between self (the expected snapshot) and the given
+    /// snapshot or `None` if they match
+    pub fn mismatch_report(&self, other: &MapSnapshot<TileData>) -> Option<String> {
+        let diffs = self.diff(other);
+        if diffs.is_empty() && self.dimensions == other.dimensions {
+            return None;
+        }
+        let mut report = String::from("Map does not match the snapshot\n");
+        if self.dimensions != other.dimensions {
+            let _ = writeln!(
+                report,
+                "  dimensions: expected {}, found {}",
+                self.dimensions, other.dimensions
+            );
+        }
+        let _ = writeln!(report, "  {} cell(s) differ", diffs.len());
+        for diff in diffs.iter().take(MAX_REPORTED_DIFFS) {
+            let _ = writeln!(
+                report,
+                "  ({}, {}): expected {:?}, found {:?}",
+                diff.cell.x, diff.cell.y, diff.expected, diff.found
+            );
+        }
+        if diffs.len() > MAX_REPORTED_DIFFS {
+            let _ = writeln!(
+                report,
+                "  ... and {} more",
+                diffs.len() - MAX_REPORTED_DIFFS
+            );
+        }
+        Some(report)
+    }
+}
+
+/// Checks the layer the given [`TilemapManager`] is set to against the `.ron` snapshot at the given path.
+///
+/// Writes the snapshot instead if the file does not exist or [`UPDATE_SNAPSHOTS_ENV`] is set.
+/// Prefer using [`assert_map_matches_ron!`](crate::assert_map_matches_ron) in tests.
+pub fn check_map_matches_ron<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    path: impl AsRef<Path>,
+) -> Result<(), MapSnapshotError>
+where
+    TileData: Hash
+        + Clone
+        + Copy
+        + Sized
+        + Default
+        + Send
+        + Sync
+        + PartialEq
+        + Debug
+        + Serialize
+        + DeserializeOwned
+        + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let path = path.as_ref();
+    let snapshot = MapSnapshot::from_manager(tilemap_manager)?;
+
+    if !path.exists() || std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+        let serialized = ron::ser::to_string_pretty(&snapshot, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, serialized)?;
+        return Ok(());
+    }
+
+    let expected: MapSnapshot<TileData> = ron::from_str(&std::fs::read_to_string(path)?)?;
+    match expected.mismatch_report(&snapshot) {
+        Some(report) => Err(MapSnapshotError::Mismatch(format!(
+            "{}: {}",
+            path.display(),
+            report
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Asserts that the layer a [`TilemapManager`] is currently set to matches the given `.ron` snapshot file.
+///
+/// Panics with a readable list of the differing cells on a mismatch. See [`check_map_matches_ron`] for
+/// details on how snapshot files are created and updated.
+///
+/// ```ignore
+/// tilemap_manager.set_layer(MapLayers::Main);
+/// assert_map_matches_ron!(tilemap_manager, "tests/snapshots/main_layer.ron");
+/// ```
+#[macro_export]
+macro_rules! assert_map_matches_ron {
+    ($manager:expr, $path:expr) => {
+        if let Err(err) = $crate::testing::check_map_matches_ron(&$manager, $path) {
+            panic!("{}", err);
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MapSnapshot;
+    use bevy::math::UVec2;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let expected = MapSnapshot {
+            dimensions: UVec2::new(2, 2),
+            tiles: vec![vec![Some(0u8), Some(1)], vec![None, Some(3)]],
+        };
+        let mut found = expected.clone();
+        assert!(expected.diff(&found).is_empty());
+        assert!(expected.mismatch_report(&found).is_none());
+
+        found.tiles[1][0] = Some(2);
+        let diffs = expected.diff(&found);
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].cell, Cell::new(0, 1));
+        assert_eq!(diffs[0].expected, None);
+        assert_eq!(diffs[0].found, Some(2));
+        assert!(expected.mismatch_report(&found).is_some());
+    }
+}
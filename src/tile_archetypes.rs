@@ -0,0 +1,319 @@
+//! Saving and restoring the tile entities of a chunk. Requires the `tile_archetypes` feature.
+//!
+//! Tile entities only live in the ECS, so they are lost whenever a chunk is despawned. A
+//! [`TileEntityAllowlist`] names the components worth keeping. [`save_chunk_tile_entities`] serializes those
+//! components of every tile entity in a chunk through reflection into a [`SavedChunkTileEntities`] that can
+//! be stored next to the chunk data, and [`restore_chunk_tile_entities`] respawns them when the chunk is
+//! loaded again.
+//!
+//! Every allowed component must be registered in the [`AppTypeRegistry`] with `#[reflect(Component)]`.
+//! Components that aren't allowed are dropped.
+
+use crate::map::chunk::{Chunk, ChunkCell, ChunkLayer};
+use crate::map::TilemapSettings;
+use bevy::ecs::reflect::{AppTypeRegistry, ReflectComponent};
+use bevy::prelude::{BuildWorldChildren, Component, Entity, Parent, Resource, World};
+use bevy::reflect::serde::{ReflectSerializer, UntypedReflectDeserializer};
+use bevy::reflect::TypePath;
+use serde::de::DeserializeSeed;
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::hash::Hash;
+
+/// Errors returned when saving or restoring tile entities
+#[derive(thiserror::Error, Debug)]
+pub enum TileArchetypeError {
+    /// The chunk entity does not exist or has no [`Chunk`]
+    #[error("The chunk entity does not exist or has no Chunk")]
+    ChunkDoesNotExist,
+
+    /// The world has no [`AppTypeRegistry`]
+    #[error("The world has no AppTypeRegistry")]
+    TypeRegistryDoesNotExist,
+
+    /// An allowed component is not registered with `#[reflect(Component)]`
+    #[error("The component {0} is not registered with #[reflect(Component)]")]
+    ComponentNotRegistered(String),
+
+    /// Failed to serialize a component
+    #[error("Failed to serialize a component: {0}")]
+    Serialize(#[from] ron::Error),
+
+    /// Failed to deserialize a component
+    #[error("Failed to deserialize a component: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+}
+
+/// The components of tile entities that survive a chunk being saved and restored
+#[derive(Resource, Default, Clone, Debug)]
+pub struct TileEntityAllowlist {
+    components: Vec<(TypeId, &'static str)>,
+}
+
+impl TileEntityAllowlist {
+    /// Creates an empty allowlist
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows the component `T`
+    pub fn allow<T: Component + TypePath>(&mut self) {
+        if !self.is_allowed::<T>() {
+            self.components.push((TypeId::of::<T>(), T::type_path()));
+        }
+    }
+
+    /// Allows the component `T` and returns self
+    pub fn with<T: Component + TypePath>(mut self) -> Self {
+        self.allow::<T>();
+        self
+    }
+
+    /// Returns true if the component `T` is allowed
+    pub fn is_allowed<T: Component>(&self) -> bool {
+        self.components
+            .iter()
+            .any(|(type_id, _)| *type_id == TypeId::of::<T>())
+    }
+}
+
+/// The saved allowed components of a single tile entity
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SavedTileEntity {
+    /// The bits of the [`MapLayer`](crate::map::MapLayer) the tile entity is in
+    pub map_layer: u64,
+    /// The position of the tile entity in its chunk
+    pub chunk_cell: ChunkCell,
+    /// Every saved component serialized to ron together with its type path
+    pub components: Vec<String>,
+}
+
+/// The saved tile entities of a chunk
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct SavedChunkTileEntities {
+    /// Every saved tile entity of the chunk
+    pub tile_entities: Vec<SavedTileEntity>,
+}
+
+/// Saves the allowed components of every tile entity in the given chunk. Tile entities that no longer exist
+/// are skipped. Doesn't despawn anything.
+pub fn save_chunk_tile_entities<TileData, MapChunk>(
+    world: &World,
+    chunk_entity: Entity,
+    allowlist: &TileEntityAllowlist,
+) -> Result<SavedChunkTileEntities, TileArchetypeError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    let chunk = world
+        .get::<Chunk<MapChunk, TileData>>(chunk_entity)
+        .ok_or(TileArchetypeError::ChunkDoesNotExist)?;
+    let registry = world
+        .get_resource::<AppTypeRegistry>()
+        .ok_or(TileArchetypeError::TypeRegistryDoesNotExist)?
+        .read();
+
+    let mut layers: Vec<(&u64, &MapChunk)> = chunk.data.iter().collect();
+    layers.sort_by_key(|(map_layer, _)| **map_layer);
+
+    let mut saved = SavedChunkTileEntities::default();
+    for (map_layer, layer) in layers {
+        for chunk_cell in ChunkCell::iter_chunk(layer.get_chunk_dimensions()) {
+            let Some(tile_entity) = layer.get_tile_entity(chunk_cell) else {
+                continue;
+            };
+            let Some(entity_ref) = world.get_entity(tile_entity) else {
+                continue;
+            };
+            let mut components = vec![];
+            for (type_id, type_path) in allowlist.components.iter() {
+                let reflect_component = registry
+                    .get_type_data::<ReflectComponent>(*type_id)
+                    .ok_or_else(|| {
+                        TileArchetypeError::ComponentNotRegistered(type_path.to_string())
+                    })?;
+                let Some(component) = reflect_component.reflect(entity_ref) else {
+                    continue;
+                };
+                components.push(ron::to_string(&ReflectSerializer::new(
+                    component, &registry,
+                ))?);
+            }
+            saved.tile_entities.push(SavedTileEntity {
+                map_layer: *map_layer,
+                chunk_cell,
+                components,
+            });
+        }
+    }
+    Ok(saved)
+}
+
+/// Spawns a new tile entity with the saved components for every [`SavedTileEntity`] and sets it as the tile
+/// entity of its cell in the given chunk. Returns the spawned entities in the same order.
+///
+/// The entities are parented according to the [`TileEntityParenting`](crate::map::TileEntityParenting) in the
+/// [`TilemapSettings`] of the map the chunk is a child of.
+pub fn restore_chunk_tile_entities<TileData, MapChunk>(
+    world: &mut World,
+    chunk_entity: Entity,
+    saved: &SavedChunkTileEntities,
+) -> Result<Vec<Entity>, TileArchetypeError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    if world
+        .get::<Chunk<MapChunk, TileData>>(chunk_entity)
+        .is_none()
+    {
+        return Err(TileArchetypeError::ChunkDoesNotExist);
+    }
+    let registry = world
+        .get_resource::<AppTypeRegistry>()
+        .ok_or(TileArchetypeError::TypeRegistryDoesNotExist)?
+        .clone();
+    let registry = registry.read();
+    let parent = world.get::<Parent>(chunk_entity).and_then(|map_entity| {
+        world
+            .get::<TilemapSettings>(map_entity.get())
+            .and_then(|settings| {
+                settings
+                    .tile_entity_parenting
+                    .parent(map_entity.get(), chunk_entity)
+            })
+    });
+
+    let mut spawned = Vec::with_capacity(saved.tile_entities.len());
+    for saved_entity in saved.tile_entities.iter() {
+        let mut components = Vec::with_capacity(saved_entity.components.len());
+        for component in saved_entity.components.iter() {
+            let mut deserializer = ron::Deserializer::from_str(component)?;
+            let component = UntypedReflectDeserializer::new(&registry)
+                .deserialize(&mut deserializer)
+                .map_err(|error| deserializer.span_error(error))?;
+            let type_path = component
+                .get_represented_type_info()
+                .map(|type_info| type_info.type_path())
+                .unwrap_or_else(|| component.reflect_type_path())
+                .to_string();
+            let reflect_component = registry
+                .get_with_type_path(&type_path)
+                .and_then(|registration| registration.data::<ReflectComponent>())
+                .ok_or(TileArchetypeError::ComponentNotRegistered(type_path))?;
+            components.push((reflect_component.clone(), component));
+        }
+
+        let mut entity_mut = world.spawn_empty();
+        for (reflect_component, component) in components.iter() {
+            reflect_component.insert(&mut entity_mut, component.as_ref(), &registry);
+        }
+        if let Some(parent) = parent {
+            entity_mut.set_parent(parent);
+        }
+        let tile_entity = entity_mut.id();
+
+        let mut chunk = world
+            .get_mut::<Chunk<MapChunk, TileData>>(chunk_entity)
+            .ok_or(TileArchetypeError::ChunkDoesNotExist)?;
+        chunk.set_tile_entity(saved_entity.map_layer, saved_entity.chunk_cell, tile_entity);
+        spawned.push(tile_entity);
+    }
+    Ok(spawned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{restore_chunk_tile_entities, save_chunk_tile_entities, TileEntityAllowlist};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::Chunk;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::reflect::{AppTypeRegistry, ReflectComponent};
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Component, Entity, With, World};
+    use bevy::reflect::Reflect;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq, Eq)]
+    #[reflect(Component)]
+    struct Door {
+        open: bool,
+    }
+
+    #[derive(Component)]
+    struct Highlighted;
+
+    #[test]
+    fn test_tile_entities_survive_save_and_restore() {
+        let mut world = World::new();
+        world.init_resource::<AppTypeRegistry>();
+        world
+            .resource::<AppTypeRegistry>()
+            .write()
+            .register::<Door>();
+        let allowlist = TileEntityAllowlist::new().with::<Door>();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(2, 1))
+            .unwrap();
+        system_state.apply(&mut world);
+        world
+            .entity_mut(tile_entity)
+            .insert((Door { open: true }, Highlighted));
+
+        let chunk_entity = world
+            .query_filtered::<Entity, With<Chunk<SquareChunkLayer<u8>, u8>>>()
+            .single(&world);
+        let saved =
+            save_chunk_tile_entities::<u8, SquareChunkLayer<u8>>(&world, chunk_entity, &allowlist)
+                .unwrap();
+        world.despawn(tile_entity);
+
+        let restored = restore_chunk_tile_entities::<u8, SquareChunkLayer<u8>>(
+            &mut world,
+            chunk_entity,
+            &saved,
+        )
+        .unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(world.get::<Door>(restored[0]), Some(&Door { open: true }));
+        assert!(world.get::<Highlighted>(restored[0]).is_none());
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(
+            tilemap_manager.get_tile_entity(Cell::new(2, 1)).unwrap(),
+            restored[0]
+        );
+    }
+}
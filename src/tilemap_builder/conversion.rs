@@ -0,0 +1,300 @@
+//! Resampling [`TilemapLayer`]s between square and hexagonal layouts, for example when porting a square map
+//! to a hex map.
+//!
+//! Every [`MapLayout`] places the cells of a layer in a shared layout space where each cell covers roughly one
+//! unit of area, with the layer starting at the origin and rows growing along +y. Converting a layer creates a layer
+//! of the target layout covering the same area and samples the source layer at the center of every target
+//! cell. Hex layers use the offset coordinates the [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder)
+//! expects for hex maps. Only tile data is converted, tile entities are left behind.
+
+#[cfg(feature = "hex")]
+use crate::hex::geometry::{HexGeometry, HEX_SPACING};
+#[cfg(feature = "hex")]
+use crate::hex::hex_offset_from_orientation;
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use bevy::math::{IVec2, UVec2, Vec2};
+use bevy::utils::hashbrown::HashMap;
+use lettuces::cell::Cell;
+#[cfg(feature = "hex")]
+use lettuces::HexOrientation;
+
+/// How the tile data of a target cell is picked from the source layer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ResamplingStrategy {
+    /// Use the source cell closest to the center of the target cell. Target cells past the edge of the
+    /// source layer use the closest cell on the edge so dense layers stay dense
+    #[default]
+    Nearest,
+    /// Use the source cell containing the center of the target cell. Target cells whose center lies outside
+    /// of the source layer are left empty
+    Center,
+}
+
+/// The way the cells of a map are laid out, used to resample layers between map types
+pub trait MapLayout {
+    /// Returns the position of the center of the given cell in layout space
+    fn cell_center(&self, cell: UVec2) -> Vec2;
+
+    /// Returns the cell containing the given position in layout space. The cell may lie outside of the map
+    fn cell_at(&self, position: Vec2) -> IVec2;
+
+    /// Returns the size of the area covered by a layer with the given dimensions in layout space
+    fn extent(&self, dimensions: UVec2) -> Vec2;
+
+    /// Returns the dimensions of a layer covering an area of the given size in layout space
+    fn dimensions_covering(&self, extent: Vec2) -> UVec2;
+}
+
+/// The layout of square maps. Every cell is a unit square
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SquareLayout;
+
+impl MapLayout for SquareLayout {
+    fn cell_center(&self, cell: UVec2) -> Vec2 {
+        cell.as_vec2() + Vec2::splat(0.5)
+    }
+
+    fn cell_at(&self, position: Vec2) -> IVec2 {
+        position.floor().as_ivec2()
+    }
+
+    fn extent(&self, dimensions: UVec2) -> Vec2 {
+        dimensions.as_vec2()
+    }
+
+    fn dimensions_covering(&self, extent: Vec2) -> UVec2 {
+        covering(extent)
+    }
+}
+
+/// The layout of hex maps. Hexes are one unit across their flat sides, odd rows of pointy hexes and odd
+/// columns of flat hexes are shifted by half a hex, matching
+/// [`hex_offset_from_orientation`](crate::hex::hex_offset_from_orientation)
+#[cfg(feature = "hex")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct HexLayout {
+    /// The orientation of the hexes
+    pub orientation: HexOrientation,
+}
+
+#[cfg(feature = "hex")]
+impl HexLayout {
+    /// Creates a new hex layout with the given orientation
+    pub fn new(orientation: HexOrientation) -> Self {
+        Self { orientation }
+    }
+
+    /// Swaps the axes of flat layouts so both orientations can share the math of pointy hexes
+    fn swap_flat_axes(&self, vec: Vec2) -> Vec2 {
+        match self.orientation {
+            HexOrientation::Pointy => vec,
+            HexOrientation::Flat => Vec2::new(vec.y, vec.x),
+        }
+    }
+
+    /// The geometry of unit hexes placing offset cell (0, 0) half a hex away from the origin on both axes
+    fn geometry(&self) -> HexGeometry {
+        HexGeometry::new(
+            self.swap_flat_axes(Vec2::new(0.5, 0.5 * HEX_SPACING)),
+            1.0,
+            self.orientation,
+        )
+    }
+}
+
+#[cfg(feature = "hex")]
+impl MapLayout for HexLayout {
+    fn cell_center(&self, cell: UVec2) -> Vec2 {
+        let offset_mode = hex_offset_from_orientation(self.orientation);
+        self.geometry().cell_center(Cell::from_offset_coordinates(
+            [cell.x as i32, cell.y as i32],
+            offset_mode,
+        ))
+    }
+
+    fn cell_at(&self, position: Vec2) -> IVec2 {
+        let offset_mode = hex_offset_from_orientation(self.orientation);
+        let [x, y] = self
+            .geometry()
+            .world_to_cell(position)
+            .to_offset_coordinates(offset_mode);
+        IVec2::new(x, y)
+    }
+
+    fn extent(&self, dimensions: UVec2) -> Vec2 {
+        let dimensions = self.swap_flat_axes(dimensions.as_vec2());
+        self.swap_flat_axes(Vec2::new(dimensions.x, dimensions.y * HEX_SPACING))
+    }
+
+    fn dimensions_covering(&self, extent: Vec2) -> UVec2 {
+        let extent = self.swap_flat_axes(extent);
+        let dimensions = covering(Vec2::new(extent.x, extent.y / HEX_SPACING));
+        self.swap_flat_axes(dimensions.as_vec2()).as_uvec2()
+    }
+}
+
+/// Returns the dimensions covering the extent, rounded to the closest whole cell
+fn covering(extent: Vec2) -> UVec2 {
+    let dimensions = extent.round().max(Vec2::ZERO).as_uvec2();
+    UVec2::new(
+        dimensions.x.max((extent.x > 0.0) as u32),
+        dimensions.y.max((extent.y > 0.0) as u32),
+    )
+}
+
+/// Resamples a layer laid out as `from` into a layer laid out as `to` covering the same area.
+///
+/// The new layer is dense if the source layer is dense or generated and every target cell received tile data, otherwise
+/// it is sparse.
+pub fn convert_layer<FromMap, ToMap, T>(
+    layer: &TilemapLayer<T>,
+    from: &FromMap,
+    to: &ToMap,
+    strategy: ResamplingStrategy,
+) -> TilemapLayer<T>
+where
+    FromMap: MapLayout,
+    ToMap: MapLayout,
+    T: Clone + Copy + Sized + Default + Send + Sync,
+{
+    let source_dimensions = layer.dimensions();
+    let extent = from.extent(source_dimensions);
+    let dimensions = to.dimensions_covering(extent);
+    let max_cell = source_dimensions.as_ivec2() - IVec2::ONE;
+
+    let mut tile_data = HashMap::new();
+    for y in 0..dimensions.y {
+        for x in 0..dimensions.x {
+            let center = to.cell_center(UVec2::new(x, y));
+            let source = match strategy {
+                ResamplingStrategy::Nearest => {
+                    let inset = Vec2::splat(0.001).min(extent / 2.0);
+                    Some(
+                        from.cell_at(center.clamp(inset, extent - inset))
+                            .clamp(IVec2::ZERO, max_cell.max(IVec2::ZERO)),
+                    )
+                }
+                ResamplingStrategy::Center => Some(from.cell_at(center))
+                    .filter(|cell| cell.cmpge(IVec2::ZERO).all() && cell.cmple(max_cell).all()),
+            };
+            if let Some(data) =
+                source.and_then(|source| layer.get_tile_data(Cell::new(source.x, source.y)))
+            {
+                tile_data.insert(Cell::new(x as i32, y as i32), data);
+            }
+        }
+    }
+
+    let filled = tile_data.len() == (dimensions.x * dimensions.y) as usize && dimensions.x > 0;
+    if matches!(layer, TilemapLayer::Dense(..) | TilemapLayer::Generated(..)) && filled {
+        let rows = (0..dimensions.y as i32)
+            .map(|y| {
+                (0..dimensions.x as i32)
+                    .map(|x| tile_data[&Cell::new(x, y)])
+                    .collect()
+            })
+            .collect();
+        return TilemapLayer::new_dense_from_vecs(rows);
+    }
+    TilemapLayer::new_sparse_from_hashmap(dimensions.x as usize, dimensions.y as usize, tile_data)
+}
+
+/// Resamples every layer of a map laid out as `from` into layers laid out as `to`, keeping the map layer each
+/// layer belongs to. See [`convert_layer`]
+pub fn convert_map<FromMap, ToMap, MapLayers, T>(
+    layers: impl IntoIterator<Item = (MapLayers, TilemapLayer<T>)>,
+    from: &FromMap,
+    to: &ToMap,
+    strategy: ResamplingStrategy,
+) -> Vec<(MapLayers, TilemapLayer<T>)>
+where
+    FromMap: MapLayout,
+    ToMap: MapLayout,
+    T: Clone + Copy + Sized + Default + Send + Sync,
+{
+    layers
+        .into_iter()
+        .map(|(map_layer, layer)| (map_layer, convert_layer(&layer, from, to, strategy)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{convert_layer, convert_map, MapLayout, ResamplingStrategy, SquareLayout};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::math::UVec2;
+    use lettuces::cell::Cell;
+
+    fn halves(width: usize, height: usize) -> TilemapLayer<u8> {
+        TilemapLayer::new_dense_from_vecs(vec![
+            (0..width)
+                .map(|x| if x < width / 2 { 1 } else { 2 })
+                .collect();
+            height
+        ])
+    }
+
+    #[test]
+    fn test_square_conversion() {
+        let layer = halves(4, 3);
+        let converted = convert_layer(
+            &layer,
+            &SquareLayout,
+            &SquareLayout,
+            ResamplingStrategy::Nearest,
+        );
+        assert!(matches!(converted, TilemapLayer::Dense(..)));
+        assert_eq!(converted.dimensions(), UVec2::new(4, 3));
+        for y in 0..3 {
+            for x in 0..4 {
+                let cell = Cell::new(x, y);
+                assert_eq!(converted.get_tile_data(cell), layer.get_tile_data(cell));
+            }
+        }
+    }
+
+    #[cfg(feature = "hex")]
+    #[test]
+    fn test_hex_conversion() {
+        use super::HexLayout;
+        use lettuces::HexOrientation;
+
+        for orientation in [HexOrientation::Pointy, HexOrientation::Flat] {
+            let layout = HexLayout::new(orientation);
+            for y in 0..6 {
+                for x in 0..6 {
+                    let cell = UVec2::new(x, y);
+                    assert_eq!(
+                        layout.cell_at(layout.cell_center(cell)),
+                        cell.as_ivec2(),
+                        "{orientation:?} {cell}"
+                    );
+                }
+            }
+        }
+
+        let pointy = HexLayout::new(HexOrientation::Pointy);
+        let hex = convert_layer(
+            &halves(10, 10),
+            &SquareLayout,
+            &pointy,
+            ResamplingStrategy::Nearest,
+        );
+        assert!(matches!(hex, TilemapLayer::Dense(..)));
+        assert_eq!(hex.dimensions(), UVec2::new(10, 12));
+        assert_eq!(hex.get_tile_data(Cell::new(1, 6)), Some(1));
+        assert_eq!(hex.get_tile_data(Cell::new(8, 6)), Some(2));
+
+        let converted = convert_map(
+            [(0u32, hex)],
+            &pointy,
+            &SquareLayout,
+            ResamplingStrategy::Center,
+        );
+        let (map_layer, square) = &converted[0];
+        assert_eq!(*map_layer, 0);
+        assert_eq!(square.dimensions(), UVec2::new(10, 10));
+        assert_eq!(square.get_tile_data(Cell::new(0, 0)), Some(1));
+        assert_eq!(square.get_tile_data(Cell::new(9, 9)), Some(2));
+    }
+}
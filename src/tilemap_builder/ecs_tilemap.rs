@@ -0,0 +1,144 @@
+//! Converting maps between the `bevy_ecs_tilemap` data model and [`TilemapLayer`]s, for porting existing maps
+//! while migrating. Requires the `ecs_tilemap` feature.
+//!
+//! `bevy_ecs_tilemap` stores a tile entity for every tile in a [`TileStorage`] on the tilemap entity, with the
+//! tile data spread over components of the tile entities. [`layer_from_tile_storage`] reads those components
+//! into a [`TilemapLayer`] that can be handed to a [`TilemapBuilder`], so map generation and save code
+//! written against `bevy_ecs_tilemap` keeps working until it is ported. [`spawn_tile_storage`] goes the other
+//! way for rendering a layer with `bevy_ecs_tilemap` in the meantime.
+//!
+//! Both crates put cell (0, 0) in the bottom left so a [`TilePos`] converts straight to a [`Cell`].
+//!
+//! ```ignore
+//! fn port_map(
+//!     mut commands: Commands,
+//!     tile_storages: Query<&TileStorage>,
+//!     tiles: Query<(&TileTextureIndex, &TileColor)>,
+//! ) {
+//!     let tile_storage = tile_storages.single();
+//!     let map_entity = tilemap_builder_from_tile_storage::<TileData, MapLayers, SquareChunkLayer<TileData>, _>(
+//!         tile_storage,
+//!         |tile| tiles.get(tile).ok().map(|(texture, color)| TileData::new(texture.0, color.0)),
+//!         SquareMapData { max_chunk_size: UVec2::splat(64) },
+//!         SquareChunkSettings { max_chunk_size: UVec2::splat(64) },
+//!     )
+//!     .spawn_tilemap(&mut commands);
+//! }
+//! ```
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_builder::TilemapBuilder;
+use bevy::prelude::{Commands, Entity, Query};
+use bevy::utils::hashbrown::HashMap;
+use bevy_ecs_tilemap::prelude::{
+    TileBundle, TilePos, TileStorage, TileTextureIndex, TilemapId, TilemapSize,
+};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// Creates a [`TilemapLayer`] from the tiles of a [`TileStorage`], reading the tile data of every tile entity
+/// with the given function.
+///
+/// The layer is dense if every position of the storage has a tile with tile data, otherwise it is sparse and
+/// only holds the tiles that do.
+pub fn layer_from_tile_storage<T>(
+    tile_storage: &TileStorage,
+    mut tile_data: impl FnMut(Entity) -> Option<T>,
+) -> TilemapLayer<T>
+where
+    T: Clone + Copy + Sized + Default + Send + Sync,
+{
+    let size = tile_storage.size;
+    let mut tiles = HashMap::new();
+    for y in 0..size.y {
+        for x in 0..size.x {
+            if let Some(data) = tile_storage.get(&TilePos { x, y }).and_then(&mut tile_data) {
+                tiles.insert(Cell::new(x as i32, y as i32), data);
+            }
+        }
+    }
+
+    if size.x == 0 || size.y == 0 || tiles.len() < (size.x * size.y) as usize {
+        return TilemapLayer::new_sparse_from_hashmap(size.x as usize, size.y as usize, tiles);
+    }
+    let rows = (0..size.y as i32)
+        .map(|y| {
+            (0..size.x as i32)
+                .map(|x| tiles[&Cell::new(x, y)])
+                .collect()
+        })
+        .collect();
+    TilemapLayer::new_dense_from_vecs(rows)
+}
+
+/// Creates a [`TilemapLayer`] holding the [`TileTextureIndex`] of every tile of a [`TileStorage`]
+pub fn texture_index_layer(
+    tile_storage: &TileStorage,
+    texture_indices: &Query<&TileTextureIndex>,
+) -> TilemapLayer<u32> {
+    layer_from_tile_storage(tile_storage, |tile| {
+        texture_indices.get(tile).ok().map(|index| index.0)
+    })
+}
+
+/// Creates a [`TilemapBuilder`] with the tiles of a [`TileStorage`] as the main layer. See
+/// [`layer_from_tile_storage`] for how the tile data is read
+pub fn tilemap_builder_from_tile_storage<TileData, MapLayers, MapChunk, MapType>(
+    tile_storage: &TileStorage,
+    tile_data: impl FnMut(Entity) -> Option<TileData>,
+    map_type: MapType,
+    chunk_settings: MapChunk::ChunkSettings,
+) -> TilemapBuilder<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData + Default + Send + Sync + 'static,
+{
+    TilemapBuilder::new(
+        layer_from_tile_storage(tile_storage, tile_data),
+        map_type,
+        chunk_settings,
+    )
+}
+
+/// Spawns a `bevy_ecs_tilemap` tile entity for every tile of the layer and returns the [`TileStorage`] holding
+/// them. The texture index of every tile is picked with the given function.
+///
+/// The tiles belong to the given tilemap entity, insert the returned storage on it together with the rest of
+/// the `bevy_ecs_tilemap` tilemap bundle.
+pub fn spawn_tile_storage<T>(
+    commands: &mut Commands,
+    tilemap_entity: Entity,
+    layer: &TilemapLayer<T>,
+    mut texture_index: impl FnMut(T) -> TileTextureIndex,
+) -> TileStorage
+where
+    T: Clone + Copy + Sized + Default + Send + Sync,
+{
+    let dimensions = layer.dimensions();
+    let mut tile_storage = TileStorage::empty(TilemapSize {
+        x: dimensions.x,
+        y: dimensions.y,
+    });
+    for y in 0..dimensions.y {
+        for x in 0..dimensions.x {
+            let Some(tile_data) = layer.get_tile_data(Cell::new(x as i32, y as i32)) else {
+                continue;
+            };
+            let position = TilePos { x, y };
+            let tile_entity = commands
+                .spawn(TileBundle {
+                    position,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    texture_index: texture_index(tile_data),
+                    ..Default::default()
+                })
+                .id();
+            tile_storage.set(&position, tile_entity);
+        }
+    }
+    tile_storage
+}
@@ -0,0 +1,349 @@
+//! Builds large tilemaps over several frames while reporting progress.
+//!
+//! [`TilemapBuilder::spawn_tilemap_incremental`] spawns the map entity immediately with a
+//! [`MapBuildProgress`] component and does the actual work in [`build_pending_tilemaps`], one layer or a
+//! budget of chunks per frame. Games can read [`MapBuildProgress`] or listen for [`MapBuildProgressed`] and
+//! [`MapBuildFinished`] to drive loading screens. The [`Tilemap`] is only inserted once every chunk is
+//! spawned, so the map can't be accessed through a [`TilemapManager`](crate::tilemap_manager::TilemapManager)
+//! until it is finished.
+
+use crate::map::chunk::{Chunk, ChunkLayer, Chunks};
+use crate::map::{MapData, MapLayer, MapVersion, Tilemap};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_builder::TilemapBuilder;
+use bevy::app::{App, Plugin, Update};
+use bevy::prelude::{BuildChildren, Commands, Component, Entity, Event, EventWriter, Query};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// The current phase of an incremental map build
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MapBuildPhase {
+    /// Splitting the layers of the map into chunks. One layer is processed per frame
+    ChunkingLayers,
+    /// Spawning the chunk entities of the map
+    SpawningChunks,
+    /// The map is fully built and has a [`Tilemap`]
+    Finished,
+}
+
+/// The progress of an incremental map build. Lives on the map entity
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapBuildProgress {
+    /// The current phase of the build
+    pub phase: MapBuildPhase,
+    /// How many layers have been split into chunks
+    pub layers_built: usize,
+    /// The total amount of layers in the map
+    pub total_layers: usize,
+    /// How many chunks have been spawned
+    pub chunks_built: usize,
+    /// The total amount of chunks in the map. 0 until the main layer has been chunked
+    pub total_chunks: usize,
+}
+
+impl MapBuildProgress {
+    /// Returns the overall progress of the build from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        if self.phase == MapBuildPhase::Finished {
+            return 1.0;
+        }
+        let total = self.total_layers + self.total_chunks;
+        if total == 0 {
+            return 0.0;
+        }
+        (self.layers_built + self.chunks_built) as f32 / total as f32
+    }
+}
+
+/// Event sent every frame an incremental map build makes progress
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapBuildProgressed {
+    /// The map entity being built
+    pub map_entity: Entity,
+    /// The progress after this frame
+    pub progress: MapBuildProgress,
+}
+
+/// Event sent when an incremental map build finishes and the [`Tilemap`] has been inserted
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MapBuildFinished {
+    /// The finished map entity
+    pub map_entity: Entity,
+}
+
+/// The remaining work of an incremental map build. Removed once the map is finished
+#[derive(Component)]
+pub struct PendingTilemapBuild<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData + Default + Send + Sync + 'static,
+{
+    builder: TilemapBuilder<TileData, MapLayers, MapChunk, MapType>,
+    main_layer: Option<TilemapLayer<TileData>>,
+    layers: Vec<(u64, TilemapLayer<TileData>)>,
+    chunks: Vec<Vec<Chunk<MapChunk, TileData>>>,
+    unspawned: Vec<(usize, usize, Chunk<MapChunk, TileData>)>,
+    chunk_entities: Vec<Vec<Entity>>,
+    chunks_per_frame: usize,
+}
+
+impl<TileData, MapLayers, MapChunk, MapType> TilemapBuilder<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData + Default + Send + Sync + 'static,
+{
+    /// Spawns the tilemap entity immediately and builds the map over the following frames, spawning at most
+    /// `chunks_per_frame` chunks each frame. Requires the [`MapBuildPlugin`].
+    ///
+    /// The returned entity has a [`MapBuildProgress`] tracking the build, which stays in the
+    /// [`MapBuildPhase::Finished`] phase once it is done.
+    #[must_use]
+    pub fn spawn_tilemap_incremental(
+        mut self,
+        commands: &mut Commands,
+        chunks_per_frame: usize,
+    ) -> Option<Entity> {
+        let main_layer = self.main_layer.take()?;
+        let layers: Vec<(u64, TilemapLayer<TileData>)> = self.layer_info.drain().collect();
+        let map_bundles = std::mem::take(&mut self.map_bundles);
+        let progress = MapBuildProgress {
+            phase: MapBuildPhase::ChunkingLayers,
+            layers_built: 0,
+            total_layers: layers.len() + 1,
+            chunks_built: 0,
+            total_chunks: 0,
+        };
+        let pending = PendingTilemapBuild {
+            builder: self,
+            main_layer: Some(main_layer),
+            layers,
+            chunks: vec![],
+            unspawned: vec![],
+            chunk_entities: vec![],
+            chunks_per_frame: chunks_per_frame.max(1),
+        };
+        let mut map_commands = commands.spawn((pending, progress));
+        for insert_bundle in map_bundles {
+            insert_bundle(&mut map_commands);
+        }
+        Some(map_commands.id())
+    }
+}
+
+/// Plugin that advances every [`PendingTilemapBuild`] of the given map type each frame
+pub struct MapBuildPlugin<TileData, MapLayers, MapChunk, MapType> {
+    ph: PhantomData<(TileData, MapLayers, MapChunk, MapType)>,
+}
+
+impl<TileData, MapLayers, MapChunk, MapType> Default
+    for MapBuildPlugin<TileData, MapLayers, MapChunk, MapType>
+{
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapLayers, MapChunk, MapType> Plugin
+    for MapBuildPlugin<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData + Default + Send + Sync + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<MapBuildProgressed>()
+            .add_event::<MapBuildFinished>()
+            .add_systems(
+                Update,
+                build_pending_tilemaps::<TileData, MapLayers, MapChunk, MapType>,
+            );
+    }
+}
+
+/// Does one frame of work on every [`PendingTilemapBuild`]
+#[allow(clippy::type_complexity)]
+pub fn build_pending_tilemaps<TileData, MapLayers, MapChunk, MapType>(
+    mut commands: Commands,
+    mut pending_builds: Query<(
+        Entity,
+        &mut PendingTilemapBuild<TileData, MapLayers, MapChunk, MapType>,
+        &mut MapBuildProgress,
+    )>,
+    mut build_progressed: EventWriter<MapBuildProgressed>,
+    mut build_finished: EventWriter<MapBuildFinished>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData + Default + Send + Sync + 'static,
+{
+    for (map_entity, mut pending, mut progress) in pending_builds.iter_mut() {
+        let pending = &mut *pending;
+        let max_chunk_size = pending.builder.map_type.max_chunk_size();
+        let chunk_settings = pending.builder.chunk_settings;
+
+        if let Some(main_layer) = pending.main_layer.take() {
+            pending.chunks = pending.builder.create_new_chunks_from_layer(
+                &main_layer,
+                chunk_settings,
+                max_chunk_size,
+            );
+            progress.layers_built += 1;
+            progress.total_chunks = pending.chunks.iter().map(|row| row.len()).sum();
+        } else if let Some((map_layer, layer)) = pending.layers.pop() {
+            pending.builder.add_layer_to_chunks(
+                map_layer,
+                &mut pending.chunks,
+                &layer,
+                max_chunk_size,
+            );
+            progress.layers_built += 1;
+        } else {
+            if progress.phase == MapBuildPhase::ChunkingLayers {
+                progress.phase = MapBuildPhase::SpawningChunks;
+                pending
+                    .builder
+                    .apply_chunk_storage_overrides(&mut pending.chunks);
+                pending.builder.apply_chunk_templates(&mut pending.chunks);
+                pending.builder.apply_layer_defaults(&mut pending.chunks);
+                pending.chunk_entities = pending
+                    .chunks
+                    .iter()
+                    .map(|row| vec![Entity::PLACEHOLDER; row.len()])
+                    .collect();
+                for (y, row) in std::mem::take(&mut pending.chunks).into_iter().enumerate() {
+                    for (x, chunk) in row.into_iter().enumerate() {
+                        pending.unspawned.push((y, x, chunk));
+                    }
+                }
+                pending.unspawned.reverse();
+            }
+
+            for _ in 0..pending.chunks_per_frame {
+                let Some((y, x, chunk)) = pending.unspawned.pop() else {
+                    break;
+                };
+                let chunk_pos = chunk.chunk_pos;
+                let dimensions = chunk.get_chunk_dimensions();
+                let mut chunk_commands = commands.spawn(chunk);
+                chunk_commands.set_parent(map_entity);
+                pending
+                    .builder
+                    .insert_chunk_bundles(chunk_pos, dimensions, &mut chunk_commands);
+                let chunk_entity = chunk_commands.id();
+                pending.chunk_entities[y][x] = chunk_entity;
+                progress.chunks_built += 1;
+            }
+
+            if pending.unspawned.is_empty() {
+                progress.phase = MapBuildPhase::Finished;
+                let chunks = Chunks::new(
+                    Chunks::new_chunk_entity_grid(std::mem::take(&mut pending.chunk_entities)),
+                    max_chunk_size,
+                );
+                commands
+                    .entity(map_entity)
+                    .insert((
+                        Tilemap::new(chunks).with_dynamic_layers(std::mem::take(
+                            &mut pending.builder.dynamic_layers,
+                        )),
+                        std::mem::take(&mut pending.builder.map_type),
+                        MapVersion::default(),
+                        std::mem::take(&mut pending.builder.render_hints),
+                    ))
+                    .remove::<PendingTilemapBuild<TileData, MapLayers, MapChunk, MapType>>();
+                build_finished.send(MapBuildFinished { map_entity });
+            }
+        }
+
+        build_progressed.send(MapBuildProgressed {
+            map_entity,
+            progress: *progress,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MapBuildProgressed;
+    use super::{build_pending_tilemaps, MapBuildFinished, MapBuildPhase, MapBuildProgress};
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::schedule::Schedule;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Secondary,
+    }
+
+    #[test]
+    fn test_incremental_build_reports_progress() {
+        let mut world = World::new();
+        world.init_resource::<Events<MapBuildProgressed>>();
+        world.init_resource::<Events<MapBuildFinished>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            build_pending_tilemaps::<u8, MapLayers, SquareChunkLayer<u8>, SquareMapData>,
+        );
+
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut world);
+        let mut commands = system_state.get_mut(&mut world);
+        let mut builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 6]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![2u8; 6]; 4]),
+            MapLayers::Secondary,
+        );
+        let map_entity = builder
+            .spawn_tilemap_incremental(&mut commands, 4)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let progress = |world: &World| *world.get::<MapBuildProgress>(map_entity).unwrap();
+        schedule.run(&mut world);
+        assert_eq!(progress(&world).phase, MapBuildPhase::ChunkingLayers);
+        assert_eq!(progress(&world).total_chunks, 6);
+        schedule.run(&mut world);
+        assert_eq!(progress(&world).layers_built, 2);
+        schedule.run(&mut world);
+        assert_eq!(progress(&world).phase, MapBuildPhase::SpawningChunks);
+        assert_eq!(progress(&world).chunks_built, 4);
+        schedule.run(&mut world);
+        assert_eq!(progress(&world).phase, MapBuildPhase::Finished);
+        assert_eq!(progress(&world).fraction(), 1.0);
+        assert_eq!(world.resource::<Events<MapBuildFinished>>().len(), 1);
+        assert_eq!(world.resource::<Events<MapBuildProgressed>>().len(), 4);
+
+        let mut system_state: SystemState<SquareTilemapManager<u8, MapLayers>> =
+            SystemState::new(&mut world);
+        let mut tilemap_manager = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 3)).unwrap(), 1);
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 3)).unwrap(), 2);
+    }
+}
@@ -1,13 +1,52 @@
+pub mod conversion;
+#[cfg(feature = "ecs_tilemap")]
+pub mod ecs_tilemap;
+mod incremental;
+mod preview;
+mod text_layer;
 pub mod tilemap_layer_builder;
 
-use crate::map::chunk::{Chunk, ChunkLayer, ChunkLayerType, Chunks};
-use crate::map::{MapData, MapLayer, Tilemap};
-use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
-use bevy::prelude::{BuildChildren, Commands, Entity, UVec2};
+pub use incremental::{
+    build_pending_tilemaps, MapBuildFinished, MapBuildPhase, MapBuildPlugin, MapBuildProgress,
+    MapBuildProgressed, PendingTilemapBuild,
+};
+pub use preview::MapDataPreview;
+pub use text_layer::TextLayerError;
+
+use crate::map::chunk::{
+    Chunk, ChunkCell, ChunkLayer, ChunkLayerType, ChunkPos, ChunkStorageOverride, ChunkStoragePool,
+    ChunkTemplates, Chunks, LayerStorage,
+};
+use crate::map::{
+    DynamicLayerId, DynamicLayerRegistry, LayerPersistence, LayerRenderHint, LayerRenderHints,
+    MapData, MapLayer, MapMarker, MapMask, MapVersion, Tilemap, TilemapHandle,
+};
+use crate::tilemap_builder::tilemap_layer_builder::{DenseTileSource, TilemapLayer};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::{BuildChildren, Bundle, Commands, Entity, UVec2};
 use bevy::utils::HashMap;
+use lettuces::cell::Cell;
 use std::hash::Hash;
 use std::marker::PhantomData;
 
+/// A [`ChunkStorageOverride`] for the inclusive rectangle of chunks between min and max
+struct ChunkStorageRegion<TileData> {
+    min: ChunkPos,
+    max: ChunkPos,
+    storage: ChunkStorageOverride,
+    is_default: fn(&TileData) -> bool,
+}
+
+/// Inserts a user bundle on a newly spawned chunk entity
+type ChunkBundleInserter = Box<dyn Fn(ChunkPos, UVec2, &mut EntityCommands) + Send + Sync>;
+
+/// Inserts a user bundle on a newly spawned tilemap entity
+type MapBundleInserter = Box<dyn FnOnce(&mut EntityCommands) + Send + Sync>;
+
+fn is_default<TileData: PartialEq + Default>(tile_data: &TileData) -> bool {
+    *tile_data == TileData::default()
+}
+
 /// Helper struct used to construct a new tilemap.
 pub struct TilemapBuilder<TileData, MapLayers, Chunk, MapType>
 where
@@ -17,10 +56,19 @@ where
     MapType: MapData + Default,
 {
     main_layer: Option<TilemapLayer<TileData>>,
-    layer_info: HashMap<u32, TilemapLayer<TileData>>,
+    layer_info: HashMap<u64, TilemapLayer<TileData>>,
+    dynamic_layers: DynamicLayerRegistry,
     map_size: UVec2,
     map_type: MapType,
     chunk_settings: Chunk::ChunkSettings,
+    chunk_storage_overrides: Vec<ChunkStorageRegion<TileData>>,
+    chunk_templates: bool,
+    storage_pool: Option<ChunkStoragePool<TileData>>,
+    render_hints: LayerRenderHints,
+    layer_persistence: LayerPersistence,
+    layer_defaults: HashMap<u64, TileData>,
+    chunk_bundles: Vec<ChunkBundleInserter>,
+    map_bundles: Vec<MapBundleInserter>,
     // All phantom data below
     td_phantom: PhantomData<TileData>,
     ml_phantom: PhantomData<MapLayers>,
@@ -39,9 +87,18 @@ where
         Self {
             main_layer: None,
             layer_info: Default::default(),
+            layer_defaults: Default::default(),
+            dynamic_layers: Default::default(),
             map_size: Default::default(),
             map_type: Default::default(),
             chunk_settings: MapChunk::ChunkSettings::default(),
+            chunk_storage_overrides: vec![],
+            chunk_templates: false,
+            storage_pool: None,
+            render_hints: Default::default(),
+            layer_persistence: Default::default(),
+            chunk_bundles: vec![],
+            map_bundles: vec![],
             td_phantom: PhantomData::default(),
             ml_phantom: PhantomData::default(),
             ct_phantom: PhantomData::default(),
@@ -59,22 +116,75 @@ where
     /// Converts all the data from the tilemap builder and spawns the tilemap returning the Tilemaps [`Entity`]
     #[must_use]
     pub fn spawn_tilemap(mut self, commands: &mut Commands) -> Option<Entity> {
-        let Some(layer) = self.main_layer.take() else {
-            return None;
-        };
+        let chunks = self.build_chunks()?;
+        Some(self.spawn_chunks(chunks, commands))
+    }
 
-        let mut chunks = self.create_new_chunks_from_layer(
-            &layer,
-            self.chunk_settings,
-            self.map_type.max_chunk_size(),
-        );
+    /// Spawns the tilemap like [`spawn_tilemap`](Self::spawn_tilemap), inserts the given [`MapMarker`] on the
+    /// tilemap entity, and returns a [`TilemapHandle`] used to get [`LayerHandle`](crate::map::LayerHandle)s
+    /// of its layers
+    #[must_use]
+    pub fn spawn_tilemap_with_marker<Marker>(
+        self,
+        commands: &mut Commands,
+        marker: Marker,
+    ) -> Option<TilemapHandle<Marker>>
+    where
+        Marker: MapMarker<Layers = MapLayers>,
+    {
+        let map_entity = self.spawn_tilemap(commands)?;
+        commands.entity(map_entity).insert(marker);
+        Some(TilemapHandle::new(map_entity))
+    }
 
-        let layers: Vec<(u32, TilemapLayer<TileData>)> = self.layer_info.drain().collect();
+    /// Splits every layer into chunks and applies the storage overrides. The layers are kept so the chunks
+    /// can be built again
+    fn build_chunks(&mut self) -> Option<Vec<Vec<Chunk<MapChunk, TileData>>>> {
+        let layer = self.main_layer.take()?;
+        let mut chunks = match self.storage_pool.is_some() {
+            // Chunks are created empty so the main layer is added from the pool like every other layer
+            true => {
+                let mut chunks = self.create_new_chunks_from_layer(
+                    &TilemapLayer::new_sparse_empty(
+                        self.map_size.x as usize,
+                        self.map_size.y as usize,
+                    ),
+                    self.chunk_settings,
+                    self.map_type.max_chunk_size(),
+                );
+                self.add_layer_to_chunks(
+                    MapLayers::default().to_bits(),
+                    &mut chunks,
+                    &layer,
+                    self.map_type.max_chunk_size(),
+                );
+                chunks
+            }
+            false => self.create_new_chunks_from_layer(
+                &layer,
+                self.chunk_settings,
+                self.map_type.max_chunk_size(),
+            ),
+        };
+        self.main_layer = Some(layer);
 
-        for (id, layer) in layers {
-            self.add_layer_to_chunks(id, &mut chunks, &layer, self.map_type.max_chunk_size())
+        let layers = std::mem::take(&mut self.layer_info);
+        for (id, layer) in layers.iter() {
+            self.add_layer_to_chunks(*id, &mut chunks, layer, self.map_type.max_chunk_size())
         }
+        self.layer_info = layers;
+        self.apply_chunk_storage_overrides(&mut chunks);
+        self.apply_chunk_templates(&mut chunks);
+        self.apply_layer_defaults(&mut chunks);
+        Some(chunks)
+    }
 
+    /// Spawns the given chunks and the tilemap entity holding them
+    fn spawn_chunks(
+        self,
+        mut chunks: Vec<Vec<Chunk<MapChunk, TileData>>>,
+        commands: &mut Commands,
+    ) -> Entity {
         let mut chunk_entities: Vec<Vec<Entity>> = vec![];
 
         let map_x = chunks[0].len();
@@ -82,8 +192,12 @@ where
         for y in 0..chunks.len() {
             let mut vec: Vec<Entity> = vec![];
             for _ in 0..map_x {
-                let entity = commands.spawn(chunks[y].remove(0)).id();
-                vec.push(entity);
+                let chunk = chunks[y].remove(0);
+                let chunk_pos = chunk.chunk_pos;
+                let dimensions = chunk.get_chunk_dimensions();
+                let mut chunk_commands = commands.spawn(chunk);
+                self.insert_chunk_bundles(chunk_pos, dimensions, &mut chunk_commands);
+                vec.push(chunk_commands.id());
             }
             chunk_entities.push(vec);
         }
@@ -99,11 +213,18 @@ where
             self.map_type.max_chunk_size(),
         );
 
-        let tilemap_entity = commands
-            .spawn((Tilemap::new(chunks), self.map_type))
-            .push_children(flattened_chunk_entities.as_slice())
-            .id();
-        Some(tilemap_entity)
+        let mut tilemap_commands = commands.spawn((
+            Tilemap::new(chunks).with_dynamic_layers(self.dynamic_layers),
+            self.map_type,
+            MapVersion::default(),
+            self.render_hints,
+            self.layer_persistence,
+        ));
+        tilemap_commands.push_children(flattened_chunk_entities.as_slice());
+        for insert_bundle in self.map_bundles {
+            insert_bundle(&mut tilemap_commands);
+        }
+        tilemap_commands.id()
     }
 
     /// Makes a new [`TilemapBuilder`] with the given [`TilemapLayer`] as the main layer.
@@ -116,9 +237,18 @@ where
         TilemapBuilder::<TileData, MapLayers, MapChunk, MapType> {
             main_layer: Some(layer_data),
             layer_info: Default::default(),
+            layer_defaults: Default::default(),
+            dynamic_layers: Default::default(),
             map_size: dimensions,
             map_type,
             chunk_settings,
+            chunk_storage_overrides: vec![],
+            chunk_templates: false,
+            storage_pool: None,
+            render_hints: Default::default(),
+            layer_persistence: Default::default(),
+            chunk_bundles: vec![],
+            map_bundles: vec![],
             td_phantom: Default::default(),
             ml_phantom: Default::default(),
             ct_phantom: PhantomData::default(),
@@ -135,6 +265,222 @@ where
         self.layer_info.insert(map_layer.to_bits(), layer_data);
     }
 
+    /// Registers a layer with the given name in the [`DynamicLayerRegistry`] of the tilemap, adds the given
+    /// [`TilemapLayer`] to it, and returns its id. See [`DynamicLayerId`] for details
+    pub fn add_dynamic_layer(
+        &mut self,
+        name: &str,
+        layer_data: TilemapLayer<TileData>,
+    ) -> DynamicLayerId {
+        assert_eq!(
+            self.map_size,
+            layer_data.dimensions(),
+            "New layers must be the same size as the map dimensions"
+        );
+        let id = self.dynamic_layers.register(name);
+        self.layer_info.insert(id.to_bits(), layer_data);
+        id
+    }
+
+    /// Sets the [`LayerRenderHint`] of the given [`MapLayer`]. The hints are inserted onto the map entity
+    /// as [`LayerRenderHints`] for rendering integrations to consume
+    pub fn set_layer_render_hint(&mut self, map_layer: MapLayers, hint: LayerRenderHint) {
+        self.render_hints.set(map_layer, hint);
+    }
+
+    /// Sets whether the given [`MapLayer`] is written to saves. The flags are inserted onto the map entity as
+    /// [`LayerPersistence`]
+    pub fn set_layer_persistent(&mut self, map_layer: MapLayers, persistent: bool) {
+        self.layer_persistence.set_persistent(map_layer, persistent);
+    }
+
+    /// Sets the data returned for cells of the given [`MapLayer`] that have no data instead of
+    /// [`TilemapManagerError::TileDataDoesNotExist`](crate::tilemap_manager::TilemapManagerError::TileDataDoesNotExist).
+    /// Useful for sparse layers that override a few cells of another layer
+    pub fn set_layer_default(&mut self, map_layer: MapLayers, tile_data: TileData) {
+        self.layer_defaults.insert(map_layer.to_bits(), tile_data);
+    }
+
+    /// Inserts the bundle returned by the given function on every chunk entity when it is spawned. The function
+    /// is passed the position and the dimensions of the chunk. Useful for adding transforms, visibility, or
+    /// render markers without a follow up pass over the chunks
+    pub fn with_chunk_bundle<B: Bundle>(
+        mut self,
+        bundle: impl Fn(ChunkPos, UVec2) -> B + Send + Sync + 'static,
+    ) -> Self {
+        self.chunk_bundles
+            .push(Box::new(move |chunk_pos, dimensions, chunk_commands| {
+                chunk_commands.insert(bundle(chunk_pos, dimensions));
+            }));
+        self
+    }
+
+    /// Inserts the given bundle on the tilemap entity when it is spawned
+    pub fn with_map_bundle(mut self, bundle: impl Bundle) -> Self {
+        self.map_bundles.push(Box::new(move |map_commands| {
+            map_commands.insert(bundle);
+        }));
+        self
+    }
+
+    /// Inserts the given [`MapMask`] on the tilemap entity, restricting the playable area of the map to the
+    /// cells set in it
+    pub fn with_map_mask(self, mask: MapMask) -> Self {
+        self.with_map_bundle(mask)
+    }
+
+    /// Inserts a [`MapMask`] on the tilemap entity where every cell the predicate returns true for is playable
+    pub fn with_map_mask_from_predicate(self, predicate: impl Fn(Cell) -> bool) -> Self {
+        let mask =
+            MapMask::from_predicate(self.map_size, self.map_type.max_chunk_size(), predicate);
+        self.with_map_mask(mask)
+    }
+
+    /// Inserts a [`MapMask`] on the tilemap entity from the alpha channel of an RGBA8 image with one pixel per
+    /// cell. Cells with an alpha above the threshold are playable. See [`MapMask::from_rgba8_alpha`]
+    pub fn with_map_mask_from_alpha(self, rgba: &[u8], threshold: u8) -> Self {
+        let mask = MapMask::from_rgba8_alpha(
+            self.map_size,
+            self.map_type.max_chunk_size(),
+            rgba,
+            threshold,
+        );
+        self.with_map_mask(mask)
+    }
+
+    /// Inserts every bundle added with [`Self::with_chunk_bundle`] on the given chunk entity
+    fn insert_chunk_bundles(
+        &self,
+        chunk_pos: ChunkPos,
+        dimensions: UVec2,
+        chunk_commands: &mut EntityCommands,
+    ) {
+        for insert_bundle in self.chunk_bundles.iter() {
+            insert_bundle(chunk_pos, dimensions, chunk_commands);
+        }
+    }
+
+    /// Overrides how every chunk in the inclusive rectangle between min and max stores its layers and tile
+    /// entities. Where regions overlap the override added last wins.
+    pub fn add_chunk_storage_override(
+        &mut self,
+        min: ChunkPos,
+        max: ChunkPos,
+        storage: ChunkStorageOverride,
+    ) where
+        TileData: PartialEq,
+    {
+        self.chunk_storage_overrides.push(ChunkStorageRegion {
+            min,
+            max,
+            storage,
+            is_default: is_default::<TileData>,
+        });
+    }
+
+    /// Applies every storage override added with [`Self::add_chunk_storage_override`] to the given chunks
+    pub fn apply_chunk_storage_overrides(&self, chunks: &mut [Vec<Chunk<MapChunk, TileData>>]) {
+        for region in self.chunk_storage_overrides.iter() {
+            for chunk_pos in ChunkPos::iter_rect(region.min, region.max) {
+                if chunk_pos.x() < 0 || chunk_pos.y() < 0 {
+                    continue;
+                }
+                let Some(chunk) = chunks
+                    .get_mut(chunk_pos.y() as usize)
+                    .and_then(|row| row.get_mut(chunk_pos.x() as usize))
+                else {
+                    continue;
+                };
+                let chunk_settings = chunk.chunk_settings;
+                for layer in chunk.data.values_mut() {
+                    let dimensions = layer.get_chunk_dimensions();
+                    let layer_type = match region.storage.layer_storage {
+                        _ if dimensions.x == 0 || dimensions.y == 0 => None,
+                        LayerStorage::Unchanged => None,
+                        LayerStorage::Dense => Some(ChunkLayerType::Dense(
+                            (0..dimensions.y as i32)
+                                .map(|y| {
+                                    (0..dimensions.x as i32)
+                                        .map(|x| {
+                                            layer
+                                                .get_tile_data(ChunkCell::new(x, y))
+                                                .cloned()
+                                                .unwrap_or_default()
+                                        })
+                                        .collect()
+                                })
+                                .collect(),
+                        )),
+                        LayerStorage::Sparse => Some(ChunkLayerType::Sparse(
+                            ChunkCell::iter_chunk(dimensions)
+                                .filter_map(|chunk_cell| {
+                                    layer
+                                        .get_tile_data(chunk_cell)
+                                        .filter(|tile_data| !(region.is_default)(tile_data))
+                                        .map(|tile_data| (chunk_cell, *tile_data))
+                                })
+                                .collect(),
+                        )),
+                    };
+                    if let Some(layer_type) = layer_type {
+                        let tile_entities: Vec<_> = ChunkCell::iter_chunk(dimensions)
+                            .filter_map(|chunk_cell| {
+                                layer
+                                    .get_tile_entity(chunk_cell)
+                                    .map(|entity| (chunk_cell, entity))
+                            })
+                            .collect();
+                        *layer = MapChunk::new(layer_type, dimensions, &chunk_settings);
+                        for (chunk_cell, entity) in tile_entities {
+                            layer.set_tile_entity(chunk_cell, entity);
+                        }
+                    }
+                    layer.set_tile_entity_storage(region.storage.tile_entity_storage);
+                }
+            }
+        }
+    }
+
+    /// Shares the tile data of dense chunk layers with identical contents between the chunks instead of
+    /// storing a copy in every chunk. Saves memory on maps built out of repeated chunks such as open ocean.
+    /// A chunk layer copies the shared data the first time it is written to. See
+    /// [`ChunkTemplate`](crate::map::chunk::ChunkTemplate) for more details
+    pub fn with_chunk_templates(mut self) -> Self {
+        self.chunk_templates = true;
+        self
+    }
+
+    /// Takes the storage of dense chunk layers from the given [`ChunkStoragePool`] instead of allocating it,
+    /// reusing the memory of maps released into the pool. See
+    /// [`ChunkStoragePool`](crate::map::chunk::ChunkStoragePool) for more details
+    pub fn with_storage_pool(mut self, pool: ChunkStoragePool<TileData>) -> Self {
+        self.storage_pool = Some(pool);
+        self
+    }
+
+    /// Replaces identical dense chunk layers with shared templates if enabled with
+    /// [`Self::with_chunk_templates`]
+    pub fn apply_chunk_templates(&self, chunks: &mut [Vec<Chunk<MapChunk, TileData>>]) {
+        if !self.chunk_templates {
+            return;
+        }
+        let mut templates = ChunkTemplates::default();
+        for chunk in chunks.iter_mut().flatten() {
+            for layer in chunk.data.values_mut() {
+                layer.share_templates(&mut templates);
+            }
+        }
+    }
+
+    /// Sets the defaults set with [`Self::set_layer_default`] on every chunk
+    pub fn apply_layer_defaults(&self, chunks: &mut [Vec<Chunk<MapChunk, TileData>>]) {
+        for chunk in chunks.iter_mut().flatten() {
+            for (map_layer, tile_data) in self.layer_defaults.iter() {
+                chunk.set_layer_default(*map_layer, Some(*tile_data));
+            }
+        }
+    }
+
     /// Function which creates new chunks and inserts the given tilemap layer into those chunks
     pub fn create_new_chunks_from_layer(
         &mut self,
@@ -172,13 +518,26 @@ where
                 );
                 chunks
             }
+            TilemapLayer::Generated(generator, entities) => {
+                let mut chunks = self.map_type.break_data_vecs_into_chunks(
+                    generator,
+                    max_chunk_size,
+                    chunk_settings,
+                );
+                self.map_type.add_entities_to_layer(
+                    MapLayers::default().to_bits(),
+                    &mut chunks,
+                    entities,
+                );
+                chunks
+            }
         };
     }
 
     /// Adds the given layer to the tilemap
     pub fn add_layer_to_chunks(
         &mut self,
-        map_layer: u32,
+        map_layer: u64,
         chunks: &mut Vec<Vec<Chunk<MapChunk, TileData>>>,
         tilemap_layer: &TilemapLayer<TileData>,
         max_chunk_size: UVec2,
@@ -203,35 +562,63 @@ where
                     .add_entities_to_layer(map_layer, chunks, entities);
             }
             TilemapLayer::Dense(data, entities) => {
-                for y in chunks.iter_mut() {
-                    for chunk in y.iter_mut() {
-                        let vec = self.map_type.break_data_vecs_down_into_chunk_data(
-                            &data,
-                            chunk.chunk_pos,
-                            max_chunk_size,
-                        );
-                        chunk.add_layer(map_layer, ChunkLayerType::Dense(vec));
-                    }
-                }
+                self.add_dense_layer_to_chunks(map_layer, chunks, data, max_chunk_size);
+                self.map_type
+                    .add_entities_to_layer(map_layer, chunks, entities);
+            }
+            TilemapLayer::Generated(generator, entities) => {
+                self.add_dense_layer_to_chunks(map_layer, chunks, generator, max_chunk_size);
                 self.map_type
                     .add_entities_to_layer(map_layer, chunks, entities);
             }
         }
     }
+
+    /// Adds the data of a dense layer to every chunk, reading only the cells of each chunk from the source
+    fn add_dense_layer_to_chunks(
+        &self,
+        map_layer: u64,
+        chunks: &mut [Vec<Chunk<MapChunk, TileData>>],
+        data: &(impl DenseTileSource<TileData> + ?Sized),
+        max_chunk_size: UVec2,
+    ) {
+        for chunk in chunks.iter_mut().flatten() {
+            let vec = self.map_type.break_data_vecs_down_into_chunk_data(
+                data,
+                chunk.chunk_pos,
+                max_chunk_size,
+            );
+            match &self.storage_pool {
+                Some(pool) => chunk.add_layer_pooled(map_layer, ChunkLayerType::Dense(vec), pool),
+                None => chunk.add_layer(map_layer, ChunkLayerType::Dense(vec)),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate as bevy_sparse_tilemap;
 
+    use crate::map::chunk::{
+        Chunk, ChunkPos, ChunkStorageOverride, ChunkStoragePool, LayerStorage, TileEntityStorage,
+    };
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkLayerData};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
     use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
     use crate::tilemap_builder::TilemapBuilder;
     use crate::tilemap_manager::TilemapManager;
-    use bevy::ecs::system::SystemState;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::ecs::system::{Commands, SystemState};
     use bevy::math::UVec2;
-    use bevy::prelude::World;
+    use bevy::prelude::{Component, World};
     use bevy::utils::hashbrown::HashMap;
     use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
 
     #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
     struct TileData(u8);
@@ -244,4 +631,255 @@ mod tests {
     }
 
     pub struct MainMap;
+
+    #[test]
+    fn test_chunk_storage_overrides() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tile_data = vec![vec![0u8; 4]; 4];
+        tile_data[0][0] = 1;
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tile_data),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        tilemap_builder.add_chunk_storage_override(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(0, 0),
+            ChunkStorageOverride {
+                layer_storage: LayerStorage::Sparse,
+                tile_entity_storage: TileEntityStorage::Dense,
+            },
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 1);
+        assert!(matches!(
+            tilemap_manager.get_tile_data(Cell::new(1, 1)),
+            Err(TilemapManagerError::TileDataDoesNotExist)
+        ));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 1)).unwrap(), 0);
+
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(1, 0))
+            .unwrap();
+        assert_eq!(
+            tilemap_manager.get_tile_entity(Cell::new(1, 0)).unwrap(),
+            tile_entity
+        );
+    }
+
+    #[test]
+    fn test_chunk_templates() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tile_data = vec![vec![3u8; 6]; 2];
+        tile_data[1][5] = 4;
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tile_data),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .with_chunk_templates()
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let shared_counts = |world: &mut World| {
+            let mut counts: Vec<(ChunkPos, usize)> = world
+                .query::<&Chunk<SquareChunkLayer<u8>, u8>>()
+                .iter(world)
+                .map(|chunk| {
+                    let count = match chunk.data[&MapLayers::Main.to_bits()].layer_data() {
+                        SquareChunkLayerData::Template(template) => template.shared_count(),
+                        _ => 0,
+                    };
+                    (chunk.chunk_pos, count)
+                })
+                .collect();
+            counts.sort_by_key(|(chunk_pos, _)| chunk_pos.x());
+            counts
+                .into_iter()
+                .map(|(_, count)| count)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(shared_counts(&mut world), vec![2, 2, 1]);
+
+        // Writing copies the template so the other chunk keeps its data
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager
+            .sets_tile_data(9, Cell::new(0, 0))
+            .expect("cell is on the map");
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 9);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 0)).unwrap(), 3);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 1)).unwrap(), 4);
+        system_state.apply(&mut world);
+        assert_eq!(shared_counts(&mut world), vec![0, 1, 1]);
+    }
+
+    #[derive(Component)]
+    struct ChunkMarker(ChunkPos, UVec2);
+
+    #[derive(Component)]
+    struct MapMarker;
+
+    #[test]
+    fn test_spawn_bundles() {
+        let mut world = World::new();
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut world);
+        let mut commands = system_state.get_mut(&mut world);
+
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 3]; 2]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .with_chunk_bundle(ChunkMarker)
+        .with_map_bundle(MapMarker)
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        assert!(world.get::<MapMarker>(map_entity).is_some());
+        let mut markers: Vec<(i32, i32, u32)> = world
+            .query::<(&ChunkMarker, &Chunk<SquareChunkLayer<u8>, u8>)>()
+            .iter(&world)
+            .map(|(marker, chunk)| {
+                assert_eq!(marker.0, chunk.chunk_pos);
+                assert_eq!(marker.1, chunk.get_chunk_dimensions());
+                (marker.0.x(), marker.0.y(), marker.1.x)
+            })
+            .collect();
+        markers.sort();
+        assert_eq!(markers, vec![(0, 0, 2), (1, 0, 1)]);
+    }
+
+    #[test]
+    fn test_storage_pool() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let pool = ChunkStoragePool::<u8>::default();
+
+        let spawn_map =
+            |world: &mut World,
+             system_state: &mut SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)>,
+             tile_data: u8| {
+                let (mut commands, _) = system_state.get_mut(world);
+                let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+                    TilemapLayer::new_dense_from_vecs(vec![vec![tile_data; 4]; 4]),
+                    SquareMapData {
+                        max_chunk_size: UVec2::new(2, 2),
+                    },
+                    SquareChunkSettings {
+                        max_chunk_size: UVec2::new(2, 2),
+                    },
+                )
+                .with_storage_pool(pool.clone());
+                tilemap_builder.add_layer(
+                    TilemapLayer::new_dense_from_vecs(vec![vec![tile_data + 1; 4]; 4]),
+                    MapLayers::Secondary,
+                );
+                let map_entity = tilemap_builder
+                    .spawn_tilemap(&mut commands)
+                    .expect("map has a main layer");
+                system_state.apply(world);
+                map_entity
+            };
+
+        let first = spawn_map(&mut world, &mut system_state, 1);
+        assert_eq!(pool.reused_and_allocated(), (0, 8));
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(first);
+        tilemap_manager.despawn_tilemap_pooled(&pool).unwrap();
+        system_state.apply(&mut world);
+        assert!(world.get_entity(first).is_none());
+        assert_eq!(pool.len(), 8);
+
+        // The second map reuses every buffer of the first
+        let second = spawn_map(&mut world, &mut system_state, 5);
+        assert_eq!(pool.reused_and_allocated(), (8, 8));
+        assert!(pool.is_empty());
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(second);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 5);
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 2)).unwrap(), 6);
+
+        pool.release(vec![0; 16]);
+        pool.clear();
+        assert_eq!(pool.heap_size(), 0);
+    }
+
+    #[test]
+    fn test_generated_layers() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let generated = Arc::new(AtomicUsize::new(0));
+        let counter = generated.clone();
+        let size = UVec2::new(5, 3);
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_generated(size, move |cell| {
+                counter.fetch_add(1, Ordering::Relaxed);
+                (cell.x * 10 + cell.y) as u8
+            }),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        tilemap_builder.add_layer(
+            TilemapLayer::new_generated(size, |cell| (cell.x + cell.y) as u8),
+            MapLayers::Secondary,
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        // Every cell is generated exactly once, straight into its chunk
+        assert_eq!(generated.load(Ordering::Relaxed), 15);
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), size);
+        for cell in [Cell::new(0, 0), Cell::new(4, 2), Cell::new(3, 1)] {
+            assert_eq!(
+                tilemap_manager.get_tile_data(cell).unwrap(),
+                (cell.x * 10 + cell.y) as u8
+            );
+        }
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 2)).unwrap(), 6);
+    }
 }
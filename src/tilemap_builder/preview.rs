@@ -0,0 +1,218 @@
+//! Building maps in memory so generated maps can be inspected, tested, and compared without a [`World`].
+//!
+//! [`TilemapBuilder::build_data`] splits every layer into chunks exactly like
+//! [`TilemapBuilder::spawn_tilemap`] but returns them as a [`MapDataPreview`] instead of spawning them. The
+//! builder keeps its layers so generation tuning loops can tweak a layer and build again. Once a preview looks
+//! right [`TilemapBuilder::spawn_from_preview`] spawns it without splitting the layers a second time.
+//!
+//! [`World`]: bevy::prelude::World
+
+use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
+use crate::map::{LayerRenderHints, MapData, MapLayer};
+use crate::tilemap_builder::TilemapBuilder;
+use bevy::math::UVec2;
+use bevy::prelude::{Commands, Entity};
+use lettuces::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+/// A map built by [`TilemapBuilder::build_data`] with the data of every chunk computed but no entities
+/// spawned
+pub struct MapDataPreview<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    chunks: Vec<Vec<Chunk<MapChunk, TileData>>>,
+    map_type: MapType,
+    render_hints: LayerRenderHints,
+    ph: PhantomData<MapLayers>,
+}
+
+impl<TileData, MapLayers, MapChunk, MapType> MapDataPreview<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData,
+{
+    /// Returns the map data the map will be spawned with
+    pub fn map_type(&self) -> &MapType {
+        &self.map_type
+    }
+
+    /// Returns the render hints the map will be spawned with
+    pub fn render_hints(&self) -> &LayerRenderHints {
+        &self.render_hints
+    }
+
+    /// Returns the amount of chunks along each axis
+    pub fn chunk_counts(&self) -> UVec2 {
+        UVec2::new(
+            self.chunks.first().map(Vec::len).unwrap_or_default() as u32,
+            self.chunks.len() as u32,
+        )
+    }
+
+    /// Returns the chunk at the given [`ChunkPos`]
+    pub fn get_chunk(&self, chunk_pos: ChunkPos) -> Option<&Chunk<MapChunk, TileData>> {
+        if chunk_pos.x() < 0 || chunk_pos.y() < 0 {
+            return None;
+        }
+        self.chunks
+            .get(chunk_pos.y() as usize)
+            .and_then(|row| row.get(chunk_pos.x() as usize))
+    }
+
+    /// Iterates over every chunk row by row
+    pub fn iter_chunks(&self) -> impl Iterator<Item = &Chunk<MapChunk, TileData>> {
+        self.chunks.iter().flatten()
+    }
+
+    /// Returns the tile data of the given layer at the given [`Cell`] if it exists
+    pub fn get_tile_data(&self, map_layer: MapLayers, cell: Cell) -> Option<TileData> {
+        self.get_chunk(self.map_type.into_chunk_pos(cell))?
+            .get_tile_data_from_cell(map_layer, cell)
+    }
+
+    /// Returns the position of every chunk whose content differs from the chunk at the same position in the
+    /// other preview, including chunks that only exist in one of them. Chunks are compared by their hash
+    pub fn changed_chunks(&self, other: &Self) -> Vec<ChunkPos> {
+        let counts = self.chunk_counts().max(other.chunk_counts());
+        ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(counts.x as i32 - 1, counts.y as i32 - 1),
+        )
+        .filter(|chunk_pos| {
+            self.get_chunk(*chunk_pos).map(chunk_hash)
+                != other.get_chunk(*chunk_pos).map(chunk_hash)
+        })
+        .collect()
+    }
+}
+
+fn chunk_hash<T: Hash>(chunk: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    chunk.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl<TileData, MapLayers, MapChunk, MapType> TilemapBuilder<TileData, MapLayers, MapChunk, MapType>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    MapType: MapData + Default + Send + Sync + 'static,
+{
+    /// Splits every layer into chunks without spawning anything. Returns `None` if the builder has no main
+    /// layer. The builder keeps its layers so it can build or spawn again
+    pub fn build_data(&mut self) -> Option<MapDataPreview<TileData, MapLayers, MapChunk, MapType>>
+    where
+        MapType: Clone,
+    {
+        let chunks = self.build_chunks()?;
+        Some(MapDataPreview {
+            chunks,
+            map_type: self.map_type.clone(),
+            render_hints: self.render_hints.clone(),
+            ph: PhantomData,
+        })
+    }
+
+    /// Spawns a map built with [`Self::build_data`], inserting the bundles added to this builder, and returns
+    /// the tilemap [`Entity`]
+    pub fn spawn_from_preview(
+        mut self,
+        preview: MapDataPreview<TileData, MapLayers, MapChunk, MapType>,
+        commands: &mut Commands,
+    ) -> Entity {
+        self.map_type = preview.map_type;
+        self.render_hints = preview.render_hints;
+        self.spawn_chunks(preview.chunks, commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::map::Tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Component, World};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Overlay,
+    }
+
+    #[derive(Component)]
+    struct MapMarker;
+
+    fn builder(value: u8) -> SquareTilemapBuilder<u8, MapLayers> {
+        let mut builder = SquareTilemapBuilder::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![value; 6]; 6]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+        );
+        builder.add_layer(TilemapLayer::new_sparse_empty(6, 6), MapLayers::Overlay);
+        builder
+    }
+
+    #[test]
+    fn test_preview() {
+        let mut first = builder(1);
+        let preview = first.build_data().unwrap();
+        assert_eq!(preview.chunk_counts(), UVec2::new(2, 2));
+        assert_eq!(
+            preview.get_tile_data(MapLayers::Main, Cell::new(4, 5)),
+            Some(1)
+        );
+        assert_eq!(
+            preview.get_tile_data(MapLayers::Overlay, Cell::new(4, 5)),
+            None
+        );
+        assert!(preview
+            .changed_chunks(&first.build_data().unwrap())
+            .is_empty());
+
+        let mut second = builder(1);
+        second
+            .main_layer
+            .as_mut()
+            .unwrap()
+            .set_tile_data(Cell::new(4, 1), 2);
+        assert_eq!(
+            preview.changed_chunks(&second.build_data().unwrap()),
+            vec![ChunkPos::new(1, 0)]
+        );
+
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = first
+            .with_map_bundle(MapMarker)
+            .spawn_from_preview(preview, &mut commands);
+        system_state.apply(&mut world);
+
+        assert!(world.get::<MapMarker>(map_entity).is_some());
+        assert!(world.get::<Tilemap>(map_entity).is_some());
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 5)).unwrap(), 1);
+    }
+}
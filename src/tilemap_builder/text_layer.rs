@@ -0,0 +1,248 @@
+//! Building [`TilemapLayer`]s out of text so small levels and test fixtures can be written inline.
+//!
+//! ASCII layers use one character per cell and one line per row, CSV layers one comma separated value per
+//! cell. The first row of the text is row 0 of the layer. Blank lines are skipped and every line is trimmed
+//! so layers can be written in indented string literals.
+//!
+//! ```
+//! # use bevy_sparse_tilemap::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+//! # use bevy_sparse_tilemap::lettuces::cell::Cell;
+//! let layer = TilemapLayer::from_ascii(
+//!     "
+//!     WWW
+//!     W.W
+//!     ",
+//!     |tile| tile == 'W',
+//! )
+//! .unwrap();
+//! assert_eq!(layer.get_tile_data(Cell::new(1, 1)), Some(false));
+//! assert_eq!(layer.to_ascii(|wall| if wall == Some(true) { 'W' } else { '.' }), "WWW\nW.W");
+//! ```
+
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use bevy::math::UVec2;
+use bevy::utils::hashbrown::HashMap;
+use lettuces::cell::Cell;
+
+/// Errors returned when building a [`TilemapLayer`] out of text
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum TextLayerError {
+    /// The text has no rows
+    #[error("The text has no rows")]
+    Empty,
+
+    /// A row has a different length than the first row
+    #[error("Row {row} has {found} cells but the first row has {expected}")]
+    RaggedRow {
+        /// The index of the row
+        row: usize,
+        /// The amount of cells in the first row
+        expected: usize,
+        /// The amount of cells in the row
+        found: usize,
+    },
+
+    /// A CSV value couldn't be turned into tile data
+    #[error("Invalid value {value:?} at {cell:?}")]
+    InvalidValue {
+        /// The cell of the value
+        cell: Cell,
+        /// The value
+        value: String,
+    },
+}
+
+impl<T> TilemapLayer<T>
+where
+    T: Clone + Copy + Sized + Default + Send + Sync,
+{
+    /// Creates a new [`TilemapLayer::Dense`] out of text with one character per cell. `tile` turns each
+    /// character into tile data.
+    pub fn from_ascii(text: &str, tile: impl Fn(char) -> T) -> Result<Self, TextLayerError> {
+        let rows = text_rows(text, |line| line.chars().map(&tile).collect())?;
+        Ok(Self::new_dense_from_vecs(rows))
+    }
+
+    /// Creates a new [`TilemapLayer::Sparse`] out of text with one character per cell. Cells `tile` returns
+    /// `None` for are left empty.
+    pub fn from_ascii_sparse(
+        text: &str,
+        tile: impl Fn(char) -> Option<T>,
+    ) -> Result<Self, TextLayerError> {
+        let rows = text_rows(text, |line| line.chars().map(&tile).collect())?;
+        Ok(sparse_from_rows(rows))
+    }
+
+    /// Creates a new [`TilemapLayer::Dense`] out of comma separated values with one value per cell. `tile`
+    /// turns each trimmed value into tile data or returns `None` if the value is invalid.
+    pub fn from_csv(text: &str, tile: impl Fn(&str) -> Option<T>) -> Result<Self, TextLayerError> {
+        let rows = text_rows(text, |line| {
+            line.split(',')
+                .map(|value| value.trim().to_string())
+                .collect()
+        })?;
+        let mut tile_data = Vec::with_capacity(rows.len());
+        for (y, row) in rows.into_iter().enumerate() {
+            let row = row
+                .into_iter()
+                .enumerate()
+                .map(|(x, value)| {
+                    tile(&value).ok_or(TextLayerError::InvalidValue {
+                        cell: Cell::new(x as i32, y as i32),
+                        value,
+                    })
+                })
+                .collect::<Result<Vec<T>, TextLayerError>>()?;
+            tile_data.push(row);
+        }
+        Ok(Self::new_dense_from_vecs(tile_data))
+    }
+
+    /// Writes the layer as text with one character per cell and one line per row. `tile` receives `None`
+    /// for empty cells of sparse layers.
+    pub fn to_ascii(&self, tile: impl Fn(Option<T>) -> char) -> String {
+        self.text_rows(|tile_data| tile(tile_data).to_string(), "")
+    }
+
+    /// Writes the layer as comma separated values with one value per cell and one line per row. `tile`
+    /// receives `None` for empty cells of sparse layers.
+    pub fn to_csv(&self, tile: impl Fn(Option<T>) -> String) -> String {
+        self.text_rows(tile, ",")
+    }
+
+    fn text_rows(&self, tile: impl Fn(Option<T>) -> String, separator: &str) -> String {
+        let dimensions = self.dimensions();
+        (0..dimensions.y as i32)
+            .map(|y| {
+                (0..dimensions.x as i32)
+                    .map(|x| tile(self.get_tile_data(Cell::new(x, y))))
+                    .collect::<Vec<String>>()
+                    .join(separator)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+/// Splits the text into trimmed non blank lines, turns each into a row, and checks that every row has the
+/// same length
+fn text_rows<V>(text: &str, row: impl Fn(&str) -> Vec<V>) -> Result<Vec<Vec<V>>, TextLayerError> {
+    let rows: Vec<Vec<V>> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(row)
+        .collect();
+    let Some(expected) = rows.first().map(Vec::len) else {
+        return Err(TextLayerError::Empty);
+    };
+    if let Some((index, found)) = rows
+        .iter()
+        .map(Vec::len)
+        .enumerate()
+        .find(|(_, found)| *found != expected)
+    {
+        return Err(TextLayerError::RaggedRow {
+            row: index,
+            expected,
+            found,
+        });
+    }
+    Ok(rows)
+}
+
+fn sparse_from_rows<T>(rows: Vec<Vec<Option<T>>>) -> TilemapLayer<T>
+where
+    T: Clone + Copy + Sized + Default + Send + Sync,
+{
+    let dimensions = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+    let tile_data: HashMap<Cell, T> = rows
+        .into_iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.into_iter()
+                .enumerate()
+                .filter_map(move |(x, tile_data)| {
+                    tile_data.map(|tile_data| (Cell::new(x as i32, y as i32), tile_data))
+                })
+        })
+        .collect();
+    TilemapLayer::new_sparse_from_hashmap(dimensions.x as usize, dimensions.y as usize, tile_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextLayerError;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::math::UVec2;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_ascii_layers() {
+        let layer = TilemapLayer::from_ascii(
+            "
+            #..#
+            #~~#
+            ",
+            |tile| match tile {
+                '#' => 1u8,
+                '~' => 2,
+                _ => 0,
+            },
+        )
+        .unwrap();
+        assert_eq!(layer.dimensions(), UVec2::new(4, 2));
+        assert_eq!(layer.get_tile_data(Cell::new(1, 1)), Some(2));
+        assert_eq!(layer.get_tile_data(Cell::new(1, 0)), Some(0));
+        assert_eq!(
+            layer.to_ascii(|tile| match tile {
+                Some(1) => '#',
+                Some(2) => '~',
+                _ => '.',
+            }),
+            "#..#\n#~~#"
+        );
+
+        let sparse =
+            TilemapLayer::from_ascii_sparse("..x\nx..", |tile| (tile == 'x').then_some(7u8))
+                .unwrap();
+        assert!(matches!(sparse, TilemapLayer::Sparse(..)));
+        assert_eq!(sparse.get_tile_data(Cell::new(2, 0)), Some(7));
+        assert_eq!(sparse.get_tile_data(Cell::new(1, 0)), None);
+        assert_eq!(
+            sparse.to_ascii(|tile| if tile.is_some() { 'x' } else { '.' }),
+            "..x\nx.."
+        );
+
+        assert_eq!(
+            TilemapLayer::from_ascii("##\n#", |_| 0u8).unwrap_err(),
+            TextLayerError::RaggedRow {
+                row: 1,
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!(
+            TilemapLayer::from_ascii("  \n", |_| 0u8).unwrap_err(),
+            TextLayerError::Empty
+        );
+    }
+
+    #[test]
+    fn test_csv_layers() {
+        let layer = TilemapLayer::from_csv("1, 2, 3\n4, 5, 6\n", |value| value.parse::<u16>().ok())
+            .unwrap();
+        assert_eq!(layer.get_tile_data(Cell::new(2, 1)), Some(6));
+        assert_eq!(
+            layer.to_csv(|tile| tile.unwrap_or_default().to_string()),
+            "1,2,3\n4,5,6"
+        );
+        assert_eq!(
+            TilemapLayer::from_csv("1,x", |value| value.parse::<u16>().ok()).unwrap_err(),
+            TextLayerError::InvalidValue {
+                cell: Cell::new(1, 0),
+                value: "x".to_string()
+            }
+        );
+    }
+}
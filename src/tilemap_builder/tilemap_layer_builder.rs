@@ -1,10 +1,13 @@
-﻿//! This module is specifically for making Tilemaps and helps to give ways to make Tilemap global layers
+//! This module is specifically for making Tilemaps and helps to give ways to make Tilemap global layers
 //! and then convert those into chunks
 
 use bevy::math::UVec2;
 use bevy::prelude::{Bundle, Commands, Entity};
+use bevy::tasks::{ComputeTaskPool, TaskPool};
 use bevy::utils::hashbrown::HashMap;
 use lettuces::cell::Cell;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
 
 /// An enum that holds all the data for a tilemap layer. This layer is only used in the [`TilemapBuilder`](crate::tilemap_builder::TilemapBuilder)
 ///
@@ -25,6 +28,90 @@ where
     Sparse(HashMap<Cell, T>, UVec2, HashMap<Cell, Entity>),
     /// A layer where ***EVERY***  position on the chunk must have data
     Dense(Vec<Vec<T>>, HashMap<Cell, Entity>),
+    /// A layer where ***EVERY*** position on the chunk has data returned by a [`TileGenerator`]
+    ///
+    /// The tile data is generated chunk by chunk straight into the storage of each chunk when the map is
+    /// built, so the whole layer never has to exist as a [`Vec<Vec<T>>`]. Writing to the layer with
+    /// [`TilemapLayer::set_tile_data`] turns it into a [`TilemapLayer::Dense`] layer.
+    Generated(TileGenerator<T>, HashMap<Cell, Entity>),
+}
+
+/// A source of the tile data of every cell of a dense layer. Read chunk by chunk by
+/// [`MapData::break_data_vecs_into_chunks`](crate::map::MapData::break_data_vecs_into_chunks) while the
+/// layer is split into chunks
+pub trait DenseTileSource<T> {
+    /// Returns the size of the layer
+    fn dimensions(&self) -> UVec2;
+
+    /// Returns the tile data of the cell in the given column and row. Only called for cells inside of the
+    /// layers dimensions
+    fn tile_data(&self, x: usize, y: usize) -> T;
+}
+
+impl<T: Copy> DenseTileSource<T> for Vec<Vec<T>> {
+    fn dimensions(&self) -> UVec2 {
+        UVec2::new(self.first().map_or(0, Vec::len) as u32, self.len() as u32)
+    }
+
+    fn tile_data(&self, x: usize, y: usize) -> T {
+        self[y][x]
+    }
+}
+
+/// A function returning the tile data of every [`Cell`] of a [`TilemapLayer::Generated`] layer of the given
+/// size
+#[derive(Clone)]
+pub struct TileGenerator<T> {
+    dimensions: UVec2,
+    generate: Arc<dyn Fn(Cell) -> T + Send + Sync>,
+}
+
+impl<T> TileGenerator<T> {
+    /// Creates a new generator of the given size
+    pub fn new(dimensions: UVec2, generate: impl Fn(Cell) -> T + Send + Sync + 'static) -> Self {
+        Self {
+            dimensions,
+            generate: Arc::new(generate),
+        }
+    }
+
+    /// Returns the tile data of the given [`Cell`], or `None` if it is outside of the generators dimensions
+    pub fn get_tile_data(&self, cell: Cell) -> Option<T> {
+        (cell.x >= 0
+            && cell.y >= 0
+            && (cell.x as u32) < self.dimensions.x
+            && (cell.y as u32) < self.dimensions.y)
+            .then(|| (self.generate)(cell))
+    }
+
+    /// Generates every row of tile data
+    pub fn generate_rows(&self) -> Vec<Vec<T>> {
+        (0..self.dimensions.y as i32)
+            .map(|y| {
+                (0..self.dimensions.x as i32)
+                    .map(|x| (self.generate)(Cell::new(x, y)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<T> DenseTileSource<T> for TileGenerator<T> {
+    fn dimensions(&self) -> UVec2 {
+        self.dimensions
+    }
+
+    fn tile_data(&self, x: usize, y: usize) -> T {
+        (self.generate)(Cell::new(x as i32, y as i32))
+    }
+}
+
+impl<T> Debug for TileGenerator<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TileGenerator")
+            .field("dimensions", &self.dimensions)
+            .finish_non_exhaustive()
+    }
 }
 
 impl<T> Default for TilemapLayer<T>
@@ -45,6 +132,7 @@ where
         match self {
             TilemapLayer::Sparse(_, dimensions, ..) => *dimensions,
             TilemapLayer::Dense(data, ..) => UVec2::new(data[0].len() as u32, data.len() as u32),
+            TilemapLayer::Generated(generator, ..) => generator.dimensions,
         }
     }
 
@@ -118,6 +206,101 @@ where
         Self::Dense(y_vec, HashMap::default())
     }
 
+    /// Creates a new [`TilemapLayer::Dense`] of the given size where the tile data of every [`Cell`] is
+    /// returned by the given function.
+    ///
+    /// Procedural generators should prefer this over [`new_dense_from_vecs`](Self::new_dense_from_vecs) as
+    /// the rows are written straight into the layer instead of being copied out of a second full size
+    /// [`Vec<Vec<T>>`], which doubles the peak memory of building huge maps.
+    pub fn new_dense_from_fn(size: UVec2, f: impl Fn(Cell) -> T) -> Self {
+        let data = (0..size.y as i32)
+            .map(|y| (0..size.x as i32).map(|x| f(Cell::new(x, y))).collect())
+            .collect();
+        Self::Dense(data, HashMap::default())
+    }
+
+    /// Same as [`new_dense_from_fn`](Self::new_dense_from_fn) but generates the rows in parallel on the
+    /// [`ComputeTaskPool`]
+    pub fn new_dense_from_fn_parallel(size: UVec2, f: impl Fn(Cell) -> T + Sync) -> Self
+    where
+        T: 'static,
+    {
+        let f = &f;
+        let mut rows = ComputeTaskPool::get_or_init(TaskPool::new).scope(|scope| {
+            for y in 0..size.y as i32 {
+                scope.spawn(async move {
+                    let row: Vec<T> = (0..size.x as i32).map(|x| f(Cell::new(x, y))).collect();
+                    (y, row)
+                });
+            }
+        });
+        rows.sort_by_key(|(y, _)| *y);
+        Self::Dense(
+            rows.into_iter().map(|(_, row)| row).collect(),
+            HashMap::default(),
+        )
+    }
+
+    /// Creates a new [`TilemapLayer::Generated`] of the given size where the tile data of every [`Cell`] is
+    /// returned by the given function.
+    ///
+    /// Unlike [`new_dense_from_fn`](Self::new_dense_from_fn) the tile data is only generated when the map is
+    /// built, one chunk at a time, so building the map never holds more than one copy of it.
+    pub fn new_generated(size: UVec2, f: impl Fn(Cell) -> T + Send + Sync + 'static) -> Self {
+        Self::Generated(TileGenerator::new(size, f), HashMap::default())
+    }
+
+    /// Turns a [`TilemapLayer::Generated`] layer into a [`TilemapLayer::Dense`] layer by generating all of its
+    /// tile data. Other layers are returned as they are
+    pub fn into_dense(self) -> Self {
+        match self {
+            TilemapLayer::Generated(generator, entities) => {
+                TilemapLayer::Dense(generator.generate_rows(), entities)
+            }
+            layer => layer,
+        }
+    }
+
+    /// Returns the tile data at the given [`Cell`] if it exists
+    pub fn get_tile_data(&self, cell: Cell) -> Option<T> {
+        match self {
+            TilemapLayer::Sparse(data, ..) => data.get(&cell).cloned(),
+            TilemapLayer::Dense(data, ..) => {
+                if cell.x < 0 || cell.y < 0 {
+                    return None;
+                }
+                data.get(cell.y as usize)
+                    .and_then(|row| row.get(cell.x as usize))
+                    .cloned()
+            }
+            TilemapLayer::Generated(generator, ..) => generator.get_tile_data(cell),
+        }
+    }
+
+    /// Sets the tile data at the given [`Cell`]. Cells outside of the layers dimensions are ignored.
+    pub fn set_tile_data(&mut self, cell: Cell, tile_data: T) {
+        let dimensions = self.dimensions();
+        if cell.x < 0
+            || cell.y < 0
+            || cell.x as u32 >= dimensions.x
+            || cell.y as u32 >= dimensions.y
+        {
+            return;
+        }
+        if let TilemapLayer::Generated(..) = self {
+            *self = std::mem::take(self).into_dense();
+        }
+        match self {
+            TilemapLayer::Sparse(data, ..) => {
+                data.insert(cell, tile_data);
+            }
+            TilemapLayer::Dense(data, ..) => {
+                data[cell.y as usize][cell.x as usize] = tile_data;
+            }
+            TilemapLayer::Generated(..) => unreachable!("generated layers were made dense"),
+        }
+    }
+
     /// Spawns an entity at the given [`Cell`] with the given [`Bundle`]
     pub fn spawn_entity_at_tile_pos<B: Bundle>(
         &mut self,
@@ -131,9 +314,75 @@ where
             TilemapLayer::Sparse(_, _, entities) => {
                 entities.insert(cell, entity);
             }
-            TilemapLayer::Dense(_, entities) => {
+            TilemapLayer::Dense(_, entities) | TilemapLayer::Generated(_, entities) => {
                 entities.insert(cell, entity);
             }
         }
     }
+
+    /// Converts the tile data of every tile with the given function, keeping the layout of the layer and its
+    /// tile entities. Used to migrate saved layers to a new `TileData` type. [`TilemapLayer::Generated`] layers
+    /// are converted into [`TilemapLayer::Dense`] layers
+    pub fn map_tile_data<NewData>(self, convert: impl Fn(T) -> NewData) -> TilemapLayer<NewData>
+    where
+        NewData: Clone + Copy + Sized + Default + Send + Sync,
+    {
+        match self {
+            TilemapLayer::Sparse(data, dimensions, entities) => TilemapLayer::Sparse(
+                data.into_iter()
+                    .map(|(cell, tile_data)| (cell, convert(tile_data)))
+                    .collect(),
+                dimensions,
+                entities,
+            ),
+            TilemapLayer::Dense(data, entities) => TilemapLayer::Dense(
+                data.into_iter()
+                    .map(|row| row.into_iter().map(&convert).collect())
+                    .collect(),
+                entities,
+            ),
+            layer @ TilemapLayer::Generated(..) => layer.into_dense().map_tile_data(convert),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TilemapLayer;
+    use bevy::math::UVec2;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_dense_from_fn() {
+        let size = UVec2::new(5, 3);
+        let generate = |cell: Cell| (cell.x * 10 + cell.y) as u8;
+        let layer = TilemapLayer::new_dense_from_fn(size, generate);
+        assert_eq!(layer.dimensions(), size);
+        assert_eq!(layer.get_tile_data(Cell::new(4, 2)), Some(42));
+        assert_eq!(layer.get_tile_data(Cell::new(5, 0)), None);
+
+        let parallel = TilemapLayer::new_dense_from_fn_parallel(size, generate);
+        let (TilemapLayer::Dense(data, _), TilemapLayer::Dense(parallel_data, _)) =
+            (layer, parallel)
+        else {
+            panic!("layers are dense");
+        };
+        assert_eq!(data, parallel_data);
+    }
+
+    #[test]
+    fn test_generated_layer() {
+        let mut layer =
+            TilemapLayer::new_generated(UVec2::new(4, 2), |cell| (cell.x + cell.y) as u8);
+        assert_eq!(layer.dimensions(), UVec2::new(4, 2));
+        assert_eq!(layer.get_tile_data(Cell::new(3, 1)), Some(4));
+        assert_eq!(layer.get_tile_data(Cell::new(4, 1)), None);
+
+        // Writing to the layer turns it dense
+        layer.set_tile_data(Cell::new(1, 1), 9);
+        let TilemapLayer::Dense(data, _) = &layer else {
+            panic!("written layers are dense");
+        };
+        assert_eq!(data, &vec![vec![0, 1, 2, 3], vec![1, 9, 3, 4]]);
+    }
 }
@@ -0,0 +1,352 @@
+//! Releasing memory held by maps that were filled and then cleared.
+//!
+//! Sparse layers keep the capacity of their largest size and cells set back to the default `TileData` keep
+//! their entries. [`TilemapManager::compact`] releases that memory on demand while the
+//! [`TilemapCompactionPlugin`] compacts every map once it stopped changing for
+//! [`CompactionSettings::idle_time`]. Compaction never changes what a cell reads, so only cells holding the
+//! layer default of their chunk are dropped.
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer, Tilemap, TilemapSettings, TilemapSubsystems};
+use crate::tilemap_manager::TilemapManager;
+use bevy::app::{App, Last, Plugin};
+use bevy::prelude::{Entity, Local, Query, Res, Resource, With};
+use bevy::time::Time;
+use bevy::utils::{Duration, HashMap};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Settings used when compacting maps
+#[derive(Resource, Clone, Copy, Debug, PartialEq)]
+pub struct CompactionSettings {
+    /// Dense layers where at most this fraction of the cells hold tile data other than the layer default are
+    /// converted to sparse layers
+    pub max_dense_fill: f32,
+    /// Sparse layers where at least this fraction of the cells hold tile data other than the layer default are
+    /// converted to dense layers. Keep it well above [`Self::max_dense_fill`] so layers filling up and
+    /// emptying around the thresholds don't convert back and forth on every compaction
+    pub min_sparse_fill: f32,
+    /// How long a map must go without changes before the [`TilemapCompactionPlugin`] compacts it
+    pub idle_time: Duration,
+}
+
+impl Default for CompactionSettings {
+    fn default() -> Self {
+        Self {
+            max_dense_fill: 0.125,
+            min_sparse_fill: 0.5,
+            idle_time: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Plugin that compacts every map with the given types once it has been idle for
+/// [`CompactionSettings::idle_time`]. Maps can opt out with [`TilemapSubsystems::COMPACTION`]
+pub struct TilemapCompactionPlugin<TileData, MapLayers, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapLayers, MapChunk, Map)>,
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Default
+    for TilemapCompactionPlugin<TileData, MapLayers, MapChunk, Map>
+{
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Plugin
+    for TilemapCompactionPlugin<TileData, MapLayers, MapChunk, Map>
+where
+    TileData: PartialEq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CompactionSettings>().add_systems(
+            Last,
+            compact_idle_tilemaps::<TileData, MapLayers, MapChunk, Map>,
+        );
+    }
+}
+
+/// How long a map has gone without changes. Used by [`compact_idle_tilemaps`]
+#[derive(Default)]
+pub struct CompactionIdleState {
+    version: u64,
+    idle: Duration,
+    compacted: bool,
+}
+
+/// Compacts every map that hasn't changed for [`CompactionSettings::idle_time`]. Each map is compacted once
+/// per idle period
+pub fn compact_idle_tilemaps<TileData, MapLayers, MapChunk, Map>(
+    settings: Res<CompactionSettings>,
+    time: Res<Time>,
+    maps: Query<(Entity, Option<&TilemapSettings>), With<Tilemap>>,
+    mut idle_states: Local<HashMap<Entity, CompactionIdleState>>,
+    mut tick: Local<u64>,
+    mut tilemap_manager: TilemapManager<TileData, MapLayers, MapChunk, Map>,
+) where
+    TileData: PartialEq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let current_tick = *tick;
+    *tick = tick.wrapping_add(1);
+    idle_states.retain(|map_entity, _| maps.contains(*map_entity));
+
+    for (map_entity, map_settings) in maps.iter() {
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let Ok(version) = tilemap_manager.map_version() else {
+            continue;
+        };
+        // Maps are only considered idle once they are seen without changes between two runs
+        let Some(state) = idle_states.get_mut(&map_entity) else {
+            idle_states.insert(
+                map_entity,
+                CompactionIdleState {
+                    version,
+                    ..CompactionIdleState::default()
+                },
+            );
+            continue;
+        };
+        if state.version != version {
+            *state = CompactionIdleState {
+                version,
+                ..CompactionIdleState::default()
+            };
+            continue;
+        }
+        state.idle += time.delta();
+        if state.compacted
+            || state.idle < settings.idle_time
+            || !TilemapSettings::should_run_for(
+                map_settings,
+                TilemapSubsystems::COMPACTION,
+                current_tick,
+            )
+        {
+            continue;
+        }
+        if tilemap_manager.compact_with_settings(&settings).is_ok() {
+            // Compacting bumps the version of layers whose content changed, which shouldn't count as a change
+            state.version = tilemap_manager.map_version().unwrap_or(version);
+            state.compacted = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CompactionSettings, TilemapCompactionPlugin};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::{ChunkLayer, ChunkPos};
+    use crate::map::{TilemapSettings, TilemapStats, TilemapSubsystems};
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::app::App;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bevy::time::Time;
+    use bevy::utils::Duration;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Overlay,
+    }
+
+    fn spawn_map(commands: &mut Commands) -> bevy::prelude::Entity {
+        let mut builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        );
+        builder.add_layer(TilemapLayer::new_sparse_empty(8, 8), MapLayers::Overlay);
+        builder
+            .spawn_tilemap(commands)
+            .expect("map has a main layer")
+    }
+
+    #[test]
+    fn test_compact() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = spawn_map(&mut commands);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.set_layer(MapLayers::Main);
+        tilemap_manager.sets_tile_data(3, Cell::new(1, 1)).unwrap();
+        tilemap_manager.set_layer(MapLayers::Overlay);
+        tilemap_manager.set_layer_default(Some(0)).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                tilemap_manager.sets_tile_data(1, Cell::new(x, y)).unwrap();
+            }
+        }
+        for y in 0..8 {
+            for x in 1..8 {
+                tilemap_manager.sets_tile_data(0, Cell::new(x, y)).unwrap();
+            }
+        }
+        let version = tilemap_manager.layer_version(MapLayers::Overlay).unwrap();
+
+        let report = tilemap_manager.compact().unwrap();
+        // Every chunk of the main layer only has one or no tiles that aren't empty
+        assert_eq!(report.converted_layers, 4);
+        assert_eq!(report.dropped_entries, 56);
+        assert!(report.reclaimed_bytes > 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 5)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 5)).unwrap(), 0);
+        assert!(tilemap_manager.layer_version(MapLayers::Overlay).unwrap() > version);
+        tilemap_manager.set_layer(MapLayers::Main);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 3);
+        assert!(tilemap_manager.get_chunk(ChunkPos::new(0, 0)).is_ok());
+        system_state.apply(&mut world);
+
+        let stats = world.get::<TilemapStats>(map_entity).unwrap();
+        assert_eq!(stats.compactions, 1);
+        assert_eq!(stats.reclaimed_bytes, report.reclaimed_bytes as u64);
+
+        // Compacting again has nothing left to release
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        let report = tilemap_manager.compact().unwrap();
+        assert_eq!(report.reclaimed_bytes, 0);
+        assert_eq!(report.converted_layers, 0);
+        assert_eq!(
+            world.get::<TilemapStats>(map_entity).unwrap().compactions,
+            2
+        );
+    }
+
+    #[test]
+    fn test_compact_densifies_filled_sparse_layers() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = spawn_map(&mut commands);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.set_layer(MapLayers::Overlay);
+        tilemap_manager.set_layer_default(Some(0)).unwrap();
+        // Chunk (0, 0) of the overlay is filled 12 of 16, chunk (1, 0) only 2 of 16
+        for y in 0..3 {
+            for x in 0..4 {
+                tilemap_manager.sets_tile_data(2, Cell::new(x, y)).unwrap();
+            }
+        }
+        tilemap_manager.sets_tile_data(2, Cell::new(4, 0)).unwrap();
+        tilemap_manager.sets_tile_data(2, Cell::new(5, 0)).unwrap();
+        let version = tilemap_manager.layer_version(MapLayers::Overlay).unwrap();
+
+        let report = tilemap_manager.compact().unwrap();
+        assert_eq!(report.densified_layers, 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 3)).unwrap(), 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 2)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 1)).unwrap(), 0);
+        assert!(tilemap_manager.layer_version(MapLayers::Overlay).unwrap() > version);
+
+        // The densified layer stays dense
+        let report = tilemap_manager.compact().unwrap();
+        assert_eq!(report.densified_layers, 0);
+        assert_eq!(report.converted_layers, 0);
+    }
+
+    #[test]
+    fn test_compact_keeps_default_cells_readable() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = spawn_map(&mut commands);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.set_layer(MapLayers::Overlay);
+        for y in 0..4 {
+            for x in 0..4 {
+                tilemap_manager.sets_tile_data(0, Cell::new(x, y)).unwrap();
+            }
+        }
+
+        let report = tilemap_manager.compact().unwrap();
+        // The overlay has no layer default so its default cells are kept
+        assert_eq!(report.dropped_entries, 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 2)).unwrap(), 0);
+        assert!(matches!(
+            tilemap_manager.get_tile_data(Cell::new(5, 5)),
+            Err(TilemapManagerError::TileDataDoesNotExist)
+        ));
+
+        // The main layer was converted to sparse and reads its dropped cells from the layer default
+        tilemap_manager.set_layer(MapLayers::Main);
+        assert_eq!(report.converted_layers, 4);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(7, 7)).unwrap(), 0);
+        let chunk = tilemap_manager.get_chunk(ChunkPos::new(0, 0)).unwrap();
+        assert!(chunk.data.values().all(|layer| layer.is_sparse()));
+    }
+
+    #[test]
+    fn test_idle_compaction() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(CompactionSettings {
+                idle_time: Duration::ZERO,
+                ..CompactionSettings::default()
+            })
+            .add_plugins(TilemapCompactionPlugin::<
+                u8,
+                MapLayers,
+                SquareChunkLayer<u8>,
+                SquareMapData,
+            >::default());
+        let mut system_state: SystemState<Commands> = SystemState::new(&mut app.world);
+        let mut commands = system_state.get_mut(&mut app.world);
+        let idle_map = spawn_map(&mut commands);
+        let opted_out = spawn_map(&mut commands);
+        let mut map_settings = TilemapSettings::default();
+        map_settings.set_enabled(TilemapSubsystems::COMPACTION, false);
+        commands.entity(opted_out).insert(map_settings);
+        system_state.apply(&mut app.world);
+
+        // The first update only records the version of each map
+        app.update();
+        assert!(app.world.get::<TilemapStats>(idle_map).is_none());
+        app.update();
+        assert_eq!(
+            app.world.get::<TilemapStats>(idle_map).unwrap().compactions,
+            1
+        );
+        assert!(app.world.get::<TilemapStats>(opted_out).is_none());
+
+        // Maps are only compacted once while they stay idle
+        app.update();
+        assert_eq!(
+            app.world.get::<TilemapStats>(idle_map).unwrap().compactions,
+            1
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Tearing down large maps over several frames.
+//!
+//! Despawning a map with millions of tile entities and thousands of chunks in one frame stalls the app for
+//! seconds. [`TilemapManager::despawn_tilemap_deferred`](crate::tilemap_manager::TilemapManager::despawn_tilemap_deferred)
+//! marks a map with a [`DeferredDespawn`] instead, and the [`DeferredDespawnPlugin`] despawns at most
+//! `budget_per_frame` tile and chunk entities every frame. The map entity itself is despawned last, followed
+//! by a [`TilemapDespawned`] event so level transitions know when the old map is gone.
+
+use crate::map::chunk::{Chunk, ChunkLayer};
+use bevy::app::{App, Last, Plugin};
+use bevy::prelude::{Commands, Component, DespawnRecursiveExt, Entity, Event, EventWriter, Query};
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+/// Marks a map that is being despawned over several frames by the [`DeferredDespawnPlugin`]. Inserted by
+/// [`TilemapManager::despawn_tilemap_deferred`](crate::tilemap_manager::TilemapManager::despawn_tilemap_deferred)
+#[derive(Component, Clone, Debug)]
+pub struct DeferredDespawn {
+    budget_per_frame: usize,
+    /// Chunks left to despawn, the last one is the chunk currently being torn down
+    chunks: Vec<Entity>,
+    /// Tile entities of the current chunk left to despawn
+    tiles: Vec<Entity>,
+    /// Whether the tile entities of the current chunk were queued yet
+    tiles_queued: bool,
+}
+
+impl DeferredDespawn {
+    /// Creates a deferred despawn of the given chunks
+    pub(super) fn new(budget_per_frame: usize, chunks: Vec<Entity>) -> Self {
+        Self {
+            budget_per_frame: budget_per_frame.max(1),
+            chunks,
+            tiles: vec![],
+            tiles_queued: false,
+        }
+    }
+
+    /// Returns the amount of entities despawned every frame
+    pub fn budget_per_frame(&self) -> usize {
+        self.budget_per_frame
+    }
+
+    /// Returns the amount of chunks that are not despawned yet
+    pub fn remaining_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+}
+
+/// Sent by the [`DeferredDespawnPlugin`] once a map and all of its chunks and tile entities are despawned
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TilemapDespawned {
+    /// The despawned tilemap entity
+    pub map_entity: Entity,
+}
+
+/// Plugin that despawns maps marked with a [`DeferredDespawn`] over several frames
+pub struct DeferredDespawnPlugin<TileData, MapChunk> {
+    ph: PhantomData<(TileData, MapChunk)>,
+}
+
+impl<TileData, MapChunk> Default for DeferredDespawnPlugin<TileData, MapChunk> {
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapChunk> Plugin for DeferredDespawnPlugin<TileData, MapChunk>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<TilemapDespawned>()
+            .add_systems(Last, despawn_tilemaps_deferred::<TileData, MapChunk>);
+    }
+}
+
+/// Despawns up to the budget of every map marked with a [`DeferredDespawn`]. Tile entities are despawned
+/// before their chunk, and the map entity once every chunk is gone
+pub fn despawn_tilemaps_deferred<TileData, MapChunk>(
+    mut commands: Commands,
+    mut maps: Query<(Entity, &mut DeferredDespawn)>,
+    chunks: Query<&Chunk<MapChunk, TileData>>,
+    mut tilemap_despawned: EventWriter<TilemapDespawned>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    for (map_entity, mut despawn) in maps.iter_mut() {
+        let mut budget = despawn.budget_per_frame;
+        while budget > 0 {
+            if let Some(tile_entity) = despawn.tiles.pop() {
+                // Tile entities may have been despawned by hand since the chunk was queued
+                if let Some(tile_commands) = commands.get_entity(tile_entity) {
+                    tile_commands.despawn_recursive();
+                }
+                budget -= 1;
+                continue;
+            }
+            let Some(&chunk_entity) = despawn.chunks.last() else {
+                break;
+            };
+            if !despawn.tiles_queued {
+                if let Ok(chunk) = chunks.get(chunk_entity) {
+                    let tiles = chunk
+                        .data
+                        .values()
+                        .flat_map(|layer| layer.iter_tile_entities())
+                        .map(|(_, tile_entity)| tile_entity)
+                        .collect();
+                    despawn.tiles = tiles;
+                }
+                despawn.tiles_queued = true;
+                continue;
+            }
+            despawn.chunks.pop();
+            despawn.tiles_queued = false;
+            if let Some(chunk_commands) = commands.get_entity(chunk_entity) {
+                chunk_commands.despawn_recursive();
+            }
+            budget -= 1;
+        }
+
+        if despawn.chunks.is_empty() && despawn.tiles.is_empty() {
+            commands.entity(map_entity).despawn_recursive();
+            tilemap_despawned.send(TilemapDespawned { map_entity });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeferredDespawnPlugin, TilemapDespawned};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::Chunk;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::app::App;
+    use bevy::ecs::event::Events;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_despawn_tilemap_deferred() {
+        let mut app = App::new();
+        app.add_plugins(DeferredDespawnPlugin::<u8, SquareChunkLayer<u8>>::default());
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut app.world);
+
+        let (mut commands, _) = system_state.get_mut(&mut app.world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut app.world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut app.world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let mut tile_entities = vec![];
+        for cell in [Cell::new(0, 0), Cell::new(1, 0), Cell::new(3, 3)] {
+            tile_entities.push(tilemap_manager.get_or_spawn_tile_entity(cell).unwrap());
+        }
+        tilemap_manager.despawn_tilemap_deferred(2).unwrap();
+        system_state.apply(&mut app.world);
+
+        // 4 chunks and 3 tile entities take 4 frames with a budget of 2
+        let mut frames = 0;
+        while app.world.get_entity(map_entity).is_some() {
+            app.update();
+            frames += 1;
+            assert!(frames <= 4);
+        }
+        assert_eq!(frames, 4);
+        assert!(tile_entities
+            .iter()
+            .all(|tile_entity| app.world.get_entity(*tile_entity).is_none()));
+        assert_eq!(
+            app.world
+                .query::<&Chunk<SquareChunkLayer<u8>, u8>>()
+                .iter(&app.world)
+                .count(),
+            0
+        );
+        let despawned: Vec<TilemapDespawned> = app
+            .world
+            .resource_mut::<Events<TilemapDespawned>>()
+            .drain()
+            .collect();
+        assert_eq!(despawned, vec![TilemapDespawned { map_entity }]);
+    }
+}
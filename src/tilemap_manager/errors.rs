@@ -15,7 +15,86 @@ pub enum TilemapManagerError {
     #[error("An Entity does not exist for the given ChunkCell")]
     TileEntityDoesNotExist,
 
+    /// A tile entity already exists for the given [`ChunkCell`](crate::map::chunk::ChunkCell)
+    #[error("An Entity already exists for the given ChunkCell")]
+    TileEntityAlreadyExists,
+
     /// `TileData` does not exist for the given [`ChunkCell`](crate::map::chunk::ChunkCell)
     #[error("TileData does not exist for the given ChunkCell")]
     TileDataDoesNotExist,
+
+    /// The tilemap does not have a [`TilePalette`](crate::map::TilePalette)
+    #[error("A TilePalette does not exist for the tilemap")]
+    PaletteDoesNotExist,
+
+    /// The [`TilePalette`](crate::map::TilePalette) has no free indexes left for new `TileData`
+    #[error("The TilePalette is full")]
+    PaletteFull,
+
+    /// The validation callback of a transaction rejected the staged changes
+    #[error("The transaction was rejected by its validation callback")]
+    TransactionRejected,
+
+    /// The [`Cell`](lettuces::cell::Cell) is outside of the region of a
+    /// [`RestrictedTilemapView`](super::RestrictedTilemapView)
+    #[error("The Cell is outside of the restricted view")]
+    OutsideRestrictedView,
+
+    /// The current layer has no corner data for the given [`CornerId`](crate::map::chunk::CornerId)
+    #[error("Corner data does not exist for the given CornerId")]
+    CornerDataDoesNotExist,
+
+    /// The tilemap does not have a [`TilemapGeometry`](crate::map::TilemapGeometry), or a
+    /// `FixedTilemapGeometry` for fixed point queries
+    #[error("A TilemapGeometry does not exist for the tilemap")]
+    GeometryDoesNotExist,
+
+    /// The [`Cell`](lettuces::cell::Cell) is outside of the map
+    #[error("The Cell is outside of the map")]
+    CellOutOfBounds,
+
+    /// The [`MapLayer`](crate::map::MapLayer) does not exist in the chunk of the cell
+    #[error("The MapLayer does not exist in the Chunk")]
+    LayerDoesNotExist,
+
+    /// The [`MapLayer`](crate::map::MapLayer) already exists in the tilemap
+    #[error("The MapLayer already exists in the Tilemap")]
+    LayerAlreadyExists,
+
+    /// The dimensions of a [`TilemapLayer`](crate::tilemap_builder::tilemap_layer_builder::TilemapLayer)
+    /// don't match the dimensions of the tilemap
+    #[error("The layer is {0} but the Tilemap is {1}")]
+    LayerDimensionsMismatch(bevy::math::UVec2, bevy::math::UVec2),
+
+    /// The [`DynamicLayerId`](crate::map::DynamicLayerId) isn't registered in the
+    /// [`DynamicLayerRegistry`](crate::map::DynamicLayerRegistry) of the tilemap
+    #[error("The DynamicLayerId is not registered in the Tilemap")]
+    DynamicLayerNotRegistered,
+
+    /// The main layer of a tilemap can't be removed
+    #[error("The main MapLayer can't be removed")]
+    CannotRemoveMainLayer,
+
+    /// The tilemap does not have [`TileOverrides`](crate::map::TileOverrides)
+    #[error("TileOverrides do not exist for the tilemap")]
+    OverridesDoNotExist,
+
+    /// The tilemap does not have a [`TileHistory`](crate::history::TileHistory)
+    #[cfg(feature = "history")]
+    #[error("A TileHistory does not exist for the tilemap")]
+    HistoryDoesNotExist,
+
+    /// A tilemap can't be resized to have no cells
+    #[error("The Tilemap can't be resized to {0}")]
+    InvalidMapSize(bevy::math::UVec2),
+
+    /// A [`TilemapDiff`](super::TilemapDiff) was made against a different tick than the one the map is at
+    #[cfg(feature = "replication")]
+    #[error("The TilemapDiff starts at tick {found} but the map is at tick {expected}")]
+    DiffTickMismatch {
+        /// The tick the map is at
+        expected: u64,
+        /// The tick the diff starts at
+        found: u64,
+    },
 }
@@ -0,0 +1,507 @@
+use crate::map::chunk::{ChunkLayer, ChunkPos};
+use crate::map::{MapData, MapLayer, TilemapGeometry};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::math::{IVec2, UVec2, Vec2};
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use bevy::utils::HashSet;
+use lettuces::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+/// The offsets of the four orthogonal neighbours of a cell
+const ORTHOGONAL: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+/// The offsets of all eight neighbours of a cell
+const SURROUNDING: [IVec2; 8] = [
+    IVec2::X,
+    IVec2::NEG_X,
+    IVec2::Y,
+    IVec2::NEG_Y,
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+/// A flow field leading every reachable cell of a map towards the closest of a set of goal cells.
+///
+/// Built with [`TilemapManager::build_flow_field`]. Each cell stores the cost of the cheapest path from it to
+/// a goal, moving between orthogonal neighbours and paying the cost of every cell entered. Units steer by
+/// following [`FlowField::direction`] or the smoothed [`FlowField::sample`]. The field is split into the same
+/// chunks as the map and when tiles change [`TilemapManager::update_flow_field`] only recomputes the chunks
+/// that are affected.
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    goals: HashSet<Cell>,
+    dimensions: IVec2,
+    chunk_size: IVec2,
+    chunk_counts: IVec2,
+    chunks: Vec<FlowChunk>,
+}
+
+/// The costs and integrated distances of a single chunk of a [`FlowField`]
+#[derive(Clone, Debug)]
+struct FlowChunk {
+    origin: IVec2,
+    size: IVec2,
+    costs: Vec<Option<u32>>,
+    integration: Vec<u32>,
+}
+
+impl FlowChunk {
+    fn index(&self, cell: IVec2) -> usize {
+        let local = cell - self.origin;
+        (local.y * self.size.x + local.x) as usize
+    }
+
+    fn contains(&self, cell: IVec2) -> bool {
+        let local = cell - self.origin;
+        local.cmpge(IVec2::ZERO).all() && local.cmplt(self.size).all()
+    }
+}
+
+impl FlowField {
+    /// Returns the goal cells of the field
+    pub fn goals(&self) -> impl Iterator<Item = Cell> + '_ {
+        self.goals.iter().cloned()
+    }
+
+    /// Returns the dimensions of the map the field covers
+    pub fn dimensions(&self) -> UVec2 {
+        self.dimensions.as_uvec2()
+    }
+
+    /// Returns the cost of the cheapest path from the given cell to a goal. `None` if no goal can be reached
+    /// from the cell or the cell is outside the map
+    pub fn integration(&self, cell: Cell) -> Option<u32> {
+        let cell = IVec2::new(cell.x, cell.y);
+        let chunk = self.chunk_of(cell)?;
+        let value = chunk.integration[chunk.index(cell)];
+        (value != u32::MAX).then_some(value)
+    }
+
+    /// Returns the offset of the neighbouring cell a unit in the given cell should move into. Diagonal moves
+    /// are only returned when both orthogonal cells next to them can be reached, so units don't cut corners.
+    ///
+    /// Returns [`IVec2::ZERO`] for goal cells and `None` if no goal can be reached from the cell
+    pub fn direction(&self, cell: Cell) -> Option<IVec2> {
+        let current = self.integration(cell)?;
+        if self.goals.contains(&cell) {
+            return Some(IVec2::ZERO);
+        }
+        let reachable = |offset: IVec2| self.integration(offset_cell(cell, offset));
+        SURROUNDING
+            .iter()
+            .filter(|offset| {
+                offset.x == 0
+                    || offset.y == 0
+                    || (reachable(IVec2::new(offset.x, 0)).is_some()
+                        && reachable(IVec2::new(0, offset.y)).is_some())
+            })
+            .filter_map(|offset| reachable(*offset).map(|value| (value, *offset)))
+            .filter(|(value, _)| *value < current)
+            .min_by_key(|(value, _)| *value)
+            .map(|(_, offset)| offset)
+    }
+
+    /// Returns a smoothed, normalized steering direction at the given position. Positions are in cell units
+    /// so cell (x, y) covers `x..x + 1` and `y..y + 1`. The directions of the four closest cells are blended by
+    /// distance. Returns [`Vec2::ZERO`] if none of them lead to a goal
+    pub fn sample(&self, position: Vec2) -> Vec2 {
+        let position = position - Vec2::splat(0.5);
+        let base = position.floor();
+        let fraction = position - base;
+        let base = base.as_ivec2();
+        let mut direction = Vec2::ZERO;
+        for (offset, weight) in [
+            (IVec2::ZERO, (1.0 - fraction.x) * (1.0 - fraction.y)),
+            (IVec2::X, fraction.x * (1.0 - fraction.y)),
+            (IVec2::Y, (1.0 - fraction.x) * fraction.y),
+            (IVec2::ONE, fraction.x * fraction.y),
+        ] {
+            let cell = base + offset;
+            if let Some(cell_direction) = self.direction(Cell::new(cell.x, cell.y)) {
+                direction += cell_direction.as_vec2().normalize_or_zero() * weight;
+            }
+        }
+        direction.normalize_or_zero()
+    }
+
+    /// Samples the field at a world position using the maps [`TilemapGeometry`]. See [`Self::sample`]
+    pub fn sample_world(&self, geometry: &TilemapGeometry, world_pos: Vec2) -> Vec2 {
+        self.sample((world_pos - geometry.origin) / geometry.cell_size)
+    }
+
+    fn chunk_of(&self, cell: IVec2) -> Option<&FlowChunk> {
+        if cell.cmplt(IVec2::ZERO).any() || cell.cmpge(self.dimensions).any() {
+            return None;
+        }
+        let chunk_pos = cell / self.chunk_size;
+        self.chunks
+            .get((chunk_pos.y * self.chunk_counts.x + chunk_pos.x) as usize)
+    }
+
+    fn chunk_index_of(&self, cell: IVec2) -> Option<usize> {
+        if cell.cmplt(IVec2::ZERO).any() || cell.cmpge(self.dimensions).any() {
+            return None;
+        }
+        let chunk_pos = cell / self.chunk_size;
+        Some((chunk_pos.y * self.chunk_counts.x + chunk_pos.x) as usize)
+    }
+
+    fn value_at(&self, cell: IVec2) -> u32 {
+        self.chunk_of(cell)
+            .map(|chunk| chunk.integration[chunk.index(cell)])
+            .unwrap_or(u32::MAX)
+    }
+
+    /// Recomputes the given chunks in parallel until no chunk border changes anymore
+    fn relax(&mut self, mut active: HashSet<usize>) {
+        let pool = ComputeTaskPool::get_or_init(TaskPool::new);
+        while !active.is_empty() {
+            let field: &FlowField = self;
+            let results = pool.scope(|scope| {
+                for index in active.iter().cloned() {
+                    scope.spawn(async move { (index, field.relax_chunk(index)) });
+                }
+            });
+            active.clear();
+            for (index, integration) in results {
+                let Some(integration) = integration else {
+                    continue;
+                };
+                let chunk = &mut self.chunks[index];
+                let border_changed = (0..chunk.size.y).any(|y| {
+                    (0..chunk.size.x).any(|x| {
+                        let on_border =
+                            x == 0 || y == 0 || x == chunk.size.x - 1 || y == chunk.size.y - 1;
+                        let i = (y * chunk.size.x + x) as usize;
+                        on_border && integration[i] != chunk.integration[i]
+                    })
+                });
+                chunk.integration = integration;
+                if border_changed {
+                    let chunk_pos = chunk.origin / self.chunk_size;
+                    for offset in ORTHOGONAL {
+                        let neighbour = chunk_pos + offset;
+                        if neighbour.cmpge(IVec2::ZERO).all()
+                            && neighbour.cmplt(self.chunk_counts).all()
+                        {
+                            active
+                                .insert((neighbour.y * self.chunk_counts.x + neighbour.x) as usize);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs Dijkstra inside a single chunk seeded from its current values and the borders of its neighbours.
+    /// Returns the new values if any of them changed
+    fn relax_chunk(&self, index: usize) -> Option<Vec<u32>> {
+        let chunk = &self.chunks[index];
+        let mut integration = chunk.integration.clone();
+        let mut open = BinaryHeap::new();
+
+        for y in 0..chunk.size.y {
+            for x in 0..chunk.size.x {
+                let cell = chunk.origin + IVec2::new(x, y);
+                let i = chunk.index(cell);
+                if let Some(cost) = chunk.costs[i] {
+                    for offset in ORTHOGONAL {
+                        let neighbour = cell + offset;
+                        if chunk.contains(neighbour) {
+                            continue;
+                        }
+                        let value = self.value_at(neighbour).saturating_add(cost);
+                        if value < integration[i] {
+                            integration[i] = value;
+                        }
+                    }
+                }
+                if integration[i] != u32::MAX {
+                    open.push(Reverse((integration[i], i)));
+                }
+            }
+        }
+
+        while let Some(Reverse((value, i))) = open.pop() {
+            if value > integration[i] {
+                continue;
+            }
+            let cell = chunk.origin + IVec2::new(i as i32 % chunk.size.x, i as i32 / chunk.size.x);
+            for offset in ORTHOGONAL {
+                let neighbour = cell + offset;
+                if !chunk.contains(neighbour) {
+                    continue;
+                }
+                let n = chunk.index(neighbour);
+                let Some(cost) = chunk.costs[n] else {
+                    continue;
+                };
+                let next = value.saturating_add(cost);
+                if next < integration[n] {
+                    integration[n] = next;
+                    open.push(Reverse((next, n)));
+                }
+            }
+        }
+
+        (integration != chunk.integration).then_some(integration)
+    }
+}
+
+fn offset_cell(cell: Cell, offset: IVec2) -> Cell {
+    Cell::new(cell.x + offset.x, cell.y + offset.y)
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Builds a [`FlowField`] over the current layer leading every cell towards the closest of the given goal
+    /// cells. Goals outside of the map are ignored.
+    ///
+    /// `cost` returns the cost of entering a cell or `None` if it can't be entered. Costs below one are treated
+    /// as one. Costs are read and chunks are integrated in parallel on the [`ComputeTaskPool`].
+    pub fn build_flow_field(
+        &self,
+        goal_cells: &[Cell],
+        cost: impl Fn(Cell, &TileData) -> Option<u32> + Sync,
+    ) -> Result<FlowField, TilemapManagerError> {
+        let dimensions = self.dimensions()?.as_ivec2();
+        let chunk_size = self
+            .get_chunk(ChunkPos::new(0, 0))?
+            .get_chunk_dimensions()
+            .as_ivec2();
+        let chunk_counts = (dimensions + chunk_size - IVec2::ONE) / chunk_size;
+
+        let mut map_chunks = Vec::with_capacity((chunk_counts.x * chunk_counts.y) as usize);
+        for y in 0..chunk_counts.y {
+            for x in 0..chunk_counts.x {
+                map_chunks.push(self.get_chunk(ChunkPos::new(x, y))?);
+            }
+        }
+
+        let map_layer = self.layer();
+        let map_mask = self.map_mask();
+        let cost = &cost;
+        let mut chunks = ComputeTaskPool::get_or_init(TaskPool::new).scope(|scope| {
+            for (index, map_chunk) in map_chunks.iter().enumerate() {
+                scope.spawn(async move {
+                    let origin =
+                        IVec2::new(index as i32 % chunk_counts.x, index as i32 / chunk_counts.x)
+                            * chunk_size;
+                    let size = map_chunk.get_chunk_dimensions().as_ivec2();
+                    let mut costs = Vec::with_capacity((size.x * size.y) as usize);
+                    for y in 0..size.y {
+                        for x in 0..size.x {
+                            let cell = Cell::new(origin.x + x, origin.y + y);
+                            if map_mask.is_some_and(|mask| !mask.contains(cell)) {
+                                costs.push(None);
+                                continue;
+                            }
+                            costs.push(
+                                map_chunk
+                                    .get_tile_data_from_cell(map_layer, cell)
+                                    .and_then(|tile_data| cost(cell, &tile_data))
+                                    .map(|cost| cost.max(1)),
+                            );
+                        }
+                    }
+                    let integration = vec![u32::MAX; costs.len()];
+                    (
+                        index,
+                        FlowChunk {
+                            origin,
+                            size,
+                            costs,
+                            integration,
+                        },
+                    )
+                });
+            }
+        });
+        chunks.sort_by_key(|(index, _)| *index);
+
+        let mut field = FlowField {
+            goals: HashSet::new(),
+            dimensions,
+            chunk_size,
+            chunk_counts,
+            chunks: chunks.into_iter().map(|(_, chunk)| chunk).collect(),
+        };
+        let mut active = HashSet::new();
+        for goal in goal_cells.iter() {
+            let cell = IVec2::new(goal.x, goal.y);
+            let Some(index) = field.chunk_index_of(cell) else {
+                continue;
+            };
+            let chunk = &mut field.chunks[index];
+            let i = chunk.index(cell);
+            chunk.integration[i] = 0;
+            field.goals.insert(*goal);
+            active.insert(index);
+        }
+        field.relax(active);
+        Ok(field)
+    }
+
+    /// Updates a [`FlowField`] after the tiles in the given cells changed on the current layer. `cost` must be
+    /// the cost function the field was built with.
+    ///
+    /// Cells that got cheaper only recompute the chunk they are in and any chunks their new values spread to.
+    /// Cells that got more expensive or can no longer be entered also reset every cell whose path could have
+    /// gone through them.
+    pub fn update_flow_field(
+        &self,
+        flow_field: &mut FlowField,
+        changed_cells: &[Cell],
+        cost: impl Fn(Cell, &TileData) -> Option<u32> + Sync,
+    ) -> Result<(), TilemapManagerError> {
+        let mut active = HashSet::new();
+        let mut reset_above = u32::MAX;
+        for cell in changed_cells.iter() {
+            let position = IVec2::new(cell.x, cell.y);
+            let Some(index) = flow_field.chunk_index_of(position) else {
+                continue;
+            };
+            let new_cost = match self.get_tile_data(*cell) {
+                Ok(tile_data) => cost(*cell, &tile_data).map(|cost| cost.max(1)),
+                Err(
+                    TilemapManagerError::TileDataDoesNotExist
+                    | TilemapManagerError::CellOutOfBounds,
+                ) => None,
+                Err(err) => return Err(err),
+            };
+            let chunk = &mut flow_field.chunks[index];
+            let i = chunk.index(position);
+            let old_cost = chunk.costs[i];
+            chunk.costs[i] = new_cost;
+            let got_worse = match (old_cost, new_cost) {
+                (Some(old), Some(new)) => new > old,
+                (Some(_), None) => true,
+                (None, _) => false,
+            };
+            if got_worse {
+                reset_above = reset_above.min(chunk.integration[i]);
+            }
+            active.insert(index);
+        }
+
+        if reset_above != u32::MAX {
+            for (index, chunk) in flow_field.chunks.iter_mut().enumerate() {
+                for i in 0..chunk.integration.len() {
+                    let cell =
+                        chunk.origin + IVec2::new(i as i32 % chunk.size.x, i as i32 / chunk.size.x);
+                    let value = chunk.integration[i];
+                    if value != u32::MAX
+                        && value >= reset_above
+                        && !flow_field.goals.contains(&Cell::new(cell.x, cell.y))
+                    {
+                        chunk.integration[i] = u32::MAX;
+                        active.insert(index);
+                    }
+                }
+            }
+        }
+
+        flow_field.relax(active);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{IVec2, UVec2, Vec2};
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    const FLOOR: u8 = 0;
+    const WALL: u8 = 1;
+
+    fn cost(_: Cell, tile: &u8) -> Option<u32> {
+        (*tile == FLOOR).then_some(1)
+    }
+
+    #[test]
+    fn test_flow_field() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        // A wall down the middle of the map with a single gap at (4, 8)
+        let mut tiles = vec![vec![FLOOR; 10]; 10];
+        for (y, row) in tiles.iter_mut().enumerate() {
+            if y != 8 {
+                row[4] = WALL;
+            }
+        }
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tiles),
+            SquareMapData {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let goal = Cell::new(9, 0);
+        let mut field = tilemap_manager.build_flow_field(&[goal], cost).unwrap();
+
+        assert_eq!(field.integration(goal), Some(0));
+        assert_eq!(field.direction(goal), Some(IVec2::ZERO));
+        assert_eq!(field.integration(Cell::new(4, 0)), None);
+        // (0, 0) -> (4, 8) is 12 steps and (4, 8) -> (9, 0) is 13 more
+        assert_eq!(field.integration(Cell::new(0, 0)), Some(25));
+        assert_eq!(field.direction(Cell::new(3, 0)), Some(IVec2::Y));
+        assert_eq!(field.direction(Cell::new(3, 8)), Some(IVec2::X));
+        assert!(field.sample(Vec2::new(3.5, 0.5)).y > 0.9);
+
+        // Opening a shortcut only makes things cheaper
+        tilemap_manager
+            .sets_tile_data(FLOOR, Cell::new(4, 0))
+            .unwrap();
+        tilemap_manager
+            .update_flow_field(&mut field, &[Cell::new(4, 0)], cost)
+            .unwrap();
+        assert_eq!(field.integration(Cell::new(0, 0)), Some(9));
+        assert_eq!(field.direction(Cell::new(3, 0)), Some(IVec2::X));
+
+        // Closing both gaps leaves the left side unreachable
+        for cell in [Cell::new(4, 0), Cell::new(4, 8)] {
+            tilemap_manager.sets_tile_data(WALL, cell).unwrap();
+        }
+        tilemap_manager
+            .update_flow_field(&mut field, &[Cell::new(4, 0), Cell::new(4, 8)], cost)
+            .unwrap();
+        assert_eq!(field.integration(Cell::new(0, 0)), None);
+        assert_eq!(field.direction(Cell::new(3, 0)), None);
+        assert_eq!(field.integration(Cell::new(5, 0)), Some(4));
+    }
+}
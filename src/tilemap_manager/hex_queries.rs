@@ -0,0 +1,105 @@
+use crate::hex::map_chunk_layer::HexChunkLayer;
+use crate::hex::{hex_range, hex_ring, hex_spiral};
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+impl<'w, 's, TileData, MapLayers, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, HexChunkLayer<TileData>, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    Map: MapData,
+{
+    /// Returns every cell of the map at most radius steps away from the center. Cells outside of the map or
+    /// its [`MapMask`](crate::map::MapMask) are skipped. See [`hex_range`]
+    pub fn cells_in_range(
+        &self,
+        center: Cell,
+        radius: u32,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        self.cells_in_map(hex_range(center, radius))
+    }
+
+    /// Returns every cell of the map exactly radius steps away from the center, in order around the ring.
+    /// Cells outside of the map or its [`MapMask`](crate::map::MapMask) are skipped, so consecutive cells are
+    /// only guaranteed to be neighbours if the whole ring is on the map. See [`hex_ring`]
+    pub fn ring(&self, center: Cell, radius: u32) -> Result<Vec<Cell>, TilemapManagerError> {
+        self.cells_in_map(hex_ring(center, radius))
+    }
+
+    /// Returns every cell of the map at most radius steps away from the center ordered by their distance to
+    /// the center, starting with the center itself. Cells outside of the map or its
+    /// [`MapMask`](crate::map::MapMask) are skipped. See [`hex_spiral`]
+    pub fn spiral(&self, center: Cell, radius: u32) -> Result<Vec<Cell>, TilemapManagerError> {
+        self.cells_in_map(hex_spiral(center, radius))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::hex::map_chunk_layer::HexagonChunkSettings;
+    use crate::hex::map_data::HexMapData;
+    use crate::hex::{hex_distance, HexTilemapBuilder, HexTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_hex_queries() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, HexTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = HexTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 6]; 6]),
+            HexMapData {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+            HexagonChunkSettings {
+                max_chunk_size: UVec2::new(3, 3),
+                ..Default::default()
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+
+        // Ranges crossing chunk boundaries in the middle of the map keep every cell
+        let center = Cell::new(3, 3);
+        assert_eq!(tilemap_manager.cells_in_range(center, 2).unwrap().len(), 19);
+        assert_eq!(tilemap_manager.ring(center, 2).unwrap().len(), 12);
+        let spiral = tilemap_manager.spiral(center, 2).unwrap();
+        assert_eq!(spiral.len(), 19);
+        assert_eq!(spiral[0], center);
+
+        // Cells outside of the map are clipped
+        let corner = Cell::new(0, 0);
+        let mut range = tilemap_manager.cells_in_range(corner, 1).unwrap();
+        range.sort_by_key(|cell| (cell.y, cell.x));
+        assert_eq!(range, vec![corner, Cell::new(1, 0), Cell::new(0, 1)]);
+        let ring = tilemap_manager.ring(corner, 2).unwrap();
+        assert!(ring
+            .iter()
+            .all(|cell| hex_distance(*cell, corner) == 2 && cell.x >= 0 && cell.y >= 0));
+        assert_eq!(ring.len(), 3);
+        assert!(tilemap_manager
+            .ring(Cell::new(20, 20), 1)
+            .unwrap()
+            .is_empty());
+    }
+}
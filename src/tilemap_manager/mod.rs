@@ -1,21 +1,71 @@
 ﻿use bevy::prelude::{Entity, Resource};
+#[cfg(feature = "reflect")]
+use bevy::prelude::{Reflect, ReflectResource};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+mod compaction;
+mod deferred_despawn;
 mod errors;
+mod flow_field;
+#[cfg(feature = "hex")]
+mod hex_queries;
+mod palette_tilemap_manager;
+#[cfg(feature = "replication")]
+mod replication;
+mod restricted_view;
+mod scoped_tilemap;
+mod stamp;
 mod tilemap_manager;
+mod transaction;
+mod visibility;
 
+pub use compaction::{
+    compact_idle_tilemaps, CompactionIdleState, CompactionSettings, TilemapCompactionPlugin,
+};
+pub use deferred_despawn::{
+    despawn_tilemaps_deferred, DeferredDespawn, DeferredDespawnPlugin, TilemapDespawned,
+};
 pub use errors::TilemapManagerError;
-pub use tilemap_manager::TilemapManager;
+pub use flow_field::FlowField;
+pub use palette_tilemap_manager::PaletteTilemapManager;
+#[cfg(feature = "replication")]
+pub use replication::{ChunkDiff, LayerDiff, ReplicationBaseline, ReplicationError, TilemapDiff};
+pub use restricted_view::RestrictedTilemapView;
+pub use scoped_tilemap::ScopedTilemap;
+pub use stamp::Stamp;
+pub use tilemap_manager::{ClonedTilemap, TileEntityCloning, TilemapManager};
+pub use transaction::{StagedTileChange, TilemapTransaction};
+pub use visibility::ViewerVisibility;
 
-/// A local resource for the tilemap manager that holds the currently selected map layer
-#[derive(Resource, Default)]
-pub(crate) struct LayerIndex<MapLayer>(pub(crate) MapLayer);
-
-/// A local resource for the tilemap that holds the map entity that the tilemap manager is working with
-#[derive(Resource)]
-pub(crate) struct MapEntity(pub(crate) Option<Entity>);
+/// The tilemap and layer a [`TilemapManager`] is working with.
+///
+/// Every system using a manager has its own selection which persists across runs of that system. Use
+/// [`TilemapManager::selection`] and [`TilemapManager::set_selection`] to inspect, save, and restore it, or
+/// [`TilemapManager::with_selection`] to work with another tilemap or layer for a single call. Systems working
+/// with several tilemaps can use [`TilemapManager::map`] instead of changing the selection at all.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TilemapSelection<MapLayers> {
+    /// The selected tilemap. The [`ActiveTilemap`] is used if this is `None`
+    pub map_entity: Option<Entity>,
+    /// The selected layer
+    pub map_layer: MapLayers,
+}
 
-impl Default for MapEntity {
-    fn default() -> Self {
-        Self(None)
+impl<MapLayers> TilemapSelection<MapLayers> {
+    /// Creates a selection of the given tilemap and layer
+    pub fn new(map_entity: Entity, map_layer: MapLayers) -> Self {
+        Self {
+            map_entity: Some(map_entity),
+            map_layer,
+        }
     }
 }
+
+/// Resource holding the tilemap used by every [`TilemapManager`] that hasn't been set to a tilemap with
+/// [`TilemapManager::set_tilemap_entity`]. Useful for games that only have a single map
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "reflect", derive(Reflect), reflect(Resource))]
+pub struct ActiveTilemap(pub Option<Entity>);
@@ -0,0 +1,102 @@
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer, PaletteIndex, TilePalette};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{Entity, Query};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// A [`SystemParam`] used to access and interact with a paletted [`Tilemap`](crate::map::Tilemap).
+///
+/// The chunks of a paletted map store `Index`es into a [`TilePalette`] that must be inserted on the
+/// tilemap entity. This manager converts between `TileData` and indexes so that users can work with
+/// `TileData` directly. New `TileData` is inserted into the palette the first time it is written.
+///
+/// Wraps a normal [`TilemapManager`] which can be accessed with [`tilemap_manager()`](Self::tilemap_manager)
+/// for functionality that doesn't touch tile data.
+#[derive(SystemParam)]
+pub struct PaletteTilemapManager<'w, 's, TileData, Index, MapLayers, MapChunk, Map>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + 'static,
+    Index: PaletteIndex,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<Index> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    tilemap_manager: TilemapManager<'w, 's, Index, MapLayers, MapChunk, Map>,
+    palette_query: Query<'w, 's, &'static mut TilePalette<TileData, Index>>,
+}
+
+impl<'w, 's, TileData, Index, MapLayers, MapChunk, Map>
+    PaletteTilemapManager<'w, 's, TileData, Index, MapLayers, MapChunk, Map>
+where
+    TileData: Eq + Hash + Clone + Copy + Default + Send + Sync + 'static,
+    Index: PaletteIndex,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<Index> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Returns the wrapped [`TilemapManager`] which works directly with palette indexes
+    pub fn tilemap_manager(&self) -> &TilemapManager<'w, 's, Index, MapLayers, MapChunk, Map> {
+        &self.tilemap_manager
+    }
+
+    /// Returns the wrapped [`TilemapManager`] mutably which works directly with palette indexes
+    pub fn tilemap_manager_mut(
+        &mut self,
+    ) -> &mut TilemapManager<'w, 's, Index, MapLayers, MapChunk, Map> {
+        &mut self.tilemap_manager
+    }
+
+    /// Sets the [`Tilemap`](crate::map::Tilemap) entity that this manager is set to affect. See
+    /// [`TilemapManager::set_tilemap_entity`]
+    pub fn set_tilemap_entity(&mut self, entity: Entity) {
+        self.tilemap_manager.set_tilemap_entity(entity);
+    }
+
+    /// Sets the [`MapLayer`] that all future operations will be conducted upon. See
+    /// [`TilemapManager::set_layer`]
+    pub fn set_layer(&mut self, map_layer: MapLayers) {
+        self.tilemap_manager.set_layer(map_layer);
+    }
+
+    /// Returns the [`TilePalette`] of the current tilemap
+    pub fn palette(&self) -> Result<&TilePalette<TileData, Index>, TilemapManagerError> {
+        self.palette_query
+            .get(
+                self.tilemap_manager
+                    .tilemap_entity()
+                    .expect("TilemapManager must have a tilemap entity set"),
+            )
+            .map_err(|_| TilemapManagerError::PaletteDoesNotExist)
+    }
+
+    /// Gets the tile data for the given [`Cell`] if it exists.
+    pub fn get_tile_data(&self, cell: Cell) -> Result<TileData, TilemapManagerError> {
+        let index = self.tilemap_manager.get_tile_data(cell)?;
+        self.palette()?
+            .get(index)
+            .ok_or(TilemapManagerError::TileDataDoesNotExist)
+    }
+
+    /// Sets the tile data for the given [`Cell`] if it exists. Inserts the tile data into the palette if it
+    /// isn't already in it.
+    pub fn set_tile_data(
+        &mut self,
+        tile_data: TileData,
+        cell: Cell,
+    ) -> Result<(), TilemapManagerError> {
+        let mut palette = self
+            .palette_query
+            .get_mut(
+                self.tilemap_manager
+                    .tilemap_entity()
+                    .expect("TilemapManager must have a tilemap entity set"),
+            )
+            .map_err(|_| TilemapManagerError::PaletteDoesNotExist)?;
+        let index = palette
+            .get_or_insert(tile_data)
+            .ok_or(TilemapManagerError::PaletteFull)?;
+        self.tilemap_manager.sets_tile_data(index, cell)
+    }
+}
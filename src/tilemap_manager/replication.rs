@@ -0,0 +1,407 @@
+//! Compact diffs of tile data for replicating maps over the network. Requires the `replication` feature.
+//!
+//! The server keeps a [`ReplicationBaseline`] per remote copy of a map, the state it knows that copy to be
+//! in. [`TilemapManager::diff_since`] compares the map against the baseline and returns a [`TilemapDiff`]
+//! holding only the changed cells of each chunk and layer as runs, then moves the baseline forward. The diff
+//! is encoded with [`TilemapDiff::to_bytes`], sent over a reliable channel, and applied to the remote copy
+//! with [`TilemapManager::apply_diff`]. Diffs carry the ticks they span so a remote copy refuses diffs that
+//! were made against a state it isn't in.
+//!
+//! ```ignore
+//! // Once, after both sides built the same map
+//! let mut baseline = tilemap_manager.capture_baseline(0)?;
+//! // Every network tick on the server
+//! let bytes = tilemap_manager.diff_since(&mut baseline, tick)?.to_bytes()?;
+//! // On the client
+//! map_tick = tilemap_manager.apply_diff(&TilemapDiff::from_bytes(&bytes)?, map_tick)?;
+//! ```
+
+use crate::map::chunk::{ChunkCell, ChunkLayer, ChunkPos};
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::math::UVec2;
+use bevy::utils::HashMap;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+
+/// Errors returned when encoding or decoding a [`TilemapDiff`]
+#[derive(thiserror::Error, Debug)]
+pub enum ReplicationError {
+    /// Failed to encode the diff
+    #[error("Failed to encode the diff: {0}")]
+    Encode(#[from] rmp_serde::encode::Error),
+
+    /// Failed to decode the diff
+    #[error("Failed to decode the diff: {0}")]
+    Decode(#[from] rmp_serde::decode::Error),
+}
+
+/// The changed cells of a single layer of a chunk. Cells are numbered row by row starting at the first cell
+/// of the chunk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LayerDiff<TileData> {
+    /// The bits of the [`MapLayer`] that changed
+    pub map_layer: u64,
+    /// Runs of the amount of unchanged cells skipped followed by the new tile data of the changed cells
+    /// after them
+    pub runs: Vec<(u32, Vec<TileData>)>,
+}
+
+/// The changed layers of a single chunk
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ChunkDiff<TileData> {
+    /// The position of the chunk
+    pub chunk_pos: ChunkPos,
+    /// The layers of the chunk with changed cells
+    pub layers: Vec<LayerDiff<TileData>>,
+}
+
+/// Every change to the tile data of a map between two ticks. See the [module docs](self) for details
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TilemapDiff<TileData> {
+    /// The tick of the state the diff was made against
+    pub from_tick: u64,
+    /// The tick of the state the diff leads to
+    pub to_tick: u64,
+    /// The chunks with changed cells
+    pub chunks: Vec<ChunkDiff<TileData>>,
+}
+
+impl<TileData> TilemapDiff<TileData> {
+    /// Returns true if no cell changed
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the amount of changed cells
+    pub fn changed_cells(&self) -> usize {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.layers.iter())
+            .flat_map(|layer| layer.runs.iter())
+            .map(|(_, tiles)| tiles.len())
+            .sum()
+    }
+}
+
+impl<TileData> TilemapDiff<TileData>
+where
+    TileData: Serialize + DeserializeOwned,
+{
+    /// Encodes the diff into a compact binary message
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ReplicationError> {
+        Ok(rmp_serde::to_vec(self)?)
+    }
+
+    /// Decodes a diff from a message made with [`Self::to_bytes`]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ReplicationError> {
+        Ok(rmp_serde::from_slice(bytes)?)
+    }
+}
+
+/// The tile data a remote copy of a map is known to have, used to diff against. See the
+/// [module docs](self) for details
+#[derive(Clone, Debug, Default)]
+pub struct ReplicationBaseline<TileData> {
+    tick: u64,
+    layers: HashMap<(ChunkPos, u64), Vec<TileData>>,
+}
+
+impl<TileData> ReplicationBaseline<TileData> {
+    /// Returns the tick of the state the baseline holds
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+}
+
+/// Returns the tile data of every cell of the layer row by row. Cells without tile data hold the default
+fn layer_tiles<TileData, MapChunk>(layer: &MapChunk) -> Vec<TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData>,
+{
+    ChunkCell::iter_chunk(layer.get_chunk_dimensions())
+        .map(|chunk_cell| layer.get_tile_data(chunk_cell).copied().unwrap_or_default())
+        .collect()
+}
+
+/// Splits the cells that differ from the old tiles into runs
+fn diff_runs<TileData>(old: Option<&[TileData]>, new: &[TileData]) -> Vec<(u32, Vec<TileData>)>
+where
+    TileData: Clone + Copy + Default + PartialEq,
+{
+    let mut runs = vec![];
+    let mut skipped = 0;
+    let mut tiles = vec![];
+    for (index, tile_data) in new.iter().enumerate() {
+        let old_tile = old
+            .and_then(|old| old.get(index).copied())
+            .unwrap_or_default();
+        if old_tile != *tile_data {
+            tiles.push(*tile_data);
+            continue;
+        }
+        if !tiles.is_empty() {
+            runs.push((skipped, std::mem::take(&mut tiles)));
+            skipped = 0;
+        }
+        skipped += 1;
+    }
+    if !tiles.is_empty() {
+        runs.push((skipped, tiles));
+    }
+    runs
+}
+
+/// Returns the [`ChunkCell`] with the given row by row index in a chunk with the given dimensions
+fn chunk_cell_at(index: u32, dimensions: UVec2) -> Option<ChunkCell> {
+    (index < dimensions.x * dimensions.y)
+        .then(|| ChunkCell::new((index % dimensions.x) as i32, (index / dimensions.x) as i32))
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: PartialEq + Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Captures the current tile data of every layer of the map as the state of a remote copy at the given
+    /// tick. Capture it once both sides hold the same map, such as right after building it from the same
+    /// data or after sending a full save
+    pub fn capture_baseline(
+        &self,
+        tick: u64,
+    ) -> Result<ReplicationBaseline<TileData>, TilemapManagerError> {
+        let mut baseline = ReplicationBaseline {
+            tick,
+            layers: HashMap::default(),
+        };
+        for chunk_pos in self.chunk_positions()? {
+            for (map_layer, layer) in self.get_chunk(chunk_pos)?.data.iter() {
+                baseline
+                    .layers
+                    .insert((chunk_pos, *map_layer), layer_tiles(layer));
+            }
+        }
+        Ok(baseline)
+    }
+
+    /// Returns every change to the tile data of the map since the state held by the baseline and moves the
+    /// baseline to the current state at the given tick.
+    ///
+    /// Changes are found by comparing tile data, so writes made by any means are included and cells written
+    /// back to their old tile data are not. Layers the baseline doesn't know are diffed against the default
+    /// tile data.
+    pub fn diff_since(
+        &self,
+        baseline: &mut ReplicationBaseline<TileData>,
+        tick: u64,
+    ) -> Result<TilemapDiff<TileData>, TilemapManagerError> {
+        let mut diff = TilemapDiff {
+            from_tick: baseline.tick,
+            to_tick: tick,
+            chunks: vec![],
+        };
+        for chunk_pos in self.chunk_positions()? {
+            let chunk = self.get_chunk(chunk_pos)?;
+            let mut map_layers: Vec<u64> = chunk.data.keys().copied().collect();
+            map_layers.sort_unstable();
+            let mut layers = vec![];
+            for map_layer in map_layers {
+                let tiles = layer_tiles(&chunk.data[&map_layer]);
+                let old = baseline.layers.get(&(chunk_pos, map_layer));
+                let runs = diff_runs(old.map(Vec::as_slice), &tiles);
+                if !runs.is_empty() {
+                    layers.push(LayerDiff { map_layer, runs });
+                    baseline.layers.insert((chunk_pos, map_layer), tiles);
+                }
+            }
+            if !layers.is_empty() {
+                diff.chunks.push(ChunkDiff { chunk_pos, layers });
+            }
+        }
+        baseline.tick = tick;
+        Ok(diff)
+    }
+
+    /// Applies a diff made with [`diff_since`](Self::diff_since) to this copy of the map, which must be at
+    /// the tick the diff was made against, and returns the tick the map is at afterwards.
+    ///
+    /// Nothing is written if the ticks don't match, with [`TilemapManagerError::DiffTickMismatch`], or if the
+    /// diff doesn't fit the chunks and layers of the map. Writes bypass the maps hooks, overrides, and
+    /// history since they mirror changes made elsewhere, the [`MapVersion`](crate::map::MapVersion) of every
+    /// changed layer is bumped.
+    pub fn apply_diff(
+        &mut self,
+        diff: &TilemapDiff<TileData>,
+        tick: u64,
+    ) -> Result<u64, TilemapManagerError> {
+        if diff.from_tick != tick {
+            return Err(TilemapManagerError::DiffTickMismatch {
+                expected: tick,
+                found: diff.from_tick,
+            });
+        }
+        for chunk_diff in diff.chunks.iter() {
+            let chunk = self.get_chunk(chunk_diff.chunk_pos)?;
+            for layer_diff in chunk_diff.layers.iter() {
+                let dimensions = chunk
+                    .data
+                    .get(&layer_diff.map_layer)
+                    .ok_or(TilemapManagerError::LayerDoesNotExist)?
+                    .get_chunk_dimensions();
+                let end: u32 = layer_diff
+                    .runs
+                    .iter()
+                    .map(|(skipped, tiles)| skipped + tiles.len() as u32)
+                    .sum();
+                if end > dimensions.x * dimensions.y {
+                    return Err(TilemapManagerError::CellOutOfBounds);
+                }
+            }
+        }
+
+        let map_entity = self.selected_map_entity();
+        let mut changed_layers = vec![];
+        for chunk_diff in diff.chunks.iter() {
+            let mut chunk = self.get_chunk_mut(chunk_diff.chunk_pos)?;
+            for layer_diff in chunk_diff.layers.iter() {
+                let dimensions = chunk.data[&layer_diff.map_layer].get_chunk_dimensions();
+                let mut index = 0;
+                for (skipped, tiles) in layer_diff.runs.iter() {
+                    index += skipped;
+                    for tile_data in tiles.iter() {
+                        if let Some(chunk_cell) = chunk_cell_at(index, dimensions) {
+                            chunk.set_tile_data(layer_diff.map_layer, chunk_cell, *tile_data);
+                        }
+                        index += 1;
+                    }
+                }
+                changed_layers.push(layer_diff.map_layer);
+            }
+        }
+        changed_layers.sort_unstable();
+        changed_layers.dedup();
+        for map_layer in changed_layers {
+            self.bump_layer_version(map_entity, map_layer);
+        }
+        Ok(diff.to_tick)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TilemapDiff;
+    use crate as bevy_sparse_tilemap;
+    use crate::map::chunk::ChunkPos;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Entity, World};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Secondary,
+    }
+
+    fn spawn_map(commands: &mut Commands) -> Entity {
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        tilemap_builder.add_layer(TilemapLayer::new_sparse_empty(4, 4), MapLayers::Secondary);
+        tilemap_builder
+            .spawn_tilemap(commands)
+            .expect("map has a main layer")
+    }
+
+    #[test]
+    fn test_diff_and_apply() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let server_map = spawn_map(&mut commands);
+        let client_map = spawn_map(&mut commands);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(server_map);
+        let mut baseline = tilemap_manager.capture_baseline(0).unwrap();
+        tilemap_manager
+            .set_tile_data_batch([
+                (Cell::new(0, 0), 1),
+                (Cell::new(1, 0), 2),
+                (Cell::new(3, 3), 3),
+            ])
+            .unwrap();
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        tilemap_manager.sets_tile_data(4, Cell::new(2, 1)).unwrap();
+        // Cells written back to their old tile data aren't part of the diff
+        tilemap_manager.set_layer(MapLayers::Main);
+        tilemap_manager.sets_tile_data(5, Cell::new(2, 2)).unwrap();
+        tilemap_manager.sets_tile_data(0, Cell::new(2, 2)).unwrap();
+
+        let diff = tilemap_manager.diff_since(&mut baseline, 1).unwrap();
+        assert_eq!(baseline.tick(), 1);
+        assert_eq!(diff.changed_cells(), 4);
+        assert_eq!(diff.chunks.len(), 3);
+        assert_eq!(diff.chunks[0].chunk_pos, ChunkPos::new(0, 0));
+        assert_eq!(diff.chunks[0].layers[0].runs, vec![(0, vec![1, 2])]);
+        assert!(tilemap_manager
+            .diff_since(&mut baseline, 2)
+            .unwrap()
+            .is_empty());
+
+        // The remote copy applies the decoded diff
+        let bytes = diff.to_bytes().unwrap();
+        let diff = TilemapDiff::<u8>::from_bytes(&bytes).unwrap();
+        tilemap_manager.set_tilemap_entity(client_map);
+        assert!(matches!(
+            tilemap_manager.apply_diff(&diff, 5),
+            Err(TilemapManagerError::DiffTickMismatch {
+                expected: 5,
+                found: 0
+            })
+        ));
+        let version = tilemap_manager.layer_version(MapLayers::Main).unwrap();
+        assert_eq!(tilemap_manager.apply_diff(&diff, 0).unwrap(), 1);
+        assert!(tilemap_manager.layer_version(MapLayers::Main).unwrap() > version);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 0)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 3);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 2)).unwrap(), 0);
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 1)).unwrap(), 4);
+
+        // Both copies now hold the same tile data
+        let client_baseline = tilemap_manager.capture_baseline(1).unwrap();
+        let mut client_baseline = client_baseline;
+        assert!(tilemap_manager
+            .diff_since(&mut client_baseline, 2)
+            .unwrap()
+            .is_empty());
+        tilemap_manager.set_tilemap_entity(server_map);
+        let mut server_baseline = tilemap_manager.capture_baseline(1).unwrap();
+        tilemap_manager.set_tilemap_entity(client_map);
+        assert!(tilemap_manager
+            .diff_since(&mut server_baseline, 2)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::math::{IRect, IVec2, UVec2};
+use bevy::prelude::Entity;
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// A view of a [`TilemapManager`] that can only access a rectangular region of the map.
+///
+/// Cells passed to the view are relative to the regions origin, so (0, 0) is the minimum corner of the
+/// region. Any access outside of the region returns [`TilemapManagerError::OutsideRestrictedView`] without
+/// touching the map. Get one with [`TilemapManager::restricted_view`].
+pub struct RestrictedTilemapView<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    tilemap_manager: &'a mut TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>,
+    region: IRect,
+}
+
+impl<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+    RestrictedTilemapView<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Returns the inclusive region of the map this view can access, in map [`Cell`] coordinates
+    pub fn region(&self) -> IRect {
+        self.region
+    }
+
+    /// Returns the size of the region
+    pub fn dimensions(&self) -> UVec2 {
+        (self.region.size() + IVec2::ONE).as_uvec2()
+    }
+
+    /// Returns true if the given view relative [`Cell`] is inside the region
+    pub fn contains(&self, cell: Cell) -> bool {
+        let dimensions = self.dimensions();
+        cell.x >= 0
+            && cell.y >= 0
+            && (cell.x as u32) < dimensions.x
+            && (cell.y as u32) < dimensions.y
+    }
+
+    /// Converts a view relative [`Cell`] into a map [`Cell`]
+    pub fn to_map_cell(&self, cell: Cell) -> Result<Cell, TilemapManagerError> {
+        if !self.contains(cell) {
+            return Err(TilemapManagerError::OutsideRestrictedView);
+        }
+        Ok(Cell::new(
+            cell.x + self.region.min.x,
+            cell.y + self.region.min.y,
+        ))
+    }
+
+    /// Returns the currently selected layer of the underlying manager
+    pub fn layer(&self) -> MapLayers {
+        self.tilemap_manager.layer()
+    }
+
+    /// Sets the layer the view accesses
+    pub fn set_layer(&mut self, map_layer: MapLayers) {
+        self.tilemap_manager.set_layer(map_layer);
+    }
+
+    /// Gets the tile data for the given view relative [`Cell`] if it exists.
+    pub fn get_tile_data(&self, cell: Cell) -> Result<TileData, TilemapManagerError> {
+        self.tilemap_manager.get_tile_data(self.to_map_cell(cell)?)
+    }
+
+    /// Sets the tile data for the given view relative [`Cell`] if it exists.
+    pub fn sets_tile_data(
+        &mut self,
+        tile_data: TileData,
+        cell: Cell,
+    ) -> Result<(), TilemapManagerError> {
+        let cell = self.to_map_cell(cell)?;
+        self.tilemap_manager.sets_tile_data(tile_data, cell)
+    }
+
+    /// Gets the [`Entity`] for the given view relative [`Cell`] if it exists.
+    pub fn get_tile_entity(&self, cell: Cell) -> Result<Entity, TilemapManagerError> {
+        self.tilemap_manager
+            .get_tile_entity(self.to_map_cell(cell)?)
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Returns a [`RestrictedTilemapView`] that can only access the given inclusive region of the map.
+    ///
+    /// Useful for handing a sub-rectangle of the map to a subsystem, such as a minigame, without letting it
+    /// touch tiles outside of it.
+    pub fn restricted_view(
+        &mut self,
+        region: IRect,
+    ) -> RestrictedTilemapView<'_, 'w, 's, TileData, MapLayers, MapChunk, Map> {
+        RestrictedTilemapView {
+            tilemap_manager: self,
+            region,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::{IRect, UVec2};
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_restricted_view_bounds() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 6]; 6]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+
+        let mut view = tilemap_manager.restricted_view(IRect::new(2, 3, 4, 5));
+        assert_eq!(view.dimensions(), UVec2::new(3, 3));
+        assert!(view.sets_tile_data(7, Cell::new(1, 0)).is_ok());
+        assert!(matches!(
+            view.sets_tile_data(7, Cell::new(3, 0)),
+            Err(TilemapManagerError::OutsideRestrictedView)
+        ));
+        assert!(matches!(
+            view.get_tile_data(Cell::new(-1, 0)),
+            Err(TilemapManagerError::OutsideRestrictedView)
+        ));
+
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).ok(), Some(7));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 3)).ok(), Some(0));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapSelection};
+use bevy::prelude::Entity;
+use std::hash::Hash;
+use std::ops::{Deref, DerefMut};
+
+/// A [`TilemapManager`] temporarily set to a single tilemap. Get one with [`TilemapManager::map`].
+///
+/// Every function of the manager can be called on it and affects the scoped tilemap. The previous
+/// [`TilemapSelection`] of the manager is restored when it is dropped, so a system can address several maps,
+/// such as a world map and its minimap or the floors of a building, without tracking which one the manager is
+/// currently set to.
+///
+/// ```ignore
+/// let tile_data = tilemap_manager.map(world_map).layer(MapLayers::Terrain).get_tile_data(cell)?;
+/// tilemap_manager.map(minimap).sets_tile_data(tile_data, cell)?;
+/// ```
+pub struct ScopedTilemap<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    tilemap_manager: &'a mut TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>,
+    previous: TilemapSelection<MapLayers>,
+}
+
+impl<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+    ScopedTilemap<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Scopes the accessor to the given layer of the tilemap. Use [`TilemapManager::selection`] to read the
+    /// scoped layer
+    pub fn layer(self, map_layer: MapLayers) -> Self {
+        self.tilemap_manager.set_layer(map_layer);
+        self
+    }
+}
+
+impl<'a, 'w, 's, TileData, MapLayers, MapChunk, Map> Deref
+    for ScopedTilemap<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    type Target = TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>;
+
+    fn deref(&self) -> &Self::Target {
+        self.tilemap_manager
+    }
+}
+
+impl<'a, 'w, 's, TileData, MapLayers, MapChunk, Map> DerefMut
+    for ScopedTilemap<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.tilemap_manager
+    }
+}
+
+impl<'a, 'w, 's, TileData, MapLayers, MapChunk, Map> Drop
+    for ScopedTilemap<'a, 'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn drop(&mut self) {
+        self.tilemap_manager.set_selection(self.previous);
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Returns a [`ScopedTilemap`] set to the given tilemap and the default layer, leaving the selection of
+    /// this manager untouched once it is dropped
+    pub fn map(
+        &mut self,
+        map_entity: Entity,
+    ) -> ScopedTilemap<'_, 'w, 's, TileData, MapLayers, MapChunk, Map> {
+        let previous = self.selection();
+        self.set_selection(TilemapSelection::new(map_entity, MapLayers::default()));
+        ScopedTilemap {
+            tilemap_manager: self,
+            previous,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapSelection;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Entity, World};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Secondary,
+    }
+
+    fn spawn_map(commands: &mut Commands, main: u8, secondary: u8) -> Entity {
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![main; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        tilemap_builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![secondary; 4]; 4]),
+            MapLayers::Secondary,
+        );
+        tilemap_builder
+            .spawn_tilemap(commands)
+            .expect("map has a main layer")
+    }
+
+    #[test]
+    fn test_scoped_tilemap() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let world_map = spawn_map(&mut commands, 1, 2);
+        let minimap = spawn_map(&mut commands, 3, 4);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_selection(TilemapSelection::new(world_map, MapLayers::Secondary));
+
+        let cell = Cell::new(3, 1);
+        assert_eq!(tilemap_manager.map(minimap).get_tile_data(cell).unwrap(), 3);
+        assert_eq!(
+            tilemap_manager
+                .map(minimap)
+                .layer(MapLayers::Secondary)
+                .get_tile_data(cell)
+                .unwrap(),
+            4
+        );
+        let tile_data = tilemap_manager.map(world_map).get_tile_data(cell).unwrap();
+        tilemap_manager
+            .map(minimap)
+            .sets_tile_data(tile_data, cell)
+            .unwrap();
+        assert_eq!(tilemap_manager.map(minimap).get_tile_data(cell).unwrap(), 1);
+
+        // The selection of the manager is untouched
+        assert_eq!(
+            tilemap_manager.selection(),
+            TilemapSelection::new(world_map, MapLayers::Secondary)
+        );
+        assert_eq!(tilemap_manager.get_tile_data(cell).unwrap(), 2);
+    }
+}
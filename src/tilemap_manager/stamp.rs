@@ -0,0 +1,294 @@
+use crate::map::chunk::{ChunkLayer, ChunkPos};
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::math::{IVec2, UVec2};
+use bevy::utils::HashSet;
+use lettuces::cell::Cell;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::hash::Hash;
+
+/// A rectangle of tile data on one or more layers that can be pasted onto any map with the same layers, such
+/// as a prefab room or a dungeon piece.
+///
+/// Copy a stamp out of a map with [`TilemapManager::copy_region`] or build one by hand with
+/// [`Stamp::set_tile_data`], then paste it with [`TilemapManager::paste`]. Cells of the stamp are relative to
+/// its first cell. Cells without tile data are left untouched when the stamp is pasted, so stamps don't have to
+/// be rectangular.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Stamp<TileData> {
+    dimensions: UVec2,
+    layers: Vec<(u64, Vec<Option<TileData>>)>,
+}
+
+impl<TileData> Stamp<TileData>
+where
+    TileData: Clone + Copy,
+{
+    /// Creates an empty stamp of the given size
+    pub fn new(dimensions: UVec2) -> Self {
+        Self {
+            dimensions,
+            layers: vec![],
+        }
+    }
+
+    /// Returns the size of the stamp
+    pub fn dimensions(&self) -> UVec2 {
+        self.dimensions
+    }
+
+    /// Returns true if the stamp has tile data for the given layer
+    pub fn has_layer(&self, map_layer: impl MapLayer) -> bool {
+        self.layer(map_layer.to_bits()).is_some()
+    }
+
+    /// Returns the tile data of the given [`Cell`] of the stamp on the given layer
+    pub fn get_tile_data(&self, map_layer: impl MapLayer, cell: Cell) -> Option<TileData> {
+        let index = self.index(cell)?;
+        self.layer(map_layer.to_bits())?[index]
+    }
+
+    /// Sets the tile data of the given [`Cell`] of the stamp on the given layer, adding the layer to the stamp
+    /// if it doesn't have it yet. `None` leaves the cell untouched when the stamp is pasted. Cells outside of
+    /// the stamp are ignored
+    pub fn set_tile_data(
+        &mut self,
+        map_layer: impl MapLayer,
+        cell: Cell,
+        tile_data: Option<TileData>,
+    ) {
+        let Some(index) = self.index(cell) else {
+            return;
+        };
+        let map_layer = map_layer.to_bits();
+        let position = match self.layers.iter().position(|(bits, _)| *bits == map_layer) {
+            Some(position) => position,
+            None => {
+                let len = (self.dimensions.x * self.dimensions.y) as usize;
+                self.layers.push((map_layer, vec![None; len]));
+                self.layers.len() - 1
+            }
+        };
+        self.layers[position].1[index] = tile_data;
+    }
+
+    fn layer(&self, map_layer: u64) -> Option<&[Option<TileData>]> {
+        self.layers
+            .iter()
+            .find(|(bits, _)| *bits == map_layer)
+            .map(|(_, tiles)| tiles.as_slice())
+    }
+
+    fn index(&self, cell: Cell) -> Option<usize> {
+        (cell.x >= 0
+            && cell.y >= 0
+            && (cell.x as u32) < self.dimensions.x
+            && (cell.y as u32) < self.dimensions.y)
+            .then(|| cell.y as usize * self.dimensions.x as usize + cell.x as usize)
+    }
+
+    fn cell(&self, index: usize) -> Cell {
+        let width = self.dimensions.x as usize;
+        Cell::new((index % width) as i32, (index / width) as i32)
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Copies the tile data of every cell whose coordinates lie between the two corners, inclusive, on the
+    /// given layers into a [`Stamp`]. The region may span any amount of chunks. Cells outside of the map or
+    /// without tile data, like empty cells of a sparse layer, are left empty in the stamp.
+    pub fn copy_region(
+        &self,
+        min: Cell,
+        max: Cell,
+        layers: &[MapLayers],
+    ) -> Result<Stamp<TileData>, TilemapManagerError> {
+        let (min, max) = (
+            IVec2::new(min.x.min(max.x), min.y.min(max.y)),
+            IVec2::new(min.x.max(max.x), min.y.max(max.y)),
+        );
+        let mut stamp = Stamp::new((max - min + IVec2::ONE).as_uvec2());
+        for map_layer in layers.iter() {
+            let bits = map_layer.to_bits();
+            if !self
+                .get_chunk(ChunkPos::new(0, 0))?
+                .data
+                .contains_key(&bits)
+            {
+                return Err(TilemapManagerError::LayerDoesNotExist);
+            }
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    let tile_data = match self.read_tile_data(bits, Cell::new(x, y)) {
+                        Ok(tile_data) => Some(tile_data),
+                        Err(
+                            TilemapManagerError::TileDataDoesNotExist
+                            | TilemapManagerError::CellOutOfBounds
+                            | TilemapManagerError::InvalidChunkPos,
+                        ) => None,
+                        Err(err) => return Err(err),
+                    };
+                    stamp.set_tile_data(*map_layer, Cell::new(x - min.x, y - min.y), tile_data);
+                }
+            }
+        }
+        Ok(stamp)
+    }
+
+    /// Pastes the [`Stamp`] with its first cell at the given origin and returns the written cells of every
+    /// layer. Empty cells of the stamp and cells landing outside of the map or its
+    /// [`MapMask`](crate::map::MapMask) are skipped.
+    ///
+    /// Every layer is written like [`set_tile_data_batch`](Self::set_tile_data_batch) and the whole paste is
+    /// a single entry in the maps history. Returns [`TilemapManagerError::LayerDoesNotExist`] without writing
+    /// anything if the map is missing a layer of the stamp.
+    pub fn paste(
+        &mut self,
+        stamp: &Stamp<TileData>,
+        origin: Cell,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let chunk = self.get_chunk(ChunkPos::new(0, 0))?;
+        if stamp
+            .layers
+            .iter()
+            .any(|(map_layer, _)| !chunk.data.contains_key(map_layer))
+        {
+            return Err(TilemapManagerError::LayerDoesNotExist);
+        }
+
+        let mut writes = vec![];
+        for (map_layer, tiles) in stamp.layers.iter() {
+            let tiles: Vec<(Cell, TileData)> = tiles
+                .iter()
+                .enumerate()
+                .filter_map(|(index, tile_data)| {
+                    let cell = stamp.cell(index);
+                    tile_data.map(|tile_data| {
+                        (Cell::new(origin.x + cell.x, origin.y + cell.y), tile_data)
+                    })
+                })
+                .collect();
+            let cells: HashSet<Cell> = self
+                .cells_in_map(tiles.iter().map(|(cell, _)| *cell))?
+                .into_iter()
+                .collect();
+            let tiles = tiles.into_iter().filter(|(cell, _)| cells.contains(cell));
+            writes.extend(self.apply_tile_write_batch(*map_layer, tiles)?);
+        }
+        let mut written = HashSet::new();
+        let cells = writes
+            .iter()
+            .map(|write| write.cell)
+            .filter(|cell| written.insert(*cell))
+            .collect();
+        self.record_history(writes);
+        Ok(cells)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stamp;
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_manager::TilemapManagerError;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Secondary,
+        Missing,
+    }
+
+    #[test]
+    fn test_copy_and_paste() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_fn(UVec2::new(6, 6), |cell| (cell.x + cell.y * 6) as u8),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        tilemap_builder.add_layer(TilemapLayer::new_sparse_empty(6, 6), MapLayers::Secondary);
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        tilemap_manager.sets_tile_data(50, Cell::new(2, 2)).unwrap();
+        tilemap_manager.set_layer(MapLayers::Main);
+
+        // The region spans four chunks and its corners can be given in any order
+        let stamp = tilemap_manager
+            .copy_region(
+                Cell::new(3, 3),
+                Cell::new(1, 1),
+                &[MapLayers::Main, MapLayers::Secondary],
+            )
+            .unwrap();
+        assert_eq!(stamp.dimensions(), UVec2::new(3, 3));
+        assert_eq!(
+            stamp.get_tile_data(MapLayers::Main, Cell::new(0, 0)),
+            Some(7)
+        );
+        assert_eq!(
+            stamp.get_tile_data(MapLayers::Main, Cell::new(2, 1)),
+            Some(15)
+        );
+        assert_eq!(
+            stamp.get_tile_data(MapLayers::Secondary, Cell::new(1, 1)),
+            Some(50)
+        );
+        assert_eq!(
+            stamp.get_tile_data(MapLayers::Secondary, Cell::new(0, 0)),
+            None
+        );
+
+        // Parts of the stamp landing outside of the map are clipped
+        let cells = tilemap_manager.paste(&stamp, Cell::new(4, 0)).unwrap();
+        assert_eq!(cells.len(), 6);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 0)).unwrap(), 7);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 2)).unwrap(), 20);
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 1)).unwrap(), 50);
+        assert!(tilemap_manager.get_tile_data(Cell::new(4, 0)).is_err());
+
+        // Stamps with layers the map doesn't have aren't pasted at all
+        let mut stamp = Stamp::new(UVec2::new(1, 1));
+        stamp.set_tile_data(MapLayers::Main, Cell::new(0, 0), Some(99));
+        stamp.set_tile_data(MapLayers::Missing, Cell::new(0, 0), Some(99));
+        assert!(matches!(
+            tilemap_manager.paste(&stamp, Cell::new(0, 0)),
+            Err(TilemapManagerError::LayerDoesNotExist)
+        ));
+        tilemap_manager.set_layer(MapLayers::Main);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 0);
+    }
+}
@@ -1,24 +1,116 @@
-use crate::map::chunk::{Chunk, ChunkLayer, ChunkPos};
-use crate::map::{MapData, MapLayer, Tilemap};
+use crate::generation::SeededRng;
+#[cfg(feature = "history")]
+use crate::history::TileHistory;
+use crate::map::chunk::{
+    Chunk, ChunkCell, ChunkCorners, ChunkLayer, ChunkLayerType, ChunkPos, ChunkStoragePool, Chunks,
+    CompactionReport, CornerId, LayerStorage,
+};
+use crate::map::geometry::{
+    polygon_bounds, rect_overlaps_circle, rect_overlaps_polygon, rect_overlaps_rect,
+};
+use crate::map::{
+    Adjacency, CellMask, DynamicLayerId, LayerHandle, MapData, MapLayer, MapMarker, MapMask,
+    MapVersion, SparseOverrideSet, TileCell, TileEntityParenting, TileOverrides, TileWrite,
+    TileWriteHooks, Tilemap, TilemapGeometry, TilemapRef, TilemapSettings, TilemapStats,
+};
+#[cfg(feature = "fixed_point")]
+use crate::map::{FixedRect, FixedTilemapGeometry};
+use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+use crate::tilemap_builder::TilemapBuilder;
 use crate::tilemap_manager::TilemapManagerError;
-use crate::tilemap_manager::{LayerIndex, MapEntity};
+use crate::tilemap_manager::{
+    ActiveTilemap, CompactionSettings, DeferredDespawn, TilemapSelection,
+};
+use bevy::ecs::component::Tick;
+use bevy::ecs::entity::{EntityMapper, MapEntities};
 use bevy::ecs::system::SystemParam;
-use bevy::math::UVec2;
-use bevy::prelude::{Children, Commands, DespawnRecursiveExt, Entity, Local, Query};
+#[cfg(any(feature = "history", feature = "replication"))]
+use bevy::ecs::world::Mut;
+use bevy::math::{IVec2, Rect, UVec2, Vec2};
+use bevy::prelude::{
+    BuildChildren, Children, Commands, DespawnRecursiveExt, DetectChanges, DetectChangesMut,
+    Entity, Local, Query, Ref, Res,
+};
+use bevy::tasks::{ComputeTaskPool, TaskPool};
+use bevy::utils::{HashMap, HashSet};
 use lettuces::cell::Cell;
+use std::collections::VecDeque;
 use std::hash::Hash;
-use std::ops::Deref;
+
+/// The tile data and tile entities of the cells of a layer, collected while rebuilding the chunks of a map
+#[derive(Default)]
+struct LayerCells<TileData> {
+    layer_data: HashMap<Cell, TileData>,
+    layer_entities: HashMap<Cell, Entity>,
+    /// The amount of cells with tile data, including cells that aren't kept
+    filled_cells: u32,
+}
+
+/// The [`LayerCells`] of every layer of a map mapped to the bits of the layer
+type MapLayerCells<TileData> = HashMap<u64, LayerCells<TileData>>;
+
+/// A chunk looked up by a ring search of a [`TilemapManager`]
+enum SearchedChunk<'a, MapChunk, TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    /// The map has no chunk at the position
+    Missing,
+    /// The chunk can't contain tile data accepted by the search
+    Empty,
+    /// The chunk may contain tile data accepted by the search
+    Chunk(&'a Chunk<MapChunk, TileData>),
+}
+
+/// Builds a layer of the given size from the tile data and tile entities of its cells. Layers with data in
+/// every cell are dense, all others are sparse
+fn tilemap_layer_from_cells<TileData>(
+    layer_data: HashMap<Cell, TileData>,
+    size: UVec2,
+    layer_entities: HashMap<Cell, Entity>,
+) -> TilemapLayer<TileData>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+{
+    if layer_data.len() != (size.x * size.y) as usize {
+        return TilemapLayer::Sparse(layer_data, size, layer_entities);
+    }
+    let dense = (0..size.y as i32)
+        .map(|y| {
+            (0..size.x as i32)
+                .map(|x| layer_data[&Cell::new(x, y)])
+                .collect()
+        })
+        .collect();
+    TilemapLayer::Dense(dense, layer_entities)
+}
 
 /// A [`SystemParam`] used to access and interact with a [`Tilemap`]
 ///
 /// # IMPORTANT
 ///
-/// You **MUST** set the [TilemapManager] to a specific tilemap using [`set_tilemap_entity()`](TilemapManager::set_tilemap_entity) before you use the Tilemap Manager.
-/// If you don't the functions on this manager will panic.
+/// You **MUST** set the [TilemapManager] to a specific tilemap using [`set_tilemap_entity()`](TilemapManager::set_tilemap_entity) or insert an
+/// [`ActiveTilemap`] resource before you use the Tilemap Manager. If you don't the functions on this manager will panic.
+/// Systems working with several tilemaps can scope single calls to one of them with [`map()`](TilemapManager::map).
+///
+/// The selected tilemap and layer are stored in a [`TilemapSelection`] owned by the system using the manager. It
+/// can be read and restored with [`selection()`](TilemapManager::selection) and [`set_selection()`](TilemapManager::set_selection).
 ///
 /// # Internal [`SystemParam`]s
-/// - `Query<(Entity, &mut Tilemap, Option<&'static Children>)>`
+/// - `Query<(Entity, Ref<Tilemap>, Option<&'static Children>)>`
 /// - `Query<(Entity, &mut Chunk<TileData>, Option<&'static Children>)>`
+/// - `Query<&mut TileWriteHooks<TileData>>`
+/// - `Query<&mut MapVersion>`
+/// - `Query<&mut TileOverrides<TileData>>`
+/// - `Query<&mut TileHistory<TileData>>` with the `history` feature
+/// - `Query<&mut ChunkCorners<TileData>>`
+/// - `Query<&TilemapGeometry>`
+/// - `Query<&FixedTilemapGeometry>` with the `fixed_point` feature
+/// - `Query<&mut TilemapStats>`
+/// - `Query<&MapMask>`
+/// - `Query<&TilemapSettings>`
+/// - `Option<Res<ActiveTilemap>>`
 #[derive(SystemParam)]
 pub struct TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
 where
@@ -32,7 +124,7 @@ where
         's,
         (
             Entity,
-            &'static mut Tilemap,
+            Ref<'static, Tilemap>,
             &'static Map,
             Option<&'static Children>,
         ),
@@ -46,9 +138,106 @@ where
             Option<&'static Children>,
         ),
     >,
+    write_hooks: Query<'w, 's, &'static mut TileWriteHooks<TileData>>,
+    map_versions: Query<'w, 's, &'static mut MapVersion>,
+    overrides: Query<'w, 's, &'static mut TileOverrides<TileData>>,
+    #[cfg(feature = "history")]
+    history: Query<'w, 's, &'static mut TileHistory<TileData>>,
+    chunk_corners: Query<'w, 's, &'static mut ChunkCorners<TileData>>,
+    geometry: Query<'w, 's, &'static TilemapGeometry>,
+    #[cfg(feature = "fixed_point")]
+    fixed_geometry: Query<'w, 's, &'static FixedTilemapGeometry>,
+    stats: Query<'w, 's, &'static mut TilemapStats>,
+    map_masks: Query<'w, 's, &'static MapMask>,
+    settings: Query<'w, 's, &'static TilemapSettings>,
     commands: Commands<'w, 's>,
-    layer_index: Local<'s, LayerIndex<MapLayers>>,
-    map_entity: Local<'s, MapEntity>,
+    active_tilemap: Option<Res<'w, ActiveTilemap>>,
+    selection: Local<'s, TilemapSelection<MapLayers>>,
+    chunk_lookup: Local<'s, ChunkLookupCache>,
+}
+
+/// The chunk entity found by the last cell lookup of a [`TilemapManager`]
+#[derive(Clone, Copy)]
+struct CachedChunkLookup {
+    map_entity: Entity,
+    tilemap_changed: Tick,
+    chunk_pos: ChunkPos,
+    chunk_entity: Entity,
+}
+
+/// Remembers the last chunk a [`TilemapManager`] looked up. Consecutive accesses usually hit the same chunk, so
+/// the lookup is reused as long as the [`Tilemap`] wasn't changed since.
+///
+/// Reads only borrow the manager immutably, so the lookup is stored in a [`std::cell::Cell`].
+#[derive(Default)]
+struct ChunkLookupCache(std::cell::Cell<Option<CachedChunkLookup>>);
+
+/// Returns the [`ChunkCell`] of the cell if the chunk has the layer and the cell lies inside of the chunk.
+/// Chunks on the edge of the map can be smaller than the max chunk size, so cells past the edge of the map
+/// can still map to a chunk
+fn checked_chunk_cell<MapChunk, TileData>(
+    chunk: &Chunk<MapChunk, TileData>,
+    map_layer: u64,
+    cell: Cell,
+) -> Result<ChunkCell, TilemapManagerError>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+{
+    let layer = chunk
+        .data
+        .get(&map_layer)
+        .ok_or(TilemapManagerError::LayerDoesNotExist)?;
+    let chunk_cell = MapChunk::into_chunk_cell(cell, &chunk.chunk_settings);
+    match chunk_cell.within(layer.get_chunk_dimensions()) {
+        true => Ok(chunk_cell),
+        false => Err(TilemapManagerError::CellOutOfBounds),
+    }
+}
+
+/// What [`TilemapManager::clone_map`] does with the tile entities of the cloned map
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TileEntityCloning {
+    /// The cloned map refers to the same tile entities as the original
+    #[default]
+    Share,
+    /// A new empty entity is spawned for every tile entity. Use [`ClonedTilemap::tile_entities`] to copy
+    /// over any components that are needed
+    SpawnEmpty,
+}
+
+/// The result of [`TilemapManager::clone_map`]
+#[derive(Clone, Debug)]
+pub struct ClonedTilemap {
+    /// The entity of the new tilemap
+    pub map_entity: Entity,
+    /// Mapping from the original maps tile entities to the new ones. Empty unless
+    /// [`TileEntityCloning::SpawnEmpty`] was used
+    pub tile_entities: HashMap<Entity, Entity>,
+}
+
+/// Maps entities to new ones, spawning a new empty entity the first time an entity is seen if it has
+/// access to commands
+#[derive(Default)]
+struct CloneEntityMapper<'a, 'w, 's> {
+    commands: Option<&'a mut Commands<'w, 's>>,
+    mapping: HashMap<Entity, Entity>,
+}
+
+impl EntityMapper for CloneEntityMapper<'_, '_, '_> {
+    fn map_entity(&mut self, entity: Entity) -> Entity {
+        if let Some(mapped) = self.mapping.get(&entity) {
+            return *mapped;
+        }
+        match self.commands.as_mut() {
+            Some(commands) => {
+                let mapped = commands.spawn_empty().id();
+                self.mapping.insert(entity, mapped);
+                mapped
+            }
+            None => entity,
+        }
+    }
 }
 
 impl<'w, 's, TileData, MapLayers, MapChunk, Map>
@@ -59,20 +248,25 @@ where
     MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
     Map: MapData,
 {
-    /// Returns the [`Tilemap`] entity that this tilemap manager is set to affect
+    /// Returns the [`Tilemap`] entity that this tilemap manager is set to affect. Falls back to the
+    /// [`ActiveTilemap`] if no entity was set
     pub fn tilemap_entity(&self) -> Option<Entity> {
-        self.map_entity.deref().0
+        self.selection.map_entity.or_else(|| {
+            self.active_tilemap
+                .as_ref()
+                .and_then(|active_tilemap| active_tilemap.0)
+        })
     }
 
     /// Sets the [`Tilemap`] entity that this tilemap manager is set to affect. This must be set before
-    /// using any other functions that modify the tilemap
+    /// using any other functions that modify the tilemap unless an [`ActiveTilemap`] is set
     pub fn set_tilemap_entity(&mut self, entity: Entity) {
-        *self.map_entity = MapEntity(Some(entity));
+        self.selection.map_entity = Some(entity);
     }
 
     /// Returns the currently set [`MapLayer`]
     pub fn layer(&self) -> MapLayers {
-        self.layer_index.0
+        self.selection.map_layer
     }
 
     /// Sets the [`MapLayer`] that all future operations will be conducted upon.
@@ -81,17 +275,159 @@ where
     ///
     /// The selected layer will persist across system runs
     pub fn set_layer(&mut self, map_layer: MapLayers) {
-        *self.layer_index = LayerIndex(map_layer)
+        self.selection.map_layer = map_layer;
+    }
+
+    /// Sets the manager to the map and layer of the given [`LayerHandle`]. Only handles of a [`MapMarker`]
+    /// using this managers layer enum are accepted
+    pub fn set_layer_handle<Marker>(&mut self, layer_handle: LayerHandle<Marker>)
+    where
+        Marker: MapMarker<Layers = MapLayers>,
+    {
+        *self.selection =
+            TilemapSelection::new(layer_handle.map_entity(), layer_handle.map_layer());
+    }
+
+    /// Returns the [`TilemapSelection`] of this manager
+    pub fn selection(&self) -> TilemapSelection<MapLayers> {
+        *self.selection
+    }
+
+    /// Replaces the [`TilemapSelection`] of this manager, for example with one that was saved earlier
+    pub fn set_selection(&mut self, selection: TilemapSelection<MapLayers>) {
+        *self.selection = selection;
+    }
+
+    /// Runs the given function with the manager set to the given [`TilemapSelection`] and restores the
+    /// previous selection afterwards
+    pub fn with_selection<R>(
+        &mut self,
+        selection: TilemapSelection<MapLayers>,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R {
+        let previous = std::mem::replace(&mut *self.selection, selection);
+        let result = f(self);
+        *self.selection = previous;
+        result
+    }
+
+    /// Returns the entity of the chunk containing the cell in the given map, reusing the last lookup if it was
+    /// for the same chunk and the [`Tilemap`] hasn't changed since
+    fn chunk_entity_for_cell(
+        &self,
+        map_entity: Entity,
+        cell: Cell,
+    ) -> Result<Entity, TilemapManagerError> {
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+        if !self.is_cell_in_mask(map_entity, cell) {
+            return Err(TilemapManagerError::CellOutOfBounds);
+        }
+        let chunk_pos = map.into_chunk_pos(cell);
+        if let Some(cached) = self.chunk_lookup.0.get() {
+            if cached.map_entity == map_entity
+                && cached.chunk_pos == chunk_pos
+                && cached.tilemap_changed == tilemap.last_changed()
+            {
+                return Ok(cached.chunk_entity);
+            }
+        }
+        let chunk_entity = tilemap
+            .get_chunk(chunk_pos)
+            .ok_or(TilemapManagerError::InvalidChunkPos)?;
+        self.chunk_lookup.0.set(Some(CachedChunkLookup {
+            map_entity,
+            tilemap_changed: tilemap.last_changed(),
+            chunk_pos,
+            chunk_entity,
+        }));
+        Ok(chunk_entity)
+    }
+
+    /// Returns false if the map has a [`MapMask`] and the cell is outside of it
+    fn is_cell_in_mask(&self, map_entity: Entity, cell: Cell) -> bool {
+        self.map_masks
+            .get(map_entity)
+            .map_or(true, |mask| mask.contains(cell))
+    }
+
+    /// Returns the [`TileEntityParenting`] of the given map
+    fn tile_entity_parenting(&self, map_entity: Entity) -> TileEntityParenting {
+        self.settings
+            .get(map_entity)
+            .map(|settings| settings.tile_entity_parenting)
+            .unwrap_or_default()
+    }
+
+    /// Parents the tile entity in the given chunk according to the [`TileEntityParenting`] of the map
+    fn parent_tile_entity(
+        &mut self,
+        map_entity: Entity,
+        chunk_entity: Entity,
+        tile_entity: Entity,
+    ) {
+        if let Some(parent) = self
+            .tile_entity_parenting(map_entity)
+            .parent(map_entity, chunk_entity)
+        {
+            self.commands.entity(tile_entity).set_parent(parent);
+        }
+    }
+
+    /// Inserts the [`TileCell`] and [`TilemapRef`] of a tile entity, replacing any it already has
+    fn insert_tile_cell(&mut self, map_entity: Entity, tile_entity: Entity, tile_cell: TileCell) {
+        self.commands
+            .entity(tile_entity)
+            .insert((tile_cell, TilemapRef(map_entity)));
+    }
+
+    pub(super) fn selected_map_entity(&self) -> Entity {
+        self.tilemap_entity()
+            .expect("TilemapManager must have a tilemap entity set")
+    }
+
+    /// Returns the version of the whole map from its [`MapVersion`]. Returns 0 if the map has no
+    /// [`MapVersion`]
+    pub fn map_version(&self) -> Result<u64, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        self.tilemap_query.get(map_entity)?;
+        Ok(self
+            .map_versions
+            .get(map_entity)
+            .map(|version| version.version())
+            .unwrap_or(0))
+    }
+
+    /// Returns the version of the given [`MapLayer`] from the maps [`MapVersion`]. Returns 0 if the map has
+    /// no [`MapVersion`]
+    pub fn layer_version(&self, map_layer: MapLayers) -> Result<u64, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        self.tilemap_query.get(map_entity)?;
+        Ok(self
+            .map_versions
+            .get(map_entity)
+            .map(|version| version.layer_version(map_layer.to_bits()))
+            .unwrap_or(0))
+    }
+
+    /// Bumps the [`MapVersion`] of the given map for the current layer if it has one
+    fn bump_map_version(&mut self, map_entity: Entity) {
+        self.bump_layer_version(map_entity, self.selection.map_layer.to_bits());
+    }
+
+    /// Bumps the [`MapVersion`] of the given map for the given layer if it has one
+    pub(super) fn bump_layer_version(&mut self, map_entity: Entity, map_layer: u64) {
+        if let Ok(mut version) = self.map_versions.get_mut(map_entity) {
+            version.bump(map_layer);
+        }
     }
 
     /// Returns the [`Tilemap`]s dimensions.
     pub fn dimensions(&self) -> Result<UVec2, TilemapManagerError> {
-        let (_, tilemap, _map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
+        let (_, tilemap, map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+
+        if let Some(map_size) = map.map_size() {
+            return Ok(map_size);
+        }
 
         let chunks = tilemap.chunks().chunk_counts();
         let average_chunk_size = self
@@ -130,146 +466,2031 @@ where
         ))
     }
 
-    /// Gets the tile data for the given [`Cell`] if it exists.
+    /// Returns true if the cell is inside of a chunk of the given map and inside of its [`MapMask`]
+    fn contains_cell(&self, map_entity: Entity, cell: Cell) -> bool {
+        self.chunk_entity_for_cell(map_entity, cell)
+            .and_then(|chunk_entity| Ok(self.chunk_query.get(chunk_entity)?))
+            .is_ok_and(|(_, chunk, _)| {
+                MapChunk::into_chunk_cell(cell, &chunk.chunk_settings)
+                    .within(chunk.get_chunk_dimensions())
+            })
+    }
+
+    /// Returns the given cells that are inside of a chunk of the selected map and inside of its [`MapMask`]
+    pub(crate) fn cells_in_map(
+        &self,
+        cells: impl IntoIterator<Item = Cell>,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        self.tilemap_query.get(map_entity)?;
+        Ok(cells
+            .into_iter()
+            .filter(|cell| self.contains_cell(map_entity, *cell))
+            .collect())
+    }
+
+    /// Returns the [`MapMask`] of the selected map if it has one
+    pub fn map_mask(&self) -> Option<&MapMask> {
+        self.map_masks.get(self.selected_map_entity()).ok()
+    }
+
+    /// Returns true if the cell is inside of the selected maps [`MapMask`]. Maps without a mask contain every
+    /// cell
+    pub fn is_cell_playable(&self, cell: Cell) -> bool {
+        self.is_cell_in_mask(self.selected_map_entity(), cell)
+    }
+
+    /// Returns a uniformly random cell of the map, only picking cells inside of the maps [`MapMask`] if it has
+    /// one. Returns `None` if the map or its mask has no cells
+    pub fn random_cell(&self, rng: &mut SeededRng) -> Result<Option<Cell>, TilemapManagerError> {
+        if let Some(mask) = self.map_mask() {
+            return Ok(mask.random_cell(rng));
+        }
+        let dimensions = self.dimensions()?;
+        if dimensions.cmpeq(UVec2::ZERO).any() {
+            return Ok(None);
+        }
+        Ok(Some(Cell::new(
+            rng.range(dimensions.x) as i32,
+            rng.range(dimensions.y) as i32,
+        )))
+    }
+
+    /// Gets the tile data for the given [`Cell`] if it exists. Returns the default of the layer for cells
+    /// without data if it has one, see [`set_layer_default`](Self::set_layer_default).
     pub fn get_tile_data(&self, cell: Cell) -> Result<TileData, TilemapManagerError> {
-        let (_, tilemap, map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
-        let (_, chunk, _) = self.chunk_query.get(
-            tilemap
-                .get_chunk_for_cell(cell, map)
-                .ok_or(TilemapManagerError::InvalidChunkPos)?,
-        )?;
+        self.read_tile_data(self.selection.map_layer.to_bits(), cell)
+    }
+
+    /// Returns the id of the dynamic layer registered with the given name on the selected map
+    pub fn dynamic_layer(&self, name: &str) -> Result<Option<DynamicLayerId>, TilemapManagerError> {
+        let (_, tilemap, _, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        Ok(tilemap.dynamic_layers().get(name))
+    }
+
+    /// Returns an error if the id isn't registered in the dynamic layers of the selected map
+    fn registered_dynamic_layer(&self, id: DynamicLayerId) -> Result<(), TilemapManagerError> {
+        let (_, tilemap, _, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        match tilemap.dynamic_layers().contains(id) {
+            true => Ok(()),
+            false => Err(TilemapManagerError::DynamicLayerNotRegistered),
+        }
+    }
+
+    /// Gets the tile data for the given [`Cell`] on the given dynamic layer if it exists
+    pub fn get_dynamic_tile_data(
+        &self,
+        id: DynamicLayerId,
+        cell: Cell,
+    ) -> Result<TileData, TilemapManagerError> {
+        self.read_tile_data(id.to_bits(), cell)
+    }
+
+    /// Returns the neighbours of the cell that are inside the map, using the adjacency of the map type. See
+    /// [`MapData::neighbors`]
+    pub fn get_neighbors(
+        &self,
+        cell: Cell,
+        adjacency: Adjacency,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, _, map, _) = self.tilemap_query.get(map_entity)?;
+        Ok(map
+            .neighbors(cell, adjacency)
+            .into_iter()
+            .filter(|neighbor| self.contains_cell(map_entity, *neighbor))
+            .collect())
+    }
+
+    /// Returns the neighbours of the cell together with their tile data in the current layer. Neighbours
+    /// outside of the map or without tile data are skipped
+    pub fn get_neighbors_data(
+        &self,
+        cell: Cell,
+        adjacency: Adjacency,
+    ) -> Result<Vec<(Cell, TileData)>, TilemapManagerError> {
+        let (_, _, map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        Ok(map
+            .neighbors(cell, adjacency)
+            .into_iter()
+            .filter_map(|neighbor| {
+                self.get_tile_data(neighbor)
+                    .ok()
+                    .map(|tile_data| (neighbor, tile_data))
+            })
+            .collect())
+    }
+
+    /// Returns true if nothing blocks the sight between the cells a and b in the current layer. See
+    /// [`cast_ray`](Self::cast_ray) for which cells block, the two ends themselves never block
+    pub fn line_of_sight(
+        &self,
+        a: Cell,
+        b: Cell,
+        blocks: impl Fn(&TileData) -> bool,
+    ) -> Result<bool, TilemapManagerError> {
+        let hit = self.cast_ray(a, b, blocks)?;
+        Ok(hit.is_none() || hit == Some(b))
+    }
+
+    /// Walks the cells from `from` towards `to` in the current layer and returns the first one after `from`
+    /// that blocks, or `None` if the ray reaches `to` without being blocked. `to` itself is returned if it
+    /// blocks.
+    ///
+    /// A cell blocks if `blocks` accepts its tile data or if it is outside of the map or its [`MapMask`].
+    /// Cells without tile data, like empty cells of a sparse layer, don't block. The cells are chosen by the
+    /// map type, see [`MapData::sight_line`], so a ray can't slip between two diagonal walls.
+    pub fn cast_ray(
+        &self,
+        from: Cell,
+        to: Cell,
+        blocks: impl Fn(&TileData) -> bool,
+    ) -> Result<Option<Cell>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, _, map, _) = self.tilemap_query.get(map_entity)?;
+        let map_layer = self.selection.map_layer.to_bits();
+        Ok(map.sight_line(from, to).into_iter().skip(1).find(|cell| {
+            !self.contains_cell(map_entity, *cell)
+                || self
+                    .read_tile_data(map_layer, *cell)
+                    .is_ok_and(|tile_data| blocks(&tile_data))
+        }))
+    }
+
+    /// Sets every cell of the connected region around start whose tile data the predicate accepts to the given
+    /// tile data and returns the filled cells.
+    ///
+    /// Cells are connected through the edge neighbours of the map type, see [`MapData::neighbors`], so the
+    /// region crosses chunk borders but stops at cells outside of the map or without tile data. The region is
+    /// written with [`set_tile_data_batch`](Self::set_tile_data_batch). Nothing is filled if the predicate
+    /// rejects start.
+    pub fn flood_fill(
+        &mut self,
+        start: Cell,
+        new_data: TileData,
+        predicate: impl Fn(&TileData) -> bool,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        if !predicate(&self.get_tile_data(start)?) {
+            return Ok(vec![]);
+        }
+        let mut region = vec![start];
+        let mut visited = HashSet::from([start]);
+        let mut open = VecDeque::from([start]);
+        while let Some(cell) = open.pop_front() {
+            for (neighbor, tile_data) in self.get_neighbors_data(cell, Adjacency::Edges)? {
+                if predicate(&tile_data) && visited.insert(neighbor) {
+                    region.push(neighbor);
+                    open.push_back(neighbor);
+                }
+            }
+        }
+        self.set_tile_data_batch(region.iter().map(|cell| (*cell, new_data)))?;
+        Ok(region)
+    }
+
+    /// Sets every cell whose coordinates lie between the two corners, inclusive, to the given tile data and
+    /// returns the painted cells. Cells outside of the map are skipped.
+    ///
+    /// The rectangle is taken in cell coordinates, so on maps whose cells aren't laid out in rows, such as
+    /// hexagonal maps, it may not look rectangular on screen.
+    pub fn fill_rect(
+        &mut self,
+        min: Cell,
+        max: Cell,
+        tile_data: TileData,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let (min, max) = (
+            IVec2::new(min.x.min(max.x), min.y.min(max.y)),
+            IVec2::new(min.x.max(max.x), min.y.max(max.y)),
+        );
+        let cells = (min.y..=max.y)
+            .flat_map(|y| (min.x..=max.x).map(move |x| Cell::new(x, y)))
+            .collect();
+        self.paint_cells(cells, tile_data)
+    }
+
+    /// Sets every cell of the line from a to b, including both ends, to the given tile data and returns the
+    /// painted cells. Cells outside of the map are skipped. The cells of the line are chosen by the map type,
+    /// see [`MapData::line`]
+    pub fn draw_line(
+        &mut self,
+        a: Cell,
+        b: Cell,
+        tile_data: TileData,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let (_, _, map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let cells = map.line(a, b);
+        self.paint_cells(cells, tile_data)
+    }
+
+    /// Sets every cell within the radius of the center to the given tile data and returns the painted cells.
+    /// Cells outside of the map are skipped. Which cells are in range is chosen by the map type, see
+    /// [`MapData::cells_in_radius`]
+    pub fn fill_circle(
+        &mut self,
+        center: Cell,
+        radius: u32,
+        tile_data: TileData,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let (_, _, map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let cells = map.cells_in_radius(center, radius);
+        self.paint_cells(cells, tile_data)
+    }
+
+    /// Returns the closest cell at most max_radius away from the center whose tile data on the current layer
+    /// the predicate accepts, or `None` if there is none.
+    ///
+    /// The search expands ring by ring from the center in the geometry of the map type, see
+    /// [`MapData::cells_in_ring`], and returns the first match without reading any cell further away. Cells on
+    /// the same ring are checked in ring order. Chunks without the layer, and sparse chunks without any tile
+    /// data the predicate accepts, are skipped without reading their cells, and the search stops once the rings
+    /// have moved past every chunk of the map. Cells outside of the maps [`MapMask`] are never returned.
+    pub fn find_nearest(
+        &self,
+        center: Cell,
+        predicate: impl Fn(&TileData) -> bool,
+        max_radius: u32,
+    ) -> Result<Option<Cell>, TilemapManagerError> {
+        let mut nearest = None;
+        self.search_rings(center, max_radius, &predicate, |cell| {
+            nearest = Some(cell);
+            false
+        })?;
+        Ok(nearest)
+    }
+
+    /// Returns every cell at most radius away from the center whose tile data on the current layer the
+    /// predicate accepts, ordered ring by ring from the center outward. Chunks are skipped like in
+    /// [`find_nearest`](Self::find_nearest)
+    pub fn find_all_in_radius(
+        &self,
+        center: Cell,
+        radius: u32,
+        predicate: impl Fn(&TileData) -> bool,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let mut cells = vec![];
+        self.search_rings(center, radius, &predicate, |cell| {
+            cells.push(cell);
+            true
+        })?;
+        Ok(cells)
+    }
+
+    /// Calls visit with every cell at most max_radius away from the center whose tile data on the current
+    /// layer the predicate accepts, ring by ring from the center outward, until visit returns false
+    fn search_rings(
+        &self,
+        center: Cell,
+        max_radius: u32,
+        predicate: &impl Fn(&TileData) -> bool,
+        mut visit: impl FnMut(Cell) -> bool,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let map_layer = self.selection.map_layer.to_bits();
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+        let mut chunks: HashMap<ChunkPos, SearchedChunk<MapChunk, TileData>> = HashMap::new();
+        let mut reached_map = false;
+        for radius in 0..=max_radius {
+            let mut ring_in_map = false;
+            for cell in map.cells_in_ring(center, radius) {
+                let searched =
+                    chunks
+                        .entry(map.into_chunk_pos(cell))
+                        .or_insert_with_key(|chunk_pos| {
+                            let Some((_, chunk, _)) = tilemap
+                                .get_chunk(*chunk_pos)
+                                .and_then(|chunk_entity| self.chunk_query.get(chunk_entity).ok())
+                            else {
+                                return SearchedChunk::Missing;
+                            };
+                            let searchable = chunk.data.get(&map_layer).is_some_and(|layer| {
+                                !layer.is_sparse()
+                                    || layer
+                                        .iter_tile_data()
+                                        .any(|(_, tile_data)| predicate(tile_data))
+                            });
+                            match searchable {
+                                true => SearchedChunk::Chunk(chunk),
+                                false => SearchedChunk::Empty,
+                            }
+                        });
+                ring_in_map |= !matches!(searched, SearchedChunk::Missing);
+                let &mut SearchedChunk::Chunk(chunk) = searched else {
+                    continue;
+                };
+                let chunk_cell = MapChunk::into_chunk_cell(cell, &chunk.chunk_settings);
+                if !chunk_cell.within(chunk.get_chunk_dimensions())
+                    || !self.is_cell_in_mask(map_entity, cell)
+                {
+                    continue;
+                }
+                if chunk.data[&map_layer]
+                    .get_tile_data(chunk_cell)
+                    .is_some_and(predicate)
+                    && !visit(cell)
+                {
+                    return Ok(());
+                }
+            }
+            // Rings only grow outward so once they leave the map no later ring can enter it again
+            if reached_map && !ring_in_map {
+                break;
+            }
+            reached_map |= ring_in_map;
+        }
+        Ok(())
+    }
+
+    /// Writes the tile data to every cell inside the map as one batch and returns the written cells
+    fn paint_cells(
+        &mut self,
+        mut cells: Vec<Cell>,
+        tile_data: TileData,
+    ) -> Result<Vec<Cell>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        cells.retain(|cell| self.contains_cell(map_entity, *cell));
+        self.set_tile_data_batch(cells.iter().map(|cell| (*cell, tile_data)))?;
+        Ok(cells)
+    }
+
+    /// Gets the tile data for the given [`Cell`] on the layer with the given bits
+    pub(super) fn read_tile_data(
+        &self,
+        map_layer: u64,
+        cell: Cell,
+    ) -> Result<TileData, TilemapManagerError> {
+        let chunk_entity = self.chunk_entity_for_cell(self.selected_map_entity(), cell)?;
+        let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+        let chunk_cell = checked_chunk_cell(chunk, map_layer, cell)?;
         chunk
-            .get_tile_data(
-                self.layer_index.0,
-                MapChunk::into_chunk_cell(cell, &chunk.chunk_settings),
-            )
+            .data
+            .get(&map_layer)
+            .and_then(|layer| layer.get_tile_data(chunk_cell))
+            .copied()
+            .or_else(|| chunk.layer_default(map_layer))
             .ok_or(TilemapManagerError::TileDataDoesNotExist)
     }
 
     /// Sets the tile data for the given [`Cell`] if it exists.
+    ///
+    /// Calls the maps [`TileWriteHooks`](crate::map::TileWriteHooks) after the write if it has any.
+    /// Bumps the maps [`MapVersion`] and records the write in its [`TileOverrides`].
     pub fn sets_tile_data(
         &mut self,
         tile_data: TileData,
         cell: Cell,
     ) -> Result<(), TilemapManagerError> {
-        let (_, tilemap, map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
-        let (_, mut chunk, _) = self.chunk_query.get_mut(
-            tilemap
+        self.write_tile_data(self.selection.map_layer.to_bits(), tile_data, cell)
+    }
+
+    /// Sets the tile data for the given [`Cell`] on the given dynamic layer like
+    /// [`sets_tile_data`](Self::sets_tile_data)
+    pub fn set_dynamic_tile_data(
+        &mut self,
+        id: DynamicLayerId,
+        tile_data: TileData,
+        cell: Cell,
+    ) -> Result<(), TilemapManagerError> {
+        self.write_tile_data(id.to_bits(), tile_data, cell)
+    }
+
+    /// Sets the tile data for the given [`Cell`] on the layer with the given bits
+    fn write_tile_data(
+        &mut self,
+        map_layer: u64,
+        tile_data: TileData,
+        cell: Cell,
+    ) -> Result<(), TilemapManagerError> {
+        let write = self.apply_tile_write(map_layer, tile_data, cell)?;
+        self.record_history([write]);
+        Ok(())
+    }
+
+    /// Sets the tile data for the given [`Cell`] on the layer with the given bits and returns the write.
+    /// Doesn't record the write in the maps history
+    fn apply_tile_write(
+        &mut self,
+        map_layer: u64,
+        tile_data: TileData,
+        cell: Cell,
+    ) -> Result<TileWrite<TileData>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let chunk_entity = self.chunk_entity_for_cell(map_entity, cell)?;
+        let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        let chunk_cell = checked_chunk_cell(&chunk, map_layer, cell)?;
+        let old = chunk
+            .data
+            .get(&map_layer)
+            .and_then(|layer| layer.get_tile_data(chunk_cell))
+            .copied();
+        chunk.set_tile_data(map_layer, chunk_cell, tile_data);
+        self.bump_layer_version(map_entity, map_layer);
+        if let Ok(mut overrides) = self.overrides.get_mut(map_entity) {
+            overrides.record(map_layer, cell, old, tile_data);
+        }
+        let write = TileWrite {
+            cell,
+            map_layer,
+            old,
+            new: tile_data,
+        };
+        if let Ok(mut hooks) = self.write_hooks.get_mut(map_entity) {
+            hooks.call(&write);
+        }
+        Ok(write)
+    }
+
+    /// Removes the tile data of the given [`Cell`] on the layer with the given bits. Removals aren't passed to
+    /// the maps [`TileWriteHooks`] as there is no written tile data. Returns
+    /// [`TilemapManagerError::TileDataDoesNotExist`] if the layer can't remove tile data
+    #[cfg(feature = "history")]
+    fn apply_tile_removal(
+        &mut self,
+        map_layer: u64,
+        cell: Cell,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let chunk_entity = self.chunk_entity_for_cell(map_entity, cell)?;
+        let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        let chunk_cell = checked_chunk_cell(&chunk, map_layer, cell)?;
+        let old = chunk
+            .remove_tile_data(map_layer, chunk_cell)
+            .ok_or(TilemapManagerError::TileDataDoesNotExist)?;
+        self.bump_layer_version(map_entity, map_layer);
+        if let Ok(mut overrides) = self.overrides.get_mut(map_entity) {
+            overrides.record_removal(map_layer, cell, Some(old));
+        }
+        Ok(())
+    }
+
+    /// Modifies the tile data of the given [`Cell`] in place on the current layer. Returns
+    /// [`TilemapManagerError::TileDataDoesNotExist`] without calling `modify` if the cell has no tile data,
+    /// like an empty cell of a sparse layer.
+    ///
+    /// Avoids copying the tile data out and back in, which is useful for large tile data. The write is
+    /// recorded and passed to the maps [`TileWriteHooks`] like [`sets_tile_data`](Self::sets_tile_data).
+    pub fn modify_tile_data(
+        &mut self,
+        cell: Cell,
+        modify: impl FnOnce(&mut TileData),
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let map_layer = self.selection.map_layer.to_bits();
+        let chunk_entity = self.chunk_entity_for_cell(map_entity, cell)?;
+        let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        let chunk_cell = checked_chunk_cell(&chunk, map_layer, cell)?;
+        let tile_data = chunk
+            .data
+            .get_mut(&map_layer)
+            .and_then(|layer| layer.get_tile_data_mut(chunk_cell))
+            .ok_or(TilemapManagerError::TileDataDoesNotExist)?;
+        let old = *tile_data;
+        modify(tile_data);
+        let new = *tile_data;
+        chunk.mark_dirty(chunk_cell);
+        self.bump_layer_version(map_entity, map_layer);
+        if let Ok(mut overrides) = self.overrides.get_mut(map_entity) {
+            overrides.record(map_layer, cell, Some(old), new);
+        }
+        let write = TileWrite {
+            cell,
+            map_layer,
+            old: Some(old),
+            new,
+        };
+        if let Ok(mut hooks) = self.write_hooks.get_mut(map_entity) {
+            hooks.call(&write);
+        }
+        self.record_history([write]);
+        Ok(())
+    }
+
+    /// Sets the tile data of every given [`Cell`] on the current layer.
+    ///
+    /// Cells are grouped by chunk so every chunk is looked up and marked as changed only once, which is much
+    /// faster than calling [`sets_tile_data`](Self::sets_tile_data) for thousands of cells. Writes are
+    /// recorded and passed to the maps [`TileWriteHooks`] like single writes, and the [`MapVersion`] is
+    /// bumped once for the whole batch.
+    ///
+    /// Every cell is checked before anything is written, so nothing is written if any cell can't be.
+    pub fn set_tile_data_batch(
+        &mut self,
+        tiles: impl IntoIterator<Item = (Cell, TileData)>,
+    ) -> Result<(), TilemapManagerError> {
+        let writes = self.apply_tile_write_batch(self.selection.map_layer.to_bits(), tiles)?;
+        self.record_history(writes);
+        Ok(())
+    }
+
+    /// Sets the tile data of every cell on the layer with the given bits like
+    /// [`set_tile_data_batch`](Self::set_tile_data_batch) and returns the writes. Doesn't record the writes in
+    /// the maps history
+    pub(super) fn apply_tile_write_batch(
+        &mut self,
+        map_layer: u64,
+        tiles: impl IntoIterator<Item = (Cell, TileData)>,
+    ) -> Result<Vec<TileWrite<TileData>>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+
+        let mut chunk_indices: HashMap<Entity, usize> = HashMap::default();
+        let mut chunk_batches: Vec<(Entity, Vec<(Cell, TileData)>)> = vec![];
+        for (cell, tile_data) in tiles {
+            if !self.is_cell_in_mask(map_entity, cell) {
+                return Err(TilemapManagerError::CellOutOfBounds);
+            }
+            let chunk_entity = tilemap
                 .get_chunk_for_cell(cell, map)
-                .ok_or(TilemapManagerError::InvalidChunkPos)?,
-        )?;
-        Ok(chunk.set_tile_data_from_cell(self.layer_index.0.to_bits(), cell, tile_data))
+                .ok_or(TilemapManagerError::InvalidChunkPos)?;
+            let index = *chunk_indices.entry(chunk_entity).or_insert_with(|| {
+                chunk_batches.push((chunk_entity, vec![]));
+                chunk_batches.len() - 1
+            });
+            chunk_batches[index].1.push((cell, tile_data));
+        }
+
+        let mut checked_batches = Vec::with_capacity(chunk_batches.len());
+        for (chunk_entity, batch) in chunk_batches {
+            let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+            let chunk_cells = batch
+                .iter()
+                .map(|(cell, _)| checked_chunk_cell(chunk, map_layer, *cell))
+                .collect::<Result<Vec<ChunkCell>, TilemapManagerError>>()?;
+            checked_batches.push((chunk_entity, batch, chunk_cells));
+        }
+
+        let mut writes = vec![];
+        for (chunk_entity, batch, chunk_cells) in checked_batches {
+            let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+            for ((cell, tile_data), chunk_cell) in batch.into_iter().zip(chunk_cells) {
+                let old = chunk
+                    .data
+                    .get(&map_layer)
+                    .and_then(|layer| layer.get_tile_data(chunk_cell))
+                    .copied();
+                chunk.set_tile_data(map_layer, chunk_cell, tile_data);
+                writes.push(TileWrite {
+                    cell,
+                    map_layer,
+                    old,
+                    new: tile_data,
+                });
+            }
+        }
+
+        if writes.is_empty() {
+            return Ok(writes);
+        }
+        self.bump_layer_version(map_entity, map_layer);
+        if let Ok(mut overrides) = self.overrides.get_mut(map_entity) {
+            for write in writes.iter() {
+                overrides.record(write.map_layer, write.cell, write.old, write.new);
+            }
+        }
+        if let Ok(mut hooks) = self.write_hooks.get_mut(map_entity) {
+            for write in writes.iter() {
+                hooks.call(write);
+            }
+        }
+        Ok(writes)
+    }
+
+    /// Records the writes as one entry in the maps [`TileHistory`](crate::history::TileHistory) if it has
+    /// one. Does nothing without the `history` feature
+    #[cfg_attr(not(feature = "history"), allow(unused_variables))]
+    pub(super) fn record_history(&mut self, writes: impl IntoIterator<Item = TileWrite<TileData>>) {
+        #[cfg(feature = "history")]
+        if let Ok(mut history) = self.history.get_mut(self.selected_map_entity()) {
+            history.record(writes);
+        }
+    }
+
+    /// Returns the [`TileHistory`] of the current map. Returns
+    /// [`TilemapManagerError::HistoryDoesNotExist`] if the map has none
+    #[cfg(feature = "history")]
+    pub fn history(&self) -> Result<&TileHistory<TileData>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        self.tilemap_query.get(map_entity)?;
+        self.history
+            .get(map_entity)
+            .map_err(|_| TilemapManagerError::HistoryDoesNotExist)
+    }
+
+    #[cfg(feature = "history")]
+    fn history_mut(&mut self) -> Result<Mut<'_, TileHistory<TileData>>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        self.tilemap_query.get(map_entity)?;
+        self.history
+            .get_mut(map_entity)
+            .map_err(|_| TilemapManagerError::HistoryDoesNotExist)
+    }
+
+    /// Starts grouping every following write into a single entry of the maps [`TileHistory`] until
+    /// [`end_history_group`](Self::end_history_group) is called. Groups can be nested. Returns
+    /// [`TilemapManagerError::HistoryDoesNotExist`] if the map has no [`TileHistory`]
+    #[cfg(feature = "history")]
+    pub fn begin_history_group(&mut self) -> Result<(), TilemapManagerError> {
+        self.history_mut()?.begin_group();
+        Ok(())
+    }
+
+    /// Ends the innermost group started with [`begin_history_group`](Self::begin_history_group). Returns
+    /// [`TilemapManagerError::HistoryDoesNotExist`] if the map has no [`TileHistory`]
+    #[cfg(feature = "history")]
+    pub fn end_history_group(&mut self) -> Result<(), TilemapManagerError> {
+        self.history_mut()?.end_group();
+        Ok(())
+    }
+
+    /// Reverts the last entry of the maps [`TileHistory`], closing any open group first. Returns false if
+    /// there was nothing to undo and [`TilemapManagerError::HistoryDoesNotExist`] if the map has no
+    /// [`TileHistory`].
+    ///
+    /// The reverting writes bump the [`MapVersion`] and are passed to the maps [`TileWriteHooks`] like any
+    /// other write. Cells that had no tile data before a write have their tile data removed again, which bumps
+    /// the [`MapVersion`] but isn't passed to the hooks. If a write can't be applied, for example because the map was resized since, the error is
+    /// returned and the entry is dropped.
+    #[cfg(feature = "history")]
+    pub fn undo(&mut self) -> Result<bool, TilemapManagerError> {
+        let Some(entry) = self.history_mut()?.pop_undo() else {
+            return Ok(false);
+        };
+        for write in entry.writes.iter().rev() {
+            match write.old {
+                Some(old) => {
+                    self.apply_tile_write(write.map_layer, old, write.cell)?;
+                }
+                None => self.apply_tile_removal(write.map_layer, write.cell)?,
+            }
+        }
+        self.history_mut()?.push_redo(entry);
+        Ok(true)
+    }
+
+    /// Applies the last entry reverted by [`undo`](Self::undo) again. Returns false if there was nothing to
+    /// redo and [`TilemapManagerError::HistoryDoesNotExist`] if the map has no [`TileHistory`]. Errors are
+    /// handled like in [`undo`](Self::undo)
+    #[cfg(feature = "history")]
+    pub fn redo(&mut self) -> Result<bool, TilemapManagerError> {
+        let Some(entry) = self.history_mut()?.pop_redo() else {
+            return Ok(false);
+        };
+        for write in entry.writes.iter() {
+            self.apply_tile_write(write.map_layer, write.new, write.cell)?;
+        }
+        self.history_mut()?.push_undo(entry);
+        Ok(true)
+    }
+
+    /// Returns every cell of the map that differs from the tile data it had when the maps [`TileOverrides`]
+    /// were inserted. Returns [`TilemapManagerError::OverridesDoNotExist`] if the map has no
+    /// [`TileOverrides`]
+    pub fn export_overrides(&self) -> Result<SparseOverrideSet<TileData>, TilemapManagerError>
+    where
+        TileData: PartialEq,
+    {
+        let map_entity = self.selected_map_entity();
+        self.tilemap_query.get(map_entity)?;
+        self.overrides
+            .get(map_entity)
+            .map(|overrides| overrides.export())
+            .map_err(|_| TilemapManagerError::OverridesDoNotExist)
+    }
+
+    /// Exports the overrides like [`export_overrides`](Self::export_overrides) and then makes the current
+    /// tile data of the map the new baseline, so the next export only contains later changes
+    pub fn drain_overrides(&mut self) -> Result<SparseOverrideSet<TileData>, TilemapManagerError>
+    where
+        TileData: PartialEq,
+    {
+        let overrides = self.export_overrides()?;
+        if let Ok(mut tracked) = self.overrides.get_mut(self.selected_map_entity()) {
+            tracked.clear();
+        }
+        Ok(overrides)
+    }
+
+    /// Writes every override in the set to the map, usually right after loading the base map the set was
+    /// exported from. Writes are tracked like any other write, so they are exported again if the map has
+    /// [`TileOverrides`]. Stops at the first override that can't be written
+    pub fn apply_overrides(
+        &mut self,
+        overrides: &SparseOverrideSet<TileData>,
+    ) -> Result<(), TilemapManagerError> {
+        for tile_override in overrides.overrides.iter() {
+            self.write_tile_data(
+                tile_override.map_layer,
+                tile_override.tile_data,
+                tile_override.cell,
+            )?;
+        }
+        Ok(())
     }
 
     /// Gets the [`Entity`] for the given [`Cell`] if it exists.
     pub fn get_tile_entity(&self, cell: Cell) -> Result<Entity, TilemapManagerError> {
-        let (_, tilemap, map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
-        let (_, chunk, _) = self.chunk_query.get(
-            tilemap
-                .get_chunk_for_cell(cell, map)
-                .ok_or(TilemapManagerError::InvalidChunkPos)?,
-        )?;
+        let chunk_entity = self.chunk_entity_for_cell(self.selected_map_entity(), cell)?;
+        let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+        let chunk_cell = checked_chunk_cell(chunk, self.selection.map_layer.to_bits(), cell)?;
         chunk
-            .get_tile_entity(
-                self.layer_index.0,
-                MapChunk::into_chunk_cell(cell, &chunk.chunk_settings),
-            )
+            .get_tile_entity(self.selection.map_layer, chunk_cell)
             .ok_or(TilemapManagerError::TileEntityDoesNotExist)
     }
 
-    /// Sets the [`Entity`] for the given [`Cell`]. Prefer to use [`get_or_spawn_tile_entity`](TilemapManager::get_or_spawn_tile_entity).
+    /// Sets the [`Entity`] for the given [`Cell`], parents it according to the maps [`TileEntityParenting`],
+    /// and inserts its [`TileCell`] and [`TilemapRef`]. Prefer to use [`get_or_spawn_tile_entity`](TilemapManager::get_or_spawn_tile_entity).
     pub fn set_tile_entity(
         &mut self,
         cell: Cell,
         entity: Entity,
     ) -> Result<(), TilemapManagerError> {
-        let (_, tilemap, map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
-        let (_, mut chunk, _) = self.chunk_query.get_mut(
-            tilemap
-                .get_chunk_for_cell(cell, map)
-                .ok_or(TilemapManagerError::InvalidChunkPos)?,
-        )?;
-        let chunk_conversion_settings = chunk.chunk_settings;
-        chunk.set_tile_entity(
-            self.layer_index.0.to_bits(),
-            MapChunk::into_chunk_cell(cell, &chunk_conversion_settings),
-            entity,
-        );
+        let map_entity = self.selected_map_entity();
+        let chunk_entity = self.chunk_entity_for_cell(map_entity, cell)?;
+        let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        let map_layer = self.selection.map_layer.to_bits();
+        let chunk_cell = checked_chunk_cell(&chunk, map_layer, cell)?;
+        chunk.set_tile_entity(map_layer, chunk_cell, entity);
+        let tile_cell = TileCell {
+            cell,
+            chunk_pos: chunk.chunk_pos,
+            map_layer,
+        };
+        self.parent_tile_entity(map_entity, chunk_entity, entity);
+        self.insert_tile_cell(map_entity, entity, tile_cell);
+        self.bump_map_version(map_entity);
 
         Ok(())
     }
 
     /// Gets the [`Entity`] for the given [`Cell`] if it exists or spawns one and returns that if it
-    /// doesn't.
+    /// doesn't. Spawned entities are parented according to the maps [`TileEntityParenting`] and get a
+    /// [`TileCell`] and a [`TilemapRef`].
     pub fn get_or_spawn_tile_entity(&mut self, cell: Cell) -> Result<Entity, TilemapManagerError> {
-        let (_, tilemap, map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
-        let (_, mut chunk, _) = self.chunk_query.get_mut(
-            tilemap
-                .get_chunk_for_cell(cell, map)
-                .ok_or(TilemapManagerError::InvalidChunkPos)?,
-        )?;
+        let map_entity = self.selected_map_entity();
+        let chunk_entity = self.chunk_entity_for_cell(map_entity, cell)?;
+        let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        let map_layer = self.selection.map_layer.to_bits();
+        let chunk_cell = checked_chunk_cell(&chunk, map_layer, cell)?;
 
-        let entity = chunk
-            .get_tile_entity(
-                self.layer_index.0,
-                MapChunk::into_chunk_cell(cell, &chunk.chunk_settings),
-            )
-            .unwrap_or_else(|| {
-                let entity = self.commands.spawn_empty().id();
-                chunk.set_tile_entity_from_cell(self.layer_index.0.to_bits(), cell, entity);
-                entity
-            });
+        if let Some(entity) = chunk.get_tile_entity(self.selection.map_layer, chunk_cell) {
+            return Ok(entity);
+        }
+        let entity = self.commands.spawn_empty().id();
+        chunk.set_tile_entity(map_layer, chunk_cell, entity);
+        let tile_cell = TileCell {
+            cell,
+            chunk_pos: chunk.chunk_pos,
+            map_layer,
+        };
+        self.parent_tile_entity(map_entity, chunk_entity, entity);
+        self.insert_tile_cell(map_entity, entity, tile_cell);
+        self.bump_map_version(map_entity);
 
         Ok(entity)
     }
 
-    /// Gets the [`Entity`] for the given [`Cell`] if it exists or spawns one and returns that if it
-    /// doesn't.
+    /// Despawns the [`Entity`] for the given [`Cell`] if it exists and removes it from the cell.
     pub fn despawn_tile_entity(&mut self, cell: Cell) -> Result<(), TilemapManagerError> {
-        let (_, tilemap, map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
-        let (_, chunk, _) = self.chunk_query.get(
-            tilemap
-                .get_chunk_for_cell(cell, map)
-                .ok_or(TilemapManagerError::InvalidChunkPos)?,
-        )?;
+        let map_entity = self.selected_map_entity();
+        let chunk_entity = self.chunk_entity_for_cell(map_entity, cell)?;
+        let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        let map_layer = self.selection.map_layer.to_bits();
+        let chunk_cell = checked_chunk_cell(&chunk, map_layer, cell)?;
 
-        if let Some(entity) = chunk.get_tile_entity(
-            self.layer_index.0,
-            MapChunk::into_chunk_cell(cell, &chunk.chunk_settings),
-        ) {
+        if let Some(entity) = chunk.remove_tile_entity(map_layer, chunk_cell) {
             self.commands.entity(entity).despawn_recursive();
+            self.bump_map_version(map_entity);
+        };
+
+        Ok(())
+    }
+
+    /// Moves the [`Entity`] of the `from` cell to the `to` cell and returns it. Tile entities parented to
+    /// their chunk are re-parented when they move into another chunk, and the [`TileCell`] of the entity is
+    /// updated to the new cell.
+    ///
+    /// Fails with [`TilemapManagerError::TileEntityDoesNotExist`] if `from` has no entity and with
+    /// [`TilemapManagerError::TileEntityAlreadyExists`] if `to` already has one.
+    pub fn move_tile_entity(
+        &mut self,
+        from: Cell,
+        to: Cell,
+    ) -> Result<Entity, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let map_layer = self.selection.map_layer.to_bits();
+        let from_chunk_entity = self.chunk_entity_for_cell(map_entity, from)?;
+        let to_chunk_entity = self.chunk_entity_for_cell(map_entity, to)?;
+
+        let (_, to_chunk, _) = self.chunk_query.get(to_chunk_entity)?;
+        let to_chunk_cell = checked_chunk_cell(to_chunk, map_layer, to)?;
+        if to_chunk
+            .data
+            .get(&map_layer)
+            .and_then(|layer| layer.get_tile_entity(to_chunk_cell))
+            .is_some()
+        {
+            return Err(TilemapManagerError::TileEntityAlreadyExists);
+        }
+
+        let (_, mut from_chunk, _) = self.chunk_query.get_mut(from_chunk_entity)?;
+        let from_chunk_cell = checked_chunk_cell(&from_chunk, map_layer, from)?;
+        let entity = from_chunk
+            .remove_tile_entity(map_layer, from_chunk_cell)
+            .ok_or(TilemapManagerError::TileEntityDoesNotExist)?;
+        let (_, mut to_chunk, _) = self.chunk_query.get_mut(to_chunk_entity)?;
+        to_chunk.set_tile_entity(map_layer, to_chunk_cell, entity);
+        let tile_cell = TileCell {
+            cell: to,
+            chunk_pos: to_chunk.chunk_pos,
+            map_layer,
+        };
+
+        if from_chunk_entity != to_chunk_entity {
+            self.parent_tile_entity(map_entity, to_chunk_entity, entity);
+        }
+        self.insert_tile_cell(map_entity, entity, tile_cell);
+        self.bump_map_version(map_entity);
+        Ok(entity)
+    }
+
+    /// Despawns the map this manager is set to over several frames, despawning at most `budget_per_frame`
+    /// tile and chunk entities every frame.
+    ///
+    /// Inserts a [`DeferredDespawn`] on the tilemap entity which is torn down by the
+    /// [`DeferredDespawnPlugin`](crate::tilemap_manager::DeferredDespawnPlugin). A
+    /// [`TilemapDespawned`](crate::tilemap_manager::TilemapDespawned) event is sent once the map entity is
+    /// gone. Don't edit the map after calling this.
+    pub fn despawn_tilemap_deferred(
+        &mut self,
+        budget_per_frame: usize,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        let mut chunks = vec![];
+        for y in 0..chunk_counts.y as i32 {
+            for x in 0..chunk_counts.x as i32 {
+                chunks.extend(tilemap.get_chunk(ChunkPos::new(x, y)));
+            }
+        }
+        self.commands
+            .entity(map_entity)
+            .insert(DeferredDespawn::new(budget_per_frame, chunks));
+        Ok(())
+    }
+
+    /// Despawns the map this manager is set to, moving the storage of its chunk layers into the given
+    /// [`ChunkStoragePool`] first so the next map built with
+    /// [`TilemapBuilder::with_storage_pool`] can reuse the memory
+    pub fn despawn_tilemap_pooled(
+        &mut self,
+        pool: &ChunkStoragePool<TileData>,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        let mut chunk_entities = vec![];
+        for y in 0..chunk_counts.y as i32 {
+            for x in 0..chunk_counts.x as i32 {
+                chunk_entities.extend(tilemap.get_chunk(ChunkPos::new(x, y)));
+            }
+        }
+        for chunk_entity in chunk_entities {
+            if let Ok((_, mut chunk, _)) = self.chunk_query.get_mut(chunk_entity) {
+                chunk.bypass_change_detection().release_storage(pool);
+            }
+        }
+        self.commands.entity(map_entity).despawn_recursive();
+        Ok(())
+    }
+
+    /// Deep copies the map this manager is set to into a new tilemap entity and returns it.
+    ///
+    /// Chunks are copied whole, including every layer. `tile_entities` controls what happens to tile
+    /// entities. The [`MapVersion`] is copied. Other components on the tilemap entity, such as
+    /// [`TileWriteHooks`], are not copied.
+    pub fn clone_map(
+        &mut self,
+        tile_entities: TileEntityCloning,
+    ) -> Result<ClonedTilemap, TilemapManagerError>
+    where
+        MapChunk: Clone,
+        Map: Clone,
+    {
+        let original_map_entity = self.selected_map_entity();
+        let (_, tilemap, map, _) = self.tilemap_query.get(original_map_entity)?;
+        let mut tilemap = tilemap.clone();
+        let map = map.clone();
+
+        let mut chunks = vec![];
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        for y in 0..chunk_counts.y as i32 {
+            for x in 0..chunk_counts.x as i32 {
+                let Some(chunk_entity) = tilemap.get_chunk(ChunkPos::new(x, y)) else {
+                    continue;
+                };
+                let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+                chunks.push((chunk_entity, chunk.clone()));
+            }
+        }
+
+        let mut tile_entity_map = CloneEntityMapper::default();
+        if tile_entities == TileEntityCloning::SpawnEmpty {
+            tile_entity_map.commands = Some(&mut self.commands);
+            for (_, chunk) in chunks.iter_mut() {
+                for layer in chunk.data.values_mut() {
+                    layer.map_entities(&mut tile_entity_map);
+                }
+            }
+        }
+        let tile_entities = tile_entity_map.mapping;
+
+        let mut chunk_entity_map = CloneEntityMapper::default();
+        let mut new_chunk_entities = Vec::with_capacity(chunks.len());
+        for (chunk_entity, chunk) in chunks {
+            let new_chunk_entity = self.commands.spawn(chunk).id();
+            chunk_entity_map
+                .mapping
+                .insert(chunk_entity, new_chunk_entity);
+            new_chunk_entities.push(new_chunk_entity);
+        }
+        tilemap.map_entities(&mut chunk_entity_map);
+
+        let version = self
+            .map_versions
+            .get(original_map_entity)
+            .cloned()
+            .unwrap_or_default();
+        let map_entity = self
+            .commands
+            .spawn((tilemap, map, version))
+            .push_children(&new_chunk_entities)
+            .id();
+        Ok(ClonedTilemap {
+            map_entity,
+            tile_entities,
+        })
+    }
+
+    /// Shrinks the map this manager is set to down to the bounds of its content plus `padding` cells on every
+    /// side and returns the offset that was subtracted from every cell.
+    ///
+    /// A cell counts as content if any layer has tile data or a tile entity in it. Every layer is rebuilt
+    /// with new chunks so the map starts at (0, 0) again. Layers that have data in every cell of the new
+    /// bounds are rebuilt as dense layers, all others as sparse layers. Tile entities are kept and moved to
+    /// their new cells. Anything positioned using map cells, such as
+    /// [`PointsOfInterest`](crate::map::PointsOfInterest), must be shifted by the returned offset.
+    ///
+    /// Cells are read from (0, 0) to the maps [`dimensions`](TilemapManager::dimensions), so only map types
+    /// whose cells cover that rectangle, such as square maps, can be cropped. Maps without any content are
+    /// left untouched and return [`IVec2::ZERO`].
+    pub fn crop_to_content(&mut self, padding: u32) -> Result<IVec2, TilemapManagerError>
+    where
+        Map: Clone + Default,
+    {
+        let map_entity = self.selected_map_entity();
+        let dimensions = self.dimensions()?;
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+        let map = map.clone();
+
+        let mut chunk_settings = None;
+        let mut layers: MapLayerCells<TileData> = HashMap::default();
+        let mut bounds: Option<(IVec2, IVec2)> = None;
+        for y in 0..dimensions.y as i32 {
+            for x in 0..dimensions.x as i32 {
+                let cell = Cell::new(x, y);
+                let Some(chunk_entity) = tilemap.get_chunk_for_cell(cell, &map) else {
+                    continue;
+                };
+                let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+                chunk_settings.get_or_insert(chunk.chunk_settings);
+                let chunk_cell = MapChunk::into_chunk_cell(cell, &chunk.chunk_settings);
+                for (map_layer, layer) in chunk.data.iter() {
+                    let layer_cells = layers.entry(*map_layer).or_default();
+                    let tile_data = layer.get_tile_data(chunk_cell).cloned();
+                    let tile_entity = layer.get_tile_entity(chunk_cell);
+                    if let Some(tile_data) = tile_data {
+                        layer_cells.filled_cells += 1;
+                        layer_cells.layer_data.insert(cell, tile_data);
+                    }
+                    if let Some(tile_entity) = tile_entity {
+                        layer_cells.layer_entities.insert(cell, tile_entity);
+                    }
+                    if tile_data.is_some() || tile_entity.is_some() {
+                        let position = IVec2::new(x, y);
+                        bounds = Some(match bounds {
+                            Some((min, max)) => (min.min(position), max.max(position)),
+                            None => (position, position),
+                        });
+                    }
+                }
+            }
+        }
+
+        let (Some((min, max)), Some(chunk_settings)) = (bounds, chunk_settings) else {
+            return Ok(IVec2::ZERO);
+        };
+        let offset = min - IVec2::splat(padding as i32);
+        let size = (max - min + IVec2::ONE).as_uvec2() + UVec2::splat(padding * 2);
+
+        let shift = |cell: Cell| Cell::new(cell.x - offset.x, cell.y - offset.y);
+        let new_layers = layers
+            .into_iter()
+            .map(|(map_layer, layer_cells)| {
+                let layer_entities = layer_cells
+                    .layer_entities
+                    .into_iter()
+                    .map(|(cell, entity)| (shift(cell), entity))
+                    .collect();
+                let layer_data = layer_cells
+                    .layer_data
+                    .into_iter()
+                    .map(|(cell, tile_data)| (shift(cell), tile_data))
+                    .collect();
+                (
+                    map_layer,
+                    tilemap_layer_from_cells(layer_data, size, layer_entities),
+                )
+            })
+            .collect();
+        self.rebuild_chunks(map_entity, map, chunk_settings, new_layers)?;
+
+        Ok(offset)
+    }
+
+    /// Grows or shrinks the map to the given size, keeping the tile data and tile entities of every cell that
+    /// is still inside of it.
+    ///
+    /// New cells of the main layer, and of every other layer that had tile data in all of its cells, are
+    /// filled with `fill`. New cells of all other layers are left empty. Tile entities of cells that are cut
+    /// off are despawned. Every chunk is rebuilt, so anything stored on the chunk entities themselves is lost.
+    /// A [`MapMask`] is not resized, so new cells outside of it stay unplayable until it is replaced.
+    ///
+    /// Like [`crop_to_content`](TilemapManager::crop_to_content), only map types whose cells cover the
+    /// rectangle from (0, 0) to their [`dimensions`](TilemapManager::dimensions) can be resized.
+    pub fn resize_map(&mut self, new_size: UVec2, fill: TileData) -> Result<(), TilemapManagerError>
+    where
+        Map: Clone + Default,
+    {
+        if new_size.x == 0 || new_size.y == 0 {
+            return Err(TilemapManagerError::InvalidMapSize(new_size));
+        }
+        let map_entity = self.selected_map_entity();
+        let dimensions = self.dimensions()?;
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+        let map = map.clone();
+
+        let mut chunk_settings = None;
+        let mut layers: MapLayerCells<TileData> = HashMap::default();
+        let mut dropped_tile_entities = vec![];
+        for y in 0..dimensions.y as i32 {
+            for x in 0..dimensions.x as i32 {
+                let cell = Cell::new(x, y);
+                let Some(chunk_entity) = tilemap.get_chunk_for_cell(cell, &map) else {
+                    continue;
+                };
+                let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+                chunk_settings.get_or_insert(chunk.chunk_settings);
+                let chunk_cell = MapChunk::into_chunk_cell(cell, &chunk.chunk_settings);
+                let kept = (x as u32) < new_size.x && (y as u32) < new_size.y;
+                for (map_layer, layer) in chunk.data.iter() {
+                    let layer_cells = layers.entry(*map_layer).or_default();
+                    if let Some(tile_data) = layer.get_tile_data(chunk_cell) {
+                        layer_cells.filled_cells += 1;
+                        if kept {
+                            layer_cells.layer_data.insert(cell, *tile_data);
+                        }
+                    }
+                    if let Some(tile_entity) = layer.get_tile_entity(chunk_cell) {
+                        match kept {
+                            true => {
+                                layer_cells.layer_entities.insert(cell, tile_entity);
+                            }
+                            false => dropped_tile_entities.push(tile_entity),
+                        }
+                    }
+                }
+            }
+        }
+        let chunk_settings = chunk_settings.ok_or(TilemapManagerError::InvalidChunkPos)?;
+
+        let main_layer_bits = MapLayers::default().to_bits();
+        let new_layers = layers
+            .into_iter()
+            .map(|(map_layer, mut layer_cells)| {
+                if map_layer == main_layer_bits
+                    || layer_cells.filled_cells == dimensions.x * dimensions.y
+                {
+                    for y in 0..new_size.y as i32 {
+                        for x in 0..new_size.x as i32 {
+                            if x as u32 >= dimensions.x || y as u32 >= dimensions.y {
+                                layer_cells.layer_data.insert(Cell::new(x, y), fill);
+                            }
+                        }
+                    }
+                }
+                (
+                    map_layer,
+                    tilemap_layer_from_cells(
+                        layer_cells.layer_data,
+                        new_size,
+                        layer_cells.layer_entities,
+                    ),
+                )
+            })
+            .collect();
+
+        for tile_entity in dropped_tile_entities {
+            self.commands.entity(tile_entity).despawn_recursive();
+        }
+        self.rebuild_chunks(map_entity, map, chunk_settings, new_layers)
+    }
+
+    /// Replaces every chunk of the map with new chunks built from the given layers, each of which must have
+    /// the new size of the map. Tile entities in the layers are kept and the old chunks are despawned
+    fn rebuild_chunks(
+        &mut self,
+        map_entity: Entity,
+        map: Map,
+        chunk_settings: MapChunk::ChunkSettings,
+        mut new_layers: Vec<(u64, TilemapLayer<TileData>)>,
+    ) -> Result<(), TilemapManagerError>
+    where
+        Map: Clone + Default,
+    {
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let dynamic_layers = tilemap.dynamic_layers().clone();
+        let mut old_chunk_entities = vec![];
+        let mut layer_defaults = HashMap::new();
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        for y in 0..chunk_counts.y as i32 {
+            for x in 0..chunk_counts.x as i32 {
+                if let Some(chunk_entity) = tilemap.get_chunk(ChunkPos::new(x, y)) {
+                    old_chunk_entities.push(chunk_entity);
+                    if let Ok((_, chunk, _)) = self.chunk_query.get(chunk_entity) {
+                        for map_layer in chunk.layers() {
+                            if let Some(tile_data) = chunk.layer_default(map_layer) {
+                                layer_defaults.insert(map_layer, tile_data);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let main_layer_bits = MapLayers::default().to_bits();
+        let main_layer = new_layers
+            .iter()
+            .position(|(map_layer, _)| *map_layer == main_layer_bits)
+            .map(|index| new_layers.swap_remove(index).1)
+            .ok_or(TilemapManagerError::LayerDoesNotExist)?;
+        let max_chunk_size = map.max_chunk_size();
+        let mut builder = TilemapBuilder::<TileData, MapLayers, MapChunk, Map>::new(
+            main_layer.clone(),
+            map.clone(),
+            chunk_settings,
+        );
+        let mut chunks =
+            builder.create_new_chunks_from_layer(&main_layer, chunk_settings, max_chunk_size);
+        for (map_layer, layer) in new_layers.iter() {
+            builder.add_layer_to_chunks(*map_layer, &mut chunks, layer, max_chunk_size);
+        }
+        for chunk in chunks.iter_mut().flatten() {
+            for (map_layer, tile_data) in layer_defaults.iter() {
+                chunk.set_layer_default(*map_layer, Some(*tile_data));
+            }
+        }
+
+        let mut new_chunk_entities = vec![];
+        let mut moved_tile_entities = vec![];
+        let chunk_entity_grid: Vec<Vec<Entity>> = chunks
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|chunk| {
+                        let tile_entities: Vec<Entity> = chunk
+                            .data
+                            .values()
+                            .flat_map(|layer| layer.iter_tile_entities().map(|(_, entity)| entity))
+                            .collect();
+                        let chunk_entity = self.commands.spawn(chunk).id();
+                        new_chunk_entities.push(chunk_entity);
+                        moved_tile_entities.push((chunk_entity, tile_entities));
+                        chunk_entity
+                    })
+                    .collect()
+            })
+            .collect();
+        // Tile entities parented to the old chunks are moved over before the old chunks are despawned
+        if self.tile_entity_parenting(map_entity) == TileEntityParenting::Chunk {
+            for (chunk_entity, tile_entities) in moved_tile_entities {
+                self.commands
+                    .entity(chunk_entity)
+                    .push_children(&tile_entities);
+            }
+        }
+        for chunk_entity in old_chunk_entities {
+            self.commands.entity(chunk_entity).despawn_recursive();
+        }
+        let chunks = Chunks::new(
+            Chunks::new_chunk_entity_grid(chunk_entity_grid),
+            max_chunk_size,
+        );
+        // The new chunks only exist once the commands are applied so the tilemap is replaced at the same time
+        self.commands
+            .entity(map_entity)
+            .insert(Tilemap::new(chunks).with_dynamic_layers(dynamic_layers))
+            .push_children(&new_chunk_entities);
+
+        if let Ok(mut version) = self.map_versions.get_mut(map_entity) {
+            for map_layer in std::iter::once(main_layer_bits)
+                .chain(new_layers.iter().map(|(map_layer, _)| *map_layer))
+            {
+                version.bump(map_layer);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds a new layer with the given data to every chunk of the map, the same way
+    /// [`TilemapBuilder::add_layer`] does at build time. Useful for layers that are unlocked during a game,
+    /// such as fog of war or ownership overlays.
+    ///
+    /// The layer must have the same dimensions as the map and must not exist yet.
+    pub fn add_layer(
+        &mut self,
+        map_layer: MapLayers,
+        layer: TilemapLayer<TileData>,
+    ) -> Result<(), TilemapManagerError> {
+        self.insert_layer(map_layer.to_bits(), layer)
+    }
+
+    /// Adds the data of a layer registered in the maps
+    /// [`DynamicLayerRegistry`](crate::map::DynamicLayerRegistry) to every chunk of the map like
+    /// [`add_layer`](Self::add_layer)
+    pub fn add_dynamic_layer(
+        &mut self,
+        id: DynamicLayerId,
+        layer: TilemapLayer<TileData>,
+    ) -> Result<(), TilemapManagerError> {
+        self.registered_dynamic_layer(id)?;
+        self.insert_layer(id.to_bits(), layer)
+    }
+
+    /// Adds a new layer with the given bits and data to every chunk of the map
+    fn insert_layer(
+        &mut self,
+        map_layer: u64,
+        layer: TilemapLayer<TileData>,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let dimensions = self.dimensions()?;
+        if layer.dimensions() != dimensions {
+            return Err(TilemapManagerError::LayerDimensionsMismatch(
+                layer.dimensions(),
+                dimensions,
+            ));
+        }
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+        let mut chunk_entities = vec![];
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+        ) {
+            let chunk_entity = tilemap
+                .get_chunk(chunk_pos)
+                .ok_or(TilemapManagerError::InvalidChunkPos)?;
+            if self
+                .chunk_query
+                .get(chunk_entity)?
+                .1
+                .data
+                .contains_key(&map_layer)
+            {
+                return Err(TilemapManagerError::LayerAlreadyExists);
+            }
+            chunk_entities.push((chunk_pos, chunk_entity));
+        }
+        let in_bounds = |cell: &Cell| {
+            cell.x >= 0
+                && cell.y >= 0
+                && (cell.x as u32) < dimensions.x
+                && (cell.y as u32) < dimensions.y
+        };
+
+        let entities = match &layer {
+            TilemapLayer::Sparse(data, _, entities) => {
+                if !data.keys().chain(entities.keys()).all(in_bounds) {
+                    return Err(TilemapManagerError::CellOutOfBounds);
+                }
+                for (_, chunk_entity) in chunk_entities.iter() {
+                    let (_, mut chunk, _) = self.chunk_query.get_mut(*chunk_entity)?;
+                    chunk.add_layer(map_layer, ChunkLayerType::Sparse(HashMap::new()));
+                }
+                for (cell, tile_data) in data.iter() {
+                    let chunk_entity = tilemap
+                        .get_chunk_for_cell(*cell, map)
+                        .ok_or(TilemapManagerError::InvalidChunkPos)?;
+                    let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+                    chunk.set_tile_data_from_cell(map_layer, *cell, *tile_data);
+                }
+                entities
+            }
+            TilemapLayer::Dense(data, entities) => {
+                if !entities.keys().all(in_bounds) {
+                    return Err(TilemapManagerError::CellOutOfBounds);
+                }
+                for (chunk_pos, chunk_entity) in chunk_entities.iter() {
+                    let chunk_data = map.break_data_vecs_down_into_chunk_data(
+                        data,
+                        *chunk_pos,
+                        map.max_chunk_size(),
+                    );
+                    let (_, mut chunk, _) = self.chunk_query.get_mut(*chunk_entity)?;
+                    chunk.add_layer(map_layer, ChunkLayerType::Dense(chunk_data));
+                }
+                entities
+            }
+            TilemapLayer::Generated(generator, entities) => {
+                if !entities.keys().all(in_bounds) {
+                    return Err(TilemapManagerError::CellOutOfBounds);
+                }
+                for (chunk_pos, chunk_entity) in chunk_entities.iter() {
+                    let chunk_data = map.break_data_vecs_down_into_chunk_data(
+                        generator,
+                        *chunk_pos,
+                        map.max_chunk_size(),
+                    );
+                    let (_, mut chunk, _) = self.chunk_query.get_mut(*chunk_entity)?;
+                    chunk.add_layer(map_layer, ChunkLayerType::Dense(chunk_data));
+                }
+                entities
+            }
+        };
+        for (cell, entity) in entities.iter() {
+            let chunk_entity = tilemap
+                .get_chunk_for_cell(*cell, map)
+                .ok_or(TilemapManagerError::InvalidChunkPos)?;
+            let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+            chunk.set_tile_entity_from_cell(map_layer, *cell, *entity);
+        }
+        self.bump_layer_version(map_entity, map_layer);
+        Ok(())
+    }
+
+    /// Removes the layer from every chunk of the map and despawns its tile entities. The main layer can't be
+    /// removed.
+    pub fn remove_layer(&mut self, map_layer: MapLayers) -> Result<(), TilemapManagerError> {
+        let map_layer = map_layer.to_bits();
+        if map_layer == MapLayers::default().to_bits() {
+            return Err(TilemapManagerError::CannotRemoveMainLayer);
+        }
+        self.delete_layer(map_layer)
+    }
+
+    /// Removes the data of a dynamic layer from every chunk of the map like
+    /// [`remove_layer`](Self::remove_layer). The layer stays registered, so its id can be filled again with
+    /// [`add_dynamic_layer`](Self::add_dynamic_layer)
+    pub fn remove_dynamic_layer(&mut self, id: DynamicLayerId) -> Result<(), TilemapManagerError> {
+        self.registered_dynamic_layer(id)?;
+        self.delete_layer(id.to_bits())
+    }
+
+    /// Sets the data returned for cells of the current layer that have no data, or clears it if `None`. Reads
+    /// of such cells return [`TilemapManagerError::TileDataDoesNotExist`] without a default. Usually set at
+    /// build time with [`TilemapBuilder::set_layer_default`]
+    pub fn set_layer_default(
+        &mut self,
+        tile_data: Option<TileData>,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let map_layer = self.selection.map_layer.to_bits();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+        let mut found_layer = false;
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+        ) {
+            let Some(chunk_entity) = tilemap.get_chunk(chunk_pos) else {
+                continue;
+            };
+            let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+            if chunk.data.contains_key(&map_layer) {
+                chunk.set_layer_default(map_layer, tile_data);
+                found_layer = true;
+            }
+        }
+        if !found_layer {
+            return Err(TilemapManagerError::LayerDoesNotExist);
+        }
+        self.bump_layer_version(map_entity, map_layer);
+        Ok(())
+    }
+
+    /// Removes the layer with the given bits from every chunk of the map and despawns its tile entities
+    fn delete_layer(&mut self, map_layer: u64) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+        let mut found_layer = false;
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+        ) {
+            let Some(chunk_entity) = tilemap.get_chunk(chunk_pos) else {
+                continue;
+            };
+            let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+            let Some(layer) = chunk.data.remove(&map_layer) else {
+                continue;
+            };
+            chunk.set_layer_default(map_layer, None);
+            found_layer = true;
+            for (_, entity) in layer.iter_tile_entities() {
+                self.commands.entity(entity).despawn_recursive();
+            }
+        }
+        if !found_layer {
+            return Err(TilemapManagerError::LayerDoesNotExist);
+        }
+        self.bump_layer_version(map_entity, map_layer);
+        Ok(())
+    }
+
+    /// Adds a corner layer for the current layer to every chunk of the map, filling every corner with
+    /// `tile_data`. Corner layers are only meaningful on square maps.
+    ///
+    /// Chunks without a [`ChunkCorners`] component get one inserted through [`Commands`], so the layer can
+    /// only be accessed on those chunks once the commands have been applied.
+    pub fn add_corner_layer(&mut self, tile_data: TileData) -> Result<(), TilemapManagerError> {
+        let (_, tilemap, _, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let map_layer = self.selection.map_layer.to_bits();
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        for y in 0..chunk_counts.y as i32 {
+            for x in 0..chunk_counts.x as i32 {
+                let Some(chunk_entity) = tilemap.get_chunk(ChunkPos::new(x, y)) else {
+                    continue;
+                };
+                let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+                let chunk_dimensions = chunk.get_chunk_dimensions();
+                match self.chunk_corners.get_mut(chunk_entity) {
+                    Ok(mut corners) => corners.add_layer(map_layer, chunk_dimensions, tile_data),
+                    Err(_) => {
+                        let mut corners = ChunkCorners::default();
+                        corners.add_layer(map_layer, chunk_dimensions, tile_data);
+                        self.commands.entity(chunk_entity).insert(corners);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the corner data of the given [`CornerId`] in the current layer
+    pub fn get_corner_data(&self, corner: CornerId) -> Result<TileData, TilemapManagerError> {
+        let (_, tilemap, map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let map_layer = self.selection.map_layer.to_bits();
+        let mut found_chunk = false;
+        for (cell, offset) in corner
+            .adjacent_cells()
+            .into_iter()
+            .zip(CornerId::ADJACENT_CELL_OFFSETS)
+        {
+            if cell.x < 0 || cell.y < 0 {
+                continue;
+            }
+            let Some(chunk_entity) = tilemap.get_chunk_for_cell(cell, map) else {
+                continue;
+            };
+            found_chunk = true;
+            let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+            let local = MapChunk::into_chunk_cell(cell, &chunk.chunk_settings).as_ivec2() - offset;
+            if let Some(tile_data) = self
+                .chunk_corners
+                .get(chunk_entity)
+                .ok()
+                .and_then(|corners| corners.get(map_layer, local.as_uvec2()))
+            {
+                return Ok(tile_data);
+            }
+        }
+        match found_chunk {
+            true => Err(TilemapManagerError::CornerDataDoesNotExist),
+            false => Err(TilemapManagerError::InvalidChunkPos),
+        }
+    }
+
+    /// Sets the corner data of the given [`CornerId`] in the current layer. Corners on the edge of a chunk
+    /// are written to every chunk that shares them.
+    pub fn set_corner_data(
+        &mut self,
+        tile_data: TileData,
+        corner: CornerId,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, map, _) = self.tilemap_query.get(map_entity)?;
+        let map_layer = self.selection.map_layer.to_bits();
+        let mut found_chunk = false;
+        let mut written = false;
+        for (cell, offset) in corner
+            .adjacent_cells()
+            .into_iter()
+            .zip(CornerId::ADJACENT_CELL_OFFSETS)
+        {
+            if cell.x < 0 || cell.y < 0 {
+                continue;
+            }
+            let Some(chunk_entity) = tilemap.get_chunk_for_cell(cell, map) else {
+                continue;
+            };
+            found_chunk = true;
+            let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+            let local = MapChunk::into_chunk_cell(cell, &chunk.chunk_settings).as_ivec2() - offset;
+            if let Ok(mut corners) = self.chunk_corners.get_mut(chunk_entity) {
+                written |= corners.set(map_layer, local.as_uvec2(), tile_data);
+            }
+        }
+        if !found_chunk {
+            return Err(TilemapManagerError::InvalidChunkPos);
+        }
+        if !written {
+            return Err(TilemapManagerError::CornerDataDoesNotExist);
+        }
+        self.bump_map_version(map_entity);
+        Ok(())
+    }
+
+    /// Gets the data of the four corners of the given [`Cell`] in the current layer, in the order bottom left,
+    /// bottom right, top left, top right. Useful for marching squares and dual grid autotiling.
+    pub fn get_cell_corners(&self, cell: Cell) -> Result<[TileData; 4], TilemapManagerError> {
+        let [bottom_left, bottom_right, top_left, top_right] = CornerId::of_cell(cell);
+        Ok([
+            self.get_corner_data(bottom_left)?,
+            self.get_corner_data(bottom_right)?,
+            self.get_corner_data(top_left)?,
+            self.get_corner_data(top_right)?,
+        ])
+    }
+
+    /// Returns every [`Cell`] of the map overlapping the world space circle. Requires a [`TilemapGeometry`]
+    /// on the map entity.
+    pub fn cells_in_world_circle(
+        &self,
+        center: Vec2,
+        radius: f32,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        let bounds = Rect::from_center_half_size(center, Vec2::splat(radius.max(0.0)));
+        self.cells_in_world_bounds(bounds, move |cell_rect| {
+            rect_overlaps_circle(cell_rect, center, radius)
+        })
+    }
+
+    /// Returns every [`Cell`] of the map overlapping the world space rect. Requires a [`TilemapGeometry`] on
+    /// the map entity.
+    pub fn cells_in_world_aabb(
+        &self,
+        rect: Rect,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        self.cells_in_world_bounds(rect, move |cell_rect| rect_overlaps_rect(cell_rect, rect))
+    }
+
+    /// Returns every [`Cell`] of the map overlapping the world space polygon. The polygon is closed
+    /// automatically and may be concave. Requires a [`TilemapGeometry`] on the map entity.
+    pub fn cells_in_world_polygon(
+        &self,
+        polygon: &[Vec2],
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        let polygon = polygon.to_vec();
+        self.cells_in_world_bounds(polygon_bounds(&polygon), move |cell_rect| {
+            rect_overlaps_polygon(cell_rect, &polygon)
+        })
+    }
+
+    /// Returns the world space rect covered by the map. Requires a [`TilemapGeometry`] on the map entity.
+    pub fn world_bounds(&self) -> Result<Rect, TilemapManagerError> {
+        let geometry = self
+            .geometry
+            .get(self.selected_map_entity())
+            .map_err(|_| TilemapManagerError::GeometryDoesNotExist)?;
+        let dimensions = self.dimensions()?.as_ivec2();
+        Ok(geometry
+            .cell_rect(Cell::new(0, 0))
+            .union(geometry.cell_rect(Cell::new(dimensions.x - 1, dimensions.y - 1))))
+    }
+
+    /// Returns the chunks of the map within `radius` chunks of any of the given world space positions, such as
+    /// the positions of players and AI. Requires a [`TilemapGeometry`] on the map entity.
+    ///
+    /// Useful for restricting expensive per tile simulation to the area around the players, see
+    /// [`SimulationGatingPlugin`](crate::simulation::SimulationGatingPlugin) for a plugin that marks these
+    /// chunks automatically.
+    pub fn chunks_near(
+        &self,
+        positions: impl IntoIterator<Item = Vec2>,
+        radius: u32,
+    ) -> Result<HashSet<ChunkPos>, TilemapManagerError> {
+        let geometry = self
+            .geometry
+            .get(self.selected_map_entity())
+            .map_err(|_| TilemapManagerError::GeometryDoesNotExist)?;
+        let (_, tilemap, map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let mut chunks = HashSet::default();
+        for position in positions {
+            let center = map.into_chunk_pos(geometry.world_to_cell(position));
+            chunks.extend(tilemap.chunks_in_radius(center, radius));
+        }
+        Ok(chunks)
+    }
+
+    /// Returns the cells inside the world space bounds that `overlaps` accepts the rect of, only visiting
+    /// chunks that intersect the bounds
+    fn cells_in_world_bounds(
+        &self,
+        bounds: Rect,
+        overlaps: impl Fn(Rect) -> bool + 'static,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        let geometry = *self
+            .geometry
+            .get(self.selected_map_entity())
+            .map_err(|_| TilemapManagerError::GeometryDoesNotExist)?;
+        let min_cell = geometry.world_to_cell(bounds.min);
+        let max_cell = geometry.world_to_cell(bounds.max);
+        // Inverted bounds contain no cells
+        let max_cell = match bounds.min.cmple(bounds.max).all() {
+            true => IVec2::new(max_cell.x, max_cell.y),
+            false => IVec2::splat(-1),
         };
+        self.cells_in_cell_range(IVec2::new(min_cell.x, min_cell.y), max_cell, move |cell| {
+            overlaps(geometry.cell_rect(cell))
+        })
+    }
+
+    /// Returns the cells of the map between the inclusive min and max cell that `filter` accepts, only
+    /// visiting chunks that intersect the range
+    fn cells_in_cell_range(
+        &self,
+        min_cell: IVec2,
+        max_cell: IVec2,
+        filter: impl Fn(Cell) -> bool + 'static,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let map_mask = self.map_masks.get(map_entity).ok().cloned();
+
+        let mut cell_ranges: Vec<(IVec2, IVec2)> = vec![];
+        let min_cell = min_cell.max(IVec2::ZERO);
+        let max_chunk_size = tilemap.get_chunks_max_size().as_ivec2();
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        if max_cell.cmpge(min_cell).all() {
+            for chunk_pos in ChunkPos::iter_rect(
+                (min_cell / max_chunk_size).into(),
+                (max_cell / max_chunk_size).into(),
+            ) {
+                if !chunk_pos.within(chunk_counts) {
+                    continue;
+                }
+                let Some(chunk_entity) = tilemap.get_chunk(chunk_pos) else {
+                    continue;
+                };
+                let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+                let chunk_min = chunk_pos.as_ivec2() * max_chunk_size;
+                let chunk_max = chunk_min + chunk.get_chunk_dimensions().as_ivec2() - IVec2::ONE;
+                let lo = chunk_min.max(min_cell);
+                let hi = chunk_max.min(max_cell);
+                if hi.cmpge(lo).all() {
+                    cell_ranges.push((lo, hi));
+                }
+            }
+        }
+
+        Ok(cell_ranges
+            .into_iter()
+            .flat_map(|(lo, hi)| {
+                (lo.y..=hi.y).flat_map(move |y| (lo.x..=hi.x).map(move |x| Cell::new(x, y)))
+            })
+            .filter(move |cell| map_mask.as_ref().is_none_or(|mask| mask.contains(*cell)))
+            .filter(move |cell| filter(*cell)))
+    }
+
+    /// Returns every [`Cell`] of the map overlapping the rect given in millipixels. Requires a
+    /// [`FixedTilemapGeometry`] on the map entity and the `fixed_point` feature.
+    #[cfg(feature = "fixed_point")]
+    pub fn cells_in_fixed_aabb(
+        &self,
+        rect: FixedRect,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        self.cells_in_fixed_bounds(rect, move |cell_rect| cell_rect.overlaps(rect))
+    }
+
+    /// Returns every [`Cell`] of the map overlapping the circle given in millipixels. Requires a
+    /// [`FixedTilemapGeometry`] on the map entity and the `fixed_point` feature.
+    #[cfg(feature = "fixed_point")]
+    pub fn cells_in_fixed_circle(
+        &self,
+        center: bevy::math::I64Vec2,
+        radius: i64,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        let half_size = bevy::math::I64Vec2::splat(radius.max(0));
+        let bounds = FixedRect::from_corners(center - half_size, center + half_size);
+        self.cells_in_fixed_bounds(bounds, move |cell_rect| {
+            cell_rect.overlaps_circle(center, radius)
+        })
+    }
+
+    /// Returns the rect covered by the map in millipixels. Requires a [`FixedTilemapGeometry`] on the map
+    /// entity and the `fixed_point` feature.
+    #[cfg(feature = "fixed_point")]
+    pub fn fixed_world_bounds(&self) -> Result<FixedRect, TilemapManagerError> {
+        let geometry = self
+            .fixed_geometry
+            .get(self.selected_map_entity())
+            .map_err(|_| TilemapManagerError::GeometryDoesNotExist)?;
+        let dimensions = self.dimensions()?.as_ivec2();
+        Ok(geometry
+            .cell_rect(Cell::new(0, 0))
+            .union(geometry.cell_rect(Cell::new(dimensions.x - 1, dimensions.y - 1))))
+    }
+
+    /// Returns the cells inside the bounds given in millipixels that `overlaps` accepts the rect of
+    #[cfg(feature = "fixed_point")]
+    fn cells_in_fixed_bounds(
+        &self,
+        bounds: FixedRect,
+        overlaps: impl Fn(FixedRect) -> bool + 'static,
+    ) -> Result<impl Iterator<Item = Cell>, TilemapManagerError> {
+        let geometry = *self
+            .fixed_geometry
+            .get(self.selected_map_entity())
+            .map_err(|_| TilemapManagerError::GeometryDoesNotExist)?;
+        let min_cell = geometry.world_to_cell(bounds.min);
+        let max_cell = geometry.world_to_cell(bounds.max);
+        self.cells_in_cell_range(
+            IVec2::new(min_cell.x, min_cell.y),
+            IVec2::new(max_cell.x, max_cell.y),
+            move |cell| overlaps(geometry.cell_rect(cell)),
+        )
+    }
+
+    /// Compacts the map this manager is set to using the default [`CompactionSettings`]. See
+    /// [`compact_with_settings`](TilemapManager::compact_with_settings)
+    pub fn compact(&mut self) -> Result<CompactionReport, TilemapManagerError>
+    where
+        TileData: PartialEq,
+    {
+        self.compact_with_settings(&CompactionSettings::default())
+    }
+
+    /// Releases memory held by every chunk of the map this manager is set to and returns how much was
+    /// released.
+    ///
+    /// Every layer is compacted with [`ChunkLayer::compact`] without changing what any cell reads:
+    ///
+    /// - Sparse entries holding the layer default of their chunk are removed. Sparse layers without a layer
+    ///   default keep every entry.
+    /// - Dense layers where at most [`CompactionSettings::max_dense_fill`] of the cells don't hold the layer
+    ///   default are converted to sparse layers without those cells. Layers without a layer default treat the
+    ///   default `TileData` as empty and get it as their layer default when they are converted.
+    /// - Sparse layers whose layer default is the default `TileData` and where at least
+    ///   [`CompactionSettings::min_sparse_fill`] of the cells are not default are converted to dense layers
+    ///   first, filling their missing cells with the default `TileData`.
+    ///
+    /// Layers that changed storage or lost entries have their [`MapVersion`] bumped. Unused capacity is
+    /// released from every layer. The result is recorded in the maps [`TilemapStats`] which is inserted if the
+    /// map has none. See [`set_layer_default`](Self::set_layer_default)
+    pub fn compact_with_settings(
+        &mut self,
+        settings: &CompactionSettings,
+    ) -> Result<CompactionReport, TilemapManagerError>
+    where
+        TileData: PartialEq,
+    {
+        let map_entity = self.selected_map_entity();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        let mut chunk_entities = vec![];
+        for y in 0..chunk_counts.y as i32 {
+            for x in 0..chunk_counts.x as i32 {
+                if let Some(chunk_entity) = tilemap.get_chunk(ChunkPos::new(x, y)) {
+                    chunk_entities.push(chunk_entity);
+                }
+            }
+        }
+
+        let mut report = CompactionReport::default();
+        let mut changed_layers = HashSet::new();
+        for chunk_entity in chunk_entities {
+            let (_, mut chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+            let mut changed = false;
+            let map_layers: Vec<u64> = chunk.data.keys().copied().collect();
+            for map_layer in map_layers {
+                let layer_default = chunk.layer_default(map_layer);
+                // Releasing capacity doesn't change what can be read from the chunk so it only triggers change
+                // detection if the storage of a layer changed
+                let chunk = chunk.bypass_change_detection();
+                let Some(layer) = chunk.data.get_mut(&map_layer) else {
+                    continue;
+                };
+                // Only cells that read the same without their tile data are empty. Dense layers have no cells
+                // without data, so they can drop the default `TileData` as long as it becomes their layer default
+                let empty = match layer_default {
+                    Some(tile_data) => Some(tile_data),
+                    None if !layer.is_sparse() => Some(TileData::default()),
+                    None => None,
+                };
+                let is_empty = |tile_data: &TileData| empty.as_ref() == Some(tile_data);
+                let dimensions = layer.get_chunk_dimensions();
+                let cell_count = (dimensions.x * dimensions.y) as f32;
+                let densified = layer.is_sparse()
+                    && layer_default == Some(TileData::default())
+                    && cell_count > 0.0
+                    && layer
+                        .iter_tile_data()
+                        .filter(|(_, tile_data)| !is_empty(tile_data))
+                        .count() as f32
+                        >= settings.min_sparse_fill * cell_count
+                    && layer.set_layer_storage(LayerStorage::Dense, &is_empty);
+                let mut layer_report = layer.compact(&is_empty, settings.max_dense_fill);
+                layer_report.densified_layers = densified as usize;
+                if layer_report.converted_layers > 0 && layer_default.is_none() {
+                    chunk.set_layer_default(map_layer, Some(TileData::default()));
+                }
+                if layer_report.dropped_entries > 0
+                    || layer_report.converted_layers > 0
+                    || layer_report.densified_layers > 0
+                {
+                    changed = true;
+                    changed_layers.insert(map_layer);
+                }
+                report += layer_report;
+            }
+            if changed {
+                chunk.set_changed();
+            }
+        }
+
+        if let Ok(mut version) = self.map_versions.get_mut(map_entity) {
+            for map_layer in changed_layers {
+                version.bump(map_layer);
+            }
+        }
+        match self.stats.get_mut(map_entity) {
+            Ok(mut stats) => stats.record_compaction(&report),
+            Err(_) => {
+                let mut stats = TilemapStats::default();
+                stats.record_compaction(&report);
+                self.commands.entity(map_entity).insert(stats);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Returns a [`CellMask`] of the cells of the given [`MapLayer`] whose tile data matches the predicate.
+    /// Cells without tile data are never set, neither are cells outside of the maps [`MapMask`]
+    pub fn layer_mask(
+        &self,
+        map_layer: MapLayers,
+        predicate: impl Fn(&TileData) -> bool,
+    ) -> Result<CellMask, TilemapManagerError> {
+        let dimensions = self.dimensions()?;
+        let chunk_size = self.get_chunk(ChunkPos::new(0, 0))?.get_chunk_dimensions();
+        let map_layer = map_layer.to_bits();
+        let mut mask = CellMask::new(dimensions, chunk_size);
+        for y in 0..dimensions.y as i32 {
+            for x in 0..dimensions.x as i32 {
+                let cell = Cell::new(x, y);
+                match self.read_tile_data(map_layer, cell) {
+                    Ok(tile_data) => {
+                        mask.set(cell, predicate(&tile_data));
+                    }
+                    Err(
+                        TilemapManagerError::TileDataDoesNotExist
+                        | TilemapManagerError::CellOutOfBounds,
+                    ) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(mask)
+    }
+
+    /// Returns a copy of the given [`MapLayer`] with the tile data of every tile converted by the given
+    /// function, for migrating a map to a new `TileData` type. Build the migrated map from the returned layers
+    /// with a [`TilemapBuilder`].
+    ///
+    /// The layer is converted one chunk at a time. It is dense if every cell of the map has tile data,
+    /// otherwise it is sparse. Tile entities of the layer are kept. Cells are placed at
+    /// `chunk_pos * max_chunk_size + chunk_cell`, so only maps chunked into rectangles, such as square maps,
+    /// can be converted.
+    pub fn map_layer_data<NewData>(
+        &self,
+        map_layer: MapLayers,
+        convert: impl Fn(TileData) -> NewData,
+    ) -> Result<TilemapLayer<NewData>, TilemapManagerError>
+    where
+        NewData: Clone + Copy + Sized + Default + Send + Sync,
+    {
+        let dimensions = self.dimensions()?;
+        let (_, tilemap, _, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let max_chunk_size = tilemap.get_chunks_max_size().as_ivec2();
+        let map_layer = map_layer.to_bits();
+
+        let (width, height) = (dimensions.x as usize, dimensions.y as usize);
+        let mut rows = vec![vec![NewData::default(); width]; height];
+        let mut has_data = vec![vec![false; width]; height];
+        let mut tile_count = 0;
+        let mut tile_entities = HashMap::new();
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        for chunk_pos in ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x as i32 - 1, chunk_counts.y as i32 - 1),
+        ) {
+            let chunk_entity = tilemap
+                .get_chunk(chunk_pos)
+                .ok_or(TilemapManagerError::InvalidChunkPos)?;
+            let (_, chunk, _) = self.chunk_query.get(chunk_entity)?;
+            let Some(layer) = chunk.data.get(&map_layer) else {
+                continue;
+            };
+            let origin = chunk_pos.as_ivec2() * max_chunk_size;
+            let to_cell = |chunk_cell: ChunkCell| {
+                Cell::new(origin.x + chunk_cell.x(), origin.y + chunk_cell.y())
+            };
+            for (chunk_cell, tile_data) in layer.iter_tile_data() {
+                let cell = to_cell(chunk_cell);
+                let (x, y) = (cell.x as usize, cell.y as usize);
+                if y >= height || x >= width {
+                    continue;
+                }
+                rows[y][x] = convert(*tile_data);
+                if !has_data[y][x] {
+                    has_data[y][x] = true;
+                    tile_count += 1;
+                }
+            }
+            for (chunk_cell, tile_entity) in layer.iter_tile_entities() {
+                tile_entities.insert(to_cell(chunk_cell), tile_entity);
+            }
+        }
+
+        if tile_count == width * height && tile_count > 0 {
+            return Ok(TilemapLayer::Dense(rows, tile_entities));
+        }
+        let mut tiles = HashMap::new();
+        for (y, row) in rows.into_iter().enumerate() {
+            for (x, tile_data) in row.into_iter().enumerate() {
+                if has_data[y][x] {
+                    tiles.insert(Cell::new(x as i32, y as i32), tile_data);
+                }
+            }
+        }
+        Ok(TilemapLayer::Sparse(tiles, dimensions, tile_entities))
+    }
+
+    /// Runs the function on the current [`MapLayer`] of every chunk of the map in parallel on the
+    /// [`ComputeTaskPool`]. Every call gets mutable access to the layer of a single chunk, so whole map passes
+    /// such as cellular automata or fluid simulations scale with the amount of threads. Chunks without the
+    /// layer are skipped.
+    ///
+    /// Writes made by the function bypass the maps [`TileWriteHooks`], [`TileOverrides`], history, and
+    /// [`TilemapStats`]. The [`MapVersion`] of the layer is bumped once every chunk is done and every chunk is
+    /// marked as dirty as a whole.
+    pub fn par_for_each_chunk(
+        &mut self,
+        f: impl Fn(ChunkPos, &mut MapChunk) + Send + Sync,
+    ) -> Result<(), TilemapManagerError> {
+        let map_entity = self.selected_map_entity();
+        let map_layer = self.selection.map_layer.to_bits();
+        let (_, tilemap, _, _) = self.tilemap_query.get(map_entity)?;
+        let chunk_counts = tilemap.chunks().chunk_counts();
+        let chunk_entities: HashSet<Entity> = ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x as i32 - 1, chunk_counts.y as i32 - 1),
+        )
+        .filter_map(|chunk_pos| tilemap.get_chunk(chunk_pos))
+        .collect();
 
+        let chunks: Vec<_> = self
+            .chunk_query
+            .iter_mut()
+            .filter(|(chunk_entity, _, _)| chunk_entities.contains(chunk_entity))
+            .map(|(_, chunk, _)| chunk)
+            .collect();
+        let f = &f;
+        ComputeTaskPool::get_or_init(TaskPool::new).scope(|scope| {
+            for mut chunk in chunks {
+                scope.spawn(async move {
+                    let chunk_pos = chunk.chunk_pos;
+                    if let Some(layer) = chunk.data.get_mut(&map_layer) {
+                        f(chunk_pos, layer);
+                        chunk.mark_all_dirty();
+                    }
+                });
+            }
+        });
+        self.bump_layer_version(map_entity, map_layer);
         Ok(())
     }
 
@@ -278,12 +2499,7 @@ where
         &self,
         chunk_pos: ChunkPos,
     ) -> Result<&Chunk<MapChunk, TileData>, TilemapManagerError> {
-        let (_, tilemap, _map, _) = self.tilemap_query.get(
-            self.map_entity
-                .deref()
-                .0
-                .expect("TilemapManager must have a tilemap entity set"),
-        )?;
+        let (_, tilemap, _map, _) = self.tilemap_query.get(self.selected_map_entity())?;
         let (_, chunk, _) = self.chunk_query.get(
             tilemap
                 .get_chunk(chunk_pos)
@@ -291,6 +2507,33 @@ where
         )?;
         Ok(chunk)
     }
+
+    /// Returns mutable [`Chunk`] data for the given [`ChunkPos`] if it exists. Writes made through it skip
+    /// every hook, override, and version of the map
+    #[cfg(feature = "replication")]
+    pub(super) fn get_chunk_mut(
+        &mut self,
+        chunk_pos: ChunkPos,
+    ) -> Result<Mut<'_, Chunk<MapChunk, TileData>>, TilemapManagerError> {
+        let (_, tilemap, _map, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let chunk_entity = tilemap
+            .get_chunk(chunk_pos)
+            .ok_or(TilemapManagerError::InvalidChunkPos)?;
+        let (_, chunk, _) = self.chunk_query.get_mut(chunk_entity)?;
+        Ok(chunk)
+    }
+
+    /// Returns the [`ChunkPos`] of every chunk of the selected map
+    #[cfg(feature = "replication")]
+    pub(super) fn chunk_positions(&self) -> Result<Vec<ChunkPos>, TilemapManagerError> {
+        let (_, tilemap, _, _) = self.tilemap_query.get(self.selected_map_entity())?;
+        let chunk_counts = tilemap.chunks().chunk_counts().as_ivec2();
+        Ok(ChunkPos::iter_rect(
+            ChunkPos::new(0, 0),
+            ChunkPos::new(chunk_counts.x - 1, chunk_counts.y - 1),
+        )
+        .collect())
+    }
 }
 
 #[cfg(test)]
@@ -300,15 +2543,25 @@ mod tests {
     use crate::square::map_data::SquareMapData;
     use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
 
+    use crate::generation::SeededRng;
+    use crate::map::chunk::{Chunk, ChunkCell, ChunkCorners, ChunkLayer, ChunkPos, CornerId};
+    use crate::map::{
+        Adjacency, DynamicLayerRegistry, MapMarker, TileCell, TileEntityParenting, TileOverride,
+        TileOverrides, TileWrite, TileWriteHooks, Tilemap, TilemapGeometry, TilemapRef,
+        TilemapSettings,
+    };
     use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
     use crate::tilemap_builder::TilemapBuilder;
-    use crate::tilemap_manager::tilemap_manager::TilemapManager;
+    use crate::tilemap_manager::tilemap_manager::{TileEntityCloning, TilemapManager};
+    use crate::tilemap_manager::TilemapManagerError;
+    use crate::tilemap_manager::{ActiveTilemap, TilemapSelection};
     use bevy::ecs::system::{Commands, SystemState};
-    use bevy::math::UVec2;
-    use bevy::prelude::World;
+    use bevy::math::{IVec2, Rect, UVec2, Vec2};
+    use bevy::prelude::{Component, DespawnRecursiveExt, Parent, World};
     use bevy::utils::hashbrown::HashMap;
     use bst_map_layer_derive::MapLayer;
     use lettuces::cell::Cell;
+    use std::sync::{Arc, Mutex};
 
     #[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
     struct TileData(u8);
@@ -539,4 +2792,1662 @@ mod tests {
 
         assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(8, 9));
     }
+
+    #[test]
+    fn tilemap_manager_write_hooks() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        let writes = Arc::new(Mutex::new(vec![]));
+        let hook_writes = writes.clone();
+        commands.entity(map_entity).insert(
+            TileWriteHooks::<u8>::new()
+                .with(move |write: &TileWrite<u8>| hook_writes.lock().unwrap().push(*write)),
+        );
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(3, Cell::new(1, 2)).unwrap();
+        assert!(tilemap_manager.sets_tile_data(3, Cell::new(9, 9)).is_err());
+
+        assert_eq!(
+            *writes.lock().unwrap(),
+            vec![TileWrite {
+                cell: Cell::new(1, 2),
+                map_layer: MapLayers::Main.to_bits(),
+                old: Some(0),
+                new: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_modify_tile_data() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut hashmap: HashMap<Cell, u8> = HashMap::new();
+        hashmap.insert(Cell::new(1, 2), 4);
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_sparse_from_hashmap(4, 4, hashmap),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        let writes = Arc::new(Mutex::new(vec![]));
+        let hook_writes = writes.clone();
+        commands.entity(map_entity).insert(
+            TileWriteHooks::<u8>::new()
+                .with(move |write: &TileWrite<u8>| hook_writes.lock().unwrap().push(*write)),
+        );
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let version = tilemap_manager.map_version().unwrap();
+        tilemap_manager
+            .modify_tile_data(Cell::new(1, 2), |tile_data| *tile_data += 3)
+            .unwrap();
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 2)).unwrap(), 7);
+        assert!(tilemap_manager.map_version().unwrap() > version);
+
+        // Empty and out of bounds cells are never passed to the closure
+        let mut called = false;
+        assert!(matches!(
+            tilemap_manager.modify_tile_data(Cell::new(0, 0), |_| called = true),
+            Err(TilemapManagerError::TileDataDoesNotExist)
+        ));
+        assert!(tilemap_manager
+            .modify_tile_data(Cell::new(9, 9), |_| called = true)
+            .is_err());
+        assert!(!called);
+
+        assert_eq!(
+            *writes.lock().unwrap(),
+            vec![TileWrite {
+                cell: Cell::new(1, 2),
+                map_layer: MapLayers::Main.to_bits(),
+                old: Some(4),
+                new: 7,
+            }]
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_set_tile_data_batch() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        let writes = Arc::new(Mutex::new(vec![]));
+        let hook_writes = writes.clone();
+        commands.entity(map_entity).insert(
+            TileWriteHooks::<u8>::new()
+                .with(move |write: &TileWrite<u8>| hook_writes.lock().unwrap().push(write.cell)),
+        );
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tiles: Vec<(Cell, u8)> = (0..4)
+            .flat_map(|y| (0..4).map(move |x| (Cell::new(x, y), (x + y * 4) as u8)))
+            .collect();
+        tilemap_manager.set_tile_data_batch(tiles.clone()).unwrap();
+        for (cell, tile_data) in tiles.iter() {
+            assert_eq!(tilemap_manager.get_tile_data(*cell).unwrap(), *tile_data);
+        }
+        assert_eq!(tilemap_manager.map_version().unwrap(), 1);
+        assert_eq!(writes.lock().unwrap().len(), 16);
+
+        // A cell outside of every chunk fails the batch before anything is written
+        assert!(tilemap_manager
+            .set_tile_data_batch([(Cell::new(0, 0), 9), (Cell::new(9, 9), 9)])
+            .is_err());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 0);
+        assert_eq!(tilemap_manager.map_version().unwrap(), 1);
+    }
+
+    #[test]
+    fn tilemap_manager_set_tile_data_batch_failing_chunk() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        // The edge chunks of a 3x3 map are smaller than the max chunk size
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 3]; 3]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        let writes = Arc::new(Mutex::new(vec![]));
+        let hook_writes = writes.clone();
+        commands.entity(map_entity).insert((
+            TileOverrides::<u8>::default(),
+            TileWriteHooks::<u8>::new()
+                .with(move |write: &TileWrite<u8>| hook_writes.lock().unwrap().push(write.cell)),
+        ));
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+
+        // The last chunk of the batch can't hold the cell (3, 3), so none of the chunks are written
+        assert!(matches!(
+            tilemap_manager.set_tile_data_batch([
+                (Cell::new(0, 0), 9),
+                (Cell::new(2, 0), 9),
+                (Cell::new(3, 3), 9),
+            ]),
+            Err(TilemapManagerError::CellOutOfBounds)
+        ));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 0);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 0)).unwrap(), 0);
+        assert_eq!(tilemap_manager.map_version().unwrap(), 0);
+        assert!(writes.lock().unwrap().is_empty());
+        assert!(tilemap_manager.export_overrides().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tilemap_manager_map_layer_data() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0, 1, 2], vec![3, 4, 5], vec![6, 7, 8]]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+        let mut secondary = HashMap::new();
+        secondary.insert(Cell::new(2, 2), 9);
+        tilemap_builder.add_layer(
+            TilemapLayer::new_sparse_from_hashmap(3, 3, secondary),
+            MapLayers::Secondary,
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(2, 1))
+            .unwrap();
+
+        let main = tilemap_manager
+            .map_layer_data(MapLayers::Main, |tile_data| (tile_data, tile_data % 2 == 0))
+            .unwrap();
+        let TilemapLayer::Dense(ref rows, ref tile_entities) = main else {
+            panic!("every cell of the main layer has data")
+        };
+        assert_eq!(rows[1], vec![(3, false), (4, true), (5, false)]);
+        assert_eq!(main.get_tile_data(Cell::new(2, 2)), Some((8, true)));
+        assert_eq!(tile_entities.get(&Cell::new(2, 1)), Some(&tile_entity));
+
+        let secondary = tilemap_manager
+            .map_layer_data(MapLayers::Secondary, u32::from)
+            .unwrap();
+        let TilemapLayer::Sparse(ref tiles, dimensions, _) = secondary else {
+            panic!("the secondary layer is sparse")
+        };
+        assert_eq!(dimensions, UVec2::new(3, 3));
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(secondary.get_tile_data(Cell::new(2, 2)), Some(9u32));
+
+        // Saved layers convert the same way
+        let saved = TilemapLayer::new_dense_from_vecs(vec![vec![1u8, 2]]).map_tile_data(u16::from);
+        assert_eq!(saved.get_tile_data(Cell::new(1, 0)), Some(2u16));
+    }
+
+    #[derive(Component, Default)]
+    struct Overworld;
+
+    impl MapMarker for Overworld {
+        type Layers = MapLayers;
+    }
+
+    #[test]
+    fn tilemap_manager_layer_handles() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+        tilemap_builder.add_layer(
+            TilemapLayer::new_dense_from_vecs(vec![vec![2u8; 4]; 4]),
+            MapLayers::Secondary,
+        );
+        let overworld = tilemap_builder
+            .spawn_tilemap_with_marker(&mut commands, Overworld)
+            .unwrap();
+        system_state.apply(&mut world);
+        assert!(world.get::<Overworld>(overworld.map_entity()).is_some());
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_layer_handle(overworld.layer(MapLayers::Secondary));
+        assert_eq!(
+            tilemap_manager.tilemap_entity(),
+            Some(overworld.map_entity())
+        );
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 2);
+        tilemap_manager.set_layer_handle(overworld.layer(MapLayers::Main));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 1);
+    }
+
+    #[test]
+    fn tilemap_manager_overrides() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let spawn_base_map = |commands: &mut Commands| {
+            SquareTilemapBuilder::<u8, MapLayers>::new(
+                TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+                SquareMapData {
+                    max_chunk_size: UVec2::new(2, 2),
+                },
+                SquareChunkSettings {
+                    max_chunk_size: UVec2 { x: 2, y: 2 },
+                },
+            )
+            .with_map_bundle(TileOverrides::<u8>::default())
+            .spawn_tilemap(commands)
+            .expect("map has a main layer")
+        };
+        let map_entity = spawn_base_map(&mut commands);
+        let loaded_map_entity = spawn_base_map(&mut commands);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(3, Cell::new(1, 2)).unwrap();
+        tilemap_manager.sets_tile_data(5, Cell::new(0, 0)).unwrap();
+        tilemap_manager.sets_tile_data(4, Cell::new(1, 2)).unwrap();
+        // Writing the baseline back removes the override
+        tilemap_manager.sets_tile_data(0, Cell::new(0, 0)).unwrap();
+
+        let overrides = tilemap_manager.export_overrides().unwrap();
+        assert_eq!(
+            overrides.overrides,
+            vec![TileOverride {
+                map_layer: MapLayers::Main.to_bits(),
+                cell: Cell::new(1, 2),
+                tile_data: 4,
+            }]
+        );
+
+        tilemap_manager.set_tilemap_entity(loaded_map_entity);
+        tilemap_manager.apply_overrides(&overrides).unwrap();
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 2)).unwrap(), 4);
+        assert_eq!(tilemap_manager.drain_overrides().unwrap(), overrides);
+        assert!(tilemap_manager.export_overrides().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tilemap_manager_map_version() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.map_version().unwrap(), 0);
+
+        tilemap_manager.sets_tile_data(3, Cell::new(1, 2)).unwrap();
+        assert!(tilemap_manager.sets_tile_data(3, Cell::new(9, 9)).is_err());
+        assert_eq!(tilemap_manager.map_version().unwrap(), 1);
+        assert_eq!(tilemap_manager.layer_version(MapLayers::Main).unwrap(), 1);
+        assert_eq!(
+            tilemap_manager.layer_version(MapLayers::Secondary).unwrap(),
+            0
+        );
+
+        let entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(0, 0))
+            .unwrap();
+        assert_eq!(
+            tilemap_manager
+                .get_or_spawn_tile_entity(Cell::new(0, 0))
+                .unwrap(),
+            entity
+        );
+        assert_eq!(tilemap_manager.map_version().unwrap(), 2);
+
+        tilemap_manager
+            .despawn_tile_entity(Cell::new(0, 0))
+            .unwrap();
+        assert_eq!(tilemap_manager.map_version().unwrap(), 3);
+    }
+
+    #[test]
+    fn tilemap_manager_crop_to_content() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut hashmap = HashMap::new();
+        hashmap.insert(Cell::new(3, 4), 1u8);
+        hashmap.insert(Cell::new(6, 5), 2u8);
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_sparse_from_hashmap(10, 10, hashmap),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 4, y: 4 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(7, 7))
+            .unwrap();
+        assert_eq!(
+            tilemap_manager.crop_to_content(1).unwrap(),
+            IVec2::new(2, 3)
+        );
+        system_state.apply(&mut world);
+
+        let (_, tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(7, 6));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 2)).unwrap(), 2);
+        assert!(tilemap_manager.get_tile_data(Cell::new(0, 0)).is_err());
+        assert_eq!(
+            tilemap_manager.get_tile_entity(Cell::new(5, 4)).unwrap(),
+            tile_entity
+        );
+        assert_eq!(
+            world
+                .query::<&Chunk<SquareChunkLayer<u8>, u8>>()
+                .iter(&world)
+                .count(),
+            4
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_resize_map() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut hashmap = HashMap::new();
+        hashmap.insert(Cell::new(1, 1), 7u8);
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 5]; 5]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 4, y: 4 },
+            },
+        );
+        tilemap_builder.add_layer(
+            TilemapLayer::new_sparse_from_hashmap(5, 5, hashmap),
+            MapLayers::Secondary,
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let kept_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(2, 2))
+            .unwrap();
+        let dropped_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(4, 4))
+            .unwrap();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        assert!(matches!(
+            tilemap_manager.resize_map(UVec2::new(0, 3), 0),
+            Err(TilemapManagerError::InvalidMapSize(_))
+        ));
+        tilemap_manager.resize_map(UVec2::new(9, 3), 2).unwrap();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(9, 3));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 2)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(8, 2)).unwrap(), 2);
+        assert!(tilemap_manager.get_tile_data(Cell::new(0, 3)).is_err());
+        assert_eq!(
+            tilemap_manager.get_tile_entity(Cell::new(2, 2)).unwrap(),
+            kept_entity
+        );
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 7);
+        assert!(tilemap_manager.get_tile_data(Cell::new(8, 2)).is_err());
+        assert!(world.get_entity(dropped_entity).is_none());
+        assert!(world.get_entity(kept_entity).is_some());
+        assert_eq!(
+            world
+                .query::<&Chunk<SquareChunkLayer<u8>, u8>>()
+                .iter(&world)
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_corner_layer() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.add_corner_layer(0).unwrap();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager
+            .set_corner_data(5, CornerId::new(2, 2))
+            .unwrap();
+        tilemap_manager
+            .set_corner_data(7, CornerId::new(4, 4))
+            .unwrap();
+        assert!(matches!(
+            tilemap_manager.set_corner_data(1, CornerId::new(5, 5)),
+            Err(TilemapManagerError::InvalidChunkPos)
+        ));
+        assert_eq!(
+            tilemap_manager.get_cell_corners(Cell::new(1, 1)).unwrap(),
+            [0, 0, 0, 5]
+        );
+        assert_eq!(
+            tilemap_manager.get_cell_corners(Cell::new(3, 3)).unwrap(),
+            [0, 0, 0, 7]
+        );
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert!(matches!(
+            tilemap_manager.get_corner_data(CornerId::new(2, 2)),
+            Err(TilemapManagerError::CornerDataDoesNotExist)
+        ));
+
+        let shared: Vec<u8> = world
+            .query::<&ChunkCorners<u8>>()
+            .iter(&world)
+            .filter_map(|corners| {
+                [
+                    UVec2::new(0, 0),
+                    UVec2::new(2, 0),
+                    UVec2::new(0, 2),
+                    UVec2::new(2, 2),
+                ]
+                .into_iter()
+                .find_map(|local| {
+                    corners
+                        .get(MapLayers::Main.to_bits(), local)
+                        .filter(|data| *data == 5)
+                })
+            })
+            .collect();
+        assert_eq!(shared, vec![5, 5, 5, 5]);
+    }
+
+    #[test]
+    fn tilemap_manager_clone_map() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tilemap_layer = TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 5]; 5]);
+        tilemap_layer.set_tile_data(Cell::new(4, 4), 9);
+        let Some(map_entity) = SquareTilemapBuilder::<u8, MapLayers>::new(
+            tilemap_layer,
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands) else {
+            return;
+        };
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(1, 1))
+            .unwrap();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let cloned = tilemap_manager
+            .clone_map(TileEntityCloning::SpawnEmpty)
+            .unwrap();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(cloned.map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 4)).unwrap(), 9);
+        assert_eq!(tilemap_manager.dimensions().unwrap(), UVec2::new(5, 5));
+        let cloned_tile_entity = tilemap_manager.get_tile_entity(Cell::new(1, 1)).unwrap();
+        assert_ne!(cloned_tile_entity, tile_entity);
+        assert_eq!(cloned.tile_entities[&tile_entity], cloned_tile_entity);
+
+        // Editing the clone leaves the original untouched
+        tilemap_manager.sets_tile_data(3, Cell::new(0, 0)).unwrap();
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 1);
+    }
+
+    #[test]
+    fn tilemap_manager_world_queries() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 6]; 6]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 4, y: 4 },
+            },
+        );
+
+        let Some(map_entity) = tilemap_builder.spawn_tilemap(&mut commands) else {
+            return;
+        };
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert!(matches!(
+            tilemap_manager.cells_in_world_circle(Vec2::ZERO, 1.0),
+            Err(TilemapManagerError::GeometryDoesNotExist)
+        ));
+        world
+            .entity_mut(map_entity)
+            .insert(TilemapGeometry::new(Vec2::ZERO, Vec2::splat(10.0)));
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let mut circle: Vec<Cell> = tilemap_manager
+            .cells_in_world_circle(Vec2::new(40.0, 40.0), 6.0)
+            .unwrap()
+            .collect();
+        circle.sort();
+        assert_eq!(
+            circle,
+            vec![
+                Cell::new(3, 3),
+                Cell::new(3, 4),
+                Cell::new(4, 3),
+                Cell::new(4, 4)
+            ]
+        );
+
+        let mut aabb: Vec<Cell> = tilemap_manager
+            .cells_in_world_aabb(Rect::new(-100.0, -100.0, 15.0, 5.0))
+            .unwrap()
+            .collect();
+        aabb.sort();
+        assert_eq!(aabb, vec![Cell::new(0, 0), Cell::new(1, 0)]);
+
+        let triangle = [Vec2::ZERO, Vec2::new(60.0, 0.0), Vec2::new(0.0, 60.0)];
+        let polygon: Vec<Cell> = tilemap_manager
+            .cells_in_world_polygon(&triangle)
+            .unwrap()
+            .collect();
+        assert_eq!(polygon.len(), 21);
+        assert!(polygon.iter().all(|cell| cell.x + cell.y <= 5));
+
+        assert_eq!(
+            tilemap_manager
+                .cells_in_world_circle(Vec2::new(500.0, 500.0), 20.0)
+                .unwrap()
+                .count(),
+            0
+        );
+
+        #[cfg(feature = "fixed_point")]
+        {
+            use crate::map::{FixedRect, FixedTilemapGeometry};
+            use bevy::math::I64Vec2;
+
+            world
+                .entity_mut(map_entity)
+                .insert(FixedTilemapGeometry::new(
+                    I64Vec2::ZERO,
+                    I64Vec2::splat(10_000),
+                ));
+            let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+            tilemap_manager.set_tilemap_entity(map_entity);
+            let mut fixed_circle: Vec<Cell> = tilemap_manager
+                .cells_in_fixed_circle(I64Vec2::splat(40_000), 6_000)
+                .unwrap()
+                .collect();
+            fixed_circle.sort();
+            assert_eq!(fixed_circle, circle);
+            let mut fixed_aabb: Vec<Cell> = tilemap_manager
+                .cells_in_fixed_aabb(FixedRect::from_corners(
+                    I64Vec2::splat(-100_000),
+                    I64Vec2::new(15_000, 5_000),
+                ))
+                .unwrap()
+                .collect();
+            fixed_aabb.sort();
+            assert_eq!(fixed_aabb, aabb);
+            assert_eq!(
+                tilemap_manager.fixed_world_bounds().unwrap(),
+                FixedRect::from_corners(I64Vec2::ZERO, I64Vec2::splat(60_000))
+            );
+        }
+    }
+
+    #[test]
+    fn tilemap_manager_selection() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.tilemap_entity(), None);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![3; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+        world.insert_resource(ActiveTilemap(Some(map_entity)));
+
+        // The active tilemap is used until the manager selects a tilemap itself
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.tilemap_entity(), Some(map_entity));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 3);
+
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(
+            tilemap_manager.selection(),
+            TilemapSelection {
+                map_entity: None,
+                map_layer: MapLayers::Secondary,
+            }
+        );
+        let tile_data = tilemap_manager.with_selection(
+            TilemapSelection::new(map_entity, MapLayers::Main),
+            |tilemap_manager| tilemap_manager.get_tile_data(Cell::new(0, 0)),
+        );
+        assert_eq!(tile_data.unwrap(), 3);
+        assert_eq!(tilemap_manager.layer(), MapLayers::Secondary);
+
+        // The selection persists across runs and can be replaced
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.layer(), MapLayers::Secondary);
+        tilemap_manager.set_selection(TilemapSelection::new(map_entity, MapLayers::Main));
+        world.remove_resource::<ActiveTilemap>();
+        let (_, tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.tilemap_entity(), Some(map_entity));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 2)).unwrap(), 3);
+    }
+
+    #[test]
+    fn tilemap_manager_chunk_lookup_cache() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.sets_tile_data(2, Cell::new(0, 0)).unwrap();
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 1);
+
+        // Swapping in another chunk entity changes the tilemap, so the cached lookup is not reused
+        let mut chunk = world
+            .get::<Chunk<SquareChunkLayer<u8>, u8>>(
+                world
+                    .get::<Tilemap>(map_entity)
+                    .unwrap()
+                    .get_chunk(ChunkPos::new(0, 0))
+                    .unwrap(),
+            )
+            .unwrap()
+            .clone();
+        chunk.set_tile_data_from_cell(MapLayers::Main.to_bits(), Cell::new(0, 0), 5);
+        let chunk_entity = world.spawn(chunk).id();
+        world
+            .get_mut::<Tilemap>(map_entity)
+            .unwrap()
+            .chunks_mut()
+            .set_chunk(ChunkPos::new(0, 0), chunk_entity);
+
+        let (_, tilemap_manager) = system_state.get_mut(&mut world);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 5);
+    }
+
+    #[test]
+    fn tilemap_manager_add_and_remove_layer() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 3]; 3]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let tile_entity = commands.spawn_empty().id();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert!(matches!(
+            tilemap_manager.add_layer(
+                MapLayers::Secondary,
+                TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 2]; 2])
+            ),
+            Err(TilemapManagerError::LayerDimensionsMismatch(..))
+        ));
+
+        let mut fog = HashMap::new();
+        fog.insert(Cell::new(2, 2), 9);
+        let mut fog_entities = HashMap::new();
+        fog_entities.insert(Cell::new(0, 2), tile_entity);
+        tilemap_manager
+            .add_layer(
+                MapLayers::Secondary,
+                TilemapLayer::Sparse(fog, UVec2::new(3, 3), fog_entities),
+            )
+            .unwrap();
+        assert!(matches!(
+            tilemap_manager.add_layer(MapLayers::Secondary, TilemapLayer::new_sparse_empty(3, 3)),
+            Err(TilemapManagerError::LayerAlreadyExists)
+        ));
+
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 2)).unwrap(), 9);
+        assert!(tilemap_manager.get_tile_data(Cell::new(1, 1)).is_err());
+        assert_eq!(
+            tilemap_manager.get_tile_entity(Cell::new(0, 2)).unwrap(),
+            tile_entity
+        );
+        assert_eq!(
+            tilemap_manager.layer_version(MapLayers::Secondary).unwrap(),
+            1
+        );
+
+        assert!(matches!(
+            tilemap_manager.remove_layer(MapLayers::Main),
+            Err(TilemapManagerError::CannotRemoveMainLayer)
+        ));
+        tilemap_manager.remove_layer(MapLayers::Secondary).unwrap();
+        assert!(matches!(
+            tilemap_manager.get_tile_data(Cell::new(2, 2)),
+            Err(TilemapManagerError::LayerDoesNotExist)
+        ));
+        assert!(matches!(
+            tilemap_manager.remove_layer(MapLayers::Secondary),
+            Err(TilemapManagerError::LayerDoesNotExist)
+        ));
+        system_state.apply(&mut world);
+        assert!(world.get_entity(tile_entity).is_none());
+    }
+
+    #[test]
+    fn tilemap_manager_neighbors() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![
+                vec![0, 1, 2, 3],
+                vec![4, 5, 6, 7],
+                vec![8, 9, 10, 11],
+            ]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        // Cell (1, 1) is on the corner of its chunk so its neighbours are spread over four chunks
+        let mut neighbors = tilemap_manager
+            .get_neighbors_data(Cell::new(1, 1), Adjacency::EdgesAndCorners)
+            .unwrap();
+        neighbors.sort_by_key(|(_, tile_data)| *tile_data);
+        assert_eq!(
+            neighbors
+                .iter()
+                .map(|(_, tile_data)| *tile_data)
+                .collect::<Vec<u8>>(),
+            vec![0, 1, 2, 4, 6, 8, 9, 10]
+        );
+        assert_eq!(
+            tilemap_manager
+                .get_neighbors(Cell::new(0, 0), Adjacency::Edges)
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            tilemap_manager
+                .get_neighbors(Cell::new(3, 2), Adjacency::EdgesAndCorners)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_flood_fill() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        // A ring of walls around the cells (1, 1) to (2, 2), crossing four chunks
+        let mut tiles = vec![vec![0u8; 6]; 6];
+        tiles[0][..4].fill(1);
+        tiles[3][..4].fill(1);
+        for row in &mut tiles[..4] {
+            row[0] = 1;
+            row[3] = 1;
+        }
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tiles),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let mut filled = tilemap_manager
+            .flood_fill(Cell::new(1, 2), 2, |tile_data| *tile_data == 0)
+            .unwrap();
+        filled.sort_by_key(|cell| (cell.y, cell.x));
+        assert_eq!(
+            filled,
+            vec![
+                Cell::new(1, 1),
+                Cell::new(2, 1),
+                Cell::new(1, 2),
+                Cell::new(2, 2)
+            ]
+        );
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 2)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 4)).unwrap(), 0);
+
+        // The open area outside of the ring wraps around it
+        let filled = tilemap_manager
+            .flood_fill(Cell::new(5, 5), 3, |tile_data| *tile_data == 0)
+            .unwrap();
+        assert_eq!(filled.len(), 20);
+        assert!(tilemap_manager
+            .flood_fill(Cell::new(0, 0), 3, |tile_data| *tile_data == 0)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn tilemap_manager_layer_defaults() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+        tilemap_builder.add_layer(TilemapLayer::new_sparse_empty(4, 4), MapLayers::Secondary);
+        tilemap_builder.set_layer_default(MapLayers::Secondary, 5);
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 1)).unwrap(), 5);
+        tilemap_manager.sets_tile_data(2, Cell::new(3, 1)).unwrap();
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 1)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 5);
+        // Cells outside of the map still error
+        assert!(tilemap_manager.get_tile_data(Cell::new(4, 0)).is_err());
+
+        tilemap_manager.set_layer_default(None).unwrap();
+        assert!(matches!(
+            tilemap_manager.get_tile_data(Cell::new(0, 0)),
+            Err(TilemapManagerError::TileDataDoesNotExist)
+        ));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 1)).unwrap(), 2);
+    }
+
+    #[test]
+    fn tilemap_manager_dynamic_layers() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        );
+        let heat = tilemap_builder.add_dynamic_layer(
+            "mod_a:heat",
+            TilemapLayer::new_dense_from_vecs(vec![vec![7u8; 4]; 4]),
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(
+            tilemap_manager.dynamic_layer("mod_a:heat").unwrap(),
+            Some(heat)
+        );
+        assert_eq!(
+            tilemap_manager
+                .get_dynamic_tile_data(heat, Cell::new(3, 3))
+                .unwrap(),
+            7
+        );
+        tilemap_manager
+            .set_dynamic_tile_data(heat, 9, Cell::new(3, 3))
+            .unwrap();
+        assert_eq!(
+            tilemap_manager
+                .get_dynamic_tile_data(heat, Cell::new(3, 3))
+                .unwrap(),
+            9
+        );
+        // Dynamic layers don't touch the layers of the enum
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).unwrap(), 0);
+
+        // Layers registered at runtime must be registered on the tilemap before they are filled
+        let mana = world
+            .get_mut::<Tilemap>(map_entity)
+            .unwrap()
+            .dynamic_layers_mut()
+            .register("mod_b:mana");
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let mut other_registry = DynamicLayerRegistry::new();
+        other_registry.register("a");
+        other_registry.register("b");
+        let unregistered = other_registry.register("c");
+        assert!(matches!(
+            tilemap_manager.add_dynamic_layer(unregistered, TilemapLayer::new_sparse_empty(4, 4)),
+            Err(TilemapManagerError::DynamicLayerNotRegistered)
+        ));
+        tilemap_manager
+            .add_dynamic_layer(mana, TilemapLayer::new_sparse_empty(4, 4))
+            .unwrap();
+        tilemap_manager
+            .set_dynamic_tile_data(mana, 3, Cell::new(1, 2))
+            .unwrap();
+        assert_eq!(
+            tilemap_manager
+                .get_dynamic_tile_data(mana, Cell::new(1, 2))
+                .unwrap(),
+            3
+        );
+        tilemap_manager.remove_dynamic_layer(mana).unwrap();
+        assert!(matches!(
+            tilemap_manager.get_dynamic_tile_data(mana, Cell::new(1, 2)),
+            Err(TilemapManagerError::LayerDoesNotExist)
+        ));
+        assert_eq!(
+            tilemap_manager.dynamic_layer("mod_b:mana").unwrap(),
+            Some(mana)
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_find_nearest() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let mut tiles = vec![vec![0u8; 8]; 8];
+        tiles[3][2] = 1;
+        tiles[1][5] = 1;
+        tiles[6][6] = 1;
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tiles),
+            SquareMapData {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 3, y: 3 },
+            },
+        );
+        tilemap_builder.add_layer(
+            TilemapLayer::new_sparse_from_hashmap(
+                8,
+                8,
+                [(Cell::new(7, 0), 9)].into_iter().collect(),
+            ),
+            MapLayers::Secondary,
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let is_tree = |tile_data: &u8| *tile_data == 1;
+        assert_eq!(
+            tilemap_manager
+                .find_nearest(Cell::new(1, 1), is_tree, 10)
+                .unwrap(),
+            Some(Cell::new(2, 3))
+        );
+        assert_eq!(
+            tilemap_manager
+                .find_nearest(Cell::new(1, 1), is_tree, 1)
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            tilemap_manager
+                .find_all_in_radius(Cell::new(1, 1), 4, is_tree)
+                .unwrap(),
+            vec![Cell::new(2, 3), Cell::new(5, 1)]
+        );
+        assert_eq!(
+            tilemap_manager
+                .find_all_in_radius(Cell::new(1, 1), 100, is_tree)
+                .unwrap()
+                .len(),
+            3
+        );
+        // Centers outside of the map search inward
+        assert_eq!(
+            tilemap_manager
+                .find_nearest(Cell::new(10, 6), is_tree, 10)
+                .unwrap(),
+            Some(Cell::new(6, 6))
+        );
+
+        tilemap_manager.set_layer(MapLayers::Secondary);
+        assert_eq!(
+            tilemap_manager
+                .find_nearest(Cell::new(0, 0), |tile_data| *tile_data == 9, 100)
+                .unwrap(),
+            Some(Cell::new(7, 0))
+        );
+        assert_eq!(
+            tilemap_manager
+                .find_nearest(Cell::new(0, 0), |tile_data| *tile_data == 1, 100)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_shapes() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 3, y: 3 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        // Corners in either order and cells off the map are clipped
+        let painted = tilemap_manager
+            .fill_rect(Cell::new(9, 2), Cell::new(6, 1), 1)
+            .unwrap();
+        assert_eq!(painted.len(), 4);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(7, 2)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(5, 2)).unwrap(), 0);
+
+        let painted = tilemap_manager
+            .draw_line(Cell::new(0, 7), Cell::new(7, 0), 2)
+            .unwrap();
+        assert_eq!(painted.len(), 8);
+        for i in 0..8 {
+            assert_eq!(
+                tilemap_manager.get_tile_data(Cell::new(i, 7 - i)).unwrap(),
+                2
+            );
+        }
+
+        let painted = tilemap_manager.fill_circle(Cell::new(0, 0), 2, 3).unwrap();
+        assert_eq!(painted.len(), 8);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 1)).unwrap(), 3);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(2, 2)).unwrap(), 0);
+    }
+
+    #[test]
+    fn tilemap_manager_line_of_sight() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 8]; 8]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(3, 3),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 3, y: 3 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        for cell in [Cell::new(5, 5), Cell::new(2, 1), Cell::new(1, 2)] {
+            tilemap_manager.sets_tile_data(1, cell).unwrap();
+        }
+        let wall = |tile_data: &u8| *tile_data == 1;
+
+        assert_eq!(
+            tilemap_manager
+                .cast_ray(Cell::new(7, 7), Cell::new(3, 3), wall)
+                .unwrap(),
+            Some(Cell::new(5, 5))
+        );
+        assert!(!tilemap_manager
+            .line_of_sight(Cell::new(7, 7), Cell::new(3, 3), wall)
+            .unwrap());
+        assert!(tilemap_manager
+            .line_of_sight(Cell::new(0, 7), Cell::new(7, 7), wall)
+            .unwrap());
+
+        // Sight can't slip between two diagonal walls
+        assert_eq!(
+            tilemap_manager
+                .cast_ray(Cell::new(0, 0), Cell::new(3, 3), wall)
+                .unwrap(),
+            Some(Cell::new(2, 1))
+        );
+
+        // The ends never block line of sight, but a ray stops at a blocking target
+        assert!(tilemap_manager
+            .line_of_sight(Cell::new(5, 0), Cell::new(5, 5), wall)
+            .unwrap());
+        assert_eq!(
+            tilemap_manager
+                .cast_ray(Cell::new(5, 0), Cell::new(5, 5), wall)
+                .unwrap(),
+            Some(Cell::new(5, 5))
+        );
+
+        // Rays stop at the edge of the map
+        assert_eq!(
+            tilemap_manager
+                .cast_ray(Cell::new(6, 6), Cell::new(9, 6), wall)
+                .unwrap(),
+            Some(Cell::new(8, 6))
+        );
+        assert_eq!(
+            tilemap_manager
+                .cast_ray(Cell::new(6, 0), Cell::new(0, 0), wall)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn tilemap_manager_map_mask() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        // Only the top left 2 x 2 corner and the cell (3, 3) are playable
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .with_map_mask_from_predicate(|cell| (cell.x < 2 && cell.y < 2) || cell == Cell::new(3, 3))
+        .with_map_bundle(TilemapGeometry::new(Vec2::ZERO, Vec2::ONE))
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).ok(), Some(1));
+        assert!(matches!(
+            tilemap_manager.get_tile_data(Cell::new(2, 1)),
+            Err(TilemapManagerError::CellOutOfBounds)
+        ));
+        assert!(matches!(
+            tilemap_manager.sets_tile_data(5, Cell::new(0, 3)),
+            Err(TilemapManagerError::CellOutOfBounds)
+        ));
+        assert!(tilemap_manager
+            .set_tile_data_batch([(Cell::new(0, 0), 2), (Cell::new(2, 2), 2)])
+            .is_err());
+        assert_eq!(
+            tilemap_manager
+                .get_neighbors(Cell::new(1, 1), Adjacency::EdgesAndCorners)
+                .unwrap()
+                .len(),
+            3
+        );
+
+        let cells: Vec<Cell> = tilemap_manager
+            .cells_in_world_aabb(Rect::new(0.0, 0.0, 4.0, 4.0))
+            .unwrap()
+            .collect();
+        assert_eq!(cells.len(), 5);
+        assert_eq!(
+            tilemap_manager
+                .layer_mask(MapLayers::Main, |tile_data| *tile_data == 1)
+                .unwrap()
+                .count_ones(),
+            5
+        );
+
+        let mut rng = SeededRng::new(3);
+        for _ in 0..32 {
+            let cell = tilemap_manager.random_cell(&mut rng).unwrap().unwrap();
+            assert!(tilemap_manager.is_cell_playable(cell));
+        }
+    }
+
+    #[test]
+    fn tilemap_manager_tile_entity_parenting() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .with_map_bundle(TilemapSettings {
+            tile_entity_parenting: TileEntityParenting::Chunk,
+            ..TilemapSettings::default()
+        })
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+        let chunk_entity = |world: &World, chunk_pos: ChunkPos| {
+            world
+                .get::<Tilemap>(map_entity)
+                .unwrap()
+                .get_chunk(chunk_pos)
+                .unwrap()
+        };
+        let parent = |world: &World, entity| world.get::<Parent>(entity).map(Parent::get);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(0, 0))
+            .unwrap();
+        let other_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(1, 1))
+            .unwrap();
+        system_state.apply(&mut world);
+        assert_eq!(
+            parent(&world, tile_entity),
+            Some(chunk_entity(&world, ChunkPos::new(0, 0)))
+        );
+
+        // Moving into another chunk re-parents the entity
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        assert!(matches!(
+            tilemap_manager.move_tile_entity(Cell::new(0, 0), Cell::new(1, 1)),
+            Err(TilemapManagerError::TileEntityAlreadyExists)
+        ));
+        assert_eq!(
+            tilemap_manager
+                .move_tile_entity(Cell::new(0, 0), Cell::new(3, 2))
+                .unwrap(),
+            tile_entity
+        );
+        assert!(matches!(
+            tilemap_manager.get_tile_entity(Cell::new(0, 0)),
+            Err(TilemapManagerError::TileEntityDoesNotExist)
+        ));
+        assert_eq!(
+            tilemap_manager.get_tile_entity(Cell::new(3, 2)).unwrap(),
+            tile_entity
+        );
+        tilemap_manager
+            .despawn_tile_entity(Cell::new(1, 1))
+            .unwrap();
+        assert!(matches!(
+            tilemap_manager.get_tile_entity(Cell::new(1, 1)),
+            Err(TilemapManagerError::TileEntityDoesNotExist)
+        ));
+        system_state.apply(&mut world);
+        assert!(world.get_entity(other_entity).is_none());
+        assert_eq!(
+            parent(&world, tile_entity),
+            Some(chunk_entity(&world, ChunkPos::new(1, 1)))
+        );
+
+        // Tile entities despawn with the map
+        world.entity_mut(map_entity).despawn_recursive();
+        assert!(world.get_entity(tile_entity).is_none());
+    }
+
+    #[test]
+    fn tilemap_manager_par_for_each_chunk() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 5]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let other_map = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![1u8; 2]; 2]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let version = tilemap_manager.layer_version(MapLayers::Main).unwrap();
+        tilemap_manager
+            .par_for_each_chunk(|chunk_pos, layer| {
+                for chunk_cell in ChunkCell::iter_chunk(layer.get_chunk_dimensions()) {
+                    let tile_data = layer.get_tile_data_mut(chunk_cell).unwrap();
+                    *tile_data += (chunk_pos.x() + chunk_pos.y() * 3) as u8;
+                }
+            })
+            .unwrap();
+        assert_eq!(
+            tilemap_manager.layer_version(MapLayers::Main).unwrap(),
+            version + 1
+        );
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).unwrap(), 1);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 1)).unwrap(), 2);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(4, 3)).unwrap(), 6);
+
+        // Chunks of other maps are left alone
+        tilemap_manager.set_tilemap_entity(other_map);
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).unwrap(), 1);
+    }
+
+    #[test]
+    fn tilemap_manager_tile_cell() {
+        let mut world = World::new();
+
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2 { x: 2, y: 2 },
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let set_entity = commands.spawn_empty().id();
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let tile_entity = tilemap_manager
+            .get_or_spawn_tile_entity(Cell::new(1, 0))
+            .unwrap();
+        tilemap_manager
+            .set_tile_entity(Cell::new(3, 3), set_entity)
+            .unwrap();
+        system_state.apply(&mut world);
+        assert_eq!(
+            world.get::<TileCell>(tile_entity),
+            Some(&TileCell {
+                cell: Cell::new(1, 0),
+                chunk_pos: ChunkPos::new(0, 0),
+                map_layer: MapLayers::Main.to_bits(),
+            })
+        );
+        assert_eq!(
+            world.get::<TilemapRef>(tile_entity),
+            Some(&TilemapRef(map_entity))
+        );
+        assert_eq!(
+            world
+                .get::<TileCell>(set_entity)
+                .map(|tile_cell| tile_cell.chunk_pos),
+            Some(ChunkPos::new(1, 1))
+        );
+
+        // Moving the entity keeps its cell up to date
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager
+            .move_tile_entity(Cell::new(1, 0), Cell::new(2, 1))
+            .unwrap();
+        system_state.apply(&mut world);
+        let tile_cell = world.get::<TileCell>(tile_entity).unwrap();
+        assert_eq!(tile_cell.cell, Cell::new(2, 1));
+        assert_eq!(tile_cell.chunk_pos, ChunkPos::new(1, 0));
+    }
 }
@@ -0,0 +1,195 @@
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// A single staged write in a [`TilemapTransaction`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StagedTileChange<TileData> {
+    /// The cell being written
+    pub cell: Cell,
+    /// The tile data currently on the map. `None` if the cell has no data
+    pub old: Option<TileData>,
+    /// The tile data that will be written
+    pub new: TileData,
+}
+
+/// Writes staged by [`TilemapManager::transaction`]. Nothing is written to the map until the transaction is
+/// validated and committed.
+pub struct TilemapTransaction<'a, TileData> {
+    read: &'a dyn Fn(Cell) -> Option<TileData>,
+    staged: HashMap<Cell, TileData>,
+    order: Vec<Cell>,
+}
+
+impl<'a, TileData> TilemapTransaction<'a, TileData>
+where
+    TileData: Clone + Copy,
+{
+    /// Stages a write of the tile data to the given [`Cell`]. Writing the same cell twice keeps the last value
+    pub fn set(&mut self, cell: Cell, tile_data: TileData) {
+        if self.staged.insert(cell, tile_data).is_none() {
+            self.order.push(cell);
+        }
+    }
+
+    /// Returns the tile data at the given [`Cell`] as it would be after the transaction is committed
+    pub fn get(&self, cell: Cell) -> Option<TileData> {
+        self.staged
+            .get(&cell)
+            .copied()
+            .or_else(|| (self.read)(cell))
+    }
+
+    /// Returns the tile data staged for the given [`Cell`]
+    pub fn staged(&self, cell: Cell) -> Option<TileData> {
+        self.staged.get(&cell).copied()
+    }
+
+    /// Returns the amount of cells with staged writes
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    /// Returns true if nothing is staged
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Stages a batch of writes to the current layer and applies them atomically.
+    ///
+    /// `build` stages writes on a [`TilemapTransaction`]. `validate` is then called with every staged change in
+    /// the order they were first staged. The writes are only applied if `validate` returns true, otherwise
+    /// [`TilemapManagerError::TransactionRejected`] is returned and the map is left untouched. If any staged
+    /// cell isn't on the map the error is returned and nothing is written. Accepted writes are applied as one
+    /// [`set_tile_data_batch`](Self::set_tile_data_batch).
+    pub fn transaction(
+        &mut self,
+        build: impl FnOnce(&mut TilemapTransaction<TileData>),
+        validate: impl FnOnce(&[StagedTileChange<TileData>]) -> bool,
+    ) -> Result<(), TilemapManagerError> {
+        let read = |cell: Cell| self.get_tile_data(cell).ok();
+        let mut transaction = TilemapTransaction {
+            read: &read,
+            staged: HashMap::default(),
+            order: vec![],
+        };
+        build(&mut transaction);
+        let TilemapTransaction { staged, order, .. } = transaction;
+
+        let mut changes = Vec::with_capacity(order.len());
+        for cell in order {
+            let old = match self.get_tile_data(cell) {
+                Ok(tile_data) => Some(tile_data),
+                Err(TilemapManagerError::TileDataDoesNotExist) => None,
+                Err(err) => return Err(err),
+            };
+            changes.push(StagedTileChange {
+                cell,
+                old,
+                new: staged[&cell],
+            });
+        }
+
+        if !validate(&changes) {
+            return Err(TilemapManagerError::TransactionRejected);
+        }
+
+        self.set_tile_data_batch(changes.iter().map(|change| (change.cell, change.new)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::{SquareChunkLayer, SquareChunkSettings};
+    use crate::square::map_data::SquareMapData;
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use crate::tilemap_builder::TilemapBuilder;
+    use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    #[test]
+    fn test_transaction_commits_or_rolls_back() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(
+            Commands,
+            TilemapManager<u8, MapLayers, SquareChunkLayer<u8>, SquareMapData>,
+        )> = SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = TilemapBuilder::<u8, MapLayers, SquareChunkLayer<u8>, SquareMapData>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![0u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+
+        // Only allow transactions that place an even amount of tiles
+        let result = tilemap_manager.transaction(
+            |tx| {
+                tx.set(Cell::new(0, 0), 1);
+                tx.set(Cell::new(3, 3), 1);
+                tx.set(Cell::new(2, 1), 1);
+            },
+            |changes| changes.len() % 2 == 0,
+        );
+        assert!(matches!(
+            result,
+            Err(TilemapManagerError::TransactionRejected)
+        ));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).ok(), Some(0));
+
+        let result = tilemap_manager.transaction(
+            |tx| {
+                tx.set(Cell::new(0, 0), 1);
+                assert_eq!(tx.get(Cell::new(0, 0)), Some(1));
+                assert_eq!(tx.get(Cell::new(3, 3)), Some(0));
+                tx.set(Cell::new(3, 3), 2);
+            },
+            |changes| changes.iter().all(|change| change.old == Some(0)),
+        );
+        assert!(result.is_ok());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(0, 0)).ok(), Some(1));
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(3, 3)).ok(), Some(2));
+
+        let result = tilemap_manager.transaction(
+            |tx| {
+                tx.set(Cell::new(1, 1), 5);
+                tx.set(Cell::new(10, 10), 5);
+            },
+            |_| true,
+        );
+        assert!(result.is_err());
+        assert_eq!(tilemap_manager.get_tile_data(Cell::new(1, 1)).ok(), Some(0));
+    }
+}
@@ -0,0 +1,197 @@
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer, SparseOverrideSet, TileOverride};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use lettuces::cell::Cell;
+use std::hash::Hash;
+
+/// Tile data of a visibility layer that records which viewers can see a cell, such as the teams of a game
+/// with fog of war.
+///
+/// Implemented for the unsigned integers as bitmasks where bit `viewer_id` is set if the viewer can see the
+/// cell. Viewers past the size of the integer never see anything.
+pub trait ViewerVisibility {
+    /// Returns true if the given viewer can see the cell holding this tile data
+    fn visible_to(&self, viewer_id: u32) -> bool;
+}
+
+impl ViewerVisibility for u8 {
+    fn visible_to(&self, viewer_id: u32) -> bool {
+        self.checked_shr(viewer_id)
+            .is_some_and(|bits| bits & 1 == 1)
+    }
+}
+
+impl ViewerVisibility for u16 {
+    fn visible_to(&self, viewer_id: u32) -> bool {
+        self.checked_shr(viewer_id)
+            .is_some_and(|bits| bits & 1 == 1)
+    }
+}
+
+impl ViewerVisibility for u32 {
+    fn visible_to(&self, viewer_id: u32) -> bool {
+        self.checked_shr(viewer_id)
+            .is_some_and(|bits| bits & 1 == 1)
+    }
+}
+
+impl ViewerVisibility for u64 {
+    fn visible_to(&self, viewer_id: u32) -> bool {
+        self.checked_shr(viewer_id)
+            .is_some_and(|bits| bits & 1 == 1)
+    }
+}
+
+impl<'w, 's, TileData, MapLayers, MapChunk, Map>
+    TilemapManager<'w, 's, TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    /// Returns the tile data of every cell of the current layer that the given viewer can see according to
+    /// the given visibility layer. Cells without visibility data are hidden.
+    ///
+    /// Meant for servers holding the authoritative map that only send each client what it can see. Clients
+    /// apply the snapshot to their own copy of the map with
+    /// [`apply_overrides`](TilemapManager::apply_overrides), and
+    /// [`SparseOverrideSet::changes_since`] turns two snapshots of the same viewer into a delta of only the
+    /// cells that changed.
+    pub fn filtered_snapshot(
+        &self,
+        visibility_layer: MapLayers,
+        viewer_id: u32,
+    ) -> Result<SparseOverrideSet<TileData>, TilemapManagerError>
+    where
+        TileData: ViewerVisibility,
+    {
+        let dimensions = self.dimensions()?;
+        let visibility_layer = visibility_layer.to_bits();
+        let map_layer = self.layer().to_bits();
+        let mut overrides = vec![];
+        for y in 0..dimensions.y as i32 {
+            for x in 0..dimensions.x as i32 {
+                let cell = Cell::new(x, y);
+                let visible = match self.read_tile_data(visibility_layer, cell) {
+                    Ok(visibility) => visibility.visible_to(viewer_id),
+                    Err(
+                        TilemapManagerError::TileDataDoesNotExist
+                        | TilemapManagerError::CellOutOfBounds,
+                    ) => false,
+                    Err(err) => return Err(err),
+                };
+                if !visible {
+                    continue;
+                }
+                match self.read_tile_data(map_layer, cell) {
+                    Ok(tile_data) => overrides.push(TileOverride {
+                        map_layer,
+                        cell,
+                        tile_data,
+                    }),
+                    Err(TilemapManagerError::TileDataDoesNotExist) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+        Ok(SparseOverrideSet { overrides })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as bevy_sparse_tilemap;
+    use crate::map::TileOverride;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bevy::utils::HashMap;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+        Visibility,
+    }
+
+    #[test]
+    fn test_filtered_snapshot() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tilemap_builder = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![vec![7u8; 4]; 4]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        );
+        // Viewer 0 sees (1, 1), viewer 1 sees (1, 1) and (3, 2)
+        tilemap_builder.add_layer(
+            TilemapLayer::new_sparse_from_hashmap(
+                4,
+                4,
+                HashMap::from([(Cell::new(1, 1), 0b11u8), (Cell::new(3, 2), 0b10u8)]),
+            ),
+            MapLayers::Visibility,
+        );
+        let map_entity = tilemap_builder
+            .spawn_tilemap(&mut commands)
+            .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let first = tilemap_manager
+            .filtered_snapshot(MapLayers::Visibility, 1)
+            .unwrap();
+        assert_eq!(
+            first.overrides,
+            vec![
+                TileOverride {
+                    map_layer: MapLayers::Main.to_bits(),
+                    cell: Cell::new(1, 1),
+                    tile_data: 7,
+                },
+                TileOverride {
+                    map_layer: MapLayers::Main.to_bits(),
+                    cell: Cell::new(3, 2),
+                    tile_data: 7,
+                }
+            ]
+        );
+
+        // Changes outside of the view of viewer 0 aren't part of its delta
+        let before = tilemap_manager
+            .filtered_snapshot(MapLayers::Visibility, 0)
+            .unwrap();
+        tilemap_manager.sets_tile_data(2, Cell::new(1, 1)).unwrap();
+        tilemap_manager.sets_tile_data(2, Cell::new(3, 2)).unwrap();
+        let after = tilemap_manager
+            .filtered_snapshot(MapLayers::Visibility, 0)
+            .unwrap();
+        assert_eq!(
+            after.changes_since(&before).overrides,
+            vec![TileOverride {
+                map_layer: MapLayers::Main.to_bits(),
+                cell: Cell::new(1, 1),
+                tile_data: 2,
+            }]
+        );
+        assert!(tilemap_manager
+            .filtered_snapshot(MapLayers::Visibility, 40)
+            .unwrap()
+            .is_empty());
+    }
+}
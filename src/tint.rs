@@ -0,0 +1,295 @@
+//! Map wide and regional color tints for day-night cycles and weather.
+//!
+//! `bevy_sparse_tilemap` doesn't render anything itself. A [`GlobalTint`] on the tilemap entity describes a
+//! tint applied to the whole map and any amount of rectangular regions with their own tint, such as a storm
+//! or the light of a town at night. Rendering integrations read [`GlobalTint::tint_at`] when drawing tiles,
+//! so a single write changes the look of every tile without touching the tile data.
+//!
+//! The [`TintPlugin`] sends a [`TintChanged`] event for every change so integrations only have to update the
+//! affected area.
+//!
+//! ```ignore
+//! let mut tint = GlobalTint::new(Tint::new(Vec4::new(0.4, 0.4, 0.7, 1.0), 0.6));
+//! let storm = tint.add_region(Cell::new(0, 0), Cell::new(31, 31), Tint::new(Vec4::splat(0.8), 1.0));
+//! commands.entity(map).insert(tint);
+//! ```
+
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::Vec4;
+use bevy::prelude::{Component, DetectChangesMut, Entity, Event, EventWriter, Query};
+use lettuces::cell::Cell;
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A color multiplied into the color of tiles together with an intensity
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Tint {
+    /// The linear RGBA color the tile colors are multiplied with
+    pub color: Vec4,
+    /// The brightness of the tint, where 1.0 leaves the brightness unchanged
+    pub intensity: f32,
+}
+
+impl Default for Tint {
+    fn default() -> Self {
+        Self {
+            color: Vec4::ONE,
+            intensity: 1.0,
+        }
+    }
+}
+
+impl Tint {
+    /// Creates a new tint with the given color and intensity
+    pub fn new(color: Vec4, intensity: f32) -> Self {
+        Self { color, intensity }
+    }
+
+    /// Returns the tint of applying both tints, multiplying their colors and intensities
+    pub fn combine(&self, other: Tint) -> Self {
+        Self {
+            color: self.color * other.color,
+            intensity: self.intensity * other.intensity,
+        }
+    }
+
+    /// Returns the final color multiplier of the tint, its color scaled by its intensity. Alpha is left
+    /// unscaled
+    pub fn multiplier(&self) -> Vec4 {
+        self.color * Vec4::new(self.intensity, self.intensity, self.intensity, 1.0)
+    }
+}
+
+/// Identifies a region of a [`GlobalTint`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TintRegionId(pub u32);
+
+/// A rectangle of cells with its own tint
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TintRegion {
+    /// The bottom left cell of the region
+    pub min: Cell,
+    /// The top right cell of the region, included in the region
+    pub max: Cell,
+    /// The tint applied on top of the map wide tint
+    pub tint: Tint,
+}
+
+impl TintRegion {
+    /// Returns true if the cell is inside of the region
+    pub fn contains(&self, cell: Cell) -> bool {
+        cell.x >= self.min.x && cell.x <= self.max.x && cell.y >= self.min.y && cell.y <= self.max.y
+    }
+}
+
+/// The area of a map affected by a tint change
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TintArea {
+    /// The whole map
+    Map,
+    /// The cells between min and max, both included
+    Cells {
+        /// The bottom left cell
+        min: Cell,
+        /// The top right cell
+        max: Cell,
+    },
+}
+
+/// Sent by the [`TintPlugin`] whenever the [`GlobalTint`] of a map changes
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TintChanged {
+    /// The tilemap entity
+    pub map_entity: Entity,
+    /// The area whose tint changed
+    pub area: TintArea,
+}
+
+/// The map wide tint of a map and its tinted regions. Lives on the map entity.
+///
+/// Regions are applied on top of the map wide tint in the order they were added.
+#[derive(Component, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct GlobalTint {
+    tint: Tint,
+    regions: BTreeMap<TintRegionId, TintRegion>,
+    next_region: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    changes: Vec<TintArea>,
+}
+
+impl GlobalTint {
+    /// Creates a new global tint with the given map wide tint and no regions
+    pub fn new(tint: Tint) -> Self {
+        Self {
+            tint,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the map wide tint
+    pub fn tint(&self) -> Tint {
+        self.tint
+    }
+
+    /// Sets the map wide tint
+    pub fn set_tint(&mut self, tint: Tint) {
+        self.tint = tint;
+        self.changes.push(TintArea::Map);
+    }
+
+    /// Adds a region with its own tint and returns its id
+    pub fn add_region(&mut self, min: Cell, max: Cell, tint: Tint) -> TintRegionId {
+        let id = TintRegionId(self.next_region);
+        self.next_region += 1;
+        self.regions.insert(id, TintRegion { min, max, tint });
+        self.changes.push(TintArea::Cells { min, max });
+        id
+    }
+
+    /// Sets the tint of the given region. Returns false if the region doesn't exist
+    pub fn set_region_tint(&mut self, id: TintRegionId, tint: Tint) -> bool {
+        let Some(region) = self.regions.get_mut(&id) else {
+            return false;
+        };
+        region.tint = tint;
+        self.changes.push(TintArea::Cells {
+            min: region.min,
+            max: region.max,
+        });
+        true
+    }
+
+    /// Removes the given region and returns it if it existed
+    pub fn remove_region(&mut self, id: TintRegionId) -> Option<TintRegion> {
+        let region = self.regions.remove(&id)?;
+        self.changes.push(TintArea::Cells {
+            min: region.min,
+            max: region.max,
+        });
+        Some(region)
+    }
+
+    /// Returns the given region
+    pub fn region(&self, id: TintRegionId) -> Option<&TintRegion> {
+        self.regions.get(&id)
+    }
+
+    /// Returns every region in the order they are applied
+    pub fn regions(&self) -> impl Iterator<Item = (TintRegionId, &TintRegion)> {
+        self.regions.iter().map(|(id, region)| (*id, region))
+    }
+
+    /// Returns the tint of the given cell, the map wide tint combined with every region containing the cell
+    pub fn tint_at(&self, cell: Cell) -> Tint {
+        self.regions
+            .values()
+            .filter(|region| region.contains(cell))
+            .fold(self.tint, |tint, region| tint.combine(region.tint))
+    }
+
+    /// Returns and clears the areas changed since the last call. Called by the [`TintPlugin`], use it
+    /// directly when not using the plugin
+    pub fn drain_changes(&mut self) -> Vec<TintArea> {
+        std::mem::take(&mut self.changes)
+    }
+}
+
+/// Plugin that sends [`TintChanged`] events for every change to a [`GlobalTint`]
+#[derive(Default)]
+pub struct TintPlugin;
+
+impl Plugin for TintPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TintChanged>()
+            .add_systems(PostUpdate, send_tint_changed_events);
+    }
+}
+
+/// Sends a [`TintChanged`] event for every area changed since the last run
+pub fn send_tint_changed_events(
+    mut tints: Query<(Entity, &mut GlobalTint)>,
+    mut tint_changed: EventWriter<TintChanged>,
+) {
+    for (map_entity, mut tint) in tints.iter_mut() {
+        if tint.changes.is_empty() {
+            continue;
+        }
+        // Draining isn't a change renderers need to react to
+        for area in tint.bypass_change_detection().drain_changes() {
+            tint_changed.send(TintChanged { map_entity, area });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GlobalTint, Tint, TintArea, TintChanged, TintPlugin};
+    use bevy::app::App;
+    use bevy::ecs::event::Events;
+    use bevy::math::Vec4;
+    use lettuces::cell::Cell;
+
+    #[test]
+    fn test_global_tint() {
+        let mut app = App::new();
+        app.add_plugins(TintPlugin);
+
+        let night = Tint::new(Vec4::new(0.5, 0.5, 1.0, 1.0), 0.5);
+        let mut global_tint = GlobalTint::new(night);
+        let storm = global_tint.add_region(
+            Cell::new(0, 0),
+            Cell::new(3, 3),
+            Tint::new(Vec4::splat(0.5), 1.0),
+        );
+        assert_eq!(global_tint.tint_at(Cell::new(4, 0)), night);
+        assert_eq!(
+            global_tint.tint_at(Cell::new(3, 3)),
+            Tint::new(Vec4::new(0.25, 0.25, 0.5, 0.5), 0.5)
+        );
+        assert_eq!(
+            global_tint.tint_at(Cell::new(3, 3)).multiplier(),
+            Vec4::new(0.125, 0.125, 0.25, 0.5)
+        );
+        let map_entity = app.world.spawn(global_tint).id();
+        app.update();
+
+        let mut tint = app.world.get_mut::<GlobalTint>(map_entity).unwrap();
+        tint.remove_region(storm);
+        tint.set_tint(Tint::default());
+        app.update();
+        let areas: Vec<TintArea> = app
+            .world
+            .resource_mut::<Events<TintChanged>>()
+            .drain()
+            .filter(|event| event.map_entity == map_entity)
+            .map(|event| event.area)
+            .collect();
+        assert_eq!(
+            areas,
+            vec![
+                TintArea::Cells {
+                    min: Cell::new(0, 0),
+                    max: Cell::new(3, 3)
+                },
+                TintArea::Cells {
+                    min: Cell::new(0, 0),
+                    max: Cell::new(3, 3)
+                },
+                TintArea::Map
+            ]
+        );
+        assert_eq!(
+            app.world
+                .get::<GlobalTint>(map_entity)
+                .unwrap()
+                .tint_at(Cell::new(1, 1)),
+            Tint::default()
+        );
+    }
+}
@@ -0,0 +1,351 @@
+//! Helpers for turn based tactics games.
+//!
+//! [`Occupancy`] tracks which unit entity stands on each cell of a map and allows at most one unit per cell.
+//! [`movement_range`] finds every cell a unit can reach with its movement points using the same cost
+//! functions as [`find_path`](crate::pathfinding::find_path), and [`MovementRange::path_preview`] returns the
+//! path to one of those cells for highlighting it while the player picks a destination.
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer};
+use crate::pathfinding::{neighbours, tile_cost, Path};
+use crate::tilemap_manager::TilemapManager;
+use bevy::prelude::{Component, Entity};
+use bevy::utils::HashMap;
+use lettuces::cell::Cell;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::Hash;
+
+/// Errors returned when changing an [`Occupancy`]
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum OccupancyError {
+    /// The cell is already occupied by another unit
+    #[error("Cell {cell:?} is already occupied by {occupant:?}")]
+    CellOccupied {
+        /// The cell that was requested
+        cell: Cell,
+        /// The unit occupying the cell
+        occupant: Entity,
+    },
+
+    /// The unit isn't registered in the occupancy
+    #[error("Unit {0:?} does not occupy any cell")]
+    UnitNotRegistered(Entity),
+}
+
+/// The unit entities standing on each cell of a map. Each cell holds at most one unit and each unit stands
+/// on exactly one cell. Insert it on the tilemap entity.
+#[derive(Component, Clone, Debug, Default)]
+pub struct Occupancy {
+    units: HashMap<Cell, Entity>,
+    cells: HashMap<Entity, Cell>,
+}
+
+impl Occupancy {
+    /// Creates an empty occupancy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places the unit on the cell. A unit that was already registered is moved off its previous cell.
+    ///
+    /// Fails if another unit occupies the cell
+    pub fn occupy(&mut self, cell: Cell, unit: Entity) -> Result<(), OccupancyError> {
+        match self.units.get(&cell) {
+            Some(occupant) if *occupant == unit => return Ok(()),
+            Some(occupant) => {
+                return Err(OccupancyError::CellOccupied {
+                    cell,
+                    occupant: *occupant,
+                })
+            }
+            None => {}
+        }
+        if let Some(previous) = self.cells.insert(unit, cell) {
+            self.units.remove(&previous);
+        }
+        self.units.insert(cell, unit);
+        Ok(())
+    }
+
+    /// Moves a registered unit to the given cell and returns the cell it was on
+    pub fn move_unit(&mut self, unit: Entity, to: Cell) -> Result<Cell, OccupancyError> {
+        let from = self
+            .cell_of(unit)
+            .ok_or(OccupancyError::UnitNotRegistered(unit))?;
+        self.occupy(to, unit)?;
+        Ok(from)
+    }
+
+    /// Removes the unit and returns the cell it was on
+    pub fn remove_unit(&mut self, unit: Entity) -> Option<Cell> {
+        let cell = self.cells.remove(&unit)?;
+        self.units.remove(&cell);
+        Some(cell)
+    }
+
+    /// Removes the unit on the given cell and returns it
+    pub fn vacate(&mut self, cell: Cell) -> Option<Entity> {
+        let unit = self.units.remove(&cell)?;
+        self.cells.remove(&unit);
+        Some(unit)
+    }
+
+    /// Returns the unit on the given cell
+    pub fn occupant(&self, cell: Cell) -> Option<Entity> {
+        self.units.get(&cell).copied()
+    }
+
+    /// Returns the cell the given unit is on
+    pub fn cell_of(&self, unit: Entity) -> Option<Cell> {
+        self.cells.get(&unit).copied()
+    }
+
+    /// Returns true if a unit stands on the given cell
+    pub fn is_occupied(&self, cell: Cell) -> bool {
+        self.units.contains_key(&cell)
+    }
+
+    /// Returns an iterator over every occupied cell and the unit on it
+    pub fn iter(&self) -> impl Iterator<Item = (Cell, Entity)> + '_ {
+        self.units.iter().map(|(cell, unit)| (*cell, *unit))
+    }
+
+    /// Returns the amount of registered units
+    pub fn len(&self) -> usize {
+        self.units.len()
+    }
+
+    /// Returns true if no units are registered
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+    }
+}
+
+/// Every cell reachable from an origin with a limited amount of movement points. Returned by
+/// [`movement_range`]
+#[derive(Clone, Debug)]
+pub struct MovementRange {
+    origin: Cell,
+    movement_points: u32,
+    costs: HashMap<Cell, u32>,
+    came_from: HashMap<Cell, Cell>,
+}
+
+impl MovementRange {
+    /// Returns the cell the range was found from
+    pub fn origin(&self) -> Cell {
+        self.origin
+    }
+
+    /// Returns the movement points the range was found with
+    pub fn movement_points(&self) -> u32 {
+        self.movement_points
+    }
+
+    /// Returns true if the cell can be reached
+    pub fn contains(&self, cell: Cell) -> bool {
+        self.costs.contains_key(&cell)
+    }
+
+    /// Returns the movement points needed to reach the cell or `None` if it can't be reached
+    pub fn cost_to(&self, cell: Cell) -> Option<u32> {
+        self.costs.get(&cell).copied()
+    }
+
+    /// Returns an iterator over every reachable cell, including the origin, and the cost of reaching it
+    pub fn iter(&self) -> impl Iterator<Item = (Cell, u32)> + '_ {
+        self.costs.iter().map(|(cell, cost)| (*cell, *cost))
+    }
+
+    /// Returns every reachable cell a unit can end its move on, which excludes the origin and every
+    /// occupied cell
+    pub fn destinations<'a>(&'a self, occupancy: &'a Occupancy) -> impl Iterator<Item = Cell> + 'a {
+        self.costs
+            .keys()
+            .copied()
+            .filter(move |cell| *cell != self.origin && !occupancy.is_occupied(*cell))
+    }
+
+    /// Returns the cheapest path from the origin to the target or `None` if the target is out of range
+    pub fn path_preview(&self, target: Cell) -> Option<Path> {
+        let cost = self.cost_to(target)?;
+        let mut cells = vec![target];
+        let mut current = target;
+        while let Some(previous) = self.came_from.get(&current) {
+            cells.push(*previous);
+            current = *previous;
+        }
+        cells.reverse();
+        Some(Path { cells, cost })
+    }
+}
+
+/// Finds every cell reachable from origin on the layer the [`TilemapManager`] is set to without spending more
+/// than `movement_points`.
+///
+/// `cost` returns the cost of entering a cell or `None` if it can't be entered, the same as for
+/// [`find_path`](crate::pathfinding::find_path). Costs below one are treated as one. Use [`Occupancy`] in the
+/// cost function to keep units from moving through each other.
+pub fn movement_range<TileData, MapLayers, MapChunk, Map>(
+    tilemap_manager: &TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    origin: Cell,
+    movement_points: u32,
+    cost: impl Fn(Cell, &TileData) -> Option<u32>,
+) -> MovementRange
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let mut open = BinaryHeap::new();
+    let mut costs: HashMap<Cell, u32> = HashMap::new();
+    let mut came_from: HashMap<Cell, Cell> = HashMap::new();
+    costs.insert(origin, 0);
+    open.push(Reverse((0u32, (origin.x, origin.y))));
+
+    while let Some(Reverse((distance, (x, y)))) = open.pop() {
+        let cell = Cell::new(x, y);
+        if costs.get(&cell).is_some_and(|best| *best < distance) {
+            continue;
+        }
+        for next in neighbours(cell) {
+            let Some(step_cost) = tile_cost(tilemap_manager, next, &cost) else {
+                continue;
+            };
+            let next_distance = distance + step_cost;
+            if next_distance > movement_points
+                || costs.get(&next).is_some_and(|best| *best <= next_distance)
+            {
+                continue;
+            }
+            costs.insert(next, next_distance);
+            came_from.insert(next, cell);
+            open.push(Reverse((next_distance, (next.x, next.y))));
+        }
+    }
+
+    MovementRange {
+        origin,
+        movement_points,
+        costs,
+        came_from,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{movement_range, Occupancy, OccupancyError};
+    use crate as bevy_sparse_tilemap;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::{Entity, World};
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    const FLOOR: u8 = 0;
+    const SWAMP: u8 = 1;
+    const WALL: u8 = 2;
+
+    #[test]
+    fn test_occupancy() {
+        let mut occupancy = Occupancy::new();
+        let (knight, archer) = (Entity::from_raw(1), Entity::from_raw(2));
+        occupancy.occupy(Cell::new(0, 0), knight).unwrap();
+        assert_eq!(
+            occupancy.occupy(Cell::new(0, 0), archer),
+            Err(OccupancyError::CellOccupied {
+                cell: Cell::new(0, 0),
+                occupant: knight
+            })
+        );
+        occupancy.occupy(Cell::new(1, 0), archer).unwrap();
+        assert_eq!(
+            occupancy.move_unit(knight, Cell::new(2, 2)),
+            Ok(Cell::new(0, 0))
+        );
+        assert!(!occupancy.is_occupied(Cell::new(0, 0)));
+        assert_eq!(occupancy.occupant(Cell::new(2, 2)), Some(knight));
+        assert_eq!(occupancy.vacate(Cell::new(1, 0)), Some(archer));
+        assert_eq!(
+            occupancy.move_unit(archer, Cell::new(0, 0)),
+            Err(OccupancyError::UnitNotRegistered(archer))
+        );
+        assert_eq!(occupancy.len(), 1);
+    }
+
+    #[test]
+    fn test_movement_range() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+
+        let mut tiles = vec![vec![FLOOR; 8]; 8];
+        tiles[0][1] = WALL;
+        tiles[1][0] = SWAMP;
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(tiles),
+            SquareMapData {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(4, 4),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+
+        let mut occupancy = Occupancy::new();
+        occupancy
+            .occupy(Cell::new(0, 0), Entity::from_raw(1))
+            .unwrap();
+        occupancy
+            .occupy(Cell::new(2, 0), Entity::from_raw(2))
+            .unwrap();
+        let cost = |cell: Cell, tile: &u8| match *tile {
+            _ if occupancy.occupant(cell) == Some(Entity::from_raw(2)) => None,
+            FLOOR => Some(1),
+            SWAMP => Some(3),
+            _ => None,
+        };
+
+        let range = movement_range(&tilemap_manager, Cell::new(0, 0), 4, cost);
+        assert_eq!(range.cost_to(Cell::new(0, 0)), Some(0));
+        assert_eq!(range.cost_to(Cell::new(0, 1)), Some(3));
+        assert_eq!(range.cost_to(Cell::new(0, 2)), Some(4));
+        assert!(!range.contains(Cell::new(1, 0)));
+        assert!(!range.contains(Cell::new(2, 0)));
+        assert!(!range.contains(Cell::new(1, 2)));
+
+        let path = range.path_preview(Cell::new(0, 2)).unwrap();
+        assert_eq!(
+            path.cells,
+            vec![Cell::new(0, 0), Cell::new(0, 1), Cell::new(0, 2)]
+        );
+        assert_eq!(path.cost, 4);
+        assert!(range.path_preview(Cell::new(5, 5)).is_none());
+
+        let mut destinations = range.destinations(&occupancy).collect::<Vec<Cell>>();
+        destinations.sort();
+        assert_eq!(
+            destinations,
+            vec![Cell::new(0, 1), Cell::new(0, 2), Cell::new(1, 1)]
+        );
+    }
+}
@@ -0,0 +1,401 @@
+//! Declarative invariants over map layers.
+//!
+//! A [`MapValidator`] holds [`ValidationRule`]s such as "every door must be next to exactly two walls".
+//! Rules can be checked on demand with [`MapValidator::validate_all`] or incrementally around changed cells
+//! with [`MapValidator::validate_cells`].
+//!
+//! In debug builds the [`ValidationPlugin`] checks the cells around every write made through the
+//! [`TilemapManager`] at the end of the frame and sends a [`ValidationFailed`] event for any violation, which
+//! catches editor and generation bugs close to their source. Register [`MapValidator::hook`] in the maps
+//! [`TileWriteHooks`](crate::map::TileWriteHooks) so the validator knows which cells changed.
+
+use crate::map::chunk::ChunkLayer;
+use crate::map::{MapData, MapLayer, TileWrite, TileWriteHook, TilemapSettings, TilemapSubsystems};
+use crate::tilemap_manager::{TilemapManager, TilemapManagerError};
+use bevy::app::{App, Plugin, PostUpdate};
+use bevy::math::IVec2;
+use bevy::prelude::{Component, Entity, Event, EventWriter, Local, Query};
+use bevy::utils::HashSet;
+use lettuces::cell::Cell;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// The offsets of the four orthogonal neighbours of a cell
+pub const ORTHOGONAL_NEIGHBOURS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+type TileMatcher<TileData> = Box<dyn Fn(&TileData) -> bool + Send + Sync>;
+type CellCheck<TileData> =
+    Box<dyn Fn(Cell, &dyn Fn(Cell) -> Option<TileData>) -> bool + Send + Sync>;
+
+/// A single invariant that must hold for every cell of a layer whose tile matches the rule
+pub struct ValidationRule<TileData, MapLayers> {
+    name: String,
+    map_layer: MapLayers,
+    radius: i32,
+    applies_to: TileMatcher<TileData>,
+    check: CellCheck<TileData>,
+}
+
+impl<TileData, MapLayers> ValidationRule<TileData, MapLayers>
+where
+    TileData: Clone + Copy + Send + Sync + 'static,
+    MapLayers: MapLayer + Clone + Copy + Send + Sync + 'static,
+{
+    /// Creates a new rule.
+    ///
+    /// `check` is called for every cell in `map_layer` whose tile matches `applies_to`. It receives the cell
+    /// and a function reading tiles of the same layer and returns false if the invariant is broken. `radius`
+    /// is the furthest distance on either axis that `check` looks at, so writes that far away from a cell
+    /// cause it to be checked again.
+    pub fn new(
+        name: impl Into<String>,
+        map_layer: MapLayers,
+        radius: u32,
+        applies_to: impl Fn(&TileData) -> bool + Send + Sync + 'static,
+        check: impl Fn(Cell, &dyn Fn(Cell) -> Option<TileData>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            map_layer,
+            radius: radius as i32,
+            applies_to: Box::new(applies_to),
+            check: Box::new(check),
+        }
+    }
+
+    /// Creates a rule requiring every matching tile to have between `min` and `max` (inclusive) orthogonal
+    /// neighbours matching `neighbour`. Eg "every door must be adjacent to exactly two walls"
+    pub fn adjacent_count(
+        name: impl Into<String>,
+        map_layer: MapLayers,
+        applies_to: impl Fn(&TileData) -> bool + Send + Sync + 'static,
+        neighbour: impl Fn(&TileData) -> bool + Send + Sync + 'static,
+        min: u32,
+        max: u32,
+    ) -> Self {
+        Self::new(name, map_layer, 1, applies_to, move |cell, read| {
+            let count = ORTHOGONAL_NEIGHBOURS
+                .iter()
+                .filter(|offset| {
+                    read(Cell::new(cell.x + offset.x, cell.y + offset.y))
+                        .is_some_and(|tile_data| neighbour(&tile_data))
+                })
+                .count() as u32;
+            (min..=max).contains(&count)
+        })
+    }
+
+    /// Returns the name of the rule
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the layer the rule checks
+    pub fn map_layer(&self) -> MapLayers {
+        self.map_layer
+    }
+}
+
+/// A cell that broke a [`ValidationRule`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RuleViolation {
+    /// The name of the rule that was broken
+    pub rule: String,
+    /// The cell that broke the rule
+    pub cell: Cell,
+}
+
+/// The [`ValidationRule`]s of a map. Insert it on the tilemap entity
+#[derive(Component)]
+pub struct MapValidator<TileData, MapLayers>
+where
+    TileData: Send + Sync + 'static,
+    MapLayers: Send + Sync + 'static,
+{
+    rules: Vec<ValidationRule<TileData, MapLayers>>,
+    dirty: Arc<Mutex<HashSet<Cell>>>,
+}
+
+impl<TileData, MapLayers> Default for MapValidator<TileData, MapLayers>
+where
+    TileData: Send + Sync + 'static,
+    MapLayers: Send + Sync + 'static,
+{
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            dirty: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+}
+
+impl<TileData, MapLayers> MapValidator<TileData, MapLayers>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+{
+    /// Creates a validator without any rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a rule
+    pub fn add_rule(&mut self, rule: ValidationRule<TileData, MapLayers>) {
+        self.rules.push(rule);
+    }
+
+    /// Adds a rule and returns self
+    pub fn with_rule(mut self, rule: ValidationRule<TileData, MapLayers>) -> Self {
+        self.add_rule(rule);
+        self
+    }
+
+    /// Returns a [`TileWriteHook`] that marks written cells as needing validation
+    pub fn hook(&self) -> impl TileWriteHook<TileData> {
+        let dirty = self.dirty.clone();
+        move |write: &TileWrite<TileData>| {
+            dirty
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .insert(write.cell);
+        }
+    }
+
+    /// Returns and clears the cells written since the last call
+    pub fn take_dirty_cells(&self) -> HashSet<Cell> {
+        std::mem::take(
+            &mut *self
+                .dirty
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    /// Checks every rule against every cell of the map
+    pub fn validate_all<MapChunk, Map>(
+        &self,
+        tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    ) -> Result<Vec<RuleViolation>, TilemapManagerError>
+    where
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let dimensions = tilemap_manager.dimensions()?.as_ivec2();
+        let cells = (0..dimensions.y).flat_map(|y| (0..dimensions.x).map(move |x| Cell::new(x, y)));
+        Ok(self.check_cells(tilemap_manager, &cells.collect::<Vec<Cell>>(), 0))
+    }
+
+    /// Checks every rule against the given cells and all cells within each rules radius of them
+    pub fn validate_cells<MapChunk, Map>(
+        &self,
+        tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+        cells: &[Cell],
+    ) -> Vec<RuleViolation>
+    where
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let radius = self.rules.iter().map(|rule| rule.radius).max().unwrap_or(0);
+        self.check_cells(tilemap_manager, cells, radius)
+    }
+
+    fn check_cells<MapChunk, Map>(
+        &self,
+        tilemap_manager: &mut TilemapManager<TileData, MapLayers, MapChunk, Map>,
+        cells: &[Cell],
+        radius: i32,
+    ) -> Vec<RuleViolation>
+    where
+        MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+        Map: MapData,
+    {
+        let mut affected = HashSet::new();
+        for cell in cells {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    affected.insert(Cell::new(cell.x + x, cell.y + y));
+                }
+            }
+        }
+
+        let original_layer = tilemap_manager.layer();
+        let mut violations = vec![];
+        for rule in self.rules.iter() {
+            tilemap_manager.set_layer(rule.map_layer);
+            let read = |cell: Cell| tilemap_manager.get_tile_data(cell).ok();
+            for cell in affected.iter() {
+                let Some(tile_data) = read(*cell) else {
+                    continue;
+                };
+                if (rule.applies_to)(&tile_data) && !(rule.check)(*cell, &read) {
+                    violations.push(RuleViolation {
+                        rule: rule.name.clone(),
+                        cell: *cell,
+                    });
+                }
+            }
+        }
+        tilemap_manager.set_layer(original_layer);
+        violations.sort_by_key(|violation| (violation.cell.y, violation.cell.x));
+        violations
+    }
+}
+
+/// Event sent by the [`ValidationPlugin`] when writes broke one or more rules
+#[derive(Event, Clone, Debug, PartialEq, Eq)]
+pub struct ValidationFailed {
+    /// The tilemap entity whose rules were broken
+    pub map_entity: Entity,
+    /// Every violation found around the changed cells
+    pub violations: Vec<RuleViolation>,
+}
+
+/// Plugin that checks the [`MapValidator`] of every map around the cells written during the frame. Only
+/// does any work in debug builds
+pub struct ValidationPlugin<TileData, MapLayers, MapChunk, Map> {
+    ph: PhantomData<(TileData, MapLayers, MapChunk, Map)>,
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Default
+    for ValidationPlugin<TileData, MapLayers, MapChunk, Map>
+{
+    fn default() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<TileData, MapLayers, MapChunk, Map> Plugin
+    for ValidationPlugin<TileData, MapLayers, MapChunk, Map>
+where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<ValidationFailed>();
+        #[cfg(debug_assertions)]
+        app.add_systems(
+            PostUpdate,
+            validate_written_cells::<TileData, MapLayers, MapChunk, Map>,
+        );
+    }
+}
+
+/// Checks the cells written since the last run of every map with a [`MapValidator`]
+pub fn validate_written_cells<TileData, MapLayers, MapChunk, Map>(
+    validators: Query<(
+        Entity,
+        &MapValidator<TileData, MapLayers>,
+        Option<&TilemapSettings>,
+    )>,
+    mut tilemap_manager: TilemapManager<TileData, MapLayers, MapChunk, Map>,
+    mut tick: Local<u64>,
+    mut validation_failed: EventWriter<ValidationFailed>,
+) where
+    TileData: Hash + Clone + Copy + Sized + Default + Send + Sync + 'static,
+    MapLayers: MapLayer + Default + Clone + Copy + Send + Sync + 'static,
+    MapChunk: ChunkLayer<TileData> + Send + Sync + 'static + Default,
+    Map: MapData,
+{
+    let current_tick = *tick;
+    *tick = tick.wrapping_add(1);
+    for (map_entity, validator, settings) in validators.iter() {
+        if !TilemapSettings::should_run_for(settings, TilemapSubsystems::VALIDATION, current_tick) {
+            continue;
+        }
+        let dirty: Vec<Cell> = validator.take_dirty_cells().into_iter().collect();
+        if dirty.is_empty() {
+            continue;
+        }
+        tilemap_manager.set_tilemap_entity(map_entity);
+        let violations = validator.validate_cells(&mut tilemap_manager, &dirty);
+        if !violations.is_empty() {
+            validation_failed.send(ValidationFailed {
+                map_entity,
+                violations,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MapValidator, RuleViolation, ValidationRule};
+    use crate as bevy_sparse_tilemap;
+    use crate::map::TileWriteHooks;
+    use crate::square::map_chunk_layer::SquareChunkSettings;
+    use crate::square::map_data::SquareMapData;
+    use crate::square::{SquareTilemapBuilder, SquareTilemapManager};
+    use crate::tilemap_builder::tilemap_layer_builder::TilemapLayer;
+    use bevy::ecs::system::{Commands, SystemState};
+    use bevy::math::UVec2;
+    use bevy::prelude::World;
+    use bst_map_layer_derive::MapLayer;
+    use lettuces::cell::Cell;
+
+    #[derive(MapLayer, Default, Debug, PartialEq, Eq, Clone, Copy)]
+    enum MapLayers {
+        #[default]
+        Main,
+    }
+
+    const FLOOR: u8 = 0;
+    const WALL: u8 = 1;
+    const DOOR: u8 = 2;
+
+    #[test]
+    fn test_door_rule() {
+        let mut world = World::new();
+        let mut system_state: SystemState<(Commands, SquareTilemapManager<u8, MapLayers>)> =
+            SystemState::new(&mut world);
+        let (mut commands, _) = system_state.get_mut(&mut world);
+        let map_entity = SquareTilemapBuilder::<u8, MapLayers>::new(
+            TilemapLayer::new_dense_from_vecs(vec![
+                vec![FLOOR, FLOOR, FLOOR],
+                vec![WALL, DOOR, WALL],
+                vec![FLOOR, FLOOR, FLOOR],
+            ]),
+            SquareMapData {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+            SquareChunkSettings {
+                max_chunk_size: UVec2::new(2, 2),
+            },
+        )
+        .spawn_tilemap(&mut commands)
+        .expect("map has a main layer");
+        let validator = MapValidator::new().with_rule(ValidationRule::adjacent_count(
+            "doors between two walls",
+            MapLayers::Main,
+            |tile: &u8| *tile == DOOR,
+            |tile: &u8| *tile == WALL,
+            2,
+            2,
+        ));
+        let hooks = TileWriteHooks::new().with(validator.hook());
+        commands.entity(map_entity).insert(hooks);
+        system_state.apply(&mut world);
+
+        let (_, mut tilemap_manager) = system_state.get_mut(&mut world);
+        tilemap_manager.set_tilemap_entity(map_entity);
+        assert!(validator
+            .validate_all(&mut tilemap_manager)
+            .expect("map is readable")
+            .is_empty());
+
+        tilemap_manager
+            .sets_tile_data(FLOOR, Cell::new(2, 1))
+            .expect("cell is on the map");
+        let dirty: Vec<Cell> = validator.take_dirty_cells().into_iter().collect();
+        assert_eq!(dirty, vec![Cell::new(2, 1)]);
+        assert_eq!(
+            validator.validate_cells(&mut tilemap_manager, &dirty),
+            vec![RuleViolation {
+                rule: "doors between two walls".to_string(),
+                cell: Cell::new(1, 1),
+            }]
+        );
+    }
+}